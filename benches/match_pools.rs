@@ -0,0 +1,119 @@
+// Room lookups in MatchPools against the linear scan over every pool that
+// leave_match and get_match_status used to do.
+//
+// Usage:
+//   cargo bench --bench match_pools
+
+#![allow(dead_code)]
+
+// Path attributes on the inline modules set the directory their files are read from
+#[path = "../src/models"]
+mod models {
+    pub mod game;
+}
+#[path = "../src/game"]
+mod game {
+    pub mod victory;
+}
+#[path = "../src/matchmaking/pools.rs"]
+mod pools;
+
+use std::collections::HashMap;
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use uuid::Uuid;
+
+use models::game::{MatchRoom, MatchStatus, PlatformPool};
+use pools::{MatchPools, PoolKey};
+
+const MATCH_TYPES: [(&str, i32); 3] = [("1v1", 2), ("2v2", 4), ("5v5", 10)];
+const BRACKETS: i32 = 8;
+const LOOKUPS: usize = 10_000;
+
+// Pools as they were before the index: every lookup walks all rooms
+fn find_by_scan(pools: &HashMap<PoolKey, Vec<MatchRoom>>, match_id: Uuid) -> Option<MatchStatus> {
+    pools.values()
+        .flat_map(|pool| pool.iter())
+        .find(|room| room.id == match_id)
+        .map(|room| room.status)
+}
+
+fn room(required_players: i32) -> MatchRoom {
+    MatchRoom {
+        id: Uuid::new_v4(),
+        required_players,
+        current_players: 0,
+        players: Vec::new(),
+        status: MatchStatus::Matching,
+        parties: Vec::new(),
+        platforms: HashMap::new(),
+        seats: HashMap::new(),
+        teams: Vec::new(),
+        selections: HashMap::new(),
+        lobby_closes_at: None,
+        private: None,
+    }
+}
+
+// `rooms` rooms spread over every match type and bracket, in both layouts
+fn build(rooms: usize) -> (MatchPools, HashMap<PoolKey, Vec<MatchRoom>>, Vec<Uuid>) {
+    let mut indexed = MatchPools::default();
+    let mut scanned: HashMap<PoolKey, Vec<MatchRoom>> = HashMap::new();
+    let mut ids = Vec::with_capacity(rooms);
+    for i in 0..rooms {
+        let (match_type, required_players) = MATCH_TYPES[i % MATCH_TYPES.len()];
+        let key = PoolKey {
+            match_type: match_type.to_string(),
+            zone_id: None,
+            suspected: false,
+            quarantined: false,
+            protected: false,
+            bracket: (i as i32 / MATCH_TYPES.len() as i32) % BRACKETS,
+            premade: false,
+            platforms: PlatformPool::Any,
+            private: false,
+        };
+        let room = room(required_players);
+        ids.push(room.id);
+        scanned.entry(key.clone()).or_default().push(room.clone());
+        indexed.insert(key, room);
+    }
+    (indexed, scanned, ids)
+}
+
+fn per_op(elapsed: Duration, ops: usize) -> f64 {
+    elapsed.as_nanos() as f64 / ops as f64
+}
+
+fn main() {
+    println!("{:>8} {:>14} {:>14} {:>14} {:>10}", "rooms", "scan ns/op", "index ns/op", "remove ns/op", "speedup");
+    for rooms in [100, 1_000, 10_000, 50_000] {
+        let (mut indexed, scanned, ids) = build(rooms);
+        // Spread lookups over the whole id range, as players leave any room
+        let targets: Vec<Uuid> = (0..LOOKUPS).map(|i| ids[(i * 7919) % ids.len()]).collect();
+
+        let start = Instant::now();
+        for &match_id in &targets {
+            black_box(find_by_scan(&scanned, match_id));
+        }
+        let scan = per_op(start.elapsed(), targets.len());
+
+        let start = Instant::now();
+        for &match_id in &targets {
+            black_box(indexed.get(match_id).map(|(_, room)| room.status));
+        }
+        let index = per_op(start.elapsed(), targets.len());
+
+        // Remove and put back, as recycling an empty room and opening a new one does
+        let start = Instant::now();
+        for &match_id in &targets {
+            if let Some((key, room)) = indexed.remove(match_id) {
+                indexed.insert(key, room);
+            }
+        }
+        let remove = per_op(start.elapsed(), targets.len());
+
+        println!("{:>8} {:>14.0} {:>14.0} {:>14.0} {:>9.0}x", rooms, scan, index, remove, scan / index);
+    }
+}
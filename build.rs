@@ -0,0 +1,6 @@
+fn main() {
+    // gRPC stubs are only generated when the `grpc` feature is enabled,
+    // so the default build doesn't need protoc.
+    #[cfg(feature = "grpc")]
+    tonic_build::compile_protos("proto/spv.proto").expect("Failed to compile proto/spv.proto");
+}
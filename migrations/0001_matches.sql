@@ -0,0 +1,59 @@
+-- Matches, their teams, members and treasure discoveries
+CREATE TABLE IF NOT EXISTS treasure_matches (
+    id uuid PRIMARY KEY DEFAULT gen_random_uuid(),
+    match_type text NOT NULL,
+    status text NOT NULL,
+    required_players_per_team integer NOT NULL,
+    start_time timestamptz,
+    end_time timestamptz,
+    winner_team_id uuid,
+    review_notes jsonb,
+    is_finished boolean NOT NULL DEFAULT false,
+    created_at timestamptz NOT NULL DEFAULT now()
+);
+
+CREATE TABLE IF NOT EXISTS match_teams (
+    id uuid PRIMARY KEY DEFAULT gen_random_uuid(),
+    match_id uuid NOT NULL REFERENCES treasure_matches (id) ON DELETE CASCADE,
+    team_number integer NOT NULL,
+    current_players integer NOT NULL DEFAULT 0,
+    max_players integer NOT NULL,
+    total_score integer NOT NULL DEFAULT 0,
+    UNIQUE (match_id, team_number)
+);
+
+CREATE TABLE IF NOT EXISTS match_members (
+    id uuid PRIMARY KEY DEFAULT gen_random_uuid(),
+    match_id uuid NOT NULL REFERENCES treasure_matches (id) ON DELETE CASCADE,
+    team_id uuid NOT NULL REFERENCES match_teams (id) ON DELETE CASCADE,
+    user_id uuid NOT NULL,
+    individual_score integer NOT NULL DEFAULT 0,
+    UNIQUE (match_id, user_id)
+);
+
+CREATE TABLE IF NOT EXISTS match_discoveries (
+    id uuid PRIMARY KEY DEFAULT gen_random_uuid(),
+    match_id uuid NOT NULL REFERENCES treasure_matches (id) ON DELETE CASCADE,
+    team_id uuid NOT NULL REFERENCES match_teams (id) ON DELETE CASCADE,
+    user_id uuid NOT NULL,
+    treasure_id uuid NOT NULL,
+    score integer NOT NULL,
+    discovered_at timestamptz NOT NULL DEFAULT now(),
+    invalidated boolean NOT NULL DEFAULT false
+);
+
+CREATE TABLE IF NOT EXISTS match_adjustments (
+    id uuid PRIMARY KEY,
+    match_id uuid NOT NULL REFERENCES treasure_matches (id) ON DELETE CASCADE,
+    actor text NOT NULL,
+    reason text NOT NULL,
+    adjustment jsonb NOT NULL,
+    created_at timestamptz NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS treasure_matches_status_idx ON treasure_matches (status);
+CREATE INDEX IF NOT EXISTS treasure_matches_end_time_idx ON treasure_matches (end_time);
+CREATE INDEX IF NOT EXISTS match_teams_match_id_idx ON match_teams (match_id);
+CREATE INDEX IF NOT EXISTS match_members_user_id_idx ON match_members (user_id);
+CREATE INDEX IF NOT EXISTS match_discoveries_match_id_idx ON match_discoveries (match_id);
+CREATE INDEX IF NOT EXISTS match_adjustments_match_id_idx ON match_adjustments (match_id);
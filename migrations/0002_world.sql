@@ -0,0 +1,40 @@
+-- Treasures, map zones, position samples and the heatmap built from them
+CREATE TABLE IF NOT EXISTS treasures (
+    id uuid PRIMARY KEY DEFAULT gen_random_uuid(),
+    name text NOT NULL,
+    x real NOT NULL,
+    y real NOT NULL,
+    base_score integer NOT NULL,
+    rarity text NOT NULL DEFAULT 'common',
+    active_from timestamptz,
+    active_until timestamptz
+);
+
+CREATE TABLE IF NOT EXISTS zones (
+    id text PRIMARY KEY,
+    name text NOT NULL,
+    polygon jsonb NOT NULL,
+    treasure_density real NOT NULL DEFAULT 1
+);
+
+CREATE TABLE IF NOT EXISTS match_positions (
+    id bigserial PRIMARY KEY,
+    match_id uuid NOT NULL,
+    user_id uuid NOT NULL,
+    x real NOT NULL,
+    y real NOT NULL,
+    recorded_at timestamptz NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS heatmap_tiles (
+    id bigserial PRIMARY KEY,
+    zone_id text,
+    x real NOT NULL,
+    y real NOT NULL,
+    size real NOT NULL,
+    samples bigint NOT NULL,
+    computed_at timestamptz NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS match_positions_recorded_at_idx ON match_positions (recorded_at, user_id);
+CREATE INDEX IF NOT EXISTS heatmap_tiles_zone_id_idx ON heatmap_tiles (zone_id);
@@ -0,0 +1,39 @@
+-- Bans, ratings and the offline inbox
+CREATE TABLE IF NOT EXISTS bans (
+    id uuid PRIMARY KEY,
+    user_id uuid NOT NULL,
+    reason text NOT NULL,
+    issued_by text NOT NULL,
+    created_at timestamptz NOT NULL,
+    expires_at timestamptz,
+    lifted_at timestamptz,
+    lifted_by text
+);
+
+CREATE TABLE IF NOT EXISTS player_ratings (
+    user_id uuid PRIMARY KEY,
+    mmr double precision NOT NULL,
+    matches_played integer NOT NULL DEFAULT 0,
+    wins integer NOT NULL DEFAULT 0,
+    early_wins integer NOT NULL DEFAULT 0,
+    early_percentile_sum double precision NOT NULL DEFAULT 0,
+    smurf_suspected boolean NOT NULL DEFAULT false,
+    flagged_at timestamptz,
+    smurf_cleared boolean NOT NULL DEFAULT false
+);
+
+CREATE TABLE IF NOT EXISTS inbox_messages (
+    id uuid PRIMARY KEY,
+    user_id uuid NOT NULL,
+    event text NOT NULL,
+    key text NOT NULL,
+    data jsonb NOT NULL,
+    created_at timestamptz NOT NULL,
+    expires_at timestamptz NOT NULL,
+    UNIQUE (user_id, event, key)
+);
+
+-- Active bans are looked up by user
+CREATE INDEX IF NOT EXISTS bans_user_id_idx ON bans (user_id) WHERE lifted_at IS NULL;
+CREATE INDEX IF NOT EXISTS player_ratings_flagged_at_idx ON player_ratings (flagged_at) WHERE smurf_suspected;
+CREATE INDEX IF NOT EXISTS inbox_messages_expires_at_idx ON inbox_messages (expires_at);
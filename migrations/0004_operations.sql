@@ -0,0 +1,46 @@
+-- Announcements, experiments, remote client config and client telemetry
+CREATE TABLE IF NOT EXISTS announcements (
+    id uuid PRIMARY KEY,
+    message text NOT NULL,
+    segment jsonb NOT NULL DEFAULT '{}',
+    send_at timestamptz NOT NULL,
+    created_by text NOT NULL,
+    created_at timestamptz NOT NULL,
+    cancelled_at timestamptz
+);
+
+CREATE TABLE IF NOT EXISTS motd (
+    id integer PRIMARY KEY,
+    message text NOT NULL,
+    updated_by text NOT NULL,
+    updated_at timestamptz NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS experiments (
+    key text PRIMARY KEY,
+    salt text NOT NULL,
+    enabled boolean NOT NULL DEFAULT true,
+    variants jsonb NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS client_config_versions (
+    version bigint PRIMARY KEY,
+    values jsonb NOT NULL,
+    changed_by text NOT NULL,
+    changed_at timestamptz NOT NULL,
+    changes jsonb NOT NULL DEFAULT '[]'
+);
+
+CREATE TABLE IF NOT EXISTS client_telemetry (
+    id bigserial PRIMARY KEY,
+    user_id uuid,
+    source text NOT NULL,
+    kind text NOT NULL,
+    name text NOT NULL,
+    properties jsonb,
+    client_time bigint,
+    received_at timestamptz NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS announcements_send_at_idx ON announcements (send_at) WHERE cancelled_at IS NULL;
+CREATE INDEX IF NOT EXISTS client_telemetry_received_at_idx ON client_telemetry (received_at);
@@ -0,0 +1,2 @@
+-- Client platform of each match member (ios, android or web); null if unknown
+ALTER TABLE match_members ADD COLUMN IF NOT EXISTS platform text;
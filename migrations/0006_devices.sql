@@ -0,0 +1,10 @@
+-- Devices registered by players, for push notifications and session management
+CREATE TABLE IF NOT EXISTS devices (
+    user_id uuid NOT NULL,
+    device_id text NOT NULL,
+    platform text NOT NULL,
+    push_token text,
+    registered_at timestamptz NOT NULL,
+    last_seen_at timestamptz NOT NULL,
+    PRIMARY KEY (user_id, device_id)
+);
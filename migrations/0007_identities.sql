@@ -0,0 +1,18 @@
+-- External identities (Google, Apple) linked to players, and guests merged
+-- into an existing account when linking
+CREATE TABLE IF NOT EXISTS linked_identities (
+    provider text NOT NULL,
+    subject text NOT NULL,
+    user_id uuid NOT NULL,
+    email text,
+    linked_at timestamptz NOT NULL,
+    PRIMARY KEY (provider, subject),
+    UNIQUE (user_id, provider)
+);
+
+CREATE TABLE IF NOT EXISTS account_merges (
+    from_user_id uuid PRIMARY KEY,
+    into_user_id uuid NOT NULL,
+    provider text NOT NULL,
+    merged_at timestamptz NOT NULL
+);
@@ -0,0 +1,24 @@
+-- Queue time and MMR of every player when their match started, and the
+-- matchmaking fairness reports computed from them
+CREATE TABLE IF NOT EXISTS match_queue_samples (
+    id bigserial PRIMARY KEY,
+    match_id uuid NOT NULL,
+    user_id uuid NOT NULL,
+    match_type text NOT NULL,
+    region text,
+    team_number integer NOT NULL,
+    mmr double precision NOT NULL,
+    queued_secs double precision NOT NULL,
+    started_at timestamptz NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS fairness_reports (
+    id uuid PRIMARY KEY,
+    computed_at timestamptz NOT NULL,
+    window_start timestamptz NOT NULL,
+    blowout_margin double precision NOT NULL,
+    groups jsonb NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS match_queue_samples_started_at_idx ON match_queue_samples (started_at, match_id);
+CREATE INDEX IF NOT EXISTS fairness_reports_computed_at_idx ON fairness_reports (computed_at);
@@ -0,0 +1,4 @@
+-- Average MMR of each team and its predicted chance to win, both taken when
+-- the match starts; null for matches started before they were recorded
+ALTER TABLE match_teams ADD COLUMN IF NOT EXISTS mmr double precision;
+ALTER TABLE match_teams ADD COLUMN IF NOT EXISTS win_probability double precision;
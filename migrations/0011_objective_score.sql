@@ -0,0 +1,3 @@
+-- Points each team earned holding capture zones; they count towards the
+-- winner along with total_score
+ALTER TABLE match_teams ADD COLUMN IF NOT EXISTS objective_score integer NOT NULL DEFAULT 0;
@@ -0,0 +1,3 @@
+-- How the winner of the match is decided, e.g. {"kind": "first_to", "points": 500};
+-- NULL for matches started before it was recorded, decided by highest score
+ALTER TABLE treasure_matches ADD COLUMN IF NOT EXISTS victory_condition jsonb;
@@ -0,0 +1,11 @@
+-- Result of each round of a best_of match, as the game loop ends it
+CREATE TABLE IF NOT EXISTS match_rounds (
+    id uuid PRIMARY KEY DEFAULT gen_random_uuid(),
+    match_id uuid NOT NULL REFERENCES treasure_matches (id) ON DELETE CASCADE,
+    round_number integer NOT NULL,
+    -- Discovery points per team id
+    scores jsonb NOT NULL,
+    winner_team_id uuid REFERENCES match_teams (id) ON DELETE SET NULL,
+    ended_at timestamptz NOT NULL,
+    UNIQUE (match_id, round_number)
+);
@@ -0,0 +1,3 @@
+-- Loadout and role each player picked in the pre-match lobby
+ALTER TABLE match_members ADD COLUMN IF NOT EXISTS loadout text;
+ALTER TABLE match_members ADD COLUMN IF NOT EXISTS role text;
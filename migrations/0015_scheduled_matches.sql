@@ -0,0 +1,19 @@
+-- Private matches planned for a later time; the leader opens their lobby
+-- shortly before starts_at and calls them off if too few players show up
+CREATE TABLE IF NOT EXISTS scheduled_matches (
+    id uuid PRIMARY KEY,
+    match_type text NOT NULL,
+    host uuid NOT NULL,
+    -- User ids invited besides the host
+    invited jsonb NOT NULL,
+    starts_at timestamptz NOT NULL,
+    min_players integer NOT NULL,
+    status text NOT NULL,
+    match_id uuid,
+    code text,
+    cancelled_reason text,
+    created_at timestamptz NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS scheduled_matches_starts_at_idx ON scheduled_matches (starts_at);
+CREATE INDEX IF NOT EXISTS scheduled_matches_host_idx ON scheduled_matches (host, starts_at);
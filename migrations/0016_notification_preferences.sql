@@ -0,0 +1,10 @@
+-- Notifications each player wants; players without a row get them all
+CREATE TABLE IF NOT EXISTS notification_preferences (
+    user_id uuid PRIMARY KEY,
+    match_found boolean NOT NULL DEFAULT true,
+    friend_requests boolean NOT NULL DEFAULT true,
+    announcements boolean NOT NULL DEFAULT true,
+    -- {start, end, utc_offset_mins}; no push notifications in between
+    quiet_hours jsonb,
+    updated_at timestamptz
+);
@@ -0,0 +1,2 @@
+-- Content bundle entry holding an announcement's text in each language
+ALTER TABLE announcements ADD COLUMN IF NOT EXISTS content_key text;
@@ -0,0 +1,8 @@
+-- How far each player got through the tutorial
+CREATE TABLE IF NOT EXISTS tutorial_progress (
+    user_id uuid PRIMARY KEY,
+    -- Id of the last step reached
+    step text,
+    completed_at timestamptz,
+    updated_at timestamptz
+);
@@ -0,0 +1,9 @@
+-- Players flagged for cheating, matched only with each other instead of
+-- being banned
+CREATE TABLE IF NOT EXISTS quarantined_players (
+    user_id uuid PRIMARY KEY,
+    reason text NOT NULL,
+    -- Admin who quarantined the player, or "anticheat"
+    added_by text NOT NULL,
+    created_at timestamptz NOT NULL DEFAULT now()
+);
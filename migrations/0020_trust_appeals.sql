@@ -0,0 +1,18 @@
+-- Players' appeals against their trust score or quarantine, with the
+-- evidence as it stood when they appealed
+CREATE TABLE IF NOT EXISTS trust_appeals (
+    id uuid PRIMARY KEY,
+    user_id uuid NOT NULL,
+    message text NOT NULL,
+    -- open, accepted or rejected
+    status text NOT NULL DEFAULT 'open',
+    trust jsonb,
+    signals jsonb NOT NULL DEFAULT '[]',
+    quarantined boolean NOT NULL DEFAULT false,
+    created_at timestamptz NOT NULL DEFAULT now(),
+    resolved_at timestamptz,
+    resolved_by text,
+    resolution text
+);
+
+CREATE INDEX IF NOT EXISTS trust_appeals_user_idx ON trust_appeals (user_id, status);
@@ -0,0 +1,21 @@
+-- Background jobs (bulk admin operations and the leader's periodic work)
+-- with their progress, so they can be looked up from any node and survive
+-- a restart
+CREATE TABLE IF NOT EXISTS jobs (
+    id uuid PRIMARY KEY,
+    kind text NOT NULL,
+    -- running, completed, failed or cancelled
+    status text NOT NULL DEFAULT 'running',
+    created_by text NOT NULL,
+    node_id text NOT NULL,
+    total integer NOT NULL DEFAULT 0,
+    done integer NOT NULL DEFAULT 0,
+    failed integer NOT NULL DEFAULT 0,
+    errors jsonb NOT NULL DEFAULT '[]',
+    created_at timestamptz NOT NULL DEFAULT now(),
+    updated_at timestamptz NOT NULL DEFAULT now(),
+    finished_at timestamptz
+);
+
+CREATE INDEX IF NOT EXISTS jobs_created_at_idx ON jobs (created_at DESC);
+CREATE INDEX IF NOT EXISTS jobs_running_idx ON jobs (node_id) WHERE status = 'running';
@@ -0,0 +1,5 @@
+-- Keeps a team within its max_players, so joining a full team fails the
+-- whole join instead of overfilling it. NOT VALID leaves existing rows
+-- unchecked; new writes are still checked.
+ALTER TABLE match_teams DROP CONSTRAINT IF EXISTS match_teams_capacity;
+ALTER TABLE match_teams ADD CONSTRAINT match_teams_capacity CHECK (current_players <= max_players) NOT VALID;
@@ -0,0 +1,90 @@
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+// Longest announcement or message of the day, in characters
+pub const MAX_MESSAGE_LEN: usize = 500;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Activity {
+    // Queued for or playing a match
+    InMatch,
+    Idle,
+}
+
+// Players an announcement is for; every condition that is set must hold,
+// so the default segment is everyone
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, ToSchema, JsonSchema)]
+pub struct Segment {
+    // Region of the node the player is connected to (NODE_REGION)
+    pub region: Option<String>,
+    // Players queued for or playing a match of this type
+    pub match_type: Option<String>,
+    pub activity: Option<Activity>,
+}
+
+impl Segment {
+    pub fn matches(&self, region: Option<&str>, match_type: Option<&str>) -> bool {
+        let activity = match match_type {
+            Some(_) => Activity::InMatch,
+            None => Activity::Idle,
+        };
+        self.region.as_deref().is_none_or(|wanted| region == Some(wanted))
+            && self.match_type.as_deref().is_none_or(|wanted| match_type == Some(wanted))
+            && self.activity.is_none_or(|wanted| wanted == activity)
+    }
+}
+
+// A message pushed to the connected players of a segment as `sys.announcement`
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, JsonSchema)]
+pub struct Announcement {
+    pub id: Uuid,
+    // Sent to players whose locale has no text for `content_key`
+    pub message: String,
+    // Content bundle entry with the message in each language
+    #[serde(default)]
+    pub content_key: Option<String>,
+    pub segment: Segment,
+    // When it goes out; the time it was made for immediate announcements
+    pub send_at: DateTime<Utc>,
+    pub created_by: String,
+    pub created_at: DateTime<Utc>,
+    pub cancelled_at: Option<DateTime<Utc>>,
+}
+
+impl Announcement {
+    // Notice carrying `message`, the announcement's text in some language
+    pub fn notice(&self, message: &str) -> AnnouncementNotice {
+        AnnouncementNotice {
+            id: self.id,
+            message: message.to_string(),
+            sent_at: self.send_at,
+        }
+    }
+}
+
+// sys.announcement payload
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct AnnouncementNotice {
+    pub id: Uuid,
+    pub message: String,
+    pub sent_at: DateTime<Utc>,
+}
+
+// Message of the day, shown to every player in the welcome message
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct Motd {
+    pub message: String,
+    pub updated_by: String,
+    pub updated_at: DateTime<Utc>,
+}
+
+// PUT /admin/motd body
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+pub struct MotdSpec {
+    pub message: String,
+    pub updated_by: String,
+}
@@ -0,0 +1,224 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use tokio::sync::{Mutex, RwLock, broadcast};
+use uuid::Uuid;
+
+use crate::db::repository::AnnouncementRepository;
+use crate::error::{Error, Result};
+use crate::supervisor;
+use super::announcement::{Announcement, MAX_MESSAGE_LEN, Motd, MotdSpec, Segment};
+
+// How often due announcements are sent
+const TICK_INTERVAL: Duration = Duration::from_secs(1);
+// How often the message of the day and scheduled announcements are reloaded,
+// so changes made through another instance reach this one
+const REFRESH_INTERVAL: Duration = Duration::from_secs(5);
+// Announcements this late (the database was down, say) are dropped
+const LATE_LIMIT: Duration = Duration::from_secs(600);
+// Furthest ahead an announcement can be scheduled
+const MAX_SCHEDULE_AHEAD: Duration = Duration::from_secs(30 * 24 * 3600);
+
+// Message of the day and announcements to segments of the connected players.
+//
+// Both live in the database. Every instance polls for scheduled announcements
+// and, when one is due, hands it to the gateway, which pushes it to the
+// matching players connected to that instance; so each player gets it once
+// from the node they are on. Announcements made before this instance started
+// are not replayed.
+pub struct AnnouncementService {
+    repo: Arc<dyn AnnouncementRepository>,
+    // Region of this node, matched against Segment::region
+    region: Option<String>,
+    started_at: DateTime<Utc>,
+    motd: RwLock<Option<Motd>>,
+    // Not cancelled and not yet sent here
+    scheduled: RwLock<HashMap<Uuid, Announcement>>,
+    // Sent here, kept until they are too late to send
+    sent: Mutex<HashMap<Uuid, Announcement>>,
+    due: broadcast::Sender<Announcement>,
+}
+
+impl AnnouncementService {
+    pub async fn init(repo: Arc<dyn AnnouncementRepository>, region: Option<String>) -> Arc<Self> {
+        let (due, _) = broadcast::channel(64);
+        let service = Arc::new(Self {
+            repo,
+            region,
+            started_at: Utc::now(),
+            motd: RwLock::new(None),
+            scheduled: RwLock::new(HashMap::new()),
+            sent: Mutex::new(HashMap::new()),
+            due,
+        });
+        if let Err(e) = service.reload().await {
+            tracing::warn!("Failed to load announcements: {}", e);
+        }
+
+        let ticker = service.clone();
+        supervisor::spawn_loop("announcement_ticker", move || {
+            let ticker = ticker.clone();
+            async move {
+                let mut interval = tokio::time::interval(TICK_INTERVAL);
+                let refresh_every = (REFRESH_INTERVAL.as_secs() / TICK_INTERVAL.as_secs()).max(1);
+                let mut ticks: u64 = 0;
+                loop {
+                    interval.tick().await;
+                    ticks += 1;
+                    if ticks.is_multiple_of(refresh_every) && let Err(e) = ticker.reload().await {
+                        tracing::warn!("Failed to refresh announcements: {}", e);
+                    }
+                    ticker.send_due().await;
+                }
+            }
+        });
+        service
+    }
+
+    // Oldest send time still worth sending
+    fn cutoff(&self) -> DateTime<Utc> {
+        let late = Utc::now() - chrono::Duration::from_std(LATE_LIMIT).unwrap_or_default();
+        late.max(self.started_at)
+    }
+
+    // Reload the MOTD and the announcements still to be sent
+    pub async fn reload(&self) -> Result<()> {
+        let motd = self.repo.get_motd().await?;
+        let cutoff = self.cutoff();
+        let upcoming = self.repo.announcements_since(cutoff).await?;
+
+        *self.motd.write().await = motd;
+        let sent = {
+            let mut sent = self.sent.lock().await;
+            sent.retain(|_, announcement| announcement.send_at >= cutoff);
+            sent.keys().copied().collect::<HashSet<Uuid>>()
+        };
+        *self.scheduled.write().await = upcoming
+            .into_iter()
+            .filter(|announcement| !sent.contains(&announcement.id))
+            .map(|announcement| (announcement.id, announcement))
+            .collect();
+        Ok(())
+    }
+
+    async fn send_due(&self) {
+        let now = Utc::now();
+        let due: Vec<Announcement> = {
+            let mut scheduled = self.scheduled.write().await;
+            let ids: Vec<Uuid> = scheduled.values()
+                .filter(|announcement| announcement.send_at <= now)
+                .map(|announcement| announcement.id)
+                .collect();
+            ids.iter().filter_map(|id| scheduled.remove(id)).collect()
+        };
+        if due.is_empty() {
+            return;
+        }
+        let mut sent = self.sent.lock().await;
+        for announcement in due {
+            sent.insert(announcement.id, announcement.clone());
+            tracing::info!("Sending announcement {} by {} to {:?}", announcement.id, announcement.created_by, announcement.segment);
+            let _ = self.due.send(announcement);
+        }
+    }
+
+    // Announcements as they fall due on this instance
+    pub fn subscribe(&self) -> broadcast::Receiver<Announcement> {
+        self.due.subscribe()
+    }
+
+    pub fn region(&self) -> Option<&str> {
+        self.region.as_deref()
+    }
+
+    pub async fn motd(&self) -> Option<Motd> {
+        self.motd.read().await.clone()
+    }
+
+    pub async fn set_motd(&self, spec: MotdSpec) -> Result<Motd> {
+        let message = spec.message.trim();
+        if message.is_empty() || message.chars().count() > MAX_MESSAGE_LEN || spec.updated_by.trim().is_empty() {
+            return Err(Error::InvalidMessage);
+        }
+        let motd = Motd {
+            message: message.to_string(),
+            updated_by: spec.updated_by.trim().to_string(),
+            updated_at: Utc::now(),
+        };
+        self.repo.upsert_motd(&motd).await?;
+        tracing::info!("Message of the day set by {}", motd.updated_by);
+        *self.motd.write().await = Some(motd.clone());
+        Ok(motd)
+    }
+
+    pub async fn clear_motd(&self) -> Result<()> {
+        self.repo.delete_motd().await?;
+        *self.motd.write().await = None;
+        Ok(())
+    }
+
+    // Store an announcement, sent now or at `send_at`
+    pub async fn announce(
+        &self,
+        message: &str,
+        content_key: Option<String>,
+        segment: Segment,
+        send_at: Option<DateTime<Utc>>,
+        created_by: &str,
+    ) -> Result<Announcement> {
+        let message = message.trim();
+        if message.is_empty() || message.chars().count() > MAX_MESSAGE_LEN || created_by.trim().is_empty() {
+            return Err(Error::InvalidMessage);
+        }
+        let now = Utc::now();
+        let latest = now + chrono::Duration::from_std(MAX_SCHEDULE_AHEAD).unwrap_or_default();
+        if send_at.is_some_and(|at| at > latest) {
+            return Err(Error::InvalidMessage);
+        }
+
+        let announcement = Announcement {
+            id: Uuid::new_v4(),
+            message: message.to_string(),
+            content_key,
+            segment,
+            send_at: send_at.unwrap_or(now).max(now),
+            created_by: created_by.trim().to_string(),
+            created_at: now,
+            cancelled_at: None,
+        };
+        self.repo.insert_announcement(&announcement).await?;
+        self.scheduled.write().await.insert(announcement.id, announcement.clone());
+        Ok(announcement)
+    }
+
+    // Announcements sent here within the last ten minutes, oldest first, for
+    // players who connect after they went out
+    pub async fn recent(&self) -> Vec<Announcement> {
+        let cutoff = self.cutoff();
+        let mut recent: Vec<Announcement> = self.sent.lock().await
+            .values()
+            .filter(|announcement| announcement.send_at >= cutoff)
+            .cloned()
+            .collect();
+        recent.sort_by_key(|announcement| announcement.send_at);
+        recent
+    }
+
+    // Announcements waiting for their send time, soonest first
+    pub async fn upcoming(&self) -> Vec<Announcement> {
+        let mut upcoming: Vec<Announcement> = self.scheduled.read().await.values().cloned().collect();
+        upcoming.sort_by_key(|announcement| announcement.send_at);
+        upcoming
+    }
+
+    // Fails with `Error::NotFound` unless the announcement is still scheduled
+    pub async fn cancel(&self, id: Uuid) -> Result<()> {
+        if !self.repo.cancel_announcement(id, Utc::now()).await? {
+            return Err(Error::NotFound(format!("scheduled announcement {}", id)));
+        }
+        self.scheduled.write().await.remove(&id);
+        Ok(())
+    }
+}
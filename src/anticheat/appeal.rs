@@ -0,0 +1,166 @@
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::db::repository::AppealRepository;
+use crate::error::{Error, Result};
+use crate::matchmaking::service::MatchService;
+use super::trust::{SignalRecord, TrustReport};
+
+// Longest appeal message, in characters
+const MAX_MESSAGE_LEN: usize = 2000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum AppealStatus {
+    Open,
+    Accepted,
+    Rejected,
+}
+
+// A player contesting their trust score or quarantine. The evidence is
+// copied in when the appeal is submitted, so reviewers see what the player
+// was judged on even after their score has moved on.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct Appeal {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub message: String,
+    pub status: AppealStatus,
+    // Trust when the appeal was submitted; absent if the node had no history
+    pub trust: Option<TrustReport>,
+    // Latest samples that raised signals, oldest first
+    pub signals: Vec<SignalRecord>,
+    pub quarantined: bool,
+    pub created_at: DateTime<Utc>,
+    pub resolved_at: Option<DateTime<Utc>>,
+    pub resolved_by: Option<String>,
+    // Reviewer's note to the record
+    pub resolution: Option<String>,
+}
+
+// trust.appeal request
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct AppealRequest {
+    pub message: String,
+}
+
+// trust.appeal reply
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct AppealReceipt {
+    pub id: Uuid,
+    pub status: AppealStatus,
+    pub created_at: DateTime<Utc>,
+}
+
+// POST /admin/appeals/{id}/resolve body
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+pub struct AppealResolution {
+    pub resolved_by: String,
+    pub accept: bool,
+    pub note: String,
+    // Trust score to set; accepting without one restores full trust
+    pub score: Option<f32>,
+}
+
+// Appeals against anti-cheat decisions. Accepting one restores the player's
+// trust and takes them out of quarantine.
+pub struct AppealService {
+    repo: Arc<dyn AppealRepository>,
+    match_service: Arc<MatchService>,
+}
+
+impl AppealService {
+    pub fn new(repo: Arc<dyn AppealRepository>, match_service: Arc<MatchService>) -> Arc<Self> {
+        Arc::new(Self { repo, match_service })
+    }
+
+    // One open appeal per player; there must be something to appeal
+    pub async fn submit(&self, user_id: Uuid, message: &str) -> Result<AppealReceipt> {
+        let message = message.trim();
+        if message.is_empty() || message.chars().count() > MAX_MESSAGE_LEN {
+            return Err(Error::InvalidMessage);
+        }
+        if self.repo.open_appeal(user_id).await?.is_some() {
+            return Err(Error::DuplicateKey(format!("open appeal of {}", user_id)));
+        }
+
+        let history = self.match_service.trust().history(user_id).await;
+        let quarantined = self.match_service.quarantine().is_quarantined(user_id).await;
+        let flagged = history.as_ref().is_some_and(|(report, _)| report.score < 1.0);
+        if !flagged && !quarantined {
+            return Err(Error::PermissionDenied("nothing to appeal".to_string()));
+        }
+        let (trust, signals) = match history {
+            Some((report, log)) => (Some(report), log),
+            None => (None, Vec::new()),
+        };
+
+        let appeal = Appeal {
+            id: Uuid::new_v4(),
+            user_id,
+            message: message.to_string(),
+            status: AppealStatus::Open,
+            trust,
+            signals,
+            quarantined,
+            created_at: Utc::now(),
+            resolved_at: None,
+            resolved_by: None,
+            resolution: None,
+        };
+        self.repo.insert_appeal(&appeal).await?;
+        tracing::info!("User {} appealed their trust score (appeal {})", user_id, appeal.id);
+        Ok(AppealReceipt {
+            id: appeal.id,
+            status: appeal.status,
+            created_at: appeal.created_at,
+        })
+    }
+
+    // Newest first
+    pub async fn list(&self, status: Option<AppealStatus>) -> Result<Vec<Appeal>> {
+        self.repo.list_appeals(status).await
+    }
+
+    pub async fn resolve(&self, id: Uuid, resolution: AppealResolution) -> Result<Appeal> {
+        if resolution.resolved_by.trim().is_empty() || resolution.note.trim().is_empty() {
+            return Err(Error::InvalidMessage);
+        }
+        if resolution.score.is_some_and(|score| !(0.0..=1.0).contains(&score)) {
+            return Err(Error::InvalidMessage);
+        }
+        let mut appeal = self.repo.get_appeal(id).await?
+            .ok_or_else(|| Error::NotFound(format!("appeal {}", id)))?;
+        if appeal.status != AppealStatus::Open {
+            return Err(Error::VersionConflict);
+        }
+
+        appeal.status = if resolution.accept { AppealStatus::Accepted } else { AppealStatus::Rejected };
+        appeal.resolved_at = Some(Utc::now());
+        appeal.resolved_by = Some(resolution.resolved_by);
+        appeal.resolution = Some(resolution.note);
+        self.repo.update_appeal(&appeal).await?;
+
+        let score = match (resolution.accept, resolution.score) {
+            (_, Some(score)) => Some(score),
+            (true, None) => Some(1.0),
+            (false, None) => None,
+        };
+        if let Some(score) = score {
+            self.match_service.trust().adjust(appeal.user_id, score).await;
+        }
+        if resolution.accept && appeal.quarantined {
+            match self.match_service.quarantine().remove(appeal.user_id).await {
+                Ok(()) | Err(Error::NotFound(_)) => {}
+                Err(e) => return Err(e),
+            }
+        }
+        tracing::info!("Appeal {} of user {} {:?} by {:?}", appeal.id, appeal.user_id, appeal.status, appeal.resolved_by);
+        Ok(appeal)
+    }
+}
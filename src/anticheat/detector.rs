@@ -0,0 +1,93 @@
+use std::time::Instant;
+
+use crate::config::AntiCheatConfig;
+use crate::models::game::PlayerPosition;
+
+// Shortest interval used to compute speed, so bursts of messages don't divide by ~0
+const MIN_SAMPLE_SECS: f32 = 0.25;
+// Speed samples needed before outlier scoring kicks in
+const MIN_OUTLIER_SAMPLES: u64 = 20;
+// Standard deviations above the player's mean speed that count as an outlier
+const OUTLIER_Z: f32 = 4.0;
+
+// Why a position sample looks spoofed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Signal {
+    // Faster than anyone can move on foot or by bike
+    ImpossibleSpeed,
+    // A single jump longer than the teleport distance
+    Teleport,
+    // The client's OS reported a mock location provider
+    MockLocation,
+    // Far off the player's own speed distribution
+    Outlier,
+}
+
+impl Signal {
+    pub fn to_str(self) -> &'static str {
+        match self {
+            Signal::ImpossibleSpeed => "impossible_speed",
+            Signal::Teleport => "teleport",
+            Signal::MockLocation => "mock_location",
+            Signal::Outlier => "outlier",
+        }
+    }
+}
+
+// Movement history of one player, enough to judge the next sample
+#[derive(Debug, Clone, Default)]
+pub struct MovementState {
+    last: Option<(PlayerPosition, Instant)>,
+    // Welford running mean/variance of speeds
+    samples: u64,
+    mean: f32,
+    m2: f32,
+}
+
+impl MovementState {
+    // Check one sample against the previous one and update the history.
+    // Suspicious jumps are not learned, so they don't widen the distribution.
+    pub fn observe(&mut self, config: &AntiCheatConfig, pos: &PlayerPosition, mock_location: bool, at: Instant) -> Vec<Signal> {
+        let mut signals = Vec::new();
+        if mock_location {
+            signals.push(Signal::MockLocation);
+        }
+
+        if let Some((prev, prev_at)) = &self.last {
+            let dx = pos.x - prev.x;
+            let dy = pos.y - prev.y;
+            let distance = (dx * dx + dy * dy).sqrt();
+            let secs = at.duration_since(*prev_at).as_secs_f32().max(MIN_SAMPLE_SECS);
+            let speed = distance / secs;
+
+            if distance > config.teleport_distance {
+                signals.push(Signal::Teleport);
+            } else if speed > config.max_speed {
+                signals.push(Signal::ImpossibleSpeed);
+            } else {
+                if self.is_outlier(speed) {
+                    signals.push(Signal::Outlier);
+                }
+                self.learn(speed);
+            }
+        }
+
+        self.last = Some((pos.clone(), at));
+        signals
+    }
+
+    fn is_outlier(&self, speed: f32) -> bool {
+        if self.samples < MIN_OUTLIER_SAMPLES {
+            return false;
+        }
+        let std_dev = (self.m2 / (self.samples - 1) as f32).sqrt();
+        std_dev > 0.0 && (speed - self.mean) / std_dev > OUTLIER_Z
+    }
+
+    fn learn(&mut self, speed: f32) {
+        self.samples += 1;
+        let delta = speed - self.mean;
+        self.mean += delta / self.samples as f32;
+        self.m2 += delta * (speed - self.mean);
+    }
+}
@@ -0,0 +1,4 @@
+pub mod appeal;
+pub mod detector;
+pub mod quarantine;
+pub mod trust;
\ No newline at end of file
@@ -0,0 +1,151 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::sync::RwLock;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::db::repository::QuarantineRepository;
+use crate::error::{Error, Result};
+use crate::supervisor;
+
+// Quarantines changed on another instance apply here within this long
+const REFRESH_INTERVAL: Duration = Duration::from_secs(30);
+
+// Recorded as `added_by` when the trust score put the player there
+const AUTOMATIC: &str = "anticheat";
+
+// A player matched only with other quarantined players
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct Quarantine {
+    pub user_id: Uuid,
+    pub reason: String,
+    // Admin who quarantined the player, or "anticheat"
+    pub added_by: String,
+    pub created_at: DateTime<Utc>,
+}
+
+// PUT /admin/quarantine/{user_id} body
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+pub struct QuarantineSpec {
+    pub added_by: String,
+    pub reason: String,
+}
+
+// Players flagged for cheating who are kept playing, but only against each
+// other. Unlike a suspected spoofer, whose trust recovers with clean
+// positions, a quarantined player stays in the pool until an admin takes them
+// out. Stored in the database and cached in memory for the join path.
+pub struct QuarantineService {
+    repo: Arc<dyn QuarantineRepository>,
+    // ANTICHEAT_QUARANTINE_THRESHOLD
+    threshold: Option<f32>,
+    quarantined: RwLock<HashMap<Uuid, Quarantine>>,
+}
+
+impl QuarantineService {
+    // Like bans, a database failure here only delays loading to the next refresh
+    pub async fn init(repo: Arc<dyn QuarantineRepository>, threshold: Option<f32>) -> Arc<Self> {
+        let service = Arc::new(Self {
+            repo,
+            threshold,
+            quarantined: RwLock::new(HashMap::new()),
+        });
+        if let Err(e) = service.reload().await {
+            tracing::warn!("Failed to load quarantined players, starting without any: {}", e);
+        }
+
+        let refresher = service.clone();
+        supervisor::spawn_loop("quarantine_refresh", move || {
+            let refresher = refresher.clone();
+            async move {
+                let mut interval = tokio::time::interval(REFRESH_INTERVAL);
+                interval.tick().await;
+                loop {
+                    interval.tick().await;
+                    if let Err(e) = refresher.reload().await {
+                        tracing::warn!("Failed to refresh quarantined players: {}", e);
+                    }
+                }
+            }
+        });
+        service
+    }
+
+    async fn reload(&self) -> Result<()> {
+        let loaded = self.repo.list_quarantined().await?
+            .into_iter()
+            .map(|quarantine| (quarantine.user_id, quarantine))
+            .collect();
+        *self.quarantined.write().await = loaded;
+        Ok(())
+    }
+
+    // Whether the player belongs in the quarantine pool. A trust score below
+    // the threshold quarantines them on the spot.
+    pub async fn check(&self, user_id: Uuid, trust: f32) -> bool {
+        if self.is_quarantined(user_id).await {
+            return true;
+        }
+        let Some(threshold) = self.threshold.filter(|threshold| trust < *threshold) else {
+            return false;
+        };
+
+        let quarantine = Quarantine {
+            user_id,
+            reason: format!("trust score {:.2} below {:.2}", trust, threshold),
+            added_by: AUTOMATIC.to_string(),
+            created_at: Utc::now(),
+        };
+        // Still pooled with the other cheaters if the write fails; it is
+        // retried on their next join
+        if let Err(e) = self.repo.add_quarantined(&quarantine).await {
+            tracing::warn!("Failed to save quarantine of user {}: {}", user_id, e);
+            return true;
+        }
+        tracing::warn!("User {} quarantined: {}", user_id, quarantine.reason);
+        self.quarantined.write().await.insert(user_id, quarantine);
+        true
+    }
+
+    pub async fn is_quarantined(&self, user_id: Uuid) -> bool {
+        self.quarantined.read().await.contains_key(&user_id)
+    }
+
+    // Newest first
+    pub async fn list(&self) -> Vec<Quarantine> {
+        let mut list: Vec<Quarantine> = self.quarantined.read().await.values().cloned().collect();
+        list.sort_by_key(|quarantine| std::cmp::Reverse(quarantine.created_at));
+        list
+    }
+
+    pub async fn add(&self, user_id: Uuid, spec: QuarantineSpec) -> Result<Quarantine> {
+        if spec.added_by.trim().is_empty() || spec.reason.trim().is_empty() {
+            return Err(Error::InvalidMessage);
+        }
+        let quarantine = Quarantine {
+            user_id,
+            reason: spec.reason,
+            added_by: spec.added_by,
+            created_at: Utc::now(),
+        };
+        self.repo.add_quarantined(&quarantine).await?;
+        self.quarantined.write().await.insert(user_id, quarantine.clone());
+        tracing::info!("User {} quarantined by {}: {}", user_id, quarantine.added_by, quarantine.reason);
+        Ok(quarantine)
+    }
+
+    // Takes effect from the player's next match
+    pub async fn remove(&self, user_id: Uuid) -> Result<()> {
+        let removed = self.repo.remove_quarantined(user_id).await?;
+        self.quarantined.write().await.remove(&user_id);
+        if !removed {
+            return Err(Error::NotFound(format!("quarantine of {}", user_id)));
+        }
+        tracing::info!("User {} released from quarantine", user_id);
+        Ok(())
+    }
+}
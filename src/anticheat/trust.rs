@@ -0,0 +1,207 @@
+use std::collections::{HashMap, VecDeque};
+use std::time::Instant;
+
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use tokio::sync::RwLock;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::config::AntiCheatConfig;
+use crate::models::game::PlayerPosition;
+use super::detector::{MovementState, Signal};
+
+// Trust regained per clean sample, so honest players recover from GPS glitches
+const RECOVERY_PER_SAMPLE: f32 = 0.01;
+// Latest signals kept per player, attached to their appeals
+const LOG_LEN: usize = 50;
+
+impl Signal {
+    // Trust lost when the signal fires
+    fn penalty(&self) -> f32 {
+        match self {
+            Signal::MockLocation => 0.5,
+            Signal::Teleport => 0.3,
+            Signal::ImpossibleSpeed => 0.1,
+            Signal::Outlier => 0.05,
+        }
+    }
+}
+
+// Trust of one player, for /admin/trust
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, JsonSchema)]
+pub struct TrustReport {
+    pub user_id: Uuid,
+    // 1.0 = fully trusted, 0.0 = certainly spoofing
+    pub score: f32,
+    pub suspected: bool,
+    pub samples: u64,
+    // Times each signal fired
+    pub signals: HashMap<String, u64>,
+    pub last_signal_at: Option<DateTime<Utc>>,
+}
+
+// One sample that raised signals
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, JsonSchema)]
+pub struct SignalRecord {
+    pub signals: Vec<String>,
+    pub x: f32,
+    pub y: f32,
+    // Score right after the sample
+    pub score: f32,
+    pub at: DateTime<Utc>,
+}
+
+struct UserTrust {
+    score: f32,
+    // When `score` was last brought up to date with the hourly recovery
+    scored_at: Instant,
+    movement: MovementState,
+    samples: u64,
+    signals: HashMap<Signal, u64>,
+    last_signal_at: Option<DateTime<Utc>>,
+    log: VecDeque<SignalRecord>,
+}
+
+impl Default for UserTrust {
+    fn default() -> Self {
+        Self {
+            score: 1.0,
+            scored_at: Instant::now(),
+            movement: MovementState::default(),
+            samples: 0,
+            signals: HashMap::new(),
+            last_signal_at: None,
+            log: VecDeque::new(),
+        }
+    }
+}
+
+impl UserTrust {
+    // Score with the trust regained since `scored_at`
+    fn current(&self, per_hour: f32, now: Instant) -> f32 {
+        let hours = now.saturating_duration_since(self.scored_at).as_secs_f32() / 3600.0;
+        (self.score + per_hour * hours).min(1.0)
+    }
+}
+
+// Per-user trust scores built from reported positions. Matchmaking puts
+// suspected spoofers in separate pools, and their discoveries are refused.
+// Suspicion wears off: players regain ANTICHEAT_TRUST_RECOVERY_PER_HOUR trust
+// every hour on top of what clean samples give back.
+// Scores live in memory and start over when the server restarts.
+pub struct TrustTracker {
+    config: AntiCheatConfig,
+    users: RwLock<HashMap<Uuid, UserTrust>>,
+}
+
+impl TrustTracker {
+    pub fn new(config: AntiCheatConfig) -> Self {
+        Self {
+            config,
+            users: RwLock::new(HashMap::new()),
+        }
+    }
+
+    // Score a position sample; returns the signals it raised
+    pub async fn observe(&self, user_id: Uuid, pos: &PlayerPosition, mock_location: bool) -> Vec<Signal> {
+        let now = Instant::now();
+        let mut users = self.users.write().await;
+        let user = users.entry(user_id).or_default();
+        user.score = user.current(self.config.recovery_per_hour, now);
+        user.scored_at = now;
+        let signals = user.movement.observe(&self.config, pos, mock_location, now);
+        user.samples += 1;
+
+        if signals.is_empty() {
+            user.score = (user.score + RECOVERY_PER_SAMPLE).min(1.0);
+            return signals;
+        }
+
+        let was_suspected = user.score < self.config.suspect_threshold;
+        for signal in &signals {
+            user.score -= signal.penalty();
+            *user.signals.entry(*signal).or_insert(0) += 1;
+        }
+        user.score = user.score.max(0.0);
+        user.last_signal_at = Some(Utc::now());
+        if user.log.len() == LOG_LEN {
+            user.log.pop_front();
+        }
+        user.log.push_back(SignalRecord {
+            signals: signals.iter().map(|signal| signal.to_str().to_string()).collect(),
+            x: pos.x,
+            y: pos.y,
+            score: user.score,
+            at: Utc::now(),
+        });
+
+        if !was_suspected && user.score < self.config.suspect_threshold {
+            tracing::warn!("User {} suspected of location spoofing (trust {:.2}, signals {:?})", user_id, user.score, signals);
+        }
+        signals
+    }
+
+    pub async fn score(&self, user_id: Uuid) -> f32 {
+        self.users
+            .read()
+            .await
+            .get(&user_id)
+            .map_or(1.0, |u| u.current(self.config.recovery_per_hour, Instant::now()))
+    }
+
+    pub async fn is_suspected(&self, user_id: Uuid) -> bool {
+        self.score(user_id).await < self.config.suspect_threshold
+    }
+
+    fn report(&self, user_id: Uuid, user: &UserTrust, now: Instant) -> TrustReport {
+        let score = user.current(self.config.recovery_per_hour, now);
+        TrustReport {
+            user_id,
+            score,
+            suspected: score < self.config.suspect_threshold,
+            samples: user.samples,
+            signals: user.signals
+                .iter()
+                .map(|(signal, count)| (signal.to_str().to_string(), *count))
+                .collect(),
+            last_signal_at: user.last_signal_at,
+        }
+    }
+
+    // Lowest scores first
+    pub async fn reports(&self, suspected_only: bool) -> Vec<TrustReport> {
+        let now = Instant::now();
+        let users = self.users.read().await;
+        let mut reports: Vec<TrustReport> = users
+            .iter()
+            .map(|(user_id, user)| self.report(*user_id, user, now))
+            .filter(|report| !suspected_only || report.suspected)
+            .collect();
+        reports.sort_by(|a, b| a.score.total_cmp(&b.score));
+        reports
+    }
+
+    // The player's report and latest signals, oldest first; None without history
+    pub async fn history(&self, user_id: Uuid) -> Option<(TrustReport, Vec<SignalRecord>)> {
+        let users = self.users.read().await;
+        let user = users.get(&user_id)?;
+        Some((self.report(user_id, user, Instant::now()), user.log.iter().cloned().collect()))
+    }
+
+    // Set a player's score by hand, keeping their history
+    pub async fn adjust(&self, user_id: Uuid, score: f32) -> TrustReport {
+        let now = Instant::now();
+        let mut users = self.users.write().await;
+        let user = users.entry(user_id).or_default();
+        user.score = score.clamp(0.0, 1.0);
+        user.scored_at = now;
+        self.report(user_id, user, now)
+    }
+
+    // Forget a user's history, e.g. after a manual review
+    pub async fn reset(&self, user_id: Uuid) -> bool {
+        self.users.write().await.remove(&user_id).is_some()
+    }
+}
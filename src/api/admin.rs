@@ -0,0 +1,307 @@
+use axum::{
+    Json,
+    async_trait,
+    extract::{FromRequestParts, Path, Request, State},
+    http::{HeaderMap, Method, StatusCode, header, request::Parts},
+    middleware::Next,
+    response::Response,
+};
+use uuid::Uuid;
+
+use crate::AppState;
+use crate::apikeys::key::{Caller, Scope};
+use crate::config::AdminConfig;
+use crate::error::{Error, Result};
+use crate::experiments::experiment::{Experiment, ExperimentSpec};
+use crate::gateway::match_stats::MatchStats;
+use crate::gateway::state::ConnectionInfo;
+use crate::remote_config::document::{ClientConfig, ConfigChange, ConfigUpdate};
+use super::listing::{ListQuery, ListSpec, Page, PageParams};
+
+// Check an admin token against ADMIN_TOKEN; admin access is off when it is unset
+pub fn verify_token(config: &AdminConfig, token: &str) -> Result<()> {
+    let expected = config.token.as_deref()
+        .ok_or_else(|| Error::PermissionDenied("admin API is disabled".to_string()))?;
+    if constant_time_eq(expected.as_bytes(), token.as_bytes()) {
+        Ok(())
+    } else {
+        Err(Error::AuthError)
+    }
+}
+
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+fn bearer_token(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+}
+
+fn is_read(method: &Method) -> bool {
+    method == Method::GET || method == Method::HEAD
+}
+
+// Requires `Authorization: Bearer <ADMIN_TOKEN>`, or an API key with the
+// admin:read scope for GET routes and admin:write for the others
+pub struct AdminAuth(pub Caller);
+
+#[async_trait]
+impl FromRequestParts<AppState> for AdminAuth {
+    type Rejection = Error;
+
+    async fn from_request_parts(parts: &mut Parts, state: &AppState) -> Result<Self> {
+        let caller = match parts.extensions.get::<Caller>() {
+            Some(caller) => caller.clone(),
+            None => {
+                let token = bearer_token(&parts.headers).ok_or(Error::AuthError)?;
+                state.api_keys.authenticate(&state.config.admin, token)?
+            }
+        };
+        let scope = if is_read(&parts.method) { Scope::AdminRead } else { Scope::AdminWrite };
+        if !caller.allows(scope) {
+            return Err(Error::PermissionDenied(format!("API key lacks the {} scope", scope.to_str())));
+        }
+        Ok(AdminAuth(caller))
+    }
+}
+
+// Identify the caller of admin routes once, for the `AdminAuth` extractor, and
+// record every change they make in the audit log. Requests without valid
+// credentials pass through; `AdminAuth` rejects them.
+pub async fn authenticate(State(state): State<AppState>, mut req: Request, next: Next) -> Response {
+    if !req.uri().path().starts_with("/admin") {
+        return next.run(req).await;
+    }
+    let caller = bearer_token(req.headers())
+        .and_then(|token| state.api_keys.authenticate(&state.config.admin, token).ok());
+    let Some(caller) = caller else {
+        return next.run(req).await;
+    };
+
+    let method = req.method().clone();
+    let path = req.uri().path().to_string();
+    req.extensions_mut().insert(caller.clone());
+    let response = next.run(req).await;
+    if !is_read(&method) {
+        state.audit.record(&caller, format!("{} {}", method, path), response.status().as_u16());
+    }
+    response
+}
+
+// Live stats of every match the server is tracking
+#[utoipa::path(
+    get,
+    path = "/admin/matches",
+    tag = "admin",
+    security(("admin_token" = [])),
+    params(PageParams),
+    responses(
+        (status = 200, description = "Per-match runtime stats", body = MatchStatsPage),
+        (status = 400, description = "Invalid sort, filter or cursor", body = ErrorBody),
+        (status = 401, description = "Missing or wrong admin token", body = ErrorBody),
+        (status = 403, description = "Admin API disabled", body = ErrorBody)
+    )
+)]
+pub async fn list_matches(_: AdminAuth, State(state): State<AppState>, query: ListQuery) -> Result<Json<Page<MatchStats>>> {
+    const SPEC: ListSpec = ListSpec {
+        default_sort: "-connected_players",
+        sort: &["duration_secs", "messages_per_sec", "discoveries", "avg_rtt_ms"],
+        filters: &["match_type", "status", "connected_players"],
+        key: &["match_id"],
+        params: &[],
+    };
+    Ok(Json(query.apply(&SPEC, state.ws_handler.match_stats().await)?))
+}
+
+// Every open WebSocket and SSE connection on this node
+#[utoipa::path(
+    get,
+    path = "/admin/connections",
+    tag = "admin",
+    security(("admin_token" = [])),
+    params(PageParams),
+    responses(
+        (status = 200, description = "Open connections with their match and link quality", body = ConnectionInfoPage),
+        (status = 400, description = "Invalid sort, filter or cursor", body = ErrorBody),
+        (status = 401, description = "Missing or wrong admin token", body = ErrorBody),
+        (status = 403, description = "Admin API disabled", body = ErrorBody)
+    )
+)]
+pub async fn list_connections(_: AdminAuth, State(state): State<AppState>, query: ListQuery) -> Result<Json<Page<ConnectionInfo>>> {
+    const SPEC: ListSpec = ListSpec {
+        default_sort: "user_id",
+        sort: &["rtt_ms", "ip"],
+        filters: &["user_id", "match_id", "ip", "platform", "quality", "rtt_ms"],
+        key: &["conn_id"],
+        params: &[],
+    };
+    Ok(Json(query.apply(&SPEC, state.conn_manager.connection_infos().await)?))
+}
+
+// End a running match right away, as if it had finished. Players are told
+// the match ended and the game loop stops.
+#[utoipa::path(
+    post,
+    path = "/admin/matches/{match_id}/end",
+    tag = "admin",
+    security(("admin_token" = [])),
+    params(("match_id" = Uuid, Path, description = "Running match owned by this node")),
+    responses(
+        (status = 204, description = "Match ended"),
+        (status = 401, description = "Missing or wrong admin token", body = ErrorBody),
+        (status = 403, description = "Admin API disabled", body = ErrorBody),
+        (status = 404, description = "Match not running", body = ErrorBody),
+        (status = 500, description = "Match owned by another node", body = ErrorBody)
+    )
+)]
+pub async fn end_match(
+    _: AdminAuth,
+    State(state): State<AppState>,
+    Path(match_id): Path<Uuid>,
+) -> Result<StatusCode> {
+    end_running_match(&state, match_id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// Shared with the admin console
+pub async fn end_running_match(state: &AppState, match_id: Uuid) -> Result<()> {
+    if !state.match_service.owns(match_id).await {
+        return match state.match_service.remote_owner(match_id).await {
+            Some(node) => Err(Error::ClusterError(format!("match is running on node {}", node))),
+            None => Err(Error::MatchNotFound),
+        };
+    }
+
+    state.match_service.abort_match(match_id).await?;
+    tracing::info!("Match {} ended by an admin", match_id);
+    Ok(())
+}
+
+// All experiment definitions
+#[utoipa::path(
+    get,
+    path = "/admin/experiments",
+    tag = "admin",
+    security(("admin_token" = [])),
+    params(PageParams),
+    responses(
+        (status = 200, description = "Experiments, by key", body = ExperimentPage),
+        (status = 400, description = "Invalid sort, filter or cursor", body = ErrorBody),
+        (status = 401, description = "Missing or wrong admin token", body = ErrorBody),
+        (status = 403, description = "Admin API disabled", body = ErrorBody)
+    )
+)]
+pub async fn list_experiments(_: AdminAuth, State(state): State<AppState>, query: ListQuery) -> Result<Json<Page<Experiment>>> {
+    const SPEC: ListSpec = ListSpec {
+        default_sort: "key",
+        sort: &[],
+        filters: &["key", "enabled"],
+        key: &["key"],
+        params: &[],
+    };
+    Ok(Json(query.apply(&SPEC, state.experiments.list().await)?))
+}
+
+// Create or replace an experiment. Connected clients see the change on their next connection.
+#[utoipa::path(
+    put,
+    path = "/admin/experiments/{key}",
+    tag = "admin",
+    security(("admin_token" = [])),
+    params(("key" = String, Path, description = "Experiment key")),
+    request_body = ExperimentSpec,
+    responses(
+        (status = 200, description = "Stored experiment", body = Experiment),
+        (status = 400, description = "No variants, duplicate names or zero total weight", body = ErrorBody),
+        (status = 401, description = "Missing or wrong admin token", body = ErrorBody),
+        (status = 403, description = "Admin API disabled", body = ErrorBody)
+    )
+)]
+pub async fn put_experiment(
+    _: AdminAuth,
+    State(state): State<AppState>,
+    Path(key): Path<String>,
+    body: String,
+) -> Result<Json<Experiment>> {
+    let spec: ExperimentSpec = serde_json::from_str(&body)
+        .map_err(|_| Error::InvalidMessage)?;
+    Ok(Json(state.experiments.put(&key, spec).await?))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/admin/experiments/{key}",
+    tag = "admin",
+    security(("admin_token" = [])),
+    params(("key" = String, Path, description = "Experiment key")),
+    responses(
+        (status = 204, description = "Deleted"),
+        (status = 401, description = "Missing or wrong admin token", body = ErrorBody),
+        (status = 403, description = "Admin API disabled", body = ErrorBody),
+        (status = 404, description = "Unknown experiment", body = ErrorBody)
+    )
+)]
+pub async fn delete_experiment(
+    _: AdminAuth,
+    State(state): State<AppState>,
+    Path(key): Path<String>,
+) -> Result<StatusCode> {
+    state.experiments.delete(&key).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// Change the remote config; the new version reaches clients on their next
+// connection or GET /api/config/client
+#[utoipa::path(
+    patch,
+    path = "/admin/config/client",
+    tag = "admin",
+    security(("admin_token" = [])),
+    request_body = ConfigUpdate,
+    responses(
+        (status = 200, description = "Document after the update", body = ClientConfig),
+        (status = 400, description = "Missing changed_by or empty key", body = ErrorBody),
+        (status = 401, description = "Missing or wrong admin token", body = ErrorBody),
+        (status = 403, description = "Admin API disabled", body = ErrorBody),
+        (status = 409, description = "expected_version is stale", body = ErrorBody)
+    )
+)]
+pub async fn update_client_config(_: AdminAuth, State(state): State<AppState>, body: String) -> Result<Json<ClientConfig>> {
+    let update: ConfigUpdate = serde_json::from_str(&body)
+        .map_err(|_| Error::InvalidMessage)?;
+    Ok(Json(state.remote_config.update(update).await?))
+}
+
+// Audit trail of the remote config, most recent first
+#[utoipa::path(
+    get,
+    path = "/admin/config/client/history",
+    tag = "admin",
+    security(("admin_token" = [])),
+    params(PageParams),
+    responses(
+        (status = 200, description = "Who changed what, per version", body = ConfigChangePage),
+        (status = 400, description = "Invalid sort, filter or cursor", body = ErrorBody),
+        (status = 401, description = "Missing or wrong admin token", body = ErrorBody),
+        (status = 403, description = "Admin API disabled", body = ErrorBody)
+    )
+)]
+pub async fn client_config_history(
+    _: AdminAuth,
+    State(state): State<AppState>,
+    query: ListQuery,
+) -> Result<Json<Page<ConfigChange>>> {
+    const SPEC: ListSpec = ListSpec {
+        default_sort: "-version",
+        sort: &["changed_at"],
+        filters: &["changed_by", "version", "changed_at"],
+        key: &["version"],
+        params: &[],
+    };
+    let keyset = query.keyset(&SPEC)?;
+    let (history, total) = state.remote_config.history(&keyset).await?;
+    Ok(Json(query.page(&keyset, history, total)?))
+}
@@ -0,0 +1,111 @@
+use axum::{
+    Json,
+    extract::{Path, State},
+    http::StatusCode,
+};
+use uuid::Uuid;
+
+use crate::AppState;
+use crate::announcements::announcement::{Announcement, Motd, MotdSpec};
+use crate::error::{Error, Result};
+use super::admin::AdminAuth;
+use super::listing::{ListQuery, ListSpec, Page, PageParams};
+
+// Message of the day and scheduled announcements. Announcements themselves
+// are made with the `admin.announce` WebSocket command.
+
+#[utoipa::path(
+    get,
+    path = "/admin/motd",
+    tag = "admin",
+    security(("admin_token" = [])),
+    responses(
+        (status = 200, description = "Current message of the day", body = Motd),
+        (status = 401, description = "Missing or wrong admin token", body = ErrorBody),
+        (status = 403, description = "Admin API disabled", body = ErrorBody),
+        (status = 404, description = "No message of the day is set", body = ErrorBody)
+    )
+)]
+pub async fn get_motd(_: AdminAuth, State(state): State<AppState>) -> Result<Json<Motd>> {
+    state.announcements.motd().await
+        .map(Json)
+        .ok_or_else(|| Error::NotFound("message of the day".to_string()))
+}
+
+// Set the message of the day, shown in the welcome message from now on
+#[utoipa::path(
+    put,
+    path = "/admin/motd",
+    tag = "admin",
+    security(("admin_token" = [])),
+    request_body = MotdSpec,
+    responses(
+        (status = 200, description = "Message of the day set", body = Motd),
+        (status = 400, description = "Empty or too long message, or missing updated_by", body = ErrorBody),
+        (status = 401, description = "Missing or wrong admin token", body = ErrorBody),
+        (status = 403, description = "Admin API disabled", body = ErrorBody)
+    )
+)]
+pub async fn put_motd(_: AdminAuth, State(state): State<AppState>, body: String) -> Result<Json<Motd>> {
+    let spec: MotdSpec = serde_json::from_str(&body)
+        .map_err(|_| Error::InvalidMessage)?;
+    Ok(Json(state.announcements.set_motd(spec).await?))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/admin/motd",
+    tag = "admin",
+    security(("admin_token" = [])),
+    responses(
+        (status = 204, description = "Message of the day removed"),
+        (status = 401, description = "Missing or wrong admin token", body = ErrorBody),
+        (status = 403, description = "Admin API disabled", body = ErrorBody)
+    )
+)]
+pub async fn delete_motd(_: AdminAuth, State(state): State<AppState>) -> Result<StatusCode> {
+    state.announcements.clear_motd().await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[utoipa::path(
+    get,
+    path = "/admin/announcements",
+    tag = "admin",
+    security(("admin_token" = [])),
+    params(PageParams),
+    responses(
+        (status = 200, description = "Announcements waiting for their send time, soonest first", body = AnnouncementPage),
+        (status = 400, description = "Invalid sort, filter or cursor", body = ErrorBody),
+        (status = 401, description = "Missing or wrong admin token", body = ErrorBody),
+        (status = 403, description = "Admin API disabled", body = ErrorBody)
+    )
+)]
+pub async fn list_announcements(_: AdminAuth, State(state): State<AppState>, query: ListQuery) -> Result<Json<Page<Announcement>>> {
+    const SPEC: ListSpec = ListSpec {
+        default_sort: "send_at",
+        sort: &["created_at"],
+        filters: &["content_key", "created_by", "send_at", "created_at"],
+        key: &["id"],
+        params: &[],
+    };
+    Ok(Json(query.apply(&SPEC, state.announcements.upcoming().await)?))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/admin/announcements/{id}",
+    tag = "admin",
+    security(("admin_token" = [])),
+    params(("id" = Uuid, Path, description = "Scheduled announcement")),
+    responses(
+        (status = 204, description = "Announcement cancelled"),
+        (status = 401, description = "Missing or wrong admin token", body = ErrorBody),
+        (status = 403, description = "Admin API disabled", body = ErrorBody),
+        (status = 404, description = "No such announcement, or already sent or cancelled", body = ErrorBody)
+    )
+)]
+pub async fn cancel_announcement(_: AdminAuth, State(state): State<AppState>, Path(id): Path<Uuid>) -> Result<StatusCode> {
+    state.announcements.cancel(id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
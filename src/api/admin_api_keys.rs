@@ -0,0 +1,171 @@
+use axum::{
+    Json,
+    extract::{Path, Query, State},
+    http::StatusCode,
+};
+use serde::Deserialize;
+use utoipa::IntoParams;
+use uuid::Uuid;
+
+use crate::AppState;
+use crate::apikeys::key::{ApiKey, ApiKeySpec, Caller, IssuedApiKey};
+use crate::audit::record::AuditRecord;
+use crate::error::{Error, Result};
+use super::admin::AdminAuth;
+use super::listing::{ListQuery, ListSpec, Page, PageParams};
+
+// API keys of service callers, and the audit log of what callers changed
+
+// Keys can't mint or revoke keys, so a leaked key can't entrench itself
+fn require_root(caller: &Caller) -> Result<()> {
+    match caller {
+        Caller::Root => Ok(()),
+        Caller::Service { .. } => Err(Error::PermissionDenied("API keys are managed with ADMIN_TOKEN".to_string())),
+    }
+}
+
+// Every key, revoked and expired ones included, newest first. Secrets are never returned.
+#[utoipa::path(
+    get,
+    path = "/admin/api_keys",
+    tag = "admin",
+    security(("admin_token" = [])),
+    params(PageParams),
+    responses(
+        (status = 200, description = "API keys", body = ApiKeyPage),
+        (status = 400, description = "Invalid sort, filter or cursor", body = ErrorBody),
+        (status = 401, description = "Missing or wrong admin token", body = ErrorBody),
+        (status = 403, description = "Admin API disabled, or called with an API key", body = ErrorBody)
+    )
+)]
+pub async fn list_keys(AdminAuth(caller): AdminAuth, State(state): State<AppState>, query: ListQuery) -> Result<Json<Page<ApiKey>>> {
+    const SPEC: ListSpec = ListSpec {
+        default_sort: "-created_at",
+        sort: &["name", "expires_at"],
+        filters: &["name", "prefix", "scopes", "created_by", "created_at", "expires_at", "revoked_at"],
+        key: &["id"],
+        params: &[],
+    };
+    require_root(&caller)?;
+    Ok(Json(query.apply(&SPEC, state.api_keys.list().await?)?))
+}
+
+// Issue a key. Its secret is in the reply and can't be retrieved later.
+#[utoipa::path(
+    post,
+    path = "/admin/api_keys",
+    tag = "admin",
+    security(("admin_token" = [])),
+    request_body = ApiKeySpec,
+    responses(
+        (status = 201, description = "New key with its secret", body = IssuedApiKey),
+        (status = 400, description = "Missing name, created_by or scopes", body = ErrorBody),
+        (status = 401, description = "Missing or wrong admin token", body = ErrorBody),
+        (status = 403, description = "Admin API disabled, or called with an API key", body = ErrorBody)
+    )
+)]
+pub async fn create_key(
+    AdminAuth(caller): AdminAuth,
+    State(state): State<AppState>,
+    body: String,
+) -> Result<(StatusCode, Json<IssuedApiKey>)> {
+    require_root(&caller)?;
+    let spec: ApiKeySpec = serde_json::from_str(&body)
+        .map_err(|_| Error::InvalidMessage)?;
+    Ok((StatusCode::CREATED, Json(state.api_keys.create(spec).await?)))
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct RotateKeyParams {
+    // Admin rotating the key; required
+    #[serde(default)]
+    pub rotated_by: String,
+}
+
+// Replace a key with a new one with the same name and scopes. The old key
+// keeps working for API_KEY_ROTATION_GRACE_SECS.
+#[utoipa::path(
+    post,
+    path = "/admin/api_keys/{id}/rotate",
+    tag = "admin",
+    security(("admin_token" = [])),
+    params(("id" = Uuid, Path, description = "Key to rotate"), RotateKeyParams),
+    responses(
+        (status = 201, description = "Replacement key with its secret", body = IssuedApiKey),
+        (status = 400, description = "Missing rotated_by", body = ErrorBody),
+        (status = 401, description = "Missing or wrong admin token", body = ErrorBody),
+        (status = 403, description = "Admin API disabled, or called with an API key", body = ErrorBody),
+        (status = 404, description = "No such active key", body = ErrorBody)
+    )
+)]
+pub async fn rotate_key(
+    AdminAuth(caller): AdminAuth,
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    Query(params): Query<RotateKeyParams>,
+) -> Result<(StatusCode, Json<IssuedApiKey>)> {
+    require_root(&caller)?;
+    Ok((StatusCode::CREATED, Json(state.api_keys.rotate(id, &params.rotated_by).await?)))
+}
+
+// Revoke a key right away
+#[utoipa::path(
+    delete,
+    path = "/admin/api_keys/{id}",
+    tag = "admin",
+    security(("admin_token" = [])),
+    params(("id" = Uuid, Path, description = "Key to revoke")),
+    responses(
+        (status = 204, description = "Key revoked"),
+        (status = 401, description = "Missing or wrong admin token", body = ErrorBody),
+        (status = 403, description = "Admin API disabled, or called with an API key", body = ErrorBody),
+        (status = 404, description = "No such key, or already revoked", body = ErrorBody)
+    )
+)]
+pub async fn revoke_key(
+    AdminAuth(caller): AdminAuth,
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Result<StatusCode> {
+    require_root(&caller)?;
+    state.api_keys.revoke(id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct AuditParams {
+    // Only this caller's changes: "admin" or "key:<name>"
+    pub actor: Option<String>,
+}
+
+// Changes made through the admin API and gRPC, newest first
+#[utoipa::path(
+    get,
+    path = "/admin/audit",
+    tag = "admin",
+    security(("admin_token" = [])),
+    params(AuditParams, PageParams),
+    responses(
+        (status = 200, description = "Audit records", body = AuditRecordPage),
+        (status = 400, description = "Invalid sort, filter or cursor", body = ErrorBody),
+        (status = 401, description = "Missing or wrong admin token", body = ErrorBody),
+        (status = 403, description = "Admin API disabled", body = ErrorBody)
+    )
+)]
+pub async fn list_audit(
+    _: AdminAuth,
+    State(state): State<AppState>,
+    Query(params): Query<AuditParams>,
+    query: ListQuery,
+) -> Result<Json<Page<AuditRecord>>> {
+    const SPEC: ListSpec = ListSpec {
+        default_sort: "-created_at",
+        sort: &[],
+        filters: &["action", "status", "api_key_id", "created_at"],
+        key: &["id"],
+        params: &["actor"],
+    };
+    let keyset = query.keyset(&SPEC)?;
+    let (records, total) = state.audit.page(params.actor.as_deref(), &keyset).await?;
+    Ok(Json(query.page(&keyset, records, total)?))
+}
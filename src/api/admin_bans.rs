@@ -0,0 +1,127 @@
+use axum::{
+    Json,
+    extract::{Path, Query, State},
+    http::StatusCode,
+};
+use serde::Deserialize;
+use utoipa::IntoParams;
+use uuid::Uuid;
+
+use crate::AppState;
+use crate::error::{Error, Result};
+use crate::moderation::ban::{Ban, BanSpec};
+use super::admin::AdminAuth;
+use super::listing::{ListQuery, ListSpec, Page, PageParams};
+
+// Player bans and suspensions
+
+#[utoipa::path(
+    get,
+    path = "/admin/bans",
+    tag = "admin",
+    security(("admin_token" = [])),
+    params(PageParams),
+    responses(
+        (status = 200, description = "Bans in force, newest first", body = BanPage),
+        (status = 400, description = "Invalid sort, filter or cursor", body = ErrorBody),
+        (status = 401, description = "Missing or wrong admin token", body = ErrorBody),
+        (status = 403, description = "Admin API disabled", body = ErrorBody)
+    )
+)]
+pub async fn list_bans(_: AdminAuth, State(state): State<AppState>, query: ListQuery) -> Result<Json<Page<Ban>>> {
+    const SPEC: ListSpec = ListSpec {
+        default_sort: "-created_at",
+        sort: &["expires_at"],
+        filters: &["user_id", "issued_by", "created_at", "expires_at", "lifted_at", "lifted_by"],
+        key: &["id"],
+        params: &[],
+    };
+    Ok(Json(query.apply(&SPEC, state.bans.list().await)?))
+}
+
+#[utoipa::path(
+    get,
+    path = "/admin/bans/{user_id}",
+    tag = "admin",
+    security(("admin_token" = [])),
+    params(("user_id" = Uuid, Path, description = "Player"), PageParams),
+    responses(
+        (status = 200, description = "Every ban of the player, lifted and expired ones included, newest first", body = BanPage),
+        (status = 400, description = "Invalid sort, filter or cursor", body = ErrorBody),
+        (status = 401, description = "Missing or wrong admin token", body = ErrorBody),
+        (status = 403, description = "Admin API disabled", body = ErrorBody)
+    )
+)]
+pub async fn ban_history(
+    _: AdminAuth,
+    State(state): State<AppState>,
+    Path(user_id): Path<Uuid>,
+    query: ListQuery,
+) -> Result<Json<Page<Ban>>> {
+    const SPEC: ListSpec = ListSpec {
+        default_sort: "-created_at",
+        sort: &["expires_at"],
+        filters: &["user_id", "issued_by", "created_at", "expires_at", "lifted_at", "lifted_by"],
+        key: &["id"],
+        params: &[],
+    };
+    Ok(Json(query.apply(&SPEC, state.bans.history(user_id).await?)?))
+}
+
+// Ban a player, replacing any ban in force. Their open connections receive
+// `sys.banned` and are closed.
+#[utoipa::path(
+    put,
+    path = "/admin/bans/{user_id}",
+    tag = "admin",
+    security(("admin_token" = [])),
+    params(("user_id" = Uuid, Path, description = "Player to ban")),
+    request_body = BanSpec,
+    responses(
+        (status = 201, description = "Ban issued", body = Ban),
+        (status = 400, description = "Missing issuer or reason, or zero duration", body = ErrorBody),
+        (status = 401, description = "Missing or wrong admin token", body = ErrorBody),
+        (status = 403, description = "Admin API disabled", body = ErrorBody)
+    )
+)]
+pub async fn issue_ban(
+    _: AdminAuth,
+    State(state): State<AppState>,
+    Path(user_id): Path<Uuid>,
+    body: String,
+) -> Result<(StatusCode, Json<Ban>)> {
+    let spec: BanSpec = serde_json::from_str(&body)
+        .map_err(|_| Error::InvalidMessage)?;
+    Ok((StatusCode::CREATED, Json(state.bans.issue(user_id, spec).await?)))
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct LiftBanParams {
+    // Admin lifting the ban, for the ban history; required
+    #[serde(default)]
+    pub lifted_by: String,
+}
+
+#[utoipa::path(
+    delete,
+    path = "/admin/bans/{user_id}",
+    tag = "admin",
+    security(("admin_token" = [])),
+    params(("user_id" = Uuid, Path, description = "Player to unban"), LiftBanParams),
+    responses(
+        (status = 204, description = "Ban lifted"),
+        (status = 400, description = "Missing lifted_by", body = ErrorBody),
+        (status = 401, description = "Missing or wrong admin token", body = ErrorBody),
+        (status = 403, description = "Admin API disabled", body = ErrorBody),
+        (status = 404, description = "No ban in force for this player", body = ErrorBody)
+    )
+)]
+pub async fn lift_ban(
+    _: AdminAuth,
+    State(state): State<AppState>,
+    Path(user_id): Path<Uuid>,
+    Query(params): Query<LiftBanParams>,
+) -> Result<StatusCode> {
+    state.bans.lift(user_id, &params.lifted_by).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
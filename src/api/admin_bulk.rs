@@ -0,0 +1,257 @@
+use axum::{
+    Json,
+    extract::{Path, State},
+    http::StatusCode,
+};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::AppState;
+use crate::cluster::node_id;
+use crate::error::{Error, Result};
+use crate::jobs::job::{Job, JobStatus};
+use crate::models::game::MatchStatus;
+use crate::moderation::ban::BanSpec;
+use super::admin::{AdminAuth, end_running_match};
+use super::listing::{ListQuery, ListSpec, Page, PageParams};
+
+// Most players one bulk ban may name
+const MAX_BULK_BANS: usize = 1000;
+// Matches deleted per mutation when purging
+const PURGE_CHUNK: usize = 100;
+
+// Operations over many matches or players at once. Each runs as a job on this
+// node: the request returns the job right away, and its progress is pushed to
+// the admin console as `console.job` and served at /admin/jobs/{id}. A
+// cancelled job stops before its next item.
+
+// POST /admin/bulk/end_matches body
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct EndMatchesRequest {
+    // Admin starting the job
+    pub requested_by: String,
+    // Matches playing for at least this long are ended
+    pub older_than_secs: u64,
+}
+
+// POST /admin/bulk/bans body
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct BulkBanRequest {
+    // At most 1000 players, each banned with the same spec
+    pub user_ids: Vec<Uuid>,
+    #[serde(flatten)]
+    pub spec: BanSpec,
+}
+
+// POST /admin/bulk/purge_matches body
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct PurgeMatchesRequest {
+    // Admin starting the job
+    pub requested_by: String,
+    // Finished and voided matches that ended in [from, to) are deleted
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+}
+
+fn parse<T: for<'de> Deserialize<'de>>(body: &str) -> Result<T> {
+    serde_json::from_str(body).map_err(|_| Error::InvalidMessage)
+}
+
+// End every match this node runs that has been playing for longer than
+// `older_than_secs`, as with POST /admin/matches/{match_id}/end
+#[utoipa::path(
+    post,
+    path = "/admin/bulk/end_matches",
+    tag = "admin",
+    security(("admin_token" = [])),
+    request_body = EndMatchesRequest,
+    responses(
+        (status = 202, description = "Job started", body = Job),
+        (status = 400, description = "Missing requested_by or zero older_than_secs", body = ErrorBody),
+        (status = 401, description = "Missing or wrong admin token", body = ErrorBody),
+        (status = 403, description = "Admin API disabled", body = ErrorBody)
+    )
+)]
+pub async fn end_matches(_: AdminAuth, State(state): State<AppState>, body: String) -> Result<(StatusCode, Json<Job>)> {
+    let request: EndMatchesRequest = parse(&body)?;
+    if request.requested_by.trim().is_empty() || request.older_than_secs == 0 {
+        return Err(Error::InvalidMessage);
+    }
+
+    let older_than_secs = request.older_than_secs;
+    let job = state.jobs.clone().spawn("end_matches", &request.requested_by, move |job| async move {
+        let stale: Vec<Uuid> = state.ws_handler.match_stats().await
+            .into_iter()
+            .filter(|stats| stats.status == MatchStatus::Playing)
+            .filter(|stats| stats.duration_secs.is_some_and(|secs| secs >= older_than_secs))
+            .map(|stats| stats.match_id)
+            .collect();
+        job.set_total(stale.len()).await;
+        for match_id in stale {
+            if job.cancelled() {
+                break;
+            }
+            let error = end_running_match(&state, match_id).await.err();
+            job.item_done(error.map(|e| format!("match {}: {}", match_id, e))).await;
+        }
+        Ok(())
+    }).await;
+    Ok((StatusCode::ACCEPTED, Json(job)))
+}
+
+// Ban a list of players, as with PUT /admin/bans/{user_id}
+#[utoipa::path(
+    post,
+    path = "/admin/bulk/bans",
+    tag = "admin",
+    security(("admin_token" = [])),
+    request_body = BulkBanRequest,
+    responses(
+        (status = 202, description = "Job started", body = Job),
+        (status = 400, description = "No players or more than 1000, missing issuer or reason, or zero duration", body = ErrorBody),
+        (status = 401, description = "Missing or wrong admin token", body = ErrorBody),
+        (status = 403, description = "Admin API disabled", body = ErrorBody)
+    )
+)]
+pub async fn ban_users(_: AdminAuth, State(state): State<AppState>, body: String) -> Result<(StatusCode, Json<Job>)> {
+    let mut request: BulkBanRequest = parse(&body)?;
+    let spec = request.spec;
+    if request.user_ids.is_empty()
+        || request.user_ids.len() > MAX_BULK_BANS
+        || spec.issued_by.trim().is_empty()
+        || spec.reason.trim().is_empty()
+        || spec.duration_secs == Some(0)
+    {
+        return Err(Error::InvalidMessage);
+    }
+    request.user_ids.sort();
+    request.user_ids.dedup();
+
+    let user_ids = request.user_ids;
+    let issued_by = spec.issued_by.clone();
+    let job = state.jobs.clone().spawn("ban_users", &issued_by, move |job| async move {
+        job.set_total(user_ids.len()).await;
+        for user_id in user_ids {
+            if job.cancelled() {
+                break;
+            }
+            let error = state.bans.issue(user_id, spec.clone()).await.err();
+            job.item_done(error.map(|e| format!("user {}: {}", user_id, e))).await;
+        }
+        Ok(())
+    }).await;
+    Ok((StatusCode::ACCEPTED, Json(job)))
+}
+
+// Delete finished and voided matches that ended in a time range, with their
+// teams, members, discoveries and adjustments. Matches under review are kept.
+#[utoipa::path(
+    post,
+    path = "/admin/bulk/purge_matches",
+    tag = "admin",
+    security(("admin_token" = [])),
+    request_body = PurgeMatchesRequest,
+    responses(
+        (status = 202, description = "Job started", body = Job),
+        (status = 400, description = "Missing requested_by, or from not before to", body = ErrorBody),
+        (status = 401, description = "Missing or wrong admin token", body = ErrorBody),
+        (status = 403, description = "Admin API disabled", body = ErrorBody)
+    )
+)]
+pub async fn purge_matches(_: AdminAuth, State(state): State<AppState>, body: String) -> Result<(StatusCode, Json<Job>)> {
+    let request: PurgeMatchesRequest = parse(&body)?;
+    if request.requested_by.trim().is_empty() || request.from >= request.to {
+        return Err(Error::InvalidMessage);
+    }
+
+    let (from, to) = (request.from, request.to);
+    let job = state.jobs.clone().spawn("purge_matches", &request.requested_by, move |job| async move {
+        let match_ids = state.match_service.closed_matches_between(from, to).await?;
+        job.set_total(match_ids.len()).await;
+        for chunk in match_ids.chunks(PURGE_CHUNK) {
+            if job.cancelled() {
+                break;
+            }
+            // A failed chunk stops the job; what was deleted stays deleted
+            state.match_service.delete_matches(chunk).await?;
+            for match_id in chunk {
+                state.cache.forget_match(*match_id);
+                job.item_done(None).await;
+            }
+        }
+        Ok(())
+    }).await;
+    Ok((StatusCode::ACCEPTED, Json(job)))
+}
+
+// Jobs of every node from the last 7 days, up to the 500 most recent
+#[utoipa::path(
+    get,
+    path = "/admin/jobs",
+    tag = "admin",
+    security(("admin_token" = [])),
+    params(PageParams),
+    responses(
+        (status = 200, description = "Jobs, newest first", body = JobPage),
+        (status = 400, description = "Invalid sort, filter or cursor", body = ErrorBody),
+        (status = 401, description = "Missing or wrong admin token", body = ErrorBody),
+        (status = 403, description = "Admin API disabled", body = ErrorBody)
+    )
+)]
+pub async fn list_jobs(_: AdminAuth, State(state): State<AppState>, query: ListQuery) -> Result<Json<Page<Job>>> {
+    const SPEC: ListSpec = ListSpec {
+        default_sort: "-created_at",
+        sort: &["finished_at"],
+        filters: &["kind", "status", "created_by", "created_at", "finished_at"],
+        key: &["id"],
+        params: &[],
+    };
+    Ok(Json(query.apply(&SPEC, state.jobs.list().await?)?))
+}
+
+#[utoipa::path(
+    get,
+    path = "/admin/jobs/{id}",
+    tag = "admin",
+    security(("admin_token" = [])),
+    params(("id" = Uuid, Path, description = "Job")),
+    responses(
+        (status = 200, description = "The job and its progress", body = Job),
+        (status = 401, description = "Missing or wrong admin token", body = ErrorBody),
+        (status = 403, description = "Admin API disabled", body = ErrorBody),
+        (status = 404, description = "No such job", body = ErrorBody)
+    )
+)]
+pub async fn get_job(_: AdminAuth, State(state): State<AppState>, Path(id): Path<Uuid>) -> Result<Json<Job>> {
+    Ok(Json(state.jobs.get(id).await?))
+}
+
+// Stop a running job before its next item. A job running on another node is
+// cancelled through that node.
+#[utoipa::path(
+    post,
+    path = "/admin/jobs/{id}/cancel",
+    tag = "admin",
+    security(("admin_token" = [])),
+    params(("id" = Uuid, Path, description = "Job")),
+    responses(
+        (status = 200, description = "Cancellation requested; the job as it was", body = Job),
+        (status = 401, description = "Missing or wrong admin token", body = ErrorBody),
+        (status = 403, description = "Admin API disabled", body = ErrorBody),
+        (status = 404, description = "No such job", body = ErrorBody),
+        (status = 409, description = "The job has already finished", body = ErrorBody),
+        (status = 500, description = "The node running the job can't be reached", body = ErrorBody)
+    )
+)]
+pub async fn cancel_job(_: AdminAuth, State(state): State<AppState>, Path(id): Path<Uuid>) -> Result<Json<Job>> {
+    let job = state.jobs.get(id).await?;
+    if job.status != JobStatus::Running {
+        return Err(Error::JobFinished);
+    }
+    if job.node_id != node_id() {
+        return Ok(Json(state.cluster.cancel_job(&job.node_id, id).await?));
+    }
+    Ok(Json(state.jobs.cancel(id).await?))
+}
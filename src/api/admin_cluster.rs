@@ -0,0 +1,111 @@
+use axum::{
+    Json,
+    extract::{Path, State},
+    http::StatusCode,
+};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::AppState;
+use crate::cluster::node_id;
+use crate::cluster::rpc::NodeHealth;
+use crate::error::{Error, Result};
+use super::admin::AdminAuth;
+use super::listing::{ListQuery, ListSpec, Page, PageParams};
+
+// Nodes of the cluster and moving matches between them
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct NodeStatus {
+    pub node_id: String,
+    pub url: String,
+    // Absent when the node didn't answer
+    pub health: Option<NodeHealth>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct TransferRequest {
+    // Node to take the match over
+    pub node: String,
+}
+
+#[utoipa::path(
+    get,
+    path = "/admin/cluster/nodes",
+    tag = "admin",
+    security(("admin_token" = [])),
+    params(PageParams),
+    responses(
+        (status = 200, description = "Advertised nodes with their health", body = NodeStatusPage),
+        (status = 400, description = "Invalid sort, filter or cursor", body = ErrorBody),
+        (status = 401, description = "Missing or wrong admin token", body = ErrorBody),
+        (status = 403, description = "Admin API disabled", body = ErrorBody),
+        (status = 500, description = "Cluster API disabled or Redis unreachable", body = ErrorBody)
+    )
+)]
+pub async fn list_nodes(_: AdminAuth, State(state): State<AppState>, query: ListQuery) -> Result<Json<Page<NodeStatus>>> {
+    const SPEC: ListSpec = ListSpec {
+        default_sort: "node_id",
+        sort: &["health.connections", "health.owned_matches"],
+        filters: &["url", "health.leader", "health.connections", "health.owned_matches", "error"],
+        key: &["node_id"],
+        params: &[],
+    };
+    let mut nodes = Vec::new();
+    for node in state.cluster.nodes().await? {
+        let (health, error) = match state.cluster.health(&node.node_id).await {
+            Ok(health) => (Some(health), None),
+            Err(e) => (None, Some(e.to_string())),
+        };
+        nodes.push(NodeStatus {
+            node_id: node.node_id,
+            url: node.url,
+            health,
+            error,
+        });
+    }
+    Ok(Json(query.apply(&SPEC, nodes)?))
+}
+
+// Hand a running match owned by this node to another node, e.g. before
+// draining this one. Players keep their connections; their commands are
+// forwarded to the new owner.
+#[utoipa::path(
+    post,
+    path = "/admin/cluster/matches/{match_id}/transfer",
+    tag = "admin",
+    security(("admin_token" = [])),
+    params(("match_id" = Uuid, Path, description = "Running match owned by this node")),
+    request_body = TransferRequest,
+    responses(
+        (status = 204, description = "Match now owned by the target node"),
+        (status = 400, description = "Malformed request or target is this node", body = ErrorBody),
+        (status = 401, description = "Missing or wrong admin token", body = ErrorBody),
+        (status = 403, description = "Admin API disabled", body = ErrorBody),
+        (status = 404, description = "Match not owned by this node, or unknown target", body = ErrorBody),
+        (status = 500, description = "Target node refused or unreachable", body = ErrorBody)
+    )
+)]
+pub async fn transfer_match(
+    _: AdminAuth,
+    State(state): State<AppState>,
+    Path(match_id): Path<Uuid>,
+    body: String,
+) -> Result<StatusCode> {
+    let request: TransferRequest = serde_json::from_str(&body)
+        .map_err(|_| Error::InvalidMessage)?;
+    if request.node == node_id() {
+        return Err(Error::InvalidMessage);
+    }
+    if !state.match_service.owns(match_id).await {
+        return Err(Error::MatchNotFound);
+    }
+
+    state.cluster.adopt_match(&request.node, match_id).await?;
+    state.match_service.hand_over(match_id).await;
+    state.game.stop(match_id).await;
+    tracing::info!("Match {} handed over to node {}", match_id, request.node);
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,70 @@
+use axum::{
+    Json,
+    extract::{Path, State},
+    http::StatusCode,
+};
+use uuid::Uuid;
+
+use crate::AppState;
+use crate::devices::device::Device;
+use crate::error::Result;
+use super::admin::AdminAuth;
+use super::listing::{ListQuery, ListSpec, Page, PageParams};
+
+// Devices players registered
+
+#[utoipa::path(
+    get,
+    path = "/admin/devices/{user_id}",
+    tag = "admin",
+    security(("admin_token" = [])),
+    params(("user_id" = Uuid, Path, description = "Player"), PageParams),
+    responses(
+        (status = 200, description = "The player's devices, most recently seen first", body = DevicePage),
+        (status = 400, description = "Invalid sort, filter or cursor", body = ErrorBody),
+        (status = 401, description = "Missing or wrong admin token", body = ErrorBody),
+        (status = 403, description = "Admin API disabled", body = ErrorBody)
+    )
+)]
+pub async fn list_devices(
+    _: AdminAuth,
+    State(state): State<AppState>,
+    Path(user_id): Path<Uuid>,
+    query: ListQuery,
+) -> Result<Json<Page<Device>>> {
+    const SPEC: ListSpec = ListSpec {
+        default_sort: "-last_seen_at",
+        sort: &["registered_at"],
+        filters: &["platform", "push_token", "registered_at", "last_seen_at"],
+        key: &["device_id"],
+        params: &[],
+    };
+    Ok(Json(query.apply(&SPEC, state.devices.list(user_id).await?)?))
+}
+
+// Remove a device, e.g. a lost phone. Its open sessions receive
+// `sys.session_ended` and are closed.
+#[utoipa::path(
+    delete,
+    path = "/admin/devices/{user_id}/{device_id}",
+    tag = "admin",
+    security(("admin_token" = [])),
+    params(
+        ("user_id" = Uuid, Path, description = "Player"),
+        ("device_id" = String, Path, description = "Device to revoke")
+    ),
+    responses(
+        (status = 204, description = "Device revoked"),
+        (status = 401, description = "Missing or wrong admin token", body = ErrorBody),
+        (status = 403, description = "Admin API disabled", body = ErrorBody),
+        (status = 404, description = "The player has no such device", body = ErrorBody)
+    )
+)]
+pub async fn revoke_device(
+    _: AdminAuth,
+    State(state): State<AppState>,
+    Path((user_id, device_id)): Path<(Uuid, String)>,
+) -> Result<StatusCode> {
+    state.ws_handler.revoke_device(user_id, &device_id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,72 @@
+use std::convert::Infallible;
+
+use axum::{
+    extract::State,
+    response::sse::{Event, KeepAlive, Sse},
+};
+use chrono::{DateTime, Utc};
+use futures_util::stream::{self, Stream};
+use serde::Serialize;
+use tokio::sync::broadcast::error::RecvError;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::AppState;
+use crate::matchmaking::events::Published;
+use super::admin::AdminAuth;
+
+// Live tail of the match events published on this node
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct MatchEventSummary {
+    // player_joined, player_left, room_ready, match_started,
+    // discovery_recorded, match_ended, result_adjusted or rank_placed
+    pub event: &'static str,
+    pub match_id: Uuid,
+    pub user_id: Option<Uuid>,
+    pub correlation_id: Option<Uuid>,
+    pub at: DateTime<Utc>,
+}
+
+impl From<&Published> for MatchEventSummary {
+    fn from(published: &Published) -> Self {
+        Self {
+            event: published.event.name(),
+            match_id: published.event.match_id(),
+            user_id: published.event.user_id(),
+            correlation_id: published.correlation_id,
+            at: Utc::now(),
+        }
+    }
+}
+
+// Server-Sent Events, one MatchEventSummary as JSON per `data:` line. Events
+// missed by a slow reader are reported as a `lagged` event with the count.
+#[utoipa::path(
+    get,
+    path = "/admin/events",
+    tag = "admin",
+    security(("admin_token" = [])),
+    responses(
+        (status = 200, description = "Event stream of MatchEventSummary", body = MatchEventSummary, content_type = "text/event-stream"),
+        (status = 401, description = "Missing or wrong admin token", body = ErrorBody),
+        (status = 403, description = "Admin API disabled", body = ErrorBody)
+    )
+)]
+pub async fn tail_events(
+    _: AdminAuth,
+    State(state): State<AppState>,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let events = stream::unfold(state.events.subscribe(), |mut rx| async move {
+        let event = match rx.recv().await {
+            Ok(published) => Event::default()
+                .json_data(MatchEventSummary::from(&published))
+                .unwrap_or_default(),
+            Err(RecvError::Lagged(missed)) => Event::default().event("lagged").data(missed.to_string()),
+            Err(RecvError::Closed) => return None,
+        };
+        Some((Ok(event), rx))
+    });
+
+    Sse::new(events).keep_alive(KeepAlive::default())
+}
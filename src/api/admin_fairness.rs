@@ -0,0 +1,57 @@
+use axum::{
+    Json,
+    extract::State,
+};
+
+use crate::AppState;
+use crate::error::Result;
+use crate::matchmaking::fairness::{FairnessReport, MAX_REPORTS};
+use super::admin::AdminAuth;
+use super::listing::{ListQuery, ListSpec, Page, PageParams};
+
+// Matchmaking fairness reports
+
+#[utoipa::path(
+    get,
+    path = "/admin/fairness",
+    tag = "admin",
+    security(("admin_token" = [])),
+    params(PageParams),
+    responses(
+        (status = 200, description = "Recent reports, newest first", body = FairnessReportPage),
+        (status = 400, description = "Invalid sort, filter or cursor", body = ErrorBody),
+        (status = 401, description = "Missing or wrong admin token", body = ErrorBody),
+        (status = 403, description = "Admin API disabled", body = ErrorBody)
+    )
+)]
+pub async fn list_reports(
+    _: AdminAuth,
+    State(state): State<AppState>,
+    query: ListQuery,
+) -> Result<Json<Page<FairnessReport>>> {
+    const SPEC: ListSpec = ListSpec {
+        default_sort: "-computed_at",
+        sort: &[],
+        filters: &["computed_at", "window_start", "blowout_margin"],
+        key: &["id"],
+        params: &[],
+    };
+    Ok(Json(query.apply(&SPEC, state.fairness.reports(MAX_REPORTS).await?)?))
+}
+
+// Compute a report now instead of waiting for the periodic job, e.g. right
+// after a tuning change
+#[utoipa::path(
+    post,
+    path = "/admin/fairness",
+    tag = "admin",
+    security(("admin_token" = [])),
+    responses(
+        (status = 200, description = "New report, also stored", body = FairnessReport),
+        (status = 401, description = "Missing or wrong admin token", body = ErrorBody),
+        (status = 403, description = "Admin API disabled", body = ErrorBody)
+    )
+)]
+pub async fn compute_report(_: AdminAuth, State(state): State<AppState>) -> Result<Json<FairnessReport>> {
+    Ok(Json(state.fairness.compute().await?))
+}
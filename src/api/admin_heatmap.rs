@@ -0,0 +1,49 @@
+use axum::{
+    Json,
+    extract::{Query, State},
+};
+use serde::Deserialize;
+use utoipa::IntoParams;
+
+use crate::AppState;
+use crate::error::Result;
+use crate::heatmap::sample::HeatmapTile;
+use super::admin::AdminAuth;
+use super::listing::{ListQuery, ListSpec, Page, PageParams};
+
+// Where players actually go, for game designers
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct HeatmapParams {
+    // Zone to show; positions outside every zone when omitted
+    pub zone_id: Option<String>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/admin/heatmap",
+    tag = "admin",
+    security(("admin_token" = [])),
+    params(HeatmapParams, PageParams),
+    responses(
+        (status = 200, description = "Tiles of the last aggregation, busiest first", body = HeatmapTilePage),
+        (status = 400, description = "Invalid sort, filter or cursor", body = ErrorBody),
+        (status = 401, description = "Missing or wrong admin token", body = ErrorBody),
+        (status = 403, description = "Admin API disabled", body = ErrorBody)
+    )
+)]
+pub async fn get_heatmap(
+    _: AdminAuth,
+    State(state): State<AppState>,
+    Query(params): Query<HeatmapParams>,
+    query: ListQuery,
+) -> Result<Json<Page<HeatmapTile>>> {
+    const SPEC: ListSpec = ListSpec {
+        default_sort: "-samples",
+        sort: &["x", "y"],
+        filters: &["x", "y", "samples"],
+        key: &["x", "y"],
+        params: &["zone_id"],
+    };
+    Ok(Json(query.apply(&SPEC, state.heatmap.tiles(params.zone_id.as_deref()).await?)?))
+}
@@ -0,0 +1,58 @@
+use axum::{
+    Json,
+    extract::State,
+};
+use serde::Deserialize;
+use utoipa::ToSchema;
+
+use crate::AppState;
+use crate::error::{Error, Result};
+use crate::matchmaking::service::Maintenance;
+use super::admin::AdminAuth;
+
+// Maintenance mode of this node. Only new matches are refused; running
+// matches and connections are left alone, so a node can be drained before a
+// deploy. Each node keeps its own flag.
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct MaintenanceSpec {
+    pub enabled: bool,
+    // Shown to players whose join is refused
+    pub message: Option<String>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/admin/maintenance",
+    tag = "admin",
+    security(("admin_token" = [])),
+    responses(
+        (status = 200, description = "Maintenance mode of this node", body = Maintenance),
+        (status = 401, description = "Missing or wrong admin token", body = ErrorBody),
+        (status = 403, description = "Admin API disabled", body = ErrorBody)
+    )
+)]
+pub async fn get_maintenance(_: AdminAuth, State(state): State<AppState>) -> Json<Maintenance> {
+    Json(state.match_service.maintenance())
+}
+
+#[utoipa::path(
+    put,
+    path = "/admin/maintenance",
+    tag = "admin",
+    security(("admin_token" = [])),
+    request_body = MaintenanceSpec,
+    responses(
+        (status = 200, description = "Maintenance mode after the change", body = Maintenance),
+        (status = 400, description = "Malformed request", body = ErrorBody),
+        (status = 401, description = "Missing or wrong admin token", body = ErrorBody),
+        (status = 403, description = "Admin API disabled", body = ErrorBody)
+    )
+)]
+pub async fn put_maintenance(_: AdminAuth, State(state): State<AppState>, body: String) -> Result<Json<Maintenance>> {
+    let spec: MaintenanceSpec = serde_json::from_str(&body)
+        .map_err(|_| Error::InvalidMessage)?;
+    let maintenance = state.match_service.set_maintenance(spec.enabled, spec.message);
+    tracing::info!("Maintenance mode {}", if maintenance.enabled { "enabled" } else { "disabled" });
+    Ok(Json(maintenance))
+}
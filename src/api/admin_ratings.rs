@@ -0,0 +1,107 @@
+use axum::{
+    Json,
+    extract::{Path, Query, State},
+    http::StatusCode,
+};
+use serde::Deserialize;
+use utoipa::IntoParams;
+use uuid::Uuid;
+
+use crate::AppState;
+use crate::error::Result;
+use crate::rating::calibration::CalibrationReport;
+use crate::rating::mmr::PlayerRating;
+use super::admin::AdminAuth;
+use super::listing::{ListQuery, ListSpec, Page, PageParams};
+
+// Matchmaking ratings, the smurf review list and how well ratings predict
+// match results
+
+// Longest period a calibration report covers
+const MAX_CALIBRATION_DAYS: u32 = 365;
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct CalibrationParams {
+    // Matches finished in the last `days` days; 30 by default (at most 365)
+    pub days: Option<u32>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/admin/ratings/{user_id}",
+    tag = "admin",
+    security(("admin_token" = [])),
+    params(("user_id" = Uuid, Path, description = "Player")),
+    responses(
+        (status = 200, description = "Rating of the player; the initial one if they haven't finished a match", body = PlayerRating),
+        (status = 401, description = "Missing or wrong admin token", body = ErrorBody),
+        (status = 403, description = "Admin API disabled", body = ErrorBody)
+    )
+)]
+pub async fn get_rating(_: AdminAuth, State(state): State<AppState>, Path(user_id): Path<Uuid>) -> Result<Json<PlayerRating>> {
+    Ok(Json(state.ratings.rating(user_id).await?))
+}
+
+#[utoipa::path(
+    get,
+    path = "/admin/smurfs",
+    tag = "admin",
+    security(("admin_token" = [])),
+    params(PageParams),
+    responses(
+        (status = 200, description = "Suspected smurfs flagged for review, most recent first", body = PlayerRatingPage),
+        (status = 400, description = "Invalid sort, filter or cursor", body = ErrorBody),
+        (status = 401, description = "Missing or wrong admin token", body = ErrorBody),
+        (status = 403, description = "Admin API disabled", body = ErrorBody)
+    )
+)]
+pub async fn list_smurfs(_: AdminAuth, State(state): State<AppState>, query: ListQuery) -> Result<Json<Page<PlayerRating>>> {
+    const SPEC: ListSpec = ListSpec {
+        default_sort: "-flagged_at",
+        sort: &["mmr", "matches_played", "early_wins"],
+        filters: &["mmr", "matches_played", "wins", "early_wins", "flagged_at"],
+        key: &["user_id"],
+        params: &[],
+    };
+    Ok(Json(query.apply(&SPEC, state.ratings.flagged().await?)?))
+}
+
+// Clear a wrong suspicion; the player is not suspected again
+#[utoipa::path(
+    delete,
+    path = "/admin/smurfs/{user_id}",
+    tag = "admin",
+    security(("admin_token" = [])),
+    params(("user_id" = Uuid, Path, description = "Player to clear")),
+    responses(
+        (status = 204, description = "Cleared"),
+        (status = 401, description = "Missing or wrong admin token", body = ErrorBody),
+        (status = 403, description = "Admin API disabled", body = ErrorBody),
+        (status = 404, description = "Player is not a suspect", body = ErrorBody)
+    )
+)]
+pub async fn clear_smurf(_: AdminAuth, State(state): State<AppState>, Path(user_id): Path<Uuid>) -> Result<StatusCode> {
+    state.ratings.clear_smurf(user_id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[utoipa::path(
+    get,
+    path = "/admin/calibration",
+    tag = "admin",
+    security(("admin_token" = [])),
+    params(CalibrationParams),
+    responses(
+        (status = 200, description = "Win probabilities predicted at match start against the results", body = CalibrationReport),
+        (status = 401, description = "Missing or wrong admin token", body = ErrorBody),
+        (status = 403, description = "Admin API disabled", body = ErrorBody)
+    )
+)]
+pub async fn get_calibration(
+    _: AdminAuth,
+    State(state): State<AppState>,
+    Query(params): Query<CalibrationParams>,
+) -> Result<Json<CalibrationReport>> {
+    let days = params.days.unwrap_or(30).clamp(1, MAX_CALIBRATION_DAYS);
+    Ok(Json(state.ratings.calibration(days).await?))
+}
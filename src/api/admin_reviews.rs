@@ -0,0 +1,84 @@
+use axum::{
+    Json,
+    extract::{Path, State},
+    http::StatusCode,
+};
+use uuid::Uuid;
+
+use crate::AppState;
+use crate::error::{Error, Result};
+use crate::matchmaking::review::{AdjustmentRequest, AuditEntry, MatchReview};
+use super::admin::AdminAuth;
+use super::listing::{ListQuery, ListSpec, Page, PageParams};
+
+// Review of matches flagged by result verification
+
+#[utoipa::path(
+    get,
+    path = "/admin/reviews",
+    tag = "admin",
+    security(("admin_token" = [])),
+    params(PageParams),
+    responses(
+        (status = 200, description = "Matches under review, oldest first", body = MatchReviewPage),
+        (status = 400, description = "Invalid sort, filter or cursor", body = ErrorBody),
+        (status = 401, description = "Missing or wrong admin token", body = ErrorBody),
+        (status = 403, description = "Admin API disabled", body = ErrorBody)
+    )
+)]
+pub async fn list_reviews(_: AdminAuth, State(state): State<AppState>, query: ListQuery) -> Result<Json<Page<MatchReview>>> {
+    const SPEC: ListSpec = ListSpec {
+        default_sort: "end_time",
+        sort: &["start_time"],
+        filters: &["match_type", "start_time", "end_time"],
+        key: &["match_id"],
+        params: &[],
+    };
+    Ok(Json(query.apply(&SPEC, state.reviews.queue().await?)?))
+}
+
+#[utoipa::path(
+    get,
+    path = "/admin/reviews/{match_id}",
+    tag = "admin",
+    security(("admin_token" = [])),
+    params(("match_id" = Uuid, Path, description = "Match id")),
+    responses(
+        (status = 200, description = "Records, review notes and audit log of the match", body = MatchReview),
+        (status = 401, description = "Missing or wrong admin token", body = ErrorBody),
+        (status = 403, description = "Admin API disabled", body = ErrorBody),
+        (status = 404, description = "Unknown match", body = ErrorBody)
+    )
+)]
+pub async fn get_review(_: AdminAuth, State(state): State<AppState>, Path(match_id): Path<Uuid>) -> Result<Json<MatchReview>> {
+    Ok(Json(state.reviews.get(match_id).await?))
+}
+
+// Apply one adjustment; it is recorded in the audit log and pushed to the
+// match's players as `match.adjusted`
+#[utoipa::path(
+    post,
+    path = "/admin/reviews/{match_id}/adjustments",
+    tag = "admin",
+    security(("admin_token" = [])),
+    params(("match_id" = Uuid, Path, description = "Match id")),
+    request_body = AdjustmentRequest,
+    responses(
+        (status = 201, description = "Adjustment applied", body = AuditEntry),
+        (status = 400, description = "Missing actor or reason, or negative score", body = ErrorBody),
+        (status = 401, description = "Missing or wrong admin token", body = ErrorBody),
+        (status = 403, description = "Admin API disabled", body = ErrorBody),
+        (status = 404, description = "Unknown match, team, player or discovery", body = ErrorBody),
+        (status = 409, description = "Match not over yet", body = ErrorBody)
+    )
+)]
+pub async fn adjust_match(
+    _: AdminAuth,
+    State(state): State<AppState>,
+    Path(match_id): Path<Uuid>,
+    body: String,
+) -> Result<(StatusCode, Json<AuditEntry>)> {
+    let request: AdjustmentRequest = serde_json::from_str(&body)
+        .map_err(|_| Error::InvalidMessage)?;
+    Ok((StatusCode::CREATED, Json(state.reviews.adjust(match_id, request).await?)))
+}
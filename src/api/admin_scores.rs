@@ -0,0 +1,46 @@
+use axum::{
+    Json,
+    extract::{Query, State},
+};
+use serde::Deserialize;
+use utoipa::IntoParams;
+use uuid::Uuid;
+
+use crate::AppState;
+use crate::error::Result;
+use crate::matchmaking::reconcile::ReconcileReport;
+use super::admin::AdminAuth;
+
+// Manual run of the score reconciliation job
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct ReconcileParams {
+    // Only this match; every match finished within RECONCILE_WINDOW_HOURS when omitted
+    pub match_id: Option<Uuid>,
+}
+
+#[utoipa::path(
+    post,
+    path = "/admin/scores/reconcile",
+    tag = "admin",
+    security(("admin_token" = [])),
+    params(ReconcileParams),
+    responses(
+        (status = 200, description = "Scores checked; lists every correction made", body = ReconcileReport),
+        (status = 401, description = "Missing or wrong admin token", body = ErrorBody),
+        (status = 403, description = "Admin API disabled", body = ErrorBody),
+        (status = 404, description = "Unknown match", body = ErrorBody),
+        (status = 409, description = "Match not finished yet", body = ErrorBody)
+    )
+)]
+pub async fn reconcile_scores(
+    _: AdminAuth,
+    State(state): State<AppState>,
+    Query(params): Query<ReconcileParams>,
+) -> Result<Json<ReconcileReport>> {
+    let report = match params.match_id {
+        Some(match_id) => state.scores.reconcile_match(match_id).await?,
+        None => state.scores.reconcile_recent(None).await?,
+    };
+    Ok(Json(report))
+}
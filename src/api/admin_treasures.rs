@@ -0,0 +1,138 @@
+use axum::{
+    Json,
+    extract::{Path, Query, State},
+    http::StatusCode,
+};
+use serde::Deserialize;
+use utoipa::IntoParams;
+use uuid::Uuid;
+
+use crate::AppState;
+use crate::error::{Error, Result};
+use crate::models::treasure::{Treasure, TreasureSpec};
+use super::admin::AdminAuth;
+use super::listing::{ListQuery, ListSpec, Page, PageParams};
+
+// Treasure catalog management for game designers
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct TreasureListParams {
+    // Only treasures inside their active window
+    #[serde(default)]
+    pub active: bool,
+}
+
+#[utoipa::path(
+    get,
+    path = "/admin/treasures",
+    tag = "admin",
+    security(("admin_token" = [])),
+    params(TreasureListParams, PageParams),
+    responses(
+        (status = 200, description = "Catalog, by name", body = TreasurePage),
+        (status = 400, description = "Invalid sort, filter or cursor", body = ErrorBody),
+        (status = 401, description = "Missing or wrong admin token", body = ErrorBody),
+        (status = 403, description = "Admin API disabled", body = ErrorBody)
+    )
+)]
+pub async fn list_treasures(
+    _: AdminAuth,
+    State(state): State<AppState>,
+    Query(params): Query<TreasureListParams>,
+    query: ListQuery,
+) -> Result<Json<Page<Treasure>>> {
+    const SPEC: ListSpec = ListSpec {
+        default_sort: "name",
+        sort: &["base_score", "rarity", "active_from", "active_until"],
+        filters: &["name", "base_score", "rarity", "active_from", "active_until"],
+        key: &["id"],
+        params: &["active"],
+    };
+    Ok(Json(query.apply(&SPEC, state.catalog.list(params.active).await)?))
+}
+
+#[utoipa::path(
+    get,
+    path = "/admin/treasures/{id}",
+    tag = "admin",
+    security(("admin_token" = [])),
+    params(("id" = Uuid, Path, description = "Treasure id")),
+    responses(
+        (status = 200, description = "The treasure", body = Treasure),
+        (status = 401, description = "Missing or wrong admin token", body = ErrorBody),
+        (status = 403, description = "Admin API disabled", body = ErrorBody),
+        (status = 404, description = "Unknown treasure", body = ErrorBody)
+    )
+)]
+pub async fn get_treasure(_: AdminAuth, State(state): State<AppState>, Path(id): Path<Uuid>) -> Result<Json<Treasure>> {
+    Ok(Json(state.catalog.get(id).await?))
+}
+
+#[utoipa::path(
+    post,
+    path = "/admin/treasures",
+    tag = "admin",
+    security(("admin_token" = [])),
+    request_body = TreasureSpec,
+    responses(
+        (status = 201, description = "Created treasure", body = Treasure),
+        (status = 400, description = "Empty name, non-positive score or inverted active window", body = ErrorBody),
+        (status = 401, description = "Missing or wrong admin token", body = ErrorBody),
+        (status = 403, description = "Admin API disabled", body = ErrorBody)
+    )
+)]
+pub async fn create_treasure(
+    _: AdminAuth,
+    State(state): State<AppState>,
+    body: String,
+) -> Result<(StatusCode, Json<Treasure>)> {
+    let spec: TreasureSpec = serde_json::from_str(&body)
+        .map_err(|_| Error::InvalidMessage)?;
+    Ok((StatusCode::CREATED, Json(state.catalog.create(spec).await?)))
+}
+
+#[utoipa::path(
+    put,
+    path = "/admin/treasures/{id}",
+    tag = "admin",
+    security(("admin_token" = [])),
+    params(("id" = Uuid, Path, description = "Treasure id")),
+    request_body = TreasureSpec,
+    responses(
+        (status = 200, description = "Updated treasure", body = Treasure),
+        (status = 400, description = "Empty name, non-positive score or inverted active window", body = ErrorBody),
+        (status = 401, description = "Missing or wrong admin token", body = ErrorBody),
+        (status = 403, description = "Admin API disabled", body = ErrorBody),
+        (status = 404, description = "Unknown treasure", body = ErrorBody)
+    )
+)]
+pub async fn update_treasure(
+    _: AdminAuth,
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    body: String,
+) -> Result<Json<Treasure>> {
+    let spec: TreasureSpec = serde_json::from_str(&body)
+        .map_err(|_| Error::InvalidMessage)?;
+    Ok(Json(state.catalog.update(id, spec).await?))
+}
+
+// Treasures that were already discovered can't be deleted; end their active window instead
+#[utoipa::path(
+    delete,
+    path = "/admin/treasures/{id}",
+    tag = "admin",
+    security(("admin_token" = [])),
+    params(("id" = Uuid, Path, description = "Treasure id")),
+    responses(
+        (status = 204, description = "Deleted"),
+        (status = 401, description = "Missing or wrong admin token", body = ErrorBody),
+        (status = 403, description = "Admin API disabled", body = ErrorBody),
+        (status = 404, description = "Unknown treasure", body = ErrorBody),
+        (status = 409, description = "Treasure referenced by discoveries", body = ErrorBody)
+    )
+)]
+pub async fn delete_treasure(_: AdminAuth, State(state): State<AppState>, Path(id): Path<Uuid>) -> Result<StatusCode> {
+    state.catalog.delete(id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
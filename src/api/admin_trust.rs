@@ -0,0 +1,249 @@
+use axum::{
+    Json,
+    extract::{Path, Query, State},
+    http::StatusCode,
+};
+use serde::Deserialize;
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+
+use crate::AppState;
+use crate::anticheat::appeal::{Appeal, AppealResolution, AppealStatus};
+use crate::anticheat::quarantine::{Quarantine, QuarantineSpec};
+use crate::anticheat::trust::TrustReport;
+use crate::error::{Error, Result};
+use super::admin::AdminAuth;
+use super::listing::{ListQuery, ListSpec, Page, PageParams};
+
+// Location trust scores from the spoofing detector
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct TrustListParams {
+    // Only players currently treated as spoofers
+    #[serde(default)]
+    pub suspected: bool,
+}
+
+#[utoipa::path(
+    get,
+    path = "/admin/trust",
+    tag = "admin",
+    security(("admin_token" = [])),
+    params(TrustListParams, PageParams),
+    responses(
+        (status = 200, description = "Players with reported positions, lowest trust first", body = TrustReportPage),
+        (status = 400, description = "Invalid sort, filter or cursor", body = ErrorBody),
+        (status = 401, description = "Missing or wrong admin token", body = ErrorBody),
+        (status = 403, description = "Admin API disabled", body = ErrorBody)
+    )
+)]
+pub async fn list_trust(
+    _: AdminAuth,
+    State(state): State<AppState>,
+    Query(params): Query<TrustListParams>,
+    query: ListQuery,
+) -> Result<Json<Page<TrustReport>>> {
+    const SPEC: ListSpec = ListSpec {
+        default_sort: "score",
+        sort: &["samples", "last_signal_at"],
+        filters: &["score", "samples", "last_signal_at"],
+        key: &["user_id"],
+        params: &["suspected"],
+    };
+    Ok(Json(query.apply(&SPEC, state.match_service.trust().reports(params.suspected).await)?))
+}
+
+// Clear a player's history after review, restoring full trust
+#[utoipa::path(
+    delete,
+    path = "/admin/trust/{user_id}",
+    tag = "admin",
+    security(("admin_token" = [])),
+    params(("user_id" = Uuid, Path, description = "Player to reset")),
+    responses(
+        (status = 204, description = "Reset"),
+        (status = 401, description = "Missing or wrong admin token", body = ErrorBody),
+        (status = 403, description = "Admin API disabled", body = ErrorBody),
+        (status = 404, description = "No history for this player", body = ErrorBody)
+    )
+)]
+pub async fn reset_trust(_: AdminAuth, State(state): State<AppState>, Path(user_id): Path<Uuid>) -> Result<StatusCode> {
+    if !state.match_service.trust().reset(user_id).await {
+        return Err(Error::NotFound(format!("trust history of {}", user_id)));
+    }
+    tracing::info!("Trust of user {} reset by admin", user_id);
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// PUT /admin/trust/{user_id} body
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct TrustAdjustment {
+    // From 0.0 to 1.0
+    pub score: f32,
+}
+
+// Set a player's trust score by hand; their signal history is kept
+#[utoipa::path(
+    put,
+    path = "/admin/trust/{user_id}",
+    tag = "admin",
+    security(("admin_token" = [])),
+    params(("user_id" = Uuid, Path, description = "Player")),
+    request_body = TrustAdjustment,
+    responses(
+        (status = 200, description = "Trust after the change", body = TrustReport),
+        (status = 400, description = "Score out of range", body = ErrorBody),
+        (status = 401, description = "Missing or wrong admin token", body = ErrorBody),
+        (status = 403, description = "Admin API disabled", body = ErrorBody)
+    )
+)]
+pub async fn adjust_trust(
+    _: AdminAuth,
+    State(state): State<AppState>,
+    Path(user_id): Path<Uuid>,
+    body: String,
+) -> Result<Json<TrustReport>> {
+    let adjustment: TrustAdjustment = serde_json::from_str(&body)
+        .map_err(|_| Error::InvalidMessage)?;
+    if !(0.0..=1.0).contains(&adjustment.score) {
+        return Err(Error::InvalidMessage);
+    }
+    tracing::info!("Trust of user {} set to {:.2} by admin", user_id, adjustment.score);
+    Ok(Json(state.match_service.trust().adjust(user_id, adjustment.score).await))
+}
+
+// Appeals against trust scores and quarantine
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct AppealListParams {
+    // open, accepted or rejected; every appeal when absent
+    pub status: Option<AppealStatus>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/admin/appeals",
+    tag = "admin",
+    security(("admin_token" = [])),
+    params(AppealListParams, PageParams),
+    responses(
+        (status = 200, description = "Appeals with the evidence attached, newest first", body = AppealPage),
+        (status = 400, description = "Invalid sort, filter or cursor", body = ErrorBody),
+        (status = 401, description = "Missing or wrong admin token", body = ErrorBody),
+        (status = 403, description = "Admin API disabled", body = ErrorBody)
+    )
+)]
+pub async fn list_appeals(
+    _: AdminAuth,
+    State(state): State<AppState>,
+    Query(params): Query<AppealListParams>,
+    query: ListQuery,
+) -> Result<Json<Page<Appeal>>> {
+    const SPEC: ListSpec = ListSpec {
+        default_sort: "-created_at",
+        sort: &["resolved_at"],
+        filters: &["user_id", "quarantined", "resolved_by", "created_at", "resolved_at"],
+        key: &["id"],
+        params: &["status"],
+    };
+    Ok(Json(query.apply(&SPEC, state.appeals.list(params.status).await?)?))
+}
+
+// Accept or reject an open appeal. Accepting restores the player's trust (or
+// sets `score`) and releases them from quarantine.
+#[utoipa::path(
+    post,
+    path = "/admin/appeals/{id}/resolve",
+    tag = "admin",
+    security(("admin_token" = [])),
+    params(("id" = Uuid, Path, description = "Appeal")),
+    request_body = AppealResolution,
+    responses(
+        (status = 200, description = "Resolved appeal", body = Appeal),
+        (status = 400, description = "Missing resolved_by or note, or score out of range", body = ErrorBody),
+        (status = 401, description = "Missing or wrong admin token", body = ErrorBody),
+        (status = 403, description = "Admin API disabled", body = ErrorBody),
+        (status = 404, description = "No such appeal", body = ErrorBody),
+        (status = 409, description = "Appeal already resolved", body = ErrorBody)
+    )
+)]
+pub async fn resolve_appeal(
+    _: AdminAuth,
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    body: String,
+) -> Result<Json<Appeal>> {
+    let resolution: AppealResolution = serde_json::from_str(&body)
+        .map_err(|_| Error::InvalidMessage)?;
+    Ok(Json(state.appeals.resolve(id, resolution).await?))
+}
+
+// Cheater quarantine pool
+
+#[utoipa::path(
+    get,
+    path = "/admin/quarantine",
+    tag = "admin",
+    security(("admin_token" = [])),
+    params(PageParams),
+    responses(
+        (status = 200, description = "Quarantined players, newest first", body = QuarantinePage),
+        (status = 400, description = "Invalid sort, filter or cursor", body = ErrorBody),
+        (status = 401, description = "Missing or wrong admin token", body = ErrorBody),
+        (status = 403, description = "Admin API disabled", body = ErrorBody)
+    )
+)]
+pub async fn list_quarantine(_: AdminAuth, State(state): State<AppState>, query: ListQuery) -> Result<Json<Page<Quarantine>>> {
+    const SPEC: ListSpec = ListSpec {
+        default_sort: "-created_at",
+        sort: &[],
+        filters: &["user_id", "added_by", "created_at"],
+        key: &["user_id"],
+        params: &[],
+    };
+    Ok(Json(query.apply(&SPEC, state.match_service.quarantine().list().await)?))
+}
+
+// Match the player only with other quarantined players from their next join
+#[utoipa::path(
+    put,
+    path = "/admin/quarantine/{user_id}",
+    tag = "admin",
+    security(("admin_token" = [])),
+    params(("user_id" = Uuid, Path, description = "Player to quarantine")),
+    request_body = QuarantineSpec,
+    responses(
+        (status = 201, description = "Quarantined", body = Quarantine),
+        (status = 400, description = "Missing added_by or reason", body = ErrorBody),
+        (status = 401, description = "Missing or wrong admin token", body = ErrorBody),
+        (status = 403, description = "Admin API disabled", body = ErrorBody)
+    )
+)]
+pub async fn quarantine_player(
+    _: AdminAuth,
+    State(state): State<AppState>,
+    Path(user_id): Path<Uuid>,
+    body: String,
+) -> Result<(StatusCode, Json<Quarantine>)> {
+    let spec: QuarantineSpec = serde_json::from_str(&body)
+        .map_err(|_| Error::InvalidMessage)?;
+    Ok((StatusCode::CREATED, Json(state.match_service.quarantine().add(user_id, spec).await?)))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/admin/quarantine/{user_id}",
+    tag = "admin",
+    security(("admin_token" = [])),
+    params(("user_id" = Uuid, Path, description = "Player to release")),
+    responses(
+        (status = 204, description = "Released"),
+        (status = 401, description = "Missing or wrong admin token", body = ErrorBody),
+        (status = 403, description = "Admin API disabled", body = ErrorBody),
+        (status = 404, description = "Player not quarantined", body = ErrorBody)
+    )
+)]
+pub async fn release_player(_: AdminAuth, State(state): State<AppState>, Path(user_id): Path<Uuid>) -> Result<StatusCode> {
+    state.match_service.quarantine().remove(user_id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
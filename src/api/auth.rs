@@ -0,0 +1,99 @@
+use axum::{
+    Json,
+    extract::{Path, State},
+};
+use uuid::Uuid;
+
+use crate::AppState;
+use crate::auth::identity::{GuestReply, LinkReply, LinkRequest, LinkedIdentity, SignInReply, SignInRequest};
+use crate::error::{Error, Result};
+use super::admin::AdminAuth;
+use super::listing::{ListQuery, ListSpec, Page, PageParams};
+
+// Sign-in and account linking with Google and Apple ID tokens
+
+// Exchange an ID token for the player's user id and a session token,
+// creating the player on the account's first sign-in
+#[utoipa::path(
+    post,
+    path = "/api/auth/sign_in",
+    tag = "auth",
+    request_body = SignInRequest,
+    responses(
+        (status = 200, description = "The player signed in with this account; connect with the session token", body = SignInReply),
+        (status = 400, description = "Malformed body", body = ErrorBody),
+        (status = 401, description = "Invalid or expired ID token", body = ErrorBody),
+        (status = 403, description = "Sign-in with this provider is not enabled", body = ErrorBody),
+        (status = 503, description = "The provider's signing keys can't be fetched", body = ErrorBody)
+    )
+)]
+pub async fn sign_in(State(state): State<AppState>, body: String) -> Result<Json<SignInReply>> {
+    let request: SignInRequest = serde_json::from_str(&body)
+        .map_err(|_| Error::InvalidMessage)?;
+    Ok(Json(state.auth.sign_in(request.provider, &request.id_token).await?))
+}
+
+// Start playing as a new guest
+#[utoipa::path(
+    post,
+    path = "/api/auth/guest",
+    tag = "auth",
+    responses(
+        (status = 200, description = "A new guest player and its session token", body = GuestReply)
+    )
+)]
+pub async fn guest(State(state): State<AppState>) -> Json<GuestReply> {
+    Json(state.auth.guest())
+}
+
+// Link an account to the player holding the session token. A guest linking
+// an account that another player signed in with is merged into that player,
+// whose user id and a session for it are returned.
+#[utoipa::path(
+    post,
+    path = "/api/auth/link",
+    tag = "auth",
+    request_body = LinkRequest,
+    responses(
+        (status = 200, description = "Account linked; continue as the returned user_id", body = LinkReply),
+        (status = 400, description = "Malformed body", body = ErrorBody),
+        (status = 401, description = "Invalid or expired session or ID token", body = ErrorBody),
+        (status = 403, description = "Sign-in with this provider is not enabled", body = ErrorBody),
+        (status = 409, description = "The player is not a guest, already linked an account of this provider, or is in a match", body = ErrorBody),
+        (status = 503, description = "The provider's signing keys can't be fetched", body = ErrorBody)
+    )
+)]
+pub async fn link(State(state): State<AppState>, body: String) -> Result<Json<LinkReply>> {
+    let request: LinkRequest = serde_json::from_str(&body)
+        .map_err(|_| Error::InvalidMessage)?;
+    Ok(Json(state.auth.link(&request.session_token, request.provider, &request.id_token).await?))
+}
+
+#[utoipa::path(
+    get,
+    path = "/admin/identities/{user_id}",
+    tag = "admin",
+    security(("admin_token" = [])),
+    params(("user_id" = Uuid, Path, description = "Player"), PageParams),
+    responses(
+        (status = 200, description = "Accounts the player linked, oldest first", body = LinkedIdentityPage),
+        (status = 400, description = "Invalid sort, filter or cursor", body = ErrorBody),
+        (status = 401, description = "Missing or wrong admin token", body = ErrorBody),
+        (status = 403, description = "Admin API disabled", body = ErrorBody)
+    )
+)]
+pub async fn list_identities(
+    _: AdminAuth,
+    State(state): State<AppState>,
+    Path(user_id): Path<Uuid>,
+    query: ListQuery,
+) -> Result<Json<Page<LinkedIdentity>>> {
+    const SPEC: ListSpec = ListSpec {
+        default_sort: "linked_at",
+        sort: &[],
+        filters: &["provider", "email", "linked_at"],
+        key: &["provider", "subject"],
+        params: &[],
+    };
+    Ok(Json(query.apply(&SPEC, state.auth.identities(user_id).await?)?))
+}
@@ -0,0 +1,148 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use axum::{
+    body::Bytes,
+    http::{HeaderMap, StatusCode, header},
+    response::{IntoResponse, Response},
+};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use tokio::sync::broadcast;
+use uuid::Uuid;
+
+use crate::config::CacheConfig;
+use crate::error::{Error, Result};
+use crate::matchmaking::events::{MatchEvent, Published};
+use crate::memory;
+use crate::metrics::METRICS;
+
+const LEADERBOARD_PREFIX: &str = "leaderboard:";
+
+// A serialized response body and its ETag
+#[derive(Clone)]
+pub struct Cached {
+    etag: String,
+    body: Bytes,
+    stored_at: Instant,
+}
+
+impl Cached {
+    fn new(value: &impl Serialize) -> Result<Self> {
+        let body = serde_json::to_vec(value).map_err(|_| Error::InvalidMessage)?;
+        let etag = format!("\"{}\"", hex::encode(&Sha256::digest(&body)[..16]));
+        Ok(Self { etag, body: Bytes::from(body), stored_at: Instant::now() })
+    }
+
+    // 304 when the client already has this version
+    pub fn respond(&self, headers: &HeaderMap, cache_control: &str) -> Response {
+        let unchanged = headers
+            .get(header::IF_NONE_MATCH)
+            .and_then(|v| v.to_str().ok())
+            .is_some_and(|v| v.split(',').any(|tag| tag.trim() == self.etag || tag.trim() == "*"));
+        let cache_headers = [
+            (header::ETAG, self.etag.clone()),
+            (header::CACHE_CONTROL, cache_control.to_string()),
+        ];
+        if unchanged {
+            return (StatusCode::NOT_MODIFIED, cache_headers).into_response();
+        }
+        (
+            cache_headers,
+            [(header::CONTENT_TYPE, "application/json")],
+            self.body.clone(),
+        ).into_response()
+    }
+}
+
+// Responses of the read-heavy public routes (leaderboard, finished matches),
+// so dashboards polling them don't each cost a repository call. Entries live
+// for API_CACHE_TTL_SECS; a match ending on this node drops the leaderboard
+// and that match's details right away. Other nodes' matches show up once the
+// entries expire. At most API_CACHE_MAX_ENTRIES are kept.
+pub struct ResponseCache {
+    config: CacheConfig,
+    entries: Mutex<HashMap<String, Cached>>,
+}
+
+impl ResponseCache {
+    pub fn new(config: CacheConfig) -> Arc<Self> {
+        Arc::new(Self {
+            config,
+            entries: Mutex::new(HashMap::new()),
+        })
+    }
+
+    // Cache-Control of responses that may be cached anywhere
+    pub fn public(&self) -> String {
+        format!("public, max-age={}", self.config.max_age.as_secs())
+    }
+
+    // A fresh entry, if there is one
+    pub fn lookup(&self, key: &str) -> Option<Cached> {
+        let entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        entries.get(key)
+            .filter(|cached| cached.stored_at.elapsed() < self.config.ttl)
+            .cloned()
+    }
+
+    pub fn store(&self, key: &str, value: &impl Serialize) -> Result<Cached> {
+        let cached = Cached::new(value)?;
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        if entries.len() >= self.config.max_entries {
+            entries.retain(|_, cached| cached.stored_at.elapsed() < self.config.ttl);
+        }
+        if entries.len() >= self.config.max_entries
+            && let Some(oldest) = entries.iter().min_by_key(|(_, cached)| cached.stored_at).map(|(key, _)| key.clone())
+        {
+            entries.remove(&oldest);
+            METRICS.record_memory_cap_hit(memory::RESPONSE_CACHE);
+        }
+        entries.insert(key.to_string(), cached.clone());
+        Ok(cached)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap_or_else(|e| e.into_inner()).len()
+    }
+
+    // A match that no longer exists, e.g. after a purge
+    pub fn forget_match(&self, match_id: Uuid) {
+        self.invalidate(match_id, false);
+    }
+
+    fn invalidate(&self, match_id: Uuid, leaderboard: bool) {
+        let match_key = match_key(match_id);
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        entries.retain(|key, _| *key != match_key && !(leaderboard && key.starts_with(LEADERBOARD_PREFIX)));
+    }
+
+    pub fn spawn_invalidation(self: Arc<Self>, mut events: broadcast::Receiver<Published>) {
+        tokio::spawn(async move {
+            loop {
+                match events.recv().await.map(|published| published.event) {
+                    // Ratings change with every finished match
+                    Ok(MatchEvent::MatchEnded { match_id }) => self.invalidate(match_id, true),
+                    Ok(MatchEvent::ResultAdjusted { entry, .. }) => self.invalidate(entry.match_id, false),
+                    Ok(_) => {}
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        // Anything could have ended in the meantime
+                        tracing::warn!("Response cache lagged, skipped {} match events", skipped);
+                        self.entries.lock().unwrap_or_else(|e| e.into_inner()).clear();
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                }
+            }
+        });
+    }
+}
+
+// One entry per query string, so every page and filter is cached
+pub fn leaderboard_key(query: &str) -> String {
+    format!("{}{}", LEADERBOARD_PREFIX, query)
+}
+
+pub fn match_key(match_id: Uuid) -> String {
+    format!("match:{}", match_id)
+}
@@ -0,0 +1,34 @@
+use axum::{
+    Json,
+    extract::State,
+    http::{HeaderMap, StatusCode, header},
+    response::{IntoResponse, Response},
+};
+
+use crate::AppState;
+
+// Current remote config; the same document clients get in sys.welcome.
+// The version doubles as ETag so polling clients can send If-None-Match.
+#[utoipa::path(
+    get,
+    path = "/api/config/client",
+    tag = "config",
+    responses(
+        (status = 200, description = "Current client config", body = ClientConfig),
+        (status = 304, description = "Unchanged since the If-None-Match version")
+    )
+)]
+pub async fn get_client_config(State(state): State<AppState>, headers: HeaderMap) -> Response {
+    let config = state.remote_config.current().await;
+    let etag = format!("\"{}\"", config.version);
+
+    let unchanged = headers
+        .get(header::IF_NONE_MATCH)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.split(',').any(|tag| tag.trim() == etag));
+    if unchanged {
+        return (StatusCode::NOT_MODIFIED, [(header::ETAG, etag)]).into_response();
+    }
+
+    ([(header::ETAG, etag)], Json(config)).into_response()
+}
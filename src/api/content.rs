@@ -0,0 +1,28 @@
+use axum::{
+    Json,
+    extract::{Query, State},
+};
+use serde::Deserialize;
+use utoipa::IntoParams;
+
+use crate::AppState;
+use crate::content::bundles::ContentBundle;
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct ContentParams {
+    // e.g. `pt-BR`; DEFAULT_LOCALE when missing or not bundled
+    pub locale: Option<String>,
+}
+
+// Localized text for clients to show, such as quest descriptions, in the
+// player's language
+#[utoipa::path(
+    get,
+    path = "/api/content",
+    tag = "game",
+    params(ContentParams),
+    responses((status = 200, description = "Every text in the resolved locale", body = ContentBundle))
+)]
+pub async fn get_content(State(state): State<AppState>, Query(params): Query<ContentParams>) -> Json<ContentBundle> {
+    Json(state.content.bundle(params.locale.as_deref()))
+}
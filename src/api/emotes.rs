@@ -0,0 +1,14 @@
+use axum::Json;
+
+use crate::models::emote::{CATALOG, Emote};
+
+// Emotes and quick-chat phrases players can send with game.emote
+#[utoipa::path(
+    get,
+    path = "/api/emotes",
+    tag = "game",
+    responses((status = 200, description = "The emote catalog", body = [Emote]))
+)]
+pub async fn list_emotes() -> Json<&'static [Emote]> {
+    Json(CATALOG)
+}
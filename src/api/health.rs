@@ -0,0 +1,61 @@
+use axum::{
+    Json,
+    extract::State,
+    http::StatusCode,
+    response::IntoResponse,
+};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::AppState;
+use crate::matchmaking::service::Capabilities;
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ReadinessReport {
+    pub ready: bool,
+    // Database unreachable; see `capabilities.persistence`
+    pub degraded: bool,
+    // "refuse" or "queue"
+    pub offline_policy: String,
+    pub capabilities: Capabilities,
+}
+
+// Liveness: the process is up and serving HTTP
+#[utoipa::path(
+    get,
+    path = "/healthz",
+    tag = "health",
+    responses((status = 200, description = "Process is alive", body = String))
+)]
+pub async fn healthz() -> impl IntoResponse {
+    (StatusCode::OK, "ok")
+}
+
+// Readiness: whether new matches can be accepted. With DB_OFFLINE_POLICY=queue
+// the server stays ready while the database is down, but reports it as degraded.
+#[utoipa::path(
+    get,
+    path = "/readyz",
+    tag = "health",
+    responses(
+        (status = 200, description = "Accepting matches", body = ReadinessReport),
+        (status = 503, description = "Database down and new matches refused", body = ReadinessReport)
+    )
+)]
+pub async fn readyz(State(state): State<AppState>) -> impl IntoResponse {
+    let capabilities = state.match_service.capabilities();
+    let status = if capabilities.matchmaking {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+
+    let report = ReadinessReport {
+        ready: capabilities.matchmaking,
+        degraded: !capabilities.db_available,
+        offline_policy: state.config.offline.policy.to_str().to_string(),
+        capabilities,
+    };
+
+    (status, Json(report))
+}
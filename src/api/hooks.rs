@@ -0,0 +1,69 @@
+use axum::{
+    body::Bytes,
+    extract::State,
+    http::{HeaderMap, StatusCode},
+};
+use serde::Deserialize;
+
+use crate::AppState;
+use crate::error::{Error, Result};
+use crate::signing;
+
+// Webhooks called by Hasura event triggers
+
+#[derive(Debug, Deserialize)]
+struct HasuraEvent {
+    table: HasuraTable,
+    trigger: HasuraTrigger,
+}
+
+#[derive(Debug, Deserialize)]
+struct HasuraTable {
+    name: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct HasuraTrigger {
+    name: String,
+}
+
+// A row of a cached table changed outside this server, e.g. in the Hasura
+// console: reload that cache now instead of on the next periodic refresh.
+// Only the node receiving the event reloads; the others catch up on their own.
+#[utoipa::path(
+    post,
+    path = "/hooks/hasura",
+    tag = "hooks",
+    params(
+        ("x-spv-timestamp" = String, Header, description = "Unix seconds when the payload was signed"),
+        ("x-spv-nonce" = String, Header, description = "Random value, never reused"),
+        ("x-spv-signature" = String, Header, description = "<key id>=<hex HMAC-SHA256 of '<timestamp>.<nonce>.<body>'>")
+    ),
+    responses(
+        (status = 204, description = "Event handled, or about a table the server doesn't cache"),
+        (status = 400, description = "Not a Hasura event payload", body = ErrorBody),
+        (status = 401, description = "Unsigned, forged, stale or replayed payload", body = ErrorBody),
+        (status = 403, description = "WEBHOOK_SIGNING_KEYS is not set", body = ErrorBody)
+    )
+)]
+pub async fn hasura_event(State(state): State<AppState>, headers: HeaderMap, body: Bytes) -> Result<StatusCode> {
+    signing::verify(&headers, &body)?;
+    let event: HasuraEvent = serde_json::from_slice(&body)
+        .map_err(|_| Error::InvalidMessage)?;
+
+    let reloaded = match event.table.name.as_str() {
+        "bans" => state.bans.reload().await,
+        "treasures" => state.catalog.reload().await,
+        "experiments" => state.experiments.reload().await,
+        "client_config_versions" => state.remote_config.reload().await,
+        "announcements" | "motd" => state.announcements.reload().await,
+        _ => {
+            tracing::debug!("Ignoring event trigger {} on {}", event.trigger.name, event.table.name);
+            return Ok(StatusCode::NO_CONTENT);
+        }
+    };
+    // Hasura retries failed deliveries
+    reloaded?;
+    tracing::info!("Reloaded {} after event trigger {}", event.table.name, event.trigger.name);
+    Ok(StatusCode::NO_CONTENT)
+}
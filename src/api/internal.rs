@@ -0,0 +1,79 @@
+use axum::{
+    Json,
+    async_trait,
+    extract::{FromRequestParts, Path, State},
+    http::request::Parts,
+};
+use uuid::Uuid;
+
+use crate::AppState;
+use crate::cluster::node_id;
+use crate::correlation;
+use crate::cluster::rpc::{AdoptRequest, DeliverReply, DeliverRequest, NodeHealth, TOKEN_HEADER};
+use crate::error::{Error, Result};
+use crate::jobs::job::Job;
+use super::admin::constant_time_eq;
+
+// Node-to-node API, called by the other nodes of the cluster. Not part of the
+// public OpenAPI document.
+
+// Requires `X-Cluster-Token: <CLUSTER_TOKEN>`; the internal API is off when it is unset
+pub struct ClusterAuth;
+
+#[async_trait]
+impl FromRequestParts<AppState> for ClusterAuth {
+    type Rejection = Error;
+
+    async fn from_request_parts(parts: &mut Parts, state: &AppState) -> Result<Self> {
+        let expected = state.config.cluster.token.as_deref()
+            .ok_or_else(|| Error::PermissionDenied("internal API is disabled".to_string()))?;
+        let token = parts.headers
+            .get(TOKEN_HEADER)
+            .and_then(|v| v.to_str().ok())
+            .ok_or(Error::AuthError)?;
+        if constant_time_eq(expected.as_bytes(), token.as_bytes()) {
+            Ok(ClusterAuth)
+        } else {
+            Err(Error::AuthError)
+        }
+    }
+}
+
+// Push an event to users connected to this node
+pub async fn deliver(_: ClusterAuth, State(state): State<AppState>, body: String) -> Result<Json<DeliverReply>> {
+    let request: DeliverRequest = serde_json::from_str(&body)
+        .map_err(|_| Error::InvalidMessage)?;
+    let push = state.ws_handler.push_to_local_users(&request.user_ids, &request.event, &request.data);
+    let delivered = correlation::scope(request.correlation_id, push).await;
+    Ok(Json(DeliverReply { delivered }))
+}
+
+// Take over a running match from the node that owns it
+pub async fn adopt_match(
+    _: ClusterAuth,
+    State(state): State<AppState>,
+    Path(match_id): Path<Uuid>,
+    body: String,
+) -> Result<Json<serde_json::Value>> {
+    let request: AdoptRequest = serde_json::from_str(&body)
+        .map_err(|_| Error::InvalidMessage)?;
+    let details = state.match_service.adopt_match(match_id, &request.from_node).await?;
+    state.game.clone().resume(&details).await;
+    Ok(Json(serde_json::json!({ "match_id": match_id })))
+}
+
+// Cancel a job this node runs
+pub async fn cancel_job(_: ClusterAuth, State(state): State<AppState>, Path(job_id): Path<Uuid>) -> Result<Json<Job>> {
+    Ok(Json(state.jobs.cancel(job_id).await?))
+}
+
+// Load and role of this node
+pub async fn health(_: ClusterAuth, State(state): State<AppState>) -> Json<NodeHealth> {
+    Json(NodeHealth {
+        node_id: node_id().to_string(),
+        connections: state.conn_manager.connection_count().await,
+        owned_matches: state.match_service.owned_match_count().await,
+        leader: state.leader.is_leader(),
+        capabilities: state.match_service.capabilities(),
+    })
+}
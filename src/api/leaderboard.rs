@@ -0,0 +1,49 @@
+use axum::{
+    extract::State,
+    http::HeaderMap,
+    response::Response,
+};
+
+use crate::AppState;
+use crate::error::Result;
+use super::cache;
+use super::listing::{ListQuery, ListSpec, PageParams};
+
+// Ranked players the leaderboard is built from
+const SIZE: usize = 500;
+
+// Highest rated players past placement, cached and served with an ETag
+#[utoipa::path(
+    get,
+    path = "/api/leaderboard",
+    tag = "game",
+    params(PageParams),
+    responses(
+        (status = 200, description = "Players by MMR, best first", body = LeaderboardPage),
+        (status = 304, description = "Unchanged since the If-None-Match version"),
+        (status = 400, description = "Invalid sort, filter or cursor", body = ErrorBody),
+        (status = 503, description = "Database unavailable", body = ErrorBody)
+    )
+)]
+pub async fn get_leaderboard(
+    State(state): State<AppState>,
+    query: ListQuery,
+    headers: HeaderMap,
+) -> Result<Response> {
+    const SPEC: ListSpec = ListSpec {
+        default_sort: "rank",
+        sort: &["wins", "matches"],
+        filters: &["tier", "mmr", "wins", "matches"],
+        key: &["user_id"],
+        params: &[],
+    };
+    let key = cache::leaderboard_key(&query.canonical());
+    let cached = match state.cache.lookup(&key) {
+        Some(cached) => cached,
+        None => {
+            let page = query.apply(&SPEC, state.ratings.leaderboard(SIZE).await?)?;
+            state.cache.store(&key, &page)?
+        }
+    };
+    Ok(cached.respond(&headers, &state.cache.public()))
+}
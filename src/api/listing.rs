@@ -0,0 +1,302 @@
+use async_trait::async_trait;
+use axum::{
+    extract::{FromRequestParts, Query},
+    http::request::Parts,
+};
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use utoipa::{IntoParams, ToSchema};
+
+use crate::anticheat::appeal::Appeal;
+use crate::anticheat::quarantine::Quarantine;
+use crate::anticheat::trust::TrustReport;
+use crate::announcements::announcement::Announcement;
+use crate::apikeys::key::ApiKey;
+use crate::audit::record::AuditRecord;
+use crate::auth::identity::LinkedIdentity;
+use crate::db::keyset::{Filter, KeysetQuery, Op};
+use crate::devices::device::Device;
+use crate::error::{Error, Result};
+use crate::experiments::experiment::Experiment;
+use crate::gateway::match_stats::MatchStats;
+use crate::gateway::state::ConnectionInfo;
+use crate::heatmap::sample::HeatmapTile;
+use crate::jobs::job::Job;
+use crate::matchmaking::fairness::FairnessReport;
+use crate::matchmaking::review::MatchReview;
+use crate::models::treasure::Treasure;
+use crate::moderation::ban::Ban;
+use crate::rating::mmr::{LeaderboardEntry, PlayerRating};
+use crate::remote_config::document::ConfigChange;
+use super::admin_cluster::NodeStatus;
+
+const DEFAULT_LIMIT: usize = 50;
+const MAX_LIMIT: usize = 500;
+
+// Parameters the framework itself reads; anything else is a filter
+const RESERVED: [&str; 3] = ["cursor", "limit", "sort"];
+
+// One page of a list route
+#[derive(Debug, Serialize, ToSchema)]
+#[aliases(
+    MatchStatsPage = Page<MatchStats>,
+    ConnectionInfoPage = Page<ConnectionInfo>,
+    ExperimentPage = Page<Experiment>,
+    ConfigChangePage = Page<ConfigChange>,
+    TreasurePage = Page<Treasure>,
+    TrustReportPage = Page<TrustReport>,
+    AppealPage = Page<Appeal>,
+    QuarantinePage = Page<Quarantine>,
+    HeatmapTilePage = Page<HeatmapTile>,
+    FairnessReportPage = Page<FairnessReport>,
+    MatchReviewPage = Page<MatchReview>,
+    BanPage = Page<Ban>,
+    DevicePage = Page<Device>,
+    LinkedIdentityPage = Page<LinkedIdentity>,
+    PlayerRatingPage = Page<PlayerRating>,
+    AnnouncementPage = Page<Announcement>,
+    ApiKeyPage = Page<ApiKey>,
+    AuditRecordPage = Page<AuditRecord>,
+    NodeStatusPage = Page<NodeStatus>,
+    LeaderboardPage = Page<LeaderboardEntry>,
+    JobPage = Page<Job>,
+)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    // Pass as `cursor` for the next page; absent on the last one
+    pub next_cursor: Option<String>,
+    // Items matching the filters
+    pub total_estimate: usize,
+}
+
+// Documents the shared parameters; routes read them through `ListQuery`
+#[allow(dead_code)]
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct PageParams {
+    // `next_cursor` of the previous page
+    pub cursor: Option<String>,
+    // Page size, 50 by default and at most 500
+    pub limit: Option<usize>,
+    // A field from the route's sort list, `-` first for descending,
+    // e.g. `-created_at`
+    pub sort: Option<String>,
+}
+
+// What a route lets clients sort and filter on. Fields are the item's JSON
+// field names.
+pub struct ListSpec {
+    // Order when no `sort` is given, `-` first for descending
+    pub default_sort: &'static str,
+    pub sort: &'static [&'static str],
+    pub filters: &'static [&'static str],
+    // Fields identifying an item, breaking ties between equal sort values
+    pub key: &'static [&'static str],
+    // The route's own query parameters, not filters
+    pub params: &'static [&'static str],
+}
+
+// Query string of a list route:
+// `?limit=&cursor=&sort=-created_at&status=open&score[lt]=0.5&platform[in]=ios,android`
+pub struct ListQuery {
+    pairs: Vec<(String, String)>,
+}
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for ListQuery {
+    type Rejection = Error;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self> {
+        let Query(pairs) = Query::<Vec<(String, String)>>::from_request_parts(parts, state)
+            .await
+            .map_err(|e| Error::InvalidQuery(e.body_text()))?;
+        Ok(Self { pairs })
+    }
+}
+
+impl ListQuery {
+    fn get(&self, name: &str) -> Option<&str> {
+        self.pairs.iter().find(|(key, _)| key == name).map(|(_, value)| value.as_str())
+    }
+
+    // Page size asked for, for routes that fetch only as much as they show
+    pub fn limit(&self) -> Result<usize> {
+        match self.get("limit") {
+            None => Ok(DEFAULT_LIMIT),
+            Some(limit) => limit.parse::<usize>()
+                .map(|limit| limit.clamp(1, MAX_LIMIT))
+                .map_err(|_| Error::InvalidQuery(format!("limit {}", limit))),
+        }
+    }
+
+    // Canonical form, e.g. to key a cache by
+    pub fn canonical(&self) -> String {
+        let mut pairs = self.pairs.clone();
+        pairs.sort();
+        pairs.iter().map(|(key, value)| format!("{}={}", key, value)).collect::<Vec<_>>().join("&")
+    }
+
+    fn filters(&self, spec: &ListSpec) -> Result<Vec<Filter>> {
+        let mut filters = Vec::new();
+        for (key, value) in &self.pairs {
+            if RESERVED.contains(&key.as_str()) || spec.params.contains(&key.as_str()) {
+                continue;
+            }
+            let (field, op) = match key.split_once('[') {
+                Some((field, op)) => {
+                    let op = op.strip_suffix(']')
+                        .and_then(Op::parse)
+                        .ok_or_else(|| Error::InvalidQuery(format!("unknown operator in {}", key)))?;
+                    (field, op)
+                }
+                None => (key.as_str(), Op::Eq),
+            };
+            if !spec.filters.contains(&field) {
+                return Err(Error::InvalidQuery(format!("can't filter on {}", field)));
+            }
+            filters.push(Filter { field: field.to_string(), op, value: value.clone() });
+        }
+        Ok(filters)
+    }
+
+    fn sort(&self, spec: &ListSpec) -> Result<(String, bool)> {
+        let sort = self.get("sort").unwrap_or(spec.default_sort);
+        let (field, descending) = match sort.strip_prefix('-') {
+            Some(field) => (field, true),
+            None => (sort, false),
+        };
+        if field != spec.default_sort.trim_start_matches('-') && !spec.sort.contains(&field) {
+            return Err(Error::InvalidQuery(format!("can't sort by {}", field)));
+        }
+        Ok((field.to_string(), descending))
+    }
+
+    // The page asked for, for the repository to fetch; see `KeysetQuery`
+    pub fn keyset(&self, spec: &ListSpec) -> Result<KeysetQuery> {
+        let (sort, descending) = self.sort(spec)?;
+        let after = match self.get("cursor") {
+            Some(cursor) => Some(decode_cursor(cursor)?),
+            None => None,
+        };
+        Ok(KeysetQuery {
+            filters: self.filters(spec)?,
+            sort,
+            descending,
+            key: spec.key.iter().map(|key| key.to_string()).collect(),
+            after,
+            limit: self.limit()? + 1,
+        })
+    }
+
+    // The page out of the rows fetched for `keyset`. The cursor holds the sort
+    // value and key of the last item shown, so the next page starts after it
+    // even if items were added or removed in between.
+    pub fn page<T: Serialize>(&self, keyset: &KeysetQuery, mut rows: Vec<T>, total_estimate: usize) -> Result<Page<T>> {
+        let limit = keyset.limit - 1;
+        let next_cursor = if rows.len() > limit {
+            rows.truncate(limit);
+            match rows.last() {
+                Some(last) => {
+                    let value = serde_json::to_value(last).map_err(|_| Error::InvalidMessage)?;
+                    Some(encode_cursor(&keyset.position(&value)))
+                }
+                None => None,
+            }
+        } else {
+            None
+        };
+        Ok(Page { items: rows, next_cursor, total_estimate })
+    }
+
+    // Filters, sorts and pages `items` in memory
+    pub fn apply<T: Serialize>(&self, spec: &ListSpec, items: Vec<T>) -> Result<Page<T>> {
+        let keyset = self.keyset(spec)?;
+        let (rows, total_estimate) = keyset.apply(items)?;
+        self.page(&keyset, rows, total_estimate)
+    }
+}
+
+fn encode_cursor(position: &[Value]) -> String {
+    URL_SAFE_NO_PAD.encode(Value::from(position.to_vec()).to_string())
+}
+
+fn decode_cursor(cursor: &str) -> Result<Vec<Value>> {
+    URL_SAFE_NO_PAD.decode(cursor)
+        .ok()
+        .and_then(|bytes| serde_json::from_slice::<Vec<Value>>(&bytes).ok())
+        .filter(|position| !position.is_empty())
+        .ok_or_else(|| Error::InvalidQuery("invalid cursor".to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    const SPEC: ListSpec = ListSpec {
+        default_sort: "-created_at",
+        sort: &["status"],
+        filters: &["status", "created_at"],
+        key: &["id"],
+        params: &[],
+    };
+
+    fn query(pairs: &[(&str, &str)]) -> ListQuery {
+        ListQuery { pairs: pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect() }
+    }
+
+    fn ids(page: &Page<Value>) -> Vec<&str> {
+        page.items.iter().map(|item| item["id"].as_str().unwrap()).collect()
+    }
+
+    fn records() -> Vec<Value> {
+        vec![
+            // 10:00:00.5, written with fewer fractional digits than the next
+            json!({ "id": "a", "status": 200, "created_at": "2026-03-01T10:00:00.5Z" }),
+            json!({ "id": "b", "status": 404, "created_at": "2026-03-01T10:00:00.25Z" }),
+            // 10:00:00.5 again, in another offset
+            json!({ "id": "c", "status": 500, "created_at": "2026-03-01T11:00:00.5+01:00" }),
+            json!({ "id": "d", "status": 200, "created_at": "2026-03-01T09:59:59Z" }),
+        ]
+    }
+
+    #[test]
+    fn timestamps_sort_as_instants_with_ties_broken_on_the_key() {
+        let page = query(&[]).apply(&SPEC, records()).unwrap();
+        assert_eq!(ids(&page), ["a", "c", "b", "d"]);
+        assert_eq!(page.total_estimate, 4);
+        assert!(page.next_cursor.is_none());
+    }
+
+    #[test]
+    fn cursor_pages_through_ties() {
+        let first = query(&[("limit", "1")]).apply(&SPEC, records()).unwrap();
+        assert_eq!(ids(&first), ["a"]);
+        let cursor = first.next_cursor.unwrap();
+        let rest = query(&[("limit", "2"), ("cursor", &cursor)]).apply(&SPEC, records()).unwrap();
+        assert_eq!(ids(&rest), ["c", "b"]);
+        let cursor = rest.next_cursor.unwrap();
+        let last = query(&[("cursor", &cursor)]).apply(&SPEC, records()).unwrap();
+        assert_eq!(ids(&last), ["d"]);
+        assert!(last.next_cursor.is_none());
+    }
+
+    #[test]
+    fn filters_compare_timestamps_and_numbers() {
+        let page = query(&[("created_at[gte]", "2026-03-01T10:00:00.3Z"), ("sort", "status")])
+            .apply(&SPEC, records())
+            .unwrap();
+        assert_eq!(ids(&page), ["a", "c"]);
+        let page = query(&[("status[in]", "404,500")]).apply(&SPEC, records()).unwrap();
+        assert_eq!(ids(&page), ["c", "b"]);
+    }
+
+    #[test]
+    fn unknown_fields_and_bad_cursors_are_refused() {
+        assert!(query(&[("actor", "admin")]).apply(&SPEC, records()).is_err());
+        assert!(query(&[("sort", "id")]).apply(&SPEC, records()).is_err());
+        assert!(query(&[("cursor", "nope")]).apply(&SPEC, records()).is_err());
+    }
+}
@@ -0,0 +1,44 @@
+use axum::{
+    extract::{Path, State},
+    http::HeaderMap,
+    response::Response,
+};
+use uuid::Uuid;
+
+use crate::AppState;
+use crate::error::{Error, Result};
+use crate::models::game::MatchStatus;
+use super::cache;
+
+// Details of a finished match: teams, members and scores. They no longer
+// change, apart from admin adjustments, so they are cached and served with
+// an ETag.
+#[utoipa::path(
+    get,
+    path = "/api/matches/{match_id}",
+    tag = "game",
+    params(("match_id" = Uuid, Path, description = "Finished match")),
+    responses(
+        (status = 200, description = "Match details", body = Object),
+        (status = 304, description = "Unchanged since the If-None-Match version"),
+        (status = 404, description = "No such match", body = ErrorBody),
+        (status = 409, description = "The match isn't finished", body = ErrorBody)
+    )
+)]
+pub async fn get_match(
+    State(state): State<AppState>,
+    Path(match_id): Path<Uuid>,
+    headers: HeaderMap,
+) -> Result<Response> {
+    let key = cache::match_key(match_id);
+    if let Some(cached) = state.cache.lookup(&key) {
+        return Ok(cached.respond(&headers, &state.cache.public()));
+    }
+
+    let details = state.match_service.get_match_details(match_id).await?;
+    if details.status != MatchStatus::Finished {
+        return Err(Error::MatchNotFinished);
+    }
+    let cached = state.cache.store(&key, &details)?;
+    Ok(cached.respond(&headers, &state.cache.public()))
+}
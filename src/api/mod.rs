@@ -0,0 +1,129 @@
+use axum::{Router, routing::{delete, get, patch, post, put}};
+
+use crate::AppState;
+
+pub mod admin;
+pub mod admin_announcements;
+pub mod admin_api_keys;
+pub mod admin_bans;
+pub mod admin_bulk;
+pub mod admin_cluster;
+pub mod admin_devices;
+pub mod admin_events;
+pub mod admin_fairness;
+pub mod admin_heatmap;
+pub mod admin_maintenance;
+pub mod admin_ratings;
+pub mod admin_reviews;
+pub mod admin_scores;
+pub mod admin_treasures;
+pub mod admin_trust;
+pub mod auth;
+pub mod cache;
+pub mod client_config;
+pub mod content;
+pub mod emotes;
+pub mod health;
+pub mod hooks;
+pub mod internal;
+pub mod leaderboard;
+pub mod listing;
+pub mod matches;
+pub mod metrics;
+pub mod openapi;
+pub mod protocol;
+pub mod schedules;
+pub mod telemetry;
+pub mod zones;
+
+// REST routes served next to the WebSocket endpoint
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/healthz", get(health::healthz))
+        .route("/readyz", get(health::readyz))
+        .route("/metrics", get(metrics::metrics))
+        .route("/api/openapi.json", get(openapi::openapi_json))
+        .route("/api/protocol.json", get(protocol::protocol_json))
+        .route("/api/telemetry", post(telemetry::ingest))
+        .route("/api/telemetry/schema", get(telemetry::list_schemas))
+        .route("/api/auth/sign_in", post(auth::sign_in))
+        .route("/api/auth/guest", post(auth::guest))
+        .route("/api/auth/link", post(auth::link))
+        .route("/api/config/client", get(client_config::get_client_config))
+        .route("/api/zones", get(zones::list_zones))
+        .route("/api/leaderboard", get(leaderboard::get_leaderboard))
+        .route("/api/matches/:match_id", get(matches::get_match))
+        .route("/api/emotes", get(emotes::list_emotes))
+        .route("/api/content", get(content::get_content))
+        .route("/api/schedules/:id/calendar.ics", get(schedules::calendar))
+        .route("/admin/matches", get(admin::list_matches))
+        .route("/admin/matches/:match_id/end", post(admin::end_match))
+        .route("/admin/connections", get(admin::list_connections))
+        .route("/admin/maintenance", get(admin_maintenance::get_maintenance).put(admin_maintenance::put_maintenance))
+        .route("/admin/events", get(admin_events::tail_events))
+        .route("/admin/experiments", get(admin::list_experiments))
+        .route("/admin/experiments/:key", put(admin::put_experiment).delete(admin::delete_experiment))
+        .route("/admin/config/client", patch(admin::update_client_config))
+        .route("/admin/config/client/history", get(admin::client_config_history))
+        .route("/admin/treasures", get(admin_treasures::list_treasures).post(admin_treasures::create_treasure))
+        .route(
+            "/admin/treasures/:id",
+            get(admin_treasures::get_treasure)
+                .put(admin_treasures::update_treasure)
+                .delete(admin_treasures::delete_treasure),
+        )
+        .route("/admin/trust", get(admin_trust::list_trust))
+        .route("/admin/heatmap", get(admin_heatmap::get_heatmap))
+        .route("/admin/scores/reconcile", post(admin_scores::reconcile_scores))
+        .route("/admin/fairness", get(admin_fairness::list_reports).post(admin_fairness::compute_report))
+        .route("/admin/reviews", get(admin_reviews::list_reviews))
+        .route("/admin/reviews/:match_id", get(admin_reviews::get_review))
+        .route("/admin/reviews/:match_id/adjustments", post(admin_reviews::adjust_match))
+        .route("/admin/trust/:user_id", put(admin_trust::adjust_trust).delete(admin_trust::reset_trust))
+        .route("/admin/appeals", get(admin_trust::list_appeals))
+        .route("/admin/appeals/:id/resolve", post(admin_trust::resolve_appeal))
+        .route("/admin/quarantine", get(admin_trust::list_quarantine))
+        .route(
+            "/admin/quarantine/:user_id",
+            put(admin_trust::quarantine_player).delete(admin_trust::release_player),
+        )
+        .route("/admin/bans", get(admin_bans::list_bans))
+        .route(
+            "/admin/bans/:user_id",
+            get(admin_bans::ban_history)
+                .put(admin_bans::issue_ban)
+                .delete(admin_bans::lift_ban),
+        )
+        .route("/admin/devices/:user_id", get(admin_devices::list_devices))
+        .route("/admin/devices/:user_id/:device_id", delete(admin_devices::revoke_device))
+        .route("/admin/identities/:user_id", get(auth::list_identities))
+        .route("/admin/ratings/:user_id", get(admin_ratings::get_rating))
+        .route("/admin/smurfs", get(admin_ratings::list_smurfs))
+        .route("/admin/smurfs/:user_id", delete(admin_ratings::clear_smurf))
+        .route("/admin/calibration", get(admin_ratings::get_calibration))
+        .route(
+            "/admin/motd",
+            get(admin_announcements::get_motd)
+                .put(admin_announcements::put_motd)
+                .delete(admin_announcements::delete_motd),
+        )
+        .route("/admin/announcements", get(admin_announcements::list_announcements))
+        .route("/admin/announcements/:id", delete(admin_announcements::cancel_announcement))
+        .route("/admin/api_keys", get(admin_api_keys::list_keys).post(admin_api_keys::create_key))
+        .route("/admin/api_keys/:id", delete(admin_api_keys::revoke_key))
+        .route("/admin/api_keys/:id/rotate", post(admin_api_keys::rotate_key))
+        .route("/admin/audit", get(admin_api_keys::list_audit))
+        .route("/admin/cluster/nodes", get(admin_cluster::list_nodes))
+        .route("/admin/cluster/matches/:match_id/transfer", post(admin_cluster::transfer_match))
+        .route("/admin/bulk/end_matches", post(admin_bulk::end_matches))
+        .route("/admin/bulk/bans", post(admin_bulk::ban_users))
+        .route("/admin/bulk/purge_matches", post(admin_bulk::purge_matches))
+        .route("/admin/jobs", get(admin_bulk::list_jobs))
+        .route("/admin/jobs/:id", get(admin_bulk::get_job))
+        .route("/admin/jobs/:id/cancel", post(admin_bulk::cancel_job))
+        .route("/hooks/hasura", post(hooks::hasura_event))
+        .route("/internal/deliver", post(internal::deliver))
+        .route("/internal/matches/:match_id/adopt", post(internal::adopt_match))
+        .route("/internal/jobs/:job_id/cancel", post(internal::cancel_job))
+        .route("/internal/health", get(internal::health))
+}
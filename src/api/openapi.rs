@@ -0,0 +1,277 @@
+use axum::Json;
+use utoipa::{
+    Modify, OpenApi,
+    openapi::security::{HttpAuthScheme, HttpBuilder, SecurityScheme},
+};
+
+use crate::announcements::announcement::{Activity, Announcement, Motd, MotdSpec, Segment};
+use crate::anticheat::appeal::{Appeal, AppealResolution, AppealStatus};
+use crate::anticheat::quarantine::{Quarantine, QuarantineSpec};
+use crate::anticheat::trust::{SignalRecord, TrustReport};
+use crate::apikeys::key::{ApiKey, ApiKeySpec, IssuedApiKey, Scope};
+use crate::audit::record::AuditRecord;
+use crate::auth::identity::{GuestReply, IdentityProvider, LinkReply, LinkRequest, LinkedIdentity, SignInReply, SignInRequest};
+use crate::cluster::rpc::NodeHealth;
+use crate::content::bundles::ContentBundle;
+use crate::devices::device::Device;
+use crate::error::ErrorBody;
+use crate::experiments::experiment::{Experiment, ExperimentSpec, Variant};
+use crate::gateway::match_stats::MatchStats;
+use crate::gateway::state::{ConnectionInfo, LinkQuality};
+use crate::heatmap::sample::HeatmapTile;
+use crate::jobs::job::{Job, JobStatus};
+use crate::gateway::sse;
+use crate::matchmaking::fairness::{Distribution, FairnessGroup, FairnessReport};
+use crate::matchmaking::reconcile::{ReconcileReport, ScoreCorrection, ScoreTarget};
+use crate::matchmaking::review::{Adjustment, AdjustmentRequest, AuditEntry, MatchReview};
+use crate::matchmaking::verify::Anomaly;
+use crate::matchmaking::service::{Capabilities, Maintenance, Persistence};
+use crate::models::emote::{Audience, Emote};
+use crate::models::game::{DiscoveryScore, MatchStatus, MemberScore, Platform, PlatformBreakdown, TeamScore};
+use crate::models::message::ClientMessage;
+use crate::models::treasure::{Rarity, Treasure, TreasureSpec};
+use crate::models::zone::Zone;
+use crate::moderation::ban::{Ban, BanSpec};
+use crate::rating::calibration::{CalibrationBucket, CalibrationReport, DailyError};
+use crate::rating::mmr::{LeaderboardEntry, PlayerRating, Tier};
+use crate::remote_config::document::{ClientConfig, ConfigChange, ConfigUpdate, FieldChange};
+use crate::telemetry::event::{TelemetryEvent, TelemetryKind};
+use crate::telemetry::schema::{EventSchema, FieldSpec, FieldType, Rejection, Violation};
+use crate::telemetry::service::TelemetryAck;
+use super::{admin, admin_announcements, admin_api_keys, admin_bans, admin_bulk, admin_cluster, admin_devices, admin_events, admin_fairness, admin_heatmap, admin_maintenance, admin_ratings, admin_reviews, admin_scores, admin_treasures, admin_trust, auth, client_config, content, emotes, health, hooks, leaderboard, listing, matches, metrics, protocol, schedules, telemetry, zones};
+
+// OpenAPI document for the REST routes. Add new handlers to `paths` and
+// their request/response types to `schemas`.
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "SPV server", description = "REST routes of the PvP matchmaking server"),
+    paths(
+        health::healthz,
+        health::readyz,
+        metrics::metrics,
+        sse::sse_command,
+        protocol::protocol_json,
+        admin::list_matches,
+        admin::list_experiments,
+        admin::put_experiment,
+        admin::delete_experiment,
+        telemetry::ingest,
+        telemetry::list_schemas,
+        auth::sign_in,
+        auth::guest,
+        auth::link,
+        hooks::hasura_event,
+        client_config::get_client_config,
+        zones::list_zones,
+        leaderboard::get_leaderboard,
+        matches::get_match,
+        emotes::list_emotes,
+        content::get_content,
+        schedules::calendar,
+        admin::update_client_config,
+        admin::client_config_history,
+        admin_treasures::list_treasures,
+        admin_treasures::get_treasure,
+        admin_treasures::create_treasure,
+        admin_treasures::update_treasure,
+        admin_treasures::delete_treasure,
+        admin_trust::list_trust,
+        admin_trust::reset_trust,
+        admin_trust::adjust_trust,
+        admin_trust::list_appeals,
+        admin_trust::resolve_appeal,
+        admin_trust::list_quarantine,
+        admin_trust::quarantine_player,
+        admin_trust::release_player,
+        admin_heatmap::get_heatmap,
+        admin_scores::reconcile_scores,
+        admin_fairness::list_reports,
+        admin_fairness::compute_report,
+        admin_reviews::list_reviews,
+        admin_reviews::get_review,
+        admin_reviews::adjust_match,
+        admin_bans::list_bans,
+        admin_bans::ban_history,
+        admin_bans::issue_ban,
+        admin_bans::lift_ban,
+        admin_devices::list_devices,
+        admin_devices::revoke_device,
+        auth::list_identities,
+        admin_ratings::get_rating,
+        admin_ratings::list_smurfs,
+        admin_ratings::clear_smurf,
+        admin_ratings::get_calibration,
+        admin_announcements::get_motd,
+        admin_announcements::put_motd,
+        admin_announcements::delete_motd,
+        admin_announcements::list_announcements,
+        admin_announcements::cancel_announcement,
+        admin_api_keys::list_keys,
+        admin_api_keys::create_key,
+        admin_api_keys::rotate_key,
+        admin_api_keys::revoke_key,
+        admin_api_keys::list_audit,
+        admin_cluster::list_nodes,
+        admin_cluster::transfer_match,
+        admin::list_connections,
+        admin::end_match,
+        admin_maintenance::get_maintenance,
+        admin_maintenance::put_maintenance,
+        admin_events::tail_events,
+        admin_bulk::end_matches,
+        admin_bulk::ban_users,
+        admin_bulk::purge_matches,
+        admin_bulk::list_jobs,
+        admin_bulk::get_job,
+        admin_bulk::cancel_job,
+    ),
+    components(schemas(
+        ErrorBody,
+        listing::MatchStatsPage,
+        listing::ConnectionInfoPage,
+        listing::ExperimentPage,
+        listing::ConfigChangePage,
+        listing::TreasurePage,
+        listing::TrustReportPage,
+        listing::AppealPage,
+        listing::QuarantinePage,
+        listing::HeatmapTilePage,
+        listing::FairnessReportPage,
+        listing::MatchReviewPage,
+        listing::BanPage,
+        listing::DevicePage,
+        listing::LinkedIdentityPage,
+        listing::PlayerRatingPage,
+        listing::AnnouncementPage,
+        listing::ApiKeyPage,
+        listing::AuditRecordPage,
+        listing::NodeStatusPage,
+        listing::LeaderboardPage,
+        listing::JobPage,
+        health::ReadinessReport,
+        Capabilities,
+        Persistence,
+        ClientMessage,
+        MatchStats,
+        MatchStatus,
+        Platform,
+        PlatformBreakdown,
+        Experiment,
+        ExperimentSpec,
+        Variant,
+        telemetry::TelemetryBatch,
+        TelemetryEvent,
+        TelemetryKind,
+        TelemetryAck,
+        Rejection,
+        Violation,
+        EventSchema,
+        FieldSpec,
+        FieldType,
+        ClientConfig,
+        ConfigUpdate,
+        ConfigChange,
+        FieldChange,
+        Treasure,
+        TreasureSpec,
+        Rarity,
+        Zone,
+        Emote,
+        Audience,
+        ContentBundle,
+        TrustReport,
+        SignalRecord,
+        admin_trust::TrustAdjustment,
+        Appeal,
+        AppealStatus,
+        AppealResolution,
+        Quarantine,
+        QuarantineSpec,
+        HeatmapTile,
+        ReconcileReport,
+        ScoreCorrection,
+        ScoreTarget,
+        FairnessReport,
+        FairnessGroup,
+        Distribution,
+        MatchReview,
+        Anomaly,
+        TeamScore,
+        MemberScore,
+        DiscoveryScore,
+        Adjustment,
+        AdjustmentRequest,
+        AuditEntry,
+        Ban,
+        BanSpec,
+        Device,
+        IdentityProvider,
+        LinkedIdentity,
+        SignInRequest,
+        SignInReply,
+        GuestReply,
+        LinkRequest,
+        LinkReply,
+        PlayerRating,
+        LeaderboardEntry,
+        Tier,
+        CalibrationReport,
+        CalibrationBucket,
+        DailyError,
+        Motd,
+        MotdSpec,
+        Announcement,
+        Segment,
+        Activity,
+        ApiKey,
+        ApiKeySpec,
+        IssuedApiKey,
+        Scope,
+        AuditRecord,
+        admin_cluster::NodeStatus,
+        admin_cluster::TransferRequest,
+        NodeHealth,
+        ConnectionInfo,
+        LinkQuality,
+        Maintenance,
+        admin_maintenance::MaintenanceSpec,
+        admin_events::MatchEventSummary,
+        admin_bulk::EndMatchesRequest,
+        admin_bulk::BulkBanRequest,
+        admin_bulk::PurgeMatchesRequest,
+        Job,
+        JobStatus,
+    )),
+    modifiers(&AdminTokenScheme),
+    tags(
+        (name = "health", description = "Liveness and readiness probes"),
+        (name = "transport", description = "SSE fallback transport"),
+        (name = "docs", description = "Machine-readable protocol descriptions"),
+        (name = "admin", description = "Live ops, requires ADMIN_TOKEN or an API key"),
+        (name = "telemetry", description = "Client analytics ingestion"),
+        (name = "auth", description = "Sign-in with Google and Apple accounts"),
+        (name = "hooks", description = "Signed webhooks from Hasura event triggers"),
+        (name = "config", description = "Remote config for clients"),
+        (name = "matchmaking", description = "Matchmaking setup"),
+        (name = "game", description = "In-match content for clients"),
+    )
+)]
+pub struct ApiDoc;
+
+// Bearer auth used by the admin routes
+struct AdminTokenScheme;
+
+impl Modify for AdminTokenScheme {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        if let Some(components) = openapi.components.as_mut() {
+            components.add_security_scheme(
+                "admin_token",
+                SecurityScheme::Http(HttpBuilder::new().scheme(HttpAuthScheme::Bearer).build()),
+            );
+        }
+    }
+}
+
+pub async fn openapi_json() -> Json<utoipa::openapi::OpenApi> {
+    Json(ApiDoc::openapi())
+}
@@ -0,0 +1,15 @@
+use axum::Json;
+use serde_json::Value;
+
+use crate::gateway::protocol::protocol_document;
+
+// JSON Schemas for every WebSocket command and server event
+#[utoipa::path(
+    get,
+    path = "/api/protocol.json",
+    tag = "docs",
+    responses((status = 200, description = "Protocol JSON Schemas", body = Object))
+)]
+pub async fn protocol_json() -> Json<Value> {
+    Json(protocol_document())
+}
@@ -0,0 +1,27 @@
+use axum::{
+    extract::{Path, State},
+    http::header,
+    response::IntoResponse,
+};
+use uuid::Uuid;
+
+use crate::AppState;
+use crate::error::Result;
+
+// Calendar invite for a scheduled match. The link is sent with the
+// schedule.invited event; its unguessable id is all it takes, so calendar
+// apps can subscribe to it and pick up a cancellation.
+#[utoipa::path(
+    get,
+    path = "/api/schedules/{id}/calendar.ics",
+    tag = "game",
+    params(("id" = Uuid, Path, description = "Scheduled match id")),
+    responses(
+        (status = 200, description = "iCalendar (RFC 5545) event", body = String, content_type = "text/calendar"),
+        (status = 404, description = "No such scheduled match", body = ErrorBody)
+    )
+)]
+pub async fn calendar(State(state): State<AppState>, Path(id): Path<Uuid>) -> Result<impl IntoResponse> {
+    let invite = state.schedules.calendar(id).await?;
+    Ok(([(header::CONTENT_TYPE, "text/calendar; charset=utf-8")], invite))
+}
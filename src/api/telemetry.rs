@@ -0,0 +1,53 @@
+use axum::{
+    Json,
+    extract::State,
+    http::StatusCode,
+};
+use serde::Deserialize;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::AppState;
+use crate::error::{Error, Result};
+use crate::telemetry::event::TelemetryEvent;
+use crate::telemetry::schema::EventSchema;
+use crate::telemetry::service::TelemetryAck;
+
+// POST /api/telemetry body
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct TelemetryBatch {
+    // Set by signed-in clients
+    pub user_id: Option<Uuid>,
+    // At most TELEMETRY_MAX_BATCH events (100 by default)
+    pub events: Vec<TelemetryEvent>,
+}
+
+// Batched client telemetry (screen views, errors), for clients that buffer
+// events between sessions or report before opening a WebSocket
+#[utoipa::path(
+    post,
+    path = "/api/telemetry",
+    tag = "telemetry",
+    request_body = TelemetryBatch,
+    responses(
+        (status = 202, description = "Batch processed; per-event outcomes in the body", body = TelemetryAck),
+        (status = 400, description = "Malformed, empty or oversized batch", body = ErrorBody)
+    )
+)]
+pub async fn ingest(State(state): State<AppState>, body: String) -> Result<(StatusCode, Json<TelemetryAck>)> {
+    let batch: TelemetryBatch = serde_json::from_str(&body)
+        .map_err(|_| Error::InvalidMessage)?;
+    let ack = state.telemetry.submit(batch.user_id, "http", batch.events)?;
+    Ok((StatusCode::ACCEPTED, Json(ack)))
+}
+
+// Events the server accepts, from TELEMETRY_SCHEMA_PATH
+#[utoipa::path(
+    get,
+    path = "/api/telemetry/schema",
+    tag = "telemetry",
+    responses((status = 200, description = "Registered events; empty when any event is accepted", body = [EventSchema]))
+)]
+pub async fn list_schemas(State(state): State<AppState>) -> Json<Vec<EventSchema>> {
+    Json(state.telemetry.schemas())
+}
@@ -0,0 +1,15 @@
+use axum::{Json, extract::State};
+
+use crate::AppState;
+use crate::models::zone::Zone;
+
+// Map zones players can queue in (see match.start)
+#[utoipa::path(
+    get,
+    path = "/api/zones",
+    tag = "matchmaking",
+    responses((status = 200, description = "Zones; empty when matchmaking is not zoned", body = [Zone]))
+)]
+pub async fn list_zones(State(state): State<AppState>) -> Json<Vec<Zone>> {
+    Json(state.match_service.zones().list().await)
+}
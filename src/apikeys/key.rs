@@ -0,0 +1,107 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+// What an API key may be used for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, ToSchema)]
+pub enum Scope {
+    // GET admin routes
+    #[serde(rename = "admin:read")]
+    AdminRead,
+    // Every admin route; implies admin:read
+    #[serde(rename = "admin:write")]
+    AdminWrite,
+    // The gRPC API
+    #[serde(rename = "grpc")]
+    Grpc,
+}
+
+impl Scope {
+    pub fn to_str(self) -> &'static str {
+        match self {
+            Scope::AdminRead => "admin:read",
+            Scope::AdminWrite => "admin:write",
+            Scope::Grpc => "grpc",
+        }
+    }
+}
+
+// A service caller's credential. Only a SHA-256 hash of the key is stored;
+// the key itself is shown once, when it is created or rotated.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ApiKey {
+    pub id: Uuid,
+    // Caller the key belongs to, e.g. "billing-service"; recorded in the audit log
+    pub name: String,
+    // First characters of the key, to tell keys apart without the secret
+    pub prefix: String,
+    #[serde(skip_serializing)]
+    pub key_hash: String,
+    pub scopes: Vec<Scope>,
+    pub created_by: String,
+    pub created_at: DateTime<Utc>,
+    // Never expires when unset
+    pub expires_at: Option<DateTime<Utc>>,
+    pub revoked_at: Option<DateTime<Utc>>,
+    // Key that replaced this one when it was rotated
+    pub replaced_by: Option<Uuid>,
+}
+
+impl ApiKey {
+    pub fn is_active(&self, now: DateTime<Utc>) -> bool {
+        self.revoked_at.is_none() && self.expires_at.is_none_or(|expires_at| expires_at > now)
+    }
+}
+
+// POST /admin/api_keys body
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+pub struct ApiKeySpec {
+    pub name: String,
+    pub scopes: Vec<Scope>,
+    pub created_by: String,
+    // Never expires when unset
+    pub expires_in_secs: Option<u64>,
+}
+
+// A new key with its secret, which can't be retrieved later
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct IssuedApiKey {
+    pub key: ApiKey,
+    // Send as `Authorization: Bearer <secret>`
+    pub secret: String,
+}
+
+// Who is calling an admin route or the gRPC API
+#[derive(Debug, Clone)]
+pub enum Caller {
+    // Holder of ADMIN_TOKEN
+    Root,
+    Service { key_id: Uuid, name: String, scopes: Vec<Scope> },
+}
+
+impl Caller {
+    pub fn allows(&self, scope: Scope) -> bool {
+        match self {
+            Caller::Root => true,
+            Caller::Service { scopes, .. } => {
+                scopes.contains(&scope) || (scope == Scope::AdminRead && scopes.contains(&Scope::AdminWrite))
+            }
+        }
+    }
+
+    // As recorded in the audit log
+    pub fn actor(&self) -> String {
+        match self {
+            Caller::Root => "admin".to_string(),
+            Caller::Service { name, .. } => format!("key:{}", name),
+        }
+    }
+
+    pub fn key_id(&self) -> Option<Uuid> {
+        match self {
+            Caller::Root => None,
+            Caller::Service { key_id, .. } => Some(*key_id),
+        }
+    }
+}
@@ -0,0 +1,175 @@
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use rand::Rng;
+use rand::distributions::Alphanumeric;
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+use crate::api::admin::verify_token;
+use crate::config::AdminConfig;
+use crate::db::repository::ApiKeyRepository;
+use crate::error::{Error, Result};
+use crate::supervisor;
+use super::key::{ApiKey, ApiKeySpec, Caller, IssuedApiKey, Scope};
+
+// Every key starts with this, which tells keys apart from ADMIN_TOKEN
+const KEY_PREFIX: &str = "spv_";
+// Characters of the key kept in clear as `prefix`
+const SHOWN_LEN: usize = 12;
+const SECRET_LEN: usize = 40;
+// How often active keys are reloaded, so keys created or revoked on another
+// instance are picked up
+const REFRESH_INTERVAL: Duration = Duration::from_secs(30);
+
+fn hash_key(key: &str) -> String {
+    hex::encode(Sha256::digest(key.as_bytes()))
+}
+
+// API keys for service callers of the admin API and gRPC.
+//
+// Active keys are cached in memory by hash, so authenticating a request needs
+// no round trip. Rotating a key issues a replacement with the same name and
+// scopes, and lets the old key keep working for API_KEY_ROTATION_GRACE_SECS
+// so callers can switch over.
+pub struct ApiKeyService {
+    repo: Arc<dyn ApiKeyRepository>,
+    rotation_grace: Duration,
+    active: RwLock<HashMap<String, ApiKey>>,
+}
+
+impl ApiKeyService {
+    pub async fn init(repo: Arc<dyn ApiKeyRepository>, rotation_grace: Duration) -> Arc<Self> {
+        let service = Arc::new(Self {
+            repo,
+            rotation_grace,
+            active: RwLock::new(HashMap::new()),
+        });
+        if let Err(e) = service.reload().await {
+            tracing::warn!("Failed to load API keys, starting without any: {}", e);
+        }
+
+        let refresher = service.clone();
+        supervisor::spawn_loop("api_key_refresh", move || {
+            let refresher = refresher.clone();
+            async move {
+                let mut interval = tokio::time::interval(REFRESH_INTERVAL);
+                interval.tick().await;
+                loop {
+                    interval.tick().await;
+                    if let Err(e) = refresher.reload().await {
+                        tracing::warn!("Failed to refresh API keys: {}", e);
+                    }
+                }
+            }
+        });
+        service
+    }
+
+    async fn reload(&self) -> Result<()> {
+        let loaded: HashMap<String, ApiKey> = self.repo.active_keys(Utc::now()).await?
+            .into_iter()
+            .map(|key| (key.key_hash.clone(), key))
+            .collect();
+        *self.active.write().unwrap() = loaded;
+        Ok(())
+    }
+
+    // The caller presenting this bearer token: an API key, or ADMIN_TOKEN
+    pub fn authenticate(&self, admin: &AdminConfig, token: &str) -> Result<Caller> {
+        if !token.starts_with(KEY_PREFIX) {
+            verify_token(admin, token)?;
+            return Ok(Caller::Root);
+        }
+        let active = self.active.read().unwrap();
+        let key = active.get(&hash_key(token))
+            .filter(|key| key.is_active(Utc::now()))
+            .ok_or(Error::AuthError)?;
+        Ok(Caller::Service {
+            key_id: key.id,
+            name: key.name.clone(),
+            scopes: key.scopes.clone(),
+        })
+    }
+
+    pub async fn list(&self) -> Result<Vec<ApiKey>> {
+        self.repo.list_keys().await
+    }
+
+    pub async fn create(&self, spec: ApiKeySpec) -> Result<IssuedApiKey> {
+        if spec.name.trim().is_empty() || spec.created_by.trim().is_empty() || spec.scopes.is_empty() {
+            return Err(Error::InvalidMessage);
+        }
+        if spec.expires_in_secs == Some(0) {
+            return Err(Error::InvalidMessage);
+        }
+        let now = Utc::now();
+        let expires_at = spec.expires_in_secs.map(|secs| now + chrono::Duration::seconds(secs as i64));
+        let (key, secret) = new_key(spec.name.trim(), spec.scopes, spec.created_by.trim(), expires_at);
+        self.repo.insert_key(&key).await?;
+        self.active.write().unwrap().insert(key.key_hash.clone(), key.clone());
+        tracing::info!("API key {} ({}) created by {}", key.name, key.prefix, key.created_by);
+        Ok(IssuedApiKey { key, secret })
+    }
+
+    // Issue a replacement with the same name, scopes and lifetime. The old key
+    // expires after the grace period, or sooner if it was about to.
+    pub async fn rotate(&self, id: Uuid, rotated_by: &str) -> Result<IssuedApiKey> {
+        if rotated_by.trim().is_empty() {
+            return Err(Error::InvalidMessage);
+        }
+        let now = Utc::now();
+        let old = self.repo.get_key(id).await?;
+        if !old.is_active(now) {
+            return Err(Error::NotFound(format!("active API key {}", id)));
+        }
+
+        let lifetime = old.expires_at.map(|expires_at| expires_at - old.created_at);
+        let (key, secret) = new_key(&old.name, old.scopes.clone(), rotated_by.trim(), lifetime.map(|lifetime| now + lifetime));
+        let grace_ends = now + chrono::Duration::from_std(self.rotation_grace).unwrap_or_default();
+        let old_expires_at = old.expires_at.map_or(grace_ends, |expires_at| expires_at.min(grace_ends));
+        self.repo.rotate_key(id, old_expires_at, &key).await?;
+
+        {
+            let mut active = self.active.write().unwrap();
+            if let Some(cached) = active.get_mut(&old.key_hash) {
+                cached.expires_at = Some(old_expires_at);
+                cached.replaced_by = Some(key.id);
+            }
+            active.insert(key.key_hash.clone(), key.clone());
+        }
+        tracing::info!("API key {} ({}) rotated by {}; the old key expires at {}", key.name, key.prefix, key.created_by, old_expires_at);
+        Ok(IssuedApiKey { key, secret })
+    }
+
+    pub async fn revoke(&self, id: Uuid) -> Result<()> {
+        self.repo.revoke_key(id, Utc::now()).await?;
+        self.active.write().unwrap().retain(|_, key| key.id != id);
+        tracing::info!("API key {} revoked", id);
+        Ok(())
+    }
+}
+
+fn new_key(name: &str, scopes: Vec<Scope>, created_by: &str, expires_at: Option<DateTime<Utc>>) -> (ApiKey, String) {
+    let random: String = rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(SECRET_LEN)
+        .map(char::from)
+        .collect();
+    let secret = format!("{}{}", KEY_PREFIX, random);
+    let key = ApiKey {
+        id: Uuid::new_v4(),
+        name: name.to_string(),
+        prefix: secret[..SHOWN_LEN].to_string(),
+        key_hash: hash_key(&secret),
+        scopes,
+        created_by: created_by.to_string(),
+        created_at: Utc::now(),
+        expires_at,
+        revoked_at: None,
+        replaced_by: None,
+    };
+    (key, secret)
+}
@@ -0,0 +1,18 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+// One change made through the admin API or gRPC, and by whom
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct AuditRecord {
+    pub id: Uuid,
+    // "admin" for ADMIN_TOKEN, "key:<name>" for an API key
+    pub actor: String,
+    pub api_key_id: Option<Uuid>,
+    // e.g. "PUT /admin/bans/<user id>" or "grpc ForceEndMatch"
+    pub action: String,
+    // HTTP status of the reply; 200 for successful gRPC calls
+    pub status: i32,
+    pub created_at: DateTime<Utc>,
+}
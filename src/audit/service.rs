@@ -0,0 +1,46 @@
+use std::sync::Arc;
+
+use chrono::Utc;
+use uuid::Uuid;
+
+use crate::apikeys::key::Caller;
+use crate::db::keyset::KeysetQuery;
+use crate::db::repository::AuditRepository;
+use crate::error::Result;
+use super::record::AuditRecord;
+
+// Log of the changes made through the admin API and gRPC, attributed to the
+// admin token or to the API key that made them. Reads aren't recorded.
+pub struct AuditLog {
+    repo: Arc<dyn AuditRepository>,
+}
+
+impl AuditLog {
+    pub fn new(repo: Arc<dyn AuditRepository>) -> Arc<Self> {
+        Arc::new(Self { repo })
+    }
+
+    // Written in the background; a failed write is only logged
+    pub fn record(&self, caller: &Caller, action: String, status: u16) {
+        let record = AuditRecord {
+            id: Uuid::new_v4(),
+            actor: caller.actor(),
+            api_key_id: caller.key_id(),
+            action,
+            status: status as i32,
+            created_at: Utc::now(),
+        };
+        let repo = self.repo.clone();
+        tokio::spawn(async move {
+            if let Err(e) = repo.insert_audit(&record).await {
+                tracing::warn!("Failed to write audit record {} by {}: {}", record.action, record.actor, e);
+            }
+        });
+    }
+
+    // One page of the log, optionally only one actor's, and how many records
+    // match its filters
+    pub async fn page(&self, actor: Option<&str>, page: &KeysetQuery) -> Result<(Vec<AuditRecord>, usize)> {
+        self.repo.audit_page(actor, page).await
+    }
+}
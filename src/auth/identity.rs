@@ -0,0 +1,116 @@
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+// External identity provider a player can sign in with
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, JsonSchema, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum IdentityProvider {
+    Google,
+    Apple,
+}
+
+impl IdentityProvider {
+    pub fn to_str(self) -> &'static str {
+        match self {
+            IdentityProvider::Google => "google",
+            IdentityProvider::Apple => "apple",
+        }
+    }
+
+    // Keys the provider signs its ID tokens with
+    pub fn jwks_url(&self) -> &'static str {
+        match self {
+            IdentityProvider::Google => "https://www.googleapis.com/oauth2/v3/certs",
+            IdentityProvider::Apple => "https://appleid.apple.com/auth/keys",
+        }
+    }
+
+    // Accepted `iss` claims
+    pub fn issuers(&self) -> &'static [&'static str] {
+        match self {
+            IdentityProvider::Google => &["https://accounts.google.com", "accounts.google.com"],
+            IdentityProvider::Apple => &["https://appleid.apple.com"],
+        }
+    }
+}
+
+// A provider account linked to a player. Each provider account belongs to one
+// player; a player may link one account per provider.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct LinkedIdentity {
+    pub provider: IdentityProvider,
+    // `sub` claim: the provider's stable id of the account
+    pub subject: String,
+    pub user_id: Uuid,
+    // As the provider reported it when linking; Apple only sends it once
+    pub email: Option<String>,
+    pub linked_at: DateTime<Utc>,
+}
+
+// Claims of a verified ID token
+#[derive(Debug, Clone)]
+pub struct ExternalIdentity {
+    pub provider: IdentityProvider,
+    pub subject: String,
+    pub email: Option<String>,
+}
+
+// A guest merged into an existing account when it linked that account's
+// identity. Only the guest's data moves; the account keeps its id.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AccountMerge {
+    pub from_user_id: Uuid,
+    pub into_user_id: Uuid,
+    pub provider: IdentityProvider,
+    pub merged_at: DateTime<Utc>,
+}
+
+// POST /api/auth/sign_in body
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+pub struct SignInRequest {
+    pub provider: IdentityProvider,
+    // ID token from the provider's client SDK
+    pub id_token: String,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct SignInReply {
+    pub user_id: Uuid,
+    // No player had linked this identity; a new account was created
+    pub created: bool,
+    // Connects to /ws and /sse as the player, with ?token=
+    pub session_token: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+// POST /api/auth/guest reply: a new guest player
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct GuestReply {
+    pub user_id: Uuid,
+    pub session_token: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+// POST /api/auth/link body
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+pub struct LinkRequest {
+    // Session token of the player linking the identity, usually a guest
+    pub session_token: String,
+    pub provider: IdentityProvider,
+    pub id_token: String,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct LinkReply {
+    // Account to use from now on: the requesting player, or the account the
+    // guest was merged into
+    pub user_id: Uuid,
+    // Set when the identity already belonged to another account
+    pub merged_from: Option<Uuid>,
+    // Session of `user_id`; the guest's token stays valid until it expires
+    pub session_token: String,
+    pub expires_at: DateTime<Utc>,
+}
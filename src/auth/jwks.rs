@@ -0,0 +1,109 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use jsonwebtoken::jwk::JwkSet;
+use jsonwebtoken::{Algorithm, DecodingKey, Validation};
+use serde::Deserialize;
+use tokio::sync::Mutex;
+
+use crate::error::{Error, Result};
+use super::identity::{ExternalIdentity, IdentityProvider};
+
+// Providers rotate their keys every few days, announcing new ones in advance
+const KEYS_TTL: Duration = Duration::from_secs(3600);
+// A token signed with an unknown key refetches the keys at most this often
+const REFETCH_INTERVAL: Duration = Duration::from_secs(60);
+const FETCH_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Deserialize)]
+struct IdTokenClaims {
+    sub: String,
+    email: Option<String>,
+}
+
+struct CachedKeys {
+    keys: JwkSet,
+    fetched_at: Instant,
+}
+
+// Signing keys of the identity providers, fetched on first use and cached
+pub struct KeyCache {
+    http: reqwest::Client,
+    keys: Mutex<HashMap<IdentityProvider, CachedKeys>>,
+}
+
+impl KeyCache {
+    pub fn new() -> Self {
+        let http = reqwest::Client::builder()
+            .timeout(FETCH_TIMEOUT)
+            .build()
+            .unwrap_or_default();
+        Self {
+            http,
+            keys: Mutex::new(HashMap::new()),
+        }
+    }
+
+    // Check the ID token's signature, issuer, audience and expiry. Any
+    // failure is `Error::AuthError`, except the provider being unreachable.
+    pub async fn verify(&self, provider: IdentityProvider, id_token: &str, audiences: &[String]) -> Result<ExternalIdentity> {
+        let header = jsonwebtoken::decode_header(id_token).map_err(|_| Error::AuthError)?;
+        let kid = header.kid.ok_or(Error::AuthError)?;
+        let key = self.decoding_key(provider, &kid).await?;
+
+        let mut validation = Validation::new(Algorithm::RS256);
+        validation.set_audience(audiences);
+        validation.set_issuer(provider.issuers());
+        let claims = jsonwebtoken::decode::<IdTokenClaims>(id_token, &key, &validation)
+            .map_err(|e| {
+                tracing::debug!("Rejected {} ID token: {}", provider.to_str(), e);
+                Error::AuthError
+            })?
+            .claims;
+
+        Ok(ExternalIdentity {
+            provider,
+            subject: claims.sub,
+            email: claims.email,
+        })
+    }
+
+    async fn decoding_key(&self, provider: IdentityProvider, kid: &str) -> Result<DecodingKey> {
+        let mut keys = self.keys.lock().await;
+        let stale = match keys.get(&provider) {
+            None => true,
+            Some(cached) if cached.fetched_at.elapsed() > KEYS_TTL => true,
+            // Possibly a key published after the last fetch
+            Some(cached) => cached.keys.find(kid).is_none() && cached.fetched_at.elapsed() > REFETCH_INTERVAL,
+        };
+        if stale {
+            match self.fetch(provider).await {
+                Ok(fetched) => {
+                    keys.insert(provider, CachedKeys { keys: fetched, fetched_at: Instant::now() });
+                }
+                // Keep verifying with the keys we have
+                Err(e) if keys.contains_key(&provider) => {
+                    tracing::warn!("Failed to refresh {} signing keys: {}", provider.to_str(), e);
+                }
+                Err(e) => return Err(e),
+            }
+        }
+
+        let jwk = keys.get(&provider)
+            .and_then(|cached| cached.keys.find(kid))
+            .ok_or(Error::AuthError)?;
+        DecodingKey::from_jwk(jwk).map_err(|_| Error::AuthError)
+    }
+
+    async fn fetch(&self, provider: IdentityProvider) -> Result<JwkSet> {
+        let unavailable = |e: reqwest::Error| Error::IdentityProviderUnavailable(format!("{}: {}", provider.to_str(), e));
+        self.http.get(provider.jwks_url())
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(unavailable)?
+            .json::<JwkSet>()
+            .await
+            .map_err(unavailable)
+    }
+}
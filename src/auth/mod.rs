@@ -0,0 +1,4 @@
+pub mod identity;
+pub mod jwks;
+pub mod service;
+pub mod session;
\ No newline at end of file
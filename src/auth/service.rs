@@ -0,0 +1,155 @@
+use std::sync::Arc;
+
+use chrono::Utc;
+use uuid::Uuid;
+
+use crate::config::AuthConfig;
+use crate::db::repository::IdentityRepository;
+use crate::error::{Error, Result};
+use crate::matchmaking::service::MatchService;
+use super::identity::{AccountMerge, ExternalIdentity, GuestReply, IdentityProvider, LinkReply, LinkedIdentity, SignInReply};
+use super::jwks::KeyCache;
+use super::session::SessionTokens;
+
+// Sign-in with Google and Apple accounts.
+//
+// Clients get an ID token from the provider's SDK and exchange it for the
+// player's user id and a session token. The first sign-in with an account
+// creates a new player. Guests (players who never signed in) get a session
+// of their own, and keep their user id when they link an account with it;
+// if that account already belongs to another player, the guest is merged
+// into it: the guest's match history, rating and devices move to the
+// account, and the client continues as the account.
+pub struct AuthService {
+    config: AuthConfig,
+    repo: Arc<dyn IdentityRepository>,
+    match_service: Arc<MatchService>,
+    keys: KeyCache,
+    sessions: SessionTokens,
+}
+
+impl AuthService {
+    pub fn new(config: AuthConfig, repo: Arc<dyn IdentityRepository>, match_service: Arc<MatchService>) -> Arc<Self> {
+        let sessions = SessionTokens::new(config.session_secret.as_deref(), config.session_ttl);
+        Arc::new(Self {
+            config,
+            repo,
+            match_service,
+            keys: KeyCache::new(),
+            sessions,
+        })
+    }
+
+    // The player a session token was issued to
+    pub fn session_user(&self, token: &str) -> Result<Uuid> {
+        self.sessions.verify(token)
+    }
+
+    // A session for a new guest; nothing is stored until the guest plays
+    pub fn guest(&self) -> GuestReply {
+        let user_id = Uuid::new_v4();
+        let (session_token, expires_at) = self.sessions.issue(user_id);
+        GuestReply { user_id, session_token, expires_at }
+    }
+
+    // A session for a known player, for the test users of local mode
+    pub fn session_for(&self, user_id: Uuid) -> String {
+        self.sessions.issue(user_id).0
+    }
+
+    async fn verify(&self, provider: IdentityProvider, id_token: &str) -> Result<ExternalIdentity> {
+        let audiences = match provider {
+            IdentityProvider::Google => &self.config.google_client_ids,
+            IdentityProvider::Apple => &self.config.apple_client_ids,
+        };
+        if audiences.is_empty() {
+            return Err(Error::PermissionDenied(format!("sign-in with {} is not enabled", provider.to_str())));
+        }
+        self.keys.verify(provider, id_token, audiences).await
+    }
+
+    // The player the identity is linked to, creating one on first sign-in
+    pub async fn sign_in(&self, provider: IdentityProvider, id_token: &str) -> Result<SignInReply> {
+        let external = self.verify(provider, id_token).await?;
+        if let Some(identity) = self.repo.find_identity(provider, &external.subject).await? {
+            return Ok(self.signed_in(identity.user_id, false));
+        }
+
+        let identity = linked(&external, Uuid::new_v4());
+        match self.repo.insert_identity(&identity).await {
+            Ok(()) => {
+                tracing::info!("Created player {} for a {} account", identity.user_id, provider.to_str());
+                Ok(self.signed_in(identity.user_id, true))
+            }
+            // A concurrent first sign-in with the same account won
+            Err(Error::DuplicateKey(_)) => {
+                let identity = self.repo.find_identity(provider, &external.subject).await?
+                    .ok_or_else(|| Error::DbError("identity vanished after a conflicting insert".to_string()))?;
+                Ok(self.signed_in(identity.user_id, false))
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    fn signed_in(&self, user_id: Uuid, created: bool) -> SignInReply {
+        let (session_token, expires_at) = self.sessions.issue(user_id);
+        SignInReply { user_id, created, session_token, expires_at }
+    }
+
+    // Link the identity to the player holding the session. An identity
+    // already linked to another account merges the player into it, provided
+    // the player is a guest.
+    pub async fn link(&self, session_token: &str, provider: IdentityProvider, id_token: &str) -> Result<LinkReply> {
+        let user_id = self.sessions.verify(session_token)?;
+        let external = self.verify(provider, id_token).await?;
+        let owner = match self.repo.find_identity(provider, &external.subject).await? {
+            None => {
+                self.repo.insert_identity(&linked(&external, user_id)).await?;
+                tracing::info!("Player {} linked a {} account", user_id, provider.to_str());
+                return Ok(self.linked_reply(user_id, None));
+            }
+            Some(identity) if identity.user_id == user_id => {
+                return Ok(self.linked_reply(user_id, None));
+            }
+            Some(identity) => identity.user_id,
+        };
+
+        // Two accounts with identities of their own are never merged
+        if !self.repo.user_identities(user_id).await?.is_empty() {
+            return Err(Error::DuplicateKey(format!("this {} account is linked to another player", provider.to_str())));
+        }
+        // Live match state is keyed by user id and isn't moved
+        if self.match_service.active_match_for_user(user_id).await?.is_some() {
+            return Err(Error::UserAlreadyInMatch);
+        }
+
+        let merge = AccountMerge {
+            from_user_id: user_id,
+            into_user_id: owner,
+            provider,
+            merged_at: Utc::now(),
+        };
+        self.repo.merge_accounts(&merge).await?;
+        tracing::info!("Merged guest {} into player {} ({} account)", user_id, owner, provider.to_str());
+        Ok(self.linked_reply(owner, Some(user_id)))
+    }
+
+    fn linked_reply(&self, user_id: Uuid, merged_from: Option<Uuid>) -> LinkReply {
+        let (session_token, expires_at) = self.sessions.issue(user_id);
+        LinkReply { user_id, merged_from, session_token, expires_at }
+    }
+
+    pub async fn identities(&self, user_id: Uuid) -> Result<Vec<LinkedIdentity>> {
+        self.repo.user_identities(user_id).await
+    }
+}
+
+fn linked(external: &ExternalIdentity, user_id: Uuid) -> LinkedIdentity {
+    LinkedIdentity {
+        provider: external.provider,
+        subject: external.subject.clone(),
+        user_id,
+        email: external.email.clone(),
+        linked_at: Utc::now(),
+    }
+}
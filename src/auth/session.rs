@@ -0,0 +1,100 @@
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use uuid::Uuid;
+
+use crate::error::{Error, Result};
+
+// Signs the resume tokens of the handshake too; the label keeps one kind of
+// token from passing for the other when both secrets are the same
+const LABEL: &str = "session";
+
+// Session tokens, the proof of which player a client is.
+//
+// Clients get one from sign-in, from POST /api/auth/guest or from linking an
+// account, and present it to /ws, /sse and POST /api/auth/link; the player
+// is always taken from the token, never from a user id the client sends. A
+// token is `<expires_at>.<user_id>.<signature>`, an HMAC-SHA256 with
+// SESSION_TOKEN_SECRET. Without a secret each process signs with a random
+// key, so tokens only work on the node that issued them until it restarts.
+pub struct SessionTokens {
+    secret: Vec<u8>,
+    ttl: Duration,
+}
+
+impl SessionTokens {
+    pub fn new(secret: Option<&str>, ttl: Duration) -> Self {
+        let secret = match secret {
+            Some(secret) => secret.as_bytes().to_vec(),
+            None => rand::random::<[u8; 32]>().to_vec(),
+        };
+        Self { secret, ttl }
+    }
+
+    // A token for the player and when it expires
+    pub fn issue(&self, user_id: Uuid) -> (String, DateTime<Utc>) {
+        let expires_at = Utc::now() + chrono::Duration::from_std(self.ttl).unwrap_or_default();
+        let payload = format!("{}.{}", expires_at.timestamp(), user_id);
+        let signature = hex::encode(self.mac(&payload).finalize().into_bytes());
+        (format!("{}.{}", payload, signature), expires_at)
+    }
+
+    // The player the token was issued to, if this server signed it and it
+    // hasn't expired
+    pub fn verify(&self, token: &str) -> Result<Uuid> {
+        let (payload, signature) = token.rsplit_once('.').ok_or(Error::AuthError)?;
+        let signature = hex::decode(signature).map_err(|_| Error::AuthError)?;
+        self.mac(payload).verify_slice(&signature).map_err(|_| Error::AuthError)?;
+
+        let (expires_at, user_id) = payload.split_once('.').ok_or(Error::AuthError)?;
+        let expires_at = expires_at.parse::<i64>().map_err(|_| Error::AuthError)?;
+        if expires_at <= Utc::now().timestamp() {
+            return Err(Error::AuthError);
+        }
+        user_id.parse::<Uuid>().map_err(|_| Error::AuthError)
+    }
+
+    fn mac(&self, payload: &str) -> Hmac<Sha256> {
+        let mut mac = Hmac::<Sha256>::new_from_slice(&self.secret)
+            .expect("HMAC accepts keys of any length");
+        mac.update(LABEL.as_bytes());
+        mac.update(b":");
+        mac.update(payload.as_bytes());
+        mac
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tokens_verify_for_the_player_they_were_issued_to() {
+        let tokens = SessionTokens::new(Some("secret"), Duration::from_secs(60));
+        let user_id = Uuid::new_v4();
+        let (token, _) = tokens.issue(user_id);
+        assert_eq!(tokens.verify(&token).unwrap(), user_id);
+    }
+
+    #[test]
+    fn forged_expired_and_foreign_tokens_are_refused() {
+        let tokens = SessionTokens::new(Some("secret"), Duration::from_secs(60));
+        let (token, _) = tokens.issue(Uuid::new_v4());
+
+        // Swapping in another player's id breaks the signature
+        let (payload, signature) = token.rsplit_once('.').unwrap();
+        let (expires_at, _) = payload.split_once('.').unwrap();
+        let forged = format!("{}.{}.{}", expires_at, Uuid::new_v4(), signature);
+        assert!(tokens.verify(&forged).is_err());
+        assert!(tokens.verify("not a token").is_err());
+
+        let other = SessionTokens::new(Some("other secret"), Duration::from_secs(60));
+        assert!(other.verify(&token).is_err());
+
+        let expired = SessionTokens::new(Some("secret"), Duration::ZERO);
+        let (token, _) = expired.issue(Uuid::new_v4());
+        assert!(expired.verify(&token).is_err());
+    }
+}
@@ -0,0 +1,442 @@
+// Generates a typed TypeScript client from the protocol description served at
+// /api/protocol.json (see gateway::protocol).
+//
+// Usage:
+//   cargo run --bin gen-client -- [--schema <url or file>] [--out <file>]
+//
+// --schema defaults to http://localhost:3000/api/protocol.json, --out to stdout.
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use serde_json::{Map, Value};
+
+const DEFAULT_SCHEMA: &str = "http://localhost:3000/api/protocol.json";
+
+#[tokio::main]
+async fn main() {
+    let mut schema_source = DEFAULT_SCHEMA.to_string();
+    let mut out_path: Option<String> = None;
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--schema" => schema_source = args.next().unwrap_or_else(|| usage()),
+            "--out" => out_path = Some(args.next().unwrap_or_else(|| usage())),
+            _ => usage(),
+        }
+    }
+
+    let document = match load_document(&schema_source).await {
+        Ok(doc) => doc,
+        Err(e) => {
+            eprintln!("Failed to load protocol from {}: {}", schema_source, e);
+            std::process::exit(1);
+        }
+    };
+
+    let source = match generate(&document) {
+        Ok(source) => source,
+        Err(e) => {
+            eprintln!("Invalid protocol document: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    match out_path {
+        Some(path) => {
+            if let Err(e) = std::fs::write(&path, source) {
+                eprintln!("Failed to write {}: {}", path, e);
+                std::process::exit(1);
+            }
+            eprintln!("Wrote {}", path);
+        }
+        None => print!("{}", source),
+    }
+}
+
+fn usage() -> ! {
+    eprintln!("usage: gen-client [--schema <url or file>] [--out <file>]");
+    std::process::exit(2);
+}
+
+async fn load_document(source: &str) -> Result<Value, String> {
+    let text = if source.starts_with("http://") || source.starts_with("https://") {
+        reqwest::get(source)
+            .await
+            .and_then(|r| r.error_for_status())
+            .map_err(|e| e.to_string())?
+            .text()
+            .await
+            .map_err(|e| e.to_string())?
+    } else {
+        std::fs::read_to_string(source).map_err(|e| e.to_string())?
+    };
+    serde_json::from_str(&text).map_err(|e| e.to_string())
+}
+
+// Collects named TypeScript declarations while converting schemas
+#[derive(Default)]
+struct TypeScriptWriter {
+    declarations: BTreeMap<String, String>,
+}
+
+impl TypeScriptWriter {
+    // Registers a root schema under `name` and returns the TypeScript type name
+    fn add_root(&mut self, name: &str, schema: &Value) -> String {
+        if let Some(definitions) = schema.get("definitions").and_then(Value::as_object) {
+            for (def_name, def) in definitions {
+                if !self.declarations.contains_key(def_name) {
+                    let ts = self.type_of(def);
+                    self.declarations.insert(def_name.clone(), ts);
+                }
+            }
+        }
+        let ts = self.type_of(schema);
+        self.declarations.insert(name.to_string(), ts);
+        name.to_string()
+    }
+
+    fn type_of(&mut self, schema: &Value) -> String {
+        // `true` / `{}` accept anything
+        let obj = match schema.as_object() {
+            Some(obj) if !obj.is_empty() => obj,
+            _ => return "unknown".to_string(),
+        };
+
+        if let Some(reference) = obj.get("$ref").and_then(Value::as_str) {
+            return reference.rsplit('/').next().unwrap_or("unknown").to_string();
+        }
+        if let Some(values) = obj.get("enum").and_then(Value::as_array) {
+            return values.iter().map(|v| v.to_string()).collect::<Vec<_>>().join(" | ");
+        }
+        if let Some(constant) = obj.get("const") {
+            return constant.to_string();
+        }
+        for key in ["anyOf", "oneOf"] {
+            if let Some(variants) = obj.get(key).and_then(Value::as_array) {
+                return self.join(variants, " | ");
+            }
+        }
+        if let Some(parts) = obj.get("allOf").and_then(Value::as_array) {
+            return self.join(parts, " & ");
+        }
+
+        match obj.get("type") {
+            Some(Value::String(t)) => self.primitive(t, obj),
+            // ["string", "null"] style nullable types
+            Some(Value::Array(types)) => types
+                .iter()
+                .filter_map(Value::as_str)
+                .map(|t| self.primitive(t, obj))
+                .collect::<Vec<_>>()
+                .join(" | "),
+            _ => "unknown".to_string(),
+        }
+    }
+
+    fn join(&mut self, schemas: &[Value], separator: &str) -> String {
+        let parts: Vec<String> = schemas.iter().map(|s| self.type_of(s)).collect();
+        if parts.len() > 1 {
+            format!("({})", parts.join(separator))
+        } else {
+            parts.join(separator)
+        }
+    }
+
+    fn primitive(&mut self, t: &str, obj: &Map<String, Value>) -> String {
+        match t {
+            "string" => "string".to_string(),
+            "integer" | "number" => "number".to_string(),
+            "boolean" => "boolean".to_string(),
+            "null" => "null".to_string(),
+            "array" => match obj.get("items") {
+                Some(items) => format!("Array<{}>", self.type_of(items)),
+                None => "unknown[]".to_string(),
+            },
+            "object" => self.object(obj),
+            _ => "unknown".to_string(),
+        }
+    }
+
+    fn object(&mut self, obj: &Map<String, Value>) -> String {
+        let properties = match obj.get("properties").and_then(Value::as_object) {
+            Some(p) => p,
+            None => {
+                return match obj.get("additionalProperties") {
+                    Some(Value::Object(values)) if !values.is_empty() => {
+                        let values = Value::Object(values.clone());
+                        format!("Record<string, {}>", self.type_of(&values))
+                    }
+                    _ => "Record<string, unknown>".to_string(),
+                };
+            }
+        };
+        let required: Vec<&str> = obj
+            .get("required")
+            .and_then(Value::as_array)
+            .map(|r| r.iter().filter_map(Value::as_str).collect())
+            .unwrap_or_default();
+
+        let mut out = String::from("{\n");
+        for (name, prop) in properties {
+            let optional = if required.contains(&name.as_str()) { "" } else { "?" };
+            let _ = writeln!(out, "  {}{}: {};", name, optional, self.type_of(prop));
+        }
+        out.push('}');
+        out
+    }
+}
+
+// "match.start" -> "MatchStart"
+fn pascal_case(name: &str) -> String {
+    name.split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|part| !part.is_empty())
+        .map(|part| {
+            let mut chars = part.chars();
+            match chars.next() {
+                Some(first) => first.to_ascii_uppercase().to_string() + chars.as_str(),
+                None => String::new(),
+            }
+        })
+        .collect()
+}
+
+fn generate(document: &Value) -> Result<String, String> {
+    let commands = document
+        .get("commands")
+        .and_then(Value::as_object)
+        .ok_or("missing `commands`")?;
+    let events = document
+        .get("events")
+        .and_then(Value::as_object)
+        .ok_or("missing `events`")?;
+
+    let mut writer = TypeScriptWriter::default();
+
+    // cmd -> (request type, reply type)
+    let mut command_types = Vec::new();
+    for (cmd, spec) in commands {
+        let base = pascal_case(cmd);
+        let request = spec.get("request").ok_or_else(|| format!("`{}` has no request schema", cmd))?;
+        let request_type = writer.add_root(&format!("{}Request", base), request);
+        // Fire-and-forget commands have a null reply
+        let reply_type = match spec.get("reply") {
+            Some(Value::Null) => "void".to_string(),
+            Some(reply) => writer.add_root(&format!("{}Reply", base), reply),
+            None => return Err(format!("`{}` has no reply schema", cmd)),
+        };
+        command_types.push((cmd.clone(), request_type, reply_type));
+    }
+
+    // event name -> payload type
+    let mut event_types = Vec::new();
+    for (event, schema) in events {
+        let payload_type = writer.add_root(&format!("{}Event", pascal_case(event)), schema);
+        event_types.push((event.clone(), payload_type));
+    }
+
+    let mut out = String::new();
+    out.push_str("// Generated by `cargo run --bin gen-client`. Do not edit by hand.\n\n");
+
+    for (name, ts) in &writer.declarations {
+        let _ = writeln!(out, "export type {} = {};\n", name, ts);
+    }
+
+    out.push_str("export interface Commands {\n");
+    for (cmd, request, reply) in &command_types {
+        let _ = writeln!(out, "  \"{}\": {{ request: {}; reply: {} }};", cmd, request, reply);
+    }
+    out.push_str("}\n\n");
+
+    out.push_str("export interface Events {\n");
+    for (event, payload) in &event_types {
+        let _ = writeln!(out, "  \"{}\": {};", event, payload);
+    }
+    out.push_str("}\n\n");
+
+    // Server-pushed messages narrowed by their event name
+    out.push_str("export type ServerEvent = { [E in keyof Events]: { event: E; data: Events[E] } }[keyof Events];\n\n");
+
+    out.push_str(CLIENT_RUNTIME);
+    Ok(out)
+}
+
+// Transport-level client shared by every generated protocol version
+const CLIENT_RUNTIME: &str = r#"export interface ServerEnvelope {
+  msg_id: string;
+  event?: string;
+  code: number;
+  data?: unknown;
+  error?: string | null;
+  // Quote this when reporting a problem; it finds the command in the server logs
+  correlation_id?: string;
+}
+
+export class SpvError extends Error {
+  constructor(public code: number, message: string) {
+    super(message);
+  }
+}
+
+export interface SpvClientOptions {
+  // Ask the server to gzip large messages (default false)
+  compress?: boolean;
+  // Reconnect automatically after the socket drops (default true)
+  reconnect?: boolean;
+  // First reconnect delay, doubled on each failure (default 500ms)
+  minReconnectDelayMs?: number;
+  maxReconnectDelayMs?: number;
+  // Reject a command if no reply arrives in time (default 10s)
+  requestTimeoutMs?: number;
+}
+
+type Handler<T> = (payload: T) => void;
+
+// Compressed messages arrive as gzip binary frames
+function gunzip(data: ArrayBuffer): Promise<string> {
+  const stream = new Blob([data]).stream().pipeThrough(new DecompressionStream("gzip"));
+  return new Response(stream).text();
+}
+
+interface Pending {
+  resolve: (data: unknown) => void;
+  reject: (error: Error) => void;
+  timer: ReturnType<typeof setTimeout>;
+}
+
+export class SpvClient {
+  private socket?: WebSocket;
+  private closedByUser = false;
+  private attempts = 0;
+  private pending = new Map<string, Pending>();
+  private handlers = new Map<string, Set<Handler<any>>>();
+  private openHandlers = new Set<() => void>();
+  private closeHandlers = new Set<() => void>();
+
+  constructor(
+    private url: string,
+    private sessionToken: string,
+    private options: SpvClientOptions = {},
+  ) {}
+
+  connect(): Promise<void> {
+    this.closedByUser = false;
+    return new Promise((resolve, reject) => {
+      const separator = this.url.includes("?") ? "&" : "?";
+      const compress = this.options.compress ? "&compress=gzip" : "";
+      const socket = new WebSocket(`${this.url}${separator}token=${encodeURIComponent(this.sessionToken)}${compress}`);
+      socket.binaryType = "arraybuffer";
+      this.socket = socket;
+
+      socket.onopen = () => {
+        this.attempts = 0;
+        this.openHandlers.forEach((h) => h());
+        resolve();
+      };
+      socket.onmessage = (ev) => {
+        if (ev.data instanceof ArrayBuffer) {
+          gunzip(ev.data).then((text) => this.dispatch(text), () => undefined);
+        } else {
+          this.dispatch(String(ev.data));
+        }
+      };
+      socket.onerror = () => {
+        if (socket.readyState !== WebSocket.OPEN) reject(new Error("connection failed"));
+      };
+      socket.onclose = () => {
+        this.failPending(new Error("connection closed"));
+        this.closeHandlers.forEach((h) => h());
+        if (!this.closedByUser && this.options.reconnect !== false) this.scheduleReconnect();
+      };
+    });
+  }
+
+  close(): void {
+    this.closedByUser = true;
+    this.socket?.close();
+  }
+
+  onOpen(handler: () => void): () => void {
+    this.openHandlers.add(handler);
+    return () => this.openHandlers.delete(handler);
+  }
+
+  onClose(handler: () => void): () => void {
+    this.closeHandlers.add(handler);
+    return () => this.closeHandlers.delete(handler);
+  }
+
+  on<E extends keyof Events>(event: E, handler: Handler<Events[E]>): () => void {
+    const name = event as string;
+    if (!this.handlers.has(name)) this.handlers.set(name, new Set());
+    this.handlers.get(name)!.add(handler);
+    return () => this.handlers.get(name)?.delete(handler);
+  }
+
+  send<C extends keyof Commands>(cmd: C, data: Commands[C]["request"]): Promise<Commands[C]["reply"]> {
+    const socket = this.socket;
+    if (!socket || socket.readyState !== WebSocket.OPEN) {
+      return Promise.reject(new Error("not connected"));
+    }
+    const msgId = crypto.randomUUID();
+    return new Promise((resolve, reject) => {
+      const timer = setTimeout(() => {
+        this.pending.delete(msgId);
+        reject(new Error(`${String(cmd)} timed out`));
+      }, this.options.requestTimeoutMs ?? 10_000);
+      this.pending.set(msgId, { resolve: resolve as (data: unknown) => void, reject, timer });
+      socket.send(JSON.stringify({ msg_id: msgId, cmd, data: data ?? null }));
+    });
+  }
+
+  // Send a fire-and-forget command (reply type void); errors are not reported
+  notify<C extends keyof Commands>(cmd: C, data: Commands[C]["request"]): void {
+    const socket = this.socket;
+    if (!socket || socket.readyState !== WebSocket.OPEN) return;
+    socket.send(JSON.stringify({ msg_id: crypto.randomUUID(), cmd, data: data ?? null }));
+  }
+
+  private dispatch(text: string): void {
+    let msg: ServerEnvelope;
+    try {
+      msg = JSON.parse(text);
+    } catch {
+      return;
+    }
+
+    if (msg.event) {
+      this.handlers.get(msg.event)?.forEach((h) => h(msg.data));
+      return;
+    }
+
+    const pending = this.pending.get(msg.msg_id);
+    if (!pending) return;
+    this.pending.delete(msg.msg_id);
+    clearTimeout(pending.timer);
+    if (msg.code === 0) {
+      pending.resolve(msg.data);
+    } else {
+      pending.reject(new SpvError(msg.code, msg.error ?? "unknown error"));
+    }
+  }
+
+  private failPending(error: Error): void {
+    this.pending.forEach((p) => {
+      clearTimeout(p.timer);
+      p.reject(error);
+    });
+    this.pending.clear();
+  }
+
+  private scheduleReconnect(): void {
+    const min = this.options.minReconnectDelayMs ?? 500;
+    const max = this.options.maxReconnectDelayMs ?? 30_000;
+    const delay = Math.min(max, min * 2 ** this.attempts);
+    this.attempts += 1;
+    setTimeout(() => {
+      if (!this.closedByUser) this.connect().catch(() => undefined);
+    }, delay);
+  }
+}
+"#;
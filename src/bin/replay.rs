@@ -0,0 +1,216 @@
+// Replays a traffic recording (see gateway::recorder) against a server, to
+// reproduce bugs seen in production on a local instance.
+//
+// Usage:
+//   cargo run --bin replay -- --file <recording> [--server <url>] [--speed <factor>]
+//
+// --server defaults to http://localhost:3000. --speed 1 (the default) keeps
+// the original timing, 10 plays ten times as fast and 0 sends everything
+// without waiting. Every recorded session is opened over SSE; each recorded
+// (pseudonymous) user plays as a guest of its own, created on the server
+// with POST /api/auth/guest when the user first shows up.
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use serde_json::Value;
+use tokio::sync::oneshot;
+use tokio::task::JoinHandle;
+
+const DEFAULT_SERVER: &str = "http://localhost:3000";
+const WELCOME_TIMEOUT: Duration = Duration::from_secs(10);
+
+// A recorded session opened on the target server
+struct Session {
+    conn_id: String,
+    token: String,
+    reader: JoinHandle<()>,
+}
+
+#[tokio::main]
+async fn main() {
+    let mut file: Option<String> = None;
+    let mut server = DEFAULT_SERVER.to_string();
+    let mut speed = 1.0_f64;
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--file" => file = Some(args.next().unwrap_or_else(|| usage())),
+            "--server" => server = args.next().unwrap_or_else(|| usage()),
+            "--speed" => {
+                speed = args.next()
+                    .and_then(|s| s.parse().ok())
+                    .filter(|&s: &f64| s >= 0.0)
+                    .unwrap_or_else(|| usage())
+            }
+            _ => usage(),
+        }
+    }
+    let Some(file) = file else { usage() };
+    let server = server.trim_end_matches('/').to_string();
+
+    let events = match load(&file) {
+        Ok(events) => events,
+        Err(e) => {
+            eprintln!("Failed to read recording {}: {}", file, e);
+            std::process::exit(1);
+        }
+    };
+    eprintln!("Replaying {} events from {} against {}", events.len(), file, server);
+
+    let client = reqwest::Client::new();
+    let mut sessions: HashMap<String, Session> = HashMap::new();
+    // Session token of the guest standing in for each recorded user
+    let mut tokens: HashMap<String, String> = HashMap::new();
+    let (mut sent, mut failed) = (0usize, 0usize);
+    let started = Instant::now();
+
+    for event in events {
+        // Keep the recorded spacing, scaled by --speed
+        if speed > 0.0 {
+            let at_ms = event["at_ms"].as_u64().unwrap_or(0);
+            let due = Duration::from_secs_f64(at_ms as f64 / 1000.0 / speed);
+            if let Some(wait) = due.checked_sub(started.elapsed()) {
+                tokio::time::sleep(wait).await;
+            }
+        }
+
+        let conn = event["conn"].as_str().unwrap_or_default().to_string();
+        match event["kind"].as_str() {
+            Some("open") => {
+                let user_id = event["user"].as_str().unwrap_or_default().to_string();
+                let token = match tokens.get(&user_id) {
+                    Some(token) => token.clone(),
+                    None => match guest_token(&client, &server).await {
+                        Ok(token) => tokens.entry(user_id.clone()).or_insert(token).clone(),
+                        Err(e) => {
+                            eprintln!("Failed to create a guest for user {}: {}", user_id, e);
+                            continue;
+                        }
+                    },
+                };
+                match open_session(&client, &server, &token).await {
+                    Ok(session) => {
+                        sessions.insert(conn, session);
+                    }
+                    Err(e) => eprintln!("Failed to open session of user {}: {}", user_id, e),
+                }
+            }
+            Some("message") => {
+                let Some(session) = sessions.get(&conn) else {
+                    // Opened before the recording started, or failed to open
+                    failed += 1;
+                    continue;
+                };
+                match send(&client, &server, session, &event["message"]).await {
+                    Ok(()) => sent += 1,
+                    Err(e) => {
+                        eprintln!("Failed to send {}: {}", event["message"]["cmd"], e);
+                        failed += 1;
+                    }
+                }
+            }
+            Some("close") => {
+                if let Some(session) = sessions.remove(&conn) {
+                    session.reader.abort();
+                }
+            }
+            _ => eprintln!("Skipping unknown event: {}", event),
+        }
+    }
+
+    for session in sessions.into_values() {
+        session.reader.abort();
+    }
+    eprintln!("Done in {:?}: {} messages sent, {} failed", started.elapsed(), sent, failed);
+}
+
+fn usage() -> ! {
+    eprintln!("usage: replay --file <recording> [--server <url>] [--speed <factor>]");
+    std::process::exit(2);
+}
+
+fn load(path: &str) -> Result<Vec<Value>, String> {
+    let text = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+    text.lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| serde_json::from_str(line).map_err(|e| e.to_string()))
+        .collect()
+}
+
+// Session token of a new guest
+async fn guest_token(client: &reqwest::Client, server: &str) -> Result<String, String> {
+    let reply: Value = client
+        .post(format!("{}/api/auth/guest", server))
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .map_err(|e| e.to_string())?
+        .json()
+        .await
+        .map_err(|e| e.to_string())?;
+    reply["session_token"].as_str().map(str::to_string).ok_or_else(|| "no session_token in the reply".to_string())
+}
+
+// Open an SSE session and wait for the welcome event carrying its conn_id.
+// The stream is then drained in the background so the session stays open.
+async fn open_session(client: &reqwest::Client, server: &str, token: &str) -> Result<Session, String> {
+    let mut response = client
+        .get(format!("{}/sse", server))
+        .query(&[("token", token)])
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .map_err(|e| e.to_string())?;
+
+    let (welcome_tx, welcome_rx) = oneshot::channel();
+    let reader = tokio::spawn(async move {
+        let mut welcome_tx = Some(welcome_tx);
+        let mut buffer = String::new();
+        while let Ok(Some(chunk)) = response.chunk().await {
+            buffer.push_str(&String::from_utf8_lossy(&chunk));
+            // Events are separated by blank lines
+            while let Some(end) = buffer.find("\n\n") {
+                let event: String = buffer.drain(..end + 2).collect();
+                let Some(data) = event.lines().find_map(|line| line.strip_prefix("data:")) else {
+                    continue;
+                };
+                let Ok(message) = serde_json::from_str::<Value>(data.trim()) else {
+                    continue;
+                };
+                if message["event"] == "sys.welcome" {
+                    if let Some(tx) = welcome_tx.take() {
+                        let _ = tx.send(message["data"]["conn_id"].as_str().map(str::to_string));
+                    }
+                } else if message["code"].as_i64().is_some_and(|code| code != 0) {
+                    eprintln!("Error reply for {}: {}", message["msg_id"], message["error"]);
+                }
+            }
+        }
+    });
+
+    match tokio::time::timeout(WELCOME_TIMEOUT, welcome_rx).await {
+        Ok(Ok(Some(conn_id))) => Ok(Session {
+            conn_id,
+            token: token.to_string(),
+            reader,
+        }),
+        _ => {
+            reader.abort();
+            Err("no welcome event (banned, or not an SSE endpoint?)".to_string())
+        }
+    }
+}
+
+async fn send(client: &reqwest::Client, server: &str, session: &Session, message: &Value) -> Result<(), String> {
+    client
+        .post(format!("{}/sse/command", server))
+        .query(&[("conn_id", &session.conn_id), ("token", &session.token)])
+        .json(message)
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+}
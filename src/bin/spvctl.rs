@@ -0,0 +1,266 @@
+// Command-line client for the admin API, for live ops without curl.
+//
+// Usage:
+//   cargo run --bin spvctl -- [--server <url>] [--token <token>] <command>
+//
+// Commands:
+//   matches                                   running matches with their stats
+//   connections                               open connections
+//   end <match_id>                            end a running match now
+//   ban <user_id> --reason <text> [--duration <secs>]
+//   unban <user_id>
+//   maintenance [on|off] [--message <text>]   show or toggle maintenance mode
+//   tail                                      follow match events as they happen
+//
+// --server defaults to http://localhost:3000 and --token to ADMIN_TOKEN.
+// Bans are recorded as issued by --by, defaulting to $USER. Maintenance mode
+// and the event tail are per node, so point --server at the node itself
+// rather than a load balancer.
+
+use serde_json::{Value, json};
+
+const DEFAULT_SERVER: &str = "http://localhost:3000";
+
+struct Admin {
+    client: reqwest::Client,
+    server: String,
+    token: String,
+}
+
+#[tokio::main]
+async fn main() {
+    let mut server = DEFAULT_SERVER.to_string();
+    let mut token = std::env::var("ADMIN_TOKEN").ok();
+    let mut by = std::env::var("USER").unwrap_or_else(|_| "spvctl".to_string());
+    let mut reason: Option<String> = None;
+    let mut duration: Option<u64> = None;
+    let mut message: Option<String> = None;
+    let mut command = Vec::new();
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--server" => server = args.next().unwrap_or_else(|| usage()),
+            "--token" => token = Some(args.next().unwrap_or_else(|| usage())),
+            "--by" => by = args.next().unwrap_or_else(|| usage()),
+            "--reason" => reason = Some(args.next().unwrap_or_else(|| usage())),
+            "--message" => message = Some(args.next().unwrap_or_else(|| usage())),
+            "--duration" => {
+                duration = Some(args.next()
+                    .and_then(|s| s.parse().ok())
+                    .unwrap_or_else(|| usage()))
+            }
+            _ if arg.starts_with("--") => usage(),
+            _ => command.push(arg),
+        }
+    }
+    let Some(token) = token else {
+        eprintln!("No admin token: pass --token or set ADMIN_TOKEN");
+        std::process::exit(2);
+    };
+    let admin = Admin {
+        client: reqwest::Client::new(),
+        server: server.trim_end_matches('/').to_string(),
+        token,
+    };
+
+    let command: Vec<&str> = command.iter().map(String::as_str).collect();
+    let result = match command.as_slice() {
+        ["matches"] => matches(&admin).await,
+        ["connections"] => connections(&admin).await,
+        ["end", match_id] => admin
+            .send(reqwest::Method::POST, &format!("/admin/matches/{}/end", match_id), None)
+            .await
+            .map(|_| println!("Match {} ended", match_id)),
+        ["ban", user_id] => {
+            let Some(reason) = reason else { usage() };
+            let body = json!({"issued_by": by, "reason": reason, "duration_secs": duration});
+            admin
+                .send(reqwest::Method::PUT, &format!("/admin/bans/{}", user_id), Some(body))
+                .await
+                .map(|ban| match ban["expires_at"].as_str() {
+                    Some(until) => println!("Banned {} until {}", user_id, until),
+                    None => println!("Banned {} permanently", user_id),
+                })
+        }
+        ["unban", user_id] => admin
+            .send(reqwest::Method::DELETE, &format!("/admin/bans/{}?lifted_by={}", user_id, by), None)
+            .await
+            .map(|_| println!("Lifted the ban of {}", user_id)),
+        ["maintenance"] => admin
+            .send(reqwest::Method::GET, "/admin/maintenance", None)
+            .await
+            .map(|m| print_maintenance(&m)),
+        ["maintenance", state @ ("on" | "off")] => {
+            let body = json!({"enabled": *state == "on", "message": message});
+            admin
+                .send(reqwest::Method::PUT, "/admin/maintenance", Some(body))
+                .await
+                .map(|m| print_maintenance(&m))
+        }
+        ["tail"] => tail(&admin).await,
+        _ => usage(),
+    };
+
+    if let Err(e) = result {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
+}
+
+fn usage() -> ! {
+    eprintln!("usage: spvctl [--server <url>] [--token <token>] [--by <name>] <command>");
+    eprintln!("commands:");
+    eprintln!("  matches");
+    eprintln!("  connections");
+    eprintln!("  end <match_id>");
+    eprintln!("  ban <user_id> --reason <text> [--duration <secs>]");
+    eprintln!("  unban <user_id>");
+    eprintln!("  maintenance [on|off] [--message <text>]");
+    eprintln!("  tail");
+    std::process::exit(2);
+}
+
+impl Admin {
+    fn request(&self, method: reqwest::Method, path: &str) -> reqwest::RequestBuilder {
+        self.client
+            .request(method, format!("{}{}", self.server, path))
+            .bearer_auth(&self.token)
+    }
+
+    // Send a request and return its JSON body, Null for an empty one. Error
+    // responses are turned into their message.
+    async fn send(&self, method: reqwest::Method, path: &str, body: Option<Value>) -> Result<Value, String> {
+        let mut request = self.request(method, path);
+        if let Some(body) = body {
+            request = request.json(&body);
+        }
+        let response = request.send().await.map_err(|e| e.to_string())?;
+        let status = response.status();
+        let text = response.text().await.map_err(|e| e.to_string())?;
+        let value = if text.is_empty() {
+            Value::Null
+        } else {
+            serde_json::from_str(&text).unwrap_or(Value::String(text))
+        };
+        if status.is_success() {
+            Ok(value)
+        } else {
+            let message = value["error"].as_str().map(str::to_string).unwrap_or_else(|| value.to_string());
+            Err(format!("{}: {}", status, message))
+        }
+    }
+}
+
+async fn matches(admin: &Admin) -> Result<(), String> {
+    let page = admin.send(reqwest::Method::GET, "/admin/matches?limit=500", None).await?;
+    let matches = page["items"].as_array().cloned().unwrap_or_default();
+    println!("{:<36}  {:<10}  {:<9}  {:>7}  {:>7}  {:>11}  {:>8}", "MATCH", "TYPE", "STATUS", "PLAYERS", "RTT", "DISCOVERIES", "DURATION");
+    for m in &matches {
+        println!(
+            "{:<36}  {:<10}  {:<9}  {:>7}  {:>7}  {:>11}  {:>8}",
+            text(&m["match_id"]),
+            text(&m["match_type"]),
+            text(&m["status"]),
+            text(&m["connected_players"]),
+            optional(&m["avg_rtt_ms"], "ms"),
+            text(&m["discoveries"]),
+            optional(&m["duration_secs"], "s"),
+        );
+    }
+    eprintln!("{} of {} matches", matches.len(), text(&page["total_estimate"]));
+    Ok(())
+}
+
+async fn connections(admin: &Admin) -> Result<(), String> {
+    let page = admin.send(reqwest::Method::GET, "/admin/connections?limit=500", None).await?;
+    let connections = page["items"].as_array().cloned().unwrap_or_default();
+    println!("{:<36}  {:<36}  {:<36}  {:<15}  {:<8}  {:>7}  QUALITY", "CONNECTION", "USER", "MATCH", "IP", "PLATFORM", "RTT");
+    for c in &connections {
+        println!(
+            "{:<36}  {:<36}  {:<36}  {:<15}  {:<8}  {:>7}  {}",
+            text(&c["conn_id"]),
+            text(&c["user_id"]),
+            optional(&c["match_id"], ""),
+            text(&c["ip"]),
+            optional(&c["platform"], ""),
+            optional(&c["rtt_ms"], "ms"),
+            optional(&c["quality"], ""),
+        );
+    }
+    eprintln!("{} of {} connections", connections.len(), text(&page["total_estimate"]));
+    Ok(())
+}
+
+fn print_maintenance(maintenance: &Value) {
+    if maintenance["enabled"].as_bool() == Some(true) {
+        println!(
+            "Maintenance mode on since {}: {}",
+            text(&maintenance["since"]),
+            maintenance["message"].as_str().unwrap_or("(no message)"),
+        );
+    } else {
+        println!("Maintenance mode off");
+    }
+}
+
+// Follow the SSE stream of /admin/events until the server closes it
+async fn tail(admin: &Admin) -> Result<(), String> {
+    let mut response = admin
+        .request(reqwest::Method::GET, "/admin/events")
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .map_err(|e| e.to_string())?;
+    eprintln!("Following events on {}", admin.server);
+
+    let mut buffer = String::new();
+    while let Some(chunk) = response.chunk().await.map_err(|e| e.to_string())? {
+        buffer.push_str(&String::from_utf8_lossy(&chunk));
+        // Events are separated by blank lines
+        while let Some(end) = buffer.find("\n\n") {
+            let event: String = buffer.drain(..end + 2).collect();
+            let name = event.lines().find_map(|line| line.strip_prefix("event:")).map(str::trim);
+            let Some(data) = event.lines().find_map(|line| line.strip_prefix("data:")) else {
+                // Keep-alive comment
+                continue;
+            };
+            if name == Some("lagged") {
+                eprintln!("... missed {} events", data.trim());
+                continue;
+            }
+            let Ok(event) = serde_json::from_str::<Value>(data.trim()) else {
+                continue;
+            };
+            println!(
+                "{}  {:<18}  match {}  user {}  {}",
+                text(&event["at"]),
+                text(&event["event"]),
+                text(&event["match_id"]),
+                optional(&event["user_id"], ""),
+                event["correlation_id"].as_str().map(|id| format!("correlation {}", id)).unwrap_or_default(),
+            );
+        }
+    }
+    eprintln!("Event stream closed by the server");
+    Ok(())
+}
+
+fn text(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        Value::Null => "-".to_string(),
+        other => other.to_string(),
+    }
+}
+
+fn optional(value: &Value, unit: &str) -> String {
+    match value {
+        Value::Null => "-".to_string(),
+        Value::Number(n) => match n.as_f64() {
+            Some(f) if n.is_f64() => format!("{:.0}{}", f, unit),
+            _ => format!("{}{}", n, unit),
+        },
+        other => format!("{}{}", text(other), unit),
+    }
+}
@@ -0,0 +1,74 @@
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::config::ChaosConfig;
+use crate::error::{Error, Result};
+
+// Fault injection for resilience tests, off unless CHAOS_ENABLED=true.
+//
+// Hasura calls are randomly delayed or failed with DbUnavailable, WebSocket
+// frames are dropped and send tasks killed, so retries, the offline write
+// queue, score reconciliation and client reconnects can be exercised against
+// a real server. Never enable it in production.
+struct Chaos {
+    config: ChaosConfig,
+    rng: Mutex<StdRng>,
+}
+
+static CHAOS: OnceLock<Chaos> = OnceLock::new();
+
+pub fn init(config: Option<&ChaosConfig>) {
+    let Some(config) = config else {
+        return;
+    };
+    tracing::warn!("FAULT INJECTION ENABLED: {:?}", config);
+    let rng = match config.seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_entropy(),
+    };
+    let _ = CHAOS.set(Chaos {
+        config: config.clone(),
+        rng: Mutex::new(rng),
+    });
+}
+
+impl Chaos {
+    fn roll(&self, rate: f64) -> bool {
+        rate > 0.0 && self.rng.lock().unwrap().gen_bool(rate)
+    }
+
+    fn delay(&self) -> Duration {
+        let max = self.config.db_max_delay.as_millis() as u64;
+        Duration::from_millis(self.rng.lock().unwrap().gen_range(0..=max))
+    }
+}
+
+// Called before every Hasura request
+pub async fn before_db_call() -> Result<()> {
+    let Some(chaos) = CHAOS.get() else {
+        return Ok(());
+    };
+    if chaos.roll(chaos.config.db_delay_rate) {
+        let delay = chaos.delay();
+        tracing::debug!("Chaos: delaying database call by {:?}", delay);
+        tokio::time::sleep(delay).await;
+    }
+    if chaos.roll(chaos.config.db_failure_rate) {
+        tracing::debug!("Chaos: failing database call");
+        return Err(Error::DbUnavailable);
+    }
+    Ok(())
+}
+
+// Whether to drop the next outgoing WebSocket frame
+pub fn drop_frame() -> bool {
+    CHAOS.get().is_some_and(|chaos| chaos.roll(chaos.config.frame_drop_rate))
+}
+
+// Whether the connection's send task should die now
+pub fn kill_send_task() -> bool {
+    CHAOS.get().is_some_and(|chaos| chaos.roll(chaos.config.send_kill_rate))
+}
@@ -0,0 +1,229 @@
+use std::net::{IpAddr, SocketAddr};
+
+use axum::{
+    async_trait,
+    extract::{ConnectInfo, FromRequestParts},
+    http::{HeaderMap, request::Parts},
+};
+
+use crate::AppState;
+
+// An IPv4/IPv6 network such as 10.0.0.0/8 or a single address
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cidr {
+    addr: IpAddr,
+    prefix: u8,
+}
+
+impl Cidr {
+    pub fn from_str(s: &str) -> Option<Self> {
+        let (addr, prefix) = match s.split_once('/') {
+            Some((addr, prefix)) => (addr.parse::<IpAddr>().ok()?, Some(prefix.parse::<u8>().ok()?)),
+            None => (s.parse::<IpAddr>().ok()?, None),
+        };
+        let max = if addr.is_ipv4() { 32 } else { 128 };
+        let prefix = prefix.unwrap_or(max);
+        if prefix > max {
+            return None;
+        }
+        Some(Self { addr, prefix })
+    }
+
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        match (self.addr, ip) {
+            (IpAddr::V4(net), IpAddr::V4(ip)) => {
+                prefix_match(u32::from(net) as u128, u32::from(ip) as u128, self.prefix, 32)
+            }
+            (IpAddr::V6(net), IpAddr::V6(ip)) => {
+                prefix_match(u128::from(net), u128::from(ip), self.prefix, 128)
+            }
+            // IPv4-mapped IPv6 peers (dual-stack sockets)
+            (IpAddr::V4(_), IpAddr::V6(ip)) => match ip.to_ipv4_mapped() {
+                Some(v4) => self.contains(IpAddr::V4(v4)),
+                None => false,
+            },
+            _ => false,
+        }
+    }
+}
+
+fn prefix_match(net: u128, ip: u128, prefix: u8, bits: u8) -> bool {
+    if prefix == 0 {
+        return true;
+    }
+    let shift = (bits - prefix) as u32;
+    (net >> shift) == (ip >> shift)
+}
+
+// Proxies whose X-Forwarded-For / Forwarded headers we believe
+#[derive(Debug, Clone, Default)]
+pub struct TrustedProxies {
+    networks: Vec<Cidr>,
+}
+
+impl TrustedProxies {
+    // Comma separated list, e.g. "10.0.0.0/8,127.0.0.1,::1"
+    pub fn from_str(s: &str) -> Self {
+        let networks = s
+            .split(',')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+            .filter_map(|entry| {
+                let cidr = Cidr::from_str(entry);
+                if cidr.is_none() {
+                    tracing::warn!("Ignoring invalid trusted proxy: {}", entry);
+                }
+                cidr
+            })
+            .collect();
+        Self { networks }
+    }
+
+    pub fn is_trusted(&self, ip: IpAddr) -> bool {
+        self.networks.iter().any(|net| net.contains(ip))
+    }
+
+    // Resolve the real client address for a request received from `peer`.
+    //
+    // Forwarding headers are only honoured when the peer itself is a trusted
+    // proxy. The chain is then walked from the right (closest hop) and the
+    // first untrusted address is the client; anything further left could
+    // have been forged by the client.
+    pub fn resolve(&self, peer: IpAddr, headers: &HeaderMap) -> IpAddr {
+        if !self.is_trusted(peer) {
+            return peer;
+        }
+
+        let chain = forwarded_chain(headers);
+        chain
+            .iter()
+            .rev()
+            .find(|ip| !self.is_trusted(**ip))
+            .or(chain.first())
+            .copied()
+            .unwrap_or(peer)
+    }
+}
+
+// Addresses from the standard Forwarded header, or X-Forwarded-For when absent
+fn forwarded_chain(headers: &HeaderMap) -> Vec<IpAddr> {
+    let forwarded: Vec<IpAddr> = headers
+        .get_all("forwarded")
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .filter_map(|element| {
+            element.split(';').find_map(|pair| {
+                let (key, value) = pair.trim().split_once('=')?;
+                if key.eq_ignore_ascii_case("for") {
+                    parse_node(value)
+                } else {
+                    None
+                }
+            })
+        })
+        .collect();
+    if !forwarded.is_empty() {
+        return forwarded;
+    }
+
+    headers
+        .get_all("x-forwarded-for")
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .filter_map(parse_node)
+        .collect()
+}
+
+// Accepts `1.2.3.4`, `1.2.3.4:5678`, `"[2001:db8::1]:443"` and bare IPv6
+fn parse_node(value: &str) -> Option<IpAddr> {
+    let value = value.trim().trim_matches('"');
+    if let Ok(ip) = value.parse::<IpAddr>() {
+        return Some(ip);
+    }
+    if let Ok(addr) = value.parse::<SocketAddr>() {
+        return Some(addr.ip());
+    }
+    value
+        .strip_prefix('[')
+        .and_then(|v| v.split_once(']'))
+        .and_then(|(ip, _)| ip.parse().ok())
+}
+
+// Real client address, resolved through trusted proxies
+#[derive(Debug, Clone, Copy)]
+pub struct ClientIp(pub IpAddr);
+
+#[async_trait]
+impl FromRequestParts<AppState> for ClientIp {
+    type Rejection = std::convert::Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, state: &AppState) -> Result<Self, Self::Rejection> {
+        let peer = parts
+            .extensions
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(addr)| addr.ip())
+            .unwrap_or(IpAddr::from([0, 0, 0, 0]));
+        Ok(ClientIp(state.config.server.trusted_proxies.resolve(peer, &parts.headers)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ip(s: &str) -> IpAddr {
+        s.parse().unwrap()
+    }
+
+    fn headers(name: &'static str, value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(name, value.parse().unwrap());
+        headers
+    }
+
+    #[test]
+    fn parses_networks_and_skips_invalid_entries() {
+        let proxies = TrustedProxies::from_str(" 10.0.0.0/8, bogus,::1 ,192.168.1.1/33,");
+        assert!(proxies.is_trusted(ip("10.20.30.40")));
+        assert!(proxies.is_trusted(ip("::1")));
+        assert!(proxies.is_trusted(ip("::ffff:10.0.0.1")));
+        assert!(!proxies.is_trusted(ip("11.0.0.1")));
+        assert!(!proxies.is_trusted(ip("192.168.1.1")));
+        assert_eq!(Cidr::from_str("0.0.0.0/0").map(|net| net.contains(ip("8.8.8.8"))), Some(true));
+    }
+
+    #[test]
+    fn headers_from_untrusted_peers_are_ignored() {
+        let proxies = TrustedProxies::from_str("10.0.0.0/8");
+        let forged = headers("x-forwarded-for", "1.2.3.4");
+        assert_eq!(proxies.resolve(ip("8.8.8.8"), &forged), ip("8.8.8.8"));
+    }
+
+    #[test]
+    fn the_first_untrusted_hop_from_the_right_is_the_client() {
+        let proxies = TrustedProxies::from_str("10.0.0.0/8");
+        let chain = headers("x-forwarded-for", "6.6.6.6, 1.2.3.4, 10.0.0.2");
+        assert_eq!(proxies.resolve(ip("10.0.0.1"), &chain), ip("1.2.3.4"));
+
+        // Only proxies in the chain: the leftmost address
+        let internal = headers("x-forwarded-for", "10.0.0.3, 10.0.0.2");
+        assert_eq!(proxies.resolve(ip("10.0.0.1"), &internal), ip("10.0.0.3"));
+    }
+
+    #[test]
+    fn forwarded_is_preferred_over_x_forwarded_for() {
+        let proxies = TrustedProxies::from_str("10.0.0.0/8");
+        let mut both = headers("forwarded", "for=\"[2001:db8::1]:443\";proto=https, for=10.0.0.2");
+        both.insert("x-forwarded-for", "1.2.3.4".parse().unwrap());
+        assert_eq!(proxies.resolve(ip("10.0.0.1"), &both), ip("2001:db8::1"));
+    }
+
+    #[test]
+    fn parses_ports_and_brackets() {
+        assert_eq!(parse_node("1.2.3.4:5678"), Some(ip("1.2.3.4")));
+        assert_eq!(parse_node("[2001:db8::1]"), Some(ip("2001:db8::1")));
+        assert_eq!(parse_node("unknown"), None);
+    }
+}
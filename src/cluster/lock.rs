@@ -0,0 +1,174 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use redis::aio::MultiplexedConnection;
+use tokio::sync::{Mutex, OnceCell};
+use uuid::Uuid;
+
+use crate::error::{Error, Result};
+
+// Only delete the key if we still own it, so an expired lock that was
+// re-acquired by another instance is never released by mistake.
+const RELEASE_SCRIPT: &str = r#"
+if redis.call("GET", KEYS[1]) == ARGV[1] then
+    return redis.call("DEL", KEYS[1])
+else
+    return 0
+end
+"#;
+
+// Same ownership check before pushing the expiry out
+const EXTEND_SCRIPT: &str = r#"
+if redis.call("GET", KEYS[1]) == ARGV[1] then
+    return redis.call("PEXPIRE", KEYS[1], ARGV[2])
+else
+    return 0
+end
+"#;
+
+// Lock shared by every server instance.
+//
+// Backed by Redis `SET NX PX` when REDIS_URL is set. Without Redis it falls
+// back to an in-process table, which still serialises joins on a single node.
+pub struct DistributedLock {
+    redis: Option<redis::Client>,
+    conn: OnceCell<MultiplexedConnection>,
+    release_script: redis::Script,
+    extend_script: redis::Script,
+    local: Mutex<HashMap<String, (String, Instant)>>,
+}
+
+// Proof of ownership returned by `try_acquire`, handed back to `release`
+#[derive(Debug)]
+pub struct LockGuard {
+    key: String,
+    token: String,
+}
+
+impl DistributedLock {
+    pub fn from_env() -> Arc<Self> {
+        let redis = match crate::secrets::get("REDIS_URL") {
+            Some(url) => match redis::Client::open(url.as_str()) {
+                Ok(client) => {
+                    tracing::info!("Distributed lock backed by Redis");
+                    Some(client)
+                }
+                Err(e) => {
+                    tracing::warn!("Invalid REDIS_URL, falling back to local locks: {}", e);
+                    None
+                }
+            },
+            None => {
+                tracing::info!("REDIS_URL not set, using local locks");
+                None
+            }
+        };
+
+        Arc::new(Self {
+            redis,
+            conn: OnceCell::new(),
+            release_script: redis::Script::new(RELEASE_SCRIPT),
+            extend_script: redis::Script::new(EXTEND_SCRIPT),
+            local: Mutex::new(HashMap::new()),
+        })
+    }
+
+    async fn connection(&self, client: &redis::Client) -> Result<MultiplexedConnection> {
+        let conn = self.conn.get_or_try_init(|| async {
+            client.get_multiplexed_async_connection()
+                .await
+                .map_err(|e| Error::LockError(e.to_string()))
+        }).await?;
+
+        Ok(conn.clone())
+    }
+
+    // Try to take the lock once. `Ok(None)` means another holder owns it.
+    // The lock expires after `ttl` even if it is never released.
+    pub async fn try_acquire(&self, key: &str, ttl: Duration) -> Result<Option<LockGuard>> {
+        let token = Uuid::new_v4().to_string();
+
+        match &self.redis {
+            Some(client) => {
+                let mut conn = self.connection(client).await?;
+                let reply = redis::cmd("SET")
+                    .arg(key)
+                    .arg(&token)
+                    .arg("NX")
+                    .arg("PX")
+                    .arg(ttl.as_millis() as u64)
+                    .query_async::<_, Option<String>>(&mut conn)
+                    .await
+                    .map_err(|e| Error::LockError(e.to_string()))?;
+
+                if reply.is_none() {
+                    return Ok(None);
+                }
+            }
+            None => {
+                let mut held = self.local.lock().await;
+                let now = Instant::now();
+
+                if let Some((_, expires_at)) = held.get(key) && *expires_at > now {
+                    return Ok(None);
+                }
+                held.insert(key.to_string(), (token.clone(), now + ttl));
+            }
+        }
+
+        Ok(Some(LockGuard {
+            key: key.to_string(),
+            token,
+        }))
+    }
+
+    pub async fn release(&self, guard: LockGuard) -> Result<()> {
+        match &self.redis {
+            Some(client) => {
+                let mut conn = self.connection(client).await?;
+                self.release_script
+                    .key(&guard.key)
+                    .arg(&guard.token)
+                    .invoke_async::<_, i32>(&mut conn)
+                    .await
+                    .map_err(|e| Error::LockError(e.to_string()))?;
+            }
+            None => {
+                let mut held = self.local.lock().await;
+                if held.get(&guard.key).map(|(token, _)| token == &guard.token).unwrap_or(false) {
+                    held.remove(&guard.key);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    // Keep holding the lock for another `ttl`. Returns false if it expired
+    // and someone else took it in the meantime.
+    pub async fn extend(&self, guard: &LockGuard, ttl: Duration) -> Result<bool> {
+        match &self.redis {
+            Some(client) => {
+                let mut conn = self.connection(client).await?;
+                let extended = self.extend_script
+                    .key(&guard.key)
+                    .arg(&guard.token)
+                    .arg(ttl.as_millis() as u64)
+                    .invoke_async::<_, i32>(&mut conn)
+                    .await
+                    .map_err(|e| Error::LockError(e.to_string()))?;
+                Ok(extended == 1)
+            }
+            None => {
+                let mut held = self.local.lock().await;
+                match held.get_mut(&guard.key) {
+                    Some((token, expires_at)) if token == &guard.token => {
+                        *expires_at = Instant::now() + ttl;
+                        Ok(true)
+                    }
+                    _ => Ok(false),
+                }
+            }
+        }
+    }
+}
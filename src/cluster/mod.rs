@@ -0,0 +1,16 @@
+pub mod lock;
+pub mod ownership;
+pub mod presence;
+pub mod rpc;
+pub mod scheduler;
+pub mod snapshot;
+
+use std::sync::OnceLock;
+use uuid::Uuid;
+
+// Identity of this server instance in the cluster: NODE_ID, or a random id
+// for this process
+pub fn node_id() -> &'static str {
+    static NODE_ID: OnceLock<String> = OnceLock::new();
+    NODE_ID.get_or_init(|| std::env::var("NODE_ID").unwrap_or_else(|_| Uuid::new_v4().to_string()))
+}
\ No newline at end of file
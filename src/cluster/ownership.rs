@@ -0,0 +1,218 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::Duration;
+use redis::aio::MultiplexedConnection;
+use tokio::sync::{Mutex, OnceCell};
+use uuid::Uuid;
+
+use crate::error::{Error, Result};
+use crate::supervisor;
+use super::node_id;
+
+// An owner that stops renewing loses its matches after this long
+const LEASE_TTL: Duration = Duration::from_secs(30);
+const RENEW_INTERVAL: Duration = Duration::from_secs(10);
+
+// Extend or delete the lease only while this node still holds it
+const RENEW_SCRIPT: &str = r#"
+if redis.call("GET", KEYS[1]) == ARGV[1] then
+    return redis.call("PEXPIRE", KEYS[1], ARGV[2])
+else
+    return 0
+end
+"#;
+const RELEASE_SCRIPT: &str = r#"
+if redis.call("GET", KEYS[1]) == ARGV[1] then
+    return redis.call("DEL", KEYS[1])
+else
+    return 0
+end
+"#;
+// Move the lease to another node only while the expected node holds it
+const TRANSFER_SCRIPT: &str = r#"
+if redis.call("GET", KEYS[1]) == ARGV[1] then
+    return redis.call("SET", KEYS[1], ARGV[2], "PX", ARGV[3]) and 1 or 0
+else
+    return 0
+end
+"#;
+
+fn lease_key(match_id: Uuid) -> String {
+    format!("spv:match:owner:{}", match_id)
+}
+
+fn cluster_error(e: redis::RedisError) -> Error {
+    Error::ClusterError(e.to_string())
+}
+
+// Which node owns each running match.
+//
+// The owner runs the match's timers and arbitrates it; other nodes forward
+// commands about the match to it. Ownership is a Redis lease taken when the
+// match starts and renewed until it ends. Without Redis there is a single
+// node, which owns everything.
+pub struct MatchOwnership {
+    redis: Option<redis::Client>,
+    conn: OnceCell<MultiplexedConnection>,
+    renew_script: redis::Script,
+    release_script: redis::Script,
+    transfer_script: redis::Script,
+    // Leases held by this node
+    owned: Mutex<HashSet<Uuid>>,
+}
+
+impl MatchOwnership {
+    pub fn from_env() -> Arc<Self> {
+        let redis = crate::secrets::get("REDIS_URL")
+            .and_then(|url| redis::Client::open(url.as_str()).ok());
+
+        let ownership = Arc::new(Self {
+            redis,
+            conn: OnceCell::new(),
+            renew_script: redis::Script::new(RENEW_SCRIPT),
+            release_script: redis::Script::new(RELEASE_SCRIPT),
+            transfer_script: redis::Script::new(TRANSFER_SCRIPT),
+            owned: Mutex::new(HashSet::new()),
+        });
+        if ownership.redis.is_some() {
+            ownership.clone().spawn_renewer();
+        }
+        ownership
+    }
+
+    async fn connection(&self, client: &redis::Client) -> Result<MultiplexedConnection> {
+        let conn = self.conn.get_or_try_init(|| async {
+            client.get_multiplexed_async_connection()
+                .await
+                .map_err(cluster_error)
+        }).await?;
+
+        Ok(conn.clone())
+    }
+
+    // Take ownership of a match; false if another node already has it
+    pub async fn claim(&self, match_id: Uuid) -> Result<bool> {
+        if let Some(client) = &self.redis {
+            let mut conn = self.connection(client).await?;
+            let reply = redis::cmd("SET")
+                .arg(lease_key(match_id))
+                .arg(node_id())
+                .arg("NX")
+                .arg("PX")
+                .arg(LEASE_TTL.as_millis() as u64)
+                .query_async::<_, Option<String>>(&mut conn)
+                .await
+                .map_err(cluster_error)?;
+            if reply.is_none() {
+                return Ok(false);
+            }
+        }
+        self.owned.lock().await.insert(match_id);
+        Ok(true)
+    }
+
+    pub async fn release(&self, match_id: Uuid) -> Result<()> {
+        if !self.owned.lock().await.remove(&match_id) {
+            return Ok(());
+        }
+        let Some(client) = &self.redis else {
+            return Ok(());
+        };
+        let mut conn = self.connection(client).await?;
+        self.release_script
+            .key(lease_key(match_id))
+            .arg(node_id())
+            .invoke_async::<_, i32>(&mut conn)
+            .await
+            .map_err(cluster_error)?;
+        Ok(())
+    }
+
+    // Take over a match from `from_node`; false if that node no longer owns it
+    pub async fn adopt(&self, match_id: Uuid, from_node: &str) -> Result<bool> {
+        if let Some(client) = &self.redis {
+            let mut conn = self.connection(client).await?;
+            let moved = self.transfer_script
+                .key(lease_key(match_id))
+                .arg(from_node)
+                .arg(node_id())
+                .arg(LEASE_TTL.as_millis() as u64)
+                .invoke_async::<_, i32>(&mut conn)
+                .await
+                .map_err(cluster_error)?;
+            if moved == 0 {
+                return Ok(false);
+            }
+        }
+        self.owned.lock().await.insert(match_id);
+        Ok(true)
+    }
+
+    // Stop renewing a lease that was handed to another node
+    pub async fn forget(&self, match_id: Uuid) {
+        self.owned.lock().await.remove(&match_id);
+    }
+
+    pub async fn owns(&self, match_id: Uuid) -> bool {
+        self.owned.lock().await.contains(&match_id)
+    }
+
+    pub async fn owned_count(&self) -> usize {
+        self.owned.lock().await.len()
+    }
+
+    // Node owning the match when that is another node; None when it is this
+    // node or the match has no owner
+    pub async fn remote_owner(&self, match_id: Uuid) -> Result<Option<String>> {
+        let Some(client) = &self.redis else {
+            return Ok(None);
+        };
+        if self.owned.lock().await.contains(&match_id) {
+            return Ok(None);
+        }
+        let mut conn = self.connection(client).await?;
+        let owner = redis::cmd("GET")
+            .arg(lease_key(match_id))
+            .query_async::<_, Option<String>>(&mut conn)
+            .await
+            .map_err(cluster_error)?;
+        Ok(owner.filter(|owner| owner != node_id()))
+    }
+
+    fn spawn_renewer(self: Arc<Self>) {
+        supervisor::spawn_loop("lease_renewer", move || {
+            let ownership = self.clone();
+            async move {
+                let mut interval = tokio::time::interval(RENEW_INTERVAL);
+                loop {
+                    interval.tick().await;
+                    if let Err(e) = ownership.renew().await {
+                        tracing::warn!("Failed to renew match leases: {}", e);
+                    }
+                }
+            }
+        });
+    }
+
+    async fn renew(&self) -> Result<()> {
+        let Some(client) = &self.redis else {
+            return Ok(());
+        };
+        let owned: Vec<Uuid> = self.owned.lock().await.iter().copied().collect();
+        let mut conn = self.connection(client).await?;
+        for match_id in owned {
+            let renewed = self.renew_script
+                .key(lease_key(match_id))
+                .arg(node_id())
+                .arg(LEASE_TTL.as_millis() as u64)
+                .invoke_async::<_, i32>(&mut conn)
+                .await
+                .map_err(cluster_error)?;
+            if renewed == 0 {
+                tracing::warn!("Lost ownership of match {}", match_id);
+                self.owned.lock().await.remove(&match_id);
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,466 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use chrono::Utc;
+use futures_util::StreamExt;
+use redis::aio::MultiplexedConnection;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::sync::{Mutex, OnceCell, broadcast};
+use uuid::Uuid;
+
+use crate::correlation;
+use crate::devices::device::SessionEnded;
+use crate::error::{Error, Result};
+use crate::lfg::post::LfgPost;
+use crate::supervisor;
+use super::node_id;
+use super::rpc::{ClusterRpc, DeliverRequest};
+
+// Channel every node listens on, for fleet-wide match broadcasts
+const BROADCAST_CHANNEL: &str = "spv:cluster:broadcast";
+// A node's presence entries lapse unless refreshed within this time, so a
+// crashed node stops receiving messages for its former users
+const PRESENCE_TTL: Duration = Duration::from_secs(60);
+const PRESENCE_REFRESH_INTERVAL: Duration = Duration::from_secs(20);
+// Where a user is connected is looked up at most this often; game ticks
+// would otherwise ask once per tick
+const LOOKUP_CACHE_TTL: Duration = Duration::from_secs(1);
+// Wait before resubscribing after the pub/sub connection drops
+const RESUBSCRIBE_DELAY: Duration = Duration::from_secs(5);
+
+// Sorted set of the nodes a user is connected to, scored by expiry time
+fn presence_key(user_id: Uuid) -> String {
+    format!("spv:presence:{}", user_id)
+}
+
+// Channel of messages addressed to one node
+fn node_channel(node: &str) -> String {
+    format!("spv:cluster:node:{}", node)
+}
+
+fn cluster_error(e: redis::RedisError) -> Error {
+    Error::ClusterError(e.to_string())
+}
+
+// Server message to deliver to connections on other nodes
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ClusterMessage {
+    // Push `event` to every connection of these users
+    ToUsers {
+        user_ids: Vec<Uuid>,
+        event: String,
+        data: Value,
+    },
+    // Push `event` to every connection in the match
+    ToMatch {
+        match_id: Uuid,
+        event: String,
+        data: Value,
+    },
+    // Client command about a match owned by the receiving node, from a player
+    // connected elsewhere
+    Command {
+        match_id: Uuid,
+        user_id: Uuid,
+        cmd: String,
+        data: Value,
+    },
+    // Close the user's sessions, only those of `device_id` when set, after
+    // sending them `notice`
+    EndSessions {
+        user_id: Uuid,
+        device_id: Option<String>,
+        notice: SessionEnded,
+    },
+    // Push the looking-for-group post to subscribed idle connections
+    Lfg {
+        post: LfgPost,
+    },
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Envelope {
+    from: String,
+    message: ClusterMessage,
+    // Correlation id of the client command behind the message, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    correlation_id: Option<Uuid>,
+}
+
+// A message from another node, as handed to subscribers
+#[derive(Debug, Clone)]
+pub struct Incoming {
+    pub message: ClusterMessage,
+    pub correlation_id: Option<Uuid>,
+}
+
+// Which node each user is connected to, and delivery of server messages
+// across nodes.
+//
+// Backed by Redis pub/sub when REDIS_URL is set; messages for specific users go
+// over the internal API instead when it is enabled. Without Redis the server
+// runs as a single node: every user is local and nothing is sent anywhere.
+pub struct Presence {
+    redis: Option<redis::Client>,
+    rpc: Arc<ClusterRpc>,
+    conn: OnceCell<MultiplexedConnection>,
+    // Open connections per local user
+    local: Mutex<HashMap<Uuid, usize>>,
+    // Recently looked up nodes of remote users
+    lookups: Mutex<HashMap<Uuid, (Instant, Vec<String>)>>,
+    incoming: broadcast::Sender<Incoming>,
+}
+
+impl Presence {
+    pub fn from_env(rpc: Arc<ClusterRpc>) -> Arc<Self> {
+        let redis = match crate::secrets::get("REDIS_URL") {
+            Some(url) => match redis::Client::open(url.as_str()) {
+                Ok(client) => {
+                    tracing::info!("Cluster presence backed by Redis, node {}", node_id());
+                    Some(client)
+                }
+                Err(e) => {
+                    tracing::warn!("Invalid REDIS_URL, running as a single node: {}", e);
+                    None
+                }
+            },
+            None => None,
+        };
+        let (incoming, _) = broadcast::channel(1024);
+
+        let presence = Arc::new(Self {
+            redis,
+            rpc,
+            conn: OnceCell::new(),
+            local: Mutex::new(HashMap::new()),
+            lookups: Mutex::new(HashMap::new()),
+            incoming,
+        });
+        if presence.redis.is_some() {
+            presence.clone().spawn_refresher();
+            presence.clone().spawn_subscriber();
+        }
+        presence
+    }
+
+    // Messages other nodes sent for connections on this one
+    pub fn subscribe(&self) -> broadcast::Receiver<Incoming> {
+        self.incoming.subscribe()
+    }
+
+    async fn connection(&self, client: &redis::Client) -> Result<MultiplexedConnection> {
+        let conn = self.conn.get_or_try_init(|| async {
+            client.get_multiplexed_async_connection()
+                .await
+                .map_err(cluster_error)
+        }).await?;
+
+        Ok(conn.clone())
+    }
+
+    // A connection of the user opened on this node
+    pub async fn connected(&self, user_id: Uuid) -> Result<()> {
+        let first = {
+            let mut local = self.local.lock().await;
+            let count = local.entry(user_id).or_insert(0);
+            *count += 1;
+            *count == 1
+        };
+        if first {
+            self.announce(&[user_id]).await?;
+        }
+        Ok(())
+    }
+
+    // A connection of the user on this node closed
+    pub async fn disconnected(&self, user_id: Uuid) -> Result<()> {
+        let last = {
+            let mut local = self.local.lock().await;
+            match local.get_mut(&user_id) {
+                Some(count) if *count > 1 => {
+                    *count -= 1;
+                    false
+                }
+                Some(_) => {
+                    local.remove(&user_id);
+                    true
+                }
+                None => false,
+            }
+        };
+        let Some(client) = &self.redis else {
+            return Ok(());
+        };
+        if last {
+            let mut conn = self.connection(client).await?;
+            redis::cmd("ZREM")
+                .arg(presence_key(user_id))
+                .arg(node_id())
+                .query_async::<_, ()>(&mut conn)
+                .await
+                .map_err(cluster_error)?;
+        }
+        Ok(())
+    }
+
+    // Record (or refresh) this node as a home of these users and drop lapsed entries
+    async fn announce(&self, user_ids: &[Uuid]) -> Result<()> {
+        let Some(client) = &self.redis else {
+            return Ok(());
+        };
+        if user_ids.is_empty() {
+            return Ok(());
+        }
+        let now = Utc::now().timestamp_millis();
+        let expires_at = now + PRESENCE_TTL.as_millis() as i64;
+
+        let mut pipe = redis::pipe();
+        for &user_id in user_ids {
+            let key = presence_key(user_id);
+            pipe.cmd("ZADD").arg(&key).arg(expires_at).arg(node_id()).ignore()
+                .cmd("ZREMRANGEBYSCORE").arg(&key).arg("-inf").arg(now).ignore()
+                .cmd("PEXPIRE").arg(&key).arg(PRESENCE_TTL.as_millis() as u64).ignore();
+        }
+        let mut conn = self.connection(client).await?;
+        pipe.query_async::<_, ()>(&mut conn)
+            .await
+            .map_err(cluster_error)
+    }
+
+    fn spawn_refresher(self: Arc<Self>) {
+        supervisor::spawn_loop("presence_refresh", move || {
+            let presence = self.clone();
+            async move {
+                let mut interval = tokio::time::interval(PRESENCE_REFRESH_INTERVAL);
+                loop {
+                    interval.tick().await;
+                    let users: Vec<Uuid> = presence.local.lock().await.keys().copied().collect();
+                    if let Err(e) = presence.announce(&users).await {
+                        tracing::warn!("Failed to refresh presence of {} users: {}", users.len(), e);
+                    }
+                }
+            }
+        });
+    }
+
+    // Those of the users connected to no node. Asks Redis afresh rather than
+    // trusting the lookup cache, since a wrong answer loses the message.
+    pub async fn offline(&self, user_ids: &[Uuid]) -> Result<Vec<Uuid>> {
+        let candidates: Vec<Uuid> = {
+            let local = self.local.lock().await;
+            user_ids.iter().filter(|user_id| !local.contains_key(user_id)).copied().collect()
+        };
+        let Some(client) = &self.redis else {
+            return Ok(candidates);
+        };
+        if candidates.is_empty() {
+            return Ok(candidates);
+        }
+
+        let now = Utc::now().timestamp_millis();
+        let mut pipe = redis::pipe();
+        for &user_id in &candidates {
+            pipe.cmd("ZCOUNT").arg(presence_key(user_id)).arg(now).arg("+inf");
+        }
+        let mut conn = self.connection(client).await?;
+        let counts: Vec<u64> = pipe.query_async(&mut conn)
+            .await
+            .map_err(cluster_error)?;
+        Ok(candidates
+            .into_iter()
+            .zip(counts)
+            .filter(|(_, nodes)| *nodes == 0)
+            .map(|(user_id, _)| user_id)
+            .collect())
+    }
+
+    // Other nodes the users are connected to, with the users on each
+    async fn remote_nodes(&self, client: &redis::Client, user_ids: &[Uuid]) -> Result<HashMap<String, Vec<Uuid>>> {
+        let mut found: Vec<(Uuid, Vec<String>)> = Vec::with_capacity(user_ids.len());
+        let mut missing = Vec::new();
+        {
+            let mut lookups = self.lookups.lock().await;
+            lookups.retain(|_, (at, _)| at.elapsed() < LOOKUP_CACHE_TTL);
+            for &user_id in user_ids {
+                match lookups.get(&user_id) {
+                    Some((_, nodes)) => found.push((user_id, nodes.clone())),
+                    None => missing.push(user_id),
+                }
+            }
+        }
+
+        if !missing.is_empty() {
+            let now = Utc::now().timestamp_millis();
+            let mut pipe = redis::pipe();
+            for &user_id in &missing {
+                pipe.cmd("ZRANGEBYSCORE").arg(presence_key(user_id)).arg(now).arg("+inf");
+            }
+            let mut conn = self.connection(client).await?;
+            let nodes: Vec<Vec<String>> = pipe.query_async(&mut conn)
+                .await
+                .map_err(cluster_error)?;
+
+            let mut lookups = self.lookups.lock().await;
+            for (user_id, user_nodes) in missing.into_iter().zip(nodes) {
+                lookups.insert(user_id, (Instant::now(), user_nodes.clone()));
+                found.push((user_id, user_nodes));
+            }
+        }
+
+        let mut by_node: HashMap<String, Vec<Uuid>> = HashMap::new();
+        for (user_id, user_nodes) in found {
+            for node in user_nodes.into_iter().filter(|node| node != node_id()) {
+                by_node.entry(node).or_default().push(user_id);
+            }
+        }
+        Ok(by_node)
+    }
+
+    async fn publish(&self, client: &redis::Client, channel: &str, message: ClusterMessage) -> Result<()> {
+        let envelope = Envelope {
+            from: node_id().to_string(),
+            message,
+            correlation_id: correlation::current(),
+        };
+        let payload = serde_json::to_string(&envelope)
+            .map_err(|e| Error::ClusterError(e.to_string()))?;
+        let mut conn = self.connection(client).await?;
+        redis::cmd("PUBLISH")
+            .arg(channel)
+            .arg(payload)
+            .query_async::<_, ()>(&mut conn)
+            .await
+            .map_err(cluster_error)
+    }
+
+    // Deliver an event to the users' connections on other nodes
+    pub async fn send_to_users(&self, user_ids: &[Uuid], event: &str, data: &Value) -> Result<()> {
+        let Some(client) = &self.redis else {
+            return Ok(());
+        };
+        if user_ids.is_empty() {
+            return Ok(());
+        }
+        for (node, user_ids) in self.remote_nodes(client, user_ids).await? {
+            if self.rpc.enabled() {
+                let request = DeliverRequest {
+                    user_ids: user_ids.clone(),
+                    event: event.to_string(),
+                    data: data.clone(),
+                    correlation_id: correlation::current(),
+                };
+                match self.rpc.deliver(&node, &request).await {
+                    Ok(_) => continue,
+                    Err(e) => tracing::warn!("Direct delivery to node {} failed, publishing instead: {}", node, e),
+                }
+            }
+            let message = ClusterMessage::ToUsers {
+                user_ids,
+                event: event.to_string(),
+                data: data.clone(),
+            };
+            self.publish(client, &node_channel(&node), message).await?;
+        }
+        Ok(())
+    }
+
+    // Deliver an event to the match's connections on every other node
+    pub async fn broadcast_to_match(&self, match_id: Uuid, event: &str, data: &Value) -> Result<()> {
+        let Some(client) = &self.redis else {
+            return Ok(());
+        };
+        let message = ClusterMessage::ToMatch {
+            match_id,
+            event: event.to_string(),
+            data: data.clone(),
+        };
+        self.publish(client, BROADCAST_CHANNEL, message).await
+    }
+
+    // Deliver a looking-for-group post to every other node
+    pub async fn broadcast_lfg(&self, post: &LfgPost) -> Result<()> {
+        let Some(client) = &self.redis else {
+            return Ok(());
+        };
+        self.publish(client, BROADCAST_CHANNEL, ClusterMessage::Lfg { post: post.clone() }).await
+    }
+
+    // Hand a player's command to the node owning their match
+    pub async fn forward_command(&self, node: &str, match_id: Uuid, user_id: Uuid, cmd: &str, data: Value) -> Result<()> {
+        let Some(client) = &self.redis else {
+            return Err(Error::ClusterError("no cluster bus without REDIS_URL".to_string()));
+        };
+        let message = ClusterMessage::Command {
+            match_id,
+            user_id,
+            cmd: cmd.to_string(),
+            data,
+        };
+        self.publish(client, &node_channel(node), message).await
+    }
+
+    // Close the user's sessions on every other node; only those of `device_id`
+    // when given
+    pub async fn end_sessions(&self, user_id: Uuid, device_id: Option<&str>, notice: &SessionEnded) -> Result<()> {
+        let Some(client) = &self.redis else {
+            return Ok(());
+        };
+        for node in self.remote_nodes(client, &[user_id]).await?.into_keys() {
+            let message = ClusterMessage::EndSessions {
+                user_id,
+                device_id: device_id.map(str::to_string),
+                notice: notice.clone(),
+            };
+            self.publish(client, &node_channel(&node), message).await?;
+        }
+        Ok(())
+    }
+
+    fn spawn_subscriber(self: Arc<Self>) {
+        supervisor::spawn_loop("cluster_subscriber", move || {
+            let presence = self.clone();
+            async move {
+                loop {
+                    if let Err(e) = presence.listen().await {
+                        tracing::warn!("Cluster subscription lost, retrying: {}", e);
+                    }
+                    tokio::time::sleep(RESUBSCRIBE_DELAY).await;
+                }
+            }
+        });
+    }
+
+    async fn listen(&self) -> Result<()> {
+        let Some(client) = &self.redis else {
+            return Ok(());
+        };
+        let mut pubsub = client.get_async_pubsub().await.map_err(cluster_error)?;
+        pubsub.subscribe(BROADCAST_CHANNEL).await.map_err(cluster_error)?;
+        pubsub.subscribe(node_channel(node_id())).await.map_err(cluster_error)?;
+
+        let mut messages = pubsub.on_message();
+        while let Some(msg) = messages.next().await {
+            let payload: String = match msg.get_payload() {
+                Ok(payload) => payload,
+                Err(e) => {
+                    tracing::warn!("Unreadable cluster message: {}", e);
+                    continue;
+                }
+            };
+            match serde_json::from_str::<Envelope>(&payload) {
+                // Our own fleet-wide broadcasts come back to us too
+                Ok(envelope) if envelope.from == node_id() => {}
+                Ok(envelope) => {
+                    let _ = self.incoming.send(Incoming {
+                        message: envelope.message,
+                        correlation_id: envelope.correlation_id,
+                    });
+                }
+                Err(e) => tracing::warn!("Malformed cluster message: {}", e),
+            }
+        }
+        Err(Error::ClusterError("subscription closed".to_string()))
+    }
+}
@@ -0,0 +1,237 @@
+use std::sync::Arc;
+use std::time::Duration;
+use chrono::Utc;
+use redis::aio::MultiplexedConnection;
+use reqwest::RequestBuilder;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::sync::OnceCell;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::config::ClusterConfig;
+use crate::error::{Error, Result};
+use crate::jobs::job::Job;
+use crate::matchmaking::service::Capabilities;
+use super::node_id;
+
+// Hash of node id -> advertised address
+const NODES_KEY: &str = "spv:nodes";
+// A node's address is dropped this long after it stops re-advertising
+const ADVERTISE_TTL: Duration = Duration::from_secs(60);
+const ADVERTISE_INTERVAL: Duration = Duration::from_secs(20);
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+pub const TOKEN_HEADER: &str = "x-cluster-token";
+
+fn cluster_error(e: impl std::fmt::Display) -> Error {
+    Error::ClusterError(e.to_string())
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct NodeEntry {
+    url: String,
+    // Unix milliseconds
+    expires_at: i64,
+}
+
+// POST /internal/deliver
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeliverRequest {
+    pub user_ids: Vec<Uuid>,
+    pub event: String,
+    pub data: Value,
+    // Correlation id of the client command behind the event, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub correlation_id: Option<Uuid>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeliverReply {
+    // Connections the event was pushed to
+    pub delivered: usize,
+}
+
+// POST /internal/matches/{match_id}/adopt
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AdoptRequest {
+    // Node handing the match over; it must still hold the lease
+    pub from_node: String,
+}
+
+// GET /internal/health
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct NodeHealth {
+    pub node_id: String,
+    // Open WebSocket/SSE connections
+    pub connections: usize,
+    // Running matches this node owns
+    pub owned_matches: usize,
+    // Runs the singleton background jobs
+    pub leader: bool,
+    pub capabilities: Capabilities,
+}
+
+// A node known to the cluster
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct NodeAddress {
+    pub node_id: String,
+    pub url: String,
+}
+
+// Client of the internal node-to-node API, and the directory of node addresses.
+//
+// Nodes advertise CLUSTER_ADVERTISE_URL in Redis and call each other over
+// HTTP with the shared CLUSTER_TOKEN. Without Redis or a token the API is off.
+pub struct ClusterRpc {
+    token: Option<String>,
+    http: reqwest::Client,
+    redis: Option<redis::Client>,
+    conn: OnceCell<MultiplexedConnection>,
+}
+
+impl ClusterRpc {
+    pub fn new(config: &ClusterConfig) -> Arc<Self> {
+        let redis = crate::secrets::get("REDIS_URL")
+            .and_then(|url| redis::Client::open(url.as_str()).ok());
+        let http = reqwest::Client::builder()
+            .timeout(REQUEST_TIMEOUT)
+            .build()
+            .unwrap_or_default();
+
+        let rpc = Arc::new(Self {
+            token: config.token.clone(),
+            http,
+            redis,
+            conn: OnceCell::new(),
+        });
+        match (&config.advertise_url, rpc.enabled()) {
+            (Some(url), true) => rpc.clone().spawn_advertiser(url.clone()),
+            (None, true) => tracing::warn!("CLUSTER_ADVERTISE_URL not set, other nodes can't call this one"),
+            _ => {}
+        }
+        rpc
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.token.is_some() && self.redis.is_some()
+    }
+
+    async fn connection(&self) -> Result<MultiplexedConnection> {
+        let client = self.redis.as_ref()
+            .ok_or_else(|| Error::ClusterError("cluster RPC is disabled".to_string()))?;
+        let conn = self.conn.get_or_try_init(|| async {
+            client.get_multiplexed_async_connection()
+                .await
+                .map_err(cluster_error)
+        }).await?;
+
+        Ok(conn.clone())
+    }
+
+    fn spawn_advertiser(self: Arc<Self>, url: String) {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(ADVERTISE_INTERVAL);
+            loop {
+                interval.tick().await;
+                if let Err(e) = self.advertise(&url).await {
+                    tracing::warn!("Failed to advertise node address: {}", e);
+                }
+            }
+        });
+    }
+
+    async fn advertise(&self, url: &str) -> Result<()> {
+        let entry = NodeEntry {
+            url: url.to_string(),
+            expires_at: Utc::now().timestamp_millis() + ADVERTISE_TTL.as_millis() as i64,
+        };
+        let entry = serde_json::to_string(&entry).map_err(cluster_error)?;
+        let mut conn = self.connection().await?;
+        redis::cmd("HSET")
+            .arg(NODES_KEY)
+            .arg(node_id())
+            .arg(entry)
+            .query_async::<_, ()>(&mut conn)
+            .await
+            .map_err(cluster_error)
+    }
+
+    // Nodes that advertised recently; lapsed entries are cleared on the way
+    pub async fn nodes(&self) -> Result<Vec<NodeAddress>> {
+        let mut conn = self.connection().await?;
+        let entries: Vec<(String, String)> = redis::cmd("HGETALL")
+            .arg(NODES_KEY)
+            .query_async(&mut conn)
+            .await
+            .map_err(cluster_error)?;
+
+        let now = Utc::now().timestamp_millis();
+        let mut nodes = Vec::new();
+        for (node, entry) in entries {
+            match serde_json::from_str::<NodeEntry>(&entry) {
+                Ok(entry) if entry.expires_at > now => nodes.push(NodeAddress { node_id: node, url: entry.url }),
+                _ => {
+                    let _: std::result::Result<(), _> = redis::cmd("HDEL")
+                        .arg(NODES_KEY)
+                        .arg(&node)
+                        .query_async(&mut conn)
+                        .await;
+                }
+            }
+        }
+        nodes.sort_by(|a, b| a.node_id.cmp(&b.node_id));
+        Ok(nodes)
+    }
+
+    async fn node_url(&self, node: &str) -> Result<String> {
+        self.nodes()
+            .await?
+            .into_iter()
+            .find(|n| n.node_id == node)
+            .map(|n| n.url)
+            .ok_or_else(|| Error::NotFound(format!("node {}", node)))
+    }
+
+    async fn call<T: DeserializeOwned>(&self, node: &str, request: RequestBuilder) -> Result<T> {
+        let token = self.token.as_deref()
+            .ok_or_else(|| Error::ClusterError("cluster RPC is disabled".to_string()))?;
+        let response = request
+            .header(TOKEN_HEADER, token)
+            .send()
+            .await
+            .map_err(|e| Error::ClusterError(format!("node {} unreachable: {}", node, e)))?;
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(Error::ClusterError(format!("node {} answered {}: {}", node, status, body)));
+        }
+        response.json().await.map_err(cluster_error)
+    }
+
+    // Push an event to users connected to `node`
+    pub async fn deliver(&self, node: &str, request: &DeliverRequest) -> Result<DeliverReply> {
+        let url = format!("{}/internal/deliver", self.node_url(node).await?);
+        self.call(node, self.http.post(url).json(request)).await
+    }
+
+    // Ask `node` to take over a match this node owns
+    pub async fn adopt_match(&self, node: &str, match_id: Uuid) -> Result<()> {
+        let url = format!("{}/internal/matches/{}/adopt", self.node_url(node).await?, match_id);
+        let request = AdoptRequest { from_node: node_id().to_string() };
+        let _: Value = self.call(node, self.http.post(url).json(&request)).await?;
+        Ok(())
+    }
+
+    // Cancel a job running on `node`
+    pub async fn cancel_job(&self, node: &str, job_id: Uuid) -> Result<Job> {
+        let url = format!("{}/internal/jobs/{}/cancel", self.node_url(node).await?, job_id);
+        self.call(node, self.http.post(url)).await
+    }
+
+    pub async fn health(&self, node: &str) -> Result<NodeHealth> {
+        let url = format!("{}/internal/health", self.node_url(node).await?);
+        self.call(node, self.http.get(url)).await
+    }
+}
@@ -0,0 +1,158 @@
+use std::future::Future;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use redis::aio::MultiplexedConnection;
+use tokio::sync::OnceCell;
+
+use crate::error::{Error, Result};
+use crate::supervisor;
+use super::node_id;
+
+const LEADER_KEY: &str = "spv:leader";
+// A leader that stops renewing is replaced after this long
+const LEASE_TTL: Duration = Duration::from_secs(15);
+const CAMPAIGN_INTERVAL: Duration = Duration::from_secs(5);
+
+// Extend the lease only while this node still holds it
+const RENEW_SCRIPT: &str = r#"
+if redis.call("GET", KEYS[1]) == ARGV[1] then
+    return redis.call("PEXPIRE", KEYS[1], ARGV[2])
+else
+    return 0
+end
+"#;
+
+fn cluster_error(e: redis::RedisError) -> Error {
+    Error::ClusterError(e.to_string())
+}
+
+// Elects the one node that runs singleton background jobs.
+//
+// The leader holds a Redis lease and renews it every few seconds; the other
+// nodes keep trying to take it, so a new leader is in place within
+// LEASE_TTL of the old one going away. A node that can't reach Redis steps
+// down rather than risk two leaders. Without Redis the single node leads.
+pub struct LeaderElection {
+    redis: Option<redis::Client>,
+    conn: OnceCell<MultiplexedConnection>,
+    renew_script: redis::Script,
+    leader: AtomicBool,
+}
+
+impl LeaderElection {
+    // Campaigns once before returning, so jobs started right after see the outcome
+    pub async fn start() -> Arc<Self> {
+        let redis = crate::secrets::get("REDIS_URL")
+            .and_then(|url| redis::Client::open(url.as_str()).ok());
+
+        let election = Arc::new(Self {
+            leader: AtomicBool::new(redis.is_none()),
+            redis,
+            conn: OnceCell::new(),
+            renew_script: redis::Script::new(RENEW_SCRIPT),
+        });
+        if election.redis.is_some() {
+            election.campaign().await;
+            let campaigner = election.clone();
+            supervisor::spawn_loop("leader_campaign", move || {
+                let campaigner = campaigner.clone();
+                async move {
+                    let mut interval = tokio::time::interval(CAMPAIGN_INTERVAL);
+                    interval.tick().await;
+                    loop {
+                        interval.tick().await;
+                        campaigner.campaign().await;
+                    }
+                }
+            });
+        }
+        election
+    }
+
+    pub fn is_leader(&self) -> bool {
+        self.leader.load(Ordering::Relaxed)
+    }
+
+    async fn connection(&self, client: &redis::Client) -> Result<MultiplexedConnection> {
+        let conn = self.conn.get_or_try_init(|| async {
+            client.get_multiplexed_async_connection()
+                .await
+                .map_err(cluster_error)
+        }).await?;
+
+        Ok(conn.clone())
+    }
+
+    async fn campaign(&self) {
+        let was_leader = self.is_leader();
+        let leader = match self.try_lead(was_leader).await {
+            Ok(leader) => leader,
+            Err(e) => {
+                tracing::warn!("Leader election failed: {}", e);
+                false
+            }
+        };
+        self.leader.store(leader, Ordering::Relaxed);
+        if leader && !was_leader {
+            tracing::info!("Node {} is now the leader", node_id());
+        } else if !leader && was_leader {
+            tracing::warn!("Node {} is no longer the leader", node_id());
+        }
+    }
+
+    // Renew our lease, or take a free one
+    async fn try_lead(&self, renew: bool) -> Result<bool> {
+        let Some(client) = &self.redis else {
+            return Ok(true);
+        };
+        let mut conn = self.connection(client).await?;
+        let ttl = LEASE_TTL.as_millis() as u64;
+
+        if renew {
+            let renewed = self.renew_script
+                .key(LEADER_KEY)
+                .arg(node_id())
+                .arg(ttl)
+                .invoke_async::<_, i32>(&mut conn)
+                .await
+                .map_err(cluster_error)?;
+            if renewed == 1 {
+                return Ok(true);
+            }
+        }
+
+        let reply = redis::cmd("SET")
+            .arg(LEADER_KEY)
+            .arg(node_id())
+            .arg("NX")
+            .arg("PX")
+            .arg(ttl)
+            .query_async::<_, Option<String>>(&mut conn)
+            .await
+            .map_err(cluster_error)?;
+        Ok(reply.is_some())
+    }
+}
+
+// Run `job` every `period` on the leader only. The first run is one period
+// after start unless `run_now` is set. A run that panics is reported under
+// `task` and the next one happens on schedule.
+pub fn spawn_singleton<F, Fut>(task: &'static str, leader: Arc<LeaderElection>, period: Duration, run_now: bool, job: F)
+where
+    F: Fn() -> Fut + Send + 'static,
+    Fut: Future<Output = ()> + Send,
+{
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(period);
+        if !run_now {
+            interval.tick().await;
+        }
+        loop {
+            interval.tick().await;
+            if leader.is_leader() {
+                supervisor::catch(task, job()).await;
+            }
+        }
+    });
+}
@@ -0,0 +1,228 @@
+use std::sync::Arc;
+use std::time::Duration;
+use chrono::{DateTime, Utc};
+use redis::aio::MultiplexedConnection;
+use serde::{Deserialize, Serialize};
+use tokio::sync::OnceCell;
+
+use crate::config::SnapshotConfig;
+use crate::error::{Error, Result};
+use crate::game::runtime::{GameRuntime, MatchSnapshot};
+use crate::matchmaking::pools::PoolKey;
+use crate::matchmaking::service::MatchService;
+use crate::matchmaking::write_queue::PendingWrite;
+use crate::models::game::MatchRoom;
+use crate::supervisor;
+use super::node_id;
+
+// Snapshots outlive their node this long, for a restart under the same NODE_ID
+const SNAPSHOT_TTL: Duration = Duration::from_secs(3600);
+// A node whose snapshot is this many intervals old is taken to be gone
+const MISSED_SNAPSHOTS: u32 = 3;
+
+fn snapshot_key(node: &str) -> String {
+    format!("spv:snapshot:{}", node)
+}
+
+fn cluster_error(e: impl std::fmt::Display) -> Error {
+    Error::ClusterError(e.to_string())
+}
+
+// In-memory state of one node at a point in time
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NodeSnapshot {
+    pub node_id: String,
+    pub taken_at: DateTime<Utc>,
+    // Queued, ready and running rooms
+    pub rooms: Vec<(PoolKey, MatchRoom)>,
+    // Game loops of running matches
+    pub matches: Vec<MatchSnapshot>,
+    // Match starts, discoveries and match ends not yet written to the database
+    pub pending_writes: Vec<PendingWrite>,
+}
+
+impl NodeSnapshot {
+    fn age(&self) -> Duration {
+        (Utc::now() - self.taken_at).to_std().unwrap_or_default()
+    }
+}
+
+// Replication of in-memory state through Redis, for fast failover.
+//
+// Every SNAPSHOT_INTERVAL_SECS each node saves its rooms, running matches and
+// pending database writes. A node restarted under the same NODE_ID picks its
+// own snapshot back up. A node started with STANDBY_FOR=<node> serves as
+// usual and also follows that node's snapshots; once they stop coming it
+// takes the node's state over, so its matches carry on after a short pause.
+// Off without Redis.
+pub struct Replication {
+    redis: redis::Client,
+    conn: OnceCell<MultiplexedConnection>,
+    interval: Duration,
+    standby_for: Option<String>,
+    match_service: Arc<MatchService>,
+    game: Arc<GameRuntime>,
+}
+
+impl Replication {
+    pub async fn start(config: &SnapshotConfig, match_service: Arc<MatchService>, game: Arc<GameRuntime>) {
+        let Some(interval) = config.interval else {
+            return;
+        };
+        let Some(redis) = crate::secrets::get("REDIS_URL")
+            .and_then(|url| redis::Client::open(url.as_str()).ok())
+        else {
+            if config.standby_for.is_some() {
+                tracing::warn!("STANDBY_FOR needs REDIS_URL, not standing by");
+            }
+            return;
+        };
+
+        let replication = Arc::new(Self {
+            redis,
+            conn: OnceCell::new(),
+            interval,
+            standby_for: config.standby_for.clone().filter(|node| node != node_id()),
+            match_service,
+            game,
+        });
+
+        // Pick up where this node left off before saving over its snapshot
+        if let Err(e) = replication.take_over(node_id()).await {
+            tracing::warn!("Failed to restore own snapshot: {}", e);
+        }
+        replication.clone().spawn_snapshots();
+        if let Some(primary) = replication.standby_for.clone() {
+            tracing::info!("Standing by for node {}", primary);
+            replication.spawn_standby(primary);
+        }
+    }
+
+    async fn connection(&self) -> Result<MultiplexedConnection> {
+        let conn = self.conn.get_or_try_init(|| async {
+            self.redis.get_multiplexed_async_connection()
+                .await
+                .map_err(cluster_error)
+        }).await?;
+
+        Ok(conn.clone())
+    }
+
+    fn spawn_snapshots(self: Arc<Self>) {
+        supervisor::spawn_loop("state_snapshots", move || {
+            let replication = self.clone();
+            async move {
+                let mut interval = tokio::time::interval(replication.interval);
+                loop {
+                    interval.tick().await;
+                    if let Err(e) = replication.save().await {
+                        tracing::warn!("Failed to save state snapshot: {}", e);
+                    }
+                }
+            }
+        });
+    }
+
+    async fn save(&self) -> Result<()> {
+        let (rooms, pending_writes) = self.match_service.snapshot().await;
+        let snapshot = NodeSnapshot {
+            node_id: node_id().to_string(),
+            taken_at: Utc::now(),
+            rooms,
+            matches: self.game.snapshot().await,
+            pending_writes,
+        };
+        let payload = serde_json::to_string(&snapshot).map_err(cluster_error)?;
+        let mut conn = self.connection().await?;
+        redis::cmd("SET")
+            .arg(snapshot_key(node_id()))
+            .arg(payload)
+            .arg("PX")
+            .arg(SNAPSHOT_TTL.as_millis() as u64)
+            .query_async::<_, ()>(&mut conn)
+            .await
+            .map_err(cluster_error)
+    }
+
+    async fn load(&self, node: &str) -> Result<Option<NodeSnapshot>> {
+        let mut conn = self.connection().await?;
+        let payload: Option<String> = redis::cmd("GET")
+            .arg(snapshot_key(node))
+            .query_async(&mut conn)
+            .await
+            .map_err(cluster_error)?;
+        payload
+            .map(|payload| serde_json::from_str(&payload).map_err(cluster_error))
+            .transpose()
+    }
+
+    // Follow the primary's snapshots until they stop, then take over
+    fn spawn_standby(self: Arc<Self>, primary: String) {
+        supervisor::spawn_loop("standby_watch", move || {
+            let replication = self.clone();
+            let primary = primary.clone();
+            async move {
+                let stale_after = replication.interval * MISSED_SNAPSHOTS;
+                let mut interval = tokio::time::interval(replication.interval);
+                loop {
+                    interval.tick().await;
+                    let snapshot = match replication.load(&primary).await {
+                        Ok(Some(snapshot)) => snapshot,
+                        // Not started yet, or already taken over
+                        Ok(None) => continue,
+                        Err(e) => {
+                            tracing::warn!("Failed to load snapshot of node {}: {}", primary, e);
+                            continue;
+                        }
+                    };
+                    if snapshot.age() < stale_after {
+                        continue;
+                    }
+                    tracing::warn!("Node {} stopped sending snapshots, taking over its state", primary);
+                    match replication.take_over(&primary).await {
+                        Ok(true) => break,
+                        Ok(false) => tracing::info!("State of node {} already taken over", primary),
+                        Err(e) => tracing::error!("Failed to take over node {}: {}", primary, e),
+                    }
+                }
+            }
+        });
+    }
+
+    // Restore a node's last snapshot here. The snapshot is removed in the
+    // same step, so only one node restores it; false if there was none.
+    async fn take_over(&self, node: &str) -> Result<bool> {
+        let mut conn = self.connection().await?;
+        let payload: Option<String> = redis::cmd("GETDEL")
+            .arg(snapshot_key(node))
+            .query_async(&mut conn)
+            .await
+            .map_err(cluster_error)?;
+        let Some(payload) = payload else {
+            return Ok(false);
+        };
+        let snapshot: NodeSnapshot = serde_json::from_str(&payload).map_err(cluster_error)?;
+        let age = snapshot.age();
+
+        let rooms = snapshot.rooms.len();
+        let writes = snapshot.pending_writes.len();
+        self.match_service.restore(snapshot.rooms, snapshot.pending_writes).await;
+
+        let mut resumed = 0;
+        for running in snapshot.matches {
+            match self.match_service.take_over(running.match_id, node).await {
+                Ok(true) => {
+                    self.game.clone().restore(running, age).await;
+                    resumed += 1;
+                }
+                Ok(false) => tracing::warn!("Match {} is owned by another node, not resuming it", running.match_id),
+                Err(e) => tracing::warn!("Failed to take over match {}: {}", running.match_id, e),
+            }
+        }
+        tracing::info!(
+            "Restored snapshot of node {} taken {:?} ago: {} rooms, {} matches resumed, {} pending writes",
+            node, age, rooms, resumed, writes
+        );
+        Ok(true)
+    }
+}
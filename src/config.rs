@@ -1,46 +1,1390 @@
-use std::sync::Arc;
+use std::collections::{BTreeMap, HashMap};
+use std::time::Duration;
 use dotenv::dotenv;
+use uuid::Uuid;
+
+use crate::client_ip::TrustedProxies;
+use crate::game::interest::InterestConfig;
+use crate::game::victory::VictoryConfig;
+use crate::telemetry::event::SamplingConfig;
 
 #[derive(Debug, Clone)]
 pub struct Config {
     pub server: ServerConfig,
     pub hasura: HasuraConfig,
+    pub offline: OfflineConfig,
+    pub matchmaking: MatchmakingConfig,
+    pub new_players: NewPlayerConfig,
+    pub rating: RatingConfig,
+    pub anticheat: AntiCheatConfig,
+    pub gateway: GatewayConfig,
+    pub game: GameConfig,
+    pub admin: AdminConfig,
+    pub cluster: ClusterConfig,
+    pub snapshot: SnapshotConfig,
+    // Fault injection for resilience tests; None unless CHAOS_ENABLED=true
+    pub chaos: Option<ChaosConfig>,
+    pub telemetry: TelemetryConfig,
+    pub heatmap: HeatmapConfig,
+    pub inbox: InboxConfig,
+    pub schedule: ScheduleConfig,
+    pub content: ContentConfig,
+    pub lfg: LfgConfig,
+    pub devices: DeviceConfig,
+    pub auth: AuthConfig,
+    pub signing: SigningConfig,
+    pub slow: SlowConfig,
+    pub cache: CacheConfig,
+    pub handshake: HandshakeConfig,
+    // In-memory repositories instead of Hasura; None unless started with --local
+    pub local: Option<LocalConfig>,
 }
 
 #[derive(Debug, Clone)]
 pub struct ServerConfig {
+    // Only used with the `grpc` feature; no gRPC server when unset
+    #[cfg_attr(not(feature = "grpc"), allow(dead_code))]
+    pub grpc_port: Option<u16>,
+    // Serve HTTPS/wss directly; plaintext when unset
+    pub tls: Option<TlsConfig>,
+    // Load balancers allowed to set X-Forwarded-For / Forwarded; none by default
+    pub trusted_proxies: TrustedProxies,
+}
+
+#[derive(Debug, Clone)]
+pub struct TlsConfig {
+    // Default certificate chain and private key (PEM)
+    pub cert_path: String,
+    pub key_path: String,
+    // Extra certificates selected by SNI host name
+    pub sni_certs: Vec<SniCert>,
+    // Plain HTTP port that redirects to HTTPS; no redirect listener when unset
+    pub redirect_port: Option<u16>,
+}
+
+#[derive(Debug, Clone)]
+pub struct SniCert {
     pub host: String,
-    pub port: u16,
+    pub cert_path: String,
+    pub key_path: String,
 }
 
+// The endpoint and credentials are read by HasuraClient itself, see HasuraAuth
 #[derive(Debug, Clone)]
 pub struct HasuraConfig {
-    pub endpoint: String,
-    pub admin_secret: String,
+    // Check the schema against what the repositories expect at startup
+    pub schema_check: bool,
+    // Apply pending migrations at startup
+    pub auto_migrate: bool,
+}
+
+// What to do with matches while the database is unreachable
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OfflinePolicy {
+    // Refuse match.start with Error::DbUnavailable
+    Refuse,
+    // Keep playing and queue writes for replay once the database is back
+    Queue,
+}
+
+impl OfflinePolicy {
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "refuse" => Some(OfflinePolicy::Refuse),
+            "queue" => Some(OfflinePolicy::Queue),
+            _ => None,
+        }
+    }
+
+    pub fn to_str(self) -> &'static str {
+        match self {
+            OfflinePolicy::Refuse => "refuse",
+            OfflinePolicy::Queue => "queue",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct OfflineConfig {
+    pub policy: OfflinePolicy,
+    pub probe_interval: Duration,
+}
+
+#[derive(Debug, Clone)]
+pub struct MatchmakingConfig {
+    // JSON file with the map zones; read from the `zones` table when unset
+    pub zones_file: Option<String>,
+    // How often scores of finished matches are checked against their discoveries
+    pub reconcile_interval: Duration,
+    // How far back the periodic check looks, by match end time
+    pub reconcile_window: Duration,
+    pub limits: LoadLimits,
+    pub priority: PriorityConfig,
+    pub fairness: FairnessConfig,
+}
+
+// Matchmaking quality report; see matchmaking::fairness
+#[derive(Debug, Clone)]
+pub struct FairnessConfig {
+    // How often the report is computed
+    pub interval: Duration,
+    // How far back each report looks, by match start and end time
+    pub window: Duration,
+    // Winning margin, as a share of the winner's score, that counts as a blowout
+    pub blowout_margin: f64,
+}
+
+// Queue priority tiers; see matchmaking::pools::QueueTier
+#[derive(Debug, Clone)]
+pub struct PriorityConfig {
+    // Players queued at the premium tier
+    pub premium: Vec<Uuid>,
+    // How long a player disconnected by the server keeps the returning tier
+    pub return_window: Duration,
+    // Rooms waiting longer than this are filled first, whatever their tier
+    pub starvation_wait: Duration,
+}
+
+// Caps on the load one node takes on; joins past them are refused as busy
+#[derive(Debug, Clone)]
+pub struct LoadLimits {
+    // Matches ready or running at once, of any type
+    pub max_matches: Option<usize>,
+    // The same, per match type
+    pub max_matches_per_type: HashMap<String, usize>,
+    // Players waiting in rooms that are still filling up
+    pub max_queued_players: Option<usize>,
+    // Database writes queued while it is offline; each is held in memory
+    pub max_pending_writes: Option<usize>,
+    // Suggested wait for refused players, before jitter
+    pub retry_after: Duration,
+}
+
+#[derive(Debug, Clone)]
+pub struct AntiCheatConfig {
+    // Fastest plausible movement, in map units per second
+    pub max_speed: f32,
+    // Longest plausible jump between two position reports
+    pub teleport_distance: f32,
+    // Players whose trust score drops below this are treated as spoofers
+    pub suspect_threshold: f32,
+    // Players whose trust score drops below this are quarantined for good;
+    // None leaves quarantine to admins
+    pub quarantine_threshold: Option<f32>,
+    // Trust regained every hour, whatever the player does
+    pub recovery_per_hour: f32,
+}
+
+#[derive(Debug, Clone)]
+pub struct GameConfig {
+    // Ticks per second of the per-match loop; 0 relays positions per message
+    pub tick_hz: u32,
+    // Matches are ended automatically after this long; no limit when unset
+    pub match_duration: Option<Duration>,
+    // Distance under which opponents produce a proximity hint
+    pub proximity_radius: f32,
+    // Whose positions each player receives, per match type
+    pub interest: InterestConfig,
+    // How the winner is decided, per match type
+    pub victory: VictoryConfig,
+    pub pings: PingConfig,
+    pub emotes: EmoteConfig,
+    // Short-lived TURN credentials for team voice; None unless TURN_SECRET is set
+    pub turn: Option<TurnConfig>,
+    pub respawn: RespawnConfig,
+    pub capture: CaptureConfig,
+    pub lobby: LobbyConfig,
+}
+
+#[derive(Debug, Clone)]
+pub struct LobbyConfig {
+    // Time between the teams being picked and the match starting; no lobby when zero
+    pub duration: Duration,
+    // What players may pick with lobby.select; nothing to pick when empty
+    pub loadouts: Vec<String>,
+    pub roles: Vec<String>,
+    // Most lobby.chat messages a player can send per window
+    pub chat_limit: usize,
+    pub chat_window: Duration,
+    // Longest chat message, in characters
+    pub chat_max_len: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct CaptureConfig {
+    // JSON array of capture zones; no capture zones when unset
+    pub file: Option<String>,
+    // How long a team must hold a zone for each award of its points
+    pub interval: Duration,
+}
+
+#[derive(Debug, Clone)]
+pub struct RespawnConfig {
+    // How often the game loop spawns treasures into a match; never when unset
+    pub interval: Option<Duration>,
+    // Matches shorter than this get no spawns
+    pub after: Duration,
+    // Most treasures spawned at once
+    pub batch: usize,
+    // Most undiscovered spawned treasures per match; in a zone, also at most
+    // its treasure_density times its area
+    pub max_active: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct EmoteConfig {
+    // Most emotes a player can send per window
+    pub limit: usize,
+    pub window: Duration,
+}
+
+#[derive(Clone)]
+pub struct TurnConfig {
+    // Shared secret of the TURN server's REST API (coturn's static-auth-secret)
+    pub secret: String,
+    // e.g. turn:turn.example.com:3478?transport=udp
+    pub urls: Vec<String>,
+    // Lifetime of minted credentials
+    pub ttl: Duration,
+}
+
+// Keep the secret out of logs
+impl std::fmt::Debug for TurnConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TurnConfig")
+            .field("secret", &"***")
+            .field("urls", &self.urls)
+            .field("ttl", &self.ttl)
+            .finish()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PingConfig {
+    // How long a map ping stays up
+    pub ttl: Duration,
+    // Most pings a player can drop per window
+    pub limit: usize,
+    pub window: Duration,
+}
+
+#[derive(Clone)]
+pub struct AdminConfig {
+    // Bearer token for admin routes and commands; admin access is off when unset
+    pub token: Option<String>,
+    // How long a rotated API key keeps working next to its replacement
+    pub key_rotation_grace: Duration,
+}
+
+// Keep the token out of logs
+impl std::fmt::Debug for AdminConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AdminConfig")
+            .field("token", &self.token.as_ref().map(|_| "***"))
+            .field("key_rotation_grace", &self.key_rotation_grace)
+            .finish()
+    }
+}
+
+#[derive(Clone)]
+pub struct ClusterConfig {
+    // Shared secret of the internal node-to-node API; the API is off when unset
+    pub token: Option<String>,
+    // Base URL other nodes reach this one at, e.g. http://10.0.0.5:3000
+    pub advertise_url: Option<String>,
+    // Deployment region of this node, for announcements to a region
+    pub region: Option<String>,
+}
+
+// Keep the token out of logs
+impl std::fmt::Debug for ClusterConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ClusterConfig")
+            .field("token", &self.token.as_ref().map(|_| "***"))
+            .field("advertise_url", &self.advertise_url)
+            .field("region", &self.region)
+            .finish()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SnapshotConfig {
+    // How often in-memory state is saved to Redis; None when disabled
+    pub interval: Option<Duration>,
+    // Node this instance stands by for, taking over its state when it stops
+    pub standby_for: Option<String>,
+}
+
+// Probabilities are per call or per frame, from 0 to 1
+#[derive(Debug, Clone)]
+pub struct ChaosConfig {
+    // Hasura calls failing as if the database were unreachable
+    pub db_failure_rate: f64,
+    // Hasura calls held back by up to `db_max_delay`
+    pub db_delay_rate: f64,
+    pub db_max_delay: Duration,
+    // WebSocket frames silently dropped
+    pub frame_drop_rate: f64,
+    // Frames on which the connection's send task dies
+    pub send_kill_rate: f64,
+    // Fixed seed, to replay the same faults
+    pub seed: Option<u64>,
+}
+
+#[derive(Debug, Clone)]
+pub struct TelemetryConfig {
+    // Share of client events kept, per kind
+    pub sampling: SamplingConfig,
+    // Most events accepted in one POST /api/telemetry
+    pub max_batch: usize,
+    // Events waiting to be written; further events are dropped
+    pub queue_capacity: usize,
+    // JSON list of allowed events; any well-formed event is accepted when unset
+    pub schema_path: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct NewPlayerConfig {
+    // Players with fewer finished matches are only matched with each other; 0 disables the protected pool
+    pub protected_matches: i64,
+    // Accounts older than this leave the protected pool even with few matches
+    pub max_account_age: Option<Duration>,
+    // Bot accounts used to fill protected rooms that wait too long; none disables padding
+    pub bots: Vec<Uuid>,
+    // How long a protected room waits for players before it is filled with bots
+    pub bot_fill_after: Duration,
+}
+
+#[derive(Debug, Clone)]
+pub struct RatingConfig {
+    // MMR of a player's first match
+    pub initial_mmr: f64,
+    // Largest MMR change from one match
+    pub k_factor: f64,
+    // MMR difference at which the stronger side is expected to win 10 times out of 11
+    pub scale: f64,
+    // Width of the MMR brackets matchmaking pools are split into; 0 disables brackets
+    pub bracket_width: f64,
+    // Matches played on a provisional rating before the player is given a rank
+    pub placement_matches: i32,
+    // K-factor of provisional ratings, higher so they settle quickly
+    pub placement_k_factor: f64,
+    // Players in placement may join rooms up to this many brackets away from their own
+    pub placement_bracket_spread: i32,
+    pub smurf: SmurfConfig,
+    pub party: PartyConfig,
+}
+
+// Heuristics for experienced players on new accounts
+#[derive(Debug, Clone)]
+pub struct SmurfConfig {
+    // Only a player's first matches are looked at
+    pub window_matches: i32,
+    // Matches needed before a verdict
+    pub min_matches: i32,
+    // Win rate over the window at or above which the account is suspected
+    pub win_rate: f64,
+    // Average score percentile within their matches, also required
+    pub score_percentile: f64,
+    // Suspected smurfs' MMR moves this many times faster
+    pub k_multiplier: f64,
+    // Added to a suspected smurf's MMR when picking their bracket
+    pub bracket_boost: f64,
+    // Whether suspected smurfs are listed for admin review
+    pub flag_for_review: bool,
+}
+
+// Premade parties queueing together
+#[derive(Debug, Clone)]
+pub struct PartyConfig {
+    // Largest party, leader included; also capped at one team
+    pub max_size: usize,
+    // Weight of the strongest member's MMR in the party's MMR, the rest going to the average
+    pub top_weight: f64,
+    // Added to the party's MMR for every member beyond the first, for the edge of playing together
+    pub member_bonus: f64,
+}
+
+#[derive(Debug, Clone)]
+pub struct HeatmapConfig {
+    // Shortest time between two stored positions of a player in a match
+    pub sample_interval: Duration,
+    // Side of a heatmap tile, in map units
+    pub tile_size: f32,
+    // Only positions this recent count towards the heatmap
+    pub window: Duration,
+    // How often the heatmap tiles are rebuilt
+    pub aggregate_interval: Duration,
+}
+
+#[derive(Debug, Clone)]
+pub struct InboxConfig {
+    // How long a message waits for its offline player
+    pub ttl: Duration,
+}
+
+#[derive(Debug, Clone)]
+pub struct ScheduleConfig {
+    // How long before the start players are reminded of a scheduled match
+    pub remind_before: Duration,
+    // How long before the start its private lobby opens
+    pub open_before: Duration,
+    // Furthest ahead a match can be scheduled
+    pub max_ahead: Duration,
+}
+
+#[derive(Debug, Clone)]
+pub struct ContentConfig {
+    // Directory of content bundles, one `<locale>.json` per locale
+    pub bundles_dir: Option<String>,
+    // Locale of players who send none, and of text missing from their bundle
+    pub default_locale: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct LfgConfig {
+    // Most lfg.post a player can send per window
+    pub limit: usize,
+    pub window: Duration,
+    // Shortest time between two posts for the same match type on one node
+    pub type_interval: Duration,
+}
+
+// What happens to a player's other sessions when they connect again
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SessionPolicy {
+    // Sessions on other devices stay open; only an older session of the same
+    // device is replaced
+    Multi,
+    // The new session replaces every other session of the player
+    Takeover,
+}
+
+impl SessionPolicy {
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "multi" => Some(SessionPolicy::Multi),
+            "takeover" => Some(SessionPolicy::Takeover),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct DeviceConfig {
+    pub session_policy: SessionPolicy,
+    // Registering more devices drops the least recently seen one
+    pub max_per_user: usize,
+    // Push gateway receiving the notifications for offline players; no
+    // pushes are sent without it
+    pub push_webhook: Option<String>,
+}
+
+// Webhook URLs tend to carry a token
+impl std::fmt::Debug for DeviceConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DeviceConfig")
+            .field("session_policy", &self.session_policy)
+            .field("max_per_user", &self.max_per_user)
+            .field("push_webhook", &self.push_webhook.as_ref().map(|_| "***"))
+            .finish()
+    }
+}
+
+#[derive(Clone)]
+pub struct AuthConfig {
+    // OAuth client ids accepted as the `aud` of ID tokens; a provider with none
+    // is disabled
+    pub google_client_ids: Vec<String>,
+    // Apple service and bundle ids
+    pub apple_client_ids: Vec<String>,
+    // Key session tokens are signed with; a random one per process when unset,
+    // so tokens only work on the node that issued them
+    pub session_secret: Option<String>,
+    // How long a session token is accepted after it was issued
+    pub session_ttl: Duration,
+}
+
+// Keep the session secret out of logs
+impl std::fmt::Debug for AuthConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AuthConfig")
+            .field("google_client_ids", &self.google_client_ids)
+            .field("apple_client_ids", &self.apple_client_ids)
+            .field("session_secret", &self.session_secret.as_ref().map(|_| "***"))
+            .field("session_ttl", &self.session_ttl)
+            .finish()
+    }
+}
+
+// Secret shared with a webhook peer, named so it can be rotated
+#[derive(Clone)]
+pub struct SigningKey {
+    pub id: String,
+    pub secret: String,
+}
+
+// Keep the secret out of logs
+impl std::fmt::Debug for SigningKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SigningKey")
+            .field("id", &self.id)
+            .field("secret", &"***")
+            .finish()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SigningConfig {
+    // The first key signs outgoing webhooks; any of them verifies incoming
+    // ones. Rotate by putting a new key first and dropping the old one once
+    // every peer has switched.
+    pub keys: Vec<SigningKey>,
+    // Signed payloads older (or further in the future) than this are refused,
+    // and nonces are remembered this long
+    pub tolerance: Duration,
+}
+
+#[derive(Clone)]
+pub struct SlowConfig {
+    // Hasura operations taking longer are logged and counted
+    pub query: Duration,
+    // Same for the handling of one WebSocket/SSE command
+    pub command: Duration,
+    // Also POSTed here, at most once a minute per operation
+    pub webhook: Option<String>,
+}
+
+impl std::fmt::Debug for SlowConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SlowConfig")
+            .field("query", &self.query)
+            .field("command", &self.command)
+            .field("webhook", &self.webhook.as_ref().map(|_| "***"))
+            .finish()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CacheConfig {
+    // How long the server reuses a cached REST response; match ends on this
+    // node drop the affected ones sooner
+    pub ttl: Duration,
+    // Cache-Control max-age of those responses, for browsers and proxies
+    pub max_age: Duration,
+    // Responses kept at most; expired ones go first, then the oldest
+    pub max_entries: usize,
+}
+
+// What the sys.welcome event carries; see gateway::handshake
+#[derive(Clone)]
+pub struct HandshakeConfig {
+    pub message: String,
+    // Client features switched on or off for this deployment
+    pub features: BTreeMap<String, bool>,
+    // Key resume tokens are signed with; a random one per process when unset,
+    // so tokens only resume on the node that issued them
+    pub resume_secret: Option<String>,
+    // How long a resume token is accepted after the session it came with opened
+    pub resume_ttl: Duration,
+}
+
+// Keep the secret out of logs
+impl std::fmt::Debug for HandshakeConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("HandshakeConfig")
+            .field("message", &self.message)
+            .field("features", &self.features)
+            .field("resume_secret", &self.resume_secret.as_ref().map(|_| "***"))
+            .field("resume_ttl", &self.resume_ttl)
+            .finish()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct LocalConfig {
+    // Every repository call waits a random delay in this range, like a round trip to Hasura
+    pub latency_min: Duration,
+    pub latency_max: Duration,
+}
+
+#[derive(Debug, Clone)]
+pub struct GatewayConfig {
+    // Messages at least this large are gzip-compressed for connections that opted in
+    pub compression_threshold: usize,
+    // Most broadcast messages sent per batch; later ones wait for the next
+    pub fanout_batch: usize,
+    // Pause after a full batch
+    pub fanout_interval: Duration,
+    // File inbound client traffic is recorded to, for the replay tool
+    pub record_path: Option<String>,
+    // How far behind the players spectators see a match
+    pub spectator_delay: Duration,
+    // Sessions (WebSocket and SSE) open at once; new ones are refused past it
+    pub max_connections: Option<usize>,
+    // Messages held back for spectators; the oldest are dropped past it
+    pub max_spectator_buffer: usize,
+    // Broadcasts waiting to be sent; the oldest of the lowest priority are dropped past it
+    pub max_fanout_queue: usize,
+    // Matches with a state document and live stats; the least recently
+    // updated is dropped past it
+    pub max_tracked_matches: usize,
 }
 
 impl Config {
     pub fn load() -> Self {
         // Load .env file if present
         dotenv().ok();
-        
+
         // Load server configuration
-        let host = std::env::var("SERVER_HOST").unwrap_or_else(|_| "127.0.0.1".to_string());
-        let port = std::env::var("SERVER_PORT")
-            .unwrap_or_else(|_| "3000".to_string())
-            .parse()
-            .unwrap_or(3000);
-        
+        let grpc_port = std::env::var("GRPC_PORT")
+            .ok()
+            .and_then(|p| p.parse().ok());
+
+        let trusted_proxies = std::env::var("TRUSTED_PROXIES")
+            .map(|s| TrustedProxies::from_str(&s))
+            .unwrap_or_default();
+
+        // TLS is enabled only when both the default cert and key are set
+        let tls = match (std::env::var("TLS_CERT_PATH"), std::env::var("TLS_KEY_PATH")) {
+            (Ok(cert_path), Ok(key_path)) => Some(TlsConfig {
+                cert_path,
+                key_path,
+                sni_certs: std::env::var("TLS_SNI_CERTS")
+                    .map(|s| parse_sni_certs(&s))
+                    .unwrap_or_default(),
+                redirect_port: std::env::var("HTTP_REDIRECT_PORT")
+                    .ok()
+                    .and_then(|p| p.parse().ok()),
+            }),
+            _ => None,
+        };
+
         // Load Hasura configuration
-        let endpoint = std::env::var("NEXT_PUBLIC_HASURA_ENDPOINT")
-            .expect("NEXT_PUBLIC_HASURA_ENDPOINT environment variable not set");
-            
-        let admin_secret = std::env::var("NEXT_PUBLIC_HASURA_ADMIN_SECRET")
-            .expect("NEXT_PUBLIC_HASURA_ADMIN_SECRET environment variable not set");
-        
+        let schema_check = std::env::var("HASURA_SCHEMA_CHECK")
+            .map(|s| s != "false")
+            .unwrap_or(true);
+        let auto_migrate = std::env::var("HASURA_AUTO_MIGRATE")
+            .map(|s| s == "true")
+            .unwrap_or(false);
+
+        // Load degraded mode configuration
+        let policy = std::env::var("DB_OFFLINE_POLICY")
+            .ok()
+            .and_then(|p| OfflinePolicy::from_str(&p))
+            .unwrap_or(OfflinePolicy::Refuse);
+        let probe_interval = std::env::var("DB_PROBE_INTERVAL_SECS")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .map(Duration::from_secs)
+            .unwrap_or(Duration::from_secs(5));
+
+        // Load matchmaking configuration
+        let zones_file = std::env::var("ZONES_FILE")
+            .ok()
+            .filter(|p| !p.is_empty());
+        let reconcile_interval = std::env::var("RECONCILE_INTERVAL_SECS")
+            .ok()
+            .and_then(|s| s.parse::<u64>().ok())
+            .filter(|secs| *secs > 0)
+            .map(Duration::from_secs)
+            .unwrap_or(Duration::from_secs(3600));
+        let reconcile_window = std::env::var("RECONCILE_WINDOW_HOURS")
+            .ok()
+            .and_then(|s| s.parse::<u64>().ok())
+            .map(|hours| Duration::from_secs(hours * 3600))
+            .unwrap_or(Duration::from_secs(24 * 3600));
+        let max_matches = std::env::var("MAX_CONCURRENT_MATCHES")
+            .ok()
+            .and_then(|s| s.parse::<usize>().ok());
+        // e.g. "1v1=200,5v5=40"
+        let max_matches_per_type = std::env::var("MAX_MATCHES_PER_TYPE")
+            .map(|s| parse_type_limits(&s))
+            .unwrap_or_default();
+        let max_queued_players = std::env::var("MAX_QUEUED_PLAYERS")
+            .ok()
+            .and_then(|s| s.parse::<usize>().ok());
+        let max_pending_writes = std::env::var("MAX_PENDING_WRITES")
+            .ok()
+            .and_then(|s| s.parse::<usize>().ok());
+        let premium = std::env::var("PREMIUM_USERS")
+            .map(|s| s.split(',').filter_map(|id| Uuid::parse_str(id.trim()).ok()).collect())
+            .unwrap_or_default();
+        let return_window = std::env::var("RETURN_PRIORITY_SECS")
+            .ok()
+            .and_then(|s| s.parse::<u64>().ok())
+            .map(Duration::from_secs)
+            .unwrap_or(Duration::from_secs(600));
+        let starvation_wait = std::env::var("PRIORITY_MAX_WAIT_SECS")
+            .ok()
+            .and_then(|s| s.parse::<u64>().ok())
+            .map(Duration::from_secs)
+            .unwrap_or(Duration::from_secs(30));
+        let retry_after = std::env::var("BUSY_RETRY_AFTER_SECS")
+            .ok()
+            .and_then(|s| s.parse::<u64>().ok())
+            .filter(|secs| *secs > 0)
+            .map(Duration::from_secs)
+            .unwrap_or(Duration::from_secs(10));
+        let fairness_interval = std::env::var("FAIRNESS_INTERVAL_SECS")
+            .ok()
+            .and_then(|s| s.parse::<u64>().ok())
+            .filter(|secs| *secs > 0)
+            .map(Duration::from_secs)
+            .unwrap_or(Duration::from_secs(3600));
+        let fairness_window = std::env::var("FAIRNESS_WINDOW_HOURS")
+            .ok()
+            .and_then(|s| s.parse::<u64>().ok())
+            .filter(|hours| *hours > 0)
+            .map(|hours| Duration::from_secs(hours * 3600))
+            .unwrap_or(Duration::from_secs(24 * 3600));
+        let blowout_margin = std::env::var("FAIRNESS_BLOWOUT_MARGIN")
+            .ok()
+            .and_then(|s| s.parse::<f64>().ok())
+            .filter(|margin| (0.0..=1.0).contains(margin))
+            .unwrap_or(0.5);
+
+        // Load new player protection configuration
+        let protected_matches = std::env::var("NEW_PLAYER_MATCHES")
+            .ok()
+            .and_then(|s| s.parse::<i64>().ok())
+            .filter(|count| *count >= 0)
+            .unwrap_or(10);
+        let max_account_age = std::env::var("NEW_PLAYER_MAX_ACCOUNT_DAYS")
+            .ok()
+            .and_then(|s| s.parse::<u64>().ok())
+            .map(|days| Duration::from_secs(days * 24 * 3600));
+        let bots = std::env::var("NEW_PLAYER_BOTS")
+            .map(|s| s.split(',').filter_map(|id| Uuid::parse_str(id.trim()).ok()).collect())
+            .unwrap_or_default();
+        let bot_fill_after = std::env::var("NEW_PLAYER_BOT_FILL_SECS")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .map(Duration::from_secs)
+            .unwrap_or(Duration::from_secs(30));
+
+        // Load rating configuration
+        let initial_mmr = std::env::var("RATING_INITIAL_MMR")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(1500.0);
+        let k_factor = std::env::var("RATING_K_FACTOR")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(32.0);
+        let scale = std::env::var("RATING_SCALE")
+            .ok()
+            .and_then(|s| s.parse::<f64>().ok())
+            .filter(|scale| *scale > 0.0)
+            .unwrap_or(400.0);
+        let bracket_width = std::env::var("RATING_BRACKET_WIDTH")
+            .ok()
+            .and_then(|s| s.parse::<f64>().ok())
+            .filter(|width| *width >= 0.0)
+            .unwrap_or(400.0);
+        let placement_matches = std::env::var("RATING_PLACEMENT_MATCHES")
+            .ok()
+            .and_then(|s| s.parse::<i32>().ok())
+            .filter(|count| *count >= 0)
+            .unwrap_or(5);
+        let placement_k_factor = std::env::var("RATING_PLACEMENT_K_FACTOR")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(64.0);
+        let placement_bracket_spread = std::env::var("RATING_PLACEMENT_BRACKET_SPREAD")
+            .ok()
+            .and_then(|s| s.parse::<i32>().ok())
+            .filter(|spread| *spread >= 0)
+            .unwrap_or(1);
+        let smurf = SmurfConfig {
+            window_matches: std::env::var("SMURF_WINDOW_MATCHES")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(10),
+            min_matches: std::env::var("SMURF_MIN_MATCHES")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(3),
+            win_rate: std::env::var("SMURF_WIN_RATE")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(0.8),
+            score_percentile: std::env::var("SMURF_SCORE_PERCENTILE")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(0.85),
+            k_multiplier: std::env::var("SMURF_K_MULTIPLIER")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(2.0),
+            bracket_boost: std::env::var("SMURF_BRACKET_BOOST")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(200.0),
+            flag_for_review: std::env::var("SMURF_FLAG_FOR_REVIEW")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(true),
+        };
+        let party = PartyConfig {
+            max_size: std::env::var("PARTY_MAX_SIZE")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(5),
+            top_weight: std::env::var("PARTY_TOP_MMR_WEIGHT")
+                .ok()
+                .and_then(|s| s.parse::<f64>().ok())
+                .filter(|weight| (0.0..=1.0).contains(weight))
+                .unwrap_or(0.5),
+            member_bonus: std::env::var("PARTY_MEMBER_MMR_BONUS")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(50.0),
+        };
+
+        // Load anti-cheat configuration
+        let max_speed = std::env::var("ANTICHEAT_MAX_SPEED")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(15.0);
+        let teleport_distance = std::env::var("ANTICHEAT_TELEPORT_DISTANCE")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(1000.0);
+        let suspect_threshold = std::env::var("ANTICHEAT_SUSPECT_THRESHOLD")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(0.5);
+        let quarantine_threshold = std::env::var("ANTICHEAT_QUARANTINE_THRESHOLD")
+            .ok()
+            .and_then(|s| s.parse().ok());
+        let recovery_per_hour = std::env::var("ANTICHEAT_TRUST_RECOVERY_PER_HOUR")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .filter(|rate: &f32| *rate >= 0.0)
+            .unwrap_or(0.05);
+
+        // Load gateway configuration
+        let compression_threshold = std::env::var("WS_COMPRESSION_THRESHOLD")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(1024);
+        let fanout_batch = std::env::var("FANOUT_BATCH_SIZE")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .filter(|&n: &usize| n > 0)
+            .unwrap_or(256);
+        let fanout_interval = std::env::var("FANOUT_INTERVAL_MS")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .map(Duration::from_millis)
+            .unwrap_or(Duration::from_millis(20));
+        let record_path = std::env::var("TRAFFIC_RECORD_PATH")
+            .ok()
+            .filter(|s| !s.is_empty());
+        let spectator_delay = std::env::var("SPECTATOR_DELAY_SECS")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .map(Duration::from_secs)
+            .unwrap_or(Duration::from_secs(30));
+        let max_connections = std::env::var("MAX_CONNECTIONS")
+            .ok()
+            .and_then(|s| s.parse::<usize>().ok());
+        let max_spectator_buffer = std::env::var("MAX_SPECTATOR_BUFFER")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .filter(|&n: &usize| n > 0)
+            .unwrap_or(100_000);
+        let max_fanout_queue = std::env::var("MAX_FANOUT_QUEUE")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .filter(|&n: &usize| n > 0)
+            .unwrap_or(100_000);
+        let max_tracked_matches = std::env::var("MAX_TRACKED_MATCHES")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .filter(|&n: &usize| n > 0)
+            .unwrap_or(20_000);
+
+        // Load game loop configuration
+        let tick_hz = std::env::var("GAME_TICK_HZ")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(4);
+        let match_duration = std::env::var("MATCH_DURATION_SECS")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .map(Duration::from_secs);
+        let proximity_radius = std::env::var("PROXIMITY_RADIUS")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(50.0);
+        let interest = std::env::var("INTEREST_POLICY")
+            .map(|s| InterestConfig::from_str(&s))
+            .unwrap_or_default();
+        let victory = std::env::var("VICTORY_CONDITIONS")
+            .map(|s| VictoryConfig::from_str(&s))
+            .unwrap_or_default();
+        let ping_ttl = std::env::var("PING_TTL_SECS")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .filter(|&secs: &u64| secs > 0)
+            .map(Duration::from_secs)
+            .unwrap_or(Duration::from_secs(10));
+        let ping_limit = std::env::var("PING_RATE_LIMIT")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(5);
+        let ping_window = std::env::var("PING_RATE_WINDOW_SECS")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .filter(|&secs: &u64| secs > 0)
+            .map(Duration::from_secs)
+            .unwrap_or(Duration::from_secs(10));
+        let emote_limit = std::env::var("EMOTE_RATE_LIMIT")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(3);
+        let emote_window = std::env::var("EMOTE_RATE_WINDOW_SECS")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .filter(|&secs: &u64| secs > 0)
+            .map(Duration::from_secs)
+            .unwrap_or(Duration::from_secs(5));
+        let lobby_duration = std::env::var("LOBBY_DURATION_SECS")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .map(Duration::from_secs)
+            .unwrap_or(Duration::ZERO);
+        let lobby_loadouts = std::env::var("LOBBY_LOADOUTS")
+            .map(|s| s.split(',').map(|id| id.trim().to_string()).filter(|id| !id.is_empty()).collect())
+            .unwrap_or_default();
+        let lobby_roles = std::env::var("LOBBY_ROLES")
+            .map(|s| s.split(',').map(|id| id.trim().to_string()).filter(|id| !id.is_empty()).collect())
+            .unwrap_or_default();
+        let lobby_chat_limit = std::env::var("LOBBY_CHAT_RATE_LIMIT")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(5);
+        let lobby_chat_window = std::env::var("LOBBY_CHAT_RATE_WINDOW_SECS")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .filter(|&secs: &u64| secs > 0)
+            .map(Duration::from_secs)
+            .unwrap_or(Duration::from_secs(10));
+        let lobby_chat_max_len = std::env::var("LOBBY_CHAT_MAX_LEN")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(200);
+        let turn = crate::secrets::get("TURN_SECRET")
+            .filter(|secret| !secret.is_empty())
+            .map(|secret| TurnConfig {
+                secret,
+                urls: std::env::var("TURN_URLS")
+                    .map(|s| s.split(',').map(|url| url.trim().to_string()).filter(|url| !url.is_empty()).collect())
+                    .unwrap_or_default(),
+                ttl: std::env::var("TURN_TTL_SECS")
+                    .ok()
+                    .and_then(|s| s.parse().ok())
+                    .filter(|&secs: &u64| secs > 0)
+                    .map(Duration::from_secs)
+                    .unwrap_or(Duration::from_secs(3600)),
+            });
+        let respawn_interval = std::env::var("TREASURE_RESPAWN_SECS")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .filter(|&secs: &u64| secs > 0)
+            .map(Duration::from_secs);
+        let respawn_after = std::env::var("TREASURE_RESPAWN_AFTER_SECS")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .map(Duration::from_secs)
+            .unwrap_or(Duration::from_secs(300));
+        let respawn_batch = std::env::var("TREASURE_RESPAWN_BATCH")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(3);
+        let respawn_max = std::env::var("TREASURE_RESPAWN_MAX")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(20);
+        let capture_file = std::env::var("CAPTURE_ZONES_FILE")
+            .ok()
+            .filter(|s| !s.is_empty());
+        let capture_interval = std::env::var("CAPTURE_INTERVAL_SECS")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .filter(|&secs: &u64| secs > 0)
+            .map(Duration::from_secs)
+            .unwrap_or(Duration::from_secs(10));
+
+        // Load admin configuration
+        let admin_token = crate::secrets::get("ADMIN_TOKEN")
+            .filter(|t| !t.is_empty());
+        let key_rotation_grace = std::env::var("API_KEY_ROTATION_GRACE_SECS")
+            .ok()
+            .and_then(|s| s.parse::<u64>().ok())
+            .map(Duration::from_secs)
+            .unwrap_or(Duration::from_secs(24 * 3600));
+
+        // Load cluster configuration
+        let cluster_token = crate::secrets::get("CLUSTER_TOKEN")
+            .filter(|t| !t.is_empty());
+        let advertise_url = std::env::var("CLUSTER_ADVERTISE_URL")
+            .ok()
+            .map(|url| url.trim_end_matches('/').to_string())
+            .filter(|url| !url.is_empty());
+        let region = std::env::var("NODE_REGION")
+            .ok()
+            .filter(|r| !r.is_empty());
+
+        // Load snapshot replication configuration
+        let snapshot_interval = std::env::var("SNAPSHOT_INTERVAL_SECS")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(5);
+        let standby_for = std::env::var("STANDBY_FOR")
+            .ok()
+            .filter(|node| !node.is_empty());
+
+        // Load fault injection configuration
+        let chaos_rate = |name: &str| std::env::var(name)
+            .ok()
+            .and_then(|s| s.parse::<f64>().ok())
+            .map(|rate| rate.clamp(0.0, 1.0))
+            .unwrap_or(0.0);
+        let chaos = std::env::var("CHAOS_ENABLED")
+            .is_ok_and(|s| s == "true")
+            .then(|| ChaosConfig {
+                db_failure_rate: chaos_rate("CHAOS_DB_FAILURE_RATE"),
+                db_delay_rate: chaos_rate("CHAOS_DB_DELAY_RATE"),
+                db_max_delay: std::env::var("CHAOS_DB_MAX_DELAY_MS")
+                    .ok()
+                    .and_then(|s| s.parse().ok())
+                    .map(Duration::from_millis)
+                    .unwrap_or(Duration::from_secs(2)),
+                frame_drop_rate: chaos_rate("CHAOS_FRAME_DROP_RATE"),
+                send_kill_rate: chaos_rate("CHAOS_SEND_KILL_RATE"),
+                seed: std::env::var("CHAOS_SEED").ok().and_then(|s| s.parse().ok()),
+            });
+
+        // Load telemetry configuration
+        let sampling = std::env::var("TELEMETRY_SAMPLE_RATES")
+            .map(|s| SamplingConfig::from_str(&s))
+            .unwrap_or_default();
+        let max_batch = std::env::var("TELEMETRY_MAX_BATCH")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(100);
+        let queue_capacity = std::env::var("TELEMETRY_QUEUE_CAPACITY")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(10_000);
+        let telemetry_schema_path = std::env::var("TELEMETRY_SCHEMA_PATH").ok().filter(|s| !s.is_empty());
+
+        // Load heatmap configuration
+        let sample_interval = std::env::var("HEATMAP_SAMPLE_INTERVAL_SECS")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .map(Duration::from_secs)
+            .unwrap_or(Duration::from_secs(5));
+        let tile_size = std::env::var("HEATMAP_TILE_SIZE")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .filter(|size: &f32| *size > 0.0)
+            .unwrap_or(50.0);
+        let window = std::env::var("HEATMAP_WINDOW_HOURS")
+            .ok()
+            .and_then(|s| s.parse::<u64>().ok())
+            .map(|hours| Duration::from_secs(hours * 3600))
+            .unwrap_or(Duration::from_secs(7 * 24 * 3600));
+        let aggregate_interval = std::env::var("HEATMAP_AGGREGATE_INTERVAL_SECS")
+            .ok()
+            .and_then(|s| s.parse::<u64>().ok())
+            .filter(|secs| *secs > 0)
+            .map(Duration::from_secs)
+            .unwrap_or(Duration::from_secs(600));
+
+        // Load offline inbox configuration
+        let inbox_ttl = std::env::var("INBOX_TTL_HOURS")
+            .ok()
+            .and_then(|s| s.parse::<u64>().ok())
+            .filter(|hours| *hours > 0)
+            .map(|hours| Duration::from_secs(hours * 3600))
+            .unwrap_or(Duration::from_secs(72 * 3600));
+
+        // Load scheduled match configuration
+        let schedule_minutes = |name: &str, default: u64| {
+            std::env::var(name)
+                .ok()
+                .and_then(|s| s.parse::<u64>().ok())
+                .map(|minutes| Duration::from_secs(minutes * 60))
+                .unwrap_or(Duration::from_secs(default * 60))
+        };
+        let schedule_remind_before = schedule_minutes("SCHEDULE_REMIND_MINS", 15);
+        let schedule_open_before = schedule_minutes("SCHEDULE_LOBBY_OPEN_MINS", 5);
+        let schedule_max_ahead = std::env::var("SCHEDULE_MAX_AHEAD_DAYS")
+            .ok()
+            .and_then(|s| s.parse::<u64>().ok())
+            .filter(|days| *days > 0)
+            .map(|days| Duration::from_secs(days * 24 * 3600))
+            .unwrap_or(Duration::from_secs(30 * 24 * 3600));
+
+        // Load localized content configuration
+        let content_bundles_dir = std::env::var("CONTENT_BUNDLES_DIR")
+            .ok()
+            .filter(|s| !s.is_empty());
+        let default_locale = std::env::var("DEFAULT_LOCALE")
+            .ok()
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty())
+            .unwrap_or_else(|| "en".to_string());
+
+        // Load looking-for-group configuration
+        let lfg_limit = std::env::var("LFG_RATE_LIMIT")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(1);
+        let lfg_window = std::env::var("LFG_RATE_WINDOW_SECS")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .filter(|&secs: &u64| secs > 0)
+            .map(Duration::from_secs)
+            .unwrap_or(Duration::from_secs(60));
+        let lfg_type_interval = std::env::var("LFG_TYPE_INTERVAL_SECS")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .map(Duration::from_secs)
+            .unwrap_or(Duration::from_secs(10));
+
+        // Load device registry configuration
+        let session_policy = std::env::var("SESSION_POLICY")
+            .ok()
+            .and_then(|p| SessionPolicy::from_str(&p))
+            .unwrap_or(SessionPolicy::Multi);
+        let max_devices = std::env::var("MAX_DEVICES_PER_USER")
+            .ok()
+            .and_then(|s| s.parse::<usize>().ok())
+            .filter(|max| *max > 0)
+            .unwrap_or(10);
+        let push_webhook = crate::secrets::get("PUSH_WEBHOOK_URL")
+            .filter(|url| !url.is_empty());
+
+        // Load external identity configuration
+        let client_ids = |name: &str| -> Vec<String> {
+            std::env::var(name)
+                .map(|s| s.split(',').map(|id| id.trim().to_string()).filter(|id| !id.is_empty()).collect())
+                .unwrap_or_default()
+        };
+        let google_client_ids = client_ids("GOOGLE_CLIENT_IDS");
+        let apple_client_ids = client_ids("APPLE_CLIENT_IDS");
+        let session_secret = crate::secrets::get("SESSION_TOKEN_SECRET")
+            .filter(|s| !s.is_empty());
+        let session_ttl = std::env::var("SESSION_TOKEN_TTL_SECS")
+            .ok()
+            .and_then(|s| s.parse::<u64>().ok())
+            .filter(|secs| *secs > 0)
+            .map(Duration::from_secs)
+            .unwrap_or(Duration::from_secs(30 * 24 * 3600));
+
+        // Load webhook signing configuration
+        let signing_keys: Vec<SigningKey> = crate::secrets::get("WEBHOOK_SIGNING_KEYS")
+            .map(|s| s.split(',')
+                .filter_map(|entry| {
+                    let (id, secret) = entry.split_once(':')?;
+                    let (id, secret) = (id.trim(), secret.trim());
+                    (!id.is_empty() && !secret.is_empty()).then(|| SigningKey { id: id.to_string(), secret: secret.to_string() })
+                })
+                .collect())
+            .unwrap_or_default();
+        let signing_tolerance = std::env::var("WEBHOOK_SIGNATURE_TOLERANCE_SECS")
+            .ok()
+            .and_then(|s| s.parse::<u64>().ok())
+            .filter(|secs| *secs > 0)
+            .map(Duration::from_secs)
+            .unwrap_or(Duration::from_secs(300));
+
+        // Load REST response cache configuration
+        let cache_ttl = std::env::var("API_CACHE_TTL_SECS")
+            .ok()
+            .and_then(|s| s.parse::<u64>().ok())
+            .map(Duration::from_secs)
+            .unwrap_or(Duration::from_secs(60));
+        let cache_max_age = std::env::var("API_CACHE_MAX_AGE_SECS")
+            .ok()
+            .and_then(|s| s.parse::<u64>().ok())
+            .map(Duration::from_secs)
+            .unwrap_or(Duration::from_secs(30));
+        let cache_max_entries = std::env::var("API_CACHE_MAX_ENTRIES")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .filter(|&n: &usize| n > 0)
+            .unwrap_or(1000);
+
+        // Load welcome handshake configuration
+        let welcome_message = std::env::var("WELCOME_MESSAGE")
+            .ok()
+            .filter(|s| !s.trim().is_empty())
+            .unwrap_or_else(|| "Connected successfully".to_string());
+        // e.g. "voice=true,ranked=false"; a bare name is switched on
+        let features = std::env::var("FEATURE_FLAGS")
+            .map(|s| parse_feature_flags(&s))
+            .unwrap_or_default();
+        let resume_secret = crate::secrets::get("RESUME_TOKEN_SECRET")
+            .filter(|s| !s.is_empty());
+        let resume_ttl = std::env::var("RESUME_TOKEN_TTL_SECS")
+            .ok()
+            .and_then(|s| s.parse::<u64>().ok())
+            .filter(|secs| *secs > 0)
+            .map(Duration::from_secs)
+            .unwrap_or(Duration::from_secs(24 * 3600));
+
+        // Load slow operation configuration
+        let slow_ms = |name: &str, default: u64| std::env::var(name)
+            .ok()
+            .and_then(|s| s.parse::<u64>().ok())
+            .filter(|ms| *ms > 0)
+            .map(Duration::from_millis)
+            .unwrap_or(Duration::from_millis(default));
+        let slow_query = slow_ms("SLOW_QUERY_MS", 500);
+        let slow_command = slow_ms("SLOW_COMMAND_MS", 1000);
+        let slow_webhook = crate::secrets::get("SLOW_ALERT_WEBHOOK")
+            .filter(|url| !url.is_empty());
+
+        // Load local mode configuration
+        let local = std::env::args().any(|arg| arg == "--local").then(|| {
+            let latency_ms = |name: &str, default: u64| std::env::var(name)
+                .ok()
+                .and_then(|s| s.parse::<u64>().ok())
+                .map(Duration::from_millis)
+                .unwrap_or(Duration::from_millis(default));
+            LocalConfig {
+                latency_min: latency_ms("LOCAL_LATENCY_MIN_MS", 5),
+                latency_max: latency_ms("LOCAL_LATENCY_MAX_MS", 40),
+            }
+        });
+
         Self {
-            server: ServerConfig { host, port },
-            hasura: HasuraConfig { endpoint, admin_secret },
+            server: ServerConfig { grpc_port, tls, trusted_proxies },
+            hasura: HasuraConfig { schema_check, auto_migrate },
+            offline: OfflineConfig { policy, probe_interval },
+            matchmaking: MatchmakingConfig {
+                zones_file,
+                reconcile_interval,
+                reconcile_window,
+                limits: LoadLimits { max_matches, max_matches_per_type, max_queued_players, max_pending_writes, retry_after },
+                priority: PriorityConfig { premium, return_window, starvation_wait },
+                fairness: FairnessConfig { interval: fairness_interval, window: fairness_window, blowout_margin },
+            },
+            new_players: NewPlayerConfig { protected_matches, max_account_age, bots, bot_fill_after },
+            rating: RatingConfig {
+                initial_mmr,
+                k_factor,
+                scale,
+                bracket_width,
+                placement_matches,
+                placement_k_factor,
+                placement_bracket_spread,
+                smurf,
+                party,
+            },
+            anticheat: AntiCheatConfig { max_speed, teleport_distance, suspect_threshold, quarantine_threshold, recovery_per_hour },
+            gateway: GatewayConfig {
+                compression_threshold,
+                fanout_batch,
+                fanout_interval,
+                record_path,
+                spectator_delay,
+                max_connections,
+                max_spectator_buffer,
+                max_fanout_queue,
+                max_tracked_matches,
+            },
+            game: GameConfig {
+                tick_hz,
+                match_duration,
+                proximity_radius,
+                interest,
+                victory,
+                pings: PingConfig { ttl: ping_ttl, limit: ping_limit, window: ping_window },
+                emotes: EmoteConfig { limit: emote_limit, window: emote_window },
+                turn,
+                respawn: RespawnConfig {
+                    interval: respawn_interval,
+                    after: respawn_after,
+                    batch: respawn_batch,
+                    max_active: respawn_max,
+                },
+                capture: CaptureConfig { file: capture_file, interval: capture_interval },
+                lobby: LobbyConfig {
+                    duration: lobby_duration,
+                    loadouts: lobby_loadouts,
+                    roles: lobby_roles,
+                    chat_limit: lobby_chat_limit,
+                    chat_window: lobby_chat_window,
+                    chat_max_len: lobby_chat_max_len,
+                },
+            },
+            admin: AdminConfig { token: admin_token, key_rotation_grace },
+            cluster: ClusterConfig { token: cluster_token, advertise_url, region },
+            snapshot: SnapshotConfig {
+                interval: (snapshot_interval > 0).then(|| Duration::from_secs(snapshot_interval)),
+                standby_for,
+            },
+            chaos,
+            telemetry: TelemetryConfig { sampling, max_batch, queue_capacity, schema_path: telemetry_schema_path },
+            heatmap: HeatmapConfig { sample_interval, tile_size, window, aggregate_interval },
+            inbox: InboxConfig { ttl: inbox_ttl },
+            schedule: ScheduleConfig {
+                remind_before: schedule_remind_before,
+                open_before: schedule_open_before,
+                max_ahead: schedule_max_ahead,
+            },
+            content: ContentConfig { bundles_dir: content_bundles_dir, default_locale },
+            lfg: LfgConfig { limit: lfg_limit, window: lfg_window, type_interval: lfg_type_interval },
+            devices: DeviceConfig { session_policy, max_per_user: max_devices, push_webhook },
+            auth: AuthConfig { google_client_ids, apple_client_ids, session_secret, session_ttl },
+            signing: SigningConfig { keys: signing_keys, tolerance: signing_tolerance },
+            slow: SlowConfig { query: slow_query, command: slow_command, webhook: slow_webhook },
+            cache: CacheConfig { ttl: cache_ttl, max_age: cache_max_age, max_entries: cache_max_entries },
+            handshake: HandshakeConfig { message: welcome_message, features, resume_secret, resume_ttl },
+            local,
         }
     }
-}
\ No newline at end of file
+}
+
+// TLS_SNI_CERTS="game.example.com=/certs/game.pem:/certs/game.key,admin.example.com=..."
+fn parse_type_limits(s: &str) -> HashMap<String, usize> {
+    s.split(',')
+        .filter_map(|entry| {
+            let (match_type, limit) = entry.split_once('=')?;
+            Some((match_type.trim().to_string(), limit.trim().parse().ok()?))
+        })
+        .collect()
+}
+
+fn parse_feature_flags(s: &str) -> BTreeMap<String, bool> {
+    s.split(',')
+        .filter_map(|entry| {
+            let (name, enabled) = match entry.split_once('=') {
+                Some((name, enabled)) => (name.trim(), enabled.trim().parse().ok()?),
+                None => (entry.trim(), true),
+            };
+            (!name.is_empty()).then(|| (name.to_string(), enabled))
+        })
+        .collect()
+}
+
+fn parse_sni_certs(s: &str) -> Vec<SniCert> {
+    s.split(',')
+        .filter_map(|entry| {
+            let (host, paths) = entry.trim().split_once('=')?;
+            let (cert_path, key_path) = paths.split_once(':')?;
+            Some(SniCert {
+                host: host.trim().to_lowercase(),
+                cert_path: cert_path.trim().to_string(),
+                key_path: key_path.trim().to_string(),
+            })
+        })
+        .collect()
+}
@@ -0,0 +1,116 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::config::ContentConfig;
+
+// Longest locale tag accepted from clients
+pub const MAX_LOCALE_LEN: usize = 35;
+
+// Localized text, keyed like `announcement.maintenance` or `quest.first_win.description`.
+//
+// Bundles are the `<locale>.json` files in CONTENT_BUNDLES_DIR, each a flat
+// object of key to text, read once at startup. Text is looked up in the
+// bundle of the player's locale (`pt-br`), then of its language (`pt`), then
+// of DEFAULT_LOCALE.
+pub struct ContentBundles {
+    default_locale: String,
+    // By lowercase locale
+    bundles: HashMap<String, HashMap<String, String>>,
+}
+
+// GET /api/content reply: every text in one locale, with the default
+// locale's text where the bundle has none
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ContentBundle {
+    pub locale: String,
+    pub entries: BTreeMap<String, String>,
+}
+
+impl ContentBundles {
+    pub fn load(config: &ContentConfig) -> Arc<Self> {
+        let default_locale = normalize(&config.default_locale).unwrap_or_else(|| "en".to_string());
+        let mut bundles = HashMap::new();
+        if let Some(dir) = &config.bundles_dir {
+            match load_dir(dir) {
+                Ok(loaded) => bundles = loaded,
+                Err(e) => tracing::error!("Failed to load content bundles from {}: {}", dir, e),
+            }
+            if !bundles.contains_key(&default_locale) {
+                tracing::warn!("No content bundle for the default locale {}", default_locale);
+            }
+        }
+        tracing::info!("Loaded {} content bundles", bundles.len());
+        Arc::new(Self { default_locale, bundles })
+    }
+
+    // Bundle locale for a requested one: itself, its language, or the default
+    pub fn resolve(&self, locale: Option<&str>) -> String {
+        locale
+            .and_then(normalize)
+            .and_then(|locale| fallbacks(&locale).into_iter().find(|l| self.bundles.contains_key(l)))
+            .unwrap_or_else(|| self.default_locale.clone())
+    }
+
+    // Text for the key in the locale, falling back to its language and then
+    // to the default locale
+    pub fn text(&self, locale: &str, key: &str) -> Option<&str> {
+        fallbacks(locale)
+            .iter()
+            .chain(std::iter::once(&self.default_locale))
+            .find_map(|l| self.bundles.get(l)?.get(key))
+            .map(String::as_str)
+    }
+
+    pub fn bundle(&self, locale: Option<&str>) -> ContentBundle {
+        let locale = self.resolve(locale);
+        let mut entries: BTreeMap<String, String> = BTreeMap::new();
+        let mut chain = fallbacks(&locale);
+        chain.push(self.default_locale.clone());
+        // Most general first, so the more specific text wins
+        for l in chain.iter().rev() {
+            if let Some(bundle) = self.bundles.get(l) {
+                entries.extend(bundle.iter().map(|(key, text)| (key.clone(), text.clone())));
+            }
+        }
+        ContentBundle { locale, entries }
+    }
+}
+
+// The locale, then its language if it has a region
+fn fallbacks(locale: &str) -> Vec<String> {
+    let mut chain = vec![locale.to_string()];
+    if let Some((language, _)) = locale.split_once('-') {
+        chain.push(language.to_string());
+    }
+    chain
+}
+
+// Lowercase BCP 47 style tag (`pt_BR` becomes `pt-br`); None if it isn't one
+pub fn normalize(locale: &str) -> Option<String> {
+    let locale = locale.trim().replace('_', "-").to_ascii_lowercase();
+    let valid = !locale.is_empty()
+        && locale.len() <= MAX_LOCALE_LEN
+        && locale.split('-').all(|part| !part.is_empty() && part.chars().all(|c| c.is_ascii_alphanumeric()));
+    valid.then_some(locale)
+}
+
+fn load_dir(dir: &str) -> std::result::Result<HashMap<String, HashMap<String, String>>, String> {
+    let mut bundles = HashMap::new();
+    for entry in std::fs::read_dir(dir).map_err(|e| e.to_string())? {
+        let path = entry.map_err(|e| e.to_string())?.path();
+        if path.extension().and_then(|ext| ext.to_str()) != Some("json") {
+            continue;
+        }
+        let Some(locale) = path.file_stem().and_then(|stem| stem.to_str()).and_then(normalize) else {
+            tracing::warn!("Ignoring content bundle with an invalid locale: {}", path.display());
+            continue;
+        };
+        let text = std::fs::read_to_string(&path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        let entries: HashMap<String, String> = serde_json::from_str(&text).map_err(|e| format!("{}: {}", path.display(), e))?;
+        bundles.insert(locale, entries);
+    }
+    Ok(bundles)
+}
@@ -0,0 +1,30 @@
+use std::future::Future;
+
+use uuid::Uuid;
+
+// Header carrying the correlation id on GraphQL requests, so it shows up in Hasura's logs
+pub const HEADER: &str = "X-Correlation-Id";
+
+tokio::task_local! {
+    static CORRELATION_ID: Uuid;
+}
+
+// Correlation id of the client command the current task is working on.
+//
+// The gateway gives every inbound command a fresh id and runs it inside
+// `scope`, so the id follows the command through MatchService and
+// HasuraClient calls on the same task, is logged with them and is echoed in
+// the replies and events it causes. Work handed to another task or node
+// (queued broadcasts, match events, cluster messages) carries the id along
+// and re-enters the scope there.
+pub fn current() -> Option<Uuid> {
+    CORRELATION_ID.try_with(|id| *id).ok()
+}
+
+// Run `f` with `id` as the current correlation id; None runs it outside any
+pub async fn scope<F: Future>(id: Option<Uuid>, f: F) -> F::Output {
+    match id {
+        Some(id) => CORRELATION_ID.scope(id, f).await,
+        None => f.await,
+    }
+}
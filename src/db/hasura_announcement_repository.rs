@@ -0,0 +1,182 @@
+use std::sync::Arc;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use crate::announcements::announcement::{Announcement, Motd};
+use crate::error::Result;
+
+use super::hasura_client::HasuraClient;
+use super::repository::AnnouncementRepository;
+
+const ANNOUNCEMENT_FIELDS: &str = r#"
+    id
+    message
+    content_key
+    segment
+    send_at
+    created_by
+    created_at
+    cancelled_at
+"#;
+
+// The message of the day is the single row of `motd`
+const MOTD_ID: i32 = 1;
+
+pub struct HasuraAnnouncementRepository {
+    client: Arc<HasuraClient>,
+}
+
+#[derive(Debug, Deserialize)]
+struct MotdQueryResponse {
+    motd_by_pk: Option<Motd>,
+}
+
+#[derive(Debug, Deserialize)]
+struct AnnouncementsQueryResponse {
+    announcements: Vec<Announcement>,
+}
+
+#[derive(Debug, Deserialize)]
+struct AnnouncementsCancelResponse {
+    update_announcements: AffectedRows,
+}
+
+#[derive(Debug, Deserialize)]
+struct AffectedRows {
+    affected_rows: i64,
+}
+
+impl HasuraAnnouncementRepository {
+    pub async fn new() -> Result<Self> {
+        let client = HasuraClient::get_instance().await?;
+        Ok(Self { client })
+    }
+}
+
+#[async_trait]
+impl AnnouncementRepository for HasuraAnnouncementRepository {
+    async fn get_motd(&self) -> Result<Option<Motd>> {
+        let query = r#"
+            query Motd($id: Int!) {
+                motd_by_pk(id: $id) {
+                    message
+                    updated_by
+                    updated_at
+                }
+            }
+        "#;
+
+        let variables = json!({
+            "id": MOTD_ID
+        });
+
+        let response: MotdQueryResponse = self.client.query(query, variables).await?;
+        Ok(response.motd_by_pk)
+    }
+
+    async fn upsert_motd(&self, motd: &Motd) -> Result<()> {
+        let mutation = r#"
+            mutation UpsertMotd($object: motd_insert_input!) {
+                insert_motd_one(
+                    object: $object,
+                    on_conflict: {
+                        constraint: motd_pkey,
+                        update_columns: [message, updated_by, updated_at]
+                    }
+                ) {
+                    id
+                }
+            }
+        "#;
+
+        let variables = json!({
+            "object": {
+                "id": MOTD_ID,
+                "message": motd.message,
+                "updated_by": motd.updated_by,
+                "updated_at": motd.updated_at
+            }
+        });
+
+        let _: Value = self.client.mutate(mutation, variables).await?;
+        Ok(())
+    }
+
+    async fn delete_motd(&self) -> Result<()> {
+        let mutation = r#"
+            mutation DeleteMotd($id: Int!) {
+                delete_motd_by_pk(id: $id) {
+                    id
+                }
+            }
+        "#;
+
+        let variables = json!({
+            "id": MOTD_ID
+        });
+
+        let _: Value = self.client.mutate(mutation, variables).await?;
+        Ok(())
+    }
+
+    async fn insert_announcement(&self, announcement: &Announcement) -> Result<()> {
+        let mutation = r#"
+            mutation InsertAnnouncement($announcement: announcements_insert_input!) {
+                insert_announcements_one(object: $announcement) {
+                    id
+                }
+            }
+        "#;
+
+        let variables = json!({
+            "announcement": announcement
+        });
+
+        let _: Value = self.client.mutate(mutation, variables).await?;
+        Ok(())
+    }
+
+    async fn announcements_since(&self, since: DateTime<Utc>) -> Result<Vec<Announcement>> {
+        let query = format!(r#"
+            query Announcements($since: timestamptz!) {{
+                announcements(
+                    where: {{send_at: {{_gte: $since}}, cancelled_at: {{_is_null: true}}}},
+                    order_by: {{send_at: asc}}
+                ) {{
+                    {}
+                }}
+            }}
+        "#, ANNOUNCEMENT_FIELDS);
+
+        let variables = json!({
+            "since": since
+        });
+
+        let response: AnnouncementsQueryResponse = self.client.query(&query, variables).await?;
+        Ok(response.announcements)
+    }
+
+    async fn cancel_announcement(&self, id: Uuid, now: DateTime<Utc>) -> Result<bool> {
+        let mutation = r#"
+            mutation CancelAnnouncement($id: uuid!, $now: timestamptz!) {
+                update_announcements(
+                    where: {id: {_eq: $id}, send_at: {_gt: $now}, cancelled_at: {_is_null: true}},
+                    _set: {cancelled_at: $now}
+                ) {
+                    affected_rows
+                }
+            }
+        "#;
+
+        let variables = json!({
+            "id": id,
+            "now": now
+        });
+
+        let response: AnnouncementsCancelResponse = self.client.mutate(mutation, variables).await?;
+        Ok(response.update_announcements.affected_rows > 0)
+    }
+}
@@ -0,0 +1,193 @@
+use std::sync::Arc;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use crate::apikeys::key::ApiKey;
+use crate::error::{Error, Result};
+
+use super::hasura_client::HasuraClient;
+use super::repository::ApiKeyRepository;
+
+const API_KEY_FIELDS: &str = r#"
+    id
+    name
+    prefix
+    key_hash
+    scopes
+    created_by
+    created_at
+    expires_at
+    revoked_at
+    replaced_by
+"#;
+
+pub struct HasuraApiKeyRepository {
+    client: Arc<HasuraClient>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ApiKeysQueryResponse {
+    api_keys: Vec<ApiKey>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ApiKeyByPkResponse {
+    api_keys_by_pk: Option<ApiKey>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ApiKeysUpdateResponse {
+    update_api_keys: AffectedRows,
+}
+
+#[derive(Debug, Deserialize)]
+struct AffectedRows {
+    affected_rows: i64,
+}
+
+impl HasuraApiKeyRepository {
+    pub async fn new() -> Result<Self> {
+        let client = HasuraClient::get_instance().await?;
+        Ok(Self { client })
+    }
+}
+
+// key_hash isn't serialized with the key, so the insert object is built by hand
+fn insert_object(key: &ApiKey) -> Value {
+    json!({
+        "id": key.id,
+        "name": key.name,
+        "prefix": key.prefix,
+        "key_hash": key.key_hash,
+        "scopes": key.scopes,
+        "created_by": key.created_by,
+        "created_at": key.created_at,
+        "expires_at": key.expires_at,
+        "revoked_at": key.revoked_at,
+        "replaced_by": key.replaced_by
+    })
+}
+
+#[async_trait]
+impl ApiKeyRepository for HasuraApiKeyRepository {
+    async fn insert_key(&self, key: &ApiKey) -> Result<()> {
+        let mutation = r#"
+            mutation InsertApiKey($key: api_keys_insert_input!) {
+                insert_api_keys_one(object: $key) {
+                    id
+                }
+            }
+        "#;
+
+        let variables = json!({
+            "key": insert_object(key)
+        });
+
+        let _: Value = self.client.mutate(mutation, variables).await?;
+        Ok(())
+    }
+
+    async fn list_keys(&self) -> Result<Vec<ApiKey>> {
+        let query = format!(r#"
+            query ApiKeys {{
+                api_keys(order_by: {{created_at: desc}}) {{
+                    {}
+                }}
+            }}
+        "#, API_KEY_FIELDS);
+
+        let response: ApiKeysQueryResponse = self.client.query(&query, json!({})).await?;
+        Ok(response.api_keys)
+    }
+
+    async fn active_keys(&self, now: DateTime<Utc>) -> Result<Vec<ApiKey>> {
+        let query = format!(r#"
+            query ActiveApiKeys($now: timestamptz!) {{
+                api_keys(where: {{
+                    revoked_at: {{_is_null: true}},
+                    _or: [{{expires_at: {{_is_null: true}}}}, {{expires_at: {{_gt: $now}}}}]
+                }}) {{
+                    {}
+                }}
+            }}
+        "#, API_KEY_FIELDS);
+
+        let variables = json!({
+            "now": now
+        });
+
+        let response: ApiKeysQueryResponse = self.client.query(&query, variables).await?;
+        Ok(response.api_keys)
+    }
+
+    async fn get_key(&self, id: Uuid) -> Result<ApiKey> {
+        let query = format!(r#"
+            query ApiKey($id: uuid!) {{
+                api_keys_by_pk(id: $id) {{
+                    {}
+                }}
+            }}
+        "#, API_KEY_FIELDS);
+
+        let variables = json!({
+            "id": id
+        });
+
+        let response: ApiKeyByPkResponse = self.client.query(&query, variables).await?;
+        response.api_keys_by_pk.ok_or_else(|| Error::NotFound(format!("API key {}", id)))
+    }
+
+    // Hasura runs the root fields of one mutation in order, in a single transaction
+    async fn rotate_key(&self, old_id: Uuid, old_expires_at: DateTime<Utc>, replacement: &ApiKey) -> Result<()> {
+        let mutation = r#"
+            mutation RotateApiKey($key: api_keys_insert_input!, $old_id: uuid!, $old_expires_at: timestamptz!, $replaced_by: uuid!) {
+                insert_api_keys_one(object: $key) {
+                    id
+                }
+                update_api_keys(
+                    where: {id: {_eq: $old_id}, revoked_at: {_is_null: true}},
+                    _set: {expires_at: $old_expires_at, replaced_by: $replaced_by}
+                ) {
+                    affected_rows
+                }
+            }
+        "#;
+
+        let variables = json!({
+            "key": insert_object(replacement),
+            "old_id": old_id,
+            "old_expires_at": old_expires_at,
+            "replaced_by": replacement.id
+        });
+
+        let _: Value = self.client.mutate(mutation, variables).await?;
+        Ok(())
+    }
+
+    async fn revoke_key(&self, id: Uuid, at: DateTime<Utc>) -> Result<()> {
+        let mutation = r#"
+            mutation RevokeApiKey($id: uuid!, $at: timestamptz!) {
+                update_api_keys(
+                    where: {id: {_eq: $id}, revoked_at: {_is_null: true}},
+                    _set: {revoked_at: $at}
+                ) {
+                    affected_rows
+                }
+            }
+        "#;
+
+        let variables = json!({
+            "id": id,
+            "at": at
+        });
+
+        let response: ApiKeysUpdateResponse = self.client.mutate(mutation, variables).await?;
+        if response.update_api_keys.affected_rows == 0 {
+            return Err(Error::NotFound(format!("API key {}", id)));
+        }
+        Ok(())
+    }
+}
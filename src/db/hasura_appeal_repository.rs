@@ -0,0 +1,145 @@
+use std::sync::Arc;
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use crate::anticheat::appeal::{Appeal, AppealStatus};
+use crate::error::Result;
+
+use super::hasura_client::HasuraClient;
+use super::repository::AppealRepository;
+
+const APPEAL_FIELDS: &str = r#"
+    id
+    user_id
+    message
+    status
+    trust
+    signals
+    quarantined
+    created_at
+    resolved_at
+    resolved_by
+    resolution
+"#;
+
+pub struct HasuraAppealRepository {
+    client: Arc<HasuraClient>,
+}
+
+#[derive(Debug, Deserialize)]
+struct AppealsQueryResponse {
+    trust_appeals: Vec<Appeal>,
+}
+
+#[derive(Debug, Deserialize)]
+struct AppealQueryResponse {
+    trust_appeals_by_pk: Option<Appeal>,
+}
+
+impl HasuraAppealRepository {
+    pub async fn new() -> Result<Self> {
+        let client = HasuraClient::get_instance().await?;
+        Ok(Self { client })
+    }
+}
+
+#[async_trait]
+impl AppealRepository for HasuraAppealRepository {
+    async fn insert_appeal(&self, appeal: &Appeal) -> Result<()> {
+        let mutation = r#"
+            mutation InsertAppeal($appeal: trust_appeals_insert_input!) {
+                insert_trust_appeals_one(object: $appeal) {
+                    id
+                }
+            }
+        "#;
+
+        let variables = json!({
+            "appeal": appeal
+        });
+
+        let _: Value = self.client.mutate(mutation, variables).await?;
+        Ok(())
+    }
+
+    async fn get_appeal(&self, id: Uuid) -> Result<Option<Appeal>> {
+        let query = format!(r#"
+            query Appeal($id: uuid!) {{
+                trust_appeals_by_pk(id: $id) {{
+                    {}
+                }}
+            }}
+        "#, APPEAL_FIELDS);
+
+        let variables = json!({
+            "id": id
+        });
+
+        let response: AppealQueryResponse = self.client.query(&query, variables).await?;
+        Ok(response.trust_appeals_by_pk)
+    }
+
+    async fn open_appeal(&self, user_id: Uuid) -> Result<Option<Appeal>> {
+        let query = format!(r#"
+            query OpenAppeal($user_id: uuid!, $status: String!) {{
+                trust_appeals(where: {{user_id: {{_eq: $user_id}}, status: {{_eq: $status}}}}, limit: 1) {{
+                    {}
+                }}
+            }}
+        "#, APPEAL_FIELDS);
+
+        let variables = json!({
+            "user_id": user_id,
+            "status": AppealStatus::Open
+        });
+
+        let response: AppealsQueryResponse = self.client.query(&query, variables).await?;
+        Ok(response.trust_appeals.into_iter().next())
+    }
+
+    async fn list_appeals(&self, status: Option<AppealStatus>) -> Result<Vec<Appeal>> {
+        let query = format!(r#"
+            query Appeals($where: trust_appeals_bool_exp!) {{
+                trust_appeals(where: $where, order_by: {{created_at: desc}}) {{
+                    {}
+                }}
+            }}
+        "#, APPEAL_FIELDS);
+
+        let filter = match status {
+            Some(status) => json!({"status": {"_eq": status}}),
+            None => json!({}),
+        };
+        let variables = json!({
+            "where": filter
+        });
+
+        let response: AppealsQueryResponse = self.client.query(&query, variables).await?;
+        Ok(response.trust_appeals)
+    }
+
+    async fn update_appeal(&self, appeal: &Appeal) -> Result<()> {
+        let mutation = r#"
+            mutation ResolveAppeal($id: uuid!, $set: trust_appeals_set_input!) {
+                update_trust_appeals_by_pk(pk_columns: {id: $id}, _set: $set) {
+                    id
+                }
+            }
+        "#;
+
+        let variables = json!({
+            "id": appeal.id,
+            "set": {
+                "status": appeal.status,
+                "resolved_at": appeal.resolved_at,
+                "resolved_by": appeal.resolved_by,
+                "resolution": appeal.resolution
+            }
+        });
+
+        let _: Value = self.client.mutate(mutation, variables).await?;
+        Ok(())
+    }
+}
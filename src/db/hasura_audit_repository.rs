@@ -0,0 +1,97 @@
+use std::sync::Arc;
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::audit::record::AuditRecord;
+use crate::error::Result;
+
+use super::hasura_client::HasuraClient;
+use super::keyset::KeysetQuery;
+use super::repository::AuditRepository;
+
+const AUDIT_FIELDS: &str = r#"
+    id
+    actor
+    api_key_id
+    action
+    status
+    created_at
+"#;
+
+pub struct HasuraAuditRepository {
+    client: Arc<HasuraClient>,
+}
+
+#[derive(Debug, Deserialize)]
+struct AuditQueryResponse {
+    audit_log: Vec<AuditRecord>,
+    audit_log_aggregate: AuditAggregate,
+}
+
+#[derive(Debug, Deserialize)]
+struct AuditAggregate {
+    aggregate: AuditCount,
+}
+
+#[derive(Debug, Deserialize)]
+struct AuditCount {
+    count: usize,
+}
+
+impl HasuraAuditRepository {
+    pub async fn new() -> Result<Self> {
+        let client = HasuraClient::get_instance().await?;
+        Ok(Self { client })
+    }
+}
+
+#[async_trait]
+impl AuditRepository for HasuraAuditRepository {
+    async fn insert_audit(&self, record: &AuditRecord) -> Result<()> {
+        let mutation = r#"
+            mutation InsertAudit($record: audit_log_insert_input!) {
+                insert_audit_log_one(object: $record) {
+                    id
+                }
+            }
+        "#;
+
+        let variables = json!({
+            "record": record
+        });
+
+        let _: Value = self.client.mutate(mutation, variables).await?;
+        Ok(())
+    }
+
+    async fn audit_page(&self, actor: Option<&str>, page: &KeysetQuery) -> Result<(Vec<AuditRecord>, usize)> {
+        let by_actor = match actor {
+            Some(actor) => json!({ "actor": { "_eq": actor } }),
+            None => json!({}),
+        };
+        let query = format!(r#"
+            query AuditPage($where: audit_log_bool_exp!, $filter: audit_log_bool_exp!, $order_by: [audit_log_order_by!]!, $limit: Int!) {{
+                audit_log(where: $where, order_by: $order_by, limit: $limit) {{
+                    {}
+                }}
+                audit_log_aggregate(where: $filter) {{
+                    aggregate {{
+                        count
+                    }}
+                }}
+            }}
+        "#, AUDIT_FIELDS);
+
+        let variables = json!({
+            "where": { "_and": [by_actor, page.page_exp()] },
+            "filter": { "_and": [by_actor, page.filter_exp()] },
+            "order_by": page.order_by(),
+            "limit": page.limit
+        });
+
+        let response: AuditQueryResponse = self.client.query(&query, variables).await?;
+        Ok((response.audit_log, response.audit_log_aggregate.aggregate.count))
+    }
+
+}
@@ -0,0 +1,159 @@
+use std::sync::Mutex;
+use std::time::Duration;
+
+use chrono::Utc;
+use jsonwebtoken::{Algorithm, EncodingKey, Header};
+use reqwest::RequestBuilder;
+use serde::Serialize;
+
+use crate::error::{Error, Result};
+use crate::secrets;
+
+// A token is replaced once it has less than this left to live
+const REFRESH_BEFORE: Duration = Duration::from_secs(60);
+const DEFAULT_TTL: Duration = Duration::from_secs(900);
+const DEFAULT_ROLE: &str = "spv_service";
+// Subject of the service tokens
+const SUBJECT: &str = "spv-server";
+
+// Hasura session claims, see Hasura's JWT mode docs
+#[derive(Debug, Serialize)]
+struct HasuraClaims<'a> {
+    #[serde(rename = "x-hasura-default-role")]
+    default_role: &'a str,
+    #[serde(rename = "x-hasura-allowed-roles")]
+    allowed_roles: [&'a str; 1],
+}
+
+#[derive(Debug, Serialize)]
+struct ServiceClaims<'a> {
+    sub: &'a str,
+    iat: i64,
+    exp: i64,
+    #[serde(rename = "https://hasura.io/jwt/claims")]
+    hasura: HasuraClaims<'a>,
+}
+
+// Short-lived JWTs the server signs for itself with Hasura's JWT key, limited
+// to one role. A new token is minted shortly before the current one expires.
+struct ServiceToken {
+    key: EncodingKey,
+    algorithm: Algorithm,
+    role: String,
+    ttl: Duration,
+    // Current token and its expiry, Unix seconds
+    current: Mutex<Option<(String, i64)>>,
+}
+
+impl ServiceToken {
+    // An HMAC key, or a PEM RSA private key for RS256
+    fn new(secret: &str, role: String, ttl: Duration) -> Result<Self> {
+        let (key, algorithm) = if secret.trim_start().starts_with("-----BEGIN") {
+            let key = EncodingKey::from_rsa_pem(secret.as_bytes())
+                .map_err(|e| Error::DbError(format!("Invalid HASURA_JWT_SECRET: {}", e)))?;
+            (key, Algorithm::RS256)
+        } else {
+            (EncodingKey::from_secret(secret.as_bytes()), Algorithm::HS256)
+        };
+        Ok(Self {
+            key,
+            algorithm,
+            role,
+            ttl,
+            current: Mutex::new(None),
+        })
+    }
+
+    fn bearer(&self) -> Result<String> {
+        let now = Utc::now().timestamp();
+        let mut current = self.current.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some((token, exp)) = current.as_ref() && *exp - now > REFRESH_BEFORE.as_secs() as i64 {
+            return Ok(token.clone());
+        }
+
+        let exp = now + self.ttl.as_secs() as i64;
+        let claims = ServiceClaims {
+            sub: SUBJECT,
+            iat: now,
+            exp,
+            hasura: HasuraClaims {
+                default_role: &self.role,
+                allowed_roles: [&self.role],
+            },
+        };
+        let token = jsonwebtoken::encode(&Header::new(self.algorithm), &claims, &self.key)
+            .map_err(|e| Error::DbError(format!("Failed to sign Hasura service token: {}", e)))?;
+        tracing::debug!("Minted Hasura service token for role {}, expiring at {}", self.role, exp);
+        *current = Some((token.clone(), exp));
+        Ok(token)
+    }
+}
+
+// How the server authenticates to Hasura.
+//
+// With HASURA_JWT_SECRET set, GraphQL calls carry a service JWT for
+// HASURA_SERVICE_ROLE instead of the admin secret. The admin secret, when
+// one is configured, is then only sent to the schema and metadata APIs that
+// migrations need; without it those calls use the service token too.
+pub struct HasuraAuth {
+    admin_secret: Option<String>,
+    service: Option<ServiceToken>,
+}
+
+impl HasuraAuth {
+    pub fn from_env() -> Result<Self> {
+        let service = match secrets::get("HASURA_JWT_SECRET") {
+            Some(key) => {
+                let role = std::env::var("HASURA_SERVICE_ROLE")
+                    .ok()
+                    .filter(|s| !s.is_empty())
+                    .unwrap_or_else(|| DEFAULT_ROLE.to_string());
+                let ttl = std::env::var("HASURA_JWT_TTL_SECS")
+                    .ok()
+                    .and_then(|s| s.parse().ok())
+                    .map(Duration::from_secs)
+                    .filter(|ttl| *ttl > REFRESH_BEFORE * 2)
+                    .unwrap_or(DEFAULT_TTL);
+                println!("Authenticating to Hasura with service tokens for role {}", role);
+                Some(ServiceToken::new(&key, role, ttl)?)
+            }
+            None => None,
+        };
+
+        let admin_secret = match secrets::get("HASURA_ADMIN_SECRET") {
+            Some(value) => Some(value),
+            None => match secrets::get("NEXT_PUBLIC_HASURA_ADMIN_SECRET") {
+                Some(value) => {
+                    tracing::warn!("NEXT_PUBLIC_HASURA_ADMIN_SECRET is deprecated, use HASURA_ADMIN_SECRET or HASURA_JWT_SECRET");
+                    Some(value)
+                }
+                // Local Hasura's default, only when nothing else is configured
+                None if service.is_none() => {
+                    println!("No Hasura credentials set, using the development admin secret");
+                    Some("dev_secret".to_string())
+                }
+                None => None,
+            },
+        };
+
+        Ok(Self { admin_secret, service })
+    }
+
+    // Credentials for a GraphQL call
+    pub fn graphql(&self, builder: RequestBuilder) -> Result<RequestBuilder> {
+        match (&self.service, &self.admin_secret) {
+            (Some(service), _) => Ok(builder.bearer_auth(service.bearer()?)),
+            (None, Some(secret)) => Ok(builder.header("X-Hasura-Admin-Secret", secret)),
+            (None, None) => Ok(builder),
+        }
+    }
+
+    // Credentials for a schema or metadata API call
+    pub fn admin(&self, builder: RequestBuilder) -> Result<RequestBuilder> {
+        match (&self.admin_secret, &self.service) {
+            (Some(secret), _) => Ok(builder.header("X-Hasura-Admin-Secret", secret)),
+            (None, Some(service)) => Ok(builder.bearer_auth(service.bearer()?)),
+            (None, None) => Ok(builder),
+        }
+    }
+}
@@ -0,0 +1,141 @@
+use std::sync::Arc;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use crate::error::Result;
+use crate::moderation::ban::Ban;
+
+use super::hasura_client::HasuraClient;
+use super::repository::BanRepository;
+
+const BAN_FIELDS: &str = r#"
+    id
+    user_id
+    reason
+    issued_by
+    created_at
+    expires_at
+    lifted_at
+    lifted_by
+"#;
+
+// A player's bans still in force at `$now`
+const ACTIVE_BAN_FILTER: &str = "{user_id: {_eq: $user_id}, lifted_at: {_is_null: true}, _or: [{expires_at: {_is_null: true}}, {expires_at: {_gt: $now}}]}";
+
+pub struct HasuraBanRepository {
+    client: Arc<HasuraClient>,
+}
+
+#[derive(Debug, Deserialize)]
+struct BansQueryResponse {
+    bans: Vec<Ban>,
+}
+
+#[derive(Debug, Deserialize)]
+struct BansLiftResponse {
+    update_bans: AffectedRows,
+}
+
+#[derive(Debug, Deserialize)]
+struct AffectedRows {
+    affected_rows: i64,
+}
+
+impl HasuraBanRepository {
+    pub async fn new() -> Result<Self> {
+        let client = HasuraClient::get_instance().await?;
+        Ok(Self { client })
+    }
+}
+
+#[async_trait]
+impl BanRepository for HasuraBanRepository {
+    async fn active_bans(&self, now: DateTime<Utc>) -> Result<Vec<Ban>> {
+        let query = format!(r#"
+            query ActiveBans($now: timestamptz!) {{
+                bans(
+                    where: {{lifted_at: {{_is_null: true}}, _or: [{{expires_at: {{_is_null: true}}}}, {{expires_at: {{_gt: $now}}}}]}},
+                    order_by: {{created_at: asc}}
+                ) {{
+                    {}
+                }}
+            }}
+        "#, BAN_FIELDS);
+
+        let variables = json!({
+            "now": now
+        });
+
+        let response: BansQueryResponse = self.client.query(&query, variables).await?;
+        Ok(response.bans)
+    }
+
+    async fn user_bans(&self, user_id: Uuid) -> Result<Vec<Ban>> {
+        let query = format!(r#"
+            query UserBans($user_id: uuid!) {{
+                bans(where: {{user_id: {{_eq: $user_id}}}}, order_by: {{created_at: desc}}) {{
+                    {}
+                }}
+            }}
+        "#, BAN_FIELDS);
+
+        let variables = json!({
+            "user_id": user_id
+        });
+
+        let response: BansQueryResponse = self.client.query(&query, variables).await?;
+        Ok(response.bans)
+    }
+
+    // Both root fields run in one transaction, so a player never has two active bans
+    async fn insert_ban(&self, ban: &Ban) -> Result<()> {
+        let mutation = format!(r#"
+            mutation InsertBan($ban: bans_insert_input!, $user_id: uuid!, $now: timestamptz!, $issued_by: String!) {{
+                update_bans(
+                    where: {},
+                    _set: {{lifted_at: $now, lifted_by: $issued_by}}
+                ) {{
+                    affected_rows
+                }}
+                insert_bans_one(object: $ban) {{
+                    id
+                }}
+            }}
+        "#, ACTIVE_BAN_FILTER);
+
+        let variables = json!({
+            "ban": ban,
+            "user_id": ban.user_id,
+            "now": ban.created_at,
+            "issued_by": ban.issued_by
+        });
+
+        let _: Value = self.client.mutate(&mutation, variables).await?;
+        Ok(())
+    }
+
+    async fn lift_bans(&self, user_id: Uuid, lifted_by: &str, now: DateTime<Utc>) -> Result<i64> {
+        let mutation = format!(r#"
+            mutation LiftBans($user_id: uuid!, $now: timestamptz!, $lifted_by: String!) {{
+                update_bans(
+                    where: {},
+                    _set: {{lifted_at: $now, lifted_by: $lifted_by}}
+                ) {{
+                    affected_rows
+                }}
+            }}
+        "#, ACTIVE_BAN_FILTER);
+
+        let variables = json!({
+            "user_id": user_id,
+            "now": now,
+            "lifted_by": lifted_by
+        });
+
+        let response: BansLiftResponse = self.client.mutate(&mutation, variables).await?;
+        Ok(response.update_bans.affected_rows)
+    }
+}
@@ -1,9 +1,15 @@
 use std::sync::Arc;
 use tokio::sync::OnceCell;
 use serde::{Deserialize, Serialize};
-use reqwest::{Client, header};
+use reqwest::Client;
 
+use crate::chaos;
+use crate::correlation;
+use crate::latency;
+use crate::slow;
 use crate::error::{Error, Result};
+use super::hasura_auth::HasuraAuth;
+use super::schema_drift;
 
 // Global Hasura client
 static HASURA_CLIENT: OnceCell<Arc<HasuraClient>> = OnceCell::const_new();
@@ -11,7 +17,7 @@ static HASURA_CLIENT: OnceCell<Arc<HasuraClient>> = OnceCell::const_new();
 pub struct HasuraClient {
     client: Client,
     endpoint: String,
-    admin_secret: String,
+    auth: HasuraAuth,
 }
 
 #[derive(Debug, Serialize)]
@@ -34,10 +40,92 @@ struct GraphQLError {
     extensions: Option<serde_json::Value>,
 }
 
+impl GraphQLError {
+    // Hasura error code, e.g. "constraint-violation" or "permission-error"
+    fn code(&self) -> Option<&str> {
+        self.extensions.as_ref()?.get("code")?.as_str()
+    }
+
+    // Postgres SQLSTATE, only present when Hasura exposes internal errors (admin role)
+    fn postgres_code(&self) -> Option<&str> {
+        self.extensions.as_ref()?.pointer("/internal/error/status_code")?.as_str()
+    }
+
+    fn describe(&self) -> String {
+        match &self.extensions {
+            Some(ext) => format!("{} - Extensions: {}", self.message, ext),
+            None => self.message.clone(),
+        }
+    }
+
+    // Map a single GraphQL error to a typed domain error, if we recognise it
+    fn to_typed_error(&self) -> Option<Error> {
+        match self.code()? {
+            "constraint-violation" => {
+                let is_unique = self.postgres_code() == Some("23505")
+                    || self.message.contains("Uniqueness violation");
+                let is_foreign_key = self.postgres_code() == Some("23503")
+                    || self.message.contains("Foreign key violation");
+
+                if is_unique {
+                    Some(Error::DuplicateKey(self.message.clone()))
+                } else if is_foreign_key {
+                    Some(Error::ForeignKeyViolation(self.message.clone()))
+                } else {
+                    None
+                }
+            }
+            "permission-error" | "access-denied" | "invalid-jwt" | "invalid-headers" => {
+                Some(Error::PermissionDenied(self.message.clone()))
+            }
+            "not-found" => Some(Error::NotFound(self.message.clone())),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct RunSqlResponse {
+    result: Option<Vec<Vec<Option<String>>>>,
+}
+
+// Error body of Hasura's schema and metadata APIs
+#[derive(Debug, Deserialize)]
+struct ApiError {
+    code: String,
+    error: String,
+}
+
+impl ApiError {
+    fn to_error(&self) -> Error {
+        match self.code.as_str() {
+            // Tracking a table or creating a relationship that's already there
+            "already-tracked" | "already-exists" => Error::DuplicateKey(self.error.clone()),
+            "permission-error" | "access-denied" => Error::PermissionDenied(self.error.clone()),
+            _ => Error::DbError(format!("{}: {}", self.code, self.error)),
+        }
+    }
+}
+
+// Convert the errors array of a GraphQL response into one domain error.
+// The first recognised error wins; anything else stays a generic DbError.
+fn map_graphql_errors(errors: &[GraphQLError]) -> Error {
+    if let Some(typed) = errors.iter().find_map(GraphQLError::to_typed_error) {
+        return typed;
+    }
+
+    let error_msg = errors.iter()
+        .map(GraphQLError::describe)
+        .collect::<Vec<_>>()
+        .join(", ");
+    Error::DbError(format!("GraphQL error: {}", error_msg))
+}
+
 impl HasuraClient {
-    // Get a singleton instance of the Hasura client
+    // Get a singleton instance of the Hasura client; fails when the
+    // configured credentials can't be loaded
     pub async fn get_instance() -> Result<Arc<Self>> {
-        Ok(HASURA_CLIENT.get_or_init(|| async {
+        HASURA_CLIENT.get_or_try_init(|| async {
             // 打印环境变量信息，便于调试
             println!("Initializing Hasura client...");
             
@@ -53,38 +141,21 @@ impl HasuraClient {
                 }
             };
                 
-            let admin_secret = match std::env::var("NEXT_PUBLIC_HASURA_ADMIN_SECRET") {
-                Ok(val) => {
-                    println!("Found HASURA_ADMIN_SECRET: {}", 
-                             if val.is_empty() { "empty string" } else { "[redacted]" });
-                    val
-                },
-                Err(_) => {
-                    let fallback = "dev_secret".to_string();
-                    println!("NEXT_PUBLIC_HASURA_ADMIN_SECRET not set, using fallback");
-                    fallback
-                }
-            };
-            
-            let mut headers = header::HeaderMap::new();
-            headers.insert(
-                "X-Hasura-Admin-Secret",
-                header::HeaderValue::from_str(&admin_secret).unwrap(),
-            );
+            // Admin secret or service JWT, see HasuraAuth
+            let auth = HasuraAuth::from_env()?;
             
             let client = Client::builder()
-                .default_headers(headers)
                 .build()
                 .expect("Failed to create HTTP client");
             
             println!("Hasura client initialized with endpoint: {}", endpoint);
             
-            Arc::new(Self {
+            Ok(Arc::new(Self {
                 client,
                 endpoint,
-                admin_secret,
-            })
-        }).await.clone())
+                auth,
+            }))
+        }).await.cloned()
     }
     
     // Execute a GraphQL query with improved error handling and logging
@@ -92,6 +163,17 @@ impl HasuraClient {
         query: &str, 
         variables: serde_json::Value
     ) -> Result<T> {
+        let start = std::time::Instant::now();
+        let result = self.execute(query, &variables).await;
+        let elapsed = start.elapsed();
+        latency::db(elapsed);
+        slow::query(query, &variables, elapsed);
+        result
+    }
+
+    async fn execute<T: for<'de> Deserialize<'de>>(&self, query: &str, variables: &serde_json::Value) -> Result<T> {
+        chaos::before_db_call().await?;
+        
         // Log the request
         let operation_type = if query.trim().starts_with("mutation") {
             "Mutation"
@@ -110,15 +192,23 @@ impl HasuraClient {
             operation_name: None,
         };
         
-        let start = std::time::Instant::now();
-        let response = self.client
+        // Tag the call with the command it serves, in our logs and Hasura's
+        let correlation_id = correlation::current();
+        let mut builder = self.auth.graphql(self.client
             .post(&self.endpoint)
-            .json(&request)
+            .json(&request))?;
+        if let Some(id) = correlation_id {
+            builder = builder.header(correlation::HEADER, id.to_string());
+        }
+
+        let start = std::time::Instant::now();
+        let response = builder
             .send()
             .await
             .map_err(|e| {
+                // Connection refused, DNS failure, timeout...: Hasura is unreachable
                 println!("HTTP Request Error: {}", e);
-                Error::DbError(format!("Request error: {}", e))
+                Error::DbUnavailable
             })?;
         
         let status = response.status();
@@ -128,6 +218,9 @@ impl HasuraClient {
             let error_text = response.text().await
                 .unwrap_or_else(|_| "Unknown error".to_string());
             println!("HTTP Error response: {}", error_text);
+            if status.is_server_error() {
+                return Err(Error::DbUnavailable);
+            }
             return Err(Error::DbError(format!("HTTP error {}: {}", status, error_text)));
         }
         
@@ -140,7 +233,8 @@ impl HasuraClient {
         
         println!("Response body: {}", response_text);
         
-        let result: GraphQLResponse<T> = serde_json::from_str(&response_text)
+        // Data is decoded on its own below, so missing fields can be reported
+        let result: GraphQLResponse<serde_json::Value> = serde_json::from_str(&response_text)
             .map_err(|e| {
                 println!("JSON parse error: {}", e);
                 Error::DbError(format!("JSON parse error: {}", e))
@@ -148,23 +242,13 @@ impl HasuraClient {
         
         let elapsed = start.elapsed();
         println!("GraphQL request completed in {:?}", elapsed);
+        tracing::debug!(correlation_id = ?correlation_id, elapsed_ms = elapsed.as_millis() as u64, "GraphQL {} completed", operation_type);
         
         // Handle GraphQL errors
-        if let Some(errors) = result.errors {
-            if !errors.is_empty() {
-                let error_msg = errors.into_iter()
-                    .map(|e| {
-                        let ext_str = e.extensions.map_or_else(
-                            || "".to_string(),
-                            |v| format!(" - Extensions: {}", v)
-                        );
-                        format!("{}{}", e.message, ext_str)
-                    })
-                    .collect::<Vec<_>>()
-                    .join(", ");
-                println!("GraphQL Errors: {}", error_msg);
-                return Err(Error::DbError(format!("GraphQL error: {}", error_msg)));
-            }
+        if let Some(errors) = result.errors && !errors.is_empty() {
+            let error = map_graphql_errors(&errors);
+            println!("GraphQL Errors: {}", error);
+            return Err(error);
         }
         
         // Handle data
@@ -173,7 +257,55 @@ impl HasuraClient {
             None => println!("GraphQL request returned no data")
         }
         
-        result.data.ok_or_else(|| Error::DbError("No data returned".to_string()))
+        let data = result.data.ok_or_else(|| Error::DbError("No data returned".to_string()))?;
+        schema_drift::decode(query, data)
+    }
+    
+    // Run raw SQL through Hasura's schema API (/v2/query). Hasura runs it in
+    // one transaction; returns the result rows, header row first.
+    pub async fn run_sql(&self, sql: &str) -> Result<Vec<Vec<Option<String>>>> {
+        let body = serde_json::json!({
+            "type": "run_sql",
+            "args": {
+                "source": "default",
+                "sql": sql
+            }
+        });
+        let response: RunSqlResponse = self.post_api("/v2/query", &body).await?;
+        Ok(response.result.unwrap_or_default())
+    }
+
+    // Call Hasura's metadata API (/v1/metadata), e.g. pg_track_table
+    pub async fn metadata(&self, body: &serde_json::Value) -> Result<serde_json::Value> {
+        self.post_api("/v1/metadata", body).await
+    }
+
+    // POST to one of Hasura's admin APIs, next to the GraphQL endpoint
+    async fn post_api<T: for<'de> Deserialize<'de>>(&self, path: &str, body: &serde_json::Value) -> Result<T> {
+        let base = self.endpoint.trim_end_matches('/').trim_end_matches("/v1/graphql");
+        let response = self.auth.admin(self.client
+            .post(format!("{}{}", base, path))
+            .json(body))?
+            .send()
+            .await
+            .map_err(|e| {
+                println!("HTTP Request Error: {}", e);
+                Error::DbUnavailable
+            })?;
+
+        let status = response.status();
+        if status.is_server_error() {
+            return Err(Error::DbUnavailable);
+        }
+        let response_text = response.text().await
+            .map_err(|e| Error::DbError(format!("Failed to get response text: {}", e)))?;
+        if !status.is_success() {
+            let error: ApiError = serde_json::from_str(&response_text)
+                .map_err(|_| Error::DbError(format!("HTTP error {}: {}", status, response_text)))?;
+            return Err(error.to_error());
+        }
+        serde_json::from_str(&response_text)
+            .map_err(|e| Error::DbError(format!("JSON parse error: {}", e)))
     }
     
     // Execute a GraphQL mutation (same as query for code reuse)
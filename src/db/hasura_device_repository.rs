@@ -0,0 +1,139 @@
+use std::sync::Arc;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use crate::devices::device::Device;
+use crate::error::{Error, Result};
+
+use super::hasura_client::HasuraClient;
+use super::repository::DeviceRepository;
+
+const DEVICE_FIELDS: &str = r#"
+    user_id
+    device_id
+    platform
+    push_token
+    registered_at
+    last_seen_at
+"#;
+
+pub struct HasuraDeviceRepository {
+    client: Arc<HasuraClient>,
+}
+
+#[derive(Debug, Deserialize)]
+struct DevicesQueryResponse {
+    devices: Vec<Device>,
+}
+
+#[derive(Debug, Deserialize)]
+struct DevicesUpdateResponse {
+    update_devices: AffectedRows,
+}
+
+#[derive(Debug, Deserialize)]
+struct DevicesDeleteResponse {
+    delete_devices: AffectedRows,
+}
+
+#[derive(Debug, Deserialize)]
+struct AffectedRows {
+    affected_rows: i64,
+}
+
+impl HasuraDeviceRepository {
+    pub async fn new() -> Result<Self> {
+        let client = HasuraClient::get_instance().await?;
+        Ok(Self { client })
+    }
+}
+
+#[async_trait]
+impl DeviceRepository for HasuraDeviceRepository {
+    // registered_at is left out of the updated columns, so it keeps the first registration
+    async fn upsert_device(&self, device: &Device) -> Result<()> {
+        let mutation = r#"
+            mutation UpsertDevice($device: devices_insert_input!) {
+                insert_devices_one(
+                    object: $device,
+                    on_conflict: {
+                        constraint: devices_pkey,
+                        update_columns: [platform, push_token, last_seen_at]
+                    }
+                ) {
+                    device_id
+                }
+            }
+        "#;
+
+        let variables = json!({
+            "device": device
+        });
+
+        let _: Value = self.client.mutate(mutation, variables).await?;
+        Ok(())
+    }
+
+    async fn user_devices(&self, user_ids: &[Uuid]) -> Result<Vec<Device>> {
+        let query = format!(r#"
+            query UserDevices($user_ids: [uuid!]!) {{
+                devices(where: {{user_id: {{_in: $user_ids}}}}, order_by: {{last_seen_at: desc}}) {{
+                    {}
+                }}
+            }}
+        "#, DEVICE_FIELDS);
+
+        let variables = json!({
+            "user_ids": user_ids
+        });
+
+        let response: DevicesQueryResponse = self.client.query(&query, variables).await?;
+        Ok(response.devices)
+    }
+
+    async fn touch_device(&self, user_id: Uuid, device_id: &str, at: DateTime<Utc>) -> Result<bool> {
+        let mutation = r#"
+            mutation TouchDevice($user_id: uuid!, $device_id: String!, $at: timestamptz!) {
+                update_devices(
+                    where: {user_id: {_eq: $user_id}, device_id: {_eq: $device_id}},
+                    _set: {last_seen_at: $at}
+                ) {
+                    affected_rows
+                }
+            }
+        "#;
+
+        let variables = json!({
+            "user_id": user_id,
+            "device_id": device_id,
+            "at": at
+        });
+
+        let response: DevicesUpdateResponse = self.client.mutate(mutation, variables).await?;
+        Ok(response.update_devices.affected_rows > 0)
+    }
+
+    async fn delete_device(&self, user_id: Uuid, device_id: &str) -> Result<()> {
+        let mutation = r#"
+            mutation DeleteDevice($user_id: uuid!, $device_id: String!) {
+                delete_devices(where: {user_id: {_eq: $user_id}, device_id: {_eq: $device_id}}) {
+                    affected_rows
+                }
+            }
+        "#;
+
+        let variables = json!({
+            "user_id": user_id,
+            "device_id": device_id
+        });
+
+        let response: DevicesDeleteResponse = self.client.mutate(mutation, variables).await?;
+        if response.delete_devices.affected_rows == 0 {
+            return Err(Error::NotFound(format!("device {} of {}", device_id, user_id)));
+        }
+        Ok(())
+    }
+}
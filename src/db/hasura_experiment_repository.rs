@@ -0,0 +1,113 @@
+use std::sync::Arc;
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::error::Result;
+use crate::experiments::experiment::{Experiment, Variant};
+
+use super::hasura_client::HasuraClient;
+use super::repository::ExperimentRepository;
+
+pub struct HasuraExperimentRepository {
+    client: Arc<HasuraClient>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ExperimentsQueryResponse {
+    experiments: Vec<ExperimentData>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ExperimentData {
+    key: String,
+    salt: String,
+    enabled: bool,
+    // jsonb column
+    variants: Vec<Variant>,
+}
+
+impl From<ExperimentData> for Experiment {
+    fn from(data: ExperimentData) -> Self {
+        Self {
+            key: data.key,
+            salt: data.salt,
+            enabled: data.enabled,
+            variants: data.variants,
+        }
+    }
+}
+
+impl HasuraExperimentRepository {
+    pub async fn new() -> Result<Self> {
+        let client = HasuraClient::get_instance().await?;
+        Ok(Self { client })
+    }
+}
+
+#[async_trait]
+impl ExperimentRepository for HasuraExperimentRepository {
+    async fn list_experiments(&self) -> Result<Vec<Experiment>> {
+        let query = r#"
+            query ListExperiments {
+                experiments(order_by: {key: asc}) {
+                    key
+                    salt
+                    enabled
+                    variants
+                }
+            }
+        "#;
+
+        let response: ExperimentsQueryResponse = self.client.query(query, json!({})).await?;
+        Ok(response.experiments.into_iter().map(Experiment::from).collect())
+    }
+
+    async fn upsert_experiment(&self, experiment: &Experiment) -> Result<()> {
+        let mutation = r#"
+            mutation UpsertExperiment($key: String!, $salt: String!, $enabled: Boolean!, $variants: jsonb!) {
+                insert_experiments_one(
+                    object: {
+                        key: $key,
+                        salt: $salt,
+                        enabled: $enabled,
+                        variants: $variants
+                    },
+                    on_conflict: {
+                        constraint: experiments_pkey,
+                        update_columns: [salt, enabled, variants]
+                    }
+                ) {
+                    key
+                }
+            }
+        "#;
+
+        let variables = json!({
+            "key": experiment.key,
+            "salt": experiment.salt,
+            "enabled": experiment.enabled,
+            "variants": experiment.variants
+        });
+
+        self.client.mutate::<Value>(mutation, variables).await?;
+        Ok(())
+    }
+
+    async fn delete_experiment(&self, key: &str) -> Result<()> {
+        let mutation = r#"
+            mutation DeleteExperiment($key: String!) {
+                delete_experiments_by_pk(key: $key) {
+                    key
+                }
+            }
+        "#;
+
+        let variables = json!({
+            "key": key
+        });
+
+        self.client.mutate::<Value>(mutation, variables).await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,125 @@
+use std::sync::Arc;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::error::Result;
+use crate::matchmaking::fairness::{FairnessReport, QueueSample};
+
+use super::hasura_client::HasuraClient;
+use super::repository::FairnessRepository;
+
+const REPORT_FIELDS: &str = r#"
+    id
+    computed_at
+    window_start
+    blowout_margin
+    groups
+"#;
+
+pub struct HasuraFairnessRepository {
+    client: Arc<HasuraClient>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SamplesQueryResponse {
+    match_queue_samples: Vec<QueueSample>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ReportsQueryResponse {
+    fairness_reports: Vec<FairnessReport>,
+}
+
+impl HasuraFairnessRepository {
+    pub async fn new() -> Result<Self> {
+        let client = HasuraClient::get_instance().await?;
+        Ok(Self { client })
+    }
+}
+
+#[async_trait]
+impl FairnessRepository for HasuraFairnessRepository {
+    async fn insert_queue_samples(&self, samples: &[QueueSample]) -> Result<()> {
+        let mutation = r#"
+            mutation InsertQueueSamples($objects: [match_queue_samples_insert_input!]!) {
+                insert_match_queue_samples(objects: $objects) {
+                    affected_rows
+                }
+            }
+        "#;
+
+        let variables = json!({
+            "objects": samples
+        });
+
+        let _: Value = self.client.mutate(mutation, variables).await?;
+        Ok(())
+    }
+
+    async fn queue_samples(&self, since: DateTime<Utc>, offset: i64, limit: i64) -> Result<Vec<QueueSample>> {
+        let query = r#"
+            query QueueSamples($since: timestamptz!, $offset: Int!, $limit: Int!) {
+                match_queue_samples(
+                    where: {started_at: {_gte: $since}},
+                    order_by: [{started_at: asc}, {match_id: asc}, {user_id: asc}],
+                    offset: $offset,
+                    limit: $limit
+                ) {
+                    match_id
+                    user_id
+                    match_type
+                    region
+                    team_number
+                    mmr
+                    queued_secs
+                    started_at
+                }
+            }
+        "#;
+
+        let variables = json!({
+            "since": since,
+            "offset": offset,
+            "limit": limit
+        });
+
+        let response: SamplesQueryResponse = self.client.query(query, variables).await?;
+        Ok(response.match_queue_samples)
+    }
+
+    async fn insert_report(&self, report: &FairnessReport) -> Result<()> {
+        let mutation = r#"
+            mutation InsertFairnessReport($report: fairness_reports_insert_input!) {
+                insert_fairness_reports_one(object: $report) {
+                    id
+                }
+            }
+        "#;
+
+        let variables = json!({
+            "report": report
+        });
+
+        let _: Value = self.client.mutate(mutation, variables).await?;
+        Ok(())
+    }
+
+    async fn recent_reports(&self, limit: usize) -> Result<Vec<FairnessReport>> {
+        let query = format!(r#"
+            query FairnessReports($limit: Int!) {{
+                fairness_reports(order_by: {{computed_at: desc}}, limit: $limit) {{
+                    {}
+                }}
+            }}
+        "#, REPORT_FIELDS);
+
+        let variables = json!({
+            "limit": limit
+        });
+
+        let response: ReportsQueryResponse = self.client.query(&query, variables).await?;
+        Ok(response.fairness_reports)
+    }
+}
@@ -0,0 +1,157 @@
+use std::sync::Arc;
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use crate::auth::identity::{AccountMerge, IdentityProvider, LinkedIdentity};
+use crate::error::Result;
+
+use super::hasura_client::HasuraClient;
+use super::repository::IdentityRepository;
+
+const IDENTITY_FIELDS: &str = r#"
+    provider
+    subject
+    user_id
+    email
+    linked_at
+"#;
+
+pub struct HasuraIdentityRepository {
+    client: Arc<HasuraClient>,
+}
+
+#[derive(Debug, Deserialize)]
+struct IdentitiesQueryResponse {
+    linked_identities: Vec<LinkedIdentity>,
+}
+
+// What the account already has, which the guest's copies must not overwrite
+#[derive(Debug, Deserialize)]
+struct MergeTargetResponse {
+    devices: Vec<DeviceId>,
+    player_ratings: Vec<Value>,
+}
+
+#[derive(Debug, Deserialize)]
+struct DeviceId {
+    device_id: String,
+}
+
+impl HasuraIdentityRepository {
+    pub async fn new() -> Result<Self> {
+        let client = HasuraClient::get_instance().await?;
+        Ok(Self { client })
+    }
+}
+
+#[async_trait]
+impl IdentityRepository for HasuraIdentityRepository {
+    async fn find_identity(&self, provider: IdentityProvider, subject: &str) -> Result<Option<LinkedIdentity>> {
+        let query = format!(r#"
+            query FindIdentity($provider: String!, $subject: String!) {{
+                linked_identities(where: {{provider: {{_eq: $provider}}, subject: {{_eq: $subject}}}}) {{
+                    {}
+                }}
+            }}
+        "#, IDENTITY_FIELDS);
+
+        let variables = json!({
+            "provider": provider,
+            "subject": subject
+        });
+
+        let response: IdentitiesQueryResponse = self.client.query(&query, variables).await?;
+        Ok(response.linked_identities.into_iter().next())
+    }
+
+    async fn user_identities(&self, user_id: Uuid) -> Result<Vec<LinkedIdentity>> {
+        let query = format!(r#"
+            query UserIdentities($user_id: uuid!) {{
+                linked_identities(where: {{user_id: {{_eq: $user_id}}}}, order_by: {{linked_at: asc}}) {{
+                    {}
+                }}
+            }}
+        "#, IDENTITY_FIELDS);
+
+        let variables = json!({
+            "user_id": user_id
+        });
+
+        let response: IdentitiesQueryResponse = self.client.query(&query, variables).await?;
+        Ok(response.linked_identities)
+    }
+
+    async fn insert_identity(&self, identity: &LinkedIdentity) -> Result<()> {
+        let mutation = r#"
+            mutation InsertIdentity($identity: linked_identities_insert_input!) {
+                insert_linked_identities_one(object: $identity) {
+                    subject
+                }
+            }
+        "#;
+
+        let variables = json!({
+            "identity": identity
+        });
+
+        let _: Value = self.client.mutate(mutation, variables).await?;
+        Ok(())
+    }
+
+    // Hasura runs the root fields of one mutation in order, in a single transaction
+    async fn merge_accounts(&self, merge: &AccountMerge) -> Result<()> {
+        let query = r#"
+            query MergeTarget($user_id: uuid!) {
+                devices(where: {user_id: {_eq: $user_id}}) {
+                    device_id
+                }
+                player_ratings(where: {user_id: {_eq: $user_id}}) {
+                    user_id
+                }
+            }
+        "#;
+        let target: MergeTargetResponse = self.client
+            .query(query, json!({ "user_id": merge.into_user_id }))
+            .await?;
+        let shared_devices: Vec<String> = target.devices.into_iter().map(|device| device.device_id).collect();
+
+        // The account's rating wins; a guest's rating only moves to an unrated account
+        let rating = if target.player_ratings.is_empty() {
+            "update_player_ratings(where: {user_id: {_eq: $from}}, _set: {user_id: $into}) { affected_rows }"
+        } else {
+            "delete_player_ratings(where: {user_id: {_eq: $from}}) { affected_rows }"
+        };
+        let mutation = format!(r#"
+            mutation MergeAccounts($from: uuid!, $into: uuid!, $shared_devices: [String!]!, $merge: account_merges_insert_input!) {{
+                update_match_members(where: {{user_id: {{_eq: $from}}}}, _set: {{user_id: $into}}) {{
+                    affected_rows
+                }}
+                update_match_discoveries(where: {{user_id: {{_eq: $from}}}}, _set: {{user_id: $into}}) {{
+                    affected_rows
+                }}
+                {}
+                delete_devices(where: {{user_id: {{_eq: $from}}, device_id: {{_in: $shared_devices}}}}) {{
+                    affected_rows
+                }}
+                update_devices(where: {{user_id: {{_eq: $from}}}}, _set: {{user_id: $into}}) {{
+                    affected_rows
+                }}
+                insert_account_merges_one(object: $merge) {{
+                    from_user_id
+                }}
+            }}
+        "#, rating);
+
+        let variables = json!({
+            "from": merge.from_user_id,
+            "into": merge.into_user_id,
+            "shared_devices": shared_devices,
+            "merge": merge
+        });
+
+        let _: Value = self.client.mutate(&mutation, variables).await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,116 @@
+use std::sync::Arc;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use crate::error::Result;
+use crate::inbox::message::InboxMessage;
+
+use super::hasura_client::HasuraClient;
+use super::repository::InboxRepository;
+
+const INBOX_FIELDS: &str = r#"
+    id
+    user_id
+    event
+    key
+    data
+    created_at
+    expires_at
+"#;
+
+pub struct HasuraInboxRepository {
+    client: Arc<HasuraClient>,
+}
+
+#[derive(Debug, Deserialize)]
+struct InboxTakeResponse {
+    delete_inbox_messages: Returning,
+}
+
+#[derive(Debug, Deserialize)]
+struct Returning {
+    returning: Vec<InboxMessage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct InboxDeleteResponse {
+    delete_inbox_messages: AffectedRows,
+}
+
+#[derive(Debug, Deserialize)]
+struct AffectedRows {
+    affected_rows: i64,
+}
+
+impl HasuraInboxRepository {
+    pub async fn new() -> Result<Self> {
+        let client = HasuraClient::get_instance().await?;
+        Ok(Self { client })
+    }
+}
+
+#[async_trait]
+impl InboxRepository for HasuraInboxRepository {
+    // Unique on (user_id, event, key), so the earlier message is kept
+    async fn insert_messages(&self, messages: &[InboxMessage]) -> Result<()> {
+        let mutation = r#"
+            mutation InsertInboxMessages($objects: [inbox_messages_insert_input!]!) {
+                insert_inbox_messages(
+                    objects: $objects,
+                    on_conflict: {
+                        constraint: inbox_messages_user_id_event_key_key,
+                        update_columns: []
+                    }
+                ) {
+                    affected_rows
+                }
+            }
+        "#;
+
+        let variables = json!({
+            "objects": messages
+        });
+
+        let _: Value = self.client.mutate(mutation, variables).await?;
+        Ok(())
+    }
+
+    async fn take_messages(&self, user_id: Uuid) -> Result<Vec<InboxMessage>> {
+        let mutation = format!(r#"
+            mutation TakeInboxMessages($user_id: uuid!) {{
+                delete_inbox_messages(where: {{user_id: {{_eq: $user_id}}}}) {{
+                    returning {{
+                        {}
+                    }}
+                }}
+            }}
+        "#, INBOX_FIELDS);
+
+        let variables = json!({
+            "user_id": user_id
+        });
+
+        let response: InboxTakeResponse = self.client.mutate(&mutation, variables).await?;
+        Ok(response.delete_inbox_messages.returning)
+    }
+
+    async fn delete_expired(&self, now: DateTime<Utc>) -> Result<i64> {
+        let mutation = r#"
+            mutation DeleteExpiredInboxMessages($now: timestamptz!) {
+                delete_inbox_messages(where: {expires_at: {_lte: $now}}) {
+                    affected_rows
+                }
+            }
+        "#;
+
+        let variables = json!({
+            "now": now
+        });
+
+        let response: InboxDeleteResponse = self.client.mutate(mutation, variables).await?;
+        Ok(response.delete_inbox_messages.affected_rows)
+    }
+}
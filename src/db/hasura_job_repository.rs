@@ -0,0 +1,191 @@
+use std::sync::Arc;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use crate::error::Result;
+use crate::jobs::job::Job;
+
+use super::hasura_client::HasuraClient;
+use super::repository::JobRepository;
+
+const JOB_FIELDS: &str = r#"
+    id
+    kind
+    status
+    created_by
+    node_id
+    total
+    done
+    failed
+    errors
+    created_at
+    updated_at
+    finished_at
+"#;
+
+pub struct HasuraJobRepository {
+    client: Arc<HasuraClient>,
+}
+
+#[derive(Debug, Deserialize)]
+struct JobQueryResponse {
+    jobs_by_pk: Option<Job>,
+}
+
+#[derive(Debug, Deserialize)]
+struct JobsQueryResponse {
+    jobs: Vec<Job>,
+}
+
+#[derive(Debug, Deserialize)]
+struct JobUpdateResponse {
+    update_jobs: AffectedRows,
+}
+
+#[derive(Debug, Deserialize)]
+struct JobDeleteResponse {
+    delete_jobs: AffectedRows,
+}
+
+#[derive(Debug, Deserialize)]
+struct AffectedRows {
+    affected_rows: i64,
+}
+
+impl HasuraJobRepository {
+    pub async fn new() -> Result<Self> {
+        let client = HasuraClient::get_instance().await?;
+        Ok(Self { client })
+    }
+}
+
+#[async_trait]
+impl JobRepository for HasuraJobRepository {
+    async fn save_job(&self, job: &Job) -> Result<()> {
+        let mutation = r#"
+            mutation SaveJob($job: jobs_insert_input!) {
+                insert_jobs_one(
+                    object: $job,
+                    on_conflict: {
+                        constraint: jobs_pkey,
+                        update_columns: [status, total, done, failed, errors, updated_at, finished_at]
+                    }
+                ) {
+                    id
+                }
+            }
+        "#;
+
+        let variables = json!({
+            "job": job
+        });
+
+        let _: Value = self.client.mutate(mutation, variables).await?;
+        Ok(())
+    }
+
+    async fn get_job(&self, id: Uuid) -> Result<Option<Job>> {
+        let query = format!(r#"
+            query Job($id: uuid!) {{
+                jobs_by_pk(id: $id) {{
+                    {}
+                }}
+            }}
+        "#, JOB_FIELDS);
+
+        let variables = json!({
+            "id": id
+        });
+
+        let response: JobQueryResponse = self.client.query(&query, variables).await?;
+        Ok(response.jobs_by_pk)
+    }
+
+    async fn recent_jobs(&self, limit: usize) -> Result<Vec<Job>> {
+        let query = format!(r#"
+            query RecentJobs($limit: Int!) {{
+                jobs(order_by: {{created_at: desc}}, limit: $limit) {{
+                    {}
+                }}
+            }}
+        "#, JOB_FIELDS);
+
+        let variables = json!({
+            "limit": limit
+        });
+
+        let response: JobsQueryResponse = self.client.query(&query, variables).await?;
+        Ok(response.jobs)
+    }
+
+    async fn touch_jobs(&self, ids: &[Uuid], at: DateTime<Utc>) -> Result<()> {
+        let mutation = r#"
+            mutation TouchJobs($ids: [uuid!]!, $at: timestamptz!) {
+                update_jobs(
+                    where: {id: {_in: $ids}, status: {_eq: "running"}},
+                    _set: {updated_at: $at}
+                ) {
+                    affected_rows
+                }
+            }
+        "#;
+
+        let variables = json!({
+            "ids": ids,
+            "at": at
+        });
+
+        let _: Value = self.client.mutate(mutation, variables).await?;
+        Ok(())
+    }
+
+    async fn fail_running_jobs(&self, node_id: Option<&str>, updated_before: DateTime<Utc>, error: &str, at: DateTime<Utc>) -> Result<i64> {
+        let mutation = r#"
+            mutation FailRunningJobs($where: jobs_bool_exp!, $errors: jsonb!, $at: timestamptz!) {
+                update_jobs(
+                    where: $where,
+                    _set: {status: "failed", updated_at: $at, finished_at: $at},
+                    _append: {errors: $errors}
+                ) {
+                    affected_rows
+                }
+            }
+        "#;
+
+        let mut filter = json!({
+            "status": {"_eq": "running"},
+            "updated_at": {"_lt": updated_before}
+        });
+        if let Some(node_id) = node_id {
+            filter["node_id"] = json!({"_eq": node_id});
+        }
+        let variables = json!({
+            "where": filter,
+            "errors": [error],
+            "at": at
+        });
+
+        let response: JobUpdateResponse = self.client.mutate(mutation, variables).await?;
+        Ok(response.update_jobs.affected_rows)
+    }
+
+    async fn delete_jobs_before(&self, cutoff: DateTime<Utc>) -> Result<i64> {
+        let mutation = r#"
+            mutation DeleteOldJobs($cutoff: timestamptz!) {
+                delete_jobs(where: {created_at: {_lt: $cutoff}, status: {_neq: "running"}}) {
+                    affected_rows
+                }
+            }
+        "#;
+
+        let variables = json!({
+            "cutoff": cutoff
+        });
+
+        let response: JobDeleteResponse = self.client.mutate(mutation, variables).await?;
+        Ok(response.delete_jobs.affected_rows)
+    }
+}
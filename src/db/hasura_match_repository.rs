@@ -1,13 +1,23 @@
+use std::collections::HashMap;
 use std::sync::Arc;
+use async_trait::async_trait;
 use uuid::Uuid;
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
 use chrono::{DateTime, Utc};
 
 use crate::error::{Error, Result};
-use crate::models::game::{MatchRoom, MatchTeam, MatchMember, MatchDetails, TeamDetails, MemberDetails};
+use crate::matchmaking::review::{AuditEntry, MatchReview};
+use crate::matchmaking::verify::Anomaly;
+use crate::models::game::{
+    MatchRoom, MatchStatus, MatchTeam, MatchMember, MatchDetails, TeamDetails, MemberDetails, MatchScores,
+    LobbySelection, Platform, PlatformBreakdown, PlayerExperience, RoundResult, TeamStrength,
+};
+use crate::game::victory::VictoryCondition;
+use crate::rating::calibration::MatchPrediction;
 
 use super::hasura_client::HasuraClient;
+use super::repository::MatchRepository;
 
 pub struct HasuraMatchRepository {
     client: Arc<HasuraClient>,
@@ -38,6 +48,16 @@ struct MatchUpdateResponse {
     update_treasure_matches_by_pk: Option<MatchData>,
 }
 
+#[derive(Debug, Deserialize)]
+struct MatchIdResponse {
+    update_treasure_matches_by_pk: Option<Value>,
+}
+
+#[derive(Debug, Deserialize)]
+struct AffectedRows {
+    affected_rows: i64,
+}
+
 #[derive(Debug, Deserialize)]
 struct MatchQueryResponse {
     treasure_matches_by_pk: Option<MatchData>,
@@ -53,15 +73,145 @@ struct UserInMatchResponse {
     match_members: Vec<UserMatchData>,
 }
 
+#[derive(Debug, Deserialize)]
+struct ScoresQueryResponse {
+    treasure_matches: Vec<MatchScores>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PredictionsQueryResponse {
+    treasure_matches: Vec<MatchPrediction>,
+}
+
+// Fields aliased to the MatchScores names
+const MATCH_SCORES_FIELDS: &str = r#"
+    match_id: id
+    victory_condition
+    status
+    start_time
+    winner_team_id
+    teams: match_teams {
+        team_id: id
+        total_score
+        objective_score
+    }
+    members: match_members {
+        user_id
+        team_id
+        individual_score
+    }
+    discoveries: match_discoveries(order_by: {discovered_at: asc}) {
+        id
+        team_id
+        user_id
+        treasure_id
+        score
+        discovered_at
+        invalidated
+    }
+    rounds: match_rounds(order_by: {round_number: asc}) {
+        match_id
+        round_number
+        scores
+        winner_team_id
+        ended_at
+    }
+"#;
+
+#[derive(Debug, Deserialize)]
+struct ReviewsQueryResponse {
+    treasure_matches: Vec<MatchReview>,
+}
+
+// Fields aliased to the MatchReview names
+const MATCH_REVIEW_FIELDS: &str = r#"
+    match_id: id
+    match_type
+    status
+    start_time
+    end_time
+    winner_team_id
+    review_notes
+    teams: match_teams(order_by: {team_number: asc}) {
+        team_id: id
+        total_score
+        objective_score
+    }
+    members: match_members {
+        user_id
+        team_id
+        individual_score
+    }
+    discoveries: match_discoveries(order_by: {discovered_at: asc}) {
+        id
+        team_id
+        user_id
+        treasure_id
+        score
+        discovered_at
+        invalidated
+    }
+    adjustments: match_adjustments(order_by: {created_at: asc}) {
+        id
+        match_id
+        actor
+        reason
+        adjustment
+        created_at
+    }
+"#;
+
+#[derive(Debug, Deserialize)]
+struct ExperienceQueryResponse {
+    users_by_pk: Option<AccountData>,
+    treasure_matches_aggregate: MatchesAggregate,
+}
+
+#[derive(Debug, Deserialize)]
+struct AccountData {
+    created_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct MatchesAggregate {
+    aggregate: MatchesCount,
+}
+
+#[derive(Debug, Deserialize)]
+struct MatchesCount {
+    count: i64,
+}
+
+#[derive(Debug, Deserialize)]
+struct DiscoveryInvalidateResponse {
+    update_match_discoveries: InvalidatedDiscoveries,
+}
+
+#[derive(Debug, Deserialize)]
+struct InvalidatedDiscoveries {
+    returning: Vec<InvalidatedDiscovery>,
+}
+
+#[derive(Debug, Deserialize)]
+struct InvalidatedDiscovery {
+    team_id: Uuid,
+    user_id: Uuid,
+    score: i32,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 struct MatchData {
     id: Uuid,
     match_type: String,
-    status: String,
+    status: MatchStatus,
     required_players_per_team: i32,
     start_time: Option<DateTime<Utc>>,
     end_time: Option<DateTime<Utc>>,
     winner_team_id: Option<Uuid>,
+    #[serde(default)]
+    victory_condition: Option<VictoryCondition>,
+    #[serde(default)]
+    rounds: Option<Vec<RoundResult>>,
     match_teams: Option<Vec<TeamData>>,
     match_members: Option<Vec<MemberData>>,
 }
@@ -73,29 +223,45 @@ struct TeamData {
     current_players: i32,
     max_players: i32,
     total_score: i32,
+    win_probability: Option<f64>,
+    #[serde(default)]
+    objective_score: i32,
     match_members: Option<Vec<MemberWithUserData>>,
 }
 
+
 #[derive(Debug, Serialize, Deserialize)]
 struct MemberData {
     id: Uuid,
     user_id: Uuid,
     #[serde(default)]
     individual_score: i32,
+    #[serde(default)]
+    platform: Option<Platform>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 struct MemberWithUserData {
     id: Uuid,
     user_id: Uuid,
+    #[serde(default)]
     individual_score: i32,
+    #[serde(default)]
+    platform: Option<Platform>,
+    #[serde(default)]
+    loadout: Option<String>,
+    #[serde(default)]
+    role: Option<String>,
     user: UserData,
 }
 
+// Profile fields are only shown to players, so a profile missing them still loads
 #[derive(Debug, Serialize, Deserialize)]
 struct UserData {
     id: Uuid,
+    #[serde(default)]
     nickname: String,
+    #[serde(default)]
     avatar_url: String,
 }
 
@@ -114,16 +280,25 @@ impl HasuraMatchRepository {
         let client = HasuraClient::get_instance().await?;
         Ok(Self { client })
     }
+}
+
+#[async_trait]
+impl MatchRepository for HasuraMatchRepository {
+    async fn ping(&self) -> Result<()> {
+        self.client.query::<Value>("query Ping { __typename }", json!({})).await?;
+        Ok(())
+    }
     
     // Create a new match in the database
-    pub async fn create_match(&self, match_id: Uuid, match_type: &str, required_players_per_team: i32) -> Result<Uuid> {
+    async fn create_match(&self, match_id: Uuid, match_type: &str, required_players_per_team: i32, victory: VictoryCondition) -> Result<Uuid> {
         let mutation = r#"
-            mutation CreateMatch($id: uuid!, $match_type: String!, $required_players: Int!) {
+            mutation CreateMatch($id: uuid!, $match_type: String!, $status: String!, $required_players: Int!, $victory_condition: jsonb!) {
                 insert_treasure_matches_one(object: {
                     id: $id,
                     match_type: $match_type,
-                    status: "matching",
-                    required_players_per_team: $required_players
+                    status: $status,
+                    required_players_per_team: $required_players,
+                    victory_condition: $victory_condition
                 }) {
                     id
                     match_type
@@ -136,7 +311,9 @@ impl HasuraMatchRepository {
         let variables = json!({
             "id": match_id,
             "match_type": match_type,
-            "required_players": required_players_per_team
+            "status": MatchStatus::Matching.to_str(),
+            "required_players": required_players_per_team,
+            "victory_condition": victory
         });
         
         let response: MatchInsertResponse = self.client.mutate(mutation, variables).await?;
@@ -144,9 +321,9 @@ impl HasuraMatchRepository {
     }
     
     // Create a team for a match
-    pub async fn create_team(&self, team_id: Uuid, match_id: Uuid, team_number: i32, max_players: i32) -> Result<Uuid> {
+    async fn create_team(&self, team_id: Uuid, match_id: Uuid, team_number: i32, max_players: i32, strength: Option<TeamStrength>) -> Result<Uuid> {
         let mutation = r#"
-            mutation CreateTeam($id: uuid!, $match_id: uuid!, $team_number: Int!, $max_players: Int!) {
+            mutation CreateTeam($id: uuid!, $match_id: uuid!, $team_number: Int!, $max_players: Int!, $mmr: float8, $win_probability: float8) {
                 insert_match_teams_one(object: {
                     id: $id,
                     match_id: $match_id,
@@ -154,6 +331,8 @@ impl HasuraMatchRepository {
                     max_players: $max_players,
                     current_players: 0
                     total_score: 0
+                    mmr: $mmr
+                    win_probability: $win_probability
                 }) {
                     id
                     team_number
@@ -168,7 +347,9 @@ impl HasuraMatchRepository {
             "id": team_id,
             "match_id": match_id,
             "team_number": team_number,
-            "max_players": max_players
+            "max_players": max_players,
+            "mmr": strength.map(|s| s.mmr),
+            "win_probability": strength.map(|s| s.win_probability)
         });
         
         let response: TeamInsertResponse = self.client.mutate(mutation, variables).await?;
@@ -176,15 +357,26 @@ impl HasuraMatchRepository {
     }
     
     // Add a player to a team
-    pub async fn add_player_to_team(&self, match_id: Uuid, team_id: Uuid, user_id: Uuid) -> Result<Uuid> {
-        // 插入队员记录
+    //
+    // Taking the slot and inserting the member are one mutation, which Hasura
+    // runs in a single transaction. The match_teams_capacity check keeps
+    // current_players within the team's stored max_players, so a full team
+    // fails the increment and nothing is written.
+    async fn add_player_to_team(&self, match_id: Uuid, team_id: Uuid, user_id: Uuid, platform: Option<Platform>) -> Result<Uuid> {
         let mutation = r#"
-            mutation AddPlayerToTeam($match_id: uuid!, $team_id: uuid!, $user_id: uuid!) {
+            mutation AddPlayerToTeam($match_id: uuid!, $team_id: uuid!, $user_id: uuid!, $platform: String) {
+                update_match_teams(
+                    where: {id: {_eq: $team_id}},
+                    _inc: {current_players: 1}
+                ) {
+                    affected_rows
+                }
                 insert_match_members_one(object: {
                     match_id: $match_id,
                     team_id: $team_id,
                     user_id: $user_id,
-                    individual_score: 0
+                    individual_score: 0,
+                    platform: $platform
                 }) {
                     id
                     user_id
@@ -195,77 +387,36 @@ impl HasuraMatchRepository {
         let variables = json!({
             "match_id": match_id,
             "team_id": team_id,
-            "user_id": user_id
+            "user_id": user_id,
+            "platform": platform.map(|p| p.to_str())
         });
         
-        let response: MemberInsertResponse = self.client.mutate(mutation, variables).await?;
-        
-        // 首先获取当前team信息
-        let query = r#"
-            query GetTeamInfo($team_id: uuid!) {
-                match_teams_by_pk(id: $team_id) {
-                    current_players
-                    max_players
-                }
+        match self.client.mutate::<MemberInsertResponse>(mutation, variables).await {
+            Ok(response) => Ok(response.insert_match_members_one.id),
+            // 队伍已满
+            Err(Error::DbError(message)) if message.contains("match_teams_capacity") => {
+                println!("队伍 {} 已满，无法加入玩家 {}", team_id, user_id);
+                Err(Error::TeamFull)
             }
-        "#;
-        
-        let query_variables = json!({
-            "team_id": team_id
-        });
-        
-        #[derive(Debug, Deserialize)]
-        struct TeamInfoResponse {
-            match_teams_by_pk: TeamInfo,
-        }
-        
-        #[derive(Debug, Deserialize)]
-        struct TeamInfo {
-            current_players: i32,
-            max_players: i32,
+            // 同一玩家重复加入
+            Err(Error::DuplicateKey(_)) => Err(Error::UserAlreadyInMatch),
+            // 比赛或队伍记录不存在
+            Err(Error::ForeignKeyViolation(_)) => Err(Error::MatchNotFound),
+            Err(e) => Err(e),
         }
-        
-        let team_info: TeamInfoResponse = self.client.query(query, query_variables).await?;
-        
-        // 计算新的玩家数量，确保不超过最大值
-        let current = team_info.match_teams_by_pk.current_players;
-        let max = team_info.match_teams_by_pk.max_players;
-        let new_count = std::cmp::min(current + 1, max);
-        
-        // 使用直接设置的方式更新
-        let update_mutation = r#"
-            mutation UpdateTeamDirectly($team_id: uuid!, $current_players: Int!) {
-                update_match_teams_by_pk(
-                    pk_columns: {id: $team_id},
-                    _set: {current_players: $current_players}
-                ) {
-                    id
-                    current_players
-                }
-            }
-        "#;
-        
-        let update_variables = json!({
-            "team_id": team_id,
-            "current_players": new_count
-        });
-        
-        self.client.mutate::<Value>(update_mutation, update_variables).await?;
-        
-        Ok(response.insert_match_members_one.id)
     }
-
+    
     // Start a match
-    pub async fn start_match(&self, match_id: Uuid) -> Result<()> {
+    async fn start_match(&self, match_id: Uuid) -> Result<()> {
         let now = chrono::Utc::now();
         let now_iso = now.to_rfc3339();
         
         // 简化查询，确保语法正确
         let mutation = r#"
-            mutation StartMatch($id: uuid!, $start_time: timestamptz!) {
+            mutation StartMatch($id: uuid!, $status: String!, $start_time: timestamptz!) {
                 update_treasure_matches_by_pk(
                     pk_columns: {id: $id},
-                    _set: {status: "playing", start_time: $start_time}
+                    _set: {status: $status, start_time: $start_time}
                 ) {
                     id
                     status
@@ -278,10 +429,11 @@ impl HasuraMatchRepository {
         
         let variables = json!({
             "id": match_id,
+            "status": MatchStatus::Playing.to_str(),
             "start_time": now_iso
         });
         
-        println!("开始匹配: {} 状态设为playing, 时间: {}", match_id, now_iso);
+        println!("开始匹配: {} 状态设为{}, 时间: {}", match_id, MatchStatus::Playing, now_iso);
         
         let response: MatchUpdateResponse = self.client.mutate(mutation, variables).await?;
         
@@ -296,7 +448,7 @@ impl HasuraMatchRepository {
     }
     
     // Record a treasure discovery
-    pub async fn record_discovery(&self, match_id: Uuid, team_id: Uuid, user_id: Uuid, treasure_id: Uuid, score: i32) -> Result<Uuid> {
+    async fn record_discovery(&self, match_id: Uuid, team_id: Uuid, user_id: Uuid, treasure_id: Uuid, score: i32) -> Result<Uuid> {
         // Create discovery record
         let mutation = r#"
             mutation RecordDiscovery($match_id: uuid!, $team_id: uuid!, $user_id: uuid!, $treasure_id: uuid!, $score: Int!) {
@@ -367,37 +519,61 @@ impl HasuraMatchRepository {
         
         Ok(response.insert_match_discoveries_one.id)
     }
-    
-    // End a match
-    pub async fn end_match(&self, match_id: Uuid) -> Result<()> {
-        // Find the winning team
-        let query = r#"
-            query GetWinningTeam($match_id: uuid!) {
-                match_teams(
-                    where: {match_id: {_eq: $match_id}},
-                    order_by: {total_score: desc},
-                    limit: 1
+
+    async fn add_objective_points(&self, team_id: Uuid, points: i32) -> Result<()> {
+        let mutation = r#"
+            mutation AddObjectivePoints($team_id: uuid!, $points: Int!) {
+                update_match_teams_by_pk(
+                    pk_columns: {id: $team_id},
+                    _inc: {objective_score: $points}
                 ) {
                     id
                 }
             }
         "#;
-        
+
         let variables = json!({
-            "match_id": match_id
+            "team_id": team_id,
+            "points": points
         });
-        
-        println!("查找匹配 {} 的获胜队伍", match_id);
-        
-        let response: TeamsQueryResponse = self.client.query(query, variables).await?;
-        
-        if response.match_teams.is_empty() {
-            println!("错误: 未找到队伍");
-            return Err(Error::MatchNotFound);
+
+        let response: Value = self.client.mutate(mutation, variables).await?;
+        if response["update_match_teams_by_pk"].is_null() {
+            return Err(Error::NotFound(format!("team {}", team_id)));
         }
-        
-        let winner_id = response.match_teams[0].id;
-        println!("获胜队伍: {}", winner_id);
+        Ok(())
+    }
+
+    async fn record_round(&self, round: &RoundResult) -> Result<()> {
+        let mutation = r#"
+            mutation RecordRound($match_id: uuid!, $round_number: Int!, $scores: jsonb!, $winner_team_id: uuid, $ended_at: timestamptz!) {
+                insert_match_rounds_one(object: {
+                    match_id: $match_id,
+                    round_number: $round_number,
+                    scores: $scores,
+                    winner_team_id: $winner_team_id,
+                    ended_at: $ended_at
+                }) {
+                    id
+                }
+            }
+        "#;
+
+        let variables = json!({
+            "match_id": round.match_id,
+            "round_number": round.round_number,
+            "scores": round.scores,
+            "winner_team_id": round.winner_team_id,
+            "ended_at": round.ended_at.to_rfc3339()
+        });
+
+        let _: Value = self.client.mutate(mutation, variables).await?;
+        Ok(())
+    }
+    
+    // End a match
+    async fn end_match(&self, match_id: Uuid, winner_team_id: Option<Uuid>) -> Result<()> {
+        println!("获胜队伍: {:?}", winner_team_id);
         
         // 使用ISO格式时间
         let now = chrono::Utc::now();
@@ -405,12 +581,12 @@ impl HasuraMatchRepository {
         
         // Update match status and set winner
         let mutation = r#"
-            mutation EndMatch($id: uuid!, $winner_id: uuid!, $end_time: timestamptz!) {
+            mutation EndMatch($id: uuid!, $status: String!, $winner_id: uuid, $end_time: timestamptz!) {
                 update_treasure_matches_by_pk(
                     pk_columns: {id: $id},
                     _set: {
-                        status: "finished",  // 修改为正确的状态值
-                        end_time: $end_time,  // 使用ISO格式时间
+                        status: $status,
+                        end_time: $end_time,  # 使用ISO格式时间
                         is_finished: true,
                         winner_team_id: $winner_id
                     }
@@ -425,11 +601,12 @@ impl HasuraMatchRepository {
         
         let variables = json!({
             "id": match_id,
-            "winner_id": winner_id,
+            "status": MatchStatus::Finished.to_str(),
+            "winner_id": winner_team_id,
             "end_time": now_iso
         });
         
-        println!("结束匹配 {}, 获胜队伍: {}", match_id, winner_id);
+        println!("结束匹配 {}, 获胜队伍: {:?}", match_id, winner_team_id);
         
         let response: MatchUpdateResponse = self.client.mutate(mutation, variables).await?;
         
@@ -443,8 +620,41 @@ impl HasuraMatchRepository {
         Ok(())
     }
     
+    // End a match as under_review; the anomalies go to review_notes
+    async fn flag_for_review(&self, match_id: Uuid, anomalies: &[Anomaly]) -> Result<()> {
+        let mutation = r#"
+            mutation FlagForReview($id: uuid!, $status: String!, $end_time: timestamptz!, $notes: jsonb!) {
+                update_treasure_matches_by_pk(
+                    pk_columns: {id: $id},
+                    _set: {
+                        status: $status,
+                        end_time: $end_time,
+                        review_notes: $notes
+                    }
+                ) {
+                    id
+                }
+            }
+        "#;
+        
+        let variables = json!({
+            "id": match_id,
+            "status": MatchStatus::UnderReview.to_str(),
+            "end_time": Utc::now(),
+            "notes": anomalies
+        });
+        
+        let response: MatchIdResponse = self.client.mutate(mutation, variables).await?;
+        
+        if response.update_treasure_matches_by_pk.is_none() {
+            return Err(Error::MatchNotFound);
+        }
+        
+        Ok(())
+    }
+    
     // Get match details
-    pub async fn get_match(&self, match_id: Uuid) -> Result<MatchRoom> {
+    async fn get_match(&self, match_id: Uuid) -> Result<MatchRoom> {
         let query = r#"
             query GetMatch($id: uuid!) {
                 treasure_matches_by_pk(id: $id) {
@@ -454,6 +664,7 @@ impl HasuraMatchRepository {
                     required_players_per_team
                     match_members {
                         user_id
+                        platform
                     }
                 }
             }
@@ -468,10 +679,12 @@ impl HasuraMatchRepository {
         let match_data = response.treasure_matches_by_pk
             .ok_or(Error::MatchNotFound)?;
         
-        // Extract player IDs
-        let players = match_data.match_members.map_or_else(Vec::new, |members| {
-            members.into_iter().map(|m| m.user_id).collect()
-        });
+        // Extract player IDs and platforms
+        let members = match_data.match_members.unwrap_or_default();
+        let players: Vec<Uuid> = members.iter().map(|m| m.user_id).collect();
+        let platforms = members.iter()
+            .filter_map(|m| Some((m.user_id, m.platform?)))
+            .collect();
         
         Ok(MatchRoom {
             id: match_data.id,
@@ -479,11 +692,18 @@ impl HasuraMatchRepository {
             current_players: players.len() as i32,
             players,
             status: match_data.status,
+            parties: Vec::new(),
+            platforms,
+            seats: HashMap::new(),
+            teams: Vec::new(),
+            selections: HashMap::new(),
+            lobby_closes_at: None,
+            private: None,
         })
     }
     
     // Get teams for a match
-    pub async fn get_match_teams(&self, match_id: Uuid) -> Result<Vec<MatchTeam>> {
+    async fn get_match_teams(&self, match_id: Uuid) -> Result<Vec<MatchTeam>> {
         let query = r#"
             query GetMatchTeams($match_id: uuid!) {
                 match_teams(
@@ -518,7 +738,6 @@ impl HasuraMatchRepository {
             let members = team.match_members.unwrap_or_default().into_iter().map(|m| {
                 MatchMember {
                     user_id: m.user_id,
-                    score: m.individual_score,
                 }
             }).collect();
             
@@ -526,7 +745,6 @@ impl HasuraMatchRepository {
                 id: team.id,
                 team_number: team.team_number,
                 members,
-                total_score: team.total_score,
             }
         }).collect();
         
@@ -534,7 +752,7 @@ impl HasuraMatchRepository {
     }
     
     // Get match details with user info
-    pub async fn get_match_details(&self, match_id: Uuid) -> Result<MatchDetails> {
+    async fn get_match_details(&self, match_id: Uuid) -> Result<MatchDetails> {
         let query = r#"
             query GetMatchDetails($id: uuid!) {
                 treasure_matches_by_pk(id: $id) {
@@ -543,14 +761,27 @@ impl HasuraMatchRepository {
                     status
                     start_time
                     end_time
+                    victory_condition
+                    rounds: match_rounds(order_by: {round_number: asc}) {
+                        match_id
+                        round_number
+                        scores
+                        winner_team_id
+                        ended_at
+                    }
                     match_teams(order_by: {team_number: asc}) {
                         id
                         team_number
                         total_score
+                        win_probability
+                        objective_score
                         match_members {
                             id
                             user_id
                             individual_score
+                            platform
+                            loadout
+                            role
                             user {
                                 id
                                 nickname
@@ -590,6 +821,9 @@ impl HasuraMatchRepository {
                     nickname: m.user.nickname,
                     avatar_url: m.user.avatar_url,
                     score: m.individual_score,
+                    platform: m.platform,
+                    loadout: m.loadout,
+                    role: m.role,
                 }
             }).collect();
             
@@ -598,8 +832,13 @@ impl HasuraMatchRepository {
                 team_number: team.team_number,
                 members,
                 total_score: team.total_score,
+                win_probability: team.win_probability,
+                objective_score: team.objective_score,
             }
-        }).collect();
+        }).collect::<Vec<TeamDetails>>();
+        let platforms: PlatformBreakdown = teams.iter()
+            .flat_map(|team| team.members.iter().map(|m| m.platform))
+            .collect();
         
         Ok(MatchDetails {
             id: match_data.id,
@@ -608,10 +847,13 @@ impl HasuraMatchRepository {
             start_time: match_data.start_time,
             teams,
             duration,
+            platforms,
+            victory_condition: match_data.victory_condition.unwrap_or_default(),
+            rounds: match_data.rounds.unwrap_or_default(),
         })
     }
     
-    pub async fn is_user_in_match(&self, user_id: Uuid) -> Result<Option<Uuid>> {
+    async fn is_user_in_match(&self, user_id: Uuid) -> Result<Option<Uuid>> {
         // First, get all match IDs for this user
         let query = r#"
             query IsUserInMatch($user_id: uuid!) {
@@ -640,11 +882,11 @@ impl HasuraMatchRepository {
         
         // Now check if any of these matches are active
         let active_match_query = r#"
-            query GetActiveMatches($match_ids: [uuid!]) {
+            query GetActiveMatches($match_ids: [uuid!], $statuses: [String!]) {
                 treasure_matches(
                     where: {
                         id: {_in: $match_ids},
-                        status: {_in: ["matching", "in_progress"]}
+                        status: {_in: $statuses}
                     },
                     limit: 1
                 ) {
@@ -663,8 +905,14 @@ impl HasuraMatchRepository {
             id: Uuid,
         }
         
+        let active_statuses: Vec<&str> = [MatchStatus::Matching, MatchStatus::Ready, MatchStatus::Playing]
+            .into_iter()
+            .map(MatchStatus::to_str)
+            .collect();
+        
         let active_match_variables = json!({
-            "match_ids": match_ids
+            "match_ids": match_ids,
+            "statuses": active_statuses
         });
         
         let active_match_response: ActiveMatchResponse = self.client.query(active_match_query, active_match_variables).await?;
@@ -675,4 +923,432 @@ impl HasuraMatchRepository {
         
         Ok(Some(active_match_response.treasure_matches[0].id))
     }
-}
\ No newline at end of file
+    
+    // Rewrite legacy status values ("in_progress", "completed") to the canonical ones.
+    // Safe to run repeatedly; returns the number of rows changed.
+    async fn migrate_legacy_statuses(&self) -> Result<i64> {
+        let mutation = r#"
+            mutation MigrateLegacyStatus($legacy: String!, $status: String!) {
+                update_treasure_matches(
+                    where: {status: {_eq: $legacy}},
+                    _set: {status: $status}
+                ) {
+                    affected_rows
+                }
+            }
+        "#;
+        
+        #[derive(Debug, Deserialize)]
+        struct MigrateResponse {
+            update_treasure_matches: AffectedRows,
+        }
+        
+        let mut migrated = 0;
+        for (legacy, status) in MatchStatus::LEGACY_VALUES {
+            let variables = json!({
+                "legacy": legacy,
+                "status": status.to_str()
+            });
+            
+            let response: MigrateResponse = self.client.mutate(mutation, variables).await?;
+            let affected = response.update_treasure_matches.affected_rows;
+            if affected > 0 {
+                println!("状态迁移: {} -> {}, {} 行", legacy, status, affected);
+            }
+            migrated += affected;
+        }
+        
+        Ok(migrated)
+    }
+    
+    async fn get_match_scores(&self, match_id: Uuid) -> Result<MatchScores> {
+        let query = format!(r#"
+            query GetMatchScores($where: treasure_matches_bool_exp!) {{
+                treasure_matches(where: $where) {{
+                    {}
+                }}
+            }}
+        "#, MATCH_SCORES_FIELDS);
+        
+        let variables = json!({
+            "where": {"id": {"_eq": match_id}}
+        });
+        
+        let response: ScoresQueryResponse = self.client.query(&query, variables).await?;
+        response.treasure_matches.into_iter().next().ok_or(Error::MatchNotFound)
+    }
+    
+    async fn finished_match_scores(&self, since: DateTime<Utc>) -> Result<Vec<MatchScores>> {
+        let query = format!(r#"
+            query FinishedMatchScores($where: treasure_matches_bool_exp!) {{
+                treasure_matches(where: $where, order_by: {{end_time: asc}}) {{
+                    {}
+                }}
+            }}
+        "#, MATCH_SCORES_FIELDS);
+        
+        let variables = json!({
+            "where": {
+                "status": {"_eq": MatchStatus::Finished.to_str()},
+                "end_time": {"_gte": since}
+            }
+        });
+        
+        let response: ScoresQueryResponse = self.client.query(&query, variables).await?;
+        Ok(response.treasure_matches)
+    }
+    
+    async fn finished_predictions(&self, since: DateTime<Utc>) -> Result<Vec<MatchPrediction>> {
+        let query = r#"
+            query FinishedPredictions($since: timestamptz!, $status: String!) {
+                treasure_matches(
+                    where: {
+                        status: {_eq: $status},
+                        end_time: {_gte: $since},
+                        match_teams: {win_probability: {_is_null: false}}
+                    },
+                    order_by: {end_time: asc}
+                ) {
+                    end_time
+                    winner_team_id
+                    teams: match_teams(where: {win_probability: {_is_null: false}}) {
+                        team_id: id
+                        mmr
+                        win_probability
+                    }
+                }
+            }
+        "#;
+        
+        let variables = json!({
+            "since": since,
+            "status": MatchStatus::Finished.to_str()
+        });
+        
+        let response: PredictionsQueryResponse = self.client.query(query, variables).await?;
+        Ok(response.treasure_matches)
+    }
+    
+    async fn set_team_score(&self, team_id: Uuid, total_score: i32) -> Result<()> {
+        let mutation = r#"
+            mutation SetTeamScore($team_id: uuid!, $total_score: Int!) {
+                update_match_teams_by_pk(
+                    pk_columns: {id: $team_id},
+                    _set: {total_score: $total_score}
+                ) {
+                    id
+                }
+            }
+        "#;
+        
+        let variables = json!({
+            "team_id": team_id,
+            "total_score": total_score
+        });
+        
+        self.client.mutate::<Value>(mutation, variables).await?;
+        Ok(())
+    }
+    
+    async fn set_member_score(&self, match_id: Uuid, user_id: Uuid, individual_score: i32) -> Result<()> {
+        let mutation = r#"
+            mutation SetMemberScore($match_id: uuid!, $user_id: uuid!, $individual_score: Int!) {
+                update_match_members(
+                    where: {
+                        match_id: {_eq: $match_id},
+                        user_id: {_eq: $user_id}
+                    },
+                    _set: {individual_score: $individual_score}
+                ) {
+                    affected_rows
+                }
+            }
+        "#;
+        
+        let variables = json!({
+            "match_id": match_id,
+            "user_id": user_id,
+            "individual_score": individual_score
+        });
+        
+        self.client.mutate::<Value>(mutation, variables).await?;
+        Ok(())
+    }
+
+    async fn set_member_selection(&self, match_id: Uuid, user_id: Uuid, selection: &LobbySelection) -> Result<()> {
+        let mutation = r#"
+            mutation SetMemberSelection($match_id: uuid!, $user_id: uuid!, $loadout: String, $role: String) {
+                update_match_members(
+                    where: {
+                        match_id: {_eq: $match_id},
+                        user_id: {_eq: $user_id}
+                    },
+                    _set: {loadout: $loadout, role: $role}
+                ) {
+                    affected_rows
+                }
+            }
+        "#;
+
+        let variables = json!({
+            "match_id": match_id,
+            "user_id": user_id,
+            "loadout": selection.loadout,
+            "role": selection.role
+        });
+
+        self.client.mutate::<Value>(mutation, variables).await?;
+        Ok(())
+    }
+    
+    async fn set_winner(&self, match_id: Uuid, team_id: Uuid) -> Result<()> {
+        let mutation = r#"
+            mutation SetWinner($id: uuid!, $winner_id: uuid!) {
+                update_treasure_matches_by_pk(
+                    pk_columns: {id: $id},
+                    _set: {winner_team_id: $winner_id}
+                ) {
+                    id
+                }
+            }
+        "#;
+        
+        let variables = json!({
+            "id": match_id,
+            "winner_id": team_id
+        });
+        
+        self.client.mutate::<Value>(mutation, variables).await?;
+        Ok(())
+    }
+    
+    async fn list_match_reviews(&self, status: MatchStatus) -> Result<Vec<MatchReview>> {
+        let query = format!(r#"
+            query ListMatchReviews($status: String!) {{
+                treasure_matches(where: {{status: {{_eq: $status}}}}, order_by: {{end_time: asc}}) {{
+                    {}
+                }}
+            }}
+        "#, MATCH_REVIEW_FIELDS);
+        
+        let variables = json!({
+            "status": status.to_str()
+        });
+        
+        let response: ReviewsQueryResponse = self.client.query(&query, variables).await?;
+        Ok(response.treasure_matches)
+    }
+    
+    async fn get_match_review(&self, match_id: Uuid) -> Result<MatchReview> {
+        let query = format!(r#"
+            query GetMatchReview($id: uuid!) {{
+                treasure_matches(where: {{id: {{_eq: $id}}}}) {{
+                    {}
+                }}
+            }}
+        "#, MATCH_REVIEW_FIELDS);
+        
+        let variables = json!({
+            "id": match_id
+        });
+        
+        let response: ReviewsQueryResponse = self.client.query(&query, variables).await?;
+        response.treasure_matches.into_iter().next().ok_or(Error::MatchNotFound)
+    }
+    
+    async fn invalidate_discovery(&self, match_id: Uuid, discovery_id: Uuid) -> Result<()> {
+        // Only flips valid discoveries, so a repeated call can't subtract twice
+        let mutation = r#"
+            mutation InvalidateDiscovery($id: uuid!, $match_id: uuid!) {
+                update_match_discoveries(
+                    where: {
+                        id: {_eq: $id},
+                        match_id: {_eq: $match_id},
+                        invalidated: {_eq: false}
+                    },
+                    _set: {invalidated: true}
+                ) {
+                    returning {
+                        team_id
+                        user_id
+                        score
+                    }
+                }
+            }
+        "#;
+        
+        let variables = json!({
+            "id": discovery_id,
+            "match_id": match_id
+        });
+        
+        let response: DiscoveryInvalidateResponse = self.client.mutate(mutation, variables).await?;
+        let discovery = response.update_match_discoveries.returning
+            .into_iter()
+            .next()
+            .ok_or_else(|| Error::NotFound(format!("valid discovery {} in match {}", discovery_id, match_id)))?;
+        
+        // Take the score back off the player and the team
+        let update_scores_mutation = r#"
+            mutation RevokeDiscoveryScore($match_id: uuid!, $user_id: uuid!, $team_id: uuid!, $score: Int!) {
+                update_match_members(
+                    where: {
+                        match_id: {_eq: $match_id},
+                        user_id: {_eq: $user_id}
+                    },
+                    _inc: {individual_score: $score}
+                ) {
+                    affected_rows
+                }
+                update_match_teams_by_pk(
+                    pk_columns: {id: $team_id},
+                    _inc: {total_score: $score}
+                ) {
+                    id
+                }
+            }
+        "#;
+        
+        let update_scores_variables = json!({
+            "match_id": match_id,
+            "user_id": discovery.user_id,
+            "team_id": discovery.team_id,
+            "score": -discovery.score
+        });
+        
+        self.client.mutate::<Value>(update_scores_mutation, update_scores_variables).await?;
+        
+        Ok(())
+    }
+    
+    async fn close_match(&self, match_id: Uuid, status: MatchStatus, winner_team_id: Option<Uuid>) -> Result<()> {
+        let mutation = r#"
+            mutation CloseMatch($id: uuid!, $status: String!, $winner_id: uuid) {
+                update_treasure_matches_by_pk(
+                    pk_columns: {id: $id},
+                    _set: {
+                        status: $status,
+                        is_finished: true,
+                        winner_team_id: $winner_id
+                    }
+                ) {
+                    id
+                }
+            }
+        "#;
+        
+        let variables = json!({
+            "id": match_id,
+            "status": status.to_str(),
+            "winner_id": winner_team_id
+        });
+        
+        let response: MatchIdResponse = self.client.mutate(mutation, variables).await?;
+        
+        if response.update_treasure_matches_by_pk.is_none() {
+            return Err(Error::MatchNotFound);
+        }
+        
+        Ok(())
+    }
+    
+    async fn record_adjustment(&self, entry: &AuditEntry) -> Result<()> {
+        let mutation = r#"
+            mutation RecordAdjustment($object: match_adjustments_insert_input!) {
+                insert_match_adjustments_one(object: $object) {
+                    id
+                }
+            }
+        "#;
+        
+        let variables = json!({
+            "object": entry
+        });
+        
+        self.client.mutate::<Value>(mutation, variables).await?;
+        Ok(())
+    }
+    
+    async fn player_experience(&self, user_id: Uuid) -> Result<PlayerExperience> {
+        let query = r#"
+            query PlayerExperience($user_id: uuid!, $status: String!) {
+                users_by_pk(id: $user_id) {
+                    created_at
+                }
+                treasure_matches_aggregate(
+                    where: {
+                        status: {_eq: $status},
+                        match_members: {user_id: {_eq: $user_id}}
+                    }
+                ) {
+                    aggregate {
+                        count
+                    }
+                }
+            }
+        "#;
+        
+        let variables = json!({
+            "user_id": user_id,
+            "status": MatchStatus::Finished.to_str()
+        });
+        
+        let response: ExperienceQueryResponse = self.client.query(query, variables).await?;
+        Ok(PlayerExperience {
+            finished_matches: response.treasure_matches_aggregate.aggregate.count,
+            account_created_at: response.users_by_pk.and_then(|user| user.created_at),
+        })
+    }
+
+    async fn closed_matches_between(&self, from: DateTime<Utc>, to: DateTime<Utc>) -> Result<Vec<Uuid>> {
+        let query = r#"
+            query ClosedMatchesBetween($from: timestamptz!, $to: timestamptz!, $statuses: [String!]!) {
+                treasure_matches(
+                    where: {status: {_in: $statuses}, end_time: {_gte: $from, _lt: $to}},
+                    order_by: {end_time: asc}
+                ) {
+                    id
+                }
+            }
+        "#;
+
+        #[derive(Debug, Deserialize)]
+        struct MatchId {
+            id: Uuid,
+        }
+
+        #[derive(Debug, Deserialize)]
+        struct ClosedQueryResponse {
+            treasure_matches: Vec<MatchId>,
+        }
+
+        let variables = json!({
+            "from": from,
+            "to": to,
+            "statuses": [MatchStatus::Finished.to_str(), MatchStatus::Voided.to_str()]
+        });
+
+        let response: ClosedQueryResponse = self.client.query(query, variables).await?;
+        Ok(response.treasure_matches.into_iter().map(|row| row.id).collect())
+    }
+
+    async fn delete_matches(&self, match_ids: &[Uuid]) -> Result<i64> {
+        // Teams, members, discoveries, rounds and adjustments go with the
+        // match (ON DELETE CASCADE)
+        let mutation = r#"
+            mutation DeleteMatches($ids: [uuid!]!) {
+                delete_treasure_matches(where: {id: {_in: $ids}}) {
+                    affected_rows
+                }
+            }
+        "#;
+
+        #[derive(Debug, Deserialize)]
+        struct DeleteResponse {
+            delete_treasure_matches: AffectedRows,
+        }
+
+        let response: DeleteResponse = self.client.mutate(mutation, json!({ "ids": match_ids })).await?;
+        Ok(response.delete_treasure_matches.affected_rows)
+    }
+}
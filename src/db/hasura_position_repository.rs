@@ -0,0 +1,139 @@
+use std::sync::Arc;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::error::Result;
+use crate::heatmap::sample::{HeatmapTile, PositionSample};
+
+use super::hasura_client::HasuraClient;
+use super::repository::PositionRepository;
+
+pub struct HasuraPositionRepository {
+    client: Arc<HasuraClient>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PositionInsertResponse {
+    insert_match_positions: AffectedRows,
+}
+
+#[derive(Debug, Deserialize)]
+struct AffectedRows {
+    affected_rows: i64,
+}
+
+#[derive(Debug, Deserialize)]
+struct PositionsQueryResponse {
+    match_positions: Vec<PositionSample>,
+}
+
+#[derive(Debug, Deserialize)]
+struct HeatmapQueryResponse {
+    heatmap_tiles: Vec<HeatmapTile>,
+}
+
+impl HasuraPositionRepository {
+    pub async fn new() -> Result<Self> {
+        let client = HasuraClient::get_instance().await?;
+        Ok(Self { client })
+    }
+}
+
+#[async_trait]
+impl PositionRepository for HasuraPositionRepository {
+    // Insert a batch of samples into match_positions
+    async fn insert_positions(&self, samples: &[PositionSample]) -> Result<i64> {
+        let mutation = r#"
+            mutation InsertPositions($objects: [match_positions_insert_input!]!) {
+                insert_match_positions(objects: $objects) {
+                    affected_rows
+                }
+            }
+        "#;
+
+        let variables = json!({
+            "objects": samples
+        });
+
+        let response: PositionInsertResponse = self.client.mutate(mutation, variables).await?;
+        Ok(response.insert_match_positions.affected_rows)
+    }
+
+    async fn list_positions(&self, since: DateTime<Utc>, until: DateTime<Utc>, offset: i64, limit: i64) -> Result<Vec<PositionSample>> {
+        let query = r#"
+            query ListPositions($since: timestamptz!, $until: timestamptz!, $offset: Int!, $limit: Int!) {
+                match_positions(
+                    where: {recorded_at: {_gte: $since, _lt: $until}},
+                    order_by: [{recorded_at: asc}, {user_id: asc}],
+                    offset: $offset,
+                    limit: $limit
+                ) {
+                    match_id
+                    user_id
+                    x
+                    y
+                    recorded_at
+                }
+            }
+        "#;
+
+        let variables = json!({
+            "since": since,
+            "until": until,
+            "offset": offset,
+            "limit": limit
+        });
+
+        let response: PositionsQueryResponse = self.client.query(query, variables).await?;
+        Ok(response.match_positions)
+    }
+
+    // Both root fields run in one transaction, so readers never see a half-built heatmap
+    async fn replace_heatmap(&self, tiles: &[HeatmapTile]) -> Result<()> {
+        let mutation = r#"
+            mutation ReplaceHeatmap($objects: [heatmap_tiles_insert_input!]!) {
+                delete_heatmap_tiles(where: {}) {
+                    affected_rows
+                }
+                insert_heatmap_tiles(objects: $objects) {
+                    affected_rows
+                }
+            }
+        "#;
+
+        let variables = json!({
+            "objects": tiles
+        });
+
+        let _: Value = self.client.mutate(mutation, variables).await?;
+        Ok(())
+    }
+
+    async fn heatmap_tiles(&self, zone_id: Option<&str>) -> Result<Vec<HeatmapTile>> {
+        let query = r#"
+            query HeatmapTiles($where: heatmap_tiles_bool_exp!) {
+                heatmap_tiles(where: $where) {
+                    zone_id
+                    x
+                    y
+                    size
+                    samples
+                    computed_at
+                }
+            }
+        "#;
+
+        let filter = match zone_id {
+            Some(zone_id) => json!({"zone_id": {"_eq": zone_id}}),
+            None => json!({"zone_id": {"_is_null": true}}),
+        };
+        let variables = json!({
+            "where": filter
+        });
+
+        let response: HeatmapQueryResponse = self.client.query(query, variables).await?;
+        Ok(response.heatmap_tiles)
+    }
+}
@@ -0,0 +1,79 @@
+use std::sync::Arc;
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use crate::error::Result;
+use crate::preferences::preferences::NotificationPreferences;
+
+use super::hasura_client::HasuraClient;
+use super::repository::PreferenceRepository;
+
+const PREFERENCE_FIELDS: &str = r#"
+    user_id
+    match_found
+    friend_requests
+    announcements
+    quiet_hours
+    updated_at
+"#;
+
+pub struct HasuraPreferenceRepository {
+    client: Arc<HasuraClient>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PreferencesQueryResponse {
+    notification_preferences: Vec<NotificationPreferences>,
+}
+
+impl HasuraPreferenceRepository {
+    pub async fn new() -> Result<Self> {
+        let client = HasuraClient::get_instance().await?;
+        Ok(Self { client })
+    }
+}
+
+#[async_trait]
+impl PreferenceRepository for HasuraPreferenceRepository {
+    async fn get_preferences(&self, user_ids: &[Uuid]) -> Result<Vec<NotificationPreferences>> {
+        let query = format!(r#"
+            query NotificationPreferences($user_ids: [uuid!]!) {{
+                notification_preferences(where: {{user_id: {{_in: $user_ids}}}}) {{
+                    {}
+                }}
+            }}
+        "#, PREFERENCE_FIELDS);
+
+        let variables = json!({
+            "user_ids": user_ids
+        });
+
+        let response: PreferencesQueryResponse = self.client.query(&query, variables).await?;
+        Ok(response.notification_preferences)
+    }
+
+    async fn upsert_preferences(&self, preferences: &NotificationPreferences) -> Result<()> {
+        let mutation = r#"
+            mutation UpsertPreferences($preferences: notification_preferences_insert_input!) {
+                insert_notification_preferences_one(
+                    object: $preferences,
+                    on_conflict: {
+                        constraint: notification_preferences_pkey,
+                        update_columns: [match_found, friend_requests, announcements, quiet_hours, updated_at]
+                    }
+                ) {
+                    user_id
+                }
+            }
+        "#;
+
+        let variables = json!({
+            "preferences": preferences
+        });
+
+        let _: Value = self.client.mutate(mutation, variables).await?;
+        Ok(())
+    }
+}
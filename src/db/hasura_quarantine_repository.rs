@@ -0,0 +1,95 @@
+use std::sync::Arc;
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use crate::anticheat::quarantine::Quarantine;
+use crate::error::Result;
+
+use super::hasura_client::HasuraClient;
+use super::repository::QuarantineRepository;
+
+const QUARANTINE_FIELDS: &str = r#"
+    user_id
+    reason
+    added_by
+    created_at
+"#;
+
+pub struct HasuraQuarantineRepository {
+    client: Arc<HasuraClient>,
+}
+
+#[derive(Debug, Deserialize)]
+struct QuarantineQueryResponse {
+    quarantined_players: Vec<Quarantine>,
+}
+
+#[derive(Debug, Deserialize)]
+struct QuarantineDeleteResponse {
+    delete_quarantined_players_by_pk: Option<Value>,
+}
+
+impl HasuraQuarantineRepository {
+    pub async fn new() -> Result<Self> {
+        let client = HasuraClient::get_instance().await?;
+        Ok(Self { client })
+    }
+}
+
+#[async_trait]
+impl QuarantineRepository for HasuraQuarantineRepository {
+    async fn list_quarantined(&self) -> Result<Vec<Quarantine>> {
+        let query = format!(r#"
+            query QuarantinedPlayers {{
+                quarantined_players(order_by: {{created_at: asc}}) {{
+                    {}
+                }}
+            }}
+        "#, QUARANTINE_FIELDS);
+
+        let response: QuarantineQueryResponse = self.client.query(&query, json!({})).await?;
+        Ok(response.quarantined_players)
+    }
+
+    async fn add_quarantined(&self, quarantine: &Quarantine) -> Result<()> {
+        let mutation = r#"
+            mutation AddQuarantined($quarantine: quarantined_players_insert_input!) {
+                insert_quarantined_players_one(
+                    object: $quarantine,
+                    on_conflict: {
+                        constraint: quarantined_players_pkey,
+                        update_columns: [reason, added_by, created_at]
+                    }
+                ) {
+                    user_id
+                }
+            }
+        "#;
+
+        let variables = json!({
+            "quarantine": quarantine
+        });
+
+        let _: Value = self.client.mutate(mutation, variables).await?;
+        Ok(())
+    }
+
+    async fn remove_quarantined(&self, user_id: Uuid) -> Result<bool> {
+        let mutation = r#"
+            mutation RemoveQuarantined($user_id: uuid!) {
+                delete_quarantined_players_by_pk(user_id: $user_id) {
+                    user_id
+                }
+            }
+        "#;
+
+        let variables = json!({
+            "user_id": user_id
+        });
+
+        let response: QuarantineDeleteResponse = self.client.mutate(mutation, variables).await?;
+        Ok(response.delete_quarantined_players_by_pk.is_some())
+    }
+}
@@ -0,0 +1,153 @@
+use std::sync::Arc;
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use crate::error::{Error, Result};
+use crate::rating::mmr::PlayerRating;
+
+use super::hasura_client::HasuraClient;
+use super::repository::RatingRepository;
+
+const RATING_FIELDS: &str = r#"
+    user_id
+    mmr
+    matches_played
+    wins
+    early_wins
+    early_percentile_sum
+    smurf_suspected
+    flagged_at
+    smurf_cleared
+"#;
+
+pub struct HasuraRatingRepository {
+    client: Arc<HasuraClient>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RatingsQueryResponse {
+    player_ratings: Vec<PlayerRating>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RatingUpdateResponse {
+    update_player_ratings: AffectedRows,
+}
+
+#[derive(Debug, Deserialize)]
+struct AffectedRows {
+    affected_rows: i64,
+}
+
+impl HasuraRatingRepository {
+    pub async fn new() -> Result<Self> {
+        let client = HasuraClient::get_instance().await?;
+        Ok(Self { client })
+    }
+}
+
+#[async_trait]
+impl RatingRepository for HasuraRatingRepository {
+    async fn get_ratings(&self, user_ids: &[Uuid]) -> Result<Vec<PlayerRating>> {
+        let query = format!(r#"
+            query GetRatings($user_ids: [uuid!]!) {{
+                player_ratings(where: {{user_id: {{_in: $user_ids}}}}) {{
+                    {}
+                }}
+            }}
+        "#, RATING_FIELDS);
+
+        let variables = json!({
+            "user_ids": user_ids
+        });
+
+        let response: RatingsQueryResponse = self.client.query(&query, variables).await?;
+        Ok(response.player_ratings)
+    }
+
+    async fn upsert_ratings(&self, ratings: &[PlayerRating]) -> Result<()> {
+        let mutation = r#"
+            mutation UpsertRatings($objects: [player_ratings_insert_input!]!) {
+                insert_player_ratings(
+                    objects: $objects,
+                    on_conflict: {
+                        constraint: player_ratings_pkey,
+                        update_columns: [mmr, matches_played, wins, early_wins, early_percentile_sum, smurf_suspected, flagged_at, smurf_cleared]
+                    }
+                ) {
+                    affected_rows
+                }
+            }
+        "#;
+
+        let variables = json!({
+            "objects": ratings
+        });
+
+        let _: Value = self.client.mutate(mutation, variables).await?;
+        Ok(())
+    }
+
+    async fn flagged_ratings(&self) -> Result<Vec<PlayerRating>> {
+        let query = format!(r#"
+            query FlaggedRatings {{
+                player_ratings(
+                    where: {{smurf_suspected: {{_eq: true}}, flagged_at: {{_is_null: false}}}},
+                    order_by: {{flagged_at: desc}}
+                ) {{
+                    {}
+                }}
+            }}
+        "#, RATING_FIELDS);
+
+        let response: RatingsQueryResponse = self.client.query(&query, json!({})).await?;
+        Ok(response.player_ratings)
+    }
+
+    async fn top_ratings(&self, min_matches: i32, limit: usize) -> Result<Vec<PlayerRating>> {
+        let query = format!(r#"
+            query TopRatings($min_matches: Int!, $limit: Int!) {{
+                player_ratings(
+                    where: {{matches_played: {{_gte: $min_matches}}}},
+                    order_by: [{{mmr: desc}}, {{user_id: asc}}],
+                    limit: $limit
+                ) {{
+                    {}
+                }}
+            }}
+        "#, RATING_FIELDS);
+
+        let variables = json!({
+            "min_matches": min_matches,
+            "limit": limit
+        });
+
+        let response: RatingsQueryResponse = self.client.query(&query, variables).await?;
+        Ok(response.player_ratings)
+    }
+
+    async fn clear_smurf(&self, user_id: Uuid) -> Result<()> {
+        let mutation = r#"
+            mutation ClearSmurf($user_id: uuid!) {
+                update_player_ratings(
+                    where: {user_id: {_eq: $user_id}, smurf_suspected: {_eq: true}},
+                    _set: {smurf_suspected: false, flagged_at: null, smurf_cleared: true}
+                ) {
+                    affected_rows
+                }
+            }
+        "#;
+
+        let variables = json!({
+            "user_id": user_id
+        });
+
+        let response: RatingUpdateResponse = self.client.mutate(mutation, variables).await?;
+        if response.update_player_ratings.affected_rows == 0 {
+            return Err(Error::NotFound(format!("smurf suspicion of {}", user_id)));
+        }
+        Ok(())
+    }
+}
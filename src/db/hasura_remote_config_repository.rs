@@ -0,0 +1,140 @@
+use std::sync::Arc;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use serde_json::{json, Map, Value};
+
+use crate::error::Result;
+use crate::remote_config::document::{ClientConfig, ConfigChange, FieldChange};
+
+use super::hasura_client::HasuraClient;
+use super::keyset::KeysetQuery;
+use super::repository::RemoteConfigRepository;
+
+pub struct HasuraRemoteConfigRepository {
+    client: Arc<HasuraClient>,
+}
+
+#[derive(Debug, Deserialize)]
+struct VersionsQueryResponse {
+    client_config_versions: Vec<VersionData>,
+}
+
+#[derive(Debug, Deserialize)]
+struct HistoryQueryResponse {
+    client_config_versions: Vec<VersionData>,
+    client_config_versions_aggregate: VersionsAggregate,
+}
+
+#[derive(Debug, Deserialize)]
+struct VersionsAggregate {
+    aggregate: VersionsCount,
+}
+
+#[derive(Debug, Deserialize)]
+struct VersionsCount {
+    count: usize,
+}
+
+// One row of client_config_versions: the full document plus its audit entry
+#[derive(Debug, Deserialize)]
+struct VersionData {
+    version: u64,
+    #[serde(default)]
+    values: Map<String, Value>,
+    changed_by: String,
+    changed_at: DateTime<Utc>,
+    #[serde(default)]
+    changes: Vec<FieldChange>,
+}
+
+impl HasuraRemoteConfigRepository {
+    pub async fn new() -> Result<Self> {
+        let client = HasuraClient::get_instance().await?;
+        Ok(Self { client })
+    }
+}
+
+#[async_trait]
+impl RemoteConfigRepository for HasuraRemoteConfigRepository {
+    async fn latest_config(&self) -> Result<Option<ClientConfig>> {
+        let query = r#"
+            query LatestClientConfig {
+                client_config_versions(order_by: {version: desc}, limit: 1) {
+                    version
+                    values
+                    changed_by
+                    changed_at
+                }
+            }
+        "#;
+
+        let response: VersionsQueryResponse = self.client.query(query, json!({})).await?;
+        Ok(response.client_config_versions.into_iter().next().map(|row| ClientConfig {
+            version: row.version,
+            values: row.values,
+            updated_at: row.changed_at,
+        }))
+    }
+
+    async fn insert_config_version(&self, config: &ClientConfig, change: &ConfigChange) -> Result<()> {
+        let mutation = r#"
+            mutation InsertClientConfigVersion($version: Int!, $values: jsonb!, $changed_by: String!, $changed_at: timestamptz!, $changes: jsonb!) {
+                insert_client_config_versions_one(object: {
+                    version: $version,
+                    values: $values,
+                    changed_by: $changed_by,
+                    changed_at: $changed_at,
+                    changes: $changes
+                }) {
+                    version
+                }
+            }
+        "#;
+
+        let variables = json!({
+            "version": config.version,
+            "values": config.values,
+            "changed_by": change.changed_by,
+            "changed_at": change.changed_at,
+            "changes": change.changes
+        });
+
+        self.client.mutate::<Value>(mutation, variables).await?;
+        Ok(())
+    }
+
+    async fn config_history(&self, page: &KeysetQuery) -> Result<(Vec<ConfigChange>, usize)> {
+        let query = r#"
+            query ClientConfigHistory($where: client_config_versions_bool_exp!, $filter: client_config_versions_bool_exp!, $order_by: [client_config_versions_order_by!]!, $limit: Int!) {
+                client_config_versions(where: $where, order_by: $order_by, limit: $limit) {
+                    version
+                    changed_by
+                    changed_at
+                    changes
+                }
+                client_config_versions_aggregate(where: $filter) {
+                    aggregate {
+                        count
+                    }
+                }
+            }
+        "#;
+
+        let variables = json!({
+            "where": page.page_exp(),
+            "filter": page.filter_exp(),
+            "order_by": page.order_by(),
+            "limit": page.limit
+        });
+
+        let response: HistoryQueryResponse = self.client.query(query, variables).await?;
+        let changes = response.client_config_versions.into_iter().map(|row| ConfigChange {
+            version: row.version,
+            changed_by: row.changed_by,
+            changed_at: row.changed_at,
+            changes: row.changes,
+        }).collect();
+        Ok((changes, response.client_config_versions_aggregate.aggregate.count))
+    }
+}
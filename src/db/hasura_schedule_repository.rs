@@ -0,0 +1,138 @@
+use std::sync::Arc;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use crate::error::Result;
+use crate::schedule::scheduled::{ScheduleStatus, ScheduledMatch};
+
+use super::hasura_client::HasuraClient;
+use super::repository::ScheduleRepository;
+
+const SCHEDULE_FIELDS: &str = r#"
+    id
+    match_type
+    host
+    invited
+    starts_at
+    min_players
+    status
+    match_id
+    code
+    cancelled_reason
+    created_at
+"#;
+
+const PENDING_STATUSES: [ScheduleStatus; 3] = [ScheduleStatus::Scheduled, ScheduleStatus::Reminded, ScheduleStatus::Open];
+
+pub struct HasuraScheduleRepository {
+    client: Arc<HasuraClient>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SchedulesQueryResponse {
+    scheduled_matches: Vec<ScheduledMatch>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ScheduleByPkResponse {
+    scheduled_matches_by_pk: Option<ScheduledMatch>,
+}
+
+impl HasuraScheduleRepository {
+    pub async fn new() -> Result<Self> {
+        let client = HasuraClient::get_instance().await?;
+        Ok(Self { client })
+    }
+}
+
+#[async_trait]
+impl ScheduleRepository for HasuraScheduleRepository {
+    async fn save_schedule(&self, schedule: &ScheduledMatch) -> Result<()> {
+        let mutation = r#"
+            mutation SaveSchedule($schedule: scheduled_matches_insert_input!) {
+                insert_scheduled_matches_one(
+                    object: $schedule,
+                    on_conflict: {
+                        constraint: scheduled_matches_pkey,
+                        update_columns: [status, match_id, code, cancelled_reason]
+                    }
+                ) {
+                    id
+                }
+            }
+        "#;
+
+        let variables = json!({
+            "schedule": schedule
+        });
+
+        let _: Value = self.client.mutate(mutation, variables).await?;
+        Ok(())
+    }
+
+    async fn get_schedule(&self, id: Uuid) -> Result<Option<ScheduledMatch>> {
+        let query = format!(r#"
+            query Schedule($id: uuid!) {{
+                scheduled_matches_by_pk(id: $id) {{
+                    {}
+                }}
+            }}
+        "#, SCHEDULE_FIELDS);
+
+        let variables = json!({
+            "id": id
+        });
+
+        let response: ScheduleByPkResponse = self.client.query(&query, variables).await?;
+        Ok(response.scheduled_matches_by_pk)
+    }
+
+    async fn pending_schedules(&self, before: DateTime<Utc>) -> Result<Vec<ScheduledMatch>> {
+        let query = format!(r#"
+            query PendingSchedules($before: timestamptz!, $statuses: [String!]!) {{
+                scheduled_matches(
+                    where: {{starts_at: {{_lte: $before}}, status: {{_in: $statuses}}}},
+                    order_by: {{starts_at: asc}}
+                ) {{
+                    {}
+                }}
+            }}
+        "#, SCHEDULE_FIELDS);
+
+        let variables = json!({
+            "before": before,
+            "statuses": PENDING_STATUSES
+        });
+
+        let response: SchedulesQueryResponse = self.client.query(&query, variables).await?;
+        Ok(response.scheduled_matches)
+    }
+
+    async fn user_schedules(&self, user_id: Uuid, since: DateTime<Utc>) -> Result<Vec<ScheduledMatch>> {
+        let query = format!(r#"
+            query UserSchedules($user_id: uuid!, $invited: jsonb!, $since: timestamptz!) {{
+                scheduled_matches(
+                    where: {{
+                        starts_at: {{_gte: $since}},
+                        _or: [{{host: {{_eq: $user_id}}}}, {{invited: {{_contains: $invited}}}}]
+                    }},
+                    order_by: {{starts_at: asc}}
+                ) {{
+                    {}
+                }}
+            }}
+        "#, SCHEDULE_FIELDS);
+
+        let variables = json!({
+            "user_id": user_id,
+            "invited": [user_id],
+            "since": since
+        });
+
+        let response: SchedulesQueryResponse = self.client.query(&query, variables).await?;
+        Ok(response.scheduled_matches)
+    }
+}
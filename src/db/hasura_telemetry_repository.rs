@@ -0,0 +1,65 @@
+use std::sync::Arc;
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::error::Result;
+use crate::telemetry::event::TelemetryRecord;
+
+use super::hasura_client::HasuraClient;
+use super::repository::TelemetryRepository;
+
+pub struct HasuraTelemetryRepository {
+    client: Arc<HasuraClient>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TelemetryInsertResponse {
+    insert_client_telemetry: AffectedRows,
+}
+
+#[derive(Debug, Deserialize)]
+struct AffectedRows {
+    affected_rows: i64,
+}
+
+impl HasuraTelemetryRepository {
+    pub async fn new() -> Result<Self> {
+        let client = HasuraClient::get_instance().await?;
+        Ok(Self { client })
+    }
+}
+
+#[async_trait]
+impl TelemetryRepository for HasuraTelemetryRepository {
+    // Insert a batch of client events into client_telemetry
+    async fn insert_events(&self, records: &[TelemetryRecord]) -> Result<i64> {
+        let mutation = r#"
+            mutation InsertTelemetry($objects: [client_telemetry_insert_input!]!) {
+                insert_client_telemetry(objects: $objects) {
+                    affected_rows
+                }
+            }
+        "#;
+
+        let objects: Vec<Value> = records
+            .iter()
+            .map(|record| json!({
+                "user_id": record.user_id,
+                "source": record.source,
+                "kind": record.event.kind.to_str(),
+                "name": record.event.name,
+                "properties": record.event.properties,
+                "client_time": record.event.client_time,
+                "received_at": record.received_at,
+            }))
+            .collect();
+
+        let variables = json!({
+            "objects": objects
+        });
+
+        let response: TelemetryInsertResponse = self.client.mutate(mutation, variables).await?;
+        Ok(response.insert_client_telemetry.affected_rows)
+    }
+}
@@ -0,0 +1,161 @@
+use std::sync::Arc;
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use crate::error::{Error, Result};
+use crate::models::treasure::Treasure;
+
+use super::hasura_client::HasuraClient;
+use super::repository::TreasureRepository;
+
+pub struct HasuraTreasureRepository {
+    client: Arc<HasuraClient>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TreasuresQueryResponse {
+    treasures: Vec<Treasure>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TreasureQueryResponse {
+    treasures_by_pk: Option<Treasure>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TreasureUpdateResponse {
+    update_treasures_by_pk: Option<Value>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TreasureDeleteResponse {
+    delete_treasures_by_pk: Option<Value>,
+}
+
+impl HasuraTreasureRepository {
+    pub async fn new() -> Result<Self> {
+        let client = HasuraClient::get_instance().await?;
+        Ok(Self { client })
+    }
+}
+
+// Column values of a treasure, for inserts and updates
+fn treasure_columns(treasure: &Treasure) -> Value {
+    json!({
+        "name": treasure.name,
+        "x": treasure.x,
+        "y": treasure.y,
+        "base_score": treasure.base_score,
+        "rarity": treasure.rarity.to_str(),
+        "active_from": treasure.active_from,
+        "active_until": treasure.active_until
+    })
+}
+
+#[async_trait]
+impl TreasureRepository for HasuraTreasureRepository {
+    async fn list_treasures(&self) -> Result<Vec<Treasure>> {
+        let query = r#"
+            query ListTreasures {
+                treasures(order_by: {name: asc}) {
+                    id
+                    name
+                    x
+                    y
+                    base_score
+                    rarity
+                    active_from
+                    active_until
+                }
+            }
+        "#;
+
+        let response: TreasuresQueryResponse = self.client.query(query, json!({})).await?;
+        Ok(response.treasures)
+    }
+
+    async fn get_treasure(&self, id: Uuid) -> Result<Treasure> {
+        let query = r#"
+            query GetTreasure($id: uuid!) {
+                treasures_by_pk(id: $id) {
+                    id
+                    name
+                    x
+                    y
+                    base_score
+                    rarity
+                    active_from
+                    active_until
+                }
+            }
+        "#;
+
+        let variables = json!({
+            "id": id
+        });
+
+        let response: TreasureQueryResponse = self.client.query(query, variables).await?;
+        response.treasures_by_pk
+            .ok_or_else(|| Error::NotFound(format!("treasure {}", id)))
+    }
+
+    async fn create_treasure(&self, treasure: &Treasure) -> Result<()> {
+        let mutation = r#"
+            mutation CreateTreasure($object: treasures_insert_input!) {
+                insert_treasures_one(object: $object) {
+                    id
+                }
+            }
+        "#;
+
+        let mut object = treasure_columns(treasure);
+        object["id"] = json!(treasure.id);
+        let variables = json!({
+            "object": object
+        });
+
+        self.client.mutate::<Value>(mutation, variables).await?;
+        Ok(())
+    }
+
+    async fn update_treasure(&self, treasure: &Treasure) -> Result<()> {
+        let mutation = r#"
+            mutation UpdateTreasure($id: uuid!, $changes: treasures_set_input!) {
+                update_treasures_by_pk(pk_columns: {id: $id}, _set: $changes) {
+                    id
+                }
+            }
+        "#;
+
+        let variables = json!({
+            "id": treasure.id,
+            "changes": treasure_columns(treasure)
+        });
+
+        let response: TreasureUpdateResponse = self.client.mutate(mutation, variables).await?;
+        response.update_treasures_by_pk
+            .map(|_| ())
+            .ok_or_else(|| Error::NotFound(format!("treasure {}", treasure.id)))
+    }
+
+    async fn delete_treasure(&self, id: Uuid) -> Result<()> {
+        let mutation = r#"
+            mutation DeleteTreasure($id: uuid!) {
+                delete_treasures_by_pk(id: $id) {
+                    id
+                }
+            }
+        "#;
+
+        let variables = json!({
+            "id": id
+        });
+
+        let response: TreasureDeleteResponse = self.client.mutate(mutation, variables).await?;
+        response.delete_treasures_by_pk
+            .map(|_| ())
+            .ok_or_else(|| Error::NotFound(format!("treasure {}", id)))
+    }
+}
@@ -0,0 +1,77 @@
+use std::sync::Arc;
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use crate::error::Result;
+use crate::tutorial::tutorial::TutorialProgress;
+
+use super::hasura_client::HasuraClient;
+use super::repository::TutorialRepository;
+
+const PROGRESS_FIELDS: &str = r#"
+    user_id
+    step
+    completed_at
+    updated_at
+"#;
+
+pub struct HasuraTutorialRepository {
+    client: Arc<HasuraClient>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ProgressQueryResponse {
+    tutorial_progress_by_pk: Option<TutorialProgress>,
+}
+
+impl HasuraTutorialRepository {
+    pub async fn new() -> Result<Self> {
+        let client = HasuraClient::get_instance().await?;
+        Ok(Self { client })
+    }
+}
+
+#[async_trait]
+impl TutorialRepository for HasuraTutorialRepository {
+    async fn get_tutorial_progress(&self, user_id: Uuid) -> Result<Option<TutorialProgress>> {
+        let query = format!(r#"
+            query TutorialProgress($user_id: uuid!) {{
+                tutorial_progress_by_pk(user_id: $user_id) {{
+                    {}
+                }}
+            }}
+        "#, PROGRESS_FIELDS);
+
+        let variables = json!({
+            "user_id": user_id
+        });
+
+        let response: ProgressQueryResponse = self.client.query(&query, variables).await?;
+        Ok(response.tutorial_progress_by_pk)
+    }
+
+    async fn save_tutorial_progress(&self, progress: &TutorialProgress) -> Result<()> {
+        let mutation = r#"
+            mutation SaveTutorialProgress($progress: tutorial_progress_insert_input!) {
+                insert_tutorial_progress_one(
+                    object: $progress,
+                    on_conflict: {
+                        constraint: tutorial_progress_pkey,
+                        update_columns: [step, completed_at, updated_at]
+                    }
+                ) {
+                    user_id
+                }
+            }
+        "#;
+
+        let variables = json!({
+            "progress": progress
+        });
+
+        let _: Value = self.client.mutate(mutation, variables).await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,46 @@
+use std::sync::Arc;
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::error::Result;
+use crate::models::zone::Zone;
+
+use super::hasura_client::HasuraClient;
+use super::repository::ZoneRepository;
+
+pub struct HasuraZoneRepository {
+    client: Arc<HasuraClient>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ZonesQueryResponse {
+    // polygon is a jsonb column of [x, y] pairs
+    zones: Vec<Zone>,
+}
+
+impl HasuraZoneRepository {
+    pub async fn new() -> Result<Self> {
+        let client = HasuraClient::get_instance().await?;
+        Ok(Self { client })
+    }
+}
+
+#[async_trait]
+impl ZoneRepository for HasuraZoneRepository {
+    async fn list_zones(&self) -> Result<Vec<Zone>> {
+        let query = r#"
+            query ListZones {
+                zones(order_by: {id: asc}) {
+                    id
+                    name
+                    polygon
+                    treasure_density
+                }
+            }
+        "#;
+
+        let response: ZonesQueryResponse = self.client.query(query, json!({})).await?;
+        Ok(response.zones)
+    }
+}
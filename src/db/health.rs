@@ -0,0 +1,25 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+// Last known reachability of the database. Updated by the periodic probe in
+// MatchService and by any repository call that fails to reach Hasura.
+#[derive(Debug)]
+pub struct DbHealth {
+    available: AtomicBool,
+}
+
+impl DbHealth {
+    pub fn new() -> Self {
+        Self {
+            available: AtomicBool::new(true),
+        }
+    }
+
+    pub fn is_available(&self) -> bool {
+        self.available.load(Ordering::SeqCst)
+    }
+
+    // Returns true when the state actually changed
+    pub fn set_available(&self, available: bool) -> bool {
+        self.available.swap(available, Ordering::SeqCst) != available
+    }
+}
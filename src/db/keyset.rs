@@ -0,0 +1,328 @@
+use std::cmp::Ordering;
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use crate::error::{Error, Result};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Lte,
+    Gt,
+    Gte,
+    In,
+    Contains,
+}
+
+impl Op {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "eq" => Some(Op::Eq),
+            "ne" => Some(Op::Ne),
+            "lt" => Some(Op::Lt),
+            "lte" => Some(Op::Lte),
+            "gt" => Some(Op::Gt),
+            "gte" => Some(Op::Gte),
+            "in" => Some(Op::In),
+            "contains" => Some(Op::Contains),
+            _ => None,
+        }
+    }
+}
+
+// `field[op]=value` from a list route's query string; fields are the item's
+// JSON field names, which are also the column names
+#[derive(Debug, Clone)]
+pub struct Filter {
+    pub field: String,
+    pub op: Op,
+    pub value: String,
+}
+
+// One page of a list: the items matching `filters`, ordered by `sort` and
+// then by the key fields, starting after `after`, the sort value and key of
+// the last item already shown.
+//
+// Routes over a history that keeps growing (audit log, config versions) hand
+// it to their repository, which runs it in the database as a keyset query, so
+// every record stays reachable however old it is. Smaller lists are filtered
+// and paged in memory with `apply`.
+#[derive(Debug, Clone)]
+pub struct KeysetQuery {
+    pub filters: Vec<Filter>,
+    pub sort: String,
+    pub descending: bool,
+    pub key: Vec<String>,
+    pub after: Option<Vec<Value>>,
+    // Items to fetch, one more than the page shows so the caller can tell
+    // whether another page follows
+    pub limit: usize,
+}
+
+impl KeysetQuery {
+    // Sort value followed by the key fields
+    pub fn position(&self, item: &Value) -> Vec<Value> {
+        std::iter::once(self.sort.as_str())
+            .chain(self.key.iter().map(String::as_str))
+            .map(|name| field(item, name).clone())
+            .collect()
+    }
+
+    // Key fields always ascending, after the sort value in either direction
+    pub fn order(&self, a: &[Value], b: &[Value]) -> Ordering {
+        let ordering = compare(&a[0], &b[0]);
+        let ordering = if self.descending { ordering.reverse() } else { ordering };
+        ordering.then_with(|| compare_all(&a[1..], &b[1..]))
+    }
+
+    // The page out of `items`, in memory, and how many items match the filters
+    pub fn apply<T: Serialize>(&self, items: Vec<T>) -> Result<(Vec<T>, usize)> {
+        let mut rows = Vec::with_capacity(items.len());
+        for item in items {
+            let value = serde_json::to_value(&item).map_err(|_| Error::InvalidMessage)?;
+            if self.filters.iter().all(|filter| matches(&value, filter)) {
+                rows.push((self.position(&value), item));
+            }
+        }
+        rows.sort_by(|(a, _), (b, _)| self.order(a, b));
+        let total = rows.len();
+
+        let start = match &self.after {
+            Some(after) => rows.partition_point(|(position, _)| self.order(position, after) != Ordering::Greater),
+            None => 0,
+        };
+        let page = rows.into_iter().skip(start).take(self.limit).map(|(_, item)| item).collect();
+        Ok((page, total))
+    }
+
+    // Hasura bool_exp of the filters alone, for counting the matches
+    pub fn filter_exp(&self) -> Value {
+        json!({ "_and": self.filters.iter().map(filter_exp).collect::<Vec<_>>() })
+    }
+
+    // ...and of the filters and the cursor, for fetching the page
+    pub fn page_exp(&self) -> Value {
+        let Some(after) = &self.after else {
+            return self.filter_exp();
+        };
+        // Past the cursor on the sort field, or tied on it and past it on the
+        // key fields in turn
+        let fields: Vec<(&str, bool)> = std::iter::once((self.sort.as_str(), self.descending))
+            .chain(self.key.iter().map(|key| (key.as_str(), false)))
+            .collect();
+        let branches: Vec<Value> = (0..fields.len().min(after.len()))
+            .filter_map(|i| {
+                let (name, descending) = fields[i];
+                let mut all = vec![beyond(name, &after[i], descending)?];
+                for (j, (name, _)) in fields[..i].iter().enumerate() {
+                    all.push(nested(name, equal_exp(&after[j])));
+                }
+                Some(json!({ "_and": all }))
+            })
+            .collect();
+        json!({ "_and": [self.filter_exp(), { "_or": branches }] })
+    }
+
+    // Hasura order_by matching `order`
+    pub fn order_by(&self) -> Value {
+        let direction = if self.descending { "desc" } else { "asc" };
+        let mut order_by = vec![nested(&self.sort, json!(direction))];
+        order_by.extend(self.key.iter().map(|key| nested(key, json!("asc"))));
+        Value::Array(order_by)
+    }
+}
+
+fn field<'a>(item: &'a Value, name: &str) -> &'a Value {
+    name.split('.').try_fold(item, |value, part| value.get(part)).unwrap_or(&Value::Null)
+}
+
+// A condition on a field, nested for dotted names: `a.b` is `{a: {b: cond}}`
+fn nested(name: &str, condition: Value) -> Value {
+    name.rsplit('.').fold(condition, |condition, part| json!({ part: condition }))
+}
+
+// The column value a query string value stands for
+fn literal(raw: &str) -> Value {
+    if let Ok(n) = raw.parse::<i64>() {
+        return json!(n);
+    }
+    if let Ok(n) = raw.parse::<f64>() {
+        return json!(n);
+    }
+    match raw.parse::<bool>() {
+        Ok(b) => json!(b),
+        Err(_) => json!(raw),
+    }
+}
+
+fn equal_exp(value: &Value) -> Value {
+    match value {
+        Value::Null => json!({ "_is_null": true }),
+        value => json!({ "_eq": value }),
+    }
+}
+
+// Strictly past `value` in the field's direction; null comes first, so
+// nothing is past it descending
+fn beyond(name: &str, value: &Value, descending: bool) -> Option<Value> {
+    let condition = match (value, descending) {
+        (Value::Null, true) => return None,
+        (Value::Null, false) => json!({ "_is_null": false }),
+        (value, true) => json!({ "_lt": value }),
+        (value, false) => json!({ "_gt": value }),
+    };
+    Some(nested(name, condition))
+}
+
+fn filter_exp(filter: &Filter) -> Value {
+    let raw = filter.value.as_str();
+    let condition = match filter.op {
+        Op::Eq if raw == "null" => json!({ "_is_null": true }),
+        Op::Ne if raw == "null" => json!({ "_is_null": false }),
+        Op::Eq => json!({ "_eq": literal(raw) }),
+        Op::Ne => json!({ "_neq": literal(raw) }),
+        Op::Lt => json!({ "_lt": literal(raw) }),
+        Op::Lte => json!({ "_lte": literal(raw) }),
+        Op::Gt => json!({ "_gt": literal(raw) }),
+        Op::Gte => json!({ "_gte": literal(raw) }),
+        Op::In => json!({ "_in": raw.split(',').map(|v| literal(v.trim())).collect::<Vec<_>>() }),
+        Op::Contains => {
+            let escaped = raw.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_");
+            json!({ "_ilike": format!("%{}%", escaped) })
+        }
+    };
+    nested(&filter.field, condition)
+}
+
+// RFC 3339 timestamps as instants, so differing offsets and fractional
+// digits still order correctly
+fn timestamp(s: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(s).ok().map(|t| t.with_timezone(&Utc))
+}
+
+// null < booleans < numbers < strings < anything else. Strings that are both
+// timestamps compare as instants, other strings as text.
+pub fn compare(a: &Value, b: &Value) -> Ordering {
+    fn rank(value: &Value) -> u8 {
+        match value {
+            Value::Null => 0,
+            Value::Bool(_) => 1,
+            Value::Number(_) => 2,
+            Value::String(_) => 3,
+            _ => 4,
+        }
+    }
+    match (a, b) {
+        (Value::Bool(a), Value::Bool(b)) => a.cmp(b),
+        (Value::Number(a), Value::Number(b)) => {
+            a.as_f64().unwrap_or(0.0).total_cmp(&b.as_f64().unwrap_or(0.0))
+        }
+        (Value::String(a), Value::String(b)) => match (timestamp(a), timestamp(b)) {
+            (Some(a), Some(b)) => a.cmp(&b),
+            _ => a.cmp(b),
+        },
+        _ if rank(a) != rank(b) => rank(a).cmp(&rank(b)),
+        _ => a.to_string().cmp(&b.to_string()),
+    }
+}
+
+fn compare_all(a: &[Value], b: &[Value]) -> Ordering {
+    a.iter().zip(b).map(|(a, b)| compare(a, b)).find(|o| o.is_ne()).unwrap_or(Ordering::Equal)
+}
+
+fn matches(item: &Value, filter: &Filter) -> bool {
+    let value = field(item, &filter.field);
+    match filter.op {
+        Op::In => filter.value.split(',').any(|candidate| equals(value, candidate.trim())),
+        Op::Eq => equals(value, &filter.value),
+        Op::Ne => !equals(value, &filter.value),
+        Op::Contains => match value {
+            Value::String(s) => s.to_lowercase().contains(&filter.value.to_lowercase()),
+            Value::Array(items) => items.iter().any(|item| equals(item, &filter.value)),
+            _ => false,
+        },
+        Op::Lt | Op::Lte | Op::Gt | Op::Gte => {
+            let Some(ordering) = order_against(value, &filter.value) else {
+                return false;
+            };
+            match filter.op {
+                Op::Lt => ordering.is_lt(),
+                Op::Lte => ordering.is_le(),
+                Op::Gt => ordering.is_gt(),
+                _ => ordering.is_ge(),
+            }
+        }
+    }
+}
+
+fn equals(value: &Value, raw: &str) -> bool {
+    match value {
+        Value::Null => raw == "null",
+        Value::Bool(b) => raw.parse::<bool>().is_ok_and(|raw| raw == *b),
+        Value::Number(n) => raw.parse::<f64>().is_ok_and(|raw| n.as_f64() == Some(raw)),
+        Value::String(s) => s == raw,
+        _ => false,
+    }
+}
+
+// How the item's value compares with the filter's; None when they can't be
+// compared, which fails the filter
+fn order_against(value: &Value, raw: &str) -> Option<Ordering> {
+    match value {
+        Value::Number(n) => n.as_f64()?.partial_cmp(&raw.parse::<f64>().ok()?),
+        Value::String(s) => match (timestamp(s), timestamp(raw)) {
+            (Some(s), Some(raw)) => Some(s.cmp(&raw)),
+            _ => Some(s.as_str().cmp(raw)),
+        },
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn query(after: Option<Vec<Value>>) -> KeysetQuery {
+        KeysetQuery {
+            filters: vec![Filter { field: "status".to_string(), op: Op::Gte, value: "400".to_string() }],
+            sort: "created_at".to_string(),
+            descending: true,
+            key: vec!["id".to_string()],
+            after,
+            limit: 11,
+        }
+    }
+
+    #[test]
+    fn timestamps_compare_as_instants() {
+        let a = json!("2026-03-01T10:00:00.5Z");
+        let b = json!("2026-03-01T10:00:00.25Z");
+        assert_eq!(compare(&a, &b), Ordering::Greater);
+        // Same instant in another offset
+        let c = json!("2026-03-01T11:00:00.5+01:00");
+        assert_eq!(compare(&a, &c), Ordering::Equal);
+        assert_eq!(compare(&json!("b"), &json!("a")), Ordering::Greater);
+    }
+
+    #[test]
+    fn cursor_becomes_a_keyset_condition() {
+        let query = query(Some(vec![json!("2026-03-01T10:00:00Z"), json!("k")]));
+        assert_eq!(query.page_exp(), json!({ "_and": [
+            { "_and": [{ "status": { "_gte": 400 } }] },
+            { "_or": [
+                { "_and": [{ "created_at": { "_lt": "2026-03-01T10:00:00Z" } }] },
+                { "_and": [{ "id": { "_gt": "k" } }, { "created_at": { "_eq": "2026-03-01T10:00:00Z" } }] },
+            ] },
+        ] }));
+        assert_eq!(query.order_by(), json!([{ "created_at": "desc" }, { "id": "asc" }]));
+    }
+
+    #[test]
+    fn first_page_only_filters() {
+        assert_eq!(query(None).page_exp(), json!({ "_and": [{ "status": { "_gte": 400 } }] }));
+    }
+}
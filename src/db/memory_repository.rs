@@ -0,0 +1,1361 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use rand::Rng;
+use uuid::Uuid;
+
+use crate::announcements::announcement::{Announcement, Motd};
+use crate::anticheat::appeal::{Appeal, AppealStatus};
+use crate::anticheat::quarantine::Quarantine;
+use crate::apikeys::key::ApiKey;
+use crate::audit::record::AuditRecord;
+use crate::auth::identity::{AccountMerge, IdentityProvider, LinkedIdentity};
+use crate::chaos;
+use crate::devices::device::Device;
+use crate::error::{Error, Result};
+use crate::experiments::experiment::Experiment;
+use crate::game::victory::VictoryCondition;
+use crate::heatmap::sample::{HeatmapTile, PositionSample};
+use crate::inbox::message::InboxMessage;
+use crate::jobs::job::{Job, JobStatus};
+use crate::matchmaking::fairness::{FairnessReport, QueueSample};
+use crate::matchmaking::review::{AuditEntry, MatchReview};
+use crate::matchmaking::verify::Anomaly;
+use crate::models::game::{
+    DiscoveryScore, LobbySelection, MatchDetails, MatchMember, MatchRoom, MatchScores, MatchStatus, MatchTeam, MemberDetails,
+    MemberScore, Platform, PlayerExperience, RoundResult, TeamDetails, TeamScore, TeamStrength,
+};
+use crate::models::treasure::Treasure;
+use crate::models::zone::Zone;
+use crate::moderation::ban::Ban;
+use crate::preferences::preferences::NotificationPreferences;
+use crate::rating::calibration::{MatchPrediction, TeamPrediction};
+use crate::rating::mmr::PlayerRating;
+use crate::remote_config::document::{ClientConfig, ConfigChange};
+use crate::schedule::scheduled::ScheduledMatch;
+use crate::telemetry::event::TelemetryRecord;
+use crate::tutorial::tutorial::TutorialProgress;
+use super::keyset::KeysetQuery;
+use super::repository::{
+    AnnouncementRepository, ApiKeyRepository, AppealRepository, AuditRepository, BanRepository, DeviceRepository, ExperimentRepository,
+    FairnessRepository, IdentityRepository, InboxRepository, JobRepository, MatchRepository, PositionRepository, PreferenceRepository,
+    QuarantineRepository, RatingRepository, RemoteConfigRepository, ScheduleRepository, TelemetryRepository, TreasureRepository, TutorialRepository,
+    ZoneRepository,
+};
+
+// Every repository kept in process memory, for `--local` runs without Hasura.
+//
+// Behaves like the Hasura repositories, errors included, and waits a random
+// delay in the configured range on every call to mimic the round trip to the
+// database. CHAOS_* fault injection applies here too. Nothing is persisted.
+pub struct MemoryRepository {
+    latency: (Duration, Duration),
+    store: Mutex<Store>,
+}
+
+#[derive(Default)]
+struct Store {
+    matches: HashMap<Uuid, StoredMatch>,
+    treasures: HashMap<Uuid, Treasure>,
+    positions: Vec<PositionSample>,
+    heatmap: Vec<HeatmapTile>,
+    queue_samples: Vec<QueueSample>,
+    fairness_reports: Vec<FairnessReport>,
+    bans: Vec<Ban>,
+    quarantined: HashMap<Uuid, Quarantine>,
+    appeals: Vec<Appeal>,
+    ratings: HashMap<Uuid, PlayerRating>,
+    inbox: Vec<InboxMessage>,
+    devices: Vec<Device>,
+    identities: Vec<LinkedIdentity>,
+    api_keys: Vec<ApiKey>,
+    audit: Vec<AuditRecord>,
+    motd: Option<Motd>,
+    announcements: Vec<Announcement>,
+    experiments: BTreeMap<String, Experiment>,
+    config_versions: Vec<(ClientConfig, ConfigChange)>,
+    schedules: HashMap<Uuid, ScheduledMatch>,
+    preferences: HashMap<Uuid, NotificationPreferences>,
+    tutorials: HashMap<Uuid, TutorialProgress>,
+    jobs: HashMap<Uuid, Job>,
+}
+
+struct StoredMatch {
+    match_type: String,
+    status: MatchStatus,
+    required_players_per_team: i32,
+    start_time: Option<DateTime<Utc>>,
+    end_time: Option<DateTime<Utc>>,
+    winner_team_id: Option<Uuid>,
+    victory_condition: VictoryCondition,
+    review_notes: Option<Vec<Anomaly>>,
+    teams: Vec<StoredTeam>,
+    members: Vec<MemberScore>,
+    platforms: HashMap<Uuid, Platform>,
+    selections: HashMap<Uuid, LobbySelection>,
+    discoveries: Vec<DiscoveryScore>,
+    rounds: Vec<RoundResult>,
+    adjustments: Vec<AuditEntry>,
+}
+
+struct StoredTeam {
+    id: Uuid,
+    team_number: i32,
+    total_score: i32,
+    max_players: i32,
+    strength: Option<TeamStrength>,
+    objective_score: i32,
+}
+
+impl StoredMatch {
+    fn scores(&self, match_id: Uuid) -> MatchScores {
+        MatchScores {
+            match_id,
+            victory_condition: Some(self.victory_condition),
+            status: self.status,
+            start_time: self.start_time,
+            winner_team_id: self.winner_team_id,
+            teams: self.team_scores(),
+            members: self.members.clone(),
+            discoveries: self.discoveries.clone(),
+            rounds: self.rounds.clone(),
+        }
+    }
+
+    fn review(&self, match_id: Uuid) -> MatchReview {
+        MatchReview {
+            match_id,
+            match_type: self.match_type.clone(),
+            status: self.status,
+            start_time: self.start_time,
+            end_time: self.end_time,
+            winner_team_id: self.winner_team_id,
+            review_notes: self.review_notes.clone(),
+            teams: self.team_scores(),
+            members: self.members.clone(),
+            discoveries: self.discoveries.clone(),
+            adjustments: self.adjustments.clone(),
+        }
+    }
+
+    // Ordered by team number, as the Hasura queries ask for
+    fn team_scores(&self) -> Vec<TeamScore> {
+        let mut teams: Vec<&StoredTeam> = self.teams.iter().collect();
+        teams.sort_by_key(|team| team.team_number);
+        teams
+            .into_iter()
+            .map(|team| TeamScore { team_id: team.id, total_score: team.total_score, objective_score: team.objective_score })
+            .collect()
+    }
+
+    fn team_members(&self, team_id: Uuid) -> impl Iterator<Item = &MemberScore> {
+        self.members.iter().filter(move |member| member.team_id == team_id)
+    }
+}
+
+impl MemoryRepository {
+    pub fn new(latency_min: Duration, latency_max: Duration) -> Self {
+        Self {
+            latency: (latency_min, latency_max.max(latency_min)),
+            store: Mutex::new(Store::default()),
+        }
+    }
+
+    // Simulated database round trip
+    async fn round_trip(&self) -> Result<()> {
+        chaos::before_db_call().await?;
+        let (min, max) = self.latency;
+        if !max.is_zero() {
+            let delay = rand::thread_rng().gen_range(min..=max);
+            tokio::time::sleep(delay).await;
+        }
+        Ok(())
+    }
+
+    fn store(&self) -> std::sync::MutexGuard<'_, Store> {
+        self.store.lock().unwrap()
+    }
+}
+
+#[async_trait]
+impl MatchRepository for MemoryRepository {
+    async fn ping(&self) -> Result<()> {
+        self.round_trip().await
+    }
+
+    async fn create_match(&self, match_id: Uuid, match_type: &str, required_players_per_team: i32, victory: VictoryCondition) -> Result<Uuid> {
+        self.round_trip().await?;
+        let mut store = self.store();
+        if store.matches.contains_key(&match_id) {
+            return Err(Error::DuplicateKey(format!("treasure_matches {}", match_id)));
+        }
+        store.matches.insert(match_id, StoredMatch {
+            match_type: match_type.to_string(),
+            status: MatchStatus::Matching,
+            required_players_per_team,
+            start_time: None,
+            end_time: None,
+            winner_team_id: None,
+            victory_condition: victory,
+            review_notes: None,
+            teams: Vec::new(),
+            members: Vec::new(),
+            platforms: HashMap::new(),
+            selections: HashMap::new(),
+            discoveries: Vec::new(),
+            rounds: Vec::new(),
+            adjustments: Vec::new(),
+        });
+        Ok(match_id)
+    }
+
+    async fn create_team(&self, team_id: Uuid, match_id: Uuid, team_number: i32, max_players: i32, strength: Option<TeamStrength>) -> Result<Uuid> {
+        self.round_trip().await?;
+        let mut store = self.store();
+        if store.matches.values().any(|m| m.teams.iter().any(|team| team.id == team_id)) {
+            return Err(Error::DuplicateKey(format!("match_teams {}", team_id)));
+        }
+        let stored = store.matches
+            .get_mut(&match_id)
+            .ok_or_else(|| Error::ForeignKeyViolation(format!("treasure_matches {}", match_id)))?;
+        stored.teams.push(StoredTeam { id: team_id, team_number, total_score: 0, max_players, strength, objective_score: 0 });
+        Ok(team_id)
+    }
+
+    async fn add_player_to_team(&self, match_id: Uuid, team_id: Uuid, user_id: Uuid, platform: Option<Platform>) -> Result<Uuid> {
+        self.round_trip().await?;
+        let mut store = self.store();
+        let stored = store.matches.get_mut(&match_id).ok_or(Error::MatchNotFound)?;
+        // Like the capacity check on match_teams: full past the team's own max_players
+        let max_players = stored.teams
+            .iter()
+            .find(|team| team.id == team_id)
+            .ok_or(Error::MatchNotFound)?
+            .max_players;
+        if stored.team_members(team_id).count() as i32 >= max_players {
+            return Err(Error::TeamFull);
+        }
+        if stored.members.iter().any(|member| member.user_id == user_id) {
+            return Err(Error::UserAlreadyInMatch);
+        }
+        stored.members.push(MemberScore { user_id, team_id, individual_score: 0 });
+        if let Some(platform) = platform {
+            stored.platforms.insert(user_id, platform);
+        }
+        Ok(Uuid::new_v4())
+    }
+
+    async fn start_match(&self, match_id: Uuid) -> Result<()> {
+        self.round_trip().await?;
+        let mut store = self.store();
+        let stored = store.matches.get_mut(&match_id).ok_or(Error::MatchNotFound)?;
+        stored.status = MatchStatus::Playing;
+        stored.start_time = Some(Utc::now());
+        Ok(())
+    }
+
+    async fn record_discovery(&self, match_id: Uuid, team_id: Uuid, user_id: Uuid, treasure_id: Uuid, score: i32) -> Result<Uuid> {
+        self.round_trip().await?;
+        let mut store = self.store();
+        let stored = store.matches
+            .get_mut(&match_id)
+            .ok_or_else(|| Error::ForeignKeyViolation(format!("treasure_matches {}", match_id)))?;
+        let id = Uuid::new_v4();
+        stored.discoveries.push(DiscoveryScore {
+            id,
+            team_id,
+            user_id,
+            treasure_id,
+            score,
+            discovered_at: Utc::now(),
+            invalidated: false,
+        });
+        if let Some(member) = stored.members.iter_mut().find(|member| member.user_id == user_id) {
+            member.individual_score += score;
+        }
+        if let Some(team) = stored.teams.iter_mut().find(|team| team.id == team_id) {
+            team.total_score += score;
+        }
+        Ok(id)
+    }
+
+    async fn end_match(&self, match_id: Uuid, winner_team_id: Option<Uuid>) -> Result<()> {
+        self.round_trip().await?;
+        let mut store = self.store();
+        let stored = store.matches.get_mut(&match_id).ok_or(Error::MatchNotFound)?;
+        stored.status = MatchStatus::Finished;
+        stored.end_time = Some(Utc::now());
+        stored.winner_team_id = winner_team_id;
+        Ok(())
+    }
+
+    async fn flag_for_review(&self, match_id: Uuid, anomalies: &[Anomaly]) -> Result<()> {
+        self.round_trip().await?;
+        let mut store = self.store();
+        let stored = store.matches.get_mut(&match_id).ok_or(Error::MatchNotFound)?;
+        stored.status = MatchStatus::UnderReview;
+        stored.end_time = Some(Utc::now());
+        stored.review_notes = Some(anomalies.to_vec());
+        Ok(())
+    }
+
+    async fn get_match(&self, match_id: Uuid) -> Result<MatchRoom> {
+        self.round_trip().await?;
+        let store = self.store();
+        let stored = store.matches.get(&match_id).ok_or(Error::MatchNotFound)?;
+        let players: Vec<Uuid> = stored.members.iter().map(|member| member.user_id).collect();
+        Ok(MatchRoom {
+            id: match_id,
+            required_players: stored.required_players_per_team * 2,
+            current_players: players.len() as i32,
+            players,
+            status: stored.status,
+            parties: Vec::new(),
+            platforms: stored.platforms.clone(),
+            seats: HashMap::new(),
+            teams: Vec::new(),
+            selections: HashMap::new(),
+            lobby_closes_at: None,
+            private: None,
+        })
+    }
+
+    async fn get_match_teams(&self, match_id: Uuid) -> Result<Vec<MatchTeam>> {
+        self.round_trip().await?;
+        let store = self.store();
+        let Some(stored) = store.matches.get(&match_id) else {
+            return Ok(Vec::new());
+        };
+        let mut teams: Vec<MatchTeam> = stored.teams
+            .iter()
+            .map(|team| MatchTeam {
+                id: team.id,
+                team_number: team.team_number,
+                members: stored.team_members(team.id)
+                    .map(|member| MatchMember { user_id: member.user_id })
+                    .collect(),
+            })
+            .collect();
+        teams.sort_by_key(|team| team.team_number);
+        Ok(teams)
+    }
+
+    async fn get_match_details(&self, match_id: Uuid) -> Result<MatchDetails> {
+        self.round_trip().await?;
+        let store = self.store();
+        let stored = store.matches.get(&match_id).ok_or(Error::MatchNotFound)?;
+        let duration = stored.start_time.map(|start| {
+            let end = stored.end_time.unwrap_or_else(Utc::now);
+            Duration::from_secs((end - start).num_seconds().max(0) as u64)
+        });
+        let mut teams: Vec<TeamDetails> = stored.teams
+            .iter()
+            .map(|team| TeamDetails {
+                id: team.id,
+                team_number: team.team_number,
+                // No users table locally; players go by the start of their id
+                members: stored.team_members(team.id)
+                    .map(|member| MemberDetails {
+                        user_id: member.user_id,
+                        nickname: member.user_id.to_string()[..8].to_string(),
+                        avatar_url: String::new(),
+                        score: member.individual_score,
+                        platform: stored.platforms.get(&member.user_id).copied(),
+                        loadout: stored.selections.get(&member.user_id).and_then(|s| s.loadout.clone()),
+                        role: stored.selections.get(&member.user_id).and_then(|s| s.role.clone()),
+                    })
+                    .collect(),
+                total_score: team.total_score,
+                win_probability: team.strength.map(|strength| strength.win_probability),
+                objective_score: team.objective_score,
+            })
+            .collect();
+        teams.sort_by_key(|team| team.team_number);
+        Ok(MatchDetails {
+            id: match_id,
+            match_type: stored.match_type.clone(),
+            status: stored.status,
+            start_time: stored.start_time,
+            platforms: teams.iter().flat_map(|team| team.members.iter().map(|m| m.platform)).collect(),
+            teams,
+            duration,
+            victory_condition: stored.victory_condition,
+            rounds: stored.rounds.clone(),
+        })
+    }
+
+    async fn is_user_in_match(&self, user_id: Uuid) -> Result<Option<Uuid>> {
+        self.round_trip().await?;
+        let store = self.store();
+        Ok(store.matches
+            .iter()
+            .find(|(_, stored)| {
+                matches!(stored.status, MatchStatus::Matching | MatchStatus::Ready | MatchStatus::Playing)
+                    && stored.members.iter().any(|member| member.user_id == user_id)
+            })
+            .map(|(id, _)| *id))
+    }
+
+    // Statuses are typed here, so there is never anything to rewrite
+    async fn migrate_legacy_statuses(&self) -> Result<i64> {
+        Ok(0)
+    }
+
+    async fn get_match_scores(&self, match_id: Uuid) -> Result<MatchScores> {
+        self.round_trip().await?;
+        let store = self.store();
+        let stored = store.matches.get(&match_id).ok_or(Error::MatchNotFound)?;
+        Ok(stored.scores(match_id))
+    }
+
+    async fn finished_match_scores(&self, since: DateTime<Utc>) -> Result<Vec<MatchScores>> {
+        self.round_trip().await?;
+        let store = self.store();
+        let mut finished: Vec<(&Uuid, &StoredMatch)> = store.matches
+            .iter()
+            .filter(|(_, stored)| stored.status == MatchStatus::Finished && stored.end_time.is_some_and(|end| end >= since))
+            .collect();
+        finished.sort_by_key(|(_, stored)| stored.end_time);
+        Ok(finished.into_iter().map(|(id, stored)| stored.scores(*id)).collect())
+    }
+
+    async fn finished_predictions(&self, since: DateTime<Utc>) -> Result<Vec<MatchPrediction>> {
+        self.round_trip().await?;
+        let store = self.store();
+        let mut predictions: Vec<MatchPrediction> = store.matches
+            .values()
+            .filter(|stored| stored.status == MatchStatus::Finished)
+            .filter_map(|stored| {
+                let end_time = stored.end_time.filter(|end| *end >= since)?;
+                let teams: Vec<TeamPrediction> = stored.teams
+                    .iter()
+                    .filter_map(|team| {
+                        let strength = team.strength?;
+                        Some(TeamPrediction { team_id: team.id, mmr: strength.mmr, win_probability: strength.win_probability })
+                    })
+                    .collect();
+                (!teams.is_empty()).then_some(MatchPrediction {
+                    end_time,
+                    winner_team_id: stored.winner_team_id,
+                    teams,
+                })
+            })
+            .collect();
+        predictions.sort_by_key(|prediction| prediction.end_time);
+        Ok(predictions)
+    }
+
+    async fn add_objective_points(&self, team_id: Uuid, points: i32) -> Result<()> {
+        self.round_trip().await?;
+        let mut store = self.store();
+        let team = store.matches
+            .values_mut()
+            .flat_map(|m| m.teams.iter_mut())
+            .find(|team| team.id == team_id)
+            .ok_or_else(|| Error::NotFound(format!("team {}", team_id)))?;
+        team.objective_score += points;
+        Ok(())
+    }
+
+    async fn record_round(&self, round: &RoundResult) -> Result<()> {
+        self.round_trip().await?;
+        let mut store = self.store();
+        let stored = store.matches.get_mut(&round.match_id).ok_or(Error::MatchNotFound)?;
+        if stored.rounds.iter().any(|r| r.round_number == round.round_number) {
+            return Err(Error::DuplicateKey(format!("match_rounds {} #{}", round.match_id, round.round_number)));
+        }
+        stored.rounds.push(round.clone());
+        Ok(())
+    }
+
+    async fn set_team_score(&self, team_id: Uuid, total_score: i32) -> Result<()> {
+        self.round_trip().await?;
+        let mut store = self.store();
+        if let Some(team) = store.matches.values_mut().flat_map(|m| m.teams.iter_mut()).find(|team| team.id == team_id) {
+            team.total_score = total_score;
+        }
+        Ok(())
+    }
+
+    async fn set_member_score(&self, match_id: Uuid, user_id: Uuid, individual_score: i32) -> Result<()> {
+        self.round_trip().await?;
+        let mut store = self.store();
+        if let Some(member) = store.matches
+            .get_mut(&match_id)
+            .and_then(|stored| stored.members.iter_mut().find(|member| member.user_id == user_id))
+        {
+            member.individual_score = individual_score;
+        }
+        Ok(())
+    }
+
+    async fn set_member_selection(&self, match_id: Uuid, user_id: Uuid, selection: &LobbySelection) -> Result<()> {
+        self.round_trip().await?;
+        let mut store = self.store();
+        if let Some(stored) = store.matches
+            .get_mut(&match_id)
+            .filter(|stored| stored.members.iter().any(|member| member.user_id == user_id))
+        {
+            stored.selections.insert(user_id, selection.clone());
+        }
+        Ok(())
+    }
+
+    async fn set_winner(&self, match_id: Uuid, team_id: Uuid) -> Result<()> {
+        self.round_trip().await?;
+        let mut store = self.store();
+        if let Some(stored) = store.matches.get_mut(&match_id) {
+            stored.winner_team_id = Some(team_id);
+        }
+        Ok(())
+    }
+
+    async fn list_match_reviews(&self, status: MatchStatus) -> Result<Vec<MatchReview>> {
+        self.round_trip().await?;
+        let store = self.store();
+        let mut reviews: Vec<MatchReview> = store.matches
+            .iter()
+            .filter(|(_, stored)| stored.status == status)
+            .map(|(id, stored)| stored.review(*id))
+            .collect();
+        reviews.sort_by_key(|review| review.end_time);
+        Ok(reviews)
+    }
+
+    async fn get_match_review(&self, match_id: Uuid) -> Result<MatchReview> {
+        self.round_trip().await?;
+        let store = self.store();
+        let stored = store.matches.get(&match_id).ok_or(Error::MatchNotFound)?;
+        Ok(stored.review(match_id))
+    }
+
+    async fn invalidate_discovery(&self, match_id: Uuid, discovery_id: Uuid) -> Result<()> {
+        self.round_trip().await?;
+        let mut store = self.store();
+        let not_found = || Error::NotFound(format!("valid discovery {} in match {}", discovery_id, match_id));
+        let stored = store.matches.get_mut(&match_id).ok_or_else(not_found)?;
+        let discovery = stored.discoveries
+            .iter_mut()
+            .find(|discovery| discovery.id == discovery_id && !discovery.invalidated)
+            .ok_or_else(not_found)?;
+        discovery.invalidated = true;
+        let (team_id, user_id, score) = (discovery.team_id, discovery.user_id, discovery.score);
+        if let Some(member) = stored.members.iter_mut().find(|member| member.user_id == user_id) {
+            member.individual_score -= score;
+        }
+        if let Some(team) = stored.teams.iter_mut().find(|team| team.id == team_id) {
+            team.total_score -= score;
+        }
+        Ok(())
+    }
+
+    async fn close_match(&self, match_id: Uuid, status: MatchStatus, winner_team_id: Option<Uuid>) -> Result<()> {
+        self.round_trip().await?;
+        let mut store = self.store();
+        let stored = store.matches.get_mut(&match_id).ok_or(Error::MatchNotFound)?;
+        stored.status = status;
+        stored.winner_team_id = winner_team_id;
+        Ok(())
+    }
+
+    async fn record_adjustment(&self, entry: &AuditEntry) -> Result<()> {
+        self.round_trip().await?;
+        let mut store = self.store();
+        let stored = store.matches
+            .get_mut(&entry.match_id)
+            .ok_or_else(|| Error::ForeignKeyViolation(format!("treasure_matches {}", entry.match_id)))?;
+        stored.adjustments.push(entry.clone());
+        Ok(())
+    }
+
+    async fn player_experience(&self, user_id: Uuid) -> Result<PlayerExperience> {
+        self.round_trip().await?;
+        let store = self.store();
+        let finished_matches = store.matches
+            .values()
+            .filter(|stored| {
+                stored.status == MatchStatus::Finished && stored.members.iter().any(|member| member.user_id == user_id)
+            })
+            .count() as i64;
+        Ok(PlayerExperience { finished_matches, account_created_at: None })
+    }
+
+    async fn closed_matches_between(&self, from: DateTime<Utc>, to: DateTime<Utc>) -> Result<Vec<Uuid>> {
+        self.round_trip().await?;
+        let store = self.store();
+        let mut closed: Vec<(&Uuid, &StoredMatch)> = store.matches
+            .iter()
+            .filter(|(_, stored)| matches!(stored.status, MatchStatus::Finished | MatchStatus::Voided))
+            .filter(|(_, stored)| stored.end_time.is_some_and(|end| end >= from && end < to))
+            .collect();
+        closed.sort_by_key(|(_, stored)| stored.end_time);
+        Ok(closed.into_iter().map(|(id, _)| *id).collect())
+    }
+
+    async fn delete_matches(&self, match_ids: &[Uuid]) -> Result<i64> {
+        self.round_trip().await?;
+        let mut store = self.store();
+        Ok(match_ids.iter().filter(|id| store.matches.remove(*id).is_some()).count() as i64)
+    }
+}
+
+#[async_trait]
+impl TelemetryRepository for MemoryRepository {
+    // Nobody reads telemetry back, so it is counted and dropped
+    async fn insert_events(&self, records: &[TelemetryRecord]) -> Result<i64> {
+        self.round_trip().await?;
+        Ok(records.len() as i64)
+    }
+}
+
+#[async_trait]
+impl ExperimentRepository for MemoryRepository {
+    async fn list_experiments(&self) -> Result<Vec<Experiment>> {
+        self.round_trip().await?;
+        Ok(self.store().experiments.values().cloned().collect())
+    }
+
+    async fn upsert_experiment(&self, experiment: &Experiment) -> Result<()> {
+        self.round_trip().await?;
+        self.store().experiments.insert(experiment.key.clone(), experiment.clone());
+        Ok(())
+    }
+
+    async fn delete_experiment(&self, key: &str) -> Result<()> {
+        self.round_trip().await?;
+        self.store().experiments.remove(key);
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl RemoteConfigRepository for MemoryRepository {
+    async fn latest_config(&self) -> Result<Option<ClientConfig>> {
+        self.round_trip().await?;
+        Ok(self.store().config_versions.last().map(|(config, _)| config.clone()))
+    }
+
+    async fn insert_config_version(&self, config: &ClientConfig, change: &ConfigChange) -> Result<()> {
+        self.round_trip().await?;
+        let mut store = self.store();
+        if store.config_versions.iter().any(|(existing, _)| existing.version == config.version) {
+            return Err(Error::DuplicateKey(format!("client_config_versions {}", config.version)));
+        }
+        store.config_versions.push((config.clone(), change.clone()));
+        store.config_versions.sort_by_key(|(config, _)| config.version);
+        Ok(())
+    }
+
+    async fn config_history(&self, page: &KeysetQuery) -> Result<(Vec<ConfigChange>, usize)> {
+        self.round_trip().await?;
+        let changes: Vec<ConfigChange> = self.store().config_versions
+            .iter()
+            .map(|(_, change)| change.clone())
+            .collect();
+        page.apply(changes)
+    }
+}
+
+#[async_trait]
+impl TreasureRepository for MemoryRepository {
+    async fn list_treasures(&self) -> Result<Vec<Treasure>> {
+        self.round_trip().await?;
+        let mut treasures: Vec<Treasure> = self.store().treasures.values().cloned().collect();
+        treasures.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(treasures)
+    }
+
+    async fn get_treasure(&self, id: Uuid) -> Result<Treasure> {
+        self.round_trip().await?;
+        self.store().treasures
+            .get(&id)
+            .cloned()
+            .ok_or_else(|| Error::NotFound(format!("treasure {}", id)))
+    }
+
+    async fn create_treasure(&self, treasure: &Treasure) -> Result<()> {
+        self.round_trip().await?;
+        let mut store = self.store();
+        if store.treasures.contains_key(&treasure.id) {
+            return Err(Error::DuplicateKey(format!("treasure {}", treasure.id)));
+        }
+        store.treasures.insert(treasure.id, treasure.clone());
+        Ok(())
+    }
+
+    async fn update_treasure(&self, treasure: &Treasure) -> Result<()> {
+        self.round_trip().await?;
+        let mut store = self.store();
+        let stored = store.treasures
+            .get_mut(&treasure.id)
+            .ok_or_else(|| Error::NotFound(format!("treasure {}", treasure.id)))?;
+        *stored = treasure.clone();
+        Ok(())
+    }
+
+    async fn delete_treasure(&self, id: Uuid) -> Result<()> {
+        self.round_trip().await?;
+        self.store().treasures
+            .remove(&id)
+            .map(|_| ())
+            .ok_or_else(|| Error::NotFound(format!("treasure {}", id)))
+    }
+}
+
+#[async_trait]
+impl ZoneRepository for MemoryRepository {
+    // No zones locally; use ZONES_FILE to partition the map
+    async fn list_zones(&self) -> Result<Vec<Zone>> {
+        self.round_trip().await?;
+        Ok(Vec::new())
+    }
+}
+
+#[async_trait]
+impl PositionRepository for MemoryRepository {
+    async fn insert_positions(&self, samples: &[PositionSample]) -> Result<i64> {
+        self.round_trip().await?;
+        self.store().positions.extend_from_slice(samples);
+        Ok(samples.len() as i64)
+    }
+
+    async fn list_positions(&self, since: DateTime<Utc>, until: DateTime<Utc>, offset: i64, limit: i64) -> Result<Vec<PositionSample>> {
+        self.round_trip().await?;
+        let store = self.store();
+        let mut samples: Vec<&PositionSample> = store.positions
+            .iter()
+            .filter(|sample| sample.recorded_at >= since && sample.recorded_at < until)
+            .collect();
+        samples.sort_by_key(|sample| (sample.recorded_at, sample.user_id));
+        Ok(samples
+            .into_iter()
+            .skip(offset.max(0) as usize)
+            .take(limit.max(0) as usize)
+            .cloned()
+            .collect())
+    }
+
+    async fn replace_heatmap(&self, tiles: &[HeatmapTile]) -> Result<()> {
+        self.round_trip().await?;
+        self.store().heatmap = tiles.to_vec();
+        Ok(())
+    }
+
+    async fn heatmap_tiles(&self, zone_id: Option<&str>) -> Result<Vec<HeatmapTile>> {
+        self.round_trip().await?;
+        Ok(self.store().heatmap
+            .iter()
+            .filter(|tile| tile.zone_id.as_deref() == zone_id)
+            .cloned()
+            .collect())
+    }
+}
+
+#[async_trait]
+impl FairnessRepository for MemoryRepository {
+    async fn insert_queue_samples(&self, samples: &[QueueSample]) -> Result<()> {
+        self.round_trip().await?;
+        self.store().queue_samples.extend_from_slice(samples);
+        Ok(())
+    }
+
+    async fn queue_samples(&self, since: DateTime<Utc>, offset: i64, limit: i64) -> Result<Vec<QueueSample>> {
+        self.round_trip().await?;
+        let store = self.store();
+        let mut samples: Vec<&QueueSample> = store.queue_samples
+            .iter()
+            .filter(|sample| sample.started_at >= since)
+            .collect();
+        samples.sort_by_key(|sample| (sample.started_at, sample.match_id, sample.user_id));
+        Ok(samples
+            .into_iter()
+            .skip(offset.max(0) as usize)
+            .take(limit.max(0) as usize)
+            .cloned()
+            .collect())
+    }
+
+    async fn insert_report(&self, report: &FairnessReport) -> Result<()> {
+        self.round_trip().await?;
+        self.store().fairness_reports.push(report.clone());
+        Ok(())
+    }
+
+    // Reports are appended in order, so the newest are at the back
+    async fn recent_reports(&self, limit: usize) -> Result<Vec<FairnessReport>> {
+        self.round_trip().await?;
+        Ok(self.store().fairness_reports.iter().rev().take(limit).cloned().collect())
+    }
+}
+
+#[async_trait]
+impl BanRepository for MemoryRepository {
+    async fn active_bans(&self, now: DateTime<Utc>) -> Result<Vec<Ban>> {
+        self.round_trip().await?;
+        let mut bans: Vec<Ban> = self.store().bans.iter().filter(|ban| ban.is_active(now)).cloned().collect();
+        bans.sort_by_key(|ban| ban.created_at);
+        Ok(bans)
+    }
+
+    async fn user_bans(&self, user_id: Uuid) -> Result<Vec<Ban>> {
+        self.round_trip().await?;
+        let mut bans: Vec<Ban> = self.store().bans.iter().filter(|ban| ban.user_id == user_id).cloned().collect();
+        bans.sort_by_key(|ban| std::cmp::Reverse(ban.created_at));
+        Ok(bans)
+    }
+
+    // A new ban replaces whatever ban the user already had
+    async fn insert_ban(&self, ban: &Ban) -> Result<()> {
+        self.round_trip().await?;
+        let mut store = self.store();
+        for existing in store.bans.iter_mut().filter(|existing| existing.user_id == ban.user_id && existing.is_active(ban.created_at)) {
+            existing.lifted_at = Some(ban.created_at);
+            existing.lifted_by = Some(ban.issued_by.clone());
+        }
+        store.bans.push(ban.clone());
+        Ok(())
+    }
+
+    async fn lift_bans(&self, user_id: Uuid, lifted_by: &str, now: DateTime<Utc>) -> Result<i64> {
+        self.round_trip().await?;
+        let mut store = self.store();
+        let mut lifted = 0;
+        for ban in store.bans.iter_mut().filter(|ban| ban.user_id == user_id && ban.is_active(now)) {
+            ban.lifted_at = Some(now);
+            ban.lifted_by = Some(lifted_by.to_string());
+            lifted += 1;
+        }
+        Ok(lifted)
+    }
+}
+
+#[async_trait]
+impl QuarantineRepository for MemoryRepository {
+    async fn list_quarantined(&self) -> Result<Vec<Quarantine>> {
+        self.round_trip().await?;
+        let mut quarantined: Vec<Quarantine> = self.store().quarantined.values().cloned().collect();
+        quarantined.sort_by_key(|quarantine| quarantine.created_at);
+        Ok(quarantined)
+    }
+
+    async fn add_quarantined(&self, quarantine: &Quarantine) -> Result<()> {
+        self.round_trip().await?;
+        self.store().quarantined.insert(quarantine.user_id, quarantine.clone());
+        Ok(())
+    }
+
+    async fn remove_quarantined(&self, user_id: Uuid) -> Result<bool> {
+        self.round_trip().await?;
+        Ok(self.store().quarantined.remove(&user_id).is_some())
+    }
+}
+
+#[async_trait]
+impl AppealRepository for MemoryRepository {
+    async fn insert_appeal(&self, appeal: &Appeal) -> Result<()> {
+        self.round_trip().await?;
+        let mut store = self.store();
+        if store.appeals.iter().any(|existing| existing.id == appeal.id) {
+            return Err(Error::DuplicateKey(format!("appeal {}", appeal.id)));
+        }
+        store.appeals.push(appeal.clone());
+        Ok(())
+    }
+
+    async fn get_appeal(&self, id: Uuid) -> Result<Option<Appeal>> {
+        self.round_trip().await?;
+        Ok(self.store().appeals.iter().find(|appeal| appeal.id == id).cloned())
+    }
+
+    async fn open_appeal(&self, user_id: Uuid) -> Result<Option<Appeal>> {
+        self.round_trip().await?;
+        Ok(self.store().appeals.iter().find(|appeal| appeal.user_id == user_id && appeal.status == AppealStatus::Open).cloned())
+    }
+
+    async fn list_appeals(&self, status: Option<AppealStatus>) -> Result<Vec<Appeal>> {
+        self.round_trip().await?;
+        let mut appeals: Vec<Appeal> = self.store().appeals
+            .iter()
+            .filter(|appeal| status.is_none_or(|status| appeal.status == status))
+            .cloned()
+            .collect();
+        appeals.sort_by_key(|appeal| std::cmp::Reverse(appeal.created_at));
+        Ok(appeals)
+    }
+
+    async fn update_appeal(&self, appeal: &Appeal) -> Result<()> {
+        self.round_trip().await?;
+        let mut store = self.store();
+        let existing = store.appeals
+            .iter_mut()
+            .find(|existing| existing.id == appeal.id)
+            .ok_or_else(|| Error::NotFound(format!("appeal {}", appeal.id)))?;
+        *existing = appeal.clone();
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl RatingRepository for MemoryRepository {
+    async fn get_ratings(&self, user_ids: &[Uuid]) -> Result<Vec<PlayerRating>> {
+        self.round_trip().await?;
+        let store = self.store();
+        Ok(user_ids.iter().filter_map(|id| store.ratings.get(id).cloned()).collect())
+    }
+
+    async fn upsert_ratings(&self, ratings: &[PlayerRating]) -> Result<()> {
+        self.round_trip().await?;
+        let mut store = self.store();
+        for rating in ratings {
+            store.ratings.insert(rating.user_id, rating.clone());
+        }
+        Ok(())
+    }
+
+    async fn flagged_ratings(&self) -> Result<Vec<PlayerRating>> {
+        self.round_trip().await?;
+        let mut flagged: Vec<PlayerRating> = self.store().ratings
+            .values()
+            .filter(|rating| rating.smurf_suspected && rating.flagged_at.is_some())
+            .cloned()
+            .collect();
+        flagged.sort_by_key(|rating| std::cmp::Reverse(rating.flagged_at));
+        Ok(flagged)
+    }
+
+    async fn clear_smurf(&self, user_id: Uuid) -> Result<()> {
+        self.round_trip().await?;
+        if let Some(rating) = self.store().ratings.get_mut(&user_id).filter(|rating| rating.smurf_suspected) {
+            rating.smurf_suspected = false;
+            rating.flagged_at = None;
+            rating.smurf_cleared = true;
+        }
+        Ok(())
+    }
+
+    async fn top_ratings(&self, min_matches: i32, limit: usize) -> Result<Vec<PlayerRating>> {
+        self.round_trip().await?;
+        let mut top: Vec<PlayerRating> = self.store().ratings
+            .values()
+            .filter(|rating| rating.matches_played >= min_matches)
+            .cloned()
+            .collect();
+        top.sort_by(|a, b| b.mmr.total_cmp(&a.mmr).then(a.user_id.cmp(&b.user_id)));
+        top.truncate(limit);
+        Ok(top)
+    }
+}
+
+#[async_trait]
+impl InboxRepository for MemoryRepository {
+    // Like the unique (user_id, event, key) constraint: the first message wins
+    async fn insert_messages(&self, messages: &[InboxMessage]) -> Result<()> {
+        self.round_trip().await?;
+        let mut store = self.store();
+        for message in messages {
+            let duplicate = store.inbox.iter().any(|held| {
+                held.user_id == message.user_id && held.event == message.event && held.key == message.key
+            });
+            if !duplicate {
+                store.inbox.push(message.clone());
+            }
+        }
+        Ok(())
+    }
+
+    async fn take_messages(&self, user_id: Uuid) -> Result<Vec<InboxMessage>> {
+        self.round_trip().await?;
+        let mut store = self.store();
+        let (taken, kept): (Vec<InboxMessage>, Vec<InboxMessage>) = std::mem::take(&mut store.inbox)
+            .into_iter()
+            .partition(|message| message.user_id == user_id);
+        store.inbox = kept;
+        Ok(taken)
+    }
+
+    async fn delete_expired(&self, now: DateTime<Utc>) -> Result<i64> {
+        self.round_trip().await?;
+        let mut store = self.store();
+        let before = store.inbox.len();
+        store.inbox.retain(|message| message.expires_at > now);
+        Ok((before - store.inbox.len()) as i64)
+    }
+}
+
+#[async_trait]
+impl DeviceRepository for MemoryRepository {
+    // Like the (user_id, device_id) primary key: registering again updates
+    // the device but keeps when it was first registered
+    async fn upsert_device(&self, device: &Device) -> Result<()> {
+        self.round_trip().await?;
+        let mut store = self.store();
+        match store.devices.iter_mut().find(|d| d.user_id == device.user_id && d.device_id == device.device_id) {
+            Some(known) => {
+                known.platform = device.platform;
+                known.push_token = device.push_token.clone();
+                known.last_seen_at = device.last_seen_at;
+            }
+            None => store.devices.push(device.clone()),
+        }
+        Ok(())
+    }
+
+    async fn user_devices(&self, user_ids: &[Uuid]) -> Result<Vec<Device>> {
+        self.round_trip().await?;
+        let mut devices: Vec<Device> = self.store().devices
+            .iter()
+            .filter(|device| user_ids.contains(&device.user_id))
+            .cloned()
+            .collect();
+        devices.sort_by_key(|device| std::cmp::Reverse(device.last_seen_at));
+        Ok(devices)
+    }
+
+    async fn touch_device(&self, user_id: Uuid, device_id: &str, at: DateTime<Utc>) -> Result<bool> {
+        self.round_trip().await?;
+        let mut store = self.store();
+        match store.devices.iter_mut().find(|d| d.user_id == user_id && d.device_id == device_id) {
+            Some(device) => {
+                device.last_seen_at = at;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    async fn delete_device(&self, user_id: Uuid, device_id: &str) -> Result<()> {
+        self.round_trip().await?;
+        let mut store = self.store();
+        let before = store.devices.len();
+        store.devices.retain(|d| !(d.user_id == user_id && d.device_id == device_id));
+        if store.devices.len() == before {
+            return Err(Error::NotFound(format!("device {} of {}", device_id, user_id)));
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl IdentityRepository for MemoryRepository {
+    async fn find_identity(&self, provider: IdentityProvider, subject: &str) -> Result<Option<LinkedIdentity>> {
+        self.round_trip().await?;
+        Ok(self.store().identities
+            .iter()
+            .find(|identity| identity.provider == provider && identity.subject == subject)
+            .cloned())
+    }
+
+    async fn user_identities(&self, user_id: Uuid) -> Result<Vec<LinkedIdentity>> {
+        self.round_trip().await?;
+        let mut identities: Vec<LinkedIdentity> = self.store().identities
+            .iter()
+            .filter(|identity| identity.user_id == user_id)
+            .cloned()
+            .collect();
+        identities.sort_by_key(|identity| identity.linked_at);
+        Ok(identities)
+    }
+
+    // Same uniqueness as the table: (provider, subject) and (user_id, provider)
+    async fn insert_identity(&self, identity: &LinkedIdentity) -> Result<()> {
+        self.round_trip().await?;
+        let mut store = self.store();
+        if store.identities.iter().any(|linked| {
+            linked.provider == identity.provider
+                && (linked.subject == identity.subject || linked.user_id == identity.user_id)
+        }) {
+            return Err(Error::DuplicateKey(format!("{} identity of {}", identity.provider.to_str(), identity.user_id)));
+        }
+        store.identities.push(identity.clone());
+        Ok(())
+    }
+
+    // Nothing reads the merge log, so local runs don't keep it
+    async fn merge_accounts(&self, merge: &AccountMerge) -> Result<()> {
+        self.round_trip().await?;
+        let (from, into) = (merge.from_user_id, merge.into_user_id);
+        let mut store = self.store();
+
+        for stored in store.matches.values_mut() {
+            for member in stored.members.iter_mut().filter(|member| member.user_id == from) {
+                member.user_id = into;
+            }
+            for discovery in stored.discoveries.iter_mut().filter(|discovery| discovery.user_id == from) {
+                discovery.user_id = into;
+            }
+            if let Some(platform) = stored.platforms.remove(&from) {
+                stored.platforms.insert(into, platform);
+            }
+        }
+
+        // The account's rating wins; a guest's rating only moves to an unrated account
+        if let Some(mut rating) = store.ratings.remove(&from) {
+            store.ratings.entry(into).or_insert_with(|| {
+                rating.user_id = into;
+                rating
+            });
+        }
+
+        let shared: Vec<String> = store.devices
+            .iter()
+            .filter(|device| device.user_id == into)
+            .map(|device| device.device_id.clone())
+            .collect();
+        store.devices.retain(|device| !(device.user_id == from && shared.contains(&device.device_id)));
+        for device in store.devices.iter_mut().filter(|device| device.user_id == from) {
+            device.user_id = into;
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl ApiKeyRepository for MemoryRepository {
+    async fn insert_key(&self, key: &ApiKey) -> Result<()> {
+        self.round_trip().await?;
+        self.store().api_keys.push(key.clone());
+        Ok(())
+    }
+
+    async fn list_keys(&self) -> Result<Vec<ApiKey>> {
+        self.round_trip().await?;
+        let mut keys = self.store().api_keys.clone();
+        keys.sort_by_key(|key| std::cmp::Reverse(key.created_at));
+        Ok(keys)
+    }
+
+    async fn active_keys(&self, now: DateTime<Utc>) -> Result<Vec<ApiKey>> {
+        self.round_trip().await?;
+        Ok(self.store().api_keys.iter().filter(|key| key.is_active(now)).cloned().collect())
+    }
+
+    async fn get_key(&self, id: Uuid) -> Result<ApiKey> {
+        self.round_trip().await?;
+        self.store().api_keys
+            .iter()
+            .find(|key| key.id == id)
+            .cloned()
+            .ok_or_else(|| Error::NotFound(format!("API key {}", id)))
+    }
+
+    async fn rotate_key(&self, old_id: Uuid, old_expires_at: DateTime<Utc>, replacement: &ApiKey) -> Result<()> {
+        self.round_trip().await?;
+        let mut store = self.store();
+        if let Some(old) = store.api_keys.iter_mut().find(|key| key.id == old_id && key.revoked_at.is_none()) {
+            old.expires_at = Some(old_expires_at);
+            old.replaced_by = Some(replacement.id);
+        }
+        store.api_keys.push(replacement.clone());
+        Ok(())
+    }
+
+    async fn revoke_key(&self, id: Uuid, at: DateTime<Utc>) -> Result<()> {
+        self.round_trip().await?;
+        let mut store = self.store();
+        match store.api_keys.iter_mut().find(|key| key.id == id && key.revoked_at.is_none()) {
+            Some(key) => {
+                key.revoked_at = Some(at);
+                Ok(())
+            }
+            None => Err(Error::NotFound(format!("API key {}", id))),
+        }
+    }
+}
+
+#[async_trait]
+impl AuditRepository for MemoryRepository {
+    async fn insert_audit(&self, record: &AuditRecord) -> Result<()> {
+        self.round_trip().await?;
+        self.store().audit.push(record.clone());
+        Ok(())
+    }
+
+    async fn audit_page(&self, actor: Option<&str>, page: &KeysetQuery) -> Result<(Vec<AuditRecord>, usize)> {
+        self.round_trip().await?;
+        let records: Vec<AuditRecord> = self.store().audit
+            .iter()
+            .filter(|record| actor.is_none_or(|actor| record.actor == actor))
+            .cloned()
+            .collect();
+        page.apply(records)
+    }
+}
+
+#[async_trait]
+impl AnnouncementRepository for MemoryRepository {
+    async fn get_motd(&self) -> Result<Option<Motd>> {
+        self.round_trip().await?;
+        Ok(self.store().motd.clone())
+    }
+
+    async fn upsert_motd(&self, motd: &Motd) -> Result<()> {
+        self.round_trip().await?;
+        self.store().motd = Some(motd.clone());
+        Ok(())
+    }
+
+    async fn delete_motd(&self) -> Result<()> {
+        self.round_trip().await?;
+        self.store().motd = None;
+        Ok(())
+    }
+
+    async fn insert_announcement(&self, announcement: &Announcement) -> Result<()> {
+        self.round_trip().await?;
+        self.store().announcements.push(announcement.clone());
+        Ok(())
+    }
+
+    async fn announcements_since(&self, since: DateTime<Utc>) -> Result<Vec<Announcement>> {
+        self.round_trip().await?;
+        let mut announcements: Vec<Announcement> = self.store().announcements
+            .iter()
+            .filter(|announcement| announcement.send_at >= since && announcement.cancelled_at.is_none())
+            .cloned()
+            .collect();
+        announcements.sort_by_key(|announcement| announcement.send_at);
+        Ok(announcements)
+    }
+
+    async fn cancel_announcement(&self, id: Uuid, now: DateTime<Utc>) -> Result<bool> {
+        self.round_trip().await?;
+        let mut store = self.store();
+        let pending = store.announcements.iter_mut().find(|announcement| {
+            announcement.id == id && announcement.send_at > now && announcement.cancelled_at.is_none()
+        });
+        match pending {
+            Some(announcement) => {
+                announcement.cancelled_at = Some(now);
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+}
+
+#[async_trait]
+impl ScheduleRepository for MemoryRepository {
+    async fn save_schedule(&self, schedule: &ScheduledMatch) -> Result<()> {
+        self.round_trip().await?;
+        self.store().schedules.insert(schedule.id, schedule.clone());
+        Ok(())
+    }
+
+    async fn get_schedule(&self, id: Uuid) -> Result<Option<ScheduledMatch>> {
+        self.round_trip().await?;
+        Ok(self.store().schedules.get(&id).cloned())
+    }
+
+    async fn pending_schedules(&self, before: DateTime<Utc>) -> Result<Vec<ScheduledMatch>> {
+        self.round_trip().await?;
+        let mut schedules: Vec<ScheduledMatch> = self.store().schedules
+            .values()
+            .filter(|schedule| schedule.starts_at <= before && schedule.status.is_pending())
+            .cloned()
+            .collect();
+        schedules.sort_by_key(|schedule| schedule.starts_at);
+        Ok(schedules)
+    }
+
+    async fn user_schedules(&self, user_id: Uuid, since: DateTime<Utc>) -> Result<Vec<ScheduledMatch>> {
+        self.round_trip().await?;
+        let mut schedules: Vec<ScheduledMatch> = self.store().schedules
+            .values()
+            .filter(|schedule| schedule.starts_at >= since && schedule.participants().contains(&user_id))
+            .cloned()
+            .collect();
+        schedules.sort_by_key(|schedule| schedule.starts_at);
+        Ok(schedules)
+    }
+}
+
+#[async_trait]
+impl PreferenceRepository for MemoryRepository {
+    async fn get_preferences(&self, user_ids: &[Uuid]) -> Result<Vec<NotificationPreferences>> {
+        self.round_trip().await?;
+        let store = self.store();
+        Ok(user_ids.iter().filter_map(|user_id| store.preferences.get(user_id).cloned()).collect())
+    }
+
+    async fn upsert_preferences(&self, preferences: &NotificationPreferences) -> Result<()> {
+        self.round_trip().await?;
+        self.store().preferences.insert(preferences.user_id, preferences.clone());
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl TutorialRepository for MemoryRepository {
+    async fn get_tutorial_progress(&self, user_id: Uuid) -> Result<Option<TutorialProgress>> {
+        self.round_trip().await?;
+        Ok(self.store().tutorials.get(&user_id).cloned())
+    }
+
+    async fn save_tutorial_progress(&self, progress: &TutorialProgress) -> Result<()> {
+        self.round_trip().await?;
+        self.store().tutorials.insert(progress.user_id, progress.clone());
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl JobRepository for MemoryRepository {
+    async fn save_job(&self, job: &Job) -> Result<()> {
+        self.round_trip().await?;
+        self.store().jobs.insert(job.id, job.clone());
+        Ok(())
+    }
+
+    async fn get_job(&self, id: Uuid) -> Result<Option<Job>> {
+        self.round_trip().await?;
+        Ok(self.store().jobs.get(&id).cloned())
+    }
+
+    async fn recent_jobs(&self, limit: usize) -> Result<Vec<Job>> {
+        self.round_trip().await?;
+        let mut jobs: Vec<Job> = self.store().jobs.values().cloned().collect();
+        jobs.sort_by_key(|job| std::cmp::Reverse(job.created_at));
+        jobs.truncate(limit);
+        Ok(jobs)
+    }
+
+    async fn touch_jobs(&self, ids: &[Uuid], at: DateTime<Utc>) -> Result<()> {
+        self.round_trip().await?;
+        let mut store = self.store();
+        for id in ids {
+            if let Some(job) = store.jobs.get_mut(id).filter(|job| job.status == JobStatus::Running) {
+                job.updated_at = at;
+            }
+        }
+        Ok(())
+    }
+
+    async fn fail_running_jobs(&self, node_id: Option<&str>, updated_before: DateTime<Utc>, error: &str, at: DateTime<Utc>) -> Result<i64> {
+        self.round_trip().await?;
+        let mut failed = 0;
+        for job in self.store().jobs.values_mut() {
+            if job.status == JobStatus::Running
+                && job.updated_at < updated_before
+                && node_id.is_none_or(|node_id| job.node_id == node_id)
+            {
+                job.status = JobStatus::Failed;
+                job.errors.push(error.to_string());
+                job.updated_at = at;
+                job.finished_at = Some(at);
+                failed += 1;
+            }
+        }
+        Ok(failed)
+    }
+
+    async fn delete_jobs_before(&self, cutoff: DateTime<Utc>) -> Result<i64> {
+        self.round_trip().await?;
+        let mut store = self.store();
+        let before = store.jobs.len();
+        store.jobs.retain(|_, job| job.created_at >= cutoff || job.status == JobStatus::Running);
+        Ok((before - store.jobs.len()) as i64)
+    }
+}
@@ -0,0 +1,205 @@
+use serde_json::json;
+
+use crate::error::{Error, Result};
+use super::hasura_client::HasuraClient;
+
+// Embedded schema migrations, applied in order through Hasura's run_sql.
+//
+// Applied versions are recorded in spv_schema_migrations. Every migration is
+// idempotent (IF NOT EXISTS) and takes an advisory lock, so nodes starting
+// together can't trip over each other. The tables are then tracked in Hasura
+// with the relationships the repositories query through. `users` belongs to
+// the auth service and is not created here.
+pub struct Migration {
+    pub version: i32,
+    pub name: &'static str,
+    sql: &'static str,
+}
+
+const MIGRATIONS: &[Migration] = &[
+    Migration { version: 1, name: "matches", sql: include_str!("../../migrations/0001_matches.sql") },
+    Migration { version: 2, name: "world", sql: include_str!("../../migrations/0002_world.sql") },
+    Migration { version: 3, name: "players", sql: include_str!("../../migrations/0003_players.sql") },
+    Migration { version: 4, name: "operations", sql: include_str!("../../migrations/0004_operations.sql") },
+    Migration { version: 5, name: "platforms", sql: include_str!("../../migrations/0005_platforms.sql") },
+    Migration { version: 6, name: "devices", sql: include_str!("../../migrations/0006_devices.sql") },
+    Migration { version: 7, name: "identities", sql: include_str!("../../migrations/0007_identities.sql") },
+    Migration { version: 8, name: "api_keys", sql: include_str!("../../migrations/0008_api_keys.sql") },
+    Migration { version: 9, name: "fairness", sql: include_str!("../../migrations/0009_fairness.sql") },
+    Migration { version: 10, name: "win_probability", sql: include_str!("../../migrations/0010_win_probability.sql") },
+    Migration { version: 11, name: "objective_score", sql: include_str!("../../migrations/0011_objective_score.sql") },
+    Migration { version: 12, name: "victory_condition", sql: include_str!("../../migrations/0012_victory_condition.sql") },
+    Migration { version: 13, name: "match_rounds", sql: include_str!("../../migrations/0013_match_rounds.sql") },
+    Migration { version: 14, name: "lobby_selections", sql: include_str!("../../migrations/0014_lobby_selections.sql") },
+    Migration { version: 15, name: "scheduled_matches", sql: include_str!("../../migrations/0015_scheduled_matches.sql") },
+    Migration { version: 16, name: "notification_preferences", sql: include_str!("../../migrations/0016_notification_preferences.sql") },
+    Migration { version: 17, name: "announcement_content", sql: include_str!("../../migrations/0017_announcement_content.sql") },
+    Migration { version: 18, name: "tutorial_progress", sql: include_str!("../../migrations/0018_tutorial_progress.sql") },
+    Migration { version: 19, name: "quarantined_players", sql: include_str!("../../migrations/0019_quarantined_players.sql") },
+    Migration { version: 20, name: "trust_appeals", sql: include_str!("../../migrations/0020_trust_appeals.sql") },
+    Migration { version: 21, name: "jobs", sql: include_str!("../../migrations/0021_jobs.sql") },
+    Migration { version: 22, name: "team_capacity", sql: include_str!("../../migrations/0022_team_capacity.sql") },
+];
+
+// Held for the length of each migration's transaction
+const LOCK_KEY: i64 = 0x0053_5056_5f6d_6967;
+
+const LEDGER: &str = r#"
+    CREATE TABLE IF NOT EXISTS spv_schema_migrations (
+        version integer PRIMARY KEY,
+        name text NOT NULL,
+        applied_at timestamptz NOT NULL DEFAULT now()
+    )
+"#;
+
+// Tables exposed through GraphQL
+const TRACKED: &[&str] = &[
+    "treasure_matches",
+    "match_teams",
+    "match_members",
+    "match_discoveries",
+    "match_adjustments",
+    "match_rounds",
+    "treasures",
+    "zones",
+    "match_positions",
+    "heatmap_tiles",
+    "match_queue_samples",
+    "fairness_reports",
+    "bans",
+    "player_ratings",
+    "inbox_messages",
+    "devices",
+    "linked_identities",
+    "account_merges",
+    "api_keys",
+    "audit_log",
+    "announcements",
+    "motd",
+    "experiments",
+    "client_config_versions",
+    "client_telemetry",
+    "scheduled_matches",
+    "notification_preferences",
+    "tutorial_progress",
+    "quarantined_players",
+    "trust_appeals",
+    "jobs",
+];
+
+// (table, relationship, remote table, foreign key column on the remote table)
+const ARRAY_RELATIONSHIPS: &[(&str, &str, &str, &str)] = &[
+    ("treasure_matches", "match_teams", "match_teams", "match_id"),
+    ("treasure_matches", "match_members", "match_members", "match_id"),
+    ("treasure_matches", "match_discoveries", "match_discoveries", "match_id"),
+    ("treasure_matches", "match_adjustments", "match_adjustments", "match_id"),
+    ("treasure_matches", "match_rounds", "match_rounds", "match_id"),
+    ("match_teams", "match_members", "match_members", "team_id"),
+];
+
+pub struct MigrationReport {
+    // Migrations applied by this run, in order
+    pub applied: Vec<&'static Migration>,
+    // Latest version now in the database
+    pub version: i32,
+}
+
+// Bring the database up to the latest migration and make sure every table
+// and relationship is tracked
+pub async fn run(client: &HasuraClient) -> Result<MigrationReport> {
+    client.run_sql(LEDGER).await?;
+    let done = applied_versions(client).await?;
+
+    let mut applied = Vec::new();
+    for migration in MIGRATIONS.iter().filter(|m| !done.contains(&m.version)) {
+        tracing::info!("Applying migration {:04} {}", migration.version, migration.name);
+        let sql = format!(
+            "SELECT pg_advisory_xact_lock({});\n{}\nINSERT INTO spv_schema_migrations (version, name) VALUES ({}, '{}') ON CONFLICT DO NOTHING;",
+            LOCK_KEY, migration.sql, migration.version, migration.name
+        );
+        client.run_sql(&sql).await.map_err(|e| {
+            Error::DbError(format!("migration {:04} {} failed: {}", migration.version, migration.name, e))
+        })?;
+        applied.push(migration);
+    }
+
+    track(client).await?;
+
+    let version = done
+        .iter()
+        .copied()
+        .chain(applied.iter().map(|m| m.version))
+        .max()
+        .unwrap_or(0);
+    Ok(MigrationReport { applied, version })
+}
+
+async fn applied_versions(client: &HasuraClient) -> Result<Vec<i32>> {
+    let rows = client.run_sql("SELECT version FROM spv_schema_migrations").await?;
+    // First row is the column header
+    Ok(rows
+        .iter()
+        .skip(1)
+        .filter_map(|row| row.first()?.as_deref()?.parse().ok())
+        .collect())
+}
+
+async fn track(client: &HasuraClient) -> Result<()> {
+    for table in TRACKED {
+        let body = json!({
+            "type": "pg_track_table",
+            "args": {
+                "source": "default",
+                "table": {"schema": "public", "name": table}
+            }
+        });
+        tolerate_existing(client.metadata(&body).await)?;
+    }
+    for (table, name, remote_table, column) in ARRAY_RELATIONSHIPS {
+        let body = json!({
+            "type": "pg_create_array_relationship",
+            "args": {
+                "source": "default",
+                "table": {"schema": "public", "name": table},
+                "name": name,
+                "using": {
+                    "foreign_key_constraint_on": {
+                        "table": {"schema": "public", "name": remote_table},
+                        "column": column
+                    }
+                }
+            }
+        });
+        tolerate_existing(client.metadata(&body).await)?;
+    }
+
+    // Nicknames and avatars come from the auth service's users table, which
+    // may not be tracked in a bare local Hasura. Migrating goes on without the
+    // relationship; the startup schema check reports it missing.
+    let body = json!({
+        "type": "pg_create_object_relationship",
+        "args": {
+            "source": "default",
+            "table": {"schema": "public", "name": "match_members"},
+            "name": "user",
+            "using": {
+                "manual_configuration": {
+                    "remote_table": {"schema": "public", "name": "users"},
+                    "column_mapping": {"user_id": "id"}
+                }
+            }
+        }
+    });
+    if let Err(e) = tolerate_existing(client.metadata(&body).await) {
+        tracing::warn!("Could not link match_members to users: {}", e);
+    }
+    Ok(())
+}
+
+// Metadata calls are repeated on every run; finding them done is fine
+fn tolerate_existing(result: Result<serde_json::Value>) -> Result<()> {
+    match result {
+        Ok(_) | Err(Error::DuplicateKey(_)) => Ok(()),
+        Err(e) => Err(e),
+    }
+}
@@ -1,2 +1,31 @@
+pub mod health;
+pub mod hasura_announcement_repository;
+pub mod hasura_api_key_repository;
+pub mod hasura_appeal_repository;
+pub mod hasura_audit_repository;
+pub mod hasura_auth;
+pub mod hasura_ban_repository;
 pub mod hasura_client;
-pub mod hasura_match_repository;
\ No newline at end of file
+pub mod hasura_device_repository;
+pub mod hasura_experiment_repository;
+pub mod hasura_fairness_repository;
+pub mod hasura_identity_repository;
+pub mod hasura_inbox_repository;
+pub mod hasura_job_repository;
+pub mod hasura_match_repository;
+pub mod hasura_position_repository;
+pub mod hasura_preference_repository;
+pub mod hasura_quarantine_repository;
+pub mod hasura_rating_repository;
+pub mod hasura_remote_config_repository;
+pub mod hasura_schedule_repository;
+pub mod hasura_telemetry_repository;
+pub mod hasura_treasure_repository;
+pub mod hasura_tutorial_repository;
+pub mod hasura_zone_repository;
+pub mod keyset;
+pub mod memory_repository;
+pub mod migrations;
+pub mod repository;
+pub mod schema_check;
+pub mod schema_drift;
\ No newline at end of file
@@ -0,0 +1,439 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+use crate::announcements::announcement::{Announcement, Motd};
+use crate::anticheat::appeal::{Appeal, AppealStatus};
+use crate::anticheat::quarantine::Quarantine;
+use crate::apikeys::key::ApiKey;
+use crate::audit::record::AuditRecord;
+use crate::auth::identity::{AccountMerge, IdentityProvider, LinkedIdentity};
+use crate::devices::device::Device;
+use crate::error::Result;
+use crate::experiments::experiment::Experiment;
+use crate::game::victory::VictoryCondition;
+use crate::heatmap::sample::{HeatmapTile, PositionSample};
+use crate::inbox::message::InboxMessage;
+use crate::jobs::job::Job;
+use crate::matchmaking::fairness::{FairnessReport, QueueSample};
+use crate::matchmaking::review::{AuditEntry, MatchReview};
+use crate::matchmaking::verify::Anomaly;
+use crate::models::game::{
+    LobbySelection, MatchDetails, MatchRoom, MatchScores, MatchStatus, MatchTeam, Platform, PlayerExperience, RoundResult, TeamStrength,
+};
+use crate::models::treasure::Treasure;
+use crate::models::zone::Zone;
+use crate::moderation::ban::Ban;
+use crate::preferences::preferences::NotificationPreferences;
+use crate::rating::calibration::MatchPrediction;
+use crate::rating::mmr::PlayerRating;
+use crate::remote_config::document::{ClientConfig, ConfigChange};
+use crate::schedule::scheduled::ScheduledMatch;
+use crate::telemetry::event::TelemetryRecord;
+use crate::tutorial::tutorial::TutorialProgress;
+
+use super::keyset::KeysetQuery;
+
+// Persistence operations the matchmaking core depends on.
+// `HasuraMatchRepository` is the production implementation.
+#[async_trait]
+pub trait MatchRepository: Send + Sync {
+    // Cheap round trip used by the health probe
+    async fn ping(&self) -> Result<()>;
+
+    async fn create_match(&self, match_id: Uuid, match_type: &str, required_players_per_team: i32, victory: VictoryCondition) -> Result<Uuid>;
+
+    async fn create_team(&self, team_id: Uuid, match_id: Uuid, team_number: i32, max_players: i32, strength: Option<TeamStrength>) -> Result<Uuid>;
+
+    // Fails with `Error::TeamFull` once the team has the max_players it was
+    // created with
+    async fn add_player_to_team(&self, match_id: Uuid, team_id: Uuid, user_id: Uuid, platform: Option<Platform>) -> Result<Uuid>;
+
+    async fn start_match(&self, match_id: Uuid) -> Result<()>;
+
+    async fn record_discovery(&self, match_id: Uuid, team_id: Uuid, user_id: Uuid, treasure_id: Uuid, score: i32) -> Result<Uuid>;
+
+    // Points a team earned holding a capture zone
+    async fn add_objective_points(&self, team_id: Uuid, points: i32) -> Result<()>;
+
+    // Result of a finished round of a best_of match
+    async fn record_round(&self, round: &RoundResult) -> Result<()>;
+
+    // Finish a match with the given winner; None is a draw
+    async fn end_match(&self, match_id: Uuid, winner_team_id: Option<Uuid>) -> Result<()>;
+
+    // End a match without a winner, keeping the anomalies for the reviewer
+    async fn flag_for_review(&self, match_id: Uuid, anomalies: &[Anomaly]) -> Result<()>;
+
+    async fn get_match(&self, match_id: Uuid) -> Result<MatchRoom>;
+
+    async fn get_match_teams(&self, match_id: Uuid) -> Result<Vec<MatchTeam>>;
+
+    async fn get_match_details(&self, match_id: Uuid) -> Result<MatchDetails>;
+
+    // Id of the active match the user belongs to, if any
+    async fn is_user_in_match(&self, user_id: Uuid) -> Result<Option<Uuid>>;
+
+    async fn migrate_legacy_statuses(&self) -> Result<i64>;
+
+    async fn get_match_scores(&self, match_id: Uuid) -> Result<MatchScores>;
+
+    // Scores of matches finished since the given time
+    async fn finished_match_scores(&self, since: DateTime<Utc>) -> Result<Vec<MatchScores>>;
+
+    // Win predictions of matches finished since the given time, oldest first;
+    // matches started without predictions are left out
+    async fn finished_predictions(&self, since: DateTime<Utc>) -> Result<Vec<MatchPrediction>>;
+
+    async fn set_team_score(&self, team_id: Uuid, total_score: i32) -> Result<()>;
+
+    async fn set_member_score(&self, match_id: Uuid, user_id: Uuid, individual_score: i32) -> Result<()>;
+
+    // Loadout and role the player picked in the lobby
+    async fn set_member_selection(&self, match_id: Uuid, user_id: Uuid, selection: &LobbySelection) -> Result<()>;
+
+    async fn set_winner(&self, match_id: Uuid, team_id: Uuid) -> Result<()>;
+
+    // Matches in the given status with their records and audit log, oldest first
+    async fn list_match_reviews(&self, status: MatchStatus) -> Result<Vec<MatchReview>>;
+
+    async fn get_match_review(&self, match_id: Uuid) -> Result<MatchReview>;
+
+    // Mark a discovery invalid and take its score off the team and the player.
+    // Fails with `Error::NotFound` if it isn't a valid discovery of the match.
+    async fn invalidate_discovery(&self, match_id: Uuid, discovery_id: Uuid) -> Result<()>;
+
+    // Settle a reviewed match as finished (with a winner) or voided
+    async fn close_match(&self, match_id: Uuid, status: MatchStatus, winner_team_id: Option<Uuid>) -> Result<()>;
+
+    async fn record_adjustment(&self, entry: &AuditEntry) -> Result<()>;
+
+    // Finished matches and account age of a player
+    async fn player_experience(&self, user_id: Uuid) -> Result<PlayerExperience>;
+
+    // Finished and voided matches that ended in [from, to), oldest first
+    async fn closed_matches_between(&self, from: DateTime<Utc>, to: DateTime<Utc>) -> Result<Vec<Uuid>>;
+
+    // Delete matches with their teams, members, discoveries, rounds and
+    // adjustments; returns how many were deleted
+    async fn delete_matches(&self, match_ids: &[Uuid]) -> Result<i64>;
+}
+
+// Storage for client telemetry events.
+// `HasuraTelemetryRepository` is the production implementation.
+#[async_trait]
+pub trait TelemetryRepository: Send + Sync {
+    // Returns the number of rows written
+    async fn insert_events(&self, records: &[TelemetryRecord]) -> Result<i64>;
+}
+
+// Storage for A/B experiment definitions.
+// `HasuraExperimentRepository` is the production implementation.
+#[async_trait]
+pub trait ExperimentRepository: Send + Sync {
+    async fn list_experiments(&self) -> Result<Vec<Experiment>>;
+
+    // Insert, or replace the experiment with the same key
+    async fn upsert_experiment(&self, experiment: &Experiment) -> Result<()>;
+
+    async fn delete_experiment(&self, key: &str) -> Result<()>;
+}
+
+// Versioned client config with its audit trail.
+// `HasuraRemoteConfigRepository` is the production implementation.
+#[async_trait]
+pub trait RemoteConfigRepository: Send + Sync {
+    // None before the first change
+    async fn latest_config(&self) -> Result<Option<ClientConfig>>;
+
+    // Fails with `Error::DuplicateKey` when the version already exists
+    async fn insert_config_version(&self, config: &ClientConfig, change: &ConfigChange) -> Result<()>;
+
+    // One page of the version history and how many versions match its filters
+    async fn config_history(&self, page: &KeysetQuery) -> Result<(Vec<ConfigChange>, usize)>;
+}
+
+// The treasure catalog.
+// `HasuraTreasureRepository` is the production implementation.
+#[async_trait]
+pub trait TreasureRepository: Send + Sync {
+    async fn list_treasures(&self) -> Result<Vec<Treasure>>;
+
+    // Fails with `Error::NotFound` for unknown ids
+    async fn get_treasure(&self, id: Uuid) -> Result<Treasure>;
+
+    async fn create_treasure(&self, treasure: &Treasure) -> Result<()>;
+
+    // Fails with `Error::NotFound` for unknown ids
+    async fn update_treasure(&self, treasure: &Treasure) -> Result<()>;
+
+    // Fails with `Error::NotFound` for unknown ids, and with
+    // `Error::ForeignKeyViolation` once the treasure has been discovered
+    async fn delete_treasure(&self, id: Uuid) -> Result<()>;
+}
+
+// Map zone definitions.
+// `HasuraZoneRepository` is the production implementation.
+#[async_trait]
+pub trait ZoneRepository: Send + Sync {
+    async fn list_zones(&self) -> Result<Vec<Zone>>;
+}
+
+// Position history and the heatmap tiles aggregated from it.
+// `HasuraPositionRepository` is the production implementation.
+#[async_trait]
+pub trait PositionRepository: Send + Sync {
+    async fn insert_positions(&self, samples: &[PositionSample]) -> Result<i64>;
+
+    // Samples recorded in [since, until), oldest first, one page at a time
+    async fn list_positions(&self, since: DateTime<Utc>, until: DateTime<Utc>, offset: i64, limit: i64) -> Result<Vec<PositionSample>>;
+
+    // Swap the whole heatmap for freshly computed tiles, atomically
+    async fn replace_heatmap(&self, tiles: &[HeatmapTile]) -> Result<()>;
+
+    // Tiles of one zone; `None` selects tiles outside every zone
+    async fn heatmap_tiles(&self, zone_id: Option<&str>) -> Result<Vec<HeatmapTile>>;
+}
+
+// Player bans, lifted and expired ones included.
+// `HasuraBanRepository` is the production implementation.
+#[async_trait]
+pub trait BanRepository: Send + Sync {
+    // Bans neither lifted nor expired at `now`
+    async fn active_bans(&self, now: DateTime<Utc>) -> Result<Vec<Ban>>;
+
+    // Every ban of a player, newest first
+    async fn user_bans(&self, user_id: Uuid) -> Result<Vec<Ban>>;
+
+    // Lifts the player's active bans and inserts the new one, atomically
+    async fn insert_ban(&self, ban: &Ban) -> Result<()>;
+
+    // Lift the player's active bans, returning how many there were
+    async fn lift_bans(&self, user_id: Uuid, lifted_by: &str, now: DateTime<Utc>) -> Result<i64>;
+}
+
+// Players kept in the cheater quarantine pool.
+// `HasuraQuarantineRepository` is the production implementation.
+#[async_trait]
+pub trait QuarantineRepository: Send + Sync {
+    async fn list_quarantined(&self) -> Result<Vec<Quarantine>>;
+
+    // Replaces the player's entry if they are already quarantined
+    async fn add_quarantined(&self, quarantine: &Quarantine) -> Result<()>;
+
+    // False if the player wasn't quarantined
+    async fn remove_quarantined(&self, user_id: Uuid) -> Result<bool>;
+}
+
+// Appeals against anti-cheat decisions.
+// `HasuraAppealRepository` is the production implementation.
+#[async_trait]
+pub trait AppealRepository: Send + Sync {
+    async fn insert_appeal(&self, appeal: &Appeal) -> Result<()>;
+
+    async fn get_appeal(&self, id: Uuid) -> Result<Option<Appeal>>;
+
+    // The player's appeal awaiting a decision, if any
+    async fn open_appeal(&self, user_id: Uuid) -> Result<Option<Appeal>>;
+
+    // Newest first; every status when None
+    async fn list_appeals(&self, status: Option<AppealStatus>) -> Result<Vec<Appeal>>;
+
+    // Writes the resolution fields
+    async fn update_appeal(&self, appeal: &Appeal) -> Result<()>;
+}
+
+// Matchmaking ratings.
+// `HasuraRatingRepository` is the production implementation.
+#[async_trait]
+pub trait RatingRepository: Send + Sync {
+    // Stored ratings of the given players; players never rated are left out
+    async fn get_ratings(&self, user_ids: &[Uuid]) -> Result<Vec<PlayerRating>>;
+
+    async fn upsert_ratings(&self, ratings: &[PlayerRating]) -> Result<()>;
+
+    // Suspected smurfs flagged for review, most recently flagged first
+    async fn flagged_ratings(&self) -> Result<Vec<PlayerRating>>;
+
+    // Fails with `Error::NotFound` if the player isn't a suspect
+    async fn clear_smurf(&self, user_id: Uuid) -> Result<()>;
+
+    // Highest ratings of players with at least `min_matches` rated matches,
+    // best first
+    async fn top_ratings(&self, min_matches: i32, limit: usize) -> Result<Vec<PlayerRating>>;
+}
+
+// Events held for offline players.
+// `HasuraInboxRepository` is the production implementation.
+#[async_trait]
+pub trait InboxRepository: Send + Sync {
+    // A message whose player already holds one with the same event and key is dropped
+    async fn insert_messages(&self, messages: &[InboxMessage]) -> Result<()>;
+
+    // Delete the player's messages and return them, in one step
+    async fn take_messages(&self, user_id: Uuid) -> Result<Vec<InboxMessage>>;
+
+    // Delete messages expired at `now`, returning how many there were
+    async fn delete_expired(&self, now: DateTime<Utc>) -> Result<i64>;
+}
+
+// Devices registered by players.
+// `HasuraDeviceRepository` is the production implementation.
+#[async_trait]
+pub trait DeviceRepository: Send + Sync {
+    // Insert the device, or update the player's device with the same device_id
+    async fn upsert_device(&self, device: &Device) -> Result<()>;
+
+    // Devices of these players, most recently seen first
+    async fn user_devices(&self, user_ids: &[Uuid]) -> Result<Vec<Device>>;
+
+    // Record a connection from the device; false if it isn't registered
+    async fn touch_device(&self, user_id: Uuid, device_id: &str, at: DateTime<Utc>) -> Result<bool>;
+
+    // Fails with `Error::NotFound` if the player has no such device
+    async fn delete_device(&self, user_id: Uuid, device_id: &str) -> Result<()>;
+}
+
+// Provider accounts linked to players.
+// `HasuraIdentityRepository` is the production implementation.
+#[async_trait]
+pub trait IdentityRepository: Send + Sync {
+    async fn find_identity(&self, provider: IdentityProvider, subject: &str) -> Result<Option<LinkedIdentity>>;
+
+    async fn user_identities(&self, user_id: Uuid) -> Result<Vec<LinkedIdentity>>;
+
+    // Fails with `Error::DuplicateKey` if the provider account is already linked,
+    // or the player already linked an account of this provider
+    async fn insert_identity(&self, identity: &LinkedIdentity) -> Result<()>;
+
+    // Move a guest's match history, rating and devices to another account and
+    // record the merge, in one transaction. The account keeps its own rating
+    // and devices where both have one.
+    async fn merge_accounts(&self, merge: &AccountMerge) -> Result<()>;
+}
+
+// Queue time samples and matchmaking fairness reports.
+// `HasuraFairnessRepository` is the production implementation.
+#[async_trait]
+pub trait FairnessRepository: Send + Sync {
+    async fn insert_queue_samples(&self, samples: &[QueueSample]) -> Result<()>;
+
+    // Samples of matches started since `since`, oldest first, one page at a time
+    async fn queue_samples(&self, since: DateTime<Utc>, offset: i64, limit: i64) -> Result<Vec<QueueSample>>;
+
+    async fn insert_report(&self, report: &FairnessReport) -> Result<()>;
+
+    // Newest first
+    async fn recent_reports(&self, limit: usize) -> Result<Vec<FairnessReport>>;
+}
+
+// API keys of service callers.
+// `HasuraApiKeyRepository` is the production implementation.
+#[async_trait]
+pub trait ApiKeyRepository: Send + Sync {
+    async fn insert_key(&self, key: &ApiKey) -> Result<()>;
+
+    // Every key, revoked and expired ones included, newest first
+    async fn list_keys(&self) -> Result<Vec<ApiKey>>;
+
+    // Keys neither revoked nor expired at `now`
+    async fn active_keys(&self, now: DateTime<Utc>) -> Result<Vec<ApiKey>>;
+
+    // Fails with `Error::NotFound` if there is no such key
+    async fn get_key(&self, id: Uuid) -> Result<ApiKey>;
+
+    // Insert the replacement and set the old key's expiry, in one step
+    async fn rotate_key(&self, old_id: Uuid, old_expires_at: DateTime<Utc>, replacement: &ApiKey) -> Result<()>;
+
+    // Fails with `Error::NotFound` if there is no such key or it is already revoked
+    async fn revoke_key(&self, id: Uuid, at: DateTime<Utc>) -> Result<()>;
+}
+
+// Changes made through the admin API and gRPC.
+// `HasuraAuditRepository` is the production implementation.
+#[async_trait]
+pub trait AuditRepository: Send + Sync {
+    async fn insert_audit(&self, record: &AuditRecord) -> Result<()>;
+
+    // One page of the log, optionally only one actor's, and how many records
+    // match its filters
+    async fn audit_page(&self, actor: Option<&str>, page: &KeysetQuery) -> Result<(Vec<AuditRecord>, usize)>;
+}
+
+// Message of the day and announcements.
+// `HasuraAnnouncementRepository` is the production implementation.
+#[async_trait]
+pub trait AnnouncementRepository: Send + Sync {
+    async fn get_motd(&self) -> Result<Option<Motd>>;
+
+    async fn upsert_motd(&self, motd: &Motd) -> Result<()>;
+
+    async fn delete_motd(&self) -> Result<()>;
+
+    async fn insert_announcement(&self, announcement: &Announcement) -> Result<()>;
+
+    // Announcements not cancelled and sent at or after `since`, soonest first
+    async fn announcements_since(&self, since: DateTime<Utc>) -> Result<Vec<Announcement>>;
+
+    // Cancel an announcement not sent yet at `now`; false if there is none
+    async fn cancel_announcement(&self, id: Uuid, now: DateTime<Utc>) -> Result<bool>;
+}
+
+// Matches scheduled for a later time.
+// `HasuraScheduleRepository` is the production implementation.
+#[async_trait]
+pub trait ScheduleRepository: Send + Sync {
+    // Insert, or update the status, lobby and reason of an existing one
+    async fn save_schedule(&self, schedule: &ScheduledMatch) -> Result<()>;
+
+    async fn get_schedule(&self, id: Uuid) -> Result<Option<ScheduledMatch>>;
+
+    // Scheduled, reminded or open, starting at or before `before`; soonest first
+    async fn pending_schedules(&self, before: DateTime<Utc>) -> Result<Vec<ScheduledMatch>>;
+
+    // Hosted by or inviting the user, starting at or after `since`; soonest first
+    async fn user_schedules(&self, user_id: Uuid, since: DateTime<Utc>) -> Result<Vec<ScheduledMatch>>;
+}
+
+// Notification preferences of players.
+// `HasuraPreferenceRepository` is the production implementation.
+#[async_trait]
+pub trait PreferenceRepository: Send + Sync {
+    // Preferences of those of these players who set any
+    async fn get_preferences(&self, user_ids: &[Uuid]) -> Result<Vec<NotificationPreferences>>;
+
+    async fn upsert_preferences(&self, preferences: &NotificationPreferences) -> Result<()>;
+}
+
+// Tutorial progress of players.
+// `HasuraTutorialRepository` is the production implementation.
+#[async_trait]
+pub trait TutorialRepository: Send + Sync {
+    // None for players who never started the tutorial
+    async fn get_tutorial_progress(&self, user_id: Uuid) -> Result<Option<TutorialProgress>>;
+
+    async fn save_tutorial_progress(&self, progress: &TutorialProgress) -> Result<()>;
+}
+
+// Background jobs and their progress.
+// `HasuraJobRepository` is the production implementation.
+#[async_trait]
+pub trait JobRepository: Send + Sync {
+    // Insert the job, or overwrite its progress and status
+    async fn save_job(&self, job: &Job) -> Result<()>;
+
+    async fn get_job(&self, id: Uuid) -> Result<Option<Job>>;
+
+    // Newest first
+    async fn recent_jobs(&self, limit: usize) -> Result<Vec<Job>>;
+
+    // Mark these jobs as still being worked on, if they are running
+    async fn touch_jobs(&self, ids: &[Uuid], at: DateTime<Utc>) -> Result<()>;
+
+    // Fail the running jobs last updated before `updated_before`, only those
+    // of `node_id` when given, returning how many there were
+    async fn fail_running_jobs(&self, node_id: Option<&str>, updated_before: DateTime<Utc>, error: &str, at: DateTime<Utc>) -> Result<i64>;
+
+    // Delete jobs created before the cutoff, returning how many there were
+    async fn delete_jobs_before(&self, cutoff: DateTime<Utc>) -> Result<i64>;
+}
@@ -0,0 +1,170 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::error::Result;
+use super::hasura_client::HasuraClient;
+
+// Tables the match repository depends on, with the fields it reads or writes
+// and their GraphQL type (a relationship's type is the related table)
+const EXPECTED: &[(&str, &[(&str, &str)])] = &[
+    ("treasure_matches", &[
+        ("id", "uuid"),
+        ("match_type", "String"),
+        ("status", "String"),
+        ("required_players_per_team", "Int"),
+        ("start_time", "timestamptz"),
+        ("end_time", "timestamptz"),
+        ("winner_team_id", "uuid"),
+        ("review_notes", "jsonb"),
+        ("is_finished", "Boolean"),
+        ("victory_condition", "jsonb"),
+        ("match_teams", "match_teams"),
+        ("match_members", "match_members"),
+        ("match_discoveries", "match_discoveries"),
+        ("match_adjustments", "match_adjustments"),
+        ("match_rounds", "match_rounds"),
+    ]),
+    ("match_teams", &[
+        ("id", "uuid"),
+        ("match_id", "uuid"),
+        ("team_number", "Int"),
+        ("current_players", "Int"),
+        ("max_players", "Int"),
+        ("total_score", "Int"),
+        ("mmr", "float8"),
+        ("win_probability", "float8"),
+        ("objective_score", "Int"),
+        ("match_members", "match_members"),
+    ]),
+    ("match_members", &[
+        ("id", "uuid"),
+        ("match_id", "uuid"),
+        ("team_id", "uuid"),
+        ("user_id", "uuid"),
+        ("individual_score", "Int"),
+        ("platform", "String"),
+        ("loadout", "String"),
+        ("role", "String"),
+        ("user", "users"),
+    ]),
+    ("match_discoveries", &[
+        ("id", "uuid"),
+        ("match_id", "uuid"),
+        ("team_id", "uuid"),
+        ("user_id", "uuid"),
+        ("treasure_id", "uuid"),
+        ("score", "Int"),
+        ("discovered_at", "timestamptz"),
+        ("invalidated", "Boolean"),
+    ]),
+    ("match_rounds", &[
+        ("match_id", "uuid"),
+        ("round_number", "Int"),
+        ("scores", "jsonb"),
+        ("winner_team_id", "uuid"),
+        ("ended_at", "timestamptz"),
+    ]),
+];
+
+// What introspection returns for a table's GraphQL object type
+#[derive(Debug, Deserialize)]
+struct IntrospectedType {
+    fields: Option<Vec<IntrospectedField>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct IntrospectedField {
+    name: String,
+    #[serde(rename = "type")]
+    field_type: TypeRef,
+}
+
+#[derive(Debug, Deserialize)]
+struct TypeRef {
+    name: Option<String>,
+    #[serde(rename = "ofType")]
+    of_type: Option<Box<TypeRef>>,
+}
+
+impl TypeRef {
+    // Named type under any NON_NULL and LIST wrappers
+    fn named(&self) -> Option<&str> {
+        match &self.name {
+            Some(name) => Some(name),
+            None => self.of_type.as_ref()?.named(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SchemaProblem {
+    MissingTable(String),
+    MissingField { table: String, field: String, expected: String },
+    WrongType { table: String, field: String, expected: String, found: String },
+}
+
+impl fmt::Display for SchemaProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SchemaProblem::MissingTable(table) => write!(f, "table `{}` is not tracked in Hasura", table),
+            SchemaProblem::MissingField { table, field, expected } => {
+                write!(f, "`{}.{}` is missing (expected {})", table, field, expected)
+            }
+            SchemaProblem::WrongType { table, field, expected, found } => {
+                write!(f, "`{}.{}` is {}, expected {}", table, field, found, expected)
+            }
+        }
+    }
+}
+
+// Introspect the Hasura schema and compare it with EXPECTED. Returns every
+// mismatch found; fails only when the schema can't be read at all.
+pub async fn preflight(client: &HasuraClient) -> Result<Vec<SchemaProblem>> {
+    // ofType three levels deep covers [T!]! relationships
+    let selections: Vec<String> = EXPECTED
+        .iter()
+        .map(|(table, _)| format!(r#"
+                {0}: __type(name: "{0}") {{
+                    fields {{
+                        name
+                        type {{ name ofType {{ name ofType {{ name ofType {{ name }} }} }} }}
+                    }}
+                }}"#, table))
+        .collect();
+    let query = format!("query SchemaCheck {{{}\n}}", selections.join(""));
+
+    let types: HashMap<String, Option<IntrospectedType>> = client.query(&query, json!({})).await?;
+
+    let mut problems = Vec::new();
+    for (table, fields) in EXPECTED {
+        let Some(found) = types.get(*table).and_then(|t| t.as_ref()) else {
+            problems.push(SchemaProblem::MissingTable(table.to_string()));
+            continue;
+        };
+        let found: HashMap<&str, Option<&str>> = found.fields
+            .iter()
+            .flatten()
+            .map(|field| (field.name.as_str(), field.field_type.named()))
+            .collect();
+        for (field, expected) in *fields {
+            match found.get(field) {
+                None => problems.push(SchemaProblem::MissingField {
+                    table: table.to_string(),
+                    field: field.to_string(),
+                    expected: expected.to_string(),
+                }),
+                Some(actual) if *actual != Some(*expected) => problems.push(SchemaProblem::WrongType {
+                    table: table.to_string(),
+                    field: field.to_string(),
+                    expected: expected.to_string(),
+                    found: actual.unwrap_or("unknown").to_string(),
+                }),
+                Some(_) => {}
+            }
+        }
+    }
+    Ok(problems)
+}
@@ -0,0 +1,258 @@
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+use crate::error::{Error, Result};
+use crate::metrics::METRICS;
+use crate::slow::operation_name;
+
+// A field of the query's selection set, keyed by its alias if it has one
+struct Selection {
+    key: String,
+    children: Vec<Selection>,
+}
+
+// Turns the `data` of a GraphQL response into the repository's model.
+//
+// The startup schema check only covers the match tables, so drift elsewhere
+// shows up here first. Every field the query selected is looked for in the
+// response. Fields the model can do without (`Option` or `#[serde(default)]`)
+// are tolerated with a warning; otherwise the error names the operation and
+// the missing fields instead of serde's bare "missing field".
+pub fn decode<T: DeserializeOwned>(query: &str, data: Value) -> Result<T> {
+    let missing = missing_fields(query, &data);
+    match serde_json::from_value(data) {
+        Ok(value) => {
+            if !missing.is_empty() {
+                METRICS.record_schema_mismatch(false);
+                tracing::warn!(
+                    operation = %operation_name(query),
+                    fields = %missing.join(", "),
+                    "Hasura response is missing selected fields, using defaults"
+                );
+            }
+            Ok(value)
+        }
+        Err(e) => {
+            let operation = operation_name(query);
+            if missing.is_empty() && !e.is_data() {
+                return Err(Error::DbError(format!("{}: invalid response: {}", operation, e)));
+            }
+            METRICS.record_schema_mismatch(true);
+            let detail = if missing.is_empty() {
+                // A field changed type rather than going away
+                e.to_string()
+            } else {
+                format!("missing {} ({})", missing.join(", "), e)
+            };
+            tracing::error!(operation = %operation, "Hasura schema mismatch: {}", detail);
+            Err(Error::DbError(format!("Schema mismatch in {}: {}", operation, detail)))
+        }
+    }
+}
+
+// Paths of the selected fields absent from the response, e.g.
+// "treasure_matches[].match_teams[].objective_score". A null counts as
+// present: that's the column's value, not drift.
+fn missing_fields(query: &str, data: &Value) -> Vec<String> {
+    let mut missing = Vec::new();
+    if let Some(selections) = parse(query) {
+        collect_missing(&selections, data, "", &mut missing);
+    }
+    missing
+}
+
+fn collect_missing(selections: &[Selection], value: &Value, path: &str, missing: &mut Vec<String>) {
+    match value {
+        Value::Array(items) => {
+            let path = format!("{}[]", path);
+            for item in items {
+                collect_missing(selections, item, &path, missing);
+            }
+        }
+        Value::Object(fields) => {
+            for selection in selections {
+                let path = if path.is_empty() {
+                    selection.key.clone()
+                } else {
+                    format!("{}.{}", path, selection.key)
+                };
+                match fields.get(&selection.key) {
+                    None => {
+                        if !missing.contains(&path) {
+                            missing.push(path);
+                        }
+                    }
+                    Some(child) if !selection.children.is_empty() => {
+                        collect_missing(&selection.children, child, &path, missing);
+                    }
+                    Some(_) => {}
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+// Selection set of the first operation in the document. Fragment spreads
+// can't be resolved without their definitions and are skipped; None when the
+// document can't be read, so nothing is reported rather than a wrong path.
+fn parse(query: &str) -> Option<Vec<Selection>> {
+    let mut parser = Parser { chars: query.chars().collect(), pos: 0 };
+    // Skip the operation type, name and variable definitions
+    loop {
+        parser.skip_ignored();
+        match parser.peek()? {
+            '{' => break,
+            '(' => parser.skip_group('(', ')')?,
+            _ => parser.pos += 1,
+        }
+    }
+    parser.pos += 1;
+    parser.selection_set()
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    // Whitespace, commas and comments
+    fn skip_ignored(&mut self) {
+        while let Some(c) = self.peek() {
+            if c == '#' {
+                while self.peek().is_some_and(|c| c != '\n') {
+                    self.pos += 1;
+                }
+            } else if c.is_whitespace() || c == ',' {
+                self.pos += 1;
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn name(&mut self) -> String {
+        let start = self.pos;
+        while self.peek().is_some_and(|c| c.is_alphanumeric() || c == '_') {
+            self.pos += 1;
+        }
+        self.chars[start..self.pos].iter().collect()
+    }
+
+    // Arguments, which may hold strings and nested objects
+    fn skip_group(&mut self, open: char, close: char) -> Option<()> {
+        let mut depth = 0;
+        loop {
+            match self.peek()? {
+                '"' => {
+                    self.pos += 1;
+                    while self.peek()? != '"' {
+                        if self.peek()? == '\\' {
+                            self.pos += 1;
+                        }
+                        self.pos += 1;
+                    }
+                }
+                c if c == open => depth += 1,
+                c if c == close => {
+                    depth -= 1;
+                    if depth == 0 {
+                        self.pos += 1;
+                        return Some(());
+                    }
+                }
+                _ => {}
+            }
+            self.pos += 1;
+        }
+    }
+
+    // After the opening brace, up to and including the closing one
+    fn selection_set(&mut self) -> Option<Vec<Selection>> {
+        let mut selections = Vec::new();
+        loop {
+            self.skip_ignored();
+            match self.peek()? {
+                '}' => {
+                    self.pos += 1;
+                    return Some(selections);
+                }
+                '.' => {
+                    // `...Fragment` or `... on Type { ... }`
+                    self.pos += 3;
+                    self.skip_ignored();
+                    if self.peek()? == '{' {
+                        self.pos += 1;
+                        selections.extend(self.selection_set()?);
+                        continue;
+                    }
+                    let name = self.name();
+                    self.skip_ignored();
+                    if name == "on" {
+                        self.name();
+                        self.skip_ignored();
+                        self.skip_directives()?;
+                        if self.peek()? == '{' {
+                            self.pos += 1;
+                            selections.extend(self.selection_set()?);
+                        }
+                    } else {
+                        self.skip_directives()?;
+                    }
+                }
+                _ => {
+                    let mut key = self.name();
+                    if key.is_empty() {
+                        return None;
+                    }
+                    self.skip_ignored();
+                    if self.peek()? == ':' {
+                        // `alias: field`
+                        self.pos += 1;
+                        self.skip_ignored();
+                        self.name();
+                        self.skip_ignored();
+                    } else if key == "__typename" {
+                        key.clear();
+                    }
+                    if self.peek()? == '(' {
+                        self.skip_group('(', ')')?;
+                        self.skip_ignored();
+                    }
+                    // Hasura leaves out fields skipped by @include/@skip
+                    let conditional = self.skip_directives()?;
+                    let children = if self.peek()? == '{' {
+                        self.pos += 1;
+                        self.selection_set()?
+                    } else {
+                        Vec::new()
+                    };
+                    if !key.is_empty() && !conditional {
+                        selections.push(Selection { key, children });
+                    }
+                }
+            }
+        }
+    }
+
+    // `@include(if: $x)` and the like; true if one of them is conditional
+    fn skip_directives(&mut self) -> Option<bool> {
+        let mut conditional = false;
+        while self.peek()? == '@' {
+            self.pos += 1;
+            let name = self.name();
+            conditional |= name == "include" || name == "skip";
+            self.skip_ignored();
+            if self.peek()? == '(' {
+                self.skip_group('(', ')')?;
+            }
+            self.skip_ignored();
+        }
+        Some(conditional)
+    }
+}
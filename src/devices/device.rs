@@ -0,0 +1,56 @@
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::models::game::Platform;
+
+// A device a player registered with device.register. `device_id` is chosen by
+// the client and stable across reinstalls where the platform allows; it is
+// unique per player, so registering again updates the device.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, ToSchema)]
+pub struct Device {
+    pub user_id: Uuid,
+    pub device_id: String,
+    pub platform: Platform,
+    // APNs or FCM token; devices without one get no push notifications
+    pub push_token: Option<String>,
+    pub registered_at: DateTime<Utc>,
+    // Last connection from the device
+    pub last_seen_at: DateTime<Utc>,
+}
+
+// device.register request
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+pub struct DeviceRegistration {
+    pub device_id: String,
+    // Defaults to the platform the connection reported
+    pub platform: Option<Platform>,
+    pub push_token: Option<String>,
+}
+
+// device.revoke request and reply
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct DeviceRevocation {
+    pub device_id: String,
+}
+
+// Why the server ended a session
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SessionEndReason {
+    // The player connected again, from this device or, under the takeover
+    // policy, from another one
+    Replaced,
+    // The device was revoked by the player or an admin
+    Revoked,
+}
+
+// Sent as `sys.session_ended` right before the server closes the connection
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct SessionEnded {
+    pub reason: SessionEndReason,
+    // Device of the session that replaced this one, if it registered
+    pub by_device: Option<String>,
+}
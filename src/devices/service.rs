@@ -0,0 +1,149 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::Utc;
+use serde::Serialize;
+use serde_json::Value;
+use uuid::Uuid;
+
+use crate::config::{DeviceConfig, SessionPolicy};
+use crate::db::repository::DeviceRepository;
+use crate::error::{Error, Result};
+use crate::models::game::Platform;
+use crate::signing;
+use super::device::{Device, DeviceRegistration};
+
+// Longest device_id a client may register
+pub const MAX_DEVICE_ID_LEN: usize = 128;
+const PUSH_TIMEOUT: Duration = Duration::from_secs(5);
+
+// Body posted to PUSH_WEBHOOK_URL; the push gateway delivers it to APNs or FCM
+#[derive(Debug, Serialize)]
+struct PushRequest<'a> {
+    event: &'a str,
+    data: &'a Value,
+    targets: Vec<PushTarget<'a>>,
+}
+
+#[derive(Debug, Serialize)]
+struct PushTarget<'a> {
+    user_id: Uuid,
+    device_id: &'a str,
+    platform: Platform,
+    push_token: &'a str,
+}
+
+// Devices registered by players.
+//
+// A client registers its device with device.register, or names it with
+// `?device_id=` when connecting. The gateway uses the device id to replace an
+// older session of the same device, and under SESSION_POLICY=takeover every
+// other session of the player. Devices with a push token receive a push
+// notification through PUSH_WEBHOOK_URL for the events held in the inbox.
+pub struct DeviceService {
+    config: DeviceConfig,
+    repo: Arc<dyn DeviceRepository>,
+    http: reqwest::Client,
+}
+
+impl DeviceService {
+    pub fn init(config: DeviceConfig, repo: Arc<dyn DeviceRepository>) -> Arc<Self> {
+        let http = reqwest::Client::builder()
+            .timeout(PUSH_TIMEOUT)
+            .build()
+            .unwrap_or_default();
+        Arc::new(Self { config, repo, http })
+    }
+
+    pub fn session_policy(&self) -> SessionPolicy {
+        self.config.session_policy
+    }
+
+    // Register or update one of the player's devices. The platform defaults to
+    // the one the connection reported. Devices beyond MAX_DEVICES_PER_USER are
+    // dropped, least recently seen first.
+    pub async fn register(&self, user_id: Uuid, registration: DeviceRegistration, connected_from: Option<Platform>) -> Result<Device> {
+        let device_id = registration.device_id.trim();
+        if device_id.is_empty() || device_id.len() > MAX_DEVICE_ID_LEN {
+            return Err(Error::InvalidMessage);
+        }
+        let platform = registration.platform.or(connected_from).ok_or(Error::InvalidMessage)?;
+        let push_token = registration.push_token.filter(|token| !token.is_empty());
+
+        let now = Utc::now();
+        let device = Device {
+            user_id,
+            device_id: device_id.to_string(),
+            platform,
+            push_token,
+            registered_at: now,
+            last_seen_at: now,
+        };
+        self.repo.upsert_device(&device).await?;
+
+        let devices = self.repo.user_devices(&[user_id]).await?;
+        for stale in devices.iter().skip(self.config.max_per_user) {
+            if let Err(e) = self.repo.delete_device(user_id, &stale.device_id).await {
+                tracing::warn!("Failed to drop device {} of {}: {}", stale.device_id, user_id, e);
+            }
+        }
+        Ok(device)
+    }
+
+    // The player's devices, most recently seen first
+    pub async fn list(&self, user_id: Uuid) -> Result<Vec<Device>> {
+        self.repo.user_devices(&[user_id]).await
+    }
+
+    pub async fn revoke(&self, user_id: Uuid, device_id: &str) -> Result<()> {
+        self.repo.delete_device(user_id, device_id).await
+    }
+
+    // Record a connection from the device, if it is registered
+    pub async fn touch(&self, user_id: Uuid, device_id: &str) {
+        if let Err(e) = self.repo.touch_device(user_id, device_id, Utc::now()).await {
+            tracing::warn!("Failed to update last seen of device {} of {}: {}", device_id, user_id, e);
+        }
+    }
+
+    // Send a push notification for the event to every device of these players
+    // that has a push token. Does nothing without PUSH_WEBHOOK_URL.
+    pub async fn push(&self, user_ids: &[Uuid], event: &str, data: &Value) {
+        let Some(url) = &self.config.push_webhook else {
+            return;
+        };
+        if user_ids.is_empty() {
+            return;
+        }
+        let devices = match self.repo.user_devices(user_ids).await {
+            Ok(devices) => devices,
+            Err(e) => {
+                tracing::warn!("Failed to load devices for push: {}", e);
+                return;
+            }
+        };
+        let targets: Vec<PushTarget> = devices
+            .iter()
+            .filter_map(|device| Some(PushTarget {
+                user_id: device.user_id,
+                device_id: &device.device_id,
+                platform: device.platform,
+                push_token: device.push_token.as_deref()?,
+            }))
+            .collect();
+        if targets.is_empty() {
+            return;
+        }
+
+        let request = signing::signed_post(&self.http, url, &PushRequest { event, data, targets });
+        tokio::spawn(async move {
+            match request.send().await {
+                Ok(response) if !response.status().is_success() => {
+                    tracing::warn!("Push webhook answered {}", response.status());
+                }
+                Ok(_) => {}
+                Err(e) => tracing::warn!("Push webhook failed: {}", e),
+            }
+        });
+    }
+}
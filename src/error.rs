@@ -1,5 +1,12 @@
-use axum::response::Response;
+use axum::{
+    Json,
+    http::{StatusCode, header},
+    response::{IntoResponse, Response},
+};
+use serde::Serialize;
+use utoipa::ToSchema;
 
+#[allow(clippy::enum_variant_names)]
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
     #[error("Authentication failed")]
@@ -22,6 +29,54 @@ pub enum Error {
     UserAlreadyInMatch,
     #[error("Your match has already started, so you can't leave")]
     MatchAlreadyStarted,
+    #[error("Record already exists: {0}")]
+    DuplicateKey(String),
+    #[error("Permission denied: {0}")]
+    PermissionDenied(String),
+    #[error("Referenced record does not exist: {0}")]
+    ForeignKeyViolation(String),
+    #[error("Not found: {0}")]
+    NotFound(String),
+    #[error("The team is already full")]
+    TeamFull,
+    #[error("Another join request for this user is in progress")]
+    JoinInProgress,
+    #[error("Lock error: {0}")]
+    LockError(String),
+    #[error("The database is unavailable, please try again later")]
+    DbUnavailable,
+    #[error("The record was changed by someone else, reload and retry")]
+    VersionConflict,
+    #[error("This treasure is not active right now")]
+    TreasureNotActive,
+    #[error("Your location can't be trusted right now")]
+    LocationUntrusted,
+    #[error("The match has not finished yet")]
+    MatchNotFinished,
+    #[error("Your account is banned")]
+    Banned,
+    #[error("Invalid party: {0}")]
+    InvalidParty(String),
+    #[error("Cluster error: {0}")]
+    ClusterError(String),
+    #[error("Too many requests, slow down")]
+    RateLimited,
+    #[error("The server is under maintenance: {0}")]
+    Maintenance(String),
+    #[error("The server is busy, retry in {retry_after_secs} seconds")]
+    ServerBusy { retry_after_secs: u64 },
+    #[error("This queue is not open to your platform: {0}")]
+    PlatformNotAllowed(String),
+    #[error("The sign-in provider can't be reached: {0}")]
+    IdentityProviderUnavailable(String),
+    #[error("Invalid lobby selection: {0}")]
+    InvalidSelection(String),
+    #[error("Telemetry event rejected: {0}")]
+    InvalidTelemetry(String),
+    #[error("Invalid list query: {0}")]
+    InvalidQuery(String),
+    #[error("The job has already finished")]
+    JobFinished,
 }
 
 impl Error {
@@ -37,6 +92,99 @@ impl Error {
             Error::MatchNotReady => 1008,
             Error::UserAlreadyInMatch => 1009,
             Error::MatchAlreadyStarted => 1010,
+            Error::DuplicateKey(_) => 1011,
+            Error::PermissionDenied(_) => 1012,
+            Error::ForeignKeyViolation(_) => 1013,
+            Error::NotFound(_) => 1014,
+            Error::TeamFull => 1015,
+            Error::JoinInProgress => 1016,
+            Error::LockError(_) => 1017,
+            Error::DbUnavailable => 1018,
+            Error::VersionConflict => 1019,
+            Error::TreasureNotActive => 1020,
+            Error::LocationUntrusted => 1021,
+            Error::MatchNotFinished => 1022,
+            Error::Banned => 1023,
+            Error::InvalidParty(_) => 1024,
+            Error::ClusterError(_) => 1025,
+            Error::RateLimited => 1026,
+            Error::Maintenance(_) => 1027,
+            Error::ServerBusy { .. } => 1028,
+            Error::PlatformNotAllowed(_) => 1029,
+            Error::IdentityProviderUnavailable(_) => 1030,
+            Error::InvalidSelection(_) => 1031,
+            Error::InvalidTelemetry(_) => 1032,
+            Error::InvalidQuery(_) => 1033,
+            Error::JobFinished => 1034,
+        }
+    }
+
+    // Seconds the client should wait before trying again, when it should
+    pub fn retry_after(&self) -> Option<u64> {
+        match self {
+            Error::ServerBusy { retry_after_secs } => Some(*retry_after_secs),
+            _ => None,
+        }
+    }
+
+    // HTTP status used when the error is returned from a REST route
+    pub fn status_code(&self) -> StatusCode {
+        match self {
+            Error::AuthError => StatusCode::UNAUTHORIZED,
+            Error::PermissionDenied(_) | Error::LocationUntrusted | Error::Banned | Error::PlatformNotAllowed(_) => {
+                StatusCode::FORBIDDEN
+            }
+            Error::InvalidMessage
+            | Error::InvalidMatchType
+            | Error::InvalidParty(_)
+            | Error::InvalidSelection(_)
+            | Error::InvalidTelemetry(_)
+            | Error::InvalidQuery(_) => {
+                StatusCode::BAD_REQUEST
+            }
+            Error::ConnectionNotFound | Error::MatchNotFound | Error::NotFound(_) => StatusCode::NOT_FOUND,
+            Error::DuplicateKey(_)
+            | Error::ForeignKeyViolation(_)
+            | Error::UserAlreadyInMatch
+            | Error::MatchAlreadyStarted
+            | Error::MatchNotReady
+            | Error::TeamFull
+            | Error::JoinInProgress
+            | Error::VersionConflict
+            | Error::TreasureNotActive
+            | Error::MatchNotFinished
+            | Error::JobFinished => StatusCode::CONFLICT,
+            Error::DbUnavailable
+            | Error::Maintenance(_)
+            | Error::ServerBusy { .. }
+            | Error::IdentityProviderUnavailable(_) => StatusCode::SERVICE_UNAVAILABLE,
+            Error::RateLimited => StatusCode::TOO_MANY_REQUESTS,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+// Error body returned by every REST route, mirroring ServerMessage's code/error pair
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ErrorBody {
+    // Same numeric code as Error::code()
+    pub code: i32,
+    pub error: String,
+    // Set when the request can be retried later, also sent as Retry-After
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retry_after_secs: Option<u64>,
+}
+
+impl IntoResponse for Error {
+    fn into_response(self) -> Response {
+        let body = ErrorBody {
+            code: self.code(),
+            error: self.to_string(),
+            retry_after_secs: self.retry_after(),
+        };
+        match body.retry_after_secs {
+            Some(secs) => (self.status_code(), [(header::RETRY_AFTER, secs.to_string())], Json(body)).into_response(),
+            None => (self.status_code(), Json(body)).into_response(),
         }
     }
 }
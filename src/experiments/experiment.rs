@@ -0,0 +1,147 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::error::{Error, Result};
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct Variant {
+    pub name: String,
+    // Relative share of users, e.g. 50/50 or 90/10
+    pub weight: u32,
+}
+
+// An A/B experiment. Users are assigned by hashing user_id with the salt, so
+// the same user always lands in the same variant until the salt or the
+// weights change.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct Experiment {
+    pub key: String,
+    pub salt: String,
+    // Disabled experiments assign nobody
+    pub enabled: bool,
+    pub variants: Vec<Variant>,
+}
+
+// PUT /admin/experiments/{key} body
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+pub struct ExperimentSpec {
+    // Defaults to the key; change it to reshuffle users
+    pub salt: Option<String>,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    pub variants: Vec<Variant>,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+impl Experiment {
+    pub fn from_spec(key: &str, spec: ExperimentSpec) -> Result<Self> {
+        let experiment = Self {
+            key: key.to_string(),
+            salt: spec.salt.unwrap_or_else(|| key.to_string()),
+            enabled: spec.enabled,
+            variants: spec.variants,
+        };
+        experiment.validate()?;
+        Ok(experiment)
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        if self.key.is_empty() || self.variants.is_empty() {
+            return Err(Error::InvalidMessage);
+        }
+        let names_ok = self.variants.iter().enumerate().all(|(i, v)| {
+            !v.name.is_empty() && !self.variants[..i].iter().any(|other| other.name == v.name)
+        });
+        if !names_ok || self.total_weight() == 0 {
+            return Err(Error::InvalidMessage);
+        }
+        Ok(())
+    }
+
+    fn total_weight(&self) -> u64 {
+        self.variants.iter().map(|v| v.weight as u64).sum()
+    }
+
+    // The user's variant, or None while the experiment is disabled
+    pub fn assign(&self, user_id: Uuid) -> Option<&str> {
+        let total = self.total_weight();
+        if !self.enabled || total == 0 {
+            return None;
+        }
+        let mut bucket = bucket_hash(user_id, &self.salt) % total;
+        for variant in &self.variants {
+            let weight = variant.weight as u64;
+            if bucket < weight {
+                return Some(&variant.name);
+            }
+            bucket -= weight;
+        }
+        None
+    }
+}
+
+// FNV-1a over user_id and salt. Unlike std's DefaultHasher it is stable
+// across Rust releases, so assignments survive upgrades and match between instances.
+fn bucket_hash(user_id: Uuid, salt: &str) -> u64 {
+    const OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0100_0000_01b3;
+    user_id
+        .as_bytes()
+        .iter()
+        .chain(salt.as_bytes())
+        .fold(OFFSET, |hash, byte| (hash ^ *byte as u64).wrapping_mul(PRIME))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn experiment(weights: &[u32]) -> Experiment {
+        let variants = weights
+            .iter()
+            .enumerate()
+            .map(|(i, weight)| Variant { name: format!("v{}", i), weight: *weight })
+            .collect();
+        Experiment::from_spec("exp", ExperimentSpec { salt: None, enabled: true, variants }).unwrap()
+    }
+
+    #[test]
+    fn the_hash_is_stable() {
+        // Changing the hash would reshuffle every running experiment
+        assert_eq!(bucket_hash(Uuid::nil(), ""), 0x8820_1fb9_60ff_6465);
+        let user_id = Uuid::new_v4();
+        assert_eq!(bucket_hash(user_id, "a"), bucket_hash(user_id, "a"));
+        assert_ne!(bucket_hash(user_id, "a"), bucket_hash(user_id, "b"));
+    }
+
+    #[test]
+    fn users_are_split_by_weight() {
+        let experiment = experiment(&[90, 10, 0]);
+        let mut counts = [0; 3];
+        for _ in 0..10_000 {
+            let variant = experiment.assign(Uuid::new_v4()).unwrap();
+            counts[variant[1..].parse::<usize>().unwrap()] += 1;
+        }
+        assert!((8_500..9_500).contains(&counts[0]), "{:?}", counts);
+        assert_eq!(counts[2], 0);
+    }
+
+    #[test]
+    fn disabled_and_invalid_experiments_assign_nobody() {
+        let mut disabled = experiment(&[1, 1]);
+        disabled.enabled = false;
+        assert_eq!(disabled.assign(Uuid::new_v4()), None);
+
+        let spec = |weights: Vec<u32>| ExperimentSpec {
+            salt: None,
+            enabled: true,
+            variants: weights.into_iter().map(|weight| Variant { name: "a".to_string(), weight }).collect(),
+        };
+        assert!(Experiment::from_spec("exp", spec(vec![0])).is_err());
+        assert!(Experiment::from_spec("exp", spec(vec![1, 1])).is_err());
+    }
+}
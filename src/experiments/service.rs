@@ -0,0 +1,105 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+use crate::db::repository::ExperimentRepository;
+use crate::error::{Error, Result};
+use crate::supervisor;
+use super::experiment::{Experiment, ExperimentSpec};
+
+// Edited experiments apply to new connections on every instance within this long
+const REFRESH_INTERVAL: Duration = Duration::from_secs(30);
+
+// Experiment definitions, stored in the database and cached in memory so
+// assignments on connect need no round trip
+pub struct ExperimentService {
+    repo: Arc<dyn ExperimentRepository>,
+    experiments: RwLock<HashMap<String, Experiment>>,
+}
+
+impl ExperimentService {
+    // Load the definitions and refresh them periodically. Until they load,
+    // nobody is assigned a variant.
+    pub async fn init(repo: Arc<dyn ExperimentRepository>) -> Arc<Self> {
+        let service = Arc::new(Self {
+            repo,
+            experiments: RwLock::new(HashMap::new()),
+        });
+        if let Err(e) = service.reload().await {
+            tracing::warn!("Failed to load experiments, starting without any: {}", e);
+        }
+
+        let refresher = service.clone();
+        supervisor::spawn_loop("experiment_refresh", move || {
+            let refresher = refresher.clone();
+            async move {
+                let mut interval = tokio::time::interval(REFRESH_INTERVAL);
+                interval.tick().await;
+                loop {
+                    interval.tick().await;
+                    if let Err(e) = refresher.reload().await {
+                        tracing::warn!("Failed to refresh experiments: {}", e);
+                    }
+                }
+            }
+        });
+        service
+    }
+
+    // Replace the cached definitions; invalid ones are skipped
+    pub async fn reload(&self) -> Result<()> {
+        let loaded = self.repo.list_experiments().await?;
+        let mut experiments = HashMap::new();
+        for experiment in loaded {
+            match experiment.validate() {
+                Ok(()) => {
+                    experiments.insert(experiment.key.clone(), experiment);
+                }
+                Err(_) => tracing::warn!("Ignoring invalid experiment definition: {}", experiment.key),
+            }
+        }
+        *self.experiments.write().await = experiments;
+        Ok(())
+    }
+
+    pub async fn list(&self) -> Vec<Experiment> {
+        let mut experiments: Vec<Experiment> = self.experiments.read().await.values().cloned().collect();
+        experiments.sort_by(|a, b| a.key.cmp(&b.key));
+        experiments
+    }
+
+    // Create or replace an experiment
+    pub async fn put(&self, key: &str, spec: ExperimentSpec) -> Result<Experiment> {
+        let experiment = Experiment::from_spec(key, spec)?;
+        self.repo.upsert_experiment(&experiment).await?;
+        self.experiments.write().await.insert(experiment.key.clone(), experiment.clone());
+        tracing::info!("Experiment {} updated: {:?}", experiment.key, experiment.variants);
+        Ok(experiment)
+    }
+
+    pub async fn delete(&self, key: &str) -> Result<()> {
+        if !self.experiments.read().await.contains_key(key) {
+            return Err(Error::NotFound(format!("experiment {}", key)));
+        }
+        self.repo.delete_experiment(key).await?;
+        self.experiments.write().await.remove(key);
+        tracing::info!("Experiment {} deleted", key);
+        Ok(())
+    }
+
+    // Variant per enabled experiment for this user, keyed by experiment key
+    pub async fn assignments(&self, user_id: Uuid) -> HashMap<String, String> {
+        self.experiments
+            .read()
+            .await
+            .values()
+            .filter_map(|experiment| {
+                let variant = experiment.assign(user_id)?;
+                Some((experiment.key.clone(), variant.to_string()))
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,184 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::models::game::PlayerPosition;
+
+// A circular area teams fight over for passive points
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CaptureZone {
+    // Short stable id, e.g. "fountain"
+    pub id: String,
+    pub name: String,
+    // Only in matches made in this map zone; in every match when unset
+    pub zone_id: Option<String>,
+    // Only in these match types; in all of them when empty
+    #[serde(default)]
+    pub match_types: Vec<String>,
+    // Center, in PlayerPosition coordinates
+    pub x: f32,
+    pub y: f32,
+    pub radius: f32,
+    // Awarded to the holding team every CAPTURE_INTERVAL_SECS
+    pub points: i32,
+}
+
+impl CaptureZone {
+    fn is_valid(&self) -> bool {
+        !self.id.is_empty()
+            && self.x.is_finite()
+            && self.y.is_finite()
+            && self.radius.is_finite()
+            && self.radius > 0.0
+            && self.points >= 0
+    }
+
+    pub fn applies_to(&self, match_type: &str, zone_id: Option<&str>) -> bool {
+        (self.match_types.is_empty() || self.match_types.iter().any(|t| t == match_type))
+            && self.zone_id.as_deref().is_none_or(|id| Some(id) == zone_id)
+    }
+
+    fn contains(&self, pos: &PlayerPosition) -> bool {
+        let (dx, dy) = (pos.x - self.x, pos.y - self.y);
+        dx * dx + dy * dy <= self.radius * self.radius
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum CaptureStatus {
+    // Nobody inside
+    Neutral,
+    // Teams inside in equal numbers; nobody scores
+    Contested,
+    // One team has more players inside than any other
+    Held,
+}
+
+// match.capture event: a capture zone changed hands
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct CaptureUpdate {
+    pub match_id: Uuid,
+    pub capture_zone_id: String,
+    pub status: CaptureStatus,
+    // Holding team, when held
+    pub team_id: Option<Uuid>,
+}
+
+// Points earned by a team from a capture zone it held
+#[derive(Debug, Clone, Copy)]
+pub struct CaptureAward {
+    pub team_id: Uuid,
+    pub points: i32,
+}
+
+struct Holding {
+    status: CaptureStatus,
+    team_id: Option<Uuid>,
+    // Time held since the holder last scored
+    held_for: Duration,
+}
+
+// Capture zones of one match, evaluated every tick
+pub struct CaptureTracker {
+    zones: Vec<CaptureZone>,
+    holdings: HashMap<String, Holding>,
+    // Objective points per team so far
+    points: HashMap<Uuid, i32>,
+}
+
+impl CaptureTracker {
+    pub fn new(zones: Vec<CaptureZone>, points: HashMap<Uuid, i32>) -> Self {
+        let holdings = zones
+            .iter()
+            .map(|zone| (zone.id.clone(), Holding { status: CaptureStatus::Neutral, team_id: None, held_for: Duration::ZERO }))
+            .collect();
+        Self { zones, holdings, points }
+    }
+
+    pub fn points(&self) -> &HashMap<Uuid, i32> {
+        &self.points
+    }
+
+    // Recount who is inside each zone after `period` of play. The team with
+    // the most players inside holds a zone, and scores its points for every
+    // full `interval` it keeps holding it. Returns the zones that changed
+    // status or holder, and the points awarded.
+    pub fn evaluate(
+        &mut self,
+        match_id: Uuid,
+        positions: &HashMap<Uuid, PlayerPosition>,
+        team_of: &HashMap<Uuid, Uuid>,
+        period: Duration,
+        interval: Duration,
+    ) -> (Vec<CaptureUpdate>, Vec<CaptureAward>) {
+        let mut updates = Vec::new();
+        let mut awards = Vec::new();
+        for zone in &self.zones {
+            let mut present: HashMap<Uuid, usize> = HashMap::new();
+            for (user_id, pos) in positions {
+                if let Some(team_id) = team_of.get(user_id).filter(|_| zone.contains(pos)) {
+                    *present.entry(*team_id).or_default() += 1;
+                }
+            }
+            let most = present.values().copied().max().unwrap_or(0);
+            let leaders: Vec<Uuid> = present.iter().filter(|(_, n)| **n == most).map(|(team, _)| *team).collect();
+            let (status, team_id) = match leaders.as_slice() {
+                [] => (CaptureStatus::Neutral, None),
+                [team] => (CaptureStatus::Held, Some(*team)),
+                _ => (CaptureStatus::Contested, None),
+            };
+
+            let Some(holding) = self.holdings.get_mut(&zone.id) else {
+                continue;
+            };
+            if holding.status != status || holding.team_id != team_id {
+                *holding = Holding { status, team_id, held_for: Duration::ZERO };
+                updates.push(CaptureUpdate { match_id, capture_zone_id: zone.id.clone(), status, team_id });
+            }
+
+            let Some(team_id) = team_id else {
+                continue;
+            };
+            holding.held_for += period;
+            while !interval.is_zero() && holding.held_for >= interval {
+                holding.held_for -= interval;
+                if zone.points > 0 {
+                    *self.points.entry(team_id).or_default() += zone.points;
+                    awards.push(CaptureAward { team_id, points: zone.points });
+                }
+            }
+        }
+        (updates, awards)
+    }
+}
+
+// CAPTURE_ZONES_FILE: a JSON array of capture zones, read once at startup.
+// Invalid zones are skipped.
+pub fn load_zones(path: &str) -> Vec<CaptureZone> {
+    let zones: Vec<CaptureZone> = match std::fs::read_to_string(path)
+        .map_err(|e| e.to_string())
+        .and_then(|text| serde_json::from_str(&text).map_err(|e| e.to_string()))
+    {
+        Ok(zones) => zones,
+        Err(e) => {
+            tracing::error!("Failed to load capture zones from {}: {}", path, e);
+            return Vec::new();
+        }
+    };
+    let valid: Vec<CaptureZone> = zones
+        .into_iter()
+        .filter(|zone| {
+            let valid = zone.is_valid();
+            if !valid {
+                tracing::warn!("Ignoring invalid capture zone definition: {}", zone.id);
+            }
+            valid
+        })
+        .collect();
+    tracing::info!("Loaded {} capture zones", valid.len());
+    valid
+}
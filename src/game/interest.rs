@@ -0,0 +1,100 @@
+use std::collections::HashMap;
+
+use uuid::Uuid;
+
+use crate::models::game::PlayerPosition;
+
+// Which other players' positions a player receives
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum InterestPolicy {
+    // Everyone in the match
+    All,
+    // Own team only
+    Teammates,
+    // Anyone within the radius
+    Radius(f32),
+    // Own team, plus opponents within the radius
+    TeammatesAndRadius(f32),
+}
+
+impl InterestPolicy {
+    // "all", "teammates", "radius:150" or "teammates+radius:150"
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s.trim() {
+            "all" => Some(InterestPolicy::All),
+            "teammates" => Some(InterestPolicy::Teammates),
+            other => {
+                if let Some(radius) = other.strip_prefix("teammates+radius:") {
+                    radius.parse().ok().map(InterestPolicy::TeammatesAndRadius)
+                } else if let Some(radius) = other.strip_prefix("radius:") {
+                    radius.parse().ok().map(InterestPolicy::Radius)
+                } else {
+                    None
+                }
+            }
+        }
+    }
+
+    // Whether a viewer receives the target's position. Players always see themselves.
+    pub fn can_see(
+        &self,
+        viewer_team: Uuid,
+        viewer_pos: Option<&PlayerPosition>,
+        target_team: Uuid,
+        target_pos: &PlayerPosition,
+    ) -> bool {
+        let within = |radius: f32| {
+            viewer_pos.is_some_and(|pos| {
+                let dx = pos.x - target_pos.x;
+                let dy = pos.y - target_pos.y;
+                dx * dx + dy * dy <= radius * radius
+            })
+        };
+        match *self {
+            InterestPolicy::All => true,
+            InterestPolicy::Teammates => viewer_team == target_team,
+            InterestPolicy::Radius(radius) => within(radius),
+            InterestPolicy::TeammatesAndRadius(radius) => viewer_team == target_team || within(radius),
+        }
+    }
+}
+
+// Interest policy per match type, with a default for unlisted types
+#[derive(Debug, Clone)]
+pub struct InterestConfig {
+    pub default: InterestPolicy,
+    pub by_match_type: HashMap<String, InterestPolicy>,
+}
+
+impl Default for InterestConfig {
+    fn default() -> Self {
+        Self {
+            default: InterestPolicy::All,
+            by_match_type: HashMap::new(),
+        }
+    }
+}
+
+impl InterestConfig {
+    // INTEREST_POLICY="5v5=teammates+radius:150,2v2=all,*=all"
+    pub fn from_str(s: &str) -> Self {
+        let mut config = Self::default();
+        for entry in s.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            let parsed = entry
+                .split_once('=')
+                .and_then(|(match_type, policy)| Some((match_type.trim(), InterestPolicy::from_str(policy)?)));
+            match parsed {
+                Some(("*", policy)) => config.default = policy,
+                Some((match_type, policy)) => {
+                    config.by_match_type.insert(match_type.to_string(), policy);
+                }
+                None => tracing::warn!("Ignoring invalid interest policy: {}", entry),
+            }
+        }
+        config
+    }
+
+    pub fn policy_for(&self, match_type: &str) -> InterestPolicy {
+        self.by_match_type.get(match_type).copied().unwrap_or(self.default)
+    }
+}
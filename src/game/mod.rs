@@ -0,0 +1,6 @@
+pub mod capture;
+pub mod interest;
+pub mod respawn;
+pub mod rounds;
+pub mod runtime;
+pub mod victory;
\ No newline at end of file
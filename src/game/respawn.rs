@@ -0,0 +1,47 @@
+use rand::Rng;
+use rand::seq::SliceRandom;
+use uuid::Uuid;
+
+use crate::config::RespawnConfig;
+use crate::models::treasure::Treasure;
+use crate::models::zone::Zone;
+
+// Tries at finding a point inside the zone per spawned treasure
+const PLACEMENT_ATTEMPTS: usize = 20;
+
+// Treasures to spawn into a match with `active` undiscovered spawns.
+//
+// Each one copies a random active catalog treasure (name, score, rarity). In a
+// zone it is placed at a random point inside it, and the zone's
+// treasure_density caps how many spawns can be up at once; in the global pool
+// it respawns where the catalog treasure is.
+pub fn plan(config: &RespawnConfig, zone: Option<&Zone>, catalog: &[Treasure], active: usize, rng: &mut impl Rng) -> Vec<Treasure> {
+    let cap = match zone {
+        Some(zone) => config.max_active.min((zone.treasure_density * zone.area()).floor() as usize),
+        None => config.max_active,
+    };
+    let count = config.batch.min(cap.saturating_sub(active));
+
+    (0..count)
+        .filter_map(|_| {
+            let template = catalog.choose(rng)?;
+            let (x, y) = match zone {
+                Some(zone) => {
+                    let pos = zone.random_point(rng, PLACEMENT_ATTEMPTS)?;
+                    (pos.x, pos.y)
+                }
+                None => (template.x, template.y),
+            };
+            Some(Treasure {
+                id: Uuid::new_v4(),
+                name: template.name.clone(),
+                x,
+                y,
+                base_score: template.base_score,
+                rarity: template.rarity,
+                active_from: None,
+                active_until: None,
+            })
+        })
+        .collect()
+}
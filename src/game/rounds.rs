@@ -0,0 +1,110 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use chrono::Utc;
+use schemars::JsonSchema;
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::models::game::RoundResult;
+use super::victory::sole_best;
+
+// Round timer sent in game.tick during best_of matches
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct RoundClock {
+    // From 1
+    pub round: u32,
+    pub rounds: u32,
+    pub remaining_ms: u64,
+    // Rounds won so far per team
+    pub won: HashMap<Uuid, u32>,
+}
+
+// Rounds of one best_of match, evaluated every tick. Round n runs from
+// (n - 1) * round_length to n * round_length of match time, and is won by the
+// team that found the most points in it.
+pub struct RoundTracker {
+    rounds: u32,
+    round_length: Duration,
+    teams: Vec<Uuid>,
+    // Rounds already over, in order
+    results: Vec<RoundResult>,
+    // Each team's discovery points when the current round started
+    baseline: HashMap<Uuid, i32>,
+}
+
+impl RoundTracker {
+    // `results` are the rounds a match taken over from elsewhere already played
+    pub fn new(rounds: u32, round_length: Duration, teams: Vec<Uuid>, results: Vec<RoundResult>) -> Self {
+        let mut baseline: HashMap<Uuid, i32> = teams.iter().map(|team| (*team, 0)).collect();
+        for result in &results {
+            for (team, points) in &result.scores {
+                *baseline.entry(*team).or_default() += points;
+            }
+        }
+        Self { rounds, round_length, teams, results, baseline }
+    }
+
+    pub fn results(&self) -> &[RoundResult] {
+        &self.results
+    }
+
+    pub fn clock(&self, elapsed: Duration) -> RoundClock {
+        let round = (self.results.len() as u32 + 1).min(self.rounds);
+        RoundClock {
+            round,
+            rounds: self.rounds,
+            remaining_ms: (self.round_length * round).saturating_sub(elapsed).as_millis() as u64,
+            won: self.won(),
+        }
+    }
+
+    // End the current round once its time is up, or right away when the
+    // match is; `scores` are each team's discovery points so far
+    pub fn evaluate(&mut self, match_id: Uuid, elapsed: Duration, scores: &HashMap<Uuid, i32>, time_up: bool) -> Option<RoundResult> {
+        if self.is_over() {
+            return None;
+        }
+        let round = self.results.len() as u32 + 1;
+        if elapsed < self.round_length * round && !time_up {
+            return None;
+        }
+
+        let tally: HashMap<Uuid, i32> = self.teams
+            .iter()
+            .map(|team| {
+                let now = scores.get(team).copied().unwrap_or(0);
+                (*team, now - self.baseline.get(team).copied().unwrap_or(0))
+            })
+            .collect();
+        let result = RoundResult {
+            match_id,
+            round_number: round as i32,
+            winner_team_id: sole_best(tally.iter().map(|(team, points)| (*team, *points))),
+            scores: tally,
+            ended_at: Utc::now(),
+        };
+        self.baseline = self.teams.iter().map(|team| (*team, scores.get(team).copied().unwrap_or(0))).collect();
+        self.results.push(result.clone());
+        Some(result)
+    }
+
+    // Team that has won more than half of the rounds, which no other team
+    // can catch up with
+    pub fn decided(&self) -> Option<Uuid> {
+        self.won().into_iter().find(|(_, won)| *won > self.rounds / 2).map(|(team, _)| team)
+    }
+
+    // Every round played, or the match decided before the last one
+    pub fn is_over(&self) -> bool {
+        self.results.len() as u32 >= self.rounds || self.decided().is_some()
+    }
+
+    fn won(&self) -> HashMap<Uuid, u32> {
+        let mut won: HashMap<Uuid, u32> = self.teams.iter().map(|team| (*team, 0)).collect();
+        for team in self.results.iter().filter_map(|result| result.winner_team_id) {
+            *won.entry(team).or_default() += 1;
+        }
+        won
+    }
+}
@@ -0,0 +1,609 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use chrono::Utc;
+use rand::thread_rng;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use tokio::sync::{RwLock, broadcast};
+use tokio::task::JoinHandle;
+use uuid::Uuid;
+
+use crate::config::GameConfig;
+use crate::error::{Error, Result};
+use crate::matchmaking::events::{MatchEvent, Published};
+use crate::matchmaking::service::MatchService;
+use crate::models::game::{MatchDetails, PlayerPosition, RoundResult, TeamAssignment};
+use crate::models::treasure::Treasure;
+use crate::supervisor;
+use super::capture::{self, CaptureTracker, CaptureZone};
+use super::interest::InterestPolicy;
+use super::respawn;
+use super::rounds::{RoundClock, RoundTracker};
+use super::victory::{VictoryCondition, VictoryEvaluator};
+
+// Latest known position of a player, relayed in ticks
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct PositionUpdate {
+    pub user_id: Uuid,
+    pub team_id: Uuid,
+    pub x: f32,
+    pub y: f32,
+}
+
+// Opponents within the proximity radius of a player
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct ProximityHint {
+    pub user_id: Uuid,
+    pub nearby_opponents: Vec<Uuid>,
+}
+
+// One consolidated update per player per tick
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct GameTick {
+    pub match_id: Uuid,
+    pub tick: u64,
+    pub elapsed_ms: u64,
+    // Absent when matches have no time limit
+    pub remaining_ms: Option<u64>,
+    // Players that moved since the previous tick and that the recipient may see
+    pub positions: Vec<PositionUpdate>,
+    // The recipient's own hint, if any
+    pub hints: Vec<ProximityHint>,
+    // Capture zone points per team so far; absent without capture zones
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    pub objective_points: HashMap<Uuid, i32>,
+    // Round timer; absent unless the match is best_of
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub round: Option<RoundClock>,
+}
+
+impl GameTick {
+    // Fold a later tick into this one for connections that skip ticks:
+    // the latest position per player wins, timers and hints come from `newer`
+    pub fn merged(mut self, newer: GameTick) -> GameTick {
+        if self.match_id != newer.match_id {
+            return newer;
+        }
+        self.positions.retain(|old| !newer.positions.iter().any(|p| p.user_id == old.user_id));
+        self.positions.extend(newer.positions);
+        GameTick {
+            positions: self.positions,
+            ..newer
+        }
+    }
+}
+
+// All views of one tick, keyed by the receiving player
+#[derive(Debug, Clone)]
+pub struct MatchTick {
+    pub match_id: Uuid,
+    pub views: HashMap<Uuid, GameTick>,
+}
+
+// A position to relay right away (tick loop disabled) and who may see it
+#[derive(Debug, Clone)]
+pub struct PositionRelay {
+    pub update: PositionUpdate,
+    pub recipients: Vec<Uuid>,
+}
+
+// Enough of a running match to resume its loop elsewhere
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MatchSnapshot {
+    pub match_id: Uuid,
+    pub match_type: String,
+    pub teams: Vec<TeamAssignment>,
+    pub elapsed_ms: u64,
+    pub positions: HashMap<Uuid, PlayerPosition>,
+    #[serde(default)]
+    pub zone_id: Option<String>,
+    // Spawned treasures not discovered yet
+    #[serde(default)]
+    pub spawned: Vec<Treasure>,
+    // Capture zone points per team
+    #[serde(default)]
+    pub objective_points: HashMap<Uuid, i32>,
+    // Discovery points per team
+    #[serde(default)]
+    pub scores: HashMap<Uuid, i32>,
+    #[serde(default)]
+    pub victory: VictoryCondition,
+    // Rounds already played
+    #[serde(default)]
+    pub rounds: Vec<RoundResult>,
+}
+
+// How far a match taken over from elsewhere has got
+#[derive(Default)]
+struct MatchProgress {
+    // How long the match has already been running
+    elapsed: Duration,
+    scores: HashMap<Uuid, i32>,
+    objective_points: HashMap<Uuid, i32>,
+    rounds: Vec<RoundResult>,
+}
+
+struct ActiveMatch {
+    match_type: String,
+    zone_id: Option<String>,
+    teams: Vec<TeamAssignment>,
+    started_at: Instant,
+    tick: u64,
+    team_of: HashMap<Uuid, Uuid>,
+    policy: InterestPolicy,
+    positions: HashMap<Uuid, PlayerPosition>,
+    // Players whose position changed since the last tick
+    moved: Vec<Uuid>,
+    // Match time of the last treasure spawn
+    last_spawn: Duration,
+    capture: CaptureTracker,
+    victory: VictoryCondition,
+    evaluator: Box<dyn VictoryEvaluator>,
+    // Discovery points per team, from the discoveries recorded on this node
+    scores: HashMap<Uuid, i32>,
+    // None unless the match is best_of
+    rounds: Option<RoundTracker>,
+    // None when the tick loop is disabled
+    task: Option<JoinHandle<()>>,
+}
+
+// Fixed-tick loop for every match in progress.
+//
+// Positions submitted between ticks are batched (only the latest one per
+// player is kept) and sent together with proximity hints and the match timer
+// as a single GameTick. Each player gets their own view, filtered by the
+// interest policy of the match type. Matches that run out of time are ended here.
+//
+// Every TREASURE_RESPAWN_SECS, once a match has run TREASURE_RESPAWN_AFTER_SECS,
+// the loop also spawns new treasures into it (see respawn::plan), and the
+// capture zones of the match are recounted (see CaptureTracker). A victory
+// condition that can be met early (first_to) ends the match once it is.
+// best_of matches are played in rounds with a timer of their own (see
+// RoundTracker), and end after the last round or once a team can't be caught.
+//
+// With GAME_TICK_HZ=0 there is no loop: matches are still tracked, but every
+// position is handed back to the caller to relay immediately.
+pub struct GameRuntime {
+    config: GameConfig,
+    match_service: Arc<MatchService>,
+    matches: RwLock<HashMap<Uuid, ActiveMatch>>,
+    ticks: broadcast::Sender<MatchTick>,
+    // From CAPTURE_ZONES_FILE
+    capture_zones: Vec<CaptureZone>,
+}
+
+impl GameRuntime {
+    pub fn new(config: GameConfig, match_service: Arc<MatchService>) -> Arc<Self> {
+        let (ticks, _) = broadcast::channel(1024);
+        let capture_zones = config.capture.file.as_deref().map(capture::load_zones).unwrap_or_default();
+        Arc::new(Self {
+            config,
+            match_service,
+            matches: RwLock::new(HashMap::new()),
+            ticks,
+            capture_zones,
+        })
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.config.tick_hz > 0
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<MatchTick> {
+        self.ticks.subscribe()
+    }
+
+    // Start and stop match loops following the match lifecycle
+    pub fn spawn_event_listener(self: Arc<Self>, mut events: broadcast::Receiver<Published>) {
+        tokio::spawn(async move {
+            loop {
+                match events.recv().await.map(|published| published.event) {
+                    // A panic handling one event skips it; the subscription stays
+                    Ok(event) => {
+                        supervisor::catch("game_runtime_events", self.on_event(event)).await;
+                    }
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        tracing::warn!("Game runtime lagged, skipped {} match events", skipped);
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                }
+            }
+        });
+    }
+
+    async fn on_event(self: &Arc<Self>, event: MatchEvent) {
+        match event {
+            MatchEvent::MatchStarted { room, teams } => {
+                let victory = self.config.victory.condition_for(&room.match_type);
+                self.clone()
+                    .start(room.match_id, &room.match_type, room.zone_id.clone(), &teams, victory, MatchProgress::default())
+                    .await;
+            }
+            MatchEvent::DiscoveryRecorded { discovery } => {
+                if let Some(active) = self.matches.write().await.get_mut(&discovery.match_id) {
+                    *active.scores.entry(discovery.team_id).or_default() += discovery.score;
+                }
+            }
+            MatchEvent::MatchEnded { match_id } => self.stop(match_id).await,
+            _ => {}
+        }
+    }
+
+    // Take over the loop of a match adopted from another node, keeping its clock
+    pub async fn resume(self: Arc<Self>, details: &MatchDetails) {
+        let teams: Vec<TeamAssignment> = details.teams
+            .iter()
+            .map(|team| TeamAssignment {
+                team_id: team.id,
+                team_number: team.team_number,
+                players: team.members.iter().map(|m| m.user_id).collect(),
+            })
+            .collect();
+        let progress = MatchProgress {
+            elapsed: details.start_time
+                .and_then(|start| (Utc::now() - start).to_std().ok())
+                .unwrap_or_default(),
+            scores: details.teams.iter().map(|team| (team.id, team.total_score)).collect(),
+            objective_points: details.teams.iter().map(|team| (team.id, team.objective_score)).collect(),
+            rounds: details.rounds.clone(),
+        };
+        self.start(details.id, &details.match_type, None, &teams, details.victory_condition, progress).await;
+    }
+
+    async fn start(
+        self: Arc<Self>,
+        match_id: Uuid,
+        match_type: &str,
+        zone_id: Option<String>,
+        teams: &[TeamAssignment],
+        victory: VictoryCondition,
+        progress: MatchProgress,
+    ) {
+        let capture_zones = self.capture_zones
+            .iter()
+            .filter(|zone| zone.applies_to(match_type, zone_id.as_deref()))
+            .cloned()
+            .collect();
+        let team_of = teams
+            .iter()
+            .flat_map(|team| team.players.iter().map(move |player| (*player, team.team_id)))
+            .collect();
+        let rounds = victory.rounds(self.config.match_duration).map(|(rounds, round_length)| {
+            RoundTracker::new(rounds, round_length, teams.iter().map(|team| team.team_id).collect(), progress.rounds)
+        });
+
+        // Hold the lock until the match is registered so the first tick finds it
+        let mut matches = self.matches.write().await;
+
+        let task = self.is_enabled().then(|| {
+            let runtime = self.clone();
+            let period = Duration::from_secs(1) / self.config.tick_hz;
+            // Restarted after a panic; the match state lives in `matches`
+            supervisor::spawn_loop("match_tick", move || {
+                let runtime = runtime.clone();
+                async move {
+                    let mut interval = tokio::time::interval(period);
+                    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+                    loop {
+                        interval.tick().await;
+                        if !runtime.tick(match_id).await {
+                            break;
+                        }
+                    }
+                }
+            })
+        });
+
+        if let Some(previous) = matches.insert(match_id, ActiveMatch {
+            match_type: match_type.to_string(),
+            zone_id,
+            teams: teams.to_vec(),
+            started_at: Instant::now().checked_sub(progress.elapsed).unwrap_or_else(Instant::now),
+            tick: 0,
+            team_of,
+            policy: self.config.interest.policy_for(match_type),
+            positions: HashMap::new(),
+            moved: Vec::new(),
+            last_spawn: Duration::ZERO,
+            capture: CaptureTracker::new(capture_zones, progress.objective_points),
+            victory,
+            evaluator: victory.evaluator(self.config.match_duration),
+            scores: progress.scores,
+            rounds,
+            task,
+        }) && let Some(task) = previous.task {
+            task.abort();
+        }
+        if self.is_enabled() {
+            tracing::info!("Game loop started for match {} at {} Hz", match_id, self.config.tick_hz);
+        }
+    }
+
+    // Every running match, for replication to a standby
+    pub async fn snapshot(&self) -> Vec<MatchSnapshot> {
+        let mut snapshots: Vec<MatchSnapshot> = self.matches.read().await
+            .iter()
+            .map(|(match_id, active)| MatchSnapshot {
+                match_id: *match_id,
+                match_type: active.match_type.clone(),
+                teams: active.teams.clone(),
+                elapsed_ms: active.started_at.elapsed().as_millis() as u64,
+                positions: active.positions.clone(),
+                zone_id: active.zone_id.clone(),
+                spawned: Vec::new(),
+                objective_points: active.capture.points().clone(),
+                scores: active.scores.clone(),
+                victory: active.victory,
+                rounds: active.rounds.as_ref().map(|rounds| rounds.results().to_vec()).unwrap_or_default(),
+            })
+            .collect();
+        for snapshot in &mut snapshots {
+            snapshot.spawned = self.match_service.catalog().spawned(snapshot.match_id).await;
+        }
+        snapshots
+    }
+
+    // Resume a match from a snapshot taken `age` ago, with its last known positions
+    pub async fn restore(self: Arc<Self>, snapshot: MatchSnapshot, age: Duration) {
+        let match_id = snapshot.match_id;
+        let progress = MatchProgress {
+            elapsed: Duration::from_millis(snapshot.elapsed_ms) + age,
+            scores: snapshot.scores.clone(),
+            objective_points: snapshot.objective_points.clone(),
+            rounds: snapshot.rounds.clone(),
+        };
+        self.clone().start(
+            match_id,
+            &snapshot.match_type,
+            snapshot.zone_id.clone(),
+            &snapshot.teams,
+            snapshot.victory,
+            progress,
+        ).await;
+        self.match_service.catalog().spawn(match_id, &snapshot.spawned).await;
+        if let Some(active) = self.matches.write().await.get_mut(&match_id) {
+            active.moved = snapshot.positions.keys().copied().collect();
+            active.positions = snapshot.positions;
+        }
+    }
+
+    pub async fn stop(&self, match_id: Uuid) {
+        self.match_service.catalog().clear_spawned(match_id).await;
+        if let Some(task) = self.matches.write().await.remove(&match_id).and_then(|active| active.task) {
+            task.abort();
+            tracing::info!("Game loop stopped for match {}", match_id);
+        }
+    }
+
+    // Queue a position for the next tick. Without a tick loop the update is
+    // returned instead, for the caller to relay right away.
+    pub async fn submit_position(&self, match_id: Uuid, user_id: Uuid, position: PlayerPosition) -> Result<Option<PositionRelay>> {
+        let mut matches = self.matches.write().await;
+        let active = matches.get_mut(&match_id).ok_or(Error::MatchNotReady)?;
+        let team_id = *active.team_of.get(&user_id)
+            .ok_or_else(|| Error::PermissionDenied("not a player in this match".to_string()))?;
+
+        active.positions.insert(user_id, position.clone());
+
+        if !self.is_enabled() {
+            let recipients = active
+                .team_of
+                .iter()
+                .filter(|(viewer, viewer_team)| {
+                    active.policy.can_see(**viewer_team, active.positions.get(*viewer), team_id, &position)
+                })
+                .map(|(viewer, _)| *viewer)
+                .collect();
+            return Ok(Some(PositionRelay {
+                update: PositionUpdate {
+                    user_id,
+                    team_id,
+                    x: position.x,
+                    y: position.y,
+                },
+                recipients,
+            }));
+        }
+
+        if !active.moved.contains(&user_id) {
+            active.moved.push(user_id);
+        }
+        Ok(None)
+    }
+
+    // Run one tick; returns false once the match loop should stop
+    async fn tick(&self, match_id: Uuid) -> bool {
+        let (tick, time_up, decided, round_ended, rounds_over, respawn_in, captures, awards) = {
+            let mut matches = self.matches.write().await;
+            let Some(active) = matches.get_mut(&match_id) else {
+                return false;
+            };
+            active.tick += 1;
+
+            let elapsed = active.started_at.elapsed();
+            let remaining = self.config.match_duration.map(|limit| limit.saturating_sub(elapsed));
+            let respawn_due = self.config.respawn.interval.is_some_and(|interval| {
+                elapsed >= self.config.respawn.after && elapsed.saturating_sub(active.last_spawn) >= interval
+            });
+            if respawn_due {
+                active.last_spawn = elapsed;
+            }
+
+            let moved: Vec<PositionUpdate> = active
+                .moved
+                .drain(..)
+                .filter_map(|user_id| {
+                    let pos = active.positions.get(&user_id)?;
+                    Some(PositionUpdate {
+                        user_id,
+                        team_id: *active.team_of.get(&user_id)?,
+                        x: pos.x,
+                        y: pos.y,
+                    })
+                })
+                .collect();
+            let mut hints = self.proximity_hints(active);
+            let period = Duration::from_secs(1) / self.config.tick_hz;
+            let (captures, awards) = active.capture.evaluate(
+                match_id,
+                &active.positions,
+                &active.team_of,
+                period,
+                self.config.capture.interval,
+            );
+            let objective_points = active.capture.points().clone();
+            let mut points = active.scores.clone();
+            for (team, objective) in &objective_points {
+                *points.entry(*team).or_default() += objective;
+            }
+            let decided = active.evaluator.decided(&points);
+            let time_up = remaining.is_some_and(|r| r.is_zero());
+            let round_ended = active.rounds
+                .as_mut()
+                .and_then(|rounds| rounds.evaluate(match_id, elapsed, &active.scores, time_up));
+            let round = active.rounds.as_ref().map(|rounds| rounds.clock(elapsed));
+
+            // Apply the interest policy per receiving player
+            let views = active
+                .team_of
+                .iter()
+                .map(|(viewer, viewer_team)| {
+                    let viewer_pos = active.positions.get(viewer);
+                    let positions = moved
+                        .iter()
+                        .filter(|update| {
+                            let target = PlayerPosition { x: update.x, y: update.y };
+                            active.policy.can_see(*viewer_team, viewer_pos, update.team_id, &target)
+                        })
+                        .cloned()
+                        .collect();
+                    let view = GameTick {
+                        match_id,
+                        tick: active.tick,
+                        elapsed_ms: elapsed.as_millis() as u64,
+                        remaining_ms: remaining.map(|r| r.as_millis() as u64),
+                        positions,
+                        hints: hints.remove(viewer).into_iter().collect(),
+                        objective_points: objective_points.clone(),
+                        round: round.clone(),
+                    };
+                    (*viewer, view)
+                })
+                .collect();
+            (
+                MatchTick { match_id, views },
+                time_up,
+                decided,
+                round_ended,
+                active.rounds.as_ref().is_some_and(|rounds| rounds.is_over()),
+                respawn_due.then(|| active.zone_id.clone()),
+                captures,
+                awards,
+            )
+        };
+
+        // No subscribers is not an error
+        let _ = self.ticks.send(tick);
+
+        // Points first, so they are in before the match can be ended below
+        for award in awards {
+            if let Err(e) = self.match_service.award_objective(match_id, award.team_id, award.points).await {
+                tracing::warn!("Failed to record {} objective points for team {}: {}", award.points, award.team_id, e);
+            }
+        }
+        for update in captures {
+            self.match_service.capture_changed(update);
+        }
+        if let Some(round) = round_ended {
+            let round_number = round.round_number;
+            if let Err(e) = self.match_service.round_ended(round).await {
+                tracing::warn!("Failed to record round {} of match {}: {}", round_number, match_id, e);
+            }
+        }
+
+        if time_up {
+            tracing::info!("Match {} ran out of time", match_id);
+            self.matches.write().await.remove(&match_id);
+            if let Err(e) = self.match_service.end_match(match_id).await {
+                tracing::error!("Failed to end match {} after time limit: {}", match_id, e);
+            }
+            return false;
+        }
+        if rounds_over {
+            tracing::info!("Match {} played its last round", match_id);
+            self.matches.write().await.remove(&match_id);
+            if let Err(e) = self.match_service.end_match(match_id).await {
+                tracing::error!("Failed to end match {} after its last round: {}", match_id, e);
+            }
+            return false;
+        }
+        if let Some(team_id) = decided {
+            tracing::info!("Match {} decided early in favor of team {}", match_id, team_id);
+            self.matches.write().await.remove(&match_id);
+            if let Err(e) = self.match_service.end_match(match_id).await {
+                tracing::error!("Failed to end decided match {}: {}", match_id, e);
+            }
+            return false;
+        }
+        if let Some(zone_id) = respawn_in {
+            self.respawn(match_id, zone_id).await;
+        }
+        true
+    }
+
+    // Spawn treasures into the match, within its zone if it has one
+    async fn respawn(&self, match_id: Uuid, zone_id: Option<String>) {
+        let zone = match self.match_service.zones().resolve(zone_id.as_deref(), None).await {
+            Ok(zone) => zone,
+            Err(e) => {
+                tracing::warn!("No treasures spawned in match {}: {}", match_id, e);
+                return;
+            }
+        };
+        let catalog = self.match_service.catalog();
+        let templates = catalog.list(true).await;
+        let active = catalog.spawned(match_id).await.len();
+        let treasures = respawn::plan(&self.config.respawn, zone.as_ref(), &templates, active, &mut thread_rng());
+        if treasures.is_empty() {
+            return;
+        }
+        tracing::debug!("Spawned {} treasures in match {}", treasures.len(), match_id);
+        self.match_service.spawn_treasures(match_id, treasures).await;
+    }
+
+    fn proximity_hints(&self, active: &ActiveMatch) -> HashMap<Uuid, ProximityHint> {
+        let radius_sq = self.config.proximity_radius * self.config.proximity_radius;
+        active
+            .positions
+            .iter()
+            .filter_map(|(user_id, pos)| {
+                let team = active.team_of.get(user_id)?;
+                let nearby_opponents: Vec<Uuid> = active
+                    .positions
+                    .iter()
+                    .filter(|(other, other_pos)| {
+                        active.team_of.get(*other) != Some(team)
+                            && distance_sq(pos, other_pos) <= radius_sq
+                    })
+                    .map(|(other, _)| *other)
+                    .collect();
+                if nearby_opponents.is_empty() {
+                    None
+                } else {
+                    Some((*user_id, ProximityHint {
+                        user_id: *user_id,
+                        nearby_opponents,
+                    }))
+                }
+            })
+            .collect()
+    }
+}
+
+fn distance_sq(a: &PlayerPosition, b: &PlayerPosition) -> f32 {
+    let dx = a.x - b.x;
+    let dy = a.y - b.y;
+    dx * dx + dy * dy
+}
@@ -0,0 +1,206 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::models::game::MatchScores;
+
+// How the winner of a match is decided. Recorded on the match when it starts.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum VictoryCondition {
+    // Highest total_score plus objective_score
+    #[default]
+    HighestScore,
+    // The match ends as soon as a team has this many points
+    FirstTo { points: i32 },
+    // Most valid discoveries; score breaks ties
+    MostTreasures,
+    // The match time is split into this many rounds, each won by the team
+    // scoring the most discovery points in it; most rounds won wins
+    BestOf { rounds: u32 },
+}
+
+impl VictoryCondition {
+    // "highest_score", "first_to:500", "most_treasures" or "best_of:3"
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s.trim() {
+            "highest_score" => Some(VictoryCondition::HighestScore),
+            "most_treasures" => Some(VictoryCondition::MostTreasures),
+            other => {
+                if let Some(points) = other.strip_prefix("first_to:") {
+                    points.parse().ok().filter(|p| *p > 0).map(|points| VictoryCondition::FirstTo { points })
+                } else if let Some(rounds) = other.strip_prefix("best_of:") {
+                    rounds.parse().ok().filter(|r| *r > 0).map(|rounds| VictoryCondition::BestOf { rounds })
+                } else {
+                    None
+                }
+            }
+        }
+    }
+
+    // Same syntax as from_str
+    #[cfg_attr(not(feature = "grpc"), allow(dead_code))]
+    pub fn label(&self) -> String {
+        match self {
+            VictoryCondition::HighestScore => "highest_score".to_string(),
+            VictoryCondition::FirstTo { points } => format!("first_to:{}", points),
+            VictoryCondition::MostTreasures => "most_treasures".to_string(),
+            VictoryCondition::BestOf { rounds } => format!("best_of:{}", rounds),
+        }
+    }
+
+    // Number and length of the rounds: those of best_of split
+    // `match_duration` evenly. Without a match duration there are no rounds.
+    pub fn rounds(&self, match_duration: Option<Duration>) -> Option<(u32, Duration)> {
+        match (*self, match_duration) {
+            (VictoryCondition::BestOf { rounds }, Some(duration)) => Some((rounds, duration / rounds)),
+            _ => None,
+        }
+    }
+
+    // best_of without rounds falls back to the highest score
+    pub fn evaluator(&self, match_duration: Option<Duration>) -> Box<dyn VictoryEvaluator> {
+        match *self {
+            VictoryCondition::HighestScore => Box::new(HighestScore),
+            VictoryCondition::FirstTo { points } => Box::new(FirstTo { points }),
+            VictoryCondition::MostTreasures => Box::new(MostTreasures),
+            VictoryCondition::BestOf { .. } => match self.rounds(match_duration) {
+                Some((rounds, round_length)) => Box::new(BestOf { rounds, round_length }),
+                None => Box::new(HighestScore),
+            },
+        }
+    }
+}
+
+// Decides the winner of a match
+pub trait VictoryEvaluator: Send + Sync {
+    // Winner of a match that is over; None is a draw
+    fn winner(&self, scores: &MatchScores) -> Option<Uuid>;
+
+    // Winner already decided while the match runs, from each team's points
+    // so far; the match is then ended early
+    fn decided(&self, _points: &HashMap<Uuid, i32>) -> Option<Uuid> {
+        None
+    }
+}
+
+struct HighestScore;
+
+impl VictoryEvaluator for HighestScore {
+    fn winner(&self, scores: &MatchScores) -> Option<Uuid> {
+        sole_best(scores.teams.iter().map(|team| (team.team_id, team.total_score + team.objective_score)))
+    }
+}
+
+struct FirstTo {
+    points: i32,
+}
+
+impl VictoryEvaluator for FirstTo {
+    // The match stops at the first team to get there, so that team has the
+    // highest score; a match that ran out of time first goes by score too
+    fn winner(&self, scores: &MatchScores) -> Option<Uuid> {
+        HighestScore.winner(scores)
+    }
+
+    fn decided(&self, points: &HashMap<Uuid, i32>) -> Option<Uuid> {
+        sole_best(points.iter().map(|(team, points)| (*team, *points))).filter(|team| points[team] >= self.points)
+    }
+}
+
+struct MostTreasures;
+
+impl VictoryEvaluator for MostTreasures {
+    fn winner(&self, scores: &MatchScores) -> Option<Uuid> {
+        let mut found: HashMap<Uuid, (usize, i32)> = scores.teams.iter().map(|team| (team.team_id, (0, 0))).collect();
+        for discovery in scores.discoveries.iter().filter(|d| !d.invalidated) {
+            let entry = found.entry(discovery.team_id).or_default();
+            entry.0 += 1;
+            entry.1 += discovery.score;
+        }
+        sole_best(found.into_iter())
+    }
+}
+
+struct BestOf {
+    rounds: u32,
+    round_length: Duration,
+}
+
+impl VictoryEvaluator for BestOf {
+    // From the rounds the game loop recorded; a match that ran without the
+    // loop has its discoveries split into rounds by time instead
+    fn winner(&self, scores: &MatchScores) -> Option<Uuid> {
+        if !scores.rounds.is_empty() {
+            let mut won: HashMap<Uuid, u32> = scores.teams.iter().map(|team| (team.team_id, 0)).collect();
+            for team in scores.rounds.iter().filter_map(|round| round.winner_team_id) {
+                *won.entry(team).or_default() += 1;
+            }
+            return sole_best(won.into_iter());
+        }
+        let Some(start_time) = scores.start_time else {
+            return HighestScore.winner(scores);
+        };
+        let round_ms = self.round_length.as_millis().max(1) as i64;
+
+        // Points per round per team; late discoveries count in the last round
+        let mut rounds: Vec<HashMap<Uuid, i32>> = vec![scores.teams.iter().map(|team| (team.team_id, 0)).collect(); self.rounds as usize];
+        for discovery in scores.discoveries.iter().filter(|d| !d.invalidated) {
+            let elapsed = (discovery.discovered_at - start_time).num_milliseconds().max(0);
+            let round = ((elapsed / round_ms) as usize).min(rounds.len() - 1);
+            *rounds[round].entry(discovery.team_id).or_default() += discovery.score;
+        }
+
+        let mut won: HashMap<Uuid, u32> = scores.teams.iter().map(|team| (team.team_id, 0)).collect();
+        for round in rounds {
+            if let Some(team) = sole_best(round.into_iter()) {
+                *won.entry(team).or_default() += 1;
+            }
+        }
+        sole_best(won.into_iter())
+    }
+}
+
+// The team with the highest value, unless it is tied
+pub fn sole_best<T: Ord + Copy>(values: impl Iterator<Item = (Uuid, T)>) -> Option<Uuid> {
+    let mut ranked: Vec<(Uuid, T)> = values.collect();
+    ranked.sort_by_key(|(_, value)| std::cmp::Reverse(*value));
+    match ranked.as_slice() {
+        [(team, _)] => Some(*team),
+        [(team, best), (_, second), ..] if best > second => Some(*team),
+        _ => None,
+    }
+}
+
+// Victory condition per match type, with a default for unlisted types
+#[derive(Debug, Clone, Default)]
+pub struct VictoryConfig {
+    pub default: VictoryCondition,
+    pub by_match_type: HashMap<String, VictoryCondition>,
+}
+
+impl VictoryConfig {
+    // VICTORY_CONDITIONS="5v5=first_to:500,2v2=best_of:3,*=highest_score"
+    pub fn from_str(s: &str) -> Self {
+        let mut config = Self::default();
+        for entry in s.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            let parsed = entry
+                .split_once('=')
+                .and_then(|(match_type, condition)| Some((match_type.trim(), VictoryCondition::from_str(condition)?)));
+            match parsed {
+                Some(("*", condition)) => config.default = condition,
+                Some((match_type, condition)) => {
+                    config.by_match_type.insert(match_type.to_string(), condition);
+                }
+                None => tracing::warn!("Ignoring invalid victory condition: {}", entry),
+            }
+        }
+        config
+    }
+
+    pub fn condition_for(&self, match_type: &str) -> VictoryCondition {
+        self.by_match_type.get(match_type).copied().unwrap_or(self.default)
+    }
+}
@@ -0,0 +1,267 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use axum::{
+    extract::{Query, State, WebSocketUpgrade, ws::{Message, WebSocket}},
+    http::{HeaderMap, header},
+    response::{IntoResponse, Response},
+};
+use futures_util::{SinkExt, StreamExt};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::sync::broadcast::{self, error::RecvError};
+use uuid::Uuid;
+
+use crate::AppState;
+use crate::api::admin::{self, end_running_match};
+use crate::api::admin_events::MatchEventSummary;
+use crate::error::{Error, Result};
+use crate::jobs::job::Job;
+use crate::matchmaking::service::QueueDepth;
+use crate::models::game::Platform;
+use crate::models::message::{ClientMessage, ServerMessage};
+use crate::moderation::ban::BanSpec;
+
+// Admin console channel at /ws/admin, for a web ops console that would
+// otherwise poll the REST routes.
+//
+// The token is sent as `Authorization: Bearer <ADMIN_TOKEN>` or, since
+// browsers can't set headers on a WebSocket, as `?token=`. Frames use the
+// player protocol's envelope: `console.*` events are pushed as they happen
+// (connections, failed commands, match events, bulk job progress, and
+// queue depths whenever they change), and commands are ClientMessages answered with a reply
+// echoing their msg_id. Everything is about the node serving the socket.
+
+// Ops events from the gateway, fanned out to every open console
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event", content = "data")]
+pub enum ConsoleEvent {
+    #[serde(rename = "console.connection_opened")]
+    ConnectionOpened {
+        conn_id: Uuid,
+        user_id: Uuid,
+        ip: String,
+        platform: Option<Platform>,
+    },
+    #[serde(rename = "console.connection_closed")]
+    ConnectionClosed {
+        conn_id: Uuid,
+        user_id: Uuid,
+    },
+    // A player command answered with an error
+    #[serde(rename = "console.command_failed")]
+    CommandFailed {
+        conn_id: Uuid,
+        user_id: Option<Uuid>,
+        cmd: Option<String>,
+        code: i32,
+        error: String,
+        correlation_id: Option<Uuid>,
+    },
+    #[serde(rename = "console.match_event")]
+    Match(MatchEventSummary),
+    #[serde(rename = "console.queues")]
+    Queues { pools: Vec<QueueDepth> },
+    // A bulk job started or progressed
+    #[serde(rename = "console.job")]
+    Job(Job),
+    // Events this console missed by reading too slowly
+    #[serde(rename = "console.lagged")]
+    Lagged { missed: u64 },
+}
+
+impl ConsoleEvent {
+    fn to_message(&self) -> serde_json::Result<ServerMessage> {
+        let mut tagged = serde_json::to_value(self)?;
+        let name = tagged["event"].as_str().unwrap_or_default().to_string();
+        Ok(ServerMessage::event(Uuid::new_v4(), &name, tagged["data"].take()))
+    }
+}
+
+#[derive(Clone)]
+pub struct ConsoleFeed {
+    sender: broadcast::Sender<ConsoleEvent>,
+}
+
+impl ConsoleFeed {
+    pub fn new(capacity: usize) -> Self {
+        let (sender, _) = broadcast::channel(capacity);
+        Self { sender }
+    }
+
+    pub fn publish(&self, event: ConsoleEvent) {
+        // No console open is the usual case
+        let _ = self.sender.send(event);
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<ConsoleEvent> {
+        self.sender.subscribe()
+    }
+}
+
+// How often queue depths are checked; they are only pushed when they change
+const QUEUE_INTERVAL: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Deserialize)]
+struct MatchRequest {
+    match_id: Uuid,
+}
+
+#[derive(Debug, Deserialize)]
+struct MaintenanceRequest {
+    enabled: bool,
+    message: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct BanRequest {
+    user_id: Uuid,
+    #[serde(flatten)]
+    spec: BanSpec,
+}
+
+#[derive(Debug, Deserialize)]
+struct UnbanRequest {
+    user_id: Uuid,
+    lifted_by: String,
+}
+
+pub async fn console_connect(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(params): Query<HashMap<String, String>>,
+    ws: WebSocketUpgrade,
+) -> Result<Response> {
+    let token = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .or(params.get("token").map(String::as_str))
+        .ok_or(Error::AuthError)?;
+    admin::verify_token(&state.config.admin, token)?;
+
+    tracing::info!("Admin console connected");
+    Ok(ws.on_upgrade(move |socket| serve(socket, state)).into_response())
+}
+
+async fn serve(socket: WebSocket, state: AppState) {
+    let (mut sender, mut receiver) = socket.split();
+    let mut feed = state.ws_handler.console.subscribe();
+    let mut events = state.events.subscribe();
+    let mut jobs = state.jobs.subscribe();
+    let mut queues = tokio::time::interval(QUEUE_INTERVAL);
+    let mut last_queues: Option<Vec<QueueDepth>> = None;
+
+    loop {
+        let outgoing = tokio::select! {
+            message = receiver.next() => match message {
+                Some(Ok(Message::Text(text))) => Some(command(&state, &text).await),
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                Some(Ok(_)) => None,
+            },
+            event = feed.recv() => match event {
+                Ok(event) => event.to_message().ok(),
+                Err(RecvError::Lagged(missed)) => ConsoleEvent::Lagged { missed }.to_message().ok(),
+                Err(RecvError::Closed) => break,
+            },
+            published = events.recv() => match published {
+                Ok(published) => ConsoleEvent::Match(MatchEventSummary::from(&published)).to_message().ok(),
+                Err(RecvError::Lagged(missed)) => ConsoleEvent::Lagged { missed }.to_message().ok(),
+                Err(RecvError::Closed) => break,
+            },
+            job = jobs.recv() => match job {
+                Ok(job) => ConsoleEvent::Job(job).to_message().ok(),
+                Err(RecvError::Lagged(missed)) => ConsoleEvent::Lagged { missed }.to_message().ok(),
+                Err(RecvError::Closed) => break,
+            },
+            _ = queues.tick() => {
+                let pools = state.match_service.queue_depths().await;
+                if last_queues.as_ref() == Some(&pools) {
+                    None
+                } else {
+                    last_queues = Some(pools.clone());
+                    ConsoleEvent::Queues { pools }.to_message().ok()
+                }
+            }
+        };
+
+        let Some(message) = outgoing else {
+            continue;
+        };
+        let Ok(text) = serde_json::to_string(&message) else {
+            continue;
+        };
+        if sender.send(Message::Text(text)).await.is_err() {
+            break;
+        }
+    }
+
+    tracing::info!("Admin console disconnected");
+}
+
+// Run one console command; failures are answered like player commands
+async fn command(state: &AppState, text: &str) -> ServerMessage {
+    let msg: ClientMessage = match serde_json::from_str(text) {
+        Ok(msg) => msg,
+        Err(_) => return reply(Uuid::new_v4(), Err(Error::InvalidMessage)),
+    };
+    let result = run(state, &msg.cmd, msg.data).await;
+    if let Err(e) = &result {
+        tracing::info!("Admin console command {} failed: {}", msg.cmd, e);
+    }
+    reply(msg.msg_id, result)
+}
+
+async fn run(state: &AppState, cmd: &str, data: Value) -> Result<Value> {
+    match cmd {
+        "console.matches" => to_data(&state.ws_handler.match_stats().await),
+        "console.connections" => to_data(&state.conn_manager.connection_infos().await),
+        "console.queues" => to_data(&state.match_service.queue_depths().await),
+        "console.end_match" => {
+            let request: MatchRequest = parse(data)?;
+            end_running_match(state, request.match_id).await?;
+            Ok(Value::Null)
+        }
+        // Without data, the current maintenance mode
+        "console.maintenance" if data.is_null() => to_data(&state.match_service.maintenance()),
+        "console.maintenance" => {
+            let request: MaintenanceRequest = parse(data)?;
+            let maintenance = state.match_service.set_maintenance(request.enabled, request.message);
+            tracing::info!("Maintenance mode {} from the admin console", if maintenance.enabled { "enabled" } else { "disabled" });
+            to_data(&maintenance)
+        }
+        "console.ban" => {
+            let request: BanRequest = parse(data)?;
+            to_data(&state.bans.issue(request.user_id, request.spec).await?)
+        }
+        "console.unban" => {
+            let request: UnbanRequest = parse(data)?;
+            state.bans.lift(request.user_id, &request.lifted_by).await?;
+            Ok(Value::Null)
+        }
+        _ => Err(Error::InvalidMessage),
+    }
+}
+
+fn reply(msg_id: Uuid, result: Result<Value>) -> ServerMessage {
+    let (code, data, error) = match result {
+        Ok(data) => (0, Some(data), None),
+        Err(e) => (e.code(), None, Some(e.to_string())),
+    };
+    ServerMessage {
+        msg_id,
+        event: None,
+        code,
+        data,
+        error,
+        correlation_id: None,
+    }
+}
+
+fn parse<T: for<'de> Deserialize<'de>>(data: Value) -> Result<T> {
+    serde_json::from_value(data).map_err(|_| Error::InvalidMessage)
+}
+
+fn to_data<T: Serialize>(payload: &T) -> Result<Value> {
+    serde_json::to_value(payload).map_err(|_| Error::InvalidMessage)
+}
@@ -0,0 +1,228 @@
+use std::collections::{HashMap, VecDeque};
+
+use serde_json::Value;
+use tokio::sync::{Mutex, Notify};
+use uuid::Uuid;
+
+use crate::correlation;
+use crate::memory;
+use crate::metrics::METRICS;
+use super::protocol::{self, ServerEvent, StateDelta};
+
+// Order in which queued messages are sent; lower goes first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Priority {
+    // Match found, started or ended
+    Critical = 0,
+    // Other match updates
+    Normal = 1,
+    // Live ops reports
+    Low = 2,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Target {
+    // Every connection in the match
+    Match(Uuid),
+    // One connection
+    Connection(Uuid),
+}
+
+#[derive(Debug, Clone)]
+pub struct Outgoing {
+    pub target: Target,
+    pub event: String,
+    pub data: Value,
+    // Also deliver to the match's connections on other nodes
+    pub publish: bool,
+    // Correlation id of the command that caused the message; merged messages keep the newest
+    pub correlation_id: Option<Uuid>,
+}
+
+impl Outgoing {
+    pub fn new(target: Target, event: &ServerEvent, publish: bool) -> serde_json::Result<Self> {
+        Ok(Self {
+            target,
+            event: event.name().to_string(),
+            data: event.data()?,
+            publish,
+            correlation_id: correlation::current(),
+        })
+    }
+}
+
+struct Queued {
+    priority: Priority,
+    message: Outgoing,
+}
+
+#[derive(Default)]
+struct Queues {
+    // Message ids per priority; ids of messages moved to a higher priority
+    // are left behind and skipped
+    pending: [VecDeque<u64>; 3],
+    messages: HashMap<u64, Queued>,
+    // Queued message each coalescable (target, event) is merged into
+    coalescing: HashMap<(Target, String), u64>,
+    next_id: u64,
+}
+
+// Outgoing fan-out waiting to be sent, highest priority first.
+//
+// A burst of events (hundreds of rooms filling at once when an event starts)
+// would otherwise be written to every connection as it happens. Instead the
+// handler drains this queue in batches, pacing between full batches. While a
+// match update waits, newer updates of the same match are merged into it:
+// state deltas are combined into one, live ops reports replace the older one.
+// Past MAX_FANOUT_QUEUE messages the oldest of the lowest priority is dropped
+// for each new one.
+pub struct FanoutQueue {
+    max: usize,
+    queues: Mutex<Queues>,
+    ready: Notify,
+}
+
+impl FanoutQueue {
+    pub fn new(max: usize) -> Self {
+        Self {
+            max,
+            queues: Mutex::new(Queues::default()),
+            ready: Notify::new(),
+        }
+    }
+
+    // Queue a message; returns true if it was merged into one already queued
+    pub async fn push(&self, priority: Priority, message: Outgoing) -> bool {
+        let merged = {
+            let mut guard = self.queues.lock().await;
+            let queues = &mut *guard;
+            let key = (message.target, message.event.clone());
+            let existing = coalesces(&message.event)
+                .then(|| queues.coalescing.get(&key).copied())
+                .flatten();
+
+            match existing {
+                Some(id) => {
+                    let Some(queued) = queues.messages.get_mut(&id) else {
+                        return false;
+                    };
+                    merge(&mut queued.message, message);
+                    // Keep merged updates in order by moving them up together
+                    if priority < queued.priority {
+                        queued.priority = priority;
+                        queues.pending[priority as usize].push_back(id);
+                    }
+                    true
+                }
+                None => {
+                    while queues.messages.len() >= self.max && queues.evict() {
+                        METRICS.record_memory_cap_hit(memory::FANOUT_QUEUE);
+                    }
+                    let id = queues.next_id;
+                    queues.next_id += 1;
+                    if coalesces(&message.event) {
+                        queues.coalescing.insert(key, id);
+                    }
+                    queues.messages.insert(id, Queued { priority, message });
+                    queues.pending[priority as usize].push_back(id);
+                    false
+                }
+            }
+        };
+        self.ready.notify_one();
+        merged
+    }
+
+    // Up to `limit` messages, highest priority first
+    pub async fn next_batch(&self, limit: usize) -> Vec<Outgoing> {
+        let mut guard = self.queues.lock().await;
+        let queues = &mut *guard;
+        let mut batch = Vec::with_capacity(limit);
+        for priority in [Priority::Critical, Priority::Normal, Priority::Low] {
+            while batch.len() < limit {
+                let Some(id) = queues.pending[priority as usize].pop_front() else {
+                    break;
+                };
+                // Skip ids left behind by a priority change
+                if queues.messages.get(&id).is_none_or(|queued| queued.priority != priority) {
+                    continue;
+                }
+                if let Some(queued) = queues.messages.remove(&id) {
+                    let key = (queued.message.target, queued.message.event.clone());
+                    if queues.coalescing.get(&key) == Some(&id) {
+                        queues.coalescing.remove(&key);
+                    }
+                    batch.push(queued.message);
+                }
+            }
+        }
+        batch
+    }
+
+    pub async fn len(&self) -> usize {
+        self.queues.lock().await.messages.len()
+    }
+
+    // Wait until something is queued
+    pub async fn wait(&self) {
+        self.ready.notified().await
+    }
+}
+
+impl Queues {
+    // Drop the oldest message of the lowest priority; false if there is none
+    fn evict(&mut self) -> bool {
+        for priority in [Priority::Low, Priority::Normal, Priority::Critical] {
+            while let Some(id) = self.pending[priority as usize].pop_front() {
+                if self.messages.get(&id).is_none_or(|queued| queued.priority != priority) {
+                    continue;
+                }
+                if let Some(queued) = self.messages.remove(&id) {
+                    let key = (queued.message.target, queued.message.event);
+                    if self.coalescing.get(&key) == Some(&id) {
+                        self.coalescing.remove(&key);
+                    }
+                    return true;
+                }
+            }
+        }
+        false
+    }
+}
+
+// Match status changes (found, started, ended) go first, live ops reports last
+pub fn priority_of(event: &str, data: &Value) -> Priority {
+    match event {
+        protocol::EVENT_STATE_DELTA if data["changes"].get("status").is_some() => Priority::Critical,
+        protocol::EVENT_ADMIN_MATCHES => Priority::Low,
+        _ => Priority::Normal,
+    }
+}
+
+// Events whose newer messages supersede queued ones for the same target
+fn coalesces(event: &str) -> bool {
+    event == protocol::EVENT_STATE_DELTA || event == protocol::EVENT_ADMIN_MATCHES
+}
+
+fn merge(queued: &mut Outgoing, newer: Outgoing) {
+    queued.publish |= newer.publish;
+    queued.correlation_id = newer.correlation_id.or(queued.correlation_id);
+    if queued.event != protocol::EVENT_STATE_DELTA {
+        queued.data = newer.data;
+        return;
+    }
+    // Fold the newer delta into the queued one: it still applies on top of the
+    // queued delta's base version and brings the client to the newer version
+    let (Ok(mut delta), Ok(later)) = (
+        serde_json::from_value::<StateDelta>(queued.data.clone()),
+        serde_json::from_value::<StateDelta>(newer.data.clone()),
+    ) else {
+        queued.data = newer.data;
+        return;
+    };
+    delta.version = later.version;
+    delta.changes.extend(later.changes);
+    if let Ok(data) = serde_json::to_value(&delta) {
+        queued.data = data;
+    }
+}
@@ -1,33 +1,641 @@
+use std::collections::{HashMap, HashSet};
+use std::io::Write;
+use std::net::IpAddr;
 use std::sync::Arc;
+use std::time::Duration;
 
-use crate::matchmaking::service::MatchService;
+use crate::matchmaking::service::{CrossPlay, MatchService};
+use crate::models::game::{LobbySelection, MemberDetails, Platform, PlatformPool};
 use crate::models::message::{ClientMessage, ServerMessage};
 use crate::error::{Error, Result};
-use axum::extract::ws::{Message, WebSocket};
+use crate::matchmaking::events::{MatchEvent, Published};
+use crate::announcements::announcement::Announcement;
+use crate::announcements::service::AnnouncementService;
+use crate::anticheat::appeal::{AppealRequest, AppealService};
+use crate::api::admin;
+use crate::chaos;
+use crate::cluster::presence::{ClusterMessage, Incoming, Presence};
+use crate::config::{Config, SessionPolicy};
+use crate::content::bundles::ContentBundles;
+use crate::correlation;
+use crate::devices::device::{DeviceRegistration, DeviceRevocation, SessionEndReason, SessionEnded};
+use crate::devices::service::DeviceService;
+use crate::experiments::service::ExperimentService;
+use crate::game::runtime::{GameRuntime, GameTick, MatchTick};
+use crate::heatmap::service::HeatmapService;
+use crate::inbox::message::InboxMessage;
+use crate::inbox::service::InboxService;
+use crate::latency;
+use crate::memory;
+use crate::lfg::post::{LfgPost, LfgSubscribeRequest, LfgSubscription};
+use crate::lfg::service::LfgService;
+use crate::metrics::METRICS;
+use crate::models::emote;
+use crate::moderation::ban::{Ban, BanNotice};
+use crate::preferences::preferences::{NotificationKind, PreferencesUpdate};
+use crate::preferences::service::PreferenceService;
+use crate::remote_config::service::RemoteConfigService;
+use crate::schedule::scheduled::{ScheduleNotice, ScheduleNoticeKind};
+use crate::schedule::service::ScheduleService;
+use crate::slow;
+use crate::supervisor;
+use crate::telemetry::event::TelemetryEvent;
+use crate::telemetry::service::TelemetryService;
+use crate::tutorial::service::TutorialService;
+use crate::tutorial::tutorial::{SCRIPT, TutorialNotice, TutorialStep};
+use axum::extract::ws::{CloseFrame, Message, WebSocket, close_code};
+use flate2::{Compression, write::GzEncoder};
 use futures_util::{stream::StreamExt, SinkExt};
-use serde_json::json;
-use tokio::sync::mpsc;
+use serde::Serialize;
+use tokio::sync::{Mutex, broadcast, mpsc};
+use tracing::Instrument;
 use uuid::Uuid;
 
-use crate::ConnectionManager;
+use super::console::{ConsoleEvent, ConsoleFeed};
+use super::fanout::{FanoutQueue, Outgoing, Target, priority_of};
+use super::match_state::MatchStateStore;
+use super::match_stats::{MatchStats, MatchStatsTracker};
+use super::protocol::{
+    EVENT_ANNOUNCEMENT, EVENT_LFG_POST, AdminAnnounceRequest, AdminWatchReply, AdminWatchRequest, CancelReply, DeviceList, DiscoverRequest, EmoteEvent, EmoteRequest, LobbyChatMessage,
+    HostChanged, LobbyChatRequest, LobbyCreateRequest, LobbyJoinRequest, LobbyKickRequest, LobbyKicked, LobbyOpened, LobbyTeamRequest, LobbySelectionUpdate, MatchStartRequest, MatchStatsReport, MatchUpdate, NetReportReply, NetReportRequest, PingRequest, Pong, PositionReport, ScheduleCancelRequest,
+    ScheduleCreateRequest, ScheduleList, ServerEvent, SpectateReply, SpectateRequest, StateResyncRequest, TimeSyncReply, TimeSyncRequest, VoiceIce, VoiceIceRequest, VoiceSdp, VoiceSdpRequest,
+};
+use super::handshake::{Handshake, Session};
+use super::recorder::TrafficRecorder;
+use super::spectator::{self, SpectatorFeed};
+use super::voice::{self, MAX_CANDIDATE_LEN, MAX_SDP_LEN};
+use super::state::{ConnectionManager, LinkQuality, NetReport};
 
 pub struct WebSocketHandler {
     pub conn_manager: ConnectionManager,
     match_service: Arc<MatchService>,
+    game: Arc<GameRuntime>,
+    telemetry: Arc<TelemetryService>,
+    heatmap: Arc<HeatmapService>,
+    // Users connected to other nodes are reached through the cluster
+    presence: Arc<Presence>,
+    // Events held for players who were offline
+    inbox: Arc<InboxService>,
+    // Registered devices, for session takeover and push notifications
+    devices: Arc<DeviceService>,
+    announcements: Arc<AnnouncementService>,
+    schedules: Arc<ScheduleService>,
+    preferences: Arc<PreferenceService>,
+    // Localized text for each player's locale
+    content: Arc<ContentBundles>,
+    lfg: Arc<LfgService>,
+    tutorials: Arc<TutorialService>,
+    appeals: Arc<AppealService>,
+    match_states: MatchStateStore,
+    match_stats: MatchStatsTracker,
+    // Broadcasts waiting to be sent, drained by spawn_fanout
+    fanout: FanoutQueue,
+    // Ticks held back for throttled connections, merged until the next send
+    pending_ticks: Mutex<HashMap<Uuid, GameTick>>,
+    // Match traffic on its way to spectators, drained by spawn_spectator_feed
+    spectators: SpectatorFeed,
+    // Set when TRAFFIC_RECORD_PATH is
+    recorder: Option<TrafficRecorder>,
+    // Connections and failed commands, for /ws/admin
+    pub console: ConsoleFeed,
+    // Builds the welcome event of new sessions
+    handshake: Handshake,
+    config: Arc<Config>,
 }
 
 impl WebSocketHandler {
-    pub fn new(match_service: Arc<MatchService>) -> Self {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        match_service: Arc<MatchService>,
+        game: Arc<GameRuntime>,
+        telemetry: Arc<TelemetryService>,
+        experiments: Arc<ExperimentService>,
+        remote_config: Arc<RemoteConfigService>,
+        heatmap: Arc<HeatmapService>,
+        presence: Arc<Presence>,
+        inbox: Arc<InboxService>,
+        devices: Arc<DeviceService>,
+        announcements: Arc<AnnouncementService>,
+        schedules: Arc<ScheduleService>,
+        preferences: Arc<PreferenceService>,
+        content: Arc<ContentBundles>,
+        lfg: Arc<LfgService>,
+        tutorials: Arc<TutorialService>,
+        appeals: Arc<AppealService>,
+        conn_manager: ConnectionManager,
+        config: Arc<Config>,
+    ) -> Self {
+        let handshake = Handshake::new(
+            config.handshake.clone(),
+            match_service.clone(),
+            experiments,
+            remote_config,
+            announcements.clone(),
+            content.clone(),
+            preferences.clone(),
+        );
         Self {
-            conn_manager: ConnectionManager::new(),
+            conn_manager,
             match_service,
+            game,
+            telemetry,
+            heatmap,
+            presence,
+            inbox,
+            devices,
+            announcements,
+            schedules,
+            preferences,
+            content,
+            lfg,
+            tutorials,
+            appeals,
+            match_states: MatchStateStore::new(config.gateway.max_tracked_matches),
+            match_stats: MatchStatsTracker::new(config.gateway.max_tracked_matches),
+            fanout: FanoutQueue::new(config.gateway.max_fanout_queue),
+            pending_ticks: Mutex::new(HashMap::new()),
+            spectators: SpectatorFeed::new(config.gateway.spectator_delay, config.gateway.max_spectator_buffer),
+            recorder: config.gateway.record_path.as_deref().and_then(TrafficRecorder::open),
+            console: ConsoleFeed::new(1024),
+            handshake,
+            config,
         }
     }
 
-    // 广播匹配状态更新
-    pub async fn broadcast_match_update(&self, match_id: Uuid, status: &str, match_type: &str, current_players: i32, required_players: i32) -> Result<()> {
-        println!("广播匹配更新: 匹配ID={}, 状态={}, 玩家={}/{}", match_id, status, current_players, required_players);
-        
+    // 所有比赛的实时统计
+    pub async fn match_stats(&self) -> Vec<MatchStats> {
+        let connections = self.conn_manager.connections_per_match().await;
+        self.match_stats.snapshot(&connections).await
+    }
+
+    // 网关在内存中持有的各类状态的条目数
+    pub async fn memory_usage(&self) -> Vec<(&'static str, usize)> {
+        vec![
+            (memory::CONNECTIONS, self.conn_manager.connection_count().await),
+            (memory::MATCH_STATES, self.match_states.len().await),
+            (memory::MATCH_STATS, self.match_stats.len().await),
+            (memory::SPECTATOR_BUFFER, self.spectators.len().await),
+            (memory::FANOUT_QUEUE, self.fanout.len().await),
+        ]
+    }
+
+    // 会话数达到 MAX_CONNECTIONS 时拒绝新的 WebSocket 和 SSE 会话
+    pub async fn check_capacity(&self) -> Result<()> {
+        let Some(max) = self.config.gateway.max_connections else {
+            return Ok(());
+        };
+        if self.conn_manager.connection_count().await < max {
+            return Ok(());
+        }
+        METRICS.record_memory_cap_hit(memory::CONNECTIONS);
+        Err(Error::ServerBusy {
+            retry_after_secs: self.config.matchmaking.limits.retry_after.as_secs().max(1),
+        })
+    }
+
+    // 订阅游戏循环，把每个玩家自己的 tick 视图推送给他的连接
+    // 网络较差的连接按 tick_stride 降频，跳过的 tick 合并到下一次发送
+    pub fn spawn_tick_listener(self: Arc<Self>, mut ticks: broadcast::Receiver<MatchTick>) {
+        tokio::spawn(async move {
+            loop {
+                match ticks.recv().await {
+                    // 单个 tick 处理时 panic 只跳过这一帧，订阅继续
+                    Ok(tick) => {
+                        supervisor::catch("tick_listener", self.deliver_tick(&tick)).await;
+                    }
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        tracing::warn!("Tick listener lagged, skipped {} ticks", skipped);
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                }
+            }
+        });
+    }
+
+    async fn deliver_tick(&self, tick: &MatchTick) {
+        let members = self.conn_manager.get_match_members_with_stride(tick.match_id).await;
+        for &(conn_id, user_id, stride) in &members {
+            let Some(view) = tick.views.get(&user_id) else {
+                continue;
+            };
+            let Some(frame) = self.throttle_tick(conn_id, view.clone(), stride).await else {
+                continue;
+            };
+            if let Err(e) = self.push_event(conn_id, &ServerEvent::Tick(frame)).await {
+                tracing::warn!("Failed to push tick to connection {}: {:?}", conn_id, e);
+            }
+        }
+
+        // 观众看到的是延迟后的全部位置，不含玩家的提示
+        if !self.conn_manager.get_spectators(tick.match_id).await.is_empty()
+            && let Some(view) = spectator::spectator_view(tick)
+        {
+            self.hold_for_spectators(tick.match_id, &ServerEvent::Tick(view)).await;
+        }
+
+        // 连接在其他节点上的玩家，转发给他们所在的节点
+        let local: HashSet<Uuid> = members.iter().map(|&(_, user_id, _)| user_id).collect();
+        for (user_id, view) in tick.views.iter().filter(|(user_id, _)| !local.contains(*user_id)) {
+            self.forward_to_remote_users(&[*user_id], &ServerEvent::Tick(view.clone())).await;
+        }
+    }
+
+    // 订阅匹配事件总线，把领域事件转换为 ServerMessage 推送给房间内的连接
+    pub fn spawn_event_listener(self: Arc<Self>, mut events: broadcast::Receiver<Published>) {
+        tokio::spawn(async move {
+            loop {
+                match events.recv().await {
+                    Ok(Published { event, correlation_id }) => {
+                        // 沿用触发该事件的命令的关联 ID；处理时 panic 只跳过这条事件
+                        let dispatch = supervisor::catch("event_listener", self.dispatch_event(&event));
+                        if let Some(Err(e)) = correlation::scope(correlation_id, dispatch).await {
+                            println!("推送匹配事件失败: {:?}", e);
+                        }
+                    }
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        println!("警告: 事件订阅落后，跳过 {} 条消息", skipped);
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                }
+            }
+        });
+    }
+
+    async fn dispatch_event(&self, event: &MatchEvent) -> Result<()> {
+        self.match_stats.on_event(event).await;
+
+        // 更新比赛状态文档，只广播变化的字段
+        if let Some(delta) = self.match_states.apply(event).await {
+            println!("广播状态增量: 匹配ID={}, 版本={}, 字段={:?}", delta.match_id, delta.version, delta.changes.keys().collect::<Vec<_>>());
+            self.broadcast_to_match(delta.match_id, &ServerEvent::StateDelta(delta)).await?;
+        }
+
+        if let MatchEvent::DiscoveryRecorded { discovery } = event {
+            self.broadcast_to_match(discovery.match_id, &ServerEvent::Discovery(discovery.clone())).await?;
+        }
+
+        if let MatchEvent::TreasuresSpawned { spawn } = event {
+            self.broadcast_to_match(spawn.match_id, &ServerEvent::TreasuresSpawned(spawn.clone())).await?;
+        }
+
+        if let MatchEvent::CaptureChanged { update } = event {
+            self.broadcast_to_match(update.match_id, &ServerEvent::Capture(update.clone())).await?;
+        }
+
+        if let MatchEvent::RoundEnded { round } = event {
+            self.broadcast_to_match(round.match_id, &ServerEvent::Round(round.clone())).await?;
+        }
+
+        // 大厅开放：每队只收到自己队伍的成员和可选项
+        if let MatchEvent::LobbyOpened { room, teams, closes_at } = event {
+            let details = self.match_service.get_match_details(room.match_id).await.ok();
+            for team in teams {
+                let teammates = team.players
+                    .iter()
+                    .map(|player| {
+                        details.as_ref()
+                            .and_then(|details| details.teams.iter().flat_map(|t| t.members.iter()).find(|m| m.user_id == *player))
+                            .cloned()
+                            .unwrap_or_else(|| MemberDetails {
+                                user_id: *player,
+                                nickname: String::new(),
+                                avatar_url: String::new(),
+                                score: 0,
+                                platform: None,
+                                loadout: None,
+                                role: None,
+                            })
+                    })
+                    .collect();
+                let opened = LobbyOpened {
+                    match_id: room.match_id,
+                    team_id: team.team_id,
+                    closes_at: *closes_at,
+                    teammates,
+                    loadouts: self.config.game.lobby.loadouts.clone(),
+                    roles: self.config.game.lobby.roles.clone(),
+                };
+                self.send_to_players(room.match_id, &team.players, &ServerEvent::LobbyOpened(opened)).await?;
+            }
+        }
+
+        if let MatchEvent::HostChanged { match_id, host, previous } = event {
+            let changed = HostChanged { match_id: *match_id, host: *host, previous: *previous };
+            self.broadcast_to_match(*match_id, &ServerEvent::LobbyHost(changed)).await?;
+        }
+
+        if let MatchEvent::TeamsChanged { teams } = event {
+            self.broadcast_to_match(teams.match_id, &ServerEvent::LobbyTeams(teams.clone())).await?;
+        }
+
+        if let MatchEvent::KickVoted { vote } = event {
+            self.broadcast_to_match(vote.match_id, &ServerEvent::LobbyKickVote(vote.clone())).await?;
+        }
+
+        // 被踢出的玩家不再属于该房间，不会再收到房间广播
+        if let MatchEvent::PlayerKicked { match_id, user_id, blocked } = event {
+            let kicked = ServerEvent::LobbyKicked(LobbyKicked { match_id: *match_id, blocked: *blocked });
+            for (member_conn, member) in self.conn_manager.get_match_members(*match_id).await {
+                if member != *user_id {
+                    continue;
+                }
+                self.conn_manager.update_match_id(&member_conn, None).await;
+                if let Err(e) = self.push_event(member_conn, &kicked).await {
+                    tracing::warn!("Failed to notify kicked player on connection {}: {:?}", member_conn, e);
+                }
+            }
+        }
+
+        if let MatchEvent::LobbySelected { match_id, user_id, selection, team } = event {
+            let update = LobbySelectionUpdate { user_id: *user_id, selection: selection.clone() };
+            self.send_to_players(*match_id, team, &ServerEvent::LobbySelection(update)).await?;
+        }
+
+        // 比赛结束后的调整按用户推送，他们可能已经不在比赛中
+        if let MatchEvent::ResultAdjusted { entry, users } = event {
+            self.notify_users(users, &entry.id.to_string(), &ServerEvent::MatchAdjusted(entry.clone())).await?;
+        }
+
+        if let MatchEvent::RankPlaced { match_id, placement } = event {
+            self.notify_users(&[placement.user_id], &match_id.to_string(), &ServerEvent::RankPlaced(placement.clone())).await?;
+        }
+
+        // 在线的玩家已经通过状态增量得知比赛开始，离线的玩家存入收件箱
+        if let MatchEvent::MatchStarted { room, teams } = event {
+            let players: Vec<Uuid> = teams.iter().flat_map(|team| team.players.iter().copied()).collect();
+            let update = MatchUpdate {
+                match_id: room.match_id,
+                status: room.status,
+                match_type: room.match_type.clone(),
+                zone_id: room.zone_id.clone(),
+                current_players: room.current_players,
+                required_players: room.required_players,
+            };
+            self.hold_for_offline(&players, &room.match_id.to_string(), &ServerEvent::MatchFound(update)).await?;
+        }
+
+        Ok(())
+    }
+
+    // 订阅其他节点转发来的消息，推送给本节点上的连接
+    pub fn spawn_cluster_listener(self: Arc<Self>, mut messages: broadcast::Receiver<Incoming>) {
+        tokio::spawn(async move {
+            loop {
+                match messages.recv().await {
+                    Ok(Incoming { message, correlation_id }) => {
+                        let handle = supervisor::catch("cluster_listener", self.handle_cluster_message(message));
+                        correlation::scope(correlation_id, handle).await;
+                    }
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        tracing::warn!("Cluster listener lagged, skipped {} messages", skipped);
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                }
+            }
+        });
+    }
+
+    async fn handle_cluster_message(&self, message: ClusterMessage) {
+        match message {
+            ClusterMessage::ToUsers { user_ids, event, data } => {
+                self.push_to_local_users(&user_ids, &event, &data).await;
+            }
+            ClusterMessage::ToMatch { match_id, event, data } => {
+                self.enqueue(Outgoing {
+                    target: Target::Match(match_id),
+                    event,
+                    data,
+                    publish: false,
+                    correlation_id: correlation::current(),
+                }).await;
+            }
+            ClusterMessage::Command { match_id, user_id, cmd, data } => {
+                if let Err(e) = self.handle_forwarded(match_id, user_id, &cmd, data).await {
+                    tracing::warn!("Forwarded {} of user {} in match {} failed: {}", cmd, user_id, match_id, e);
+                }
+            }
+            ClusterMessage::EndSessions { user_id, device_id, notice } => {
+                self.end_local_sessions(user_id, device_id.as_deref(), None, &notice).await;
+            }
+            ClusterMessage::Lfg { post } => self.deliver_lfg(&post).await,
+        }
+    }
+
+    // 其他节点转发来的、关于本节点拥有的比赛的命令；没有回复，出错只记录日志
+    async fn handle_forwarded(&self, match_id: Uuid, user_id: Uuid, cmd: &str, data: serde_json::Value) -> Result<()> {
+        match cmd {
+            "game.position" => {
+                let report: PositionReport = serde_json::from_value(data)
+                    .map_err(|_| Error::InvalidMessage)?;
+                self.apply_position(match_id, user_id, report).await
+            }
+            "game.ping" => {
+                let request: PingRequest = serde_json::from_value(data)
+                    .map_err(|_| Error::InvalidMessage)?;
+                self.apply_map_ping(match_id, user_id, request).await
+            }
+            "game.emote" => {
+                let request: EmoteRequest = serde_json::from_value(data)
+                    .map_err(|_| Error::InvalidMessage)?;
+                self.apply_emote(match_id, user_id, request).await
+            }
+            "game.discover" => {
+                let request: DiscoverRequest = serde_json::from_value(data)
+                    .map_err(|_| Error::InvalidMessage)?;
+                self.apply_discover(match_id, user_id, request).await
+            }
+            "lobby.chat" => {
+                let request: LobbyChatRequest = serde_json::from_value(data)
+                    .map_err(|_| Error::InvalidMessage)?;
+                self.apply_lobby_chat(match_id, user_id, request).await
+            }
+            // 选择结果通过 lobby.selection 事件送达
+            "lobby.select" => {
+                let selection: LobbySelection = serde_json::from_value(data)
+                    .map_err(|_| Error::InvalidMessage)?;
+                self.match_service.select_loadout(match_id, user_id, selection).await.map(|_| ())
+            }
+            "voice.offer" | "voice.answer" | "voice.ice" => self.relay_voice(match_id, user_id, cmd, data).await,
+            _ => Err(Error::InvalidMessage),
+        }
+    }
+
+    // 按用户推送事件，包括连接在其他节点上的用户
+    async fn push_to_users(&self, user_ids: &[Uuid], event: &ServerEvent) -> Result<()> {
+        let data = event_data(event)?;
+        self.push_to_local_users(user_ids, event.name(), &data).await;
+        if let Err(e) = self.presence.send_to_users(user_ids, event.name(), &data).await {
+            tracing::warn!("Failed to forward {} to other nodes: {}", event.name(), e);
+        }
+        Ok(())
+    }
+
+    // 按用户推送事件；不在任何节点上的用户存入收件箱，下次连接时补发
+    async fn notify_users(&self, user_ids: &[Uuid], key: &str, event: &ServerEvent) -> Result<()> {
+        let offline = self.hold_for_offline(user_ids, key, event).await?;
+        let online: Vec<Uuid> = user_ids.iter().filter(|user_id| !offline.contains(user_id)).copied().collect();
+        self.push_to_users(&online, event).await
+    }
+
+    // 把事件存入离线用户的收件箱（机器人除外），返回这些用户
+    async fn hold_for_offline(&self, user_ids: &[Uuid], key: &str, event: &ServerEvent) -> Result<Vec<Uuid>> {
+        let name = event.name();
+        let users: Vec<Uuid> = user_ids.iter()
+            .filter(|user_id| !self.config.new_players.bots.contains(user_id))
+            .copied()
+            .collect();
+        let offline = match self.presence.offline(&users).await {
+            Ok(offline) => offline,
+            Err(e) => {
+                // 无法判断时按在线处理，照常推送
+                tracing::warn!("Failed to look up offline users for {}: {}", name, e);
+                return Ok(Vec::new());
+            }
+        };
+        if offline.is_empty() {
+            return Ok(offline);
+        }
+
+        let data = event_data(event)?;
+        if let Err(e) = self.inbox.store(&offline, name, key, &data).await {
+            tracing::warn!("Failed to hold {} for {} offline users: {}", name, offline.len(), e);
+            return Ok(offline);
+        }
+        // 同时推送通知到离线用户登记过推送令牌的设备，按各自的通知偏好和免打扰时段筛选
+        let push_to = self.preferences.pushable(&offline, notification_kind(event)).await;
+        self.devices.push(&push_to, name, &data).await;
+
+        // 存入期间刚好上线的用户已经错过了连接时的补发，立即补发给他们
+        match self.presence.offline(&offline).await {
+            Ok(still_offline) => {
+                for &user_id in offline.iter().filter(|user_id| !still_offline.contains(user_id)) {
+                    self.deliver_inbox(user_id).await;
+                }
+            }
+            Err(e) => tracing::warn!("Failed to recheck offline users for {}: {}", name, e),
+        }
+        Ok(offline)
+    }
+
+    // 补发用户收件箱中的消息，msg_id 沿用收件箱消息的 id，客户端可据此去重；
+    // 取出即删除，所以每条消息只补发一次
+    async fn deliver_inbox(&self, user_id: Uuid) {
+        let messages = self.take_inbox(user_id).await;
+        self.deliver_held(user_id, messages).await;
+    }
+
+    async fn take_inbox(&self, user_id: Uuid) -> Vec<InboxMessage> {
+        self.inbox.take(user_id).await.unwrap_or_else(|e| {
+            tracing::warn!("Failed to take inbox of user {}: {}", user_id, e);
+            Vec::new()
+        })
+    }
+
+    async fn deliver_held(&self, user_id: Uuid, messages: Vec<InboxMessage>) {
+        for message in messages {
+            let local = self.conn_manager.get_user_connections(&[user_id]).await;
+            if local.is_empty() {
+                if let Err(e) = self.presence.send_to_users(&[user_id], &message.event, &message.data).await {
+                    tracing::warn!("Failed to forward held {} to user {}: {}", message.event, user_id, e);
+                }
+                continue;
+            }
+            let msg = ServerMessage::event(message.id, &message.event, message.data.clone());
+            for conn_id in local {
+                if let Err(e) = self.send_message(conn_id, &msg).await {
+                    tracing::warn!("Failed to deliver held {} to connection {}: {:?}", message.event, conn_id, e);
+                }
+            }
+        }
+    }
+
+    // 只转发给这些用户在其他节点上的连接
+    async fn forward_to_remote_users(&self, user_ids: &[Uuid], event: &ServerEvent) {
+        let result = match event_data(event) {
+            Ok(data) => self.presence.send_to_users(user_ids, event.name(), &data).await,
+            Err(e) => Err(e),
+        };
+        if let Err(e) = result {
+            tracing::warn!("Failed to forward {} to other nodes: {}", event.name(), e);
+        }
+    }
+
+    // 推送给这些用户在本节点上的连接，返回送达的连接数
+    pub async fn push_to_local_users(&self, user_ids: &[Uuid], event: &str, data: &serde_json::Value) -> usize {
+        let mut delivered = 0;
+        for conn_id in self.conn_manager.get_user_connections(user_ids).await {
+            match self.push_raw(conn_id, event, data).await {
+                Ok(()) => delivered += 1,
+                Err(e) => tracing::warn!("Failed to push {} to connection {}: {:?}", event, conn_id, e),
+            }
+        }
+        delivered
+    }
+
+    // 向某个匹配中的所有连接推送事件，包括连接在其他节点上的玩家；排队后分批发送
+    async fn broadcast_to_match(&self, match_id: Uuid, event: &ServerEvent) -> Result<()> {
+        let message = Outgoing::new(Target::Match(match_id), event, true)
+            .map_err(|_| Error::InvalidMessage)?;
+        self.enqueue(message).await;
+        Ok(())
+    }
+
+    async fn enqueue(&self, message: Outgoing) {
+        let priority = priority_of(&message.event, &message.data);
+        if self.fanout.push(priority, message).await {
+            METRICS.record_fanout_coalesced();
+        }
+    }
+
+    // 按优先级分批发送排队的广播；整批发满后暂停 fanout_interval，避免同时涌向所有连接
+    // 发送循环 panic 后由 supervisor 重启，队列中的消息保留
+    pub fn spawn_fanout(self: Arc<Self>) {
+        supervisor::spawn_loop("fanout", move || {
+            let handler = self.clone();
+            async move {
+                let batch_size = handler.config.gateway.fanout_batch;
+                loop {
+                    let batch = handler.fanout.next_batch(batch_size).await;
+                    if batch.is_empty() {
+                        handler.fanout.wait().await;
+                        continue;
+                    }
+                    let full = batch.len() == batch_size;
+                    for message in batch {
+                        correlation::scope(message.correlation_id, handler.send_outgoing(message)).await;
+                    }
+                    if full {
+                        tokio::time::sleep(handler.config.gateway.fanout_interval).await;
+                    }
+                }
+            }
+        });
+    }
+
+    async fn send_outgoing(&self, message: Outgoing) {
+        match message.target {
+            Target::Match(match_id) => {
+                self.deliver_to_match(match_id, &message.event, &message.data).await;
+                if message.publish
+                    && let Err(e) = self.presence.broadcast_to_match(match_id, &message.event, &message.data).await
+                {
+                    tracing::warn!("Failed to broadcast {} of match {} to other nodes: {}", message.event, match_id, e);
+                }
+            }
+            Target::Connection(conn_id) => {
+                if let Err(e) = self.push_raw(conn_id, &message.event, &message.data).await {
+                    tracing::warn!("Failed to push {} to connection {}: {:?}", message.event, conn_id, e);
+                }
+            }
+        }
+    }
+
+    // 推送给本节点上该匹配的所有连接；观众延迟后收到
+    async fn deliver_to_match(&self, match_id: Uuid, event: &str, data: &serde_json::Value) {
+        if !self.conn_manager.get_spectators(match_id).await.is_empty() {
+            self.spectators.push(match_id, event, data.clone()).await;
+        }
+
         // 获取所有在这个匹配中的连接
         let connections = self.conn_manager.get_connections_by_match(match_id).await;
         println!("找到 {} 个连接需要通知", connections.len());
@@ -37,18 +645,7 @@ impl WebSocketHandler {
         }
         
         for conn_id in connections {
-            let update_msg = ServerMessage {
-                msg_id: Uuid::new_v4(),
-                code: 0,
-                data: Some(json!({
-                    "match_id": match_id,
-                    "status": status,
-                    "type": match_type,
-                    "current_players": current_players,
-                    "required_players": required_players
-                })),
-                error: None,
-            };
+            let update_msg = ServerMessage::event(Uuid::new_v4(), event, data.clone());
             
             // 发送更新消息（忽略错误，因为有些连接可能已断开）
             if let Err(e) = self.send_message(conn_id, &update_msg).await {
@@ -57,179 +654,1717 @@ impl WebSocketHandler {
                 println!("成功通知连接: {}", conn_id);
             }
         }
-        
-        Ok(())
+    }
+
+    // 交给观众延迟队列
+    async fn hold_for_spectators(&self, match_id: Uuid, event: &ServerEvent) {
+        match event.data() {
+            Ok(data) => self.spectators.push(match_id, event.name(), data).await,
+            Err(e) => tracing::warn!("Failed to serialize {} for spectators: {}", event.name(), e),
+        }
+    }
+
+    // 把延迟期满的消息推送给当时仍在观看该比赛的连接
+    pub fn spawn_spectator_feed(self: Arc<Self>) {
+        supervisor::spawn_loop("spectator_feed", move || {
+            let handler = self.clone();
+            async move {
+                let mut interval = tokio::time::interval(Duration::from_millis(100));
+                loop {
+                    interval.tick().await;
+                    for delayed in handler.spectators.take_due().await {
+                        for conn_id in handler.conn_manager.get_spectators(delayed.match_id).await {
+                            if let Err(e) = handler.push_raw(conn_id, &delayed.event, &delayed.data).await {
+                                tracing::warn!("Failed to push {} to spectator {}: {:?}", delayed.event, conn_id, e);
+                            }
+                        }
+                    }
+                }
+            }
+        });
+    }
+
+    // 合并挂起的 tick；到了该连接的发送间隔才返回要发送的 tick
+    async fn throttle_tick(&self, conn_id: Uuid, view: GameTick, stride: u64) -> Option<GameTick> {
+        let mut pending = self.pending_ticks.lock().await;
+        let tick_no = view.tick;
+        let merged = match pending.remove(&conn_id) {
+            Some(older) => older.merged(view),
+            None => view,
+        };
+        if tick_no.is_multiple_of(stride) {
+            Some(merged)
+        } else {
+            pending.insert(conn_id, merged);
+            None
+        }
+    }
+
+    // 向单个连接推送事件
+    async fn push_event(&self, conn_id: Uuid, event: &ServerEvent) -> Result<()> {
+        let msg = event.to_message().map_err(|_| Error::InvalidMessage)?;
+        self.send_message(conn_id, &msg).await
+    }
+
+    // 向单个连接推送已序列化的事件，用于转发队列、收件箱和其他节点来的消息
+    async fn push_raw(&self, conn_id: Uuid, event: &str, data: &serde_json::Value) -> Result<()> {
+        self.send_message(conn_id, &ServerMessage::event(Uuid::new_v4(), event, data.clone())).await
     }
 
     async fn send_message(&self, conn_id: Uuid, message: &ServerMessage) -> Result<()> {
+        let started = std::time::Instant::now();
         let msg = serde_json::to_string(message)
             .map_err(|_| Error::InvalidMessage)?;
         
-        // 获取连接对应的 sender
-        if let Some(sender) = self.conn_manager.get_sender(&conn_id).await {
-            sender.send(Message::Text(msg))
+        // 获取连接对应的状态
+        if let Some(state) = self.conn_manager.get_connection(&conn_id).await {
+            // 开启压缩的连接，大消息以 gzip 二进制帧发送
+            let frame = if state.compress && msg.len() >= self.config.gateway.compression_threshold {
+                let compressed = gzip(msg.as_bytes())?;
+                METRICS.record_compression(msg.len(), compressed.len());
+                Message::Binary(compressed)
+            } else {
+                Message::Text(msg)
+            };
+            state.sender.send(frame)
                 .map_err(|e| Error::WsError(e.to_string()))?;
         }
+        latency::sent(started.elapsed());
         
         Ok(())
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub async fn handle_connection(
         self: Arc<Self>,
         socket: WebSocket,
         user_id: Uuid,
+        ip: IpAddr,
+        compress: bool,
+        platform: Option<Platform>,
+        locale: Option<String>,
+        device_id: Option<String>,
+        resume_token: Option<String>,
     ) {
-        let conn_id = Uuid::new_v4();
         let (mut ws_sender, mut ws_receiver) = socket.split();
         let (tx, mut rx) = mpsc::unbounded_channel();
         
         // 创建发送任务
-        let send_task = tokio::spawn(async move {
+        let send_task = supervisor::spawn("ws_send", async move {
             while let Some(message) = rx.recv().await {
+                // 故障注入：模拟发送任务崩溃或丢帧
+                if chaos::kill_send_task() {
+                    break;
+                }
+                if chaos::drop_frame() {
+                    continue;
+                }
                 if ws_sender.send(message).await.is_err() {
                     break;
                 }
             }
         });
         
-        // 添加到连接管理器
-        self.conn_manager.add_connection(conn_id, user_id, tx.clone()).await;
-    
-        // 发送欢迎消息
-        let welcome_msg = ServerMessage {
-            msg_id: Uuid::new_v4(),
-            code: 0,
-            data: Some(json!({
-                "conn_id": conn_id,
-                "message": "Connected successfully"
-            })),
-            error: None,
-        };
-    
-        let _ = self.send_message(conn_id, &welcome_msg).await;
+        let conn_id = self.open_session(user_id, ip, compress, platform, locale, device_id, resume_token.as_deref(), tx).await;
     
         // 处理接收消息
         while let Some(Ok(message)) = ws_receiver.next().await {
             match message {
-                Message::Text(text) => {
-                    if let Err(e) = self.handle_message(conn_id, &text).await {
-                        let error_msg = ServerMessage {
-                            msg_id: Uuid::new_v4(),
-                            code: e.code(),
-                            data: None,
-                            error: Some(e.to_string()),
-                        };
-                        let _ = self.send_message(conn_id, &error_msg).await;
-                    }
-                }
+                Message::Text(text) => self.handle_text(conn_id, &text).await,
                 Message::Close(_) => break,
                 _ => {}
             }
         }
     
+        // 发送任务先于客户端结束（发送失败或任务崩溃），算作服务端造成的断线，下次匹配优先
+        if send_task.is_finished() {
+            self.match_service.grant_return_priority(&[user_id]);
+        }
+        
         // 清理连接
-        self.conn_manager.remove_connection(&conn_id).await;
+        self.close_session(conn_id).await;
         send_task.abort();
     }
 
-    // 开始匹配
-    async fn handle_match_start(&self, conn_id: Uuid, msg: ClientMessage) -> Result<()> {
-        // 获取匹配类型
-        let match_type: String = serde_json::from_value(msg.data)
-            .map_err(|_| Error::InvalidMessage)?;
+    // 注册一个新会话（WebSocket 或 SSE）并发送欢迎消息，返回连接ID
+    #[allow(clippy::too_many_arguments)]
+    pub async fn open_session(
+        &self,
+        user_id: Uuid,
+        ip: IpAddr,
+        compress: bool,
+        platform: Option<Platform>,
+        locale: Option<String>,
+        device_id: Option<String>,
+        resume_token: Option<&str>,
+        sender: mpsc::UnboundedSender<Message>,
+    ) -> Uuid {
+        let conn_id = Uuid::new_v4();
         
-        let state = self.conn_manager.get_connection(&conn_id)
-            .await
-            .ok_or(Error::ConnectionNotFound)?;
+        // 添加到连接管理器，并登记用户所在的节点
+        let content_locale = self.content.resolve(locale.as_deref());
+        self.conn_manager.add_connection(conn_id, user_id, ip, compress, platform, locale, sender).await;
+        if let Some(recorder) = &self.recorder {
+            recorder.opened(conn_id, user_id).await;
+        }
+        self.console.publish(ConsoleEvent::ConnectionOpened {
+            conn_id,
+            user_id,
+            ip: ip.to_string(),
+            platform,
+        });
+        if let Err(e) = self.presence.connected(user_id).await {
+            tracing::warn!("Failed to publish presence of user {}: {}", user_id, e);
+        }
         
-        // 加入匹配
-        let match_result = self.match_service.clone().join_match(
-            state.user_id,
-            &match_type
-        ).await?;
+        // 同一设备上的旧会话（独占策略下是所有其他会话）被新会话取代
+        if let Some(device_id) = &device_id {
+            self.conn_manager.set_device(&conn_id, device_id).await;
+            self.devices.touch(user_id, device_id).await;
+        }
+        self.replace_sessions(conn_id, user_id, device_id.as_deref()).await;
         
-        // 立即更新连接的match_id，确保广播能找到该连接
-        println!("更新连接 {} 的match_id为 {}", conn_id, match_result.match_id);
-        self.conn_manager.update_match_id(&conn_id, Some(match_result.match_id)).await;
+        // 重连的玩家（可能连到了另一个节点）继续接收所在比赛的推送
+        let mut match_state = None;
+        match self.match_service.active_match_for_user(user_id).await {
+            Ok(Some(match_id)) => {
+                self.conn_manager.update_match_id(&conn_id, Some(match_id)).await;
+                match_state = self.match_states.snapshot(match_id).await;
+            }
+            Ok(None) => {}
+            Err(e) => tracing::warn!("Failed to look up active match of user {}: {}", user_id, e),
+        }
+    
+        // 先取出离线期间收到的消息，欢迎消息里告知条数，随后补发
+        let held = self.take_inbox(user_id).await;
+        let welcome = self.handshake.welcome(Session {
+            conn_id,
+            user_id,
+            compress,
+            locale: content_locale,
+            match_state,
+            resume_token,
+            pending_inbox: held.len(),
+        }).await;
+        let _ = self.push_event(conn_id, &ServerEvent::Welcome(Box::new(welcome))).await;
+        self.deliver_held(user_id, held).await;
         
-        // 返回响应
-        let response = ServerMessage {
-            msg_id: msg.msg_id,
-            code: 0,
-            data: Some(json!({
-                "match_id": match_result.match_id,
-                "status": match_result.status,
-                "type": match_type,
-                "current_players": match_result.current_players,
-                "required_players": match_result.required_players
-            })),
-            error: None,
+        conn_id
+    }
+
+    // 结束该用户被这个连接取代的其他会话，包括其他节点上的
+    async fn replace_sessions(&self, conn_id: Uuid, user_id: Uuid, device_id: Option<&str>) {
+        let scope = match self.devices.session_policy() {
+            SessionPolicy::Takeover => None,
+            SessionPolicy::Multi => match device_id {
+                Some(device_id) => Some(device_id),
+                // 没有设备 ID 的会话不取代任何会话
+                None => return,
+            },
         };
-        
-        self.send_message(conn_id, &response).await
+        let notice = SessionEnded {
+            reason: SessionEndReason::Replaced,
+            by_device: device_id.map(str::to_string),
+        };
+        self.end_local_sessions(user_id, scope, Some(conn_id), &notice).await;
+        if let Err(e) = self.presence.end_sessions(user_id, scope, &notice).await {
+            tracing::warn!("Failed to end sessions of user {} on other nodes: {}", user_id, e);
+        }
     }
 
-    // 取消匹配
-    async fn handle_match_cancel(&self, conn_id: Uuid, msg: ClientMessage) -> Result<()> {
-        let state = self.conn_manager.get_connection(&conn_id)
-            .await
-            .ok_or(Error::ConnectionNotFound)?;
-        
-        if let Some(match_id) = state.match_id {
-            // 从匹配池中移除
-            self.match_service.leave_match(state.user_id, match_id).await?;
+    // 通知并关闭该用户在本节点上的会话，device_id 为空时不限设备；返回关闭的会话数
+    async fn end_local_sessions(&self, user_id: Uuid, device_id: Option<&str>, except: Option<Uuid>, notice: &SessionEnded) -> usize {
+        let mut ended = 0;
+        for conn_id in self.conn_manager.get_user_connections(&[user_id]).await {
+            if except == Some(conn_id) {
+                continue;
+            }
+            let Some(state) = self.conn_manager.get_connection(&conn_id).await else {
+                continue;
+            };
+            if device_id.is_some() && state.device_id.as_deref() != device_id {
+                continue;
+            }
+            tracing::info!("Ending session {} of user {} ({:?})", conn_id, user_id, notice.reason);
+            // 与封禁相同：关闭帧由发送任务转发，会话随后被清理
+            for frame in session_end_frames(notice) {
+                let _ = state.sender.send(frame);
+            }
+            ended += 1;
         }
+        ended
+    }
 
-        let response = ServerMessage {
-            msg_id: msg.msg_id,
-            code: 0,
-            data: Some(json!({
-                "status": "cancelled"
-            })),
-            error: None,
+    // 删除玩家的设备，并关闭该设备在所有节点上的会话
+    pub async fn revoke_device(&self, user_id: Uuid, device_id: &str) -> Result<()> {
+        self.devices.revoke(user_id, device_id).await?;
+        self.end_device_sessions(user_id, device_id).await;
+        Ok(())
+    }
+
+    async fn end_device_sessions(&self, user_id: Uuid, device_id: &str) {
+        let notice = SessionEnded {
+            reason: SessionEndReason::Revoked,
+            by_device: None,
         };
-        
-        self.send_message(conn_id, &response).await
+        self.end_local_sessions(user_id, Some(device_id), None, &notice).await;
+        if let Err(e) = self.presence.end_sessions(user_id, Some(device_id), &notice).await {
+            tracing::warn!("Failed to end sessions of device {} on other nodes: {}", device_id, e);
+        }
     }
 
-    // 处理心跳检测
-    async fn handle_ping(&self, conn_id: Uuid, msg: ClientMessage) -> Result<()> {
-        // 检查比赛状态
-        let state = self.conn_manager.get_connection(&conn_id)
-            .await
+    pub async fn close_session(&self, conn_id: Uuid) {
+        if let Some(recorder) = &self.recorder {
+            recorder.closed(conn_id);
+        }
+        let state = self.conn_manager.get_connection(&conn_id).await;
+        if let Some(state) = &state {
+            if let Err(e) = self.presence.disconnected(state.user_id).await {
+                tracing::warn!("Failed to withdraw presence of user {}: {}", state.user_id, e);
+            }
+            self.console.publish(ConsoleEvent::ConnectionClosed {
+                conn_id,
+                user_id: state.user_id,
+            });
+        }
+        self.conn_manager.remove_connection(&conn_id).await;
+        self.pending_ticks.lock().await.remove(&conn_id);
+
+        // 私人房间的房主掉线后，由仍在线的成员接任
+        if let Some(state) = state
+            && let Some(match_id) = state.match_id
+            && self.conn_manager.get_user_connections(&[state.user_id]).await.is_empty()
+        {
+            let connected: Vec<Uuid> = self.conn_manager.get_match_members(match_id)
+                .await
+                .into_iter()
+                .map(|(_, user_id)| user_id)
+                .collect();
+            self.match_service.host_disconnected(state.user_id, match_id, &connected).await;
+        }
+    }
+
+    // 拒绝被封禁用户的 WebSocket 连接：先发送封禁通知，再关闭
+    pub async fn reject_banned(&self, mut socket: WebSocket, ban: &Ban) {
+        tracing::info!("Rejecting connection of banned user {}", ban.user_id);
+        for frame in ban_frames(&ban.notice()) {
+            if socket.send(frame).await.is_err() {
+                break;
+            }
+        }
+    }
+
+    // 订阅新的封禁，断开被封禁用户在本实例上的所有连接
+    pub fn spawn_ban_listener(self: Arc<Self>, mut bans: broadcast::Receiver<Ban>) {
+        tokio::spawn(async move {
+            loop {
+                match bans.recv().await {
+                    Ok(ban) => {
+                        supervisor::catch("ban_listener", self.disconnect_banned(&ban)).await;
+                    }
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        tracing::warn!("Ban listener lagged, skipped {} bans", skipped);
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                }
+            }
+        });
+    }
+
+    // 订阅到期的公告，推送给本节点上符合条件的连接；其他节点各自推送自己的连接
+    pub fn spawn_announcement_listener(self: Arc<Self>, mut announcements: broadcast::Receiver<Announcement>) {
+        tokio::spawn(async move {
+            loop {
+                match announcements.recv().await {
+                    Ok(announcement) => {
+                        supervisor::catch("announcement_listener", self.deliver_announcement(&announcement)).await;
+                    }
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        tracing::warn!("Announcement listener lagged, skipped {} announcements", skipped);
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                }
+            }
+        });
+    }
+
+    async fn deliver_announcement(&self, announcement: &Announcement) {
+        let region = self.announcements.region();
+        let connections = self.conn_manager.all_connections().await;
+        let locales = self.conn_manager.connection_locales().await;
+        // 每种语言的载荷只生成一次
+        let mut payloads: HashMap<String, serde_json::Value> = HashMap::new();
+        // 关闭了公告通知的玩家不推送
+        let users: Vec<Uuid> = connections.iter().map(|(_, user_id, _)| *user_id).collect::<HashSet<_>>().into_iter().collect();
+        let wanting: HashSet<Uuid> = self.preferences.wanting(&users, NotificationKind::Announcement).await.into_iter().collect();
+        let mut match_types: HashMap<Uuid, Option<String>> = HashMap::new();
+        let mut delivered = 0;
+        for (conn_id, user_id, match_id) in connections {
+            if !wanting.contains(&user_id) {
+                continue;
+            }
+            let match_type = match match_id {
+                Some(match_id) => match match_types.get(&match_id) {
+                    Some(match_type) => match_type.clone(),
+                    None => {
+                        let match_type = self.match_states.snapshot(match_id).await.map(|snapshot| snapshot.state.match_type);
+                        match_types.insert(match_id, match_type.clone());
+                        match_type
+                    }
+                },
+                None => None,
+            };
+            // 所在比赛的状态已清理时按空闲处理
+            if !announcement.segment.matches(region, match_type.as_deref()) {
+                continue;
+            }
+            let locale = self.content.resolve(locales.get(&conn_id).and_then(|l| l.as_deref()));
+            let data = match payloads.get(&locale) {
+                Some(data) => data.clone(),
+                None => {
+                    // 内容包里没有该语言（及默认语言）的文本时用公告原文
+                    let message = announcement.content_key.as_deref()
+                        .and_then(|key| self.content.text(&locale, key))
+                        .unwrap_or(&announcement.message);
+                    let Ok(data) = event_data(&ServerEvent::Announcement(announcement.notice(message))) else {
+                        continue;
+                    };
+                    payloads.insert(locale, data.clone());
+                    data
+                }
+            };
+            // 排队分批发送，避免同时涌向所有连接
+            self.enqueue(Outgoing {
+                target: Target::Connection(conn_id),
+                event: EVENT_ANNOUNCEMENT.to_string(),
+                data,
+                publish: false,
+                correlation_id: None,
+            }).await;
+            delivered += 1;
+        }
+        tracing::info!("Announcement {} queued for {} connections", announcement.id, delivered);
+    }
+
+    // 订阅预约比赛的通知，推送给房主和受邀玩家；离线的存入收件箱并推送到设备
+    pub fn spawn_schedule_listener(self: Arc<Self>, mut notices: broadcast::Receiver<ScheduleNotice>) {
+        tokio::spawn(async move {
+            loop {
+                match notices.recv().await {
+                    Ok(notice) => {
+                        if let Some(Err(e)) = supervisor::catch("schedule_listener", self.deliver_schedule_notice(&notice)).await {
+                            tracing::warn!("Failed to deliver notice for scheduled match {}: {:?}", notice.schedule.id, e);
+                        }
+                    }
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        tracing::warn!("Schedule listener lagged, skipped {} notices", skipped);
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                }
+            }
+        });
+    }
+
+    async fn deliver_schedule_notice(&self, notice: &ScheduleNotice) -> Result<()> {
+        let schedule = &notice.schedule;
+        let (event, users) = match notice.kind {
+            // 房主自己创建的，只通知受邀玩家
+            ScheduleNoticeKind::Invited => (ServerEvent::ScheduleInvited(schedule.clone()), schedule.invited.clone()),
+            ScheduleNoticeKind::Reminder => (ServerEvent::ScheduleReminder(schedule.clone()), schedule.participants()),
+            ScheduleNoticeKind::LobbyOpen => (ServerEvent::ScheduleLobbyOpen(schedule.clone()), schedule.participants()),
+            ScheduleNoticeKind::Cancelled => {
+                // 房间已关闭，已进入房间的连接不再属于它
+                if let Some(match_id) = schedule.match_id {
+                    for (conn_id, _) in self.conn_manager.get_match_members(match_id).await {
+                        self.conn_manager.update_match_id(&conn_id, None).await;
+                    }
+                }
+                (ServerEvent::ScheduleCancelled(schedule.clone()), schedule.participants())
+            }
+        };
+        let key = format!("{}:{}", schedule.id, event.name());
+        self.notify_users(&users, &key, &event).await
+    }
+
+    // 订阅教程进度，推送给本节点上该玩家的连接，文本按各连接的语言取
+    pub fn spawn_tutorial_listener(self: Arc<Self>, mut notices: broadcast::Receiver<TutorialNotice>) {
+        tokio::spawn(async move {
+            loop {
+                match notices.recv().await {
+                    Ok(notice) => {
+                        supervisor::catch("tutorial_listener", self.deliver_tutorial_step(&notice)).await;
+                    }
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        tracing::warn!("Tutorial listener lagged, skipped {} notices", skipped);
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                }
+            }
+        });
+    }
+
+    async fn deliver_tutorial_step(&self, notice: &TutorialNotice) {
+        let Some(spec) = SCRIPT.get(notice.index) else {
+            return;
+        };
+        let key = format!("tutorial.{}", spec.id);
+        let locales = self.conn_manager.connection_locales().await;
+        for conn_id in self.conn_manager.get_user_connections(&[notice.user_id]).await {
+            let locale = self.content.resolve(locales.get(&conn_id).and_then(|l| l.as_deref()));
+            let step = TutorialStep {
+                match_id: notice.match_id,
+                step: spec.id.to_string(),
+                index: notice.index,
+                total: SCRIPT.len(),
+                text: self.content.text(&locale, &key).unwrap_or(spec.text).to_string(),
+                completed: notice.index + 1 == SCRIPT.len(),
+            };
+            if let Err(e) = self.push_event(conn_id, &ServerEvent::TutorialStep(step)).await {
+                tracing::warn!("Failed to send tutorial step to connection {}: {:?}", conn_id, e);
+            }
+        }
+    }
+
+    async fn disconnect_banned(&self, ban: &Ban) {
+        for conn_id in self.conn_manager.get_user_connections(&[ban.user_id]).await {
+            let Some(state) = self.conn_manager.get_connection(&conn_id).await else {
+                continue;
+            };
+            tracing::info!("Disconnecting banned user {} from connection {}", ban.user_id, conn_id);
+            // 关闭帧由发送任务转发；WebSocket 客户端回应关闭后会话即被清理，SSE 流直接结束
+            for frame in ban_frames(&ban.notice()) {
+                let _ = state.sender.send(frame);
+            }
+        }
+    }
+
+    // 处理一条客户端文本消息；每条消息一个关联 ID，贯穿数据库调用、日志和由此产生的推送
+    pub async fn handle_text(self: &Arc<Self>, conn_id: Uuid, text: &str) {
+        let correlation_id = Uuid::new_v4();
+        let span = tracing::info_span!("command", %conn_id, %correlation_id, cmd = tracing::field::Empty);
+        latency::track(correlation::scope(Some(correlation_id), self.process_text(conn_id, text).instrument(span))).await
+    }
+
+    // 出错时把错误回复给该连接
+    async fn process_text(self: &Arc<Self>, conn_id: Uuid, text: &str) {
+        if let Some(recorder) = &self.recorder {
+            recorder.message(conn_id, text).await;
+        }
+
+        // 统计比赛内的消息量
+        if let Some(match_id) = self.conn_manager.get_connection(&conn_id).await.and_then(|s| s.match_id) {
+            self.match_stats.record_message(match_id).await;
+        }
+
+        if let Err(e) = self.handle_message(conn_id, text).await {
+            // 尽量回显请求的 msg_id，方便客户端把错误对应到请求
+            let request = serde_json::from_str::<serde_json::Value>(text).ok();
+            let msg_id = request.as_ref()
+                .and_then(|v| v.get("msg_id")?.as_str().and_then(|id| Uuid::parse_str(id).ok()))
+                .unwrap_or_else(Uuid::new_v4);
+            self.console.publish(ConsoleEvent::CommandFailed {
+                conn_id,
+                user_id: self.conn_manager.get_connection(&conn_id).await.map(|state| state.user_id),
+                cmd: request.as_ref().and_then(|v| Some(v.get("cmd")?.as_str()?.to_string())),
+                code: e.code(),
+                error: e.to_string(),
+                correlation_id: correlation::current(),
+            });
+            let error_msg = ServerMessage {
+                msg_id,
+                event: None,
+                code: e.code(),
+                data: e.retry_after().map(|secs| serde_json::json!({ "retry_after_secs": secs })),
+                error: Some(e.to_string()),
+                correlation_id: correlation::current(),
+            };
+            let _ = self.send_message(conn_id, &error_msg).await;
+        }
+    }
+
+    // 开始匹配
+    async fn handle_match_start(&self, conn_id: Uuid, msg: ClientMessage) -> Result<()> {
+        // 获取匹配类型
+        let request: MatchStartRequest = serde_json::from_value(msg.data)
+            .map_err(|_| Error::InvalidMessage)?;
+        let (match_type, zone_id, position, party, platform_pool) = match request {
+            MatchStartRequest::MatchType(match_type) => (match_type, None, None, Vec::new(), PlatformPool::Any),
+            MatchStartRequest::Options { match_type, zone_id, position, party, platforms } => {
+                (match_type, zone_id, position, party, platforms)
+            }
+        };
+        
+        let state = self.conn_manager.get_connection(&conn_id)
+            .await
+            .ok_or(Error::ConnectionNotFound)?;
+        
+        // 组队的其他成员必须在线
+        let party: Vec<Uuid> = party.into_iter().filter(|&member| member != state.user_id).collect();
+        for &member in &party {
+            if self.conn_manager.get_user_connections(&[member]).await.is_empty() {
+                return Err(Error::InvalidParty(format!("{} is not online", member)));
+            }
+        }
+        
+        // 各成员连接时上报的平台
+        let mut members = party.clone();
+        members.push(state.user_id);
+        let crossplay = CrossPlay {
+            platforms: self.conn_manager.platforms_of(&members).await,
+            pool: platform_pool,
+        };
+        
+        // 加入匹配
+        let match_result = self.match_service.clone().join_match(
+            state.user_id,
+            &party,
+            &match_type,
+            zone_id.as_deref(),
+            position.as_ref(),
+            &crossplay,
+        ).await?;
+        
+        // 立即更新连接的match_id，确保广播能找到该连接
+        println!("更新连接 {} 的match_id为 {}", conn_id, match_result.match_id);
+        self.conn_manager.update_match_id(&conn_id, Some(match_result.match_id)).await;
+        
+        let update = MatchUpdate {
+            match_id: match_result.match_id,
+            status: match_result.status,
+            match_type,
+            zone_id: match_result.zone_id,
+            current_players: match_result.current_players,
+            required_players: match_result.required_players,
+        };
+        
+        // 通知组队成员已被队长加入匹配
+        for member_conn in self.conn_manager.get_user_connections(&party).await {
+            self.conn_manager.update_match_id(&member_conn, Some(match_result.match_id)).await;
+            if let Err(e) = self.push_event(member_conn, &ServerEvent::PartyQueued(update.clone())).await {
+                tracing::warn!("Failed to notify party member on connection {}: {:?}", member_conn, e);
+            }
+        }
+        
+        // 返回响应
+        let response = ServerMessage {
+            msg_id: msg.msg_id,
+            event: None,
+            code: 0,
+            data: Some(to_data(&update)?),
+            error: None,
+            correlation_id: correlation::current(),
+        };
+        
+        self.send_message(conn_id, &response).await
+    }
+
+    // 创建私人房间，其他玩家凭邀请码加入
+    async fn handle_lobby_create(&self, conn_id: Uuid, msg: ClientMessage) -> Result<()> {
+        let request: LobbyCreateRequest = serde_json::from_value(msg.data)
+            .map_err(|_| Error::InvalidMessage)?;
+        let state = self.conn_manager.get_connection(&conn_id)
+            .await
+            .ok_or(Error::ConnectionNotFound)?;
+
+        let crossplay = CrossPlay {
+            platforms: self.conn_manager.platforms_of(&[state.user_id]).await,
+            pool: PlatformPool::Any,
+        };
+        let lobby = self.match_service.create_private(state.user_id, &request.match_type, &crossplay).await?;
+        self.conn_manager.update_match_id(&conn_id, Some(lobby.match_id)).await;
+
+        let response = ServerMessage {
+            msg_id: msg.msg_id,
+            event: None,
+            code: 0,
+            data: Some(to_data(&lobby)?),
+            error: None,
+            correlation_id: correlation::current(),
+        };
+        self.send_message(conn_id, &response).await
+    }
+
+    // 凭邀请码加入私人房间
+    async fn handle_lobby_join(&self, conn_id: Uuid, msg: ClientMessage) -> Result<()> {
+        let request: LobbyJoinRequest = serde_json::from_value(msg.data)
+            .map_err(|_| Error::InvalidMessage)?;
+        let state = self.conn_manager.get_connection(&conn_id)
+            .await
+            .ok_or(Error::ConnectionNotFound)?;
+
+        let crossplay = CrossPlay {
+            platforms: self.conn_manager.platforms_of(&[state.user_id]).await,
+            pool: PlatformPool::Any,
+        };
+        let lobby = self.match_service.join_private(state.user_id, &request.code, &crossplay).await?;
+        self.conn_manager.update_match_id(&conn_id, Some(lobby.match_id)).await;
+
+        let response = ServerMessage {
+            msg_id: msg.msg_id,
+            event: None,
+            code: 0,
+            data: Some(to_data(&lobby)?),
+            error: None,
+            correlation_id: correlation::current(),
+        };
+        self.send_message(conn_id, &response).await
+    }
+
+    // 房主开始私人房间的比赛，房间必须已满
+    async fn handle_lobby_start(&self, conn_id: Uuid, msg: ClientMessage) -> Result<()> {
+        let state = self.conn_manager.get_connection(&conn_id)
+            .await
+            .ok_or(Error::ConnectionNotFound)?;
+        let match_id = state.match_id.ok_or(Error::MatchNotFound)?;
+
+        let room = self.match_service.start_private(state.user_id, match_id).await?;
+        let update = MatchUpdate {
+            match_id: room.match_id,
+            status: room.status,
+            match_type: room.match_type,
+            zone_id: room.zone_id,
+            current_players: room.current_players,
+            required_players: room.required_players,
+        };
+
+        let response = ServerMessage {
+            msg_id: msg.msg_id,
+            event: None,
+            code: 0,
+            data: Some(to_data(&update)?),
+            error: None,
+            correlation_id: correlation::current(),
+        };
+        self.send_message(conn_id, &response).await
+    }
+
+    // 私人房间开始前换队：房主可以调整任何人，其他成员只能调整自己
+    async fn handle_lobby_team(&self, conn_id: Uuid, msg: ClientMessage) -> Result<()> {
+        let request: LobbyTeamRequest = serde_json::from_value(msg.data)
+            .map_err(|_| Error::InvalidMessage)?;
+        let state = self.conn_manager.get_connection(&conn_id)
+            .await
+            .ok_or(Error::ConnectionNotFound)?;
+        let match_id = state.match_id.ok_or(Error::MatchNotFound)?;
+
+        let player = request.user_id.unwrap_or(state.user_id);
+        let teams = self.match_service.switch_team(state.user_id, match_id, player, request.team).await?;
+
+        let response = ServerMessage {
+            msg_id: msg.msg_id,
+            event: None,
+            code: 0,
+            data: Some(to_data(&teams)?),
+            error: None,
+            correlation_id: correlation::current(),
+        };
+        self.send_message(conn_id, &response).await
+    }
+
+    // 房主直接踢人，其他成员发起投票，过半数即踢出
+    async fn handle_lobby_kick(&self, conn_id: Uuid, msg: ClientMessage) -> Result<()> {
+        let request: LobbyKickRequest = serde_json::from_value(msg.data)
+            .map_err(|_| Error::InvalidMessage)?;
+        let state = self.conn_manager.get_connection(&conn_id)
+            .await
+            .ok_or(Error::ConnectionNotFound)?;
+        let match_id = state.match_id.ok_or(Error::MatchNotFound)?;
+
+        let vote = self.match_service.kick_private(state.user_id, match_id, request.user_id, request.block).await?;
+
+        let response = ServerMessage {
+            msg_id: msg.msg_id,
+            event: None,
+            code: 0,
+            data: Some(to_data(&vote)?),
+            error: None,
+            correlation_id: correlation::current(),
+        };
+        self.send_message(conn_id, &response).await
+    }
+
+    // 订阅组队招募，不指定比赛类型时接收全部
+    async fn handle_lfg_subscribe(&self, conn_id: Uuid, msg: ClientMessage) -> Result<()> {
+        let request: LfgSubscribeRequest = if msg.data.is_null() {
+            LfgSubscribeRequest::default()
+        } else {
+            serde_json::from_value(msg.data).map_err(|_| Error::InvalidMessage)?
+        };
+        for match_type in &request.match_types {
+            self.match_service.get_required_players(match_type)?;
+        }
+
+        self.conn_manager.set_lfg(&conn_id, Some(request.match_types.clone())).await;
+        let response = ServerMessage {
+            msg_id: msg.msg_id,
+            event: None,
+            code: 0,
+            data: Some(to_data(&LfgSubscription {
+                subscribed: true,
+                match_types: request.match_types,
+            })?),
+            error: None,
+            correlation_id: correlation::current(),
+        };
+        self.send_message(conn_id, &response).await
+    }
+
+    async fn handle_lfg_unsubscribe(&self, conn_id: Uuid, msg: ClientMessage) -> Result<()> {
+        self.conn_manager.set_lfg(&conn_id, None).await;
+        let response = ServerMessage {
+            msg_id: msg.msg_id,
+            event: None,
+            code: 0,
+            data: Some(to_data(&LfgSubscription {
+                subscribed: false,
+                match_types: Vec::new(),
+            })?),
+            error: None,
+            correlation_id: correlation::current(),
+        };
+        self.send_message(conn_id, &response).await
+    }
+
+    // 排队中的玩家发布招募，限频后推送给所有节点上订阅的空闲连接
+    async fn handle_lfg_post(&self, conn_id: Uuid, msg: ClientMessage) -> Result<()> {
+        let state = self.conn_manager.get_connection(&conn_id)
+            .await
+            .ok_or(Error::ConnectionNotFound)?;
+        let Some(match_id) = state.match_id else {
+            return Err(Error::PermissionDenied("not queued for a match".to_string()));
+        };
+        // 私人房间和已开始的比赛不能招募
+        let room = self.match_service.queued_room(match_id, state.user_id)
+            .await
+            .ok_or_else(|| Error::PermissionDenied("not queued for a public match".to_string()))?;
+
+        let post = self.lfg.post(state.user_id, room).await?;
+        self.deliver_lfg(&post).await;
+        if let Err(e) = self.presence.broadcast_lfg(&post).await {
+            tracing::warn!("Failed to send lfg post of user {} to other nodes: {}", state.user_id, e);
+        }
+
+        let response = ServerMessage {
+            msg_id: msg.msg_id,
+            event: None,
+            code: 0,
+            data: Some(to_data(&post)?),
+            error: None,
+            correlation_id: correlation::current(),
+        };
+        self.send_message(conn_id, &response).await
+    }
+
+    // 推送给本节点上订阅了该比赛类型的空闲连接，不含发布者本人
+    async fn deliver_lfg(&self, post: &LfgPost) {
+        let Ok(data) = event_data(&ServerEvent::LfgPost(post.clone())) else {
+            return;
+        };
+        for (conn_id, user_id) in self.conn_manager.lfg_subscribers(&post.match_type).await {
+            if user_id == post.user_id {
+                continue;
+            }
+            self.enqueue(Outgoing {
+                target: Target::Connection(conn_id),
+                event: EVENT_LFG_POST.to_string(),
+                data: data.clone(),
+                publish: false,
+                correlation_id: correlation::current(),
+            }).await;
+        }
+    }
+
+    // 开始一局单人教程比赛，对手是机器人
+    async fn handle_tutorial_start(&self, conn_id: Uuid, msg: ClientMessage) -> Result<()> {
+        let state = self.conn_manager.get_connection(&conn_id)
+            .await
+            .ok_or(Error::ConnectionNotFound)?;
+
+        let started = self.tutorials.start(state.user_id).await?;
+        self.conn_manager.update_match_id(&conn_id, Some(started.match_id)).await;
+
+        let response = ServerMessage {
+            msg_id: msg.msg_id,
+            event: None,
+            code: 0,
+            data: Some(to_data(&started)?),
+            error: None,
+            correlation_id: correlation::current(),
+        };
+        self.send_message(conn_id, &response).await
+    }
+
+    // 玩家的教程进度
+    async fn handle_tutorial_status(&self, conn_id: Uuid, msg: ClientMessage) -> Result<()> {
+        let state = self.conn_manager.get_connection(&conn_id)
+            .await
+            .ok_or(Error::ConnectionNotFound)?;
+
+        let response = ServerMessage {
+            msg_id: msg.msg_id,
+            event: None,
+            code: 0,
+            data: Some(to_data(&self.tutorials.progress(state.user_id).await?)?),
+            error: None,
+            correlation_id: correlation::current(),
+        };
+        self.send_message(conn_id, &response).await
+    }
+
+    // 对反作弊的判定提出申诉，附上当时的信任分和信号记录
+    async fn handle_trust_appeal(&self, conn_id: Uuid, msg: ClientMessage) -> Result<()> {
+        let request: AppealRequest = serde_json::from_value(msg.data)
+            .map_err(|_| Error::InvalidMessage)?;
+
+        let state = self.conn_manager.get_connection(&conn_id)
+            .await
+            .ok_or(Error::ConnectionNotFound)?;
+
+        let receipt = self.appeals.submit(state.user_id, &request.message).await?;
+
+        let response = ServerMessage {
+            msg_id: msg.msg_id,
+            event: None,
+            code: 0,
+            data: Some(to_data(&receipt)?),
+            error: None,
+            correlation_id: correlation::current(),
+        };
+        self.send_message(conn_id, &response).await
+    }
+
+    // 玩家的通知偏好，未设置过的返回默认值
+    async fn handle_user_preferences(&self, conn_id: Uuid, msg: ClientMessage) -> Result<()> {
+        let state = self.conn_manager.get_connection(&conn_id)
+            .await
+            .ok_or(Error::ConnectionNotFound)?;
+
+        let response = ServerMessage {
+            msg_id: msg.msg_id,
+            event: None,
+            code: 0,
+            data: Some(to_data(&self.preferences.get(state.user_id).await?)?),
+            error: None,
+            correlation_id: correlation::current(),
+        };
+        self.send_message(conn_id, &response).await
+    }
+
+    // 修改通知偏好，未给出的字段保持不变
+    async fn handle_update_preferences(&self, conn_id: Uuid, msg: ClientMessage) -> Result<()> {
+        let update: PreferencesUpdate = serde_json::from_value(msg.data)
+            .map_err(|_| Error::InvalidMessage)?;
+        let state = self.conn_manager.get_connection(&conn_id)
+            .await
+            .ok_or(Error::ConnectionNotFound)?;
+
+        let preferences = self.preferences.update(state.user_id, update).await?;
+
+        let response = ServerMessage {
+            msg_id: msg.msg_id,
+            event: None,
+            code: 0,
+            data: Some(to_data(&preferences)?),
+            error: None,
+            correlation_id: correlation::current(),
+        };
+        self.send_message(conn_id, &response).await
+    }
+
+    // 预约一场私人比赛，由当前玩家做房主；受邀玩家收到 schedule.invited
+    async fn handle_schedule_create(&self, conn_id: Uuid, msg: ClientMessage) -> Result<()> {
+        let request: ScheduleCreateRequest = serde_json::from_value(msg.data)
+            .map_err(|_| Error::InvalidMessage)?;
+        let state = self.conn_manager.get_connection(&conn_id)
+            .await
+            .ok_or(Error::ConnectionNotFound)?;
+
+        let schedule = self.schedules
+            .create(state.user_id, &request.match_type, request.starts_at, request.invited, request.min_players)
+            .await?;
+
+        let response = ServerMessage {
+            msg_id: msg.msg_id,
+            event: None,
+            code: 0,
+            data: Some(to_data(&schedule)?),
+            error: None,
+            correlation_id: correlation::current(),
+        };
+        self.send_message(conn_id, &response).await
+    }
+
+    // 房主在房间开放前取消预约
+    async fn handle_schedule_cancel(&self, conn_id: Uuid, msg: ClientMessage) -> Result<()> {
+        let request: ScheduleCancelRequest = serde_json::from_value(msg.data)
+            .map_err(|_| Error::InvalidMessage)?;
+        let state = self.conn_manager.get_connection(&conn_id)
+            .await
+            .ok_or(Error::ConnectionNotFound)?;
+
+        let schedule = self.schedules.cancel(state.user_id, request.id).await?;
+
+        let response = ServerMessage {
+            msg_id: msg.msg_id,
+            event: None,
+            code: 0,
+            data: Some(to_data(&schedule)?),
+            error: None,
+            correlation_id: correlation::current(),
+        };
+        self.send_message(conn_id, &response).await
+    }
+
+    // 玩家主持或受邀的预约比赛
+    async fn handle_schedule_list(&self, conn_id: Uuid, msg: ClientMessage) -> Result<()> {
+        let state = self.conn_manager.get_connection(&conn_id)
+            .await
+            .ok_or(Error::ConnectionNotFound)?;
+
+        let response = ServerMessage {
+            msg_id: msg.msg_id,
+            event: None,
+            code: 0,
+            data: Some(to_data(&ScheduleList {
+                schedules: self.schedules.for_user(state.user_id).await?,
+            })?),
+            error: None,
+            correlation_id: correlation::current(),
+        };
+        self.send_message(conn_id, &response).await
+    }
+
+    // 取消匹配
+    async fn handle_match_cancel(&self, conn_id: Uuid, msg: ClientMessage) -> Result<()> {
+        let state = self.conn_manager.get_connection(&conn_id)
+            .await
+            .ok_or(Error::ConnectionNotFound)?;
+        
+        if let Some(match_id) = state.match_id {
+            // 从匹配池中移除
+            self.match_service.leave_match(state.user_id, match_id).await?;
+            self.conn_manager.update_match_id(&conn_id, None).await;
+        }
+
+        let response = ServerMessage {
+            msg_id: msg.msg_id,
+            event: None,
+            code: 0,
+            data: Some(to_data(&CancelReply {
+                status: "cancelled".to_string(),
+            })?),
+            error: None,
+            correlation_id: correlation::current(),
+        };
+        
+        self.send_message(conn_id, &response).await
+    }
+
+    // 观看本节点上正在进行的公开比赛，比玩家晚 spectator_delay 看到
+    async fn handle_match_spectate(&self, conn_id: Uuid, msg: ClientMessage) -> Result<()> {
+        let request: SpectateRequest = serde_json::from_value(msg.data)
+            .map_err(|_| Error::InvalidMessage)?;
+
+        let state = self.conn_manager.get_connection(&conn_id)
+            .await
+            .ok_or(Error::ConnectionNotFound)?;
+        if state.match_id.is_some() {
+            return Err(Error::UserAlreadyInMatch);
+        }
+        if !self.match_service.spectatable(request.match_id).await {
+            return Err(Error::MatchNotFound);
+        }
+        self.conn_manager.set_spectating(&conn_id, Some(request.match_id)).await;
+
+        let response = ServerMessage {
+            msg_id: msg.msg_id,
+            event: None,
+            code: 0,
+            data: Some(to_data(&SpectateReply {
+                match_id: Some(request.match_id),
+                delay_ms: self.spectators.delay().as_millis() as u64,
+            })?),
+            error: None,
+            correlation_id: correlation::current(),
+        };
+        self.send_message(conn_id, &response).await
+    }
+
+    async fn handle_match_unspectate(&self, conn_id: Uuid, msg: ClientMessage) -> Result<()> {
+        self.conn_manager.set_spectating(&conn_id, None).await;
+
+        let response = ServerMessage {
+            msg_id: msg.msg_id,
+            event: None,
+            code: 0,
+            data: Some(to_data(&SpectateReply {
+                match_id: None,
+                delay_ms: self.spectators.delay().as_millis() as u64,
+            })?),
+            error: None,
+            correlation_id: correlation::current(),
+        };
+        self.send_message(conn_id, &response).await
+    }
+
+    // 处理心跳检测
+    async fn handle_ping(&self, conn_id: Uuid, msg: ClientMessage) -> Result<()> {
+        // 检查比赛状态
+        let state = self.conn_manager.get_connection(&conn_id)
+            .await
+            .ok_or(Error::ConnectionNotFound)?;
+        
+        // 如果在比赛中，检查比赛进度
+        let match_status = if let Some(match_id) = state.match_id {
+            let status = self.match_service.get_match_status(match_id).await?;
+            Some(status)
+        } else {
+            None
+        };
+
+        let response = ServerMessage {
+            msg_id: msg.msg_id,
+            event: None,
+            code: 0,
+            data: Some(to_data(&Pong {
+                time: chrono::Utc::now(),
+                match_status,
+            })?),
+            error: None,
+            correlation_id: correlation::current(),
+        };
+        
+        self.send_message(conn_id, &response).await
+    }
+
+    // 客户端发现状态版本不连续时请求完整快照
+    async fn handle_state_resync(&self, conn_id: Uuid, msg: ClientMessage) -> Result<()> {
+        let request: StateResyncRequest = if msg.data.is_null() {
+            StateResyncRequest::default()
+        } else {
+            serde_json::from_value(msg.data).map_err(|_| Error::InvalidMessage)?
+        };
+
+        let state = self.conn_manager.get_connection(&conn_id)
+            .await
             .ok_or(Error::ConnectionNotFound)?;
         
-        // 如果在比赛中，检查比赛进度
-        let match_status = if let Some(match_id) = state.match_id {
-            let status = self.match_service.get_match_status(match_id).await?;
-            Some(status)
+        // 只能同步自己所在的比赛
+        let match_id = state.match_id.ok_or(Error::MatchNotFound)?;
+        if request.match_id.is_some_and(|id| id != match_id) {
+            return Err(Error::PermissionDenied("not in this match".to_string()));
+        }
+        let mut snapshot = self.match_states.snapshot(match_id)
+            .await
+            .ok_or(Error::MatchNotFound)?;
+        snapshot.pings = self.match_states.active_pings(match_id, state.user_id).await;
+
+        let response = ServerMessage {
+            msg_id: msg.msg_id,
+            event: None,
+            code: 0,
+            data: Some(to_data(&snapshot)?),
+            error: None,
+            correlation_id: correlation::current(),
+        };
+        
+        self.send_message(conn_id, &response).await
+    }
+
+    // 上报位置：先做防作弊检测，开启游戏循环时合并到下一个 tick，否则立即转发
+    async fn handle_position(&self, conn_id: Uuid, msg: ClientMessage) -> Result<()> {
+        let report: PositionReport = serde_json::from_value(msg.data)
+            .map_err(|_| Error::InvalidMessage)?;
+
+        let state = self.conn_manager.get_connection(&conn_id)
+            .await
+            .ok_or(Error::ConnectionNotFound)?;
+        let match_id = state.match_id.ok_or(Error::MatchNotFound)?;
+
+        // 比赛由其他节点运行时，交给该节点处理
+        if let Some(owner) = self.match_service.remote_owner(match_id).await {
+            let data = serde_json::to_value(&report).map_err(|_| Error::InvalidMessage)?;
+            return self.presence.forward_command(&owner, match_id, state.user_id, &msg.cmd, data).await;
+        }
+
+        self.apply_position(match_id, state.user_id, report).await
+    }
+
+    async fn apply_position(&self, match_id: Uuid, user_id: Uuid, report: PositionReport) -> Result<()> {
+        // 可疑的位置仍然转发，只降低信任分
+        let position = report.position;
+        let signals = self.match_service.trust().observe(user_id, &position, report.mock_location).await;
+
+        // 没有游戏循环时立即转发给可见该位置的玩家
+        let relay = self.game.submit_position(match_id, user_id, position.clone()).await?;
+
+        // 只有可信的位置才写入热力图
+        if signals.is_empty() && !self.match_service.trust().is_suspected(user_id).await {
+            self.heatmap.record(match_id, user_id, &position).await;
+        }
+
+        if let Some(relay) = relay {
+            let event = ServerEvent::Position(relay.update);
+            self.send_to_players(match_id, &relay.recipients, &event).await?;
+            if !self.conn_manager.get_spectators(match_id).await.is_empty() {
+                self.hold_for_spectators(match_id, &event).await;
+            }
+        }
+        
+        Ok(())
+    }
+
+    // 在地图上标记位置，转发给队友（包括自己）；限制频率，到期自动消失
+    async fn handle_map_ping(&self, conn_id: Uuid, msg: ClientMessage) -> Result<()> {
+        let request: PingRequest = serde_json::from_value(msg.data)
+            .map_err(|_| Error::InvalidMessage)?;
+
+        let state = self.conn_manager.get_connection(&conn_id)
+            .await
+            .ok_or(Error::ConnectionNotFound)?;
+        let match_id = state.match_id.ok_or(Error::MatchNotFound)?;
+
+        // 比赛由其他节点运行时，交给该节点处理
+        if let Some(owner) = self.match_service.remote_owner(match_id).await {
+            let data = serde_json::to_value(&request).map_err(|_| Error::InvalidMessage)?;
+            return self.presence.forward_command(&owner, match_id, state.user_id, &msg.cmd, data).await;
+        }
+
+        self.apply_map_ping(match_id, state.user_id, request).await
+    }
+
+    async fn apply_map_ping(&self, match_id: Uuid, user_id: Uuid, request: PingRequest) -> Result<()> {
+        let (ping, team) = self.match_states.add_ping(match_id, user_id, request, &self.config.game.pings).await?;
+        self.send_to_players(match_id, &team, &ServerEvent::Ping(ping)).await
+    }
+
+    // 发送预设表情或快捷语；只接受目录中的 id，限制频率
+    async fn handle_emote(&self, conn_id: Uuid, msg: ClientMessage) -> Result<()> {
+        let request: EmoteRequest = serde_json::from_value(msg.data)
+            .map_err(|_| Error::InvalidMessage)?;
+        if emote::find(&request.emote).is_none() {
+            return Err(Error::InvalidMessage);
+        }
+
+        let state = self.conn_manager.get_connection(&conn_id)
+            .await
+            .ok_or(Error::ConnectionNotFound)?;
+        let match_id = state.match_id.ok_or(Error::MatchNotFound)?;
+
+        // 比赛由其他节点运行时，交给该节点处理
+        if let Some(owner) = self.match_service.remote_owner(match_id).await {
+            let data = serde_json::to_value(&request).map_err(|_| Error::InvalidMessage)?;
+            return self.presence.forward_command(&owner, match_id, state.user_id, &msg.cmd, data).await;
+        }
+
+        self.apply_emote(match_id, state.user_id, request).await
+    }
+
+    async fn apply_emote(&self, match_id: Uuid, user_id: Uuid, request: EmoteRequest) -> Result<()> {
+        let entry = emote::find(&request.emote).ok_or(Error::InvalidMessage)?;
+        let recipients = self.match_states
+            .emote_recipients(match_id, user_id, entry.audience, &self.config.game.emotes)
+            .await?;
+        let event = EmoteEvent {
+            user_id,
+            emote: entry.id.to_string(),
+            sent_at: chrono::Utc::now(),
+        };
+        self.send_to_players(match_id, &recipients, &ServerEvent::Emote(event)).await
+    }
+
+    // 玩家报告找到宝藏；分数取宝藏的基础分，不信任客户端。
+    // 结果通过 match.discovery 广播
+    async fn handle_discover(&self, conn_id: Uuid, msg: ClientMessage) -> Result<()> {
+        let request: DiscoverRequest = serde_json::from_value(msg.data)
+            .map_err(|_| Error::InvalidMessage)?;
+
+        let state = self.conn_manager.get_connection(&conn_id)
+            .await
+            .ok_or(Error::ConnectionNotFound)?;
+        let match_id = state.match_id.ok_or(Error::MatchNotFound)?;
+
+        // 比赛由其他节点运行时，交给该节点处理
+        if let Some(owner) = self.match_service.remote_owner(match_id).await {
+            let data = serde_json::to_value(&request).map_err(|_| Error::InvalidMessage)?;
+            return self.presence.forward_command(&owner, match_id, state.user_id, &msg.cmd, data).await;
+        }
+
+        self.apply_discover(match_id, state.user_id, request).await
+    }
+
+    async fn apply_discover(&self, match_id: Uuid, user_id: Uuid, request: DiscoverRequest) -> Result<()> {
+        let team_id = self.match_states.playing_team(match_id, user_id).await?;
+        self.match_service.record_discovery(match_id, team_id, user_id, request.treasure_id, None).await
+    }
+
+    // 大厅内的队伍聊天，只发给队友（包括自己）；限制长度和频率
+    async fn handle_lobby_chat(&self, conn_id: Uuid, msg: ClientMessage) -> Result<()> {
+        let request: LobbyChatRequest = serde_json::from_value(msg.data)
+            .map_err(|_| Error::InvalidMessage)?;
+
+        let state = self.conn_manager.get_connection(&conn_id)
+            .await
+            .ok_or(Error::ConnectionNotFound)?;
+        let match_id = state.match_id.ok_or(Error::MatchNotFound)?;
+
+        // 比赛由其他节点运行时，交给该节点处理
+        if let Some(owner) = self.match_service.remote_owner(match_id).await {
+            let data = serde_json::to_value(&request).map_err(|_| Error::InvalidMessage)?;
+            return self.presence.forward_command(&owner, match_id, state.user_id, &msg.cmd, data).await;
+        }
+
+        self.apply_lobby_chat(match_id, state.user_id, request).await
+    }
+
+    async fn apply_lobby_chat(&self, match_id: Uuid, user_id: Uuid, request: LobbyChatRequest) -> Result<()> {
+        let text = request.text.trim();
+        if text.is_empty() || text.chars().count() > self.config.game.lobby.chat_max_len {
+            return Err(Error::InvalidMessage);
+        }
+        let recipients = self.match_states
+            .lobby_chat_recipients(match_id, user_id, &self.config.game.lobby)
+            .await?;
+        let message = LobbyChatMessage {
+            user_id,
+            text: text.to_string(),
+            sent_at: chrono::Utc::now(),
+        };
+        self.send_to_players(match_id, &recipients, &ServerEvent::LobbyChat(message)).await
+    }
+
+    // 大厅内选择装备和角色；队友通过 lobby.selection 事件得知
+    async fn handle_lobby_select(&self, conn_id: Uuid, msg: ClientMessage) -> Result<()> {
+        let selection: LobbySelection = serde_json::from_value(msg.data)
+            .map_err(|_| Error::InvalidMessage)?;
+
+        let state = self.conn_manager.get_connection(&conn_id)
+            .await
+            .ok_or(Error::ConnectionNotFound)?;
+        let match_id = state.match_id.ok_or(Error::MatchNotFound)?;
+
+        // 比赛由其他节点运行时，交给该节点处理，原样回复请求
+        let selection = if let Some(owner) = self.match_service.remote_owner(match_id).await {
+            let data = serde_json::to_value(&selection).map_err(|_| Error::InvalidMessage)?;
+            self.presence.forward_command(&owner, match_id, state.user_id, &msg.cmd, data).await?;
+            selection
         } else {
-            None
+            self.match_service.select_loadout(match_id, state.user_id, selection).await?
+        };
+
+        let response = ServerMessage {
+            msg_id: msg.msg_id,
+            event: None,
+            code: 0,
+            data: Some(to_data(&selection)?),
+            error: None,
+            correlation_id: correlation::current(),
+        };
+        self.send_message(conn_id, &response).await
+    }
+
+    // 语音信令（offer/answer/ice）原样转给同队的一名队友，媒体不经过服务器
+    async fn handle_voice_signal(&self, conn_id: Uuid, msg: ClientMessage) -> Result<()> {
+        let state = self.conn_manager.get_connection(&conn_id)
+            .await
+            .ok_or(Error::ConnectionNotFound)?;
+        let match_id = state.match_id.ok_or(Error::MatchNotFound)?;
+
+        // 比赛由其他节点运行时，交给该节点校验队伍并转发
+        if let Some(owner) = self.match_service.remote_owner(match_id).await {
+            return self.presence.forward_command(&owner, match_id, state.user_id, &msg.cmd, msg.data).await;
+        }
+
+        self.relay_voice(match_id, state.user_id, &msg.cmd, msg.data).await
+    }
+
+    async fn relay_voice(&self, match_id: Uuid, user_id: Uuid, cmd: &str, data: serde_json::Value) -> Result<()> {
+        if cmd == "voice.ice" {
+            let request: VoiceIceRequest = serde_json::from_value(data)
+                .map_err(|_| Error::InvalidMessage)?;
+            if request.candidate.to_string().len() > MAX_CANDIDATE_LEN {
+                return Err(Error::InvalidMessage);
+            }
+            self.match_states.check_teammates(match_id, user_id, request.to).await?;
+            let signal = VoiceIce { from: user_id, candidate: request.candidate };
+            return self.send_to_players(match_id, &[request.to], &ServerEvent::VoiceIce(signal)).await;
+        }
+
+        let request: VoiceSdpRequest = serde_json::from_value(data)
+            .map_err(|_| Error::InvalidMessage)?;
+        if request.sdp.is_empty() || request.sdp.len() > MAX_SDP_LEN {
+            return Err(Error::InvalidMessage);
+        }
+        self.match_states.check_teammates(match_id, user_id, request.to).await?;
+        let signal = VoiceSdp { from: user_id, sdp: request.sdp };
+        let event = if cmd == "voice.offer" { ServerEvent::VoiceOffer(signal) } else { ServerEvent::VoiceAnswer(signal) };
+        self.send_to_players(match_id, &[request.to], &event).await
+    }
+
+    // 为比赛中的玩家签发短期 TURN 凭据；未配置 TURN 时返回 NotFound
+    async fn handle_voice_turn(&self, conn_id: Uuid, msg: ClientMessage) -> Result<()> {
+        let state = self.conn_manager.get_connection(&conn_id)
+            .await
+            .ok_or(Error::ConnectionNotFound)?;
+        if state.match_id.is_none() {
+            return Err(Error::MatchNotFound);
+        }
+        let turn = self.config.game.turn.as_ref()
+            .ok_or_else(|| Error::NotFound("TURN server".to_string()))?;
+
+        let response = ServerMessage {
+            msg_id: msg.msg_id,
+            event: None,
+            code: 0,
+            data: Some(to_data(&voice::turn_credentials(turn, state.user_id))?),
+            error: None,
+            correlation_id: correlation::current(),
+        };
+        self.send_message(conn_id, &response).await
+    }
+
+    // 推送给比赛中的这些玩家，连接在其他节点上的经集群转发
+    async fn send_to_players(&self, match_id: Uuid, players: &[Uuid], event: &ServerEvent) -> Result<()> {
+        let data = event_data(event)?;
+        let mut local = HashSet::new();
+        for (member_conn, member) in self.conn_manager.get_match_members(match_id).await {
+            if players.contains(&member) {
+                local.insert(member);
+                if let Err(e) = self.push_raw(member_conn, event.name(), &data).await {
+                    tracing::warn!("Failed to push {} to connection {}: {:?}", event.name(), member_conn, e);
+                }
+            }
+        }
+        let remote: Vec<Uuid> = players.iter().filter(|player| !local.contains(player)).copied().collect();
+        if !remote.is_empty() {
+            self.forward_to_remote_users(&remote, event).await;
+        }
+        Ok(())
+    }
+
+    // 客户端上报网络质量，用于调整该连接的推送频率
+    async fn handle_net_report(&self, conn_id: Uuid, msg: ClientMessage) -> Result<()> {
+        let request: NetReportRequest = serde_json::from_value(msg.data)
+            .map_err(|_| Error::InvalidMessage)?;
+        if !(0.0..=1.0).contains(&request.packet_loss) {
+            return Err(Error::InvalidMessage);
+        }
+
+        let quality = LinkQuality::classify(request.rtt_ms, request.packet_loss);
+        METRICS.record_net_report(request.rtt_ms, request.packet_loss);
+        self.conn_manager.update_net_report(&conn_id, NetReport {
+            rtt_ms: request.rtt_ms,
+            packet_loss: request.packet_loss,
+            quality,
+            reported_at: std::time::Instant::now(),
+        }).await;
+
+        let response = ServerMessage {
+            msg_id: msg.msg_id,
+            event: None,
+            code: 0,
+            data: Some(to_data(&NetReportReply {
+                quality,
+                tick_stride: quality.tick_stride(),
+            })?),
+            error: None,
+            correlation_id: correlation::current(),
+        };
+        
+        self.send_message(conn_id, &response).await
+    }
+
+    // 客户端埋点事件，抽样后异步写库；成功时不回复
+    async fn handle_telemetry(&self, conn_id: Uuid, msg: ClientMessage) -> Result<()> {
+        let event: TelemetryEvent = serde_json::from_value(msg.data)
+            .map_err(|_| Error::InvalidMessage)?;
+
+        let state = self.conn_manager.get_connection(&conn_id)
+            .await
+            .ok_or(Error::ConnectionNotFound)?;
+
+        let ack = self.telemetry.submit(Some(state.user_id), "ws", vec![event])?;
+        if let Some(rejection) = ack.errors.first() {
+            let reason = match &rejection.field {
+                Some(field) => format!("{} ({})", rejection.reason.to_str(), field),
+                None => rejection.reason.to_str().to_string(),
+            };
+            return Err(Error::InvalidTelemetry(reason));
+        }
+        Ok(())
+    }
+
+    // 时钟同步：回传客户端发送时间以及服务器收到/发出的时间
+    async fn handle_time_sync(&self, conn_id: Uuid, msg: ClientMessage, received_at: i64) -> Result<()> {
+        let request: TimeSyncRequest = serde_json::from_value(msg.data)
+            .map_err(|_| Error::InvalidMessage)?;
+
+        let reply = TimeSyncReply {
+            client_send_time: request.client_send_time,
+            server_receive_time: received_at,
+            server_transmit_time: chrono::Utc::now().timestamp_millis(),
+        };
+        let response = ServerMessage {
+            msg_id: msg.msg_id,
+            event: None,
+            code: 0,
+            data: Some(to_data(&reply)?),
+            error: None,
+            correlation_id: correlation::current(),
         };
+        
+        self.send_message(conn_id, &response).await
+    }
+
+    // 运营后台订阅所有比赛的实时统计，直到连接断开
+    async fn handle_admin_watch(self: &Arc<Self>, conn_id: Uuid, msg: ClientMessage) -> Result<()> {
+        let request: AdminWatchRequest = serde_json::from_value(msg.data)
+            .map_err(|_| Error::InvalidMessage)?;
+        admin::verify_token(&self.config.admin, &request.token)?;
+
+        let interval_ms = request.interval_ms.unwrap_or(1000).max(250);
+        if self.conn_manager.start_admin_watch(&conn_id).await {
+            let handler = self.clone();
+            supervisor::spawn("admin_watch", async move {
+                let mut interval = tokio::time::interval(Duration::from_millis(interval_ms));
+                loop {
+                    interval.tick().await;
+                    if handler.conn_manager.get_connection(&conn_id).await.is_none() {
+                        break;
+                    }
+                    let report = MatchStatsReport {
+                        matches: handler.match_stats().await,
+                    };
+                    let Ok(message) = Outgoing::new(Target::Connection(conn_id), &ServerEvent::AdminMatches(report), false) else {
+                        break;
+                    };
+                    handler.enqueue(message).await;
+                }
+            });
+        }
 
         let response = ServerMessage {
             msg_id: msg.msg_id,
+            event: None,
             code: 0,
-            data: Some(json!({
-                "time": chrono::Utc::now(),
-                "match_status": match_status
-            })),
+            data: Some(to_data(&AdminWatchReply {
+                watching: true,
+                interval_ms,
+            })?),
             error: None,
+            correlation_id: correlation::current(),
         };
         
         self.send_message(conn_id, &response).await
     }
 
-    async fn handle_message(&self, conn_id: Uuid, text: &str) -> Result<()> {
+    // 运营发布公告，立即或定时推送给所有连接或指定人群
+    async fn handle_admin_announce(&self, conn_id: Uuid, msg: ClientMessage) -> Result<()> {
+        let request: AdminAnnounceRequest = serde_json::from_value(msg.data)
+            .map_err(|_| Error::InvalidMessage)?;
+        admin::verify_token(&self.config.admin, &request.token)?;
+        let content_key = request.content_key.map(|key| key.trim().to_string()).filter(|key| !key.is_empty());
+        if let Some(key) = &content_key && self.content.text(&self.content.resolve(None), key).is_none() {
+            return Err(Error::NotFound(format!("content {}", key)));
+        }
+
+        let announcement = self.announcements
+            .announce(&request.message, content_key, request.segment, request.send_at, &request.created_by)
+            .await?;
+
+        let response = ServerMessage {
+            msg_id: msg.msg_id,
+            event: None,
+            code: 0,
+            data: Some(to_data(&announcement)?),
+            error: None,
+            correlation_id: correlation::current(),
+        };
+
+        self.send_message(conn_id, &response).await
+    }
+
+    // 登记或更新当前设备；连接时没有带设备 ID 的会话从此归属该设备
+    async fn handle_device_register(&self, conn_id: Uuid, msg: ClientMessage) -> Result<()> {
+        let registration: DeviceRegistration = serde_json::from_value(msg.data)
+            .map_err(|_| Error::InvalidMessage)?;
+        let state = self.conn_manager.get_connection(&conn_id).await
+            .ok_or(Error::ConnectionNotFound)?;
+
+        let device = self.devices.register(state.user_id, registration, state.platform).await?;
+        if state.device_id.as_deref() != Some(device.device_id.as_str()) {
+            self.conn_manager.set_device(&conn_id, &device.device_id).await;
+            self.replace_sessions(conn_id, state.user_id, Some(&device.device_id)).await;
+        }
+
+        let response = ServerMessage {
+            msg_id: msg.msg_id,
+            event: None,
+            code: 0,
+            data: Some(to_data(&device)?),
+            error: None,
+            correlation_id: correlation::current(),
+        };
+
+        self.send_message(conn_id, &response).await
+    }
+
+    // 列出玩家登记的设备
+    async fn handle_device_list(&self, conn_id: Uuid, msg: ClientMessage) -> Result<()> {
+        let state = self.conn_manager.get_connection(&conn_id).await
+            .ok_or(Error::ConnectionNotFound)?;
+
+        let response = ServerMessage {
+            msg_id: msg.msg_id,
+            event: None,
+            code: 0,
+            data: Some(to_data(&DeviceList {
+                devices: self.devices.list(state.user_id).await?,
+            })?),
+            error: None,
+            correlation_id: correlation::current(),
+        };
+
+        self.send_message(conn_id, &response).await
+    }
+
+    // 删除玩家的一台设备并关闭它的会话；先回复，撤销的可能正是当前设备
+    async fn handle_device_revoke(&self, conn_id: Uuid, msg: ClientMessage) -> Result<()> {
+        let revocation: DeviceRevocation = serde_json::from_value(msg.data)
+            .map_err(|_| Error::InvalidMessage)?;
+        let state = self.conn_manager.get_connection(&conn_id).await
+            .ok_or(Error::ConnectionNotFound)?;
+
+        self.devices.revoke(state.user_id, &revocation.device_id).await?;
+
+        let response = ServerMessage {
+            msg_id: msg.msg_id,
+            event: None,
+            code: 0,
+            data: Some(to_data(&revocation)?),
+            error: None,
+            correlation_id: correlation::current(),
+        };
+        let result = self.send_message(conn_id, &response).await;
+
+        self.end_device_sessions(state.user_id, &revocation.device_id).await;
+        result
+    }
+
+    async fn handle_message(self: &Arc<Self>, conn_id: Uuid, text: &str) -> Result<()> {
+        // 尽早记录收到时间，供时钟同步使用
+        let received_at = chrono::Utc::now().timestamp_millis();
         let client_msg: ClientMessage = serde_json::from_str(text)
             .map_err(|_| Error::InvalidMessage)?;
+        tracing::Span::current().record("cmd", client_msg.cmd.as_str());
+        latency::parsed();
 
-        match client_msg.cmd.as_str() {
+        let cmd = client_msg.cmd.clone();
+        let started = std::time::Instant::now();
+        // 未知命令不单独计入延迟指标，避免客户端随意制造标签
+        let mut known = true;
+        let result = match cmd.as_str() {
             "match.start" => self.handle_match_start(conn_id, client_msg).await,
             "match.cancel" => self.handle_match_cancel(conn_id, client_msg).await,
+            "match.spectate" => self.handle_match_spectate(conn_id, client_msg).await,
+            "match.unspectate" => self.handle_match_unspectate(conn_id, client_msg).await,
             "sys.ping" => self.handle_ping(conn_id, client_msg).await,
-            _ => Err(Error::InvalidMessage),
-        }
+            "sys.time_sync" => self.handle_time_sync(conn_id, client_msg, received_at).await,
+            "sys.net_report" => self.handle_net_report(conn_id, client_msg).await,
+            "state.resync" => self.handle_state_resync(conn_id, client_msg).await,
+            "game.position" => self.handle_position(conn_id, client_msg).await,
+            "game.ping" => self.handle_map_ping(conn_id, client_msg).await,
+            "game.emote" => self.handle_emote(conn_id, client_msg).await,
+            "game.discover" => self.handle_discover(conn_id, client_msg).await,
+            "lobby.chat" => self.handle_lobby_chat(conn_id, client_msg).await,
+            "lobby.select" => self.handle_lobby_select(conn_id, client_msg).await,
+            "lobby.create" => self.handle_lobby_create(conn_id, client_msg).await,
+            "lobby.join" => self.handle_lobby_join(conn_id, client_msg).await,
+            "lobby.start" => self.handle_lobby_start(conn_id, client_msg).await,
+            "lobby.team" => self.handle_lobby_team(conn_id, client_msg).await,
+            "lobby.kick" => self.handle_lobby_kick(conn_id, client_msg).await,
+            "schedule.create" => self.handle_schedule_create(conn_id, client_msg).await,
+            "schedule.cancel" => self.handle_schedule_cancel(conn_id, client_msg).await,
+            "schedule.list" => self.handle_schedule_list(conn_id, client_msg).await,
+            "lfg.subscribe" => self.handle_lfg_subscribe(conn_id, client_msg).await,
+            "lfg.unsubscribe" => self.handle_lfg_unsubscribe(conn_id, client_msg).await,
+            "lfg.post" => self.handle_lfg_post(conn_id, client_msg).await,
+            "tutorial.start" => self.handle_tutorial_start(conn_id, client_msg).await,
+            "tutorial.status" => self.handle_tutorial_status(conn_id, client_msg).await,
+            "trust.appeal" => self.handle_trust_appeal(conn_id, client_msg).await,
+            "user.preferences" => self.handle_user_preferences(conn_id, client_msg).await,
+            "user.update_preferences" => self.handle_update_preferences(conn_id, client_msg).await,
+            "voice.offer" | "voice.answer" | "voice.ice" => self.handle_voice_signal(conn_id, client_msg).await,
+            "voice.turn" => self.handle_voice_turn(conn_id, client_msg).await,
+            "admin.watch_matches" => self.handle_admin_watch(conn_id, client_msg).await,
+            "admin.announce" => self.handle_admin_announce(conn_id, client_msg).await,
+            "telemetry.event" => self.handle_telemetry(conn_id, client_msg).await,
+            "device.register" => self.handle_device_register(conn_id, client_msg).await,
+            "device.list" => self.handle_device_list(conn_id, client_msg).await,
+            "device.revoke" => self.handle_device_revoke(conn_id, client_msg).await,
+            _ => {
+                known = false;
+                Err(Error::InvalidMessage)
+            }
+        };
+        latency::served(if known { cmd.as_str() } else { "unknown" }, started.elapsed());
+        slow::command(&cmd, started.elapsed());
+        result
+    }
+}
+
+// sys.banned 通知和随后的关闭帧
+pub fn ban_frames(notice: &BanNotice) -> Vec<Message> {
+    let close = Message::Close(Some(CloseFrame {
+        code: close_code::POLICY,
+        reason: "banned".into(),
+    }));
+    let msg = ServerEvent::Banned(notice.clone()).to_message();
+    match msg.and_then(|msg| serde_json::to_string(&msg)) {
+        Ok(text) => vec![Message::Text(text), close],
+        Err(_) => vec![close],
+    }
+}
+
+// sys.session_ended 通知和随后的关闭帧
+pub fn session_end_frames(notice: &SessionEnded) -> Vec<Message> {
+    let reason = match notice.reason {
+        SessionEndReason::Replaced => "session replaced",
+        SessionEndReason::Revoked => "device revoked",
+    };
+    let close = Message::Close(Some(CloseFrame {
+        code: close_code::POLICY,
+        reason: reason.into(),
+    }));
+    let msg = ServerEvent::SessionEnded(notice.clone()).to_message();
+    match msg.and_then(|msg| serde_json::to_string(&msg)) {
+        Ok(text) => vec![Message::Text(text), close],
+        Err(_) => vec![close],
     }
+}
+
+// 把协议载荷序列化为 ServerMessage.data
+fn to_data<T: Serialize>(payload: &T) -> Result<serde_json::Value> {
+    serde_json::to_value(payload).map_err(|_| Error::InvalidMessage)
+}
+
+// 事件载荷，即 ServerMessage.data
+fn event_data(event: &ServerEvent) -> Result<serde_json::Value> {
+    event.data().map_err(|_| Error::InvalidMessage)
+}
+
+// 事件对应的通知类别，玩家可按类别关闭推送
+fn notification_kind(event: &ServerEvent) -> NotificationKind {
+    match event {
+        ServerEvent::MatchFound(_) => NotificationKind::MatchFound,
+        ServerEvent::Announcement(_) => NotificationKind::Announcement,
+        _ => NotificationKind::Other,
+    }
+}
 
+fn gzip(data: &[u8]) -> Result<Vec<u8>> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::fast());
+    encoder.write_all(data)
+        .and_then(|_| encoder.finish())
+        .map_err(|e| Error::WsError(e.to_string()))
 }
\ No newline at end of file
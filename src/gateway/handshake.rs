@@ -0,0 +1,151 @@
+use std::sync::Arc;
+
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use uuid::Uuid;
+
+use crate::announcements::announcement::AnnouncementNotice;
+use crate::announcements::service::AnnouncementService;
+use crate::config::HandshakeConfig;
+use crate::content::bundles::ContentBundles;
+use crate::experiments::service::ExperimentService;
+use crate::matchmaking::service::MatchService;
+use crate::preferences::preferences::NotificationKind;
+use crate::preferences::service::PreferenceService;
+use crate::remote_config::service::RemoteConfigService;
+use super::protocol::{PROTOCOL_VERSION, StateSnapshot, Welcome};
+
+// A session as it opens, as far as the welcome event needs it
+pub struct Session<'a> {
+    pub conn_id: Uuid,
+    pub user_id: Uuid,
+    pub compress: bool,
+    // Resolved content locale
+    pub locale: String,
+    // State of the player's match, if they are in one on this node
+    pub match_state: Option<StateSnapshot>,
+    // ?resume= of the connect request
+    pub resume_token: Option<&'a str>,
+    // Inbox messages delivered right after the welcome
+    pub pending_inbox: usize,
+}
+
+// Builds the sys.welcome event that opens every WebSocket and SSE session.
+//
+// Besides what the client needs to start (protocol version, server time,
+// capabilities, feature flags, remote config, experiments) the welcome
+// catches a returning player up: announcements they missed in the last ten
+// minutes, how many held messages follow, and a resume token. A client that
+// reconnects with ?resume=<token> gets its match state in the welcome instead
+// of sending state.resync. Tokens are signed with RESUME_TOKEN_SECRET and
+// expire after RESUME_TOKEN_TTL_SECS.
+pub struct Handshake {
+    config: HandshakeConfig,
+    secret: Vec<u8>,
+    match_service: Arc<MatchService>,
+    experiments: Arc<ExperimentService>,
+    remote_config: Arc<RemoteConfigService>,
+    announcements: Arc<AnnouncementService>,
+    content: Arc<ContentBundles>,
+    preferences: Arc<PreferenceService>,
+}
+
+impl Handshake {
+    pub fn new(
+        config: HandshakeConfig,
+        match_service: Arc<MatchService>,
+        experiments: Arc<ExperimentService>,
+        remote_config: Arc<RemoteConfigService>,
+        announcements: Arc<AnnouncementService>,
+        content: Arc<ContentBundles>,
+        preferences: Arc<PreferenceService>,
+    ) -> Self {
+        let secret = match &config.resume_secret {
+            Some(secret) => secret.as_bytes().to_vec(),
+            None => rand::random::<[u8; 32]>().to_vec(),
+        };
+        Self {
+            config,
+            secret,
+            match_service,
+            experiments,
+            remote_config,
+            announcements,
+            content,
+            preferences,
+        }
+    }
+
+    pub async fn welcome(&self, session: Session<'_>) -> Welcome {
+        let resumed = session.resume_token.is_some_and(|token| self.verify(token, session.user_id));
+        let match_type = session.match_state.as_ref().map(|snapshot| snapshot.state.match_type.clone());
+        Welcome {
+            protocol_version: PROTOCOL_VERSION,
+            conn_id: session.conn_id,
+            user_id: session.user_id,
+            message: self.config.message.clone(),
+            server_time: Utc::now(),
+            capabilities: self.match_service.capabilities(),
+            features: self.config.features.clone(),
+            compression: session.compress.then(|| "gzip".to_string()),
+            experiments: self.experiments.assignments(session.user_id).await,
+            config: self.remote_config.current().await,
+            motd: self.announcements.motd().await.map(|motd| motd.message),
+            announcements: self.missed_announcements(session.user_id, match_type.as_deref(), &session.locale).await,
+            pending_inbox: session.pending_inbox,
+            resume_token: self.resume_token(session.user_id),
+            resumed,
+            state: session.match_state.filter(|_| resumed),
+            locale: session.locale,
+        }
+    }
+
+    // Recent announcements for this player, in their language, unless they
+    // turned announcements off
+    async fn missed_announcements(&self, user_id: Uuid, match_type: Option<&str>, locale: &str) -> Vec<AnnouncementNotice> {
+        if self.preferences.wanting(&[user_id], NotificationKind::Announcement).await.is_empty() {
+            return Vec::new();
+        }
+        let region = self.announcements.region();
+        self.announcements.recent()
+            .await
+            .iter()
+            .filter(|announcement| announcement.segment.matches(region, match_type))
+            .map(|announcement| {
+                let message = announcement.content_key.as_deref()
+                    .and_then(|key| self.content.text(locale, key))
+                    .unwrap_or(&announcement.message);
+                announcement.notice(message)
+            })
+            .collect()
+    }
+
+    // "<expiry, Unix seconds>.<user id>.<hex HMAC-SHA256 of both>"
+    fn resume_token(&self, user_id: Uuid) -> String {
+        let expires_at = Utc::now() + chrono::Duration::from_std(self.config.resume_ttl).unwrap_or_default();
+        let payload = format!("{}.{}", expires_at.timestamp(), user_id);
+        let signature = hex::encode(self.mac(&payload).finalize().into_bytes());
+        format!("{}.{}", payload, signature)
+    }
+
+    // Signed by this server for this player and not expired
+    fn verify(&self, token: &str, user_id: Uuid) -> bool {
+        let Some((payload, signature)) = token.rsplit_once('.') else {
+            return false;
+        };
+        let Some((expires_at, token_user)) = payload.split_once('.') else {
+            return false;
+        };
+        let valid_for_user = token_user.parse::<Uuid>().is_ok_and(|id| id == user_id)
+            && expires_at.parse::<i64>().is_ok_and(|expires_at| expires_at > Utc::now().timestamp());
+        valid_for_user && hex::decode(signature).is_ok_and(|signature| self.mac(payload).verify_slice(&signature).is_ok())
+    }
+
+    fn mac(&self, payload: &str) -> Hmac<Sha256> {
+        let mut mac = Hmac::<Sha256>::new_from_slice(&self.secret)
+            .expect("HMAC accepts keys of any length");
+        mac.update(payload.as_bytes());
+        mac
+    }
+}
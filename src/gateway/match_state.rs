@@ -0,0 +1,307 @@
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
+
+use chrono::Utc;
+use serde_json::{Map, Value};
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+use crate::config::{EmoteConfig, LobbyConfig, PingConfig};
+use crate::error::{Error, Result};
+use crate::memory;
+use crate::metrics::METRICS;
+use crate::matchmaking::events::MatchEvent;
+use crate::models::emote::Audience;
+use crate::models::game::MatchStatus;
+use super::protocol::{MapPing, MatchState, PingRequest, StateDelta, StateSnapshot};
+
+// Most pings of one player up at once; the oldest is dropped for a new one
+const MAX_ACTIVE_PINGS: usize = 3;
+
+struct VersionedState {
+    version: u64,
+    state: MatchState,
+    // Team map pings, kept out of the shared document so opponents never see them
+    pings: Vec<MapPing>,
+    // Recent ping times per player, for rate limiting
+    ping_times: HashMap<Uuid, VecDeque<Instant>>,
+    // Recent emote times per player, for rate limiting
+    emote_times: HashMap<Uuid, VecDeque<Instant>>,
+    // Recent lobby chat times per player, for rate limiting
+    chat_times: HashMap<Uuid, VecDeque<Instant>>,
+    // Last match event applied
+    updated_at: Instant,
+}
+
+// Versioned state document per match, as seen by clients.
+//
+// Every match event bumps the version and yields a delta with only the
+// top-level fields that changed; clients that miss a version ask for a
+// full snapshot with `state.resync`. Documents normally go when their match
+// ends; past MAX_TRACKED_MATCHES the least recently updated one is dropped,
+// in case an end was never seen.
+pub struct MatchStateStore {
+    max: usize,
+    states: RwLock<HashMap<Uuid, VersionedState>>,
+}
+
+impl MatchStateStore {
+    pub fn new(max: usize) -> Self {
+        Self {
+            max,
+            states: RwLock::new(HashMap::new()),
+        }
+    }
+
+    pub async fn len(&self) -> usize {
+        self.states.read().await.len()
+    }
+
+    // Apply a match event; returns the delta to broadcast, if anything changed
+    pub async fn apply(&self, event: &MatchEvent) -> Option<StateDelta> {
+        // Adjustments and placements come after the match is over, when it has
+        // no document any more; spawned treasures and captures are sent as
+        // events of their own
+        if matches!(
+            event,
+            MatchEvent::ResultAdjusted { .. }
+                | MatchEvent::RankPlaced { .. }
+                | MatchEvent::TreasuresSpawned { .. }
+                | MatchEvent::CaptureChanged { .. }
+                | MatchEvent::RoundEnded { .. }
+                | MatchEvent::LobbySelected { .. }
+                | MatchEvent::HostChanged { .. }
+                | MatchEvent::TeamsChanged { .. }
+                | MatchEvent::KickVoted { .. }
+                | MatchEvent::PlayerKicked { .. }
+        ) {
+            return None;
+        }
+
+        let match_id = event.match_id();
+        let mut states = self.states.write().await;
+        if !states.contains_key(&match_id) && states.len() >= self.max
+            && let Some(stalest) = states.iter().min_by_key(|(_, entry)| entry.updated_at).map(|(id, _)| *id)
+        {
+            states.remove(&stalest);
+            METRICS.record_memory_cap_hit(memory::MATCH_STATES);
+        }
+
+        let entry = states.entry(match_id).or_insert_with(|| VersionedState {
+            version: 0,
+            state: MatchState::empty(match_id),
+            pings: Vec::new(),
+            ping_times: HashMap::new(),
+            emote_times: HashMap::new(),
+            chat_times: HashMap::new(),
+            updated_at: Instant::now(),
+        });
+        entry.updated_at = Instant::now();
+        let before = to_fields(&entry.state);
+
+        match event {
+            MatchEvent::PlayerJoined { room, .. }
+            | MatchEvent::PlayerLeft { room, .. }
+            | MatchEvent::RoomReady { room } => {
+                entry.state.status = room.status;
+                entry.state.match_type = room.match_type.clone();
+                entry.state.zone_id = room.zone_id.clone();
+                entry.state.current_players = room.current_players;
+                entry.state.required_players = room.required_players;
+            }
+            MatchEvent::LobbyOpened { room, teams, .. } | MatchEvent::MatchStarted { room, teams } => {
+                entry.state.status = room.status;
+                entry.state.match_type = room.match_type.clone();
+                entry.state.zone_id = room.zone_id.clone();
+                entry.state.current_players = room.current_players;
+                entry.state.required_players = room.required_players;
+                entry.state.teams = teams.clone();
+                entry.state.team_scores = teams.iter().map(|t| (t.team_id, 0)).collect();
+            }
+            MatchEvent::DiscoveryRecorded { discovery } => {
+                *entry.state.team_scores.entry(discovery.team_id).or_insert(0) += discovery.score;
+            }
+            MatchEvent::MatchEnded { .. } => {
+                entry.state.status = MatchStatus::Finished;
+            }
+            MatchEvent::ResultAdjusted { .. }
+            | MatchEvent::RankPlaced { .. }
+            | MatchEvent::TreasuresSpawned { .. }
+            | MatchEvent::CaptureChanged { .. }
+            | MatchEvent::RoundEnded { .. }
+            | MatchEvent::LobbySelected { .. }
+            | MatchEvent::HostChanged { .. }
+            | MatchEvent::TeamsChanged { .. }
+            | MatchEvent::KickVoted { .. }
+            | MatchEvent::PlayerKicked { .. } => {}
+        }
+
+        let after = to_fields(&entry.state);
+        let changes: Map<String, Value> = after
+            .into_iter()
+            .filter(|(key, value)| before.get(key) != Some(value))
+            .collect();
+
+        let delta = if changes.is_empty() {
+            None
+        } else {
+            entry.version += 1;
+            Some(StateDelta {
+                match_id,
+                base_version: entry.version - 1,
+                version: entry.version,
+                changes,
+            })
+        };
+
+        // Finished matches no longer need a document
+        if matches!(event, MatchEvent::MatchEnded { .. }) {
+            states.remove(&match_id);
+        }
+
+        delta
+    }
+
+    pub async fn snapshot(&self, match_id: Uuid) -> Option<StateSnapshot> {
+        let states = self.states.read().await;
+        states.get(&match_id).map(|entry| StateSnapshot {
+            match_id,
+            version: entry.version,
+            state: entry.state.clone(),
+            pings: Vec::new(),
+        })
+    }
+
+    // Drop a player's map ping; returns it with the teammates to relay it to,
+    // the player included
+    pub async fn add_ping(&self, match_id: Uuid, user_id: Uuid, request: PingRequest, config: &PingConfig) -> Result<(MapPing, Vec<Uuid>)> {
+        let mut states = self.states.write().await;
+        let entry = states.get_mut(&match_id).ok_or(Error::MatchNotFound)?;
+        if entry.state.status != MatchStatus::Playing {
+            return Err(Error::MatchNotReady);
+        }
+        let team = entry.state.teams
+            .iter()
+            .find(|team| team.players.contains(&user_id))
+            .ok_or_else(|| Error::PermissionDenied("not in this match".to_string()))?;
+
+        take_slot(entry.ping_times.entry(user_id).or_default(), config.limit, config.window)?;
+
+        let created_at = Utc::now();
+        let ping = MapPing {
+            id: Uuid::new_v4(),
+            user_id,
+            team_id: team.team_id,
+            kind: request.kind,
+            position: request.position,
+            created_at,
+            expires_at: created_at + chrono::Duration::from_std(config.ttl).unwrap_or_default(),
+        };
+        entry.pings.retain(|ping| ping.expires_at > created_at);
+        let own: Vec<Uuid> = entry.pings.iter().filter(|ping| ping.user_id == user_id).map(|ping| ping.id).collect();
+        if own.len() >= MAX_ACTIVE_PINGS {
+            entry.pings.retain(|ping| ping.id != own[0]);
+        }
+        entry.pings.push(ping.clone());
+        Ok((ping, team.players.clone()))
+    }
+
+    // Players to send a player's emote to, the player included; counts
+    // against the player's emote rate limit
+    pub async fn emote_recipients(&self, match_id: Uuid, user_id: Uuid, audience: Audience, config: &EmoteConfig) -> Result<Vec<Uuid>> {
+        let mut states = self.states.write().await;
+        let entry = states.get_mut(&match_id).ok_or(Error::MatchNotFound)?;
+        let team = entry.state.teams
+            .iter()
+            .find(|team| team.players.contains(&user_id))
+            .ok_or_else(|| Error::PermissionDenied("not in this match".to_string()))?;
+        let recipients = match audience {
+            Audience::Match => entry.state.teams.iter().flat_map(|team| team.players.iter().copied()).collect(),
+            Audience::Team => team.players.clone(),
+        };
+        take_slot(entry.emote_times.entry(user_id).or_default(), config.limit, config.window)?;
+        Ok(recipients)
+    }
+
+    // Teammates to send a player's lobby chat message to, the player included;
+    // only while the lobby is open, and counts against the chat rate limit
+    pub async fn lobby_chat_recipients(&self, match_id: Uuid, user_id: Uuid, config: &LobbyConfig) -> Result<Vec<Uuid>> {
+        let mut states = self.states.write().await;
+        let entry = states.get_mut(&match_id).ok_or(Error::MatchNotFound)?;
+        if entry.state.status != MatchStatus::Lobby {
+            return Err(Error::MatchNotReady);
+        }
+        let team = entry.state.teams
+            .iter()
+            .find(|team| team.players.contains(&user_id))
+            .ok_or_else(|| Error::PermissionDenied("not in this match".to_string()))?;
+        let recipients = team.players.clone();
+        take_slot(entry.chat_times.entry(user_id).or_default(), config.chat_limit, config.chat_window)?;
+        Ok(recipients)
+    }
+
+    // Fails unless both players are on the same team of this match
+    pub async fn check_teammates(&self, match_id: Uuid, user_id: Uuid, other: Uuid) -> Result<()> {
+        let states = self.states.read().await;
+        let entry = states.get(&match_id).ok_or(Error::MatchNotFound)?;
+        let team = entry.state.teams
+            .iter()
+            .find(|team| team.players.contains(&user_id))
+            .ok_or_else(|| Error::PermissionDenied("not in this match".to_string()))?;
+        if user_id == other || !team.players.contains(&other) {
+            return Err(Error::PermissionDenied("not a teammate".to_string()));
+        }
+        Ok(())
+    }
+
+    // The player's team, while the match is being played
+    pub async fn playing_team(&self, match_id: Uuid, user_id: Uuid) -> Result<Uuid> {
+        let states = self.states.read().await;
+        let entry = states.get(&match_id).ok_or(Error::MatchNotFound)?;
+        if entry.state.status != MatchStatus::Playing {
+            return Err(Error::MatchNotReady);
+        }
+        entry.state.teams
+            .iter()
+            .find(|team| team.players.contains(&user_id))
+            .map(|team| team.team_id)
+            .ok_or_else(|| Error::PermissionDenied("not in this match".to_string()))
+    }
+
+    // Map pings of the player's team still up
+    pub async fn active_pings(&self, match_id: Uuid, user_id: Uuid) -> Vec<MapPing> {
+        let states = self.states.read().await;
+        let Some(entry) = states.get(&match_id) else {
+            return Vec::new();
+        };
+        let Some(team) = entry.state.teams.iter().find(|team| team.players.contains(&user_id)) else {
+            return Vec::new();
+        };
+        let now = Utc::now();
+        entry.pings
+            .iter()
+            .filter(|ping| ping.team_id == team.team_id && ping.expires_at > now)
+            .cloned()
+            .collect()
+    }
+}
+
+// Sliding window rate limit over the given send times
+fn take_slot(times: &mut VecDeque<Instant>, limit: usize, window: Duration) -> Result<()> {
+    let now = Instant::now();
+    while times.front().is_some_and(|at| now.duration_since(*at) >= window) {
+        times.pop_front();
+    }
+    if times.len() >= limit {
+        return Err(Error::RateLimited);
+    }
+    times.push_back(now);
+    Ok(())
+}
+
+fn to_fields(state: &MatchState) -> Map<String, Value> {
+    match serde_json::to_value(state) {
+        Ok(Value::Object(fields)) => fields,
+        _ => Map::new(),
+    }
+}
@@ -0,0 +1,194 @@
+use std::collections::HashMap;
+use std::time::Instant;
+
+use schemars::JsonSchema;
+use serde::Serialize;
+use tokio::sync::RwLock;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::matchmaking::events::MatchEvent;
+use crate::memory;
+use crate::metrics::METRICS;
+use crate::models::game::{MatchResult, MatchStatus, PlatformBreakdown};
+use super::state::MatchConnections;
+
+// Seconds covered by the messages/sec window
+const RATE_WINDOW_SECS: usize = 10;
+
+// Runtime stats of one match, for live ops
+#[derive(Debug, Clone, Serialize, ToSchema, JsonSchema)]
+pub struct MatchStats {
+    pub match_id: Uuid,
+    pub match_type: String,
+    pub status: MatchStatus,
+    pub connected_players: usize,
+    // Connected players per platform
+    pub platforms: PlatformBreakdown,
+    // Averages of the players' sys.net_report, absent without reports
+    pub avg_rtt_ms: Option<u64>,
+    pub avg_packet_loss: Option<f64>,
+    // Client messages per second over the last 10 seconds
+    pub messages_per_sec: f64,
+    pub messages_total: u64,
+    pub discoveries: u64,
+    // Seconds since the match started; absent while matching
+    pub duration_secs: Option<u64>,
+}
+
+// Message counts in one-second buckets
+struct RateWindow {
+    buckets: [u64; RATE_WINDOW_SECS],
+    // Second (since `origin`) the newest bucket belongs to
+    current: u64,
+    origin: Instant,
+}
+
+impl RateWindow {
+    fn new() -> Self {
+        Self {
+            buckets: [0; RATE_WINDOW_SECS],
+            current: 0,
+            origin: Instant::now(),
+        }
+    }
+
+    // Clear buckets that fell out of the window
+    fn advance(&mut self) {
+        let now = self.origin.elapsed().as_secs();
+        let stale = (now - self.current).min(RATE_WINDOW_SECS as u64);
+        for i in 1..=stale {
+            self.buckets[((self.current + i) % RATE_WINDOW_SECS as u64) as usize] = 0;
+        }
+        self.current = now;
+    }
+
+    fn record(&mut self) {
+        self.advance();
+        self.buckets[(self.current % RATE_WINDOW_SECS as u64) as usize] += 1;
+    }
+
+    fn per_sec(&mut self) -> f64 {
+        self.advance();
+        self.buckets.iter().sum::<u64>() as f64 / RATE_WINDOW_SECS as f64
+    }
+}
+
+struct MatchCounters {
+    match_type: String,
+    status: MatchStatus,
+    started_at: Option<Instant>,
+    messages: RateWindow,
+    messages_total: u64,
+    discoveries: u64,
+    // Last match event seen
+    updated_at: Instant,
+}
+
+// Per-match counters fed by match events and client messages; past
+// MAX_TRACKED_MATCHES the least recently updated match is dropped
+pub struct MatchStatsTracker {
+    max: usize,
+    matches: RwLock<HashMap<Uuid, MatchCounters>>,
+}
+
+impl MatchStatsTracker {
+    pub fn new(max: usize) -> Self {
+        Self {
+            max,
+            matches: RwLock::new(HashMap::new()),
+        }
+    }
+
+    pub async fn len(&self) -> usize {
+        self.matches.read().await.len()
+    }
+
+    pub async fn on_event(&self, event: &MatchEvent) {
+        let mut matches = self.matches.write().await;
+        match event {
+            MatchEvent::PlayerJoined { room, .. }
+            | MatchEvent::PlayerLeft { room, .. }
+            | MatchEvent::RoomReady { room }
+            | MatchEvent::LobbyOpened { room, .. } => {
+                self.entry(&mut matches, room).status = room.status;
+            }
+            MatchEvent::MatchStarted { room, .. } => {
+                let counters = self.entry(&mut matches, room);
+                counters.status = room.status;
+                counters.started_at = Some(Instant::now());
+            }
+            MatchEvent::DiscoveryRecorded { discovery } => {
+                if let Some(counters) = matches.get_mut(&discovery.match_id) {
+                    counters.discoveries += 1;
+                }
+            }
+            MatchEvent::MatchEnded { match_id } => {
+                matches.remove(match_id);
+            }
+            MatchEvent::ResultAdjusted { .. }
+            | MatchEvent::RankPlaced { .. }
+            | MatchEvent::TreasuresSpawned { .. }
+            | MatchEvent::CaptureChanged { .. }
+            | MatchEvent::RoundEnded { .. }
+            | MatchEvent::LobbySelected { .. }
+            | MatchEvent::HostChanged { .. }
+            | MatchEvent::TeamsChanged { .. }
+            | MatchEvent::KickVoted { .. }
+            | MatchEvent::PlayerKicked { .. } => {}
+        }
+    }
+
+    fn entry<'a>(&self, matches: &'a mut HashMap<Uuid, MatchCounters>, room: &MatchResult) -> &'a mut MatchCounters {
+        if !matches.contains_key(&room.match_id) && matches.len() >= self.max
+            && let Some(stalest) = matches.iter().min_by_key(|(_, counters)| counters.updated_at).map(|(id, _)| *id)
+        {
+            matches.remove(&stalest);
+            METRICS.record_memory_cap_hit(memory::MATCH_STATS);
+        }
+        let counters = matches.entry(room.match_id).or_insert_with(|| MatchCounters {
+            match_type: room.match_type.clone(),
+            status: room.status,
+            started_at: None,
+            messages: RateWindow::new(),
+            messages_total: 0,
+            discoveries: 0,
+            updated_at: Instant::now(),
+        });
+        counters.updated_at = Instant::now();
+        counters
+    }
+
+    pub async fn record_message(&self, match_id: Uuid) {
+        if let Some(counters) = self.matches.write().await.get_mut(&match_id) {
+            counters.messages.record();
+            counters.messages_total += 1;
+        }
+    }
+
+    // Stats of every tracked match, joined with the live connections per match
+    pub async fn snapshot(&self, connections: &HashMap<Uuid, MatchConnections>) -> Vec<MatchStats> {
+        let mut matches = self.matches.write().await;
+        let mut stats: Vec<MatchStats> = matches
+            .iter_mut()
+            .map(|(match_id, counters)| {
+                let conns = connections.get(match_id).cloned().unwrap_or_default();
+                MatchStats {
+                    match_id: *match_id,
+                    match_type: counters.match_type.clone(),
+                    status: counters.status,
+                    connected_players: conns.connected,
+                    avg_rtt_ms: conns.avg_rtt_ms(),
+                    platforms: conns.platforms.clone(),
+                    avg_packet_loss: conns.avg_packet_loss(),
+                    messages_per_sec: counters.messages.per_sec(),
+                    messages_total: counters.messages_total,
+                    discoveries: counters.discoveries,
+                    duration_secs: counters.started_at.map(|t| t.elapsed().as_secs()),
+                }
+            })
+            .collect();
+        stats.sort_by_key(|s| s.match_id);
+        stats
+    }
+}
@@ -1,2 +1,12 @@
+pub mod console;
+pub mod fanout;
 pub mod handler;
-pub mod state;
\ No newline at end of file
+pub mod handshake;
+pub mod match_state;
+pub mod match_stats;
+pub mod protocol;
+pub mod recorder;
+pub mod spectator;
+pub mod sse;
+pub mod state;
+pub mod voice;
\ No newline at end of file
@@ -0,0 +1,946 @@
+use std::collections::{BTreeMap, HashMap};
+
+use chrono::{DateTime, Utc};
+use schemars::{JsonSchema, schema::RootSchema, schema_for};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value, json};
+use uuid::Uuid;
+
+use crate::announcements::announcement::{Announcement, AnnouncementNotice, Segment};
+use crate::anticheat::appeal::{AppealReceipt, AppealRequest};
+use crate::devices::device::{Device, DeviceRegistration, DeviceRevocation, SessionEnded};
+use crate::game::capture::CaptureUpdate;
+use crate::game::runtime::{GameTick, PositionUpdate};
+use crate::matchmaking::review::AuditEntry;
+use crate::matchmaking::service::Capabilities;
+use super::match_stats::MatchStats;
+use super::state::LinkQuality;
+use crate::models::game::{
+    KickVote, LobbySelection, LobbyTeams, MatchStatus, MemberDetails, PlatformPool, PlayerPosition, PrivateLobby, RoundResult, TeamAssignment,
+    TreasureDiscovery,
+};
+use crate::models::message::{ClientMessage, ServerMessage};
+use crate::models::treasure::TreasureSpawn;
+use crate::moderation::ban::BanNotice;
+use crate::rating::mmr::RankPlacement;
+use crate::lfg::post::{LfgPost, LfgSubscribeRequest, LfgSubscription};
+use crate::preferences::preferences::{NotificationPreferences, PreferencesUpdate};
+use crate::remote_config::document::ClientConfig;
+use crate::schedule::scheduled::ScheduledMatch;
+use crate::telemetry::event::TelemetryEvent;
+use crate::tutorial::tutorial::{TutorialProgress, TutorialStarted, TutorialStep};
+
+// Typed `data` payloads of the WebSocket/SSE protocol. The handler builds its
+// messages from these structs so the exported schemas can't drift from the wire.
+
+// Version of the protocol below; bumped when a change breaks existing clients
+pub const PROTOCOL_VERSION: u32 = 1;
+
+// Server-pushed event names, sent in ServerMessage.event
+pub const EVENT_WELCOME: &str = "sys.welcome";
+pub const EVENT_STATE_DELTA: &str = "state.delta";
+pub const EVENT_DISCOVERY: &str = "match.discovery";
+// Treasures added to the match by the server during play
+pub const EVENT_TREASURES_SPAWNED: &str = "match.treasures_spawned";
+// A capture zone became held, contested or neutral
+pub const EVENT_CAPTURE: &str = "match.capture";
+// A round of a best_of match is over
+pub const EVENT_ROUND: &str = "match.round";
+pub const EVENT_TICK: &str = "game.tick";
+// Single position relay, only sent when the tick loop is disabled
+pub const EVENT_POSITION: &str = "game.position";
+pub const EVENT_ADMIN_MATCHES: &str = "admin.matches";
+// Admin adjustment of a finished match the player took part in
+pub const EVENT_MATCH_ADJUSTED: &str = "match.adjusted";
+// The player finished placement and has a visible rank now
+pub const EVENT_RANK_PLACED: &str = "rank.placed";
+// The player's party leader queued them, with the match.start reply's data;
+// match.cancel leaves the queue as usual
+pub const EVENT_PARTY_QUEUED: &str = "match.party_queued";
+// A match the player was queued for started while they were offline; only
+// delivered from the inbox, connected players follow state.delta instead
+pub const EVENT_MATCH_FOUND: &str = "match.found";
+// The player is banned; the server closes the connection right after
+pub const EVENT_BANNED: &str = "sys.banned";
+// Another session replaced this one, or its device was revoked; the server
+// closes the connection right after
+pub const EVENT_SESSION_ENDED: &str = "sys.session_ended";
+// Message from the operators, sent with admin.announce
+pub const EVENT_ANNOUNCEMENT: &str = "sys.announcement";
+// A teammate (or the player) dropped a map marker with game.ping
+pub const EVENT_PING: &str = "game.ping";
+// A player in the match (or on the team, for team-only phrases) sent game.emote
+pub const EVENT_EMOTE: &str = "game.emote";
+// WebRTC signaling from a teammate, relayed as sent
+pub const EVENT_VOICE_OFFER: &str = "voice.offer";
+pub const EVENT_VOICE_ANSWER: &str = "voice.answer";
+pub const EVENT_VOICE_ICE: &str = "voice.ice";
+// Sent to each team when the pre-match lobby opens
+pub const EVENT_LOBBY_OPENED: &str = "lobby.opened";
+// A teammate (or the player) wrote in the lobby with lobby.chat
+pub const EVENT_LOBBY_CHAT: &str = "lobby.chat";
+// A teammate (or the player) picked a loadout or role with lobby.select
+pub const EVENT_LOBBY_SELECTION: &str = "lobby.selection";
+// Another player took over as host of the private lobby
+pub const EVENT_LOBBY_HOST: &str = "lobby.host";
+// Players of the private lobby joined, left or switched teams
+pub const EVENT_LOBBY_TEAMS: &str = "lobby.teams";
+// Someone voted to kick a player out of the private lobby
+pub const EVENT_LOBBY_KICK_VOTE: &str = "lobby.kick_vote";
+// The player was kicked out of the private lobby
+pub const EVENT_LOBBY_KICKED: &str = "lobby.kicked";
+// The player was invited to a scheduled match
+pub const EVENT_SCHEDULE_INVITED: &str = "schedule.invited";
+// A scheduled match the player is in starts soon
+pub const EVENT_SCHEDULE_REMINDER: &str = "schedule.reminder";
+// The private lobby of a scheduled match opened; the event carries its code
+pub const EVENT_SCHEDULE_LOBBY_OPEN: &str = "schedule.lobby_open";
+// A scheduled match was called off, by its host or for lack of players
+pub const EVENT_SCHEDULE_CANCELLED: &str = "schedule.cancelled";
+// A queued player is looking for more players; sent to lfg.subscribe'd
+// connections not in a match
+pub const EVENT_LFG_POST: &str = "lfg.post";
+// The player reached the next step of their tutorial match
+pub const EVENT_TUTORIAL_STEP: &str = "tutorial.step";
+
+// match.start request: either just the match type ("1v1", "2v2" or "5v5"),
+// or an object that also picks the map zone to queue in
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(untagged)]
+pub enum MatchStartRequest {
+    MatchType(String),
+    Options {
+        #[serde(rename = "type")]
+        match_type: String,
+        // Zone to queue in; auto-assigned from `position` when absent
+        zone_id: Option<String>,
+        // Player's current position, must be inside `zone_id` if both are set
+        position: Option<PlayerPosition>,
+        // Other members of a premade party queued along with the player; they
+        // must be connected and are kept on the player's team
+        #[serde(default)]
+        party: Vec<Uuid>,
+        // "mobile" to only be matched with iOS and Android players; every
+        // member must have connected from one
+        #[serde(default)]
+        platforms: PlatformPool,
+    },
+}
+
+// match.start reply
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct MatchUpdate {
+    pub match_id: Uuid,
+    pub status: MatchStatus,
+    #[serde(rename = "type")]
+    pub match_type: String,
+    // Map zone the player was queued in; absent for the global pool
+    pub zone_id: Option<String>,
+    pub current_players: i32,
+    pub required_players: i32,
+}
+
+// game.position request
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct PositionReport {
+    #[serde(flatten)]
+    pub position: PlayerPosition,
+    // Set when the OS says the location comes from a mock provider
+    #[serde(default)]
+    pub mock_location: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PingKind {
+    Attention,
+    Enemy,
+    Danger,
+    Treasure,
+    Regroup,
+}
+
+// game.ping request
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct PingRequest {
+    #[serde(flatten)]
+    pub position: PlayerPosition,
+    pub kind: PingKind,
+}
+
+// game.ping event; also listed in the state.resync reply while it lasts
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct MapPing {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub team_id: Uuid,
+    pub kind: PingKind,
+    pub position: PlayerPosition,
+    pub created_at: DateTime<Utc>,
+    // Clients remove the marker at this time
+    pub expires_at: DateTime<Utc>,
+}
+
+// game.emote request: an id from the catalog at /api/emotes
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct EmoteRequest {
+    pub emote: String,
+}
+
+// game.discover request: a treasure the player reached; it scores the
+// treasure's base score for the player's team
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct DiscoverRequest {
+    pub treasure_id: Uuid,
+}
+
+// game.emote event
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct EmoteEvent {
+    pub user_id: Uuid,
+    pub emote: String,
+    pub sent_at: DateTime<Utc>,
+}
+
+// lobby.opened event: the player's team, until the match starts at closes_at
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct LobbyOpened {
+    pub match_id: Uuid,
+    pub team_id: Uuid,
+    pub closes_at: DateTime<Utc>,
+    // The player included; nickname and avatar are empty while the database
+    // can't be reached
+    pub teammates: Vec<MemberDetails>,
+    // What can be picked with lobby.select
+    pub loadouts: Vec<String>,
+    pub roles: Vec<String>,
+}
+
+// lobby.chat request
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct LobbyChatRequest {
+    pub text: String,
+}
+
+// lobby.chat event
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct LobbyChatMessage {
+    pub user_id: Uuid,
+    pub text: String,
+    pub sent_at: DateTime<Utc>,
+}
+
+// lobby.selection event
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct LobbySelectionUpdate {
+    pub user_id: Uuid,
+    pub selection: LobbySelection,
+}
+
+// lobby.create request
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct LobbyCreateRequest {
+    #[serde(rename = "type")]
+    pub match_type: String,
+}
+
+// lobby.join request: the invite code of a private lobby, in any case
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct LobbyJoinRequest {
+    pub code: String,
+}
+
+// lobby.kick request; `block` keeps the player from joining again
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct LobbyKickRequest {
+    pub user_id: Uuid,
+    #[serde(default)]
+    pub block: bool,
+}
+
+// lobby.team request: move `user_id` (the player when absent, anyone else
+// for the host only) to team 1 or 2
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct LobbyTeamRequest {
+    pub team: i32,
+    pub user_id: Option<Uuid>,
+}
+
+// schedule.create request; `min_players` defaults to the whole room
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ScheduleCreateRequest {
+    #[serde(rename = "type")]
+    pub match_type: String,
+    pub starts_at: DateTime<Utc>,
+    #[serde(default)]
+    pub invited: Vec<Uuid>,
+    pub min_players: Option<i32>,
+}
+
+// schedule.cancel request
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ScheduleCancelRequest {
+    pub id: Uuid,
+}
+
+// schedule.list reply
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ScheduleList {
+    pub schedules: Vec<ScheduledMatch>,
+}
+
+// lobby.kicked event
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct LobbyKicked {
+    pub match_id: Uuid,
+    pub blocked: bool,
+}
+
+// lobby.host event: `host` may now start the lobby, in place of `previous`
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct HostChanged {
+    pub match_id: Uuid,
+    pub host: Uuid,
+    pub previous: Uuid,
+}
+
+// voice.offer and voice.answer request: an SDP for one teammate
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct VoiceSdpRequest {
+    pub to: Uuid,
+    pub sdp: String,
+}
+
+// voice.ice request: an ICE candidate for one teammate, as produced by the browser
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct VoiceIceRequest {
+    pub to: Uuid,
+    pub candidate: Value,
+}
+
+// voice.offer and voice.answer events
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct VoiceSdp {
+    pub from: Uuid,
+    pub sdp: String,
+}
+
+// voice.ice event
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct VoiceIce {
+    pub from: Uuid,
+    pub candidate: Value,
+}
+
+// voice.turn reply: credentials for the TURN server, valid until `expires_at`
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct TurnCredentials {
+    pub urls: Vec<String>,
+    pub username: String,
+    pub credential: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+// match.cancel reply
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct CancelReply {
+    // Always "cancelled"
+    pub status: String,
+}
+
+// match.spectate request
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct SpectateRequest {
+    pub match_id: Uuid,
+}
+
+// match.spectate and match.unspectate reply
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct SpectateReply {
+    // None once no longer spectating
+    pub match_id: Option<Uuid>,
+    // How far behind the players the match is shown
+    pub delay_ms: u64,
+}
+
+// device.list reply, most recently seen first
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct DeviceList {
+    pub devices: Vec<Device>,
+}
+
+// sys.ping reply
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct Pong {
+    pub time: DateTime<Utc>,
+    // Set when the connection is in a match
+    pub match_status: Option<MatchStatus>,
+}
+
+// sys.time_sync request, NTP-style. Times are Unix milliseconds.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct TimeSyncRequest {
+    // Client clock when the request was sent (t0)
+    pub client_send_time: i64,
+}
+
+// sys.time_sync reply. With t3 the client clock on receipt:
+// offset = ((t1 - t0) + (t2 - t3)) / 2, round trip = (t3 - t0) - (t2 - t1)
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct TimeSyncReply {
+    // t0, echoed back
+    pub client_send_time: i64,
+    // t1: server clock when the request arrived
+    pub server_receive_time: i64,
+    // t2: server clock when the reply was sent
+    pub server_transmit_time: i64,
+}
+
+// admin.watch_matches request
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct AdminWatchRequest {
+    // ADMIN_TOKEN
+    pub token: String,
+    // Push period, 1000 ms by default (minimum 250)
+    pub interval_ms: Option<u64>,
+}
+
+// admin.announce request
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct AdminAnnounceRequest {
+    // ADMIN_TOKEN
+    pub token: String,
+    // Text for players whose locale has none under `content_key`
+    pub message: String,
+    // Content bundle entry with the text in each language
+    pub content_key: Option<String>,
+    // Admin making the announcement
+    pub created_by: String,
+    // Everyone when absent
+    #[serde(default)]
+    pub segment: Segment,
+    // Sent right away when absent
+    pub send_at: Option<DateTime<Utc>>,
+}
+
+// admin.watch_matches reply
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct AdminWatchReply {
+    pub watching: bool,
+    pub interval_ms: u64,
+}
+
+// admin.matches event
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct MatchStatsReport {
+    pub matches: Vec<MatchStats>,
+}
+
+// sys.net_report request, measured by the client
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct NetReportRequest {
+    pub rtt_ms: u32,
+    // Ratio between 0.0 and 1.0
+    pub packet_loss: f32,
+}
+
+// sys.net_report reply: how the server will pace game ticks for this connection
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct NetReportReply {
+    pub quality: LinkQuality,
+    // One game.tick is sent every `tick_stride` ticks, merging the skipped ones
+    pub tick_stride: u64,
+}
+
+// sys.welcome event, the first message on every connection
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct Welcome {
+    // PROTOCOL_VERSION of the server; clients built for another major version should update
+    pub protocol_version: u32,
+    pub conn_id: Uuid,
+    pub user_id: Uuid,
+    // WELCOME_MESSAGE
+    pub message: String,
+    // For clients to estimate their clock offset before the first sys.time_sync
+    pub server_time: DateTime<Utc>,
+    pub capabilities: Capabilities,
+    // FEATURE_FLAGS of this deployment
+    pub features: BTreeMap<String, bool>,
+    // "gzip" when messages over the threshold arrive as compressed binary frames
+    pub compression: Option<String>,
+    // Variant per running A/B experiment, keyed by experiment key
+    pub experiments: HashMap<String, String>,
+    // Remote config; also served at GET /api/config/client
+    pub config: ClientConfig,
+    // Message of the day, if one is set
+    pub motd: Option<String>,
+    // Announcements for the player sent in the last ten minutes, oldest first
+    pub announcements: Vec<AnnouncementNotice>,
+    // Messages held while the player was offline, delivered right after this event
+    pub pending_inbox: usize,
+    // Pass as ?resume= when reconnecting, to get `state` in the next welcome
+    pub resume_token: String,
+    // Set when ?resume= carried a valid token of this player
+    pub resumed: bool,
+    // The player's match state, when resumed in a match on this node
+    pub state: Option<StateSnapshot>,
+    // Locale the player's content is delivered in, from ?locale= or DEFAULT_LOCALE
+    pub locale: String,
+}
+
+// Full state document of a match; see gateway::match_state
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct MatchState {
+    pub match_id: Uuid,
+    pub status: MatchStatus,
+    #[serde(rename = "type")]
+    pub match_type: String,
+    pub zone_id: Option<String>,
+    pub current_players: i32,
+    pub required_players: i32,
+    // Empty until the lobby opens, or the match starts without one
+    pub teams: Vec<TeamAssignment>,
+    pub team_scores: HashMap<Uuid, i32>,
+}
+
+impl MatchState {
+    pub fn empty(match_id: Uuid) -> Self {
+        Self {
+            match_id,
+            status: MatchStatus::Matching,
+            match_type: String::new(),
+            zone_id: None,
+            current_players: 0,
+            required_players: 0,
+            teams: Vec::new(),
+            team_scores: HashMap::new(),
+        }
+    }
+}
+
+// state.delta event: top-level MatchState fields that changed since
+// `base_version`, bringing the state to `version`. Usually `base_version` is
+// `version - 1`; updates merged while waiting to be sent span several versions.
+// A client holding any version other than `base_version` must send state.resync.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct StateDelta {
+    pub match_id: Uuid,
+    pub base_version: u64,
+    pub version: u64,
+    pub changes: Map<String, Value>,
+}
+
+// state.resync request; only the connection's current match can be resynced
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct StateResyncRequest {
+    pub match_id: Option<Uuid>,
+}
+
+// state.resync reply
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct StateSnapshot {
+    pub match_id: Uuid,
+    pub version: u64,
+    pub state: MatchState,
+    // Map pings of the player's team still up
+    #[serde(default)]
+    pub pings: Vec<MapPing>,
+}
+
+// Schema for a command's request data when the server ignores it
+fn any_data() -> RootSchema {
+    schema_for!(Value)
+}
+
+// A server-pushed event with its typed payload. Serialized adjacently
+// tagged, `{"event": ..., "data": ...}`, which is exactly the `event` and
+// `data` fields of ServerMessage, so clients that dispatch on the event name
+// and read `data` untyped keep working unchanged.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event", content = "data")]
+pub enum ServerEvent {
+    #[serde(rename = "sys.welcome")]
+    Welcome(Box<Welcome>),
+    #[serde(rename = "state.delta")]
+    StateDelta(StateDelta),
+    #[serde(rename = "match.discovery")]
+    Discovery(TreasureDiscovery),
+    #[serde(rename = "match.treasures_spawned")]
+    TreasuresSpawned(TreasureSpawn),
+    #[serde(rename = "match.capture")]
+    Capture(CaptureUpdate),
+    #[serde(rename = "match.round")]
+    Round(RoundResult),
+    #[serde(rename = "game.tick")]
+    Tick(GameTick),
+    #[serde(rename = "game.position")]
+    Position(PositionUpdate),
+    #[serde(rename = "admin.matches")]
+    AdminMatches(MatchStatsReport),
+    #[serde(rename = "match.adjusted")]
+    MatchAdjusted(AuditEntry),
+    #[serde(rename = "rank.placed")]
+    RankPlaced(RankPlacement),
+    #[serde(rename = "match.party_queued")]
+    PartyQueued(MatchUpdate),
+    #[serde(rename = "match.found")]
+    MatchFound(MatchUpdate),
+    #[serde(rename = "sys.banned")]
+    Banned(BanNotice),
+    #[serde(rename = "sys.session_ended")]
+    SessionEnded(SessionEnded),
+    #[serde(rename = "sys.announcement")]
+    Announcement(AnnouncementNotice),
+    #[serde(rename = "game.ping")]
+    Ping(MapPing),
+    #[serde(rename = "game.emote")]
+    Emote(EmoteEvent),
+    #[serde(rename = "voice.offer")]
+    VoiceOffer(VoiceSdp),
+    #[serde(rename = "voice.answer")]
+    VoiceAnswer(VoiceSdp),
+    #[serde(rename = "voice.ice")]
+    VoiceIce(VoiceIce),
+    #[serde(rename = "lobby.opened")]
+    LobbyOpened(LobbyOpened),
+    #[serde(rename = "lobby.chat")]
+    LobbyChat(LobbyChatMessage),
+    #[serde(rename = "lobby.selection")]
+    LobbySelection(LobbySelectionUpdate),
+    #[serde(rename = "lobby.host")]
+    LobbyHost(HostChanged),
+    #[serde(rename = "lobby.teams")]
+    LobbyTeams(LobbyTeams),
+    #[serde(rename = "lobby.kick_vote")]
+    LobbyKickVote(KickVote),
+    #[serde(rename = "lobby.kicked")]
+    LobbyKicked(LobbyKicked),
+    #[serde(rename = "schedule.invited")]
+    ScheduleInvited(ScheduledMatch),
+    #[serde(rename = "schedule.reminder")]
+    ScheduleReminder(ScheduledMatch),
+    #[serde(rename = "schedule.lobby_open")]
+    ScheduleLobbyOpen(ScheduledMatch),
+    #[serde(rename = "schedule.cancelled")]
+    ScheduleCancelled(ScheduledMatch),
+    #[serde(rename = "lfg.post")]
+    LfgPost(LfgPost),
+    #[serde(rename = "tutorial.step")]
+    TutorialStep(TutorialStep),
+}
+
+impl ServerEvent {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ServerEvent::Welcome(_) => EVENT_WELCOME,
+            ServerEvent::StateDelta(_) => EVENT_STATE_DELTA,
+            ServerEvent::Discovery(_) => EVENT_DISCOVERY,
+            ServerEvent::TreasuresSpawned(_) => EVENT_TREASURES_SPAWNED,
+            ServerEvent::Capture(_) => EVENT_CAPTURE,
+            ServerEvent::Round(_) => EVENT_ROUND,
+            ServerEvent::Tick(_) => EVENT_TICK,
+            ServerEvent::Position(_) => EVENT_POSITION,
+            ServerEvent::AdminMatches(_) => EVENT_ADMIN_MATCHES,
+            ServerEvent::MatchAdjusted(_) => EVENT_MATCH_ADJUSTED,
+            ServerEvent::RankPlaced(_) => EVENT_RANK_PLACED,
+            ServerEvent::PartyQueued(_) => EVENT_PARTY_QUEUED,
+            ServerEvent::MatchFound(_) => EVENT_MATCH_FOUND,
+            ServerEvent::Banned(_) => EVENT_BANNED,
+            ServerEvent::SessionEnded(_) => EVENT_SESSION_ENDED,
+            ServerEvent::Announcement(_) => EVENT_ANNOUNCEMENT,
+            ServerEvent::Ping(_) => EVENT_PING,
+            ServerEvent::Emote(_) => EVENT_EMOTE,
+            ServerEvent::VoiceOffer(_) => EVENT_VOICE_OFFER,
+            ServerEvent::VoiceAnswer(_) => EVENT_VOICE_ANSWER,
+            ServerEvent::VoiceIce(_) => EVENT_VOICE_ICE,
+            ServerEvent::LobbyOpened(_) => EVENT_LOBBY_OPENED,
+            ServerEvent::LobbyChat(_) => EVENT_LOBBY_CHAT,
+            ServerEvent::LobbySelection(_) => EVENT_LOBBY_SELECTION,
+            ServerEvent::LobbyHost(_) => EVENT_LOBBY_HOST,
+            ServerEvent::LobbyTeams(_) => EVENT_LOBBY_TEAMS,
+            ServerEvent::LobbyKickVote(_) => EVENT_LOBBY_KICK_VOTE,
+            ServerEvent::LobbyKicked(_) => EVENT_LOBBY_KICKED,
+            ServerEvent::ScheduleInvited(_) => EVENT_SCHEDULE_INVITED,
+            ServerEvent::ScheduleReminder(_) => EVENT_SCHEDULE_REMINDER,
+            ServerEvent::ScheduleLobbyOpen(_) => EVENT_SCHEDULE_LOBBY_OPEN,
+            ServerEvent::ScheduleCancelled(_) => EVENT_SCHEDULE_CANCELLED,
+            ServerEvent::LfgPost(_) => EVENT_LFG_POST,
+            ServerEvent::TutorialStep(_) => EVENT_TUTORIAL_STEP,
+        }
+    }
+
+    // The payload alone, as queued, held in inboxes and forwarded between nodes
+    pub fn data(&self) -> serde_json::Result<Value> {
+        let mut tagged = serde_json::to_value(self)?;
+        Ok(tagged["data"].take())
+    }
+
+    // A fresh ServerMessage carrying this event
+    pub fn to_message(&self) -> serde_json::Result<ServerMessage> {
+        Ok(ServerMessage::event(Uuid::new_v4(), self.name(), self.data()?))
+    }
+}
+
+// Full protocol description served at /api/protocol.json.
+//
+// `commands` maps each `cmd` to the schema of ClientMessage.data and of the
+// reply's ServerMessage.data (null for fire-and-forget commands, which only
+// get a reply on error); `events` maps each ServerMessage.event to its data.
+pub fn protocol_document() -> Value {
+    // Described in groups: the whole document as one json! literal is past
+    // the macro's recursion limit
+    let mut commands = Map::new();
+    for group in [match_commands(), game_commands(), lobby_commands(), schedule_commands(), player_commands(), device_commands()] {
+        merge(&mut commands, group);
+    }
+    json!({
+        "version": PROTOCOL_VERSION,
+        "envelopes": {
+            "client_message": schema_for!(ClientMessage),
+            "server_message": schema_for!(ServerMessage),
+        },
+        "commands": commands,
+        "events": events(),
+    })
+}
+
+fn merge(commands: &mut Map<String, Value>, group: Value) {
+    if let Value::Object(group) = group {
+        commands.extend(group);
+    }
+}
+
+// match.*, sys.*, state.* and admin.*
+fn match_commands() -> Value {
+    json!({
+        "match.start": {
+            "request": schema_for!(MatchStartRequest),
+            "reply": schema_for!(MatchUpdate),
+        },
+        "match.cancel": {
+            "request": any_data(),
+            "reply": schema_for!(CancelReply),
+        },
+        "sys.ping": {
+            "request": any_data(),
+            "reply": schema_for!(Pong),
+        },
+        "sys.net_report": {
+            "request": schema_for!(NetReportRequest),
+            "reply": schema_for!(NetReportReply),
+        },
+        "sys.time_sync": {
+            "request": schema_for!(TimeSyncRequest),
+            "reply": schema_for!(TimeSyncReply),
+        },
+        "state.resync": {
+            "request": schema_for!(StateResyncRequest),
+            "reply": schema_for!(StateSnapshot),
+        },
+        "admin.watch_matches": {
+            "request": schema_for!(AdminWatchRequest),
+            "reply": schema_for!(AdminWatchReply),
+        },
+        "admin.announce": {
+            "request": schema_for!(AdminAnnounceRequest),
+            "reply": schema_for!(Announcement),
+        },
+        "match.spectate": {
+            "request": schema_for!(SpectateRequest),
+            "reply": schema_for!(SpectateReply),
+        },
+        "match.unspectate": {
+            "request": any_data(),
+            "reply": schema_for!(SpectateReply),
+        },
+    })
+}
+
+// game.*, voice.* and telemetry.*
+fn game_commands() -> Value {
+    json!({
+        "game.position": {
+            "request": schema_for!(PositionReport),
+            "reply": null,
+        },
+        "game.ping": {
+            "request": schema_for!(PingRequest),
+            "reply": null,
+        },
+        "game.emote": {
+            "request": schema_for!(EmoteRequest),
+            "reply": null,
+        },
+        "game.discover": {
+            "request": schema_for!(DiscoverRequest),
+            "reply": null,
+        },
+        "voice.offer": {
+            "request": schema_for!(VoiceSdpRequest),
+            "reply": null,
+        },
+        "voice.answer": {
+            "request": schema_for!(VoiceSdpRequest),
+            "reply": null,
+        },
+        "voice.ice": {
+            "request": schema_for!(VoiceIceRequest),
+            "reply": null,
+        },
+        "voice.turn": {
+            "request": any_data(),
+            "reply": schema_for!(TurnCredentials),
+        },
+        "telemetry.event": {
+            "request": schema_for!(TelemetryEvent),
+            "reply": null,
+        },
+    })
+}
+
+// lobby.*
+fn lobby_commands() -> Value {
+    json!({
+        "lobby.chat": {
+            "request": schema_for!(LobbyChatRequest),
+            "reply": null,
+        },
+        "lobby.select": {
+            "request": schema_for!(LobbySelection),
+            "reply": schema_for!(LobbySelection),
+        },
+        "lobby.create": {
+            "request": schema_for!(LobbyCreateRequest),
+            "reply": schema_for!(PrivateLobby),
+        },
+        "lobby.join": {
+            "request": schema_for!(LobbyJoinRequest),
+            "reply": schema_for!(PrivateLobby),
+        },
+        "lobby.start": {
+            "request": any_data(),
+            "reply": schema_for!(MatchUpdate),
+        },
+        "lobby.kick": {
+            "request": schema_for!(LobbyKickRequest),
+            "reply": schema_for!(KickVote),
+        },
+        "lobby.team": {
+            "request": schema_for!(LobbyTeamRequest),
+            "reply": schema_for!(LobbyTeams),
+        },
+    })
+}
+
+// schedule.*
+fn schedule_commands() -> Value {
+    json!({
+        "schedule.create": {
+            "request": schema_for!(ScheduleCreateRequest),
+            "reply": schema_for!(ScheduledMatch),
+        },
+        "schedule.cancel": {
+            "request": schema_for!(ScheduleCancelRequest),
+            "reply": schema_for!(ScheduledMatch),
+        },
+        "schedule.list": {
+            "request": any_data(),
+            "reply": schema_for!(ScheduleList),
+        },
+    })
+}
+
+// user.*, lfg.*, tutorial.* and trust.*
+fn player_commands() -> Value {
+    json!({
+        "user.preferences": {
+            "request": any_data(),
+            "reply": schema_for!(NotificationPreferences),
+        },
+        "user.update_preferences": {
+            "request": schema_for!(PreferencesUpdate),
+            "reply": schema_for!(NotificationPreferences),
+        },
+        "lfg.subscribe": {
+            "request": schema_for!(LfgSubscribeRequest),
+            "reply": schema_for!(LfgSubscription),
+        },
+        "lfg.unsubscribe": {
+            "request": any_data(),
+            "reply": schema_for!(LfgSubscription),
+        },
+        "lfg.post": {
+            "request": any_data(),
+            "reply": schema_for!(LfgPost),
+        },
+        "tutorial.start": {
+            "request": any_data(),
+            "reply": schema_for!(TutorialStarted),
+        },
+        "tutorial.status": {
+            "request": any_data(),
+            "reply": schema_for!(TutorialProgress),
+        },
+        "trust.appeal": {
+            "request": schema_for!(AppealRequest),
+            "reply": schema_for!(AppealReceipt),
+        },
+    })
+}
+
+// device.*
+fn device_commands() -> Value {
+    json!({
+        "device.register": {
+            "request": schema_for!(DeviceRegistration),
+            "reply": schema_for!(Device),
+        },
+        "device.list": {
+            "request": any_data(),
+            "reply": schema_for!(DeviceList),
+        },
+        "device.revoke": {
+            "request": schema_for!(DeviceRevocation),
+            "reply": schema_for!(DeviceRevocation),
+        },
+    })
+}
+
+fn events() -> Value {
+    json!({
+        EVENT_WELCOME: schema_for!(Welcome),
+        EVENT_STATE_DELTA: schema_for!(StateDelta),
+        EVENT_DISCOVERY: schema_for!(TreasureDiscovery),
+        EVENT_TREASURES_SPAWNED: schema_for!(TreasureSpawn),
+        EVENT_CAPTURE: schema_for!(CaptureUpdate),
+        EVENT_ROUND: schema_for!(RoundResult),
+        EVENT_TICK: schema_for!(GameTick),
+        EVENT_POSITION: schema_for!(PositionUpdate),
+        EVENT_ADMIN_MATCHES: schema_for!(MatchStatsReport),
+        EVENT_MATCH_ADJUSTED: schema_for!(AuditEntry),
+        EVENT_RANK_PLACED: schema_for!(RankPlacement),
+        EVENT_PARTY_QUEUED: schema_for!(MatchUpdate),
+        EVENT_MATCH_FOUND: schema_for!(MatchUpdate),
+        EVENT_BANNED: schema_for!(BanNotice),
+        EVENT_SESSION_ENDED: schema_for!(SessionEnded),
+        EVENT_ANNOUNCEMENT: schema_for!(AnnouncementNotice),
+        EVENT_PING: schema_for!(MapPing),
+        EVENT_EMOTE: schema_for!(EmoteEvent),
+        EVENT_VOICE_OFFER: schema_for!(VoiceSdp),
+        EVENT_VOICE_ANSWER: schema_for!(VoiceSdp),
+        EVENT_VOICE_ICE: schema_for!(VoiceIce),
+        EVENT_LOBBY_OPENED: schema_for!(LobbyOpened),
+        EVENT_LOBBY_CHAT: schema_for!(LobbyChatMessage),
+        EVENT_LOBBY_SELECTION: schema_for!(LobbySelectionUpdate),
+        EVENT_LOBBY_HOST: schema_for!(HostChanged),
+        EVENT_LOBBY_KICK_VOTE: schema_for!(KickVote),
+        EVENT_LOBBY_KICKED: schema_for!(LobbyKicked),
+        EVENT_LOBBY_TEAMS: schema_for!(LobbyTeams),
+        EVENT_SCHEDULE_INVITED: schema_for!(ScheduledMatch),
+        EVENT_SCHEDULE_REMINDER: schema_for!(ScheduledMatch),
+        EVENT_SCHEDULE_LOBBY_OPEN: schema_for!(ScheduledMatch),
+        EVENT_SCHEDULE_CANCELLED: schema_for!(ScheduledMatch),
+        EVENT_LFG_POST: schema_for!(LfgPost),
+        EVENT_TUTORIAL_STEP: schema_for!(TutorialStep),
+    })
+}
@@ -0,0 +1,136 @@
+use std::collections::HashMap;
+use std::time::Instant;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::{Mutex, mpsc};
+use uuid::Uuid;
+
+use crate::models::message::ClientMessage;
+
+// Fields replaced wholesale before a message is written
+const REDACTED_FIELDS: [&str; 4] = ["token", "secret", "nickname", "email"];
+
+// One line of a recording
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum RecordedEvent {
+    Open { at_ms: u64, conn: Uuid, user: Uuid },
+    Message { at_ms: u64, conn: Uuid, message: ClientMessage },
+    Close { at_ms: u64, conn: Uuid },
+}
+
+// Recorder of inbound client traffic, for reproducing bugs with the replay
+// tool (src/bin/replay.rs). Off unless TRAFFIC_RECORD_PATH is set.
+//
+// Sessions opening and closing and every ClientMessage are appended to the
+// file as JSON lines, timed from server start. User and other ids are
+// replaced with stable pseudonyms, and tokens, nicknames and emails are
+// redacted, so recordings can leave production. Messages that don't parse
+// are not recorded.
+pub struct TrafficRecorder {
+    started: Instant,
+    lines: mpsc::UnboundedSender<String>,
+    // Real id to pseudonym, stable for the life of the recording
+    pseudonyms: Mutex<HashMap<Uuid, Uuid>>,
+}
+
+impl TrafficRecorder {
+    pub fn open(path: &str) -> Option<Self> {
+        let file = match std::fs::OpenOptions::new().create(true).append(true).open(path) {
+            Ok(file) => tokio::fs::File::from_std(file),
+            Err(e) => {
+                tracing::error!("Failed to open traffic recording {}: {}", path, e);
+                return None;
+            }
+        };
+        tracing::warn!("Recording client traffic to {}", path);
+
+        let (lines, mut rx) = mpsc::unbounded_channel::<String>();
+        let path = path.to_string();
+        tokio::spawn(async move {
+            let mut out = tokio::io::BufWriter::new(file);
+            while let Some(line) = rx.recv().await {
+                let mut result = out.write_all(line.as_bytes()).await;
+                // Flush once the burst is written
+                while let Ok(line) = rx.try_recv() {
+                    if result.is_ok() {
+                        result = out.write_all(line.as_bytes()).await;
+                    }
+                }
+                if let Err(e) = result.and(out.flush().await) {
+                    tracing::error!("Failed to write traffic recording {}: {}", path, e);
+                }
+            }
+        });
+
+        Some(Self {
+            started: Instant::now(),
+            lines,
+            pseudonyms: Mutex::new(HashMap::new()),
+        })
+    }
+
+    fn at_ms(&self) -> u64 {
+        self.started.elapsed().as_millis() as u64
+    }
+
+    fn write(&self, event: &RecordedEvent) {
+        if let Ok(mut line) = serde_json::to_string(event) {
+            line.push('\n');
+            let _ = self.lines.send(line);
+        }
+    }
+
+    async fn pseudonym(&self, id: Uuid) -> Uuid {
+        *self.pseudonyms.lock().await.entry(id).or_insert_with(Uuid::new_v4)
+    }
+
+    pub async fn opened(&self, conn: Uuid, user: Uuid) {
+        let event = RecordedEvent::Open {
+            at_ms: self.at_ms(),
+            conn,
+            user: self.pseudonym(user).await,
+        };
+        self.write(&event);
+    }
+
+    pub async fn message(&self, conn: Uuid, text: &str) {
+        let at_ms = self.at_ms();
+        let Ok(mut message) = serde_json::from_str::<ClientMessage>(text) else {
+            return;
+        };
+        self.redact(&mut message.data).await;
+        self.write(&RecordedEvent::Message { at_ms, conn, message });
+    }
+
+    pub fn closed(&self, conn: Uuid) {
+        self.write(&RecordedEvent::Close { at_ms: self.at_ms(), conn });
+    }
+
+    // Pseudonymize ids and blank out personal or secret fields
+    async fn redact(&self, value: &mut Value) {
+        let mut pending = vec![value];
+        while let Some(value) = pending.pop() {
+            match value {
+                Value::Object(fields) => {
+                    for (name, field) in fields.iter_mut() {
+                        if REDACTED_FIELDS.contains(&name.as_str()) {
+                            *field = Value::String("[redacted]".to_string());
+                        } else {
+                            pending.push(field);
+                        }
+                    }
+                }
+                Value::Array(items) => pending.extend(items.iter_mut()),
+                Value::String(s) => {
+                    if let Ok(id) = Uuid::parse_str(s) {
+                        *s = self.pseudonym(id).await.to_string();
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+}
@@ -0,0 +1,86 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use serde_json::Value;
+use tokio::sync::Mutex;
+use uuid::Uuid;
+
+use crate::game::runtime::{GameTick, MatchTick};
+use crate::memory;
+use crate::metrics::METRICS;
+
+// A match message waiting out the spectator delay
+pub struct Delayed {
+    pub match_id: Uuid,
+    pub event: String,
+    pub data: Value,
+    due: Instant,
+}
+
+// Match traffic held back for spectators.
+//
+// Spectators see a match SPECTATOR_DELAY_SECS behind its players, so someone
+// watching can't call out positions to a friend playing in it. Messages are
+// only buffered for matches someone on this node is watching. Past
+// MAX_SPECTATOR_BUFFER messages the oldest are dropped.
+pub struct SpectatorFeed {
+    delay: Duration,
+    max: usize,
+    queue: Mutex<VecDeque<Delayed>>,
+}
+
+impl SpectatorFeed {
+    pub fn new(delay: Duration, max: usize) -> Self {
+        Self {
+            delay,
+            max,
+            queue: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    pub fn delay(&self) -> Duration {
+        self.delay
+    }
+
+    pub async fn push(&self, match_id: Uuid, event: &str, data: Value) {
+        let mut queue = self.queue.lock().await;
+        while queue.len() >= self.max {
+            queue.pop_front();
+            METRICS.record_memory_cap_hit(memory::SPECTATOR_BUFFER);
+        }
+        queue.push_back(Delayed {
+            match_id,
+            event: event.to_string(),
+            data,
+            due: Instant::now() + self.delay,
+        });
+    }
+
+    pub async fn len(&self) -> usize {
+        self.queue.lock().await.len()
+    }
+
+    // Messages whose delay is over, oldest first
+    pub async fn take_due(&self) -> Vec<Delayed> {
+        let now = Instant::now();
+        let mut queue = self.queue.lock().await;
+        let ready = queue.iter().take_while(|delayed| delayed.due <= now).count();
+        queue.drain(..ready).collect()
+    }
+}
+
+// What spectators get of a tick: every player that moved, and none of the
+// players' proximity hints
+pub fn spectator_view(tick: &MatchTick) -> Option<GameTick> {
+    let mut views = tick.views.values();
+    let mut view = views.next()?.clone();
+    for other in views {
+        for position in &other.positions {
+            if !view.positions.iter().any(|p| p.user_id == position.user_id) {
+                view.positions.push(position.clone());
+            }
+        }
+    }
+    view.hints.clear();
+    Some(view)
+}
@@ -0,0 +1,132 @@
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::sync::Arc;
+use axum::{
+    extract::{Query, State, ws::Message},
+    http::StatusCode,
+    response::sse::{Event, KeepAlive, Sse},
+};
+use futures_util::stream::{self, Stream};
+use tokio::sync::mpsc;
+use uuid::Uuid;
+
+use crate::AppState;
+use crate::client_ip::ClientIp;
+use crate::error::Error;
+use super::handler::{WebSocketHandler, ban_frames};
+
+// Server-Sent Events fallback for networks that block WebSockets.
+//
+// GET /sse opens the downstream: the first event is the usual welcome message
+// carrying `conn_id`. Both take the player's session token in `token`, like
+// /ws. Commands are sent with POST /sse/command?conn_id=...&token=...
+// using the same ClientMessage JSON as the WebSocket; replies and broadcasts
+// arrive on the event stream. Banned players get a single `sys.banned` event
+// instead, and the stream ends. Past MAX_CONNECTIONS sessions the stream is
+// refused with a 503.
+pub async fn sse_connect(
+    State(state): State<AppState>,
+    ClientIp(ip): ClientIp,
+    Query(params): Query<HashMap<String, String>>,
+) -> crate::error::Result<Sse<impl Stream<Item = Result<Event, Infallible>>>> {
+    let user_id = crate::user_id_from_params(&state, &params)?;
+    tracing::info!("SSE connection from user: {} ({})", user_id, ip);
+    state.ws_handler.check_capacity().await?;
+
+    let (tx, rx) = mpsc::unbounded_channel();
+    let conn_id = match state.bans.active_ban(user_id).await {
+        // Deliver the ban notice and end the stream without opening a session
+        Some(ban) => {
+            tracing::info!("Rejecting SSE connection of banned user {}", user_id);
+            for frame in ban_frames(&ban.notice()) {
+                let _ = tx.send(frame);
+            }
+            None
+        }
+        // SSE can't carry binary frames, so never compress
+        None => {
+            let platform = crate::platform_from_params(&params);
+            let locale = crate::locale_from_params(&params);
+            let device_id = crate::device_id_from_params(&params);
+            let resume = params.get("resume").map(String::as_str);
+            Some(state.ws_handler.open_session(user_id, ip, false, platform, locale, device_id, resume, tx).await)
+        }
+    };
+    let guard = SessionGuard {
+        handler: state.ws_handler.clone(),
+        conn_id,
+    };
+
+    let events = stream::unfold((rx, guard), |(mut rx, guard)| async move {
+        loop {
+            match rx.recv().await {
+                Some(Message::Text(text)) => {
+                    return Some((Ok(Event::default().data(text)), (rx, guard)));
+                }
+                Some(Message::Close(_)) | None => return None,
+                // Binary and control frames have no SSE equivalent
+                Some(_) => continue,
+            }
+        }
+    });
+
+    Ok(Sse::new(events).keep_alive(KeepAlive::default()))
+}
+
+#[utoipa::path(
+    post,
+    path = "/sse/command",
+    tag = "transport",
+    params(
+        ("conn_id" = Uuid, Query, description = "conn_id from the welcome event"),
+        ("token" = String, Query, description = "Session token of the SSE session's player")
+    ),
+    request_body = ClientMessage,
+    responses(
+        (status = 202, description = "Accepted; the reply arrives on the event stream"),
+        (status = 400, description = "Missing or invalid conn_id", body = ErrorBody),
+        (status = 401, description = "Missing, invalid or expired session token", body = ErrorBody),
+        (status = 403, description = "Session belongs to another user", body = ErrorBody),
+        (status = 404, description = "Unknown session", body = ErrorBody)
+    )
+)]
+pub async fn sse_command(
+    State(state): State<AppState>,
+    Query(params): Query<HashMap<String, String>>,
+    body: String,
+) -> crate::error::Result<StatusCode> {
+    let conn_id = params.get("conn_id")
+        .and_then(|id| Uuid::parse_str(id).ok())
+        .ok_or(Error::InvalidMessage)?;
+    let user_id = crate::user_id_from_params(&state, &params)?;
+
+    // The session must exist and belong to the caller
+    let conn = state.conn_manager.get_connection(&conn_id)
+        .await
+        .ok_or(Error::ConnectionNotFound)?;
+    if conn.user_id != user_id {
+        return Err(Error::PermissionDenied("session belongs to another user".to_string()));
+    }
+
+    state.ws_handler.handle_text(conn_id, &body).await;
+    Ok(StatusCode::ACCEPTED)
+}
+
+// Closes the session once the client goes away and the stream is dropped
+struct SessionGuard {
+    handler: Arc<WebSocketHandler>,
+    // None when the connection was refused
+    conn_id: Option<Uuid>,
+}
+
+impl Drop for SessionGuard {
+    fn drop(&mut self) {
+        let Some(conn_id) = self.conn_id else {
+            return;
+        };
+        let handler = self.handler.clone();
+        tokio::spawn(async move {
+            handler.close_session(conn_id).await;
+        });
+    }
+}
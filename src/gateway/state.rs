@@ -1,13 +1,116 @@
 use std::collections::HashMap;
+use std::net::IpAddr;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use axum::extract::ws::Message;
+use schemars::JsonSchema;
+use serde::Serialize;
 use tokio::sync::{mpsc, RwLock};
+use utoipa::ToSchema;
 use uuid::Uuid;
 
+use crate::models::game::{Platform, PlatformBreakdown};
+
+// A net report older than this no longer throttles the connection
+const NET_REPORT_TTL: Duration = Duration::from_secs(30);
+
+// Link quality class derived from the last sys.net_report
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, JsonSchema, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum LinkQuality {
+    Good,
+    Fair,
+    Poor,
+}
+
+impl LinkQuality {
+    pub fn classify(rtt_ms: u32, packet_loss: f32) -> Self {
+        if rtt_ms <= 150 && packet_loss <= 0.02 {
+            LinkQuality::Good
+        } else if rtt_ms <= 400 && packet_loss <= 0.08 {
+            LinkQuality::Fair
+        } else {
+            LinkQuality::Poor
+        }
+    }
+
+    // Send one game tick out of this many; skipped ticks are merged into the next
+    pub fn tick_stride(&self) -> u64 {
+        match self {
+            LinkQuality::Good => 1,
+            LinkQuality::Fair => 2,
+            LinkQuality::Poor => 4,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct NetReport {
+    pub rtt_ms: u32,
+    // 0.0 - 1.0
+    pub packet_loss: f32,
+    pub quality: LinkQuality,
+    pub reported_at: Instant,
+}
+
+// Connections of one match, with summed net reports
+#[derive(Debug, Clone, Default)]
+pub struct MatchConnections {
+    pub connected: usize,
+    pub rtt_ms_sum: u64,
+    pub packet_loss_sum: f64,
+    pub reports: usize,
+    pub platforms: PlatformBreakdown,
+}
+
+impl MatchConnections {
+    pub fn avg_rtt_ms(&self) -> Option<u64> {
+        (self.reports > 0).then(|| self.rtt_ms_sum / self.reports as u64)
+    }
+
+    pub fn avg_packet_loss(&self) -> Option<f64> {
+        (self.reports > 0).then(|| self.packet_loss_sum / self.reports as f64)
+    }
+}
+
+// One open connection, for live ops
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ConnectionInfo {
+    pub conn_id: Uuid,
+    pub user_id: Uuid,
+    pub match_id: Option<Uuid>,
+    #[schema(value_type = String)]
+    pub ip: IpAddr,
+    // From the last sys.net_report, absent without one
+    pub rtt_ms: Option<u32>,
+    pub quality: Option<LinkQuality>,
+    pub platform: Option<Platform>,
+    pub device_id: Option<String>,
+}
+
 #[derive(Debug, Clone)]
 pub struct ClientState {
     pub user_id: Uuid,
     pub match_id: Option<Uuid>,
+    // Real client address (resolved through trusted proxies)
+    pub ip: IpAddr,
+    // Large messages are sent as gzip-compressed binary frames
+    pub compress: bool,
+    // From ?platform= at connect; None if missing or unrecognised
+    pub platform: Option<Platform>,
+    // From ?locale= at connect, normalized; None if missing or malformed
+    pub locale: Option<String>,
+    // From ?device_id= at connect or device.register
+    pub device_id: Option<String>,
+    // Receiving admin.matches updates
+    pub admin_watch: bool,
+    // Match being watched with match.spectate
+    pub spectating: Option<Uuid>,
+    // Match types of the lfg.post events wanted, empty for all; None when
+    // not subscribed
+    pub lfg: Option<Vec<String>>,
+    // Last connection quality report; treated as good until one arrives
+    pub net: Option<NetReport>,
     pub sender: mpsc::UnboundedSender<Message>,
 }
 
@@ -23,15 +126,29 @@ impl ConnectionManager {
         }
     }
 
-    pub async fn get_sender(&self, conn_id: &Uuid) -> Option<mpsc::UnboundedSender<Message>> {
-        let connections = self.connections.read().await;
-        connections.get(conn_id).map(|state| state.sender.clone())
-    }
-
-    pub async fn add_connection(&self, conn_id: Uuid, user_id: Uuid, sender: mpsc::UnboundedSender<Message>) {
+    #[allow(clippy::too_many_arguments)]
+    pub async fn add_connection(
+        &self,
+        conn_id: Uuid,
+        user_id: Uuid,
+        ip: IpAddr,
+        compress: bool,
+        platform: Option<Platform>,
+        locale: Option<String>,
+        sender: mpsc::UnboundedSender<Message>,
+    ) {
         let state = ClientState {
             user_id,
             match_id: None,
+            ip,
+            compress,
+            platform,
+            locale,
+            device_id: None,
+            admin_watch: false,
+            spectating: None,
+            lfg: None,
+            net: None,
             sender,
         };
         
@@ -44,6 +161,11 @@ impl ConnectionManager {
         connections.remove(conn_id);
     }
 
+    // 当前打开的连接数
+    pub async fn connection_count(&self) -> usize {
+        self.connections.read().await.len()
+    }
+
     pub async fn get_connection(&self, conn_id: &Uuid) -> Option<ClientState> {
         let connections = self.connections.read().await;
         connections.get(conn_id).cloned()
@@ -64,12 +186,189 @@ impl ConnectionManager {
             .collect()
     }
     
+    // 这些用户的所有连接，不论是否在比赛中
+    pub async fn get_user_connections(&self, user_ids: &[Uuid]) -> Vec<Uuid> {
+        let connections = self.connections.read().await;
+        
+        connections.iter()
+            .filter(|(_, state)| user_ids.contains(&state.user_id))
+            .map(|(conn_id, _)| *conn_id)
+            .collect()
+    }
+    
+    // 比赛中的连接及其用户，用于按玩家过滤推送内容
+    pub async fn get_match_members(&self, match_id: Uuid) -> Vec<(Uuid, Uuid)> {
+        let connections = self.connections.read().await;
+        
+        connections.iter()
+            .filter(|(_, state)| state.match_id == Some(match_id))
+            .map(|(conn_id, state)| (*conn_id, state.user_id))
+            .collect()
+    }
+    
+    pub async fn update_net_report(&self, conn_id: &Uuid, report: NetReport) {
+        let mut connections = self.connections.write().await;
+        
+        if let Some(state) = connections.get_mut(conn_id) {
+            state.net = Some(report);
+        }
+    }
+    
+    // 比赛中的连接、用户以及 tick 间隔
+    pub async fn get_match_members_with_stride(&self, match_id: Uuid) -> Vec<(Uuid, Uuid, u64)> {
+        let connections = self.connections.read().await;
+        
+        connections.iter()
+            .filter(|(_, state)| state.match_id == Some(match_id))
+            .map(|(conn_id, state)| {
+                // 报告过期后按良好网络处理
+                let stride = state.net.as_ref()
+                    .filter(|n| n.reported_at.elapsed() < NET_REPORT_TTL)
+                    .map(|n| n.quality.tick_stride())
+                    .unwrap_or(1);
+                (*conn_id, state.user_id, stride)
+            })
+            .collect()
+    }
+    
+    // 每场比赛的连接数和网络质量汇总
+    pub async fn connections_per_match(&self) -> HashMap<Uuid, MatchConnections> {
+        let connections = self.connections.read().await;
+        let mut summary: HashMap<Uuid, MatchConnections> = HashMap::new();
+        for state in connections.values() {
+            let Some(match_id) = state.match_id else {
+                continue;
+            };
+            let entry = summary.entry(match_id).or_default();
+            entry.connected += 1;
+            entry.platforms.add(state.platform);
+            if let Some(net) = &state.net {
+                entry.rtt_ms_sum += net.rtt_ms as u64;
+                entry.packet_loss_sum += net.packet_loss as f64;
+                entry.reports += 1;
+            }
+        }
+        summary
+    }
+    
+    // 这些用户连接时上报的平台，没有上报的用户不在结果中
+    pub async fn platforms_of(&self, user_ids: &[Uuid]) -> HashMap<Uuid, Platform> {
+        let connections = self.connections.read().await;
+        
+        connections.values()
+            .filter(|state| user_ids.contains(&state.user_id))
+            .filter_map(|state| Some((state.user_id, state.platform?)))
+            .collect()
+    }
+    
+    // 所有连接及其用户和所在的比赛
+    pub async fn all_connections(&self) -> Vec<(Uuid, Uuid, Option<Uuid>)> {
+        let connections = self.connections.read().await;
+        
+        connections.iter()
+            .map(|(conn_id, state)| (*conn_id, state.user_id, state.match_id))
+            .collect()
+    }
+    
+    // 各连接的语言区域
+    pub async fn connection_locales(&self) -> HashMap<Uuid, Option<String>> {
+        let connections = self.connections.read().await;
+        
+        connections.iter()
+            .map(|(conn_id, state)| (*conn_id, state.locale.clone()))
+            .collect()
+    }
+    
+    // 所有连接的概况，供运维查看
+    pub async fn connection_infos(&self) -> Vec<ConnectionInfo> {
+        let connections = self.connections.read().await;
+        
+        connections.iter()
+            .map(|(conn_id, state)| ConnectionInfo {
+                conn_id: *conn_id,
+                user_id: state.user_id,
+                match_id: state.match_id,
+                ip: state.ip,
+                rtt_ms: state.net.as_ref().map(|net| net.rtt_ms),
+                quality: state.net.as_ref().map(|net| net.quality),
+                platform: state.platform,
+                device_id: state.device_id.clone(),
+            })
+            .collect()
+    }
+    
+    // 标记连接开始订阅比赛统计，已订阅时返回 false
+    pub async fn start_admin_watch(&self, conn_id: &Uuid) -> bool {
+        let mut connections = self.connections.write().await;
+        match connections.get_mut(conn_id) {
+            Some(state) if !state.admin_watch => {
+                state.admin_watch = true;
+                true
+            }
+            _ => false,
+        }
+    }
+    
+    // 订阅或取消订阅组队招募
+    pub async fn set_lfg(&self, conn_id: &Uuid, match_types: Option<Vec<String>>) {
+        let mut connections = self.connections.write().await;
+        
+        if let Some(state) = connections.get_mut(conn_id) {
+            state.lfg = match_types;
+        }
+    }
+    
+    // 订阅了该比赛类型招募的空闲连接及其用户
+    pub async fn lfg_subscribers(&self, match_type: &str) -> Vec<(Uuid, Uuid)> {
+        let connections = self.connections.read().await;
+        
+        connections.iter()
+            .filter(|(_, state)| state.match_id.is_none())
+            .filter(|(_, state)| state.lfg.as_ref().is_some_and(|types| {
+                types.is_empty() || types.iter().any(|t| t == match_type)
+            }))
+            .map(|(conn_id, state)| (*conn_id, state.user_id))
+            .collect()
+    }
+    
+    // 开始或停止观战
+    pub async fn set_spectating(&self, conn_id: &Uuid, match_id: Option<Uuid>) {
+        let mut connections = self.connections.write().await;
+        
+        if let Some(state) = connections.get_mut(conn_id) {
+            state.spectating = match_id;
+        }
+    }
+    
+    // 正在观看该比赛的连接
+    pub async fn get_spectators(&self, match_id: Uuid) -> Vec<Uuid> {
+        let connections = self.connections.read().await;
+        
+        connections.iter()
+            .filter(|(_, state)| state.spectating == Some(match_id))
+            .map(|(conn_id, _)| *conn_id)
+            .collect()
+    }
+    
+    // 记录连接所属的设备
+    pub async fn set_device(&self, conn_id: &Uuid, device_id: &str) {
+        let mut connections = self.connections.write().await;
+        
+        if let Some(state) = connections.get_mut(conn_id) {
+            state.device_id = Some(device_id.to_string());
+        }
+    }
+    
     // 添加更新连接匹配ID的方法
     pub async fn update_match_id(&self, conn_id: &Uuid, match_id: Option<Uuid>) {
         let mut connections = self.connections.write().await;
         
         if let Some(state) = connections.get_mut(conn_id) {
             state.match_id = match_id;
+            // 加入比赛后不再观战
+            if match_id.is_some() {
+                state.spectating = None;
+            }
         }
     }
 }
\ No newline at end of file
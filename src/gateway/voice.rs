@@ -0,0 +1,35 @@
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use sha1::Sha1;
+use uuid::Uuid;
+
+use crate::config::TurnConfig;
+use super::protocol::TurnCredentials;
+
+// Longest SDP relayed; real offers are a few kilobytes
+pub const MAX_SDP_LEN: usize = 16 * 1024;
+// Longest serialized ICE candidate relayed
+pub const MAX_CANDIDATE_LEN: usize = 2 * 1024;
+
+// Team voice signaling is a plain relay: voice.offer, voice.answer and
+// voice.ice go from one player to one teammate in the same match, through the
+// node running the match, and media flows peer to peer or through TURN.
+
+// Credentials in the TURN REST API scheme that coturn checks with its
+// static-auth-secret: the username is "<expiry unix time>:<user id>" and the
+// password is base64(HMAC-SHA1(secret, username)), so nothing is stored.
+pub fn turn_credentials(config: &TurnConfig, user_id: Uuid) -> TurnCredentials {
+    let expires_at = Utc::now() + chrono::Duration::from_std(config.ttl).unwrap_or_default();
+    let username = format!("{}:{}", expires_at.timestamp(), user_id);
+    let mut mac = Hmac::<Sha1>::new_from_slice(config.secret.as_bytes())
+        .expect("HMAC accepts keys of any length");
+    mac.update(username.as_bytes());
+    TurnCredentials {
+        urls: config.urls.clone(),
+        username,
+        credential: STANDARD.encode(mac.finalize().into_bytes()),
+        expires_at,
+    }
+}
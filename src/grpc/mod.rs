@@ -0,0 +1,214 @@
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tonic::{Request, Response, Status};
+use uuid::Uuid;
+
+use crate::apikeys::key::{Caller, Scope};
+use crate::apikeys::service::ApiKeyService;
+use crate::audit::service::AuditLog;
+use crate::config::AdminConfig;
+use crate::error::Error;
+use crate::matchmaking::service::MatchService;
+use crate::models::game;
+
+pub mod proto {
+    tonic::include_proto!("spv");
+}
+
+use proto::match_admin_server::{MatchAdmin, MatchAdminServer};
+
+// gRPC front for other backend services, sharing the same MatchService core.
+// Callers send `authorization: Bearer <key>` metadata with an API key that has
+// the grpc scope, or ADMIN_TOKEN.
+pub struct MatchAdminService {
+    match_service: Arc<MatchService>,
+    api_keys: Arc<ApiKeyService>,
+    audit: Arc<AuditLog>,
+    admin: AdminConfig,
+}
+
+// Serve the gRPC API on its own port in the background
+pub fn spawn(
+    match_service: Arc<MatchService>,
+    api_keys: Arc<ApiKeyService>,
+    audit: Arc<AuditLog>,
+    admin: AdminConfig,
+    port: u16,
+) {
+    let addr = SocketAddr::from(([0, 0, 0, 0], port));
+    let service = MatchAdminService { match_service, api_keys, audit, admin };
+
+    tokio::spawn(async move {
+        tracing::info!("Starting gRPC server on {}", addr);
+        if let Err(e) = tonic::transport::Server::builder()
+            .add_service(MatchAdminServer::new(service))
+            .serve(addr)
+            .await
+        {
+            tracing::error!("gRPC server stopped: {}", e);
+        }
+    });
+}
+
+impl MatchAdminService {
+    fn authenticate<T>(&self, request: &Request<T>) -> Result<Caller, Status> {
+        let token = request.metadata()
+            .get("authorization")
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.strip_prefix("Bearer "))
+            .ok_or_else(|| Status::unauthenticated("missing API key"))?;
+        let caller = self.api_keys.authenticate(&self.admin, token).map_err(to_status)?;
+        if !caller.allows(Scope::Grpc) {
+            return Err(Status::permission_denied("API key lacks the grpc scope"));
+        }
+        Ok(caller)
+    }
+
+    // Only calls that change something are recorded
+    fn audit<R>(&self, caller: &Caller, method: &str, result: &Result<R, Status>) {
+        let status = match result {
+            Ok(_) => 200,
+            Err(status) => match status.code() {
+                tonic::Code::NotFound => 404,
+                tonic::Code::InvalidArgument => 400,
+                tonic::Code::PermissionDenied => 403,
+                tonic::Code::FailedPrecondition => 409,
+                tonic::Code::Unavailable => 503,
+                _ => 500,
+            },
+        };
+        self.audit.record(caller, format!("grpc {}", method), status);
+    }
+}
+
+fn parse_uuid(field: &str, value: &str) -> Result<Uuid, Status> {
+    Uuid::parse_str(value)
+        .map_err(|_| Status::invalid_argument(format!("{} is not a valid UUID", field)))
+}
+
+fn to_status(e: Error) -> Status {
+    match e {
+        Error::MatchNotFound | Error::NotFound(_) => Status::not_found(e.to_string()),
+        Error::InvalidMessage | Error::InvalidMatchType | Error::InvalidParty(_) => Status::invalid_argument(e.to_string()),
+        Error::AuthError => Status::unauthenticated(e.to_string()),
+        Error::PermissionDenied(_) | Error::LocationUntrusted | Error::Banned => {
+            Status::permission_denied(e.to_string())
+        }
+        Error::DbUnavailable => Status::unavailable(e.to_string()),
+        Error::MatchNotReady
+        | Error::MatchAlreadyStarted
+        | Error::UserAlreadyInMatch
+        | Error::TeamFull
+        | Error::TreasureNotActive
+        | Error::MatchNotFinished => Status::failed_precondition(e.to_string()),
+        _ => Status::internal(e.to_string()),
+    }
+}
+
+impl From<game::MatchDetails> for proto::MatchDetails {
+    fn from(details: game::MatchDetails) -> Self {
+        Self {
+            id: details.id.to_string(),
+            match_type: details.match_type,
+            status: details.status.to_str().to_string(),
+            start_time: details.start_time.map(|t| t.to_rfc3339()).unwrap_or_default(),
+            teams: details.teams.into_iter().map(|team| proto::TeamDetails {
+                id: team.id.to_string(),
+                team_number: team.team_number,
+                members: team.members.into_iter().map(|m| proto::MemberDetails {
+                    user_id: m.user_id.to_string(),
+                    nickname: m.nickname,
+                    avatar_url: m.avatar_url,
+                    score: m.score,
+                    platform: m.platform.map(|p| p.to_str().to_string()).unwrap_or_default(),
+                    loadout: m.loadout.unwrap_or_default(),
+                    role: m.role.unwrap_or_default(),
+                }).collect(),
+                total_score: team.total_score,
+                win_probability: team.win_probability.unwrap_or(-1.0),
+                objective_score: team.objective_score,
+            }).collect(),
+            duration_secs: details.duration.map(|d| d.as_secs()).unwrap_or(0),
+            platforms: Some(proto::PlatformBreakdown {
+                ios: details.platforms.ios as u32,
+                android: details.platforms.android as u32,
+                web: details.platforms.web as u32,
+                unknown: details.platforms.unknown as u32,
+            }),
+            victory_condition: details.victory_condition.label(),
+        }
+    }
+}
+
+#[tonic::async_trait]
+impl MatchAdmin for MatchAdminService {
+    async fn get_match(
+        &self,
+        request: Request<proto::GetMatchRequest>,
+    ) -> Result<Response<proto::MatchDetails>, Status> {
+        self.authenticate(&request)?;
+        let match_id = parse_uuid("match_id", &request.into_inner().match_id)?;
+        let details = self.match_service.get_match_details(match_id).await.map_err(to_status)?;
+        Ok(Response::new(details.into()))
+    }
+
+    async fn get_match_status(
+        &self,
+        request: Request<proto::GetMatchRequest>,
+    ) -> Result<Response<proto::MatchStatusReply>, Status> {
+        self.authenticate(&request)?;
+        let match_id = parse_uuid("match_id", &request.into_inner().match_id)?;
+        let status = self.match_service.get_match_status(match_id).await.map_err(to_status)?;
+        Ok(Response::new(proto::MatchStatusReply {
+            match_id: match_id.to_string(),
+            status: status.to_str().to_string(),
+        }))
+    }
+
+    async fn force_end_match(
+        &self,
+        request: Request<proto::GetMatchRequest>,
+    ) -> Result<Response<proto::Empty>, Status> {
+        let caller = self.authenticate(&request)?;
+        let result = async {
+            let match_id = parse_uuid("match_id", &request.into_inner().match_id)?;
+            tracing::info!("gRPC force-end of match {} by {}", match_id, caller.actor());
+            self.match_service.end_match(match_id).await.map_err(to_status)
+        }.await;
+        self.audit(&caller, "ForceEndMatch", &result);
+        result?;
+        Ok(Response::new(proto::Empty {}))
+    }
+
+    async fn record_discovery(
+        &self,
+        request: Request<proto::TreasureDiscovery>,
+    ) -> Result<Response<proto::Empty>, Status> {
+        let caller = self.authenticate(&request)?;
+        let d = request.into_inner();
+        let result = async {
+            self.match_service.record_discovery(
+                parse_uuid("match_id", &d.match_id)?,
+                parse_uuid("team_id", &d.team_id)?,
+                parse_uuid("user_id", &d.user_id)?,
+                parse_uuid("treasure_id", &d.treasure_id)?,
+                Some(d.score),
+            ).await.map_err(to_status)
+        }.await;
+        self.audit(&caller, "RecordDiscovery", &result);
+        result?;
+        Ok(Response::new(proto::Empty {}))
+    }
+
+    async fn get_player_match(
+        &self,
+        request: Request<proto::GetPlayerMatchRequest>,
+    ) -> Result<Response<proto::PlayerMatchReply>, Status> {
+        self.authenticate(&request)?;
+        let user_id = parse_uuid("user_id", &request.into_inner().user_id)?;
+        let match_id = self.match_service.active_match_for_user(user_id).await.map_err(to_status)?;
+        Ok(Response::new(proto::PlayerMatchReply {
+            match_id: match_id.map(|id| id.to_string()).unwrap_or_default(),
+        }))
+    }
+}
@@ -0,0 +1,41 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::models::game::PlayerPosition;
+use crate::models::zone::Zone;
+
+// One stored position report of a player in a match
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PositionSample {
+    pub match_id: Uuid,
+    pub user_id: Uuid,
+    pub x: f32,
+    pub y: f32,
+    pub recorded_at: DateTime<Utc>,
+}
+
+// Samples that fell in one square of the map
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct HeatmapTile {
+    // Zone containing the samples; None for positions outside every zone
+    pub zone_id: Option<String>,
+    // Lower-left corner and side of the square, in map units
+    pub x: f32,
+    pub y: f32,
+    pub size: f32,
+    pub samples: i64,
+    pub computed_at: DateTime<Utc>,
+}
+
+// Square the position falls in, as (column, row) of a grid of `tile_size` squares
+pub fn tile_of(x: f32, y: f32, tile_size: f32) -> (i32, i32) {
+    ((x / tile_size).floor() as i32, (y / tile_size).floor() as i32)
+}
+
+// First zone containing the sample
+pub fn zone_of(zones: &[Zone], x: f32, y: f32) -> Option<&Zone> {
+    let pos = PlayerPosition { x, y };
+    zones.iter().find(|zone| zone.contains(&pos))
+}
@@ -0,0 +1,181 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use chrono::Utc;
+use tokio::sync::{Mutex, mpsc};
+use uuid::Uuid;
+
+use crate::cluster::scheduler::LeaderElection;
+use crate::config::HeatmapConfig;
+use crate::db::repository::PositionRepository;
+use crate::error::Result;
+use crate::jobs::service::JobService;
+use crate::matchmaking::zones::ZoneRegistry;
+use crate::models::game::PlayerPosition;
+use super::sample::{HeatmapTile, PositionSample, tile_of, zone_of};
+
+// Samples waiting to be written; further samples are dropped
+const QUEUE_CAPACITY: usize = 10_000;
+// Most samples written in one insert
+const WRITE_BATCH: usize = 500;
+// Longest a sample waits in the queue before being written
+const FLUSH_INTERVAL: Duration = Duration::from_secs(2);
+// Throttle entries of players not heard from in this long are forgotten
+const STALE_AFTER: Duration = Duration::from_secs(300);
+// Samples read per page while aggregating
+const READ_PAGE: i64 = 5_000;
+
+type LastSamples = Arc<Mutex<HashMap<(Uuid, Uuid), Instant>>>;
+
+// Position history and the heatmap built from it.
+//
+// Position reports are throttled to one sample per player and match every
+// HEATMAP_SAMPLE_INTERVAL_SECS and written in batches in the background, like
+// telemetry. A periodic job rebuilds the per-zone heatmap tiles from the
+// samples of the last HEATMAP_WINDOW_HOURS; with several instances only the
+// leader rebuilds.
+pub struct HeatmapService {
+    config: HeatmapConfig,
+    repo: Arc<dyn PositionRepository>,
+    zones: Arc<ZoneRegistry>,
+    last_sample: LastSamples,
+    queue: mpsc::Sender<PositionSample>,
+}
+
+impl HeatmapService {
+    pub fn init(
+        config: HeatmapConfig,
+        repo: Arc<dyn PositionRepository>,
+        zones: Arc<ZoneRegistry>,
+        leader: Arc<LeaderElection>,
+        jobs: &Arc<JobService>,
+    ) -> Arc<Self> {
+        let (queue, samples) = mpsc::channel(QUEUE_CAPACITY);
+        let last_sample: LastSamples = Arc::new(Mutex::new(HashMap::new()));
+        tokio::spawn(run_writer(repo.clone(), samples, last_sample.clone()));
+
+        let service = Arc::new(Self {
+            config,
+            repo,
+            zones,
+            last_sample,
+            queue,
+        });
+
+        let aggregator = service.clone();
+        jobs.spawn_periodic(leader, "heatmap_aggregate", service.config.aggregate_interval, true, move |_| {
+            let aggregator = aggregator.clone();
+            async move { aggregator.aggregate().await }
+        });
+
+        service
+    }
+
+    // Keep a position report if the player's previous sample in this match is old enough
+    pub async fn record(&self, match_id: Uuid, user_id: Uuid, position: &PlayerPosition) {
+        let now = Instant::now();
+        {
+            let mut last_sample = self.last_sample.lock().await;
+            if let Some(at) = last_sample.get(&(match_id, user_id))
+                && now.duration_since(*at) < self.config.sample_interval
+            {
+                return;
+            }
+            last_sample.insert((match_id, user_id), now);
+        }
+
+        let sample = PositionSample {
+            match_id,
+            user_id,
+            x: position.x,
+            y: position.y,
+            recorded_at: Utc::now(),
+        };
+        if self.queue.try_send(sample).is_err() {
+            tracing::debug!("Position queue full, dropping sample of user {}", user_id);
+        }
+    }
+
+    // Tiles of one zone, or outside every zone when `zone_id` is None, busiest first
+    pub async fn tiles(&self, zone_id: Option<&str>) -> Result<Vec<HeatmapTile>> {
+        let mut tiles = self.repo.heatmap_tiles(zone_id).await?;
+        tiles.sort_by_key(|tile| std::cmp::Reverse(tile.samples));
+        Ok(tiles)
+    }
+
+    // Rebuild every tile from the samples inside the window
+    async fn aggregate(&self) -> Result<()> {
+        let computed_at = Utc::now();
+        let since = computed_at - chrono::Duration::from_std(self.config.window).unwrap_or(chrono::Duration::zero());
+        let zones = self.zones.list().await;
+        let tile_size = self.config.tile_size;
+
+        let mut counts: HashMap<(Option<String>, i32, i32), i64> = HashMap::new();
+        let mut offset = 0;
+        let mut total = 0;
+        loop {
+            let page = self.repo.list_positions(since, computed_at, offset, READ_PAGE).await?;
+            for sample in &page {
+                let zone_id = zone_of(&zones, sample.x, sample.y).map(|zone| zone.id.clone());
+                let (col, row) = tile_of(sample.x, sample.y, tile_size);
+                *counts.entry((zone_id, col, row)).or_insert(0) += 1;
+            }
+            total += page.len();
+            if (page.len() as i64) < READ_PAGE {
+                break;
+            }
+            offset += READ_PAGE;
+        }
+
+        let tiles: Vec<HeatmapTile> = counts
+            .into_iter()
+            .map(|((zone_id, col, row), samples)| HeatmapTile {
+                zone_id,
+                x: col as f32 * tile_size,
+                y: row as f32 * tile_size,
+                size: tile_size,
+                samples,
+                computed_at,
+            })
+            .collect();
+        self.repo.replace_heatmap(&tiles).await?;
+        tracing::info!("Heatmap rebuilt: {} tiles from {} samples", tiles.len(), total);
+        Ok(())
+    }
+}
+
+async fn run_writer(repo: Arc<dyn PositionRepository>, mut samples: mpsc::Receiver<PositionSample>, last_sample: LastSamples) {
+    let mut batch = Vec::with_capacity(WRITE_BATCH);
+    let mut interval = tokio::time::interval(FLUSH_INTERVAL);
+    loop {
+        tokio::select! {
+            sample = samples.recv() => match sample {
+                Some(sample) => {
+                    batch.push(sample);
+                    if batch.len() >= WRITE_BATCH {
+                        flush(repo.as_ref(), &mut batch).await;
+                    }
+                }
+                None => {
+                    flush(repo.as_ref(), &mut batch).await;
+                    break;
+                }
+            },
+            _ = interval.tick() => {
+                flush(repo.as_ref(), &mut batch).await;
+                last_sample.lock().await.retain(|_, at| at.elapsed() < STALE_AFTER);
+            }
+        }
+    }
+}
+
+async fn flush(repo: &dyn PositionRepository, batch: &mut Vec<PositionSample>) {
+    if batch.is_empty() {
+        return;
+    }
+    if let Err(e) = repo.insert_positions(batch).await {
+        tracing::warn!("Failed to write {} position samples: {}", batch.len(), e);
+    }
+    batch.clear();
+}
@@ -0,0 +1,19 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use uuid::Uuid;
+
+// A server event held for a player who was offline when it was sent.
+// Delivered on their next connection with `id` as the message's msg_id.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InboxMessage {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub event: String,
+    // What the event is about, e.g. the match id; a player holds at most one
+    // message per event and key
+    pub key: String,
+    pub data: Value,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
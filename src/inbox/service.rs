@@ -0,0 +1,80 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::Utc;
+use serde_json::Value;
+use uuid::Uuid;
+
+use crate::cluster::scheduler::LeaderElection;
+use crate::config::InboxConfig;
+use crate::db::repository::InboxRepository;
+use crate::error::Result;
+use crate::jobs::service::JobService;
+use super::message::InboxMessage;
+
+// How often expired messages are deleted
+const PURGE_INTERVAL: Duration = Duration::from_secs(3600);
+
+// Per-player inbox for events sent while they were offline.
+//
+// The gateway stores match found, party and result notifications for players
+// connected to no node, and flushes them when the player next connects.
+// Taking a player's messages deletes them in the same step, so each message
+// is delivered once even if the player connects to two nodes at once.
+// Messages expire after INBOX_TTL_HOURS; the leader instance deletes expired
+// ones periodically.
+pub struct InboxService {
+    config: InboxConfig,
+    repo: Arc<dyn InboxRepository>,
+}
+
+impl InboxService {
+    pub fn init(config: InboxConfig, repo: Arc<dyn InboxRepository>, leader: Arc<LeaderElection>, jobs: &Arc<JobService>) -> Arc<Self> {
+        let service = Arc::new(Self { config, repo });
+
+        let purger = service.clone();
+        jobs.spawn_periodic(leader, "inbox_purge", PURGE_INTERVAL, false, move |_| {
+            let purger = purger.clone();
+            async move {
+                let deleted = purger.repo.delete_expired(Utc::now()).await?;
+                if deleted > 0 {
+                    tracing::info!("Deleted {} expired inbox messages", deleted);
+                }
+                Ok(())
+            }
+        });
+
+        service
+    }
+
+    // Hold an event for each of these players
+    pub async fn store(&self, user_ids: &[Uuid], event: &str, key: &str, data: &Value) -> Result<()> {
+        if user_ids.is_empty() {
+            return Ok(());
+        }
+        let now = Utc::now();
+        let expires_at = now + chrono::Duration::from_std(self.config.ttl).unwrap_or(chrono::Duration::days(3));
+        let messages: Vec<InboxMessage> = user_ids
+            .iter()
+            .map(|&user_id| InboxMessage {
+                id: Uuid::new_v4(),
+                user_id,
+                event: event.to_string(),
+                key: key.to_string(),
+                data: data.clone(),
+                created_at: now,
+                expires_at,
+            })
+            .collect();
+        self.repo.insert_messages(&messages).await
+    }
+
+    // Remove and return the player's unexpired messages, oldest first
+    pub async fn take(&self, user_id: Uuid) -> Result<Vec<InboxMessage>> {
+        let now = Utc::now();
+        let mut messages = self.repo.take_messages(user_id).await?;
+        messages.retain(|message| message.expires_at > now);
+        messages.sort_by_key(|message| message.created_at);
+        Ok(messages)
+    }
+}
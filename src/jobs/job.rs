@@ -0,0 +1,88 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+// Failures kept on a job; later ones are only counted
+pub const MAX_ERRORS: usize = 50;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum JobStatus {
+    Running,
+    // Every item was attempted; some may have failed
+    Completed,
+    // Stopped before the end, see `errors`
+    Failed,
+    // Stopped by an admin
+    Cancelled,
+}
+
+// A long-running admin operation and how far it got
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct Job {
+    pub id: Uuid,
+    // What it does, e.g. "purge_matches"
+    pub kind: String,
+    pub status: JobStatus,
+    // Admin who started it, or "scheduler" for periodic jobs
+    pub created_by: String,
+    // NODE_ID of the node running it
+    pub node_id: String,
+    // Items to process; 0 until they are known
+    pub total: usize,
+    // Items processed so far, failed ones included
+    pub done: usize,
+    pub failed: usize,
+    // First MAX_ERRORS failures, e.g. "match <id>: ..."
+    pub errors: Vec<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub finished_at: Option<DateTime<Utc>>,
+}
+
+impl Job {
+    pub fn new(kind: &str, created_by: &str) -> Self {
+        let now = Utc::now();
+        Self {
+            id: Uuid::new_v4(),
+            kind: kind.to_string(),
+            status: JobStatus::Running,
+            created_by: created_by.to_string(),
+            node_id: crate::cluster::node_id().to_string(),
+            total: 0,
+            done: 0,
+            failed: 0,
+            errors: Vec::new(),
+            created_at: now,
+            updated_at: now,
+            finished_at: None,
+        }
+    }
+
+    fn record_error(&mut self, error: String) {
+        if self.errors.len() < MAX_ERRORS {
+            self.errors.push(error);
+        }
+    }
+
+    pub fn item_done(&mut self, error: Option<String>) {
+        self.done += 1;
+        if let Some(error) = error {
+            self.failed += 1;
+            self.record_error(error);
+        }
+    }
+
+    pub fn finish(&mut self, error: Option<String>, cancelled: bool) {
+        self.status = match error {
+            Some(error) => {
+                self.record_error(error);
+                JobStatus::Failed
+            }
+            None if cancelled => JobStatus::Cancelled,
+            None => JobStatus::Completed,
+        };
+        self.finished_at = Some(Utc::now());
+    }
+}
@@ -0,0 +1,280 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+use chrono::Utc;
+use tokio::sync::{RwLock, broadcast};
+use uuid::Uuid;
+
+use crate::cluster::node_id;
+use crate::cluster::scheduler::{LeaderElection, spawn_singleton};
+use crate::db::repository::JobRepository;
+use crate::error::{Error, Result};
+use crate::supervisor;
+use super::job::{Job, JobStatus};
+
+// Progress is written to the repository at most this often per job; the
+// start and the end are always written
+const SAVE_INTERVAL: Duration = Duration::from_secs(2);
+// Running jobs are marked alive this often...
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(60);
+// ...and failed by the leader once they missed a few heartbeats
+const STALE_AFTER: Duration = Duration::from_secs(600);
+// Jobs are deleted this long after they were started
+const RETENTION: Duration = Duration::from_secs(7 * 24 * 3600);
+const PRUNE_INTERVAL: Duration = Duration::from_secs(3600);
+// Jobs served by GET /admin/jobs
+const LIST_LIMIT: usize = 500;
+// `created_by` of periodic jobs
+const SCHEDULER: &str = "scheduler";
+
+// A job running on this node
+struct Running {
+    job: Job,
+    cancel: Arc<AtomicBool>,
+    saved_at: Instant,
+}
+
+// Long-running operations: bulk admin operations and the leader's periodic
+// work.
+//
+// Each job reports its progress through its JobHandle. Every change is
+// published to subscribers (the admin console) and written to the `jobs`
+// table, so any node can serve a job by id and the record outlives the
+// process. Jobs a node was running when it stopped are failed when it starts
+// again under the same NODE_ID, or by the leader once they stop sending
+// heartbeats.
+pub struct JobService {
+    repo: Arc<dyn JobRepository>,
+    running: RwLock<HashMap<Uuid, Running>>,
+    updates: broadcast::Sender<Job>,
+}
+
+// What a running job uses to report its progress
+pub struct JobHandle {
+    id: Uuid,
+    cancel: Arc<AtomicBool>,
+    service: Arc<JobService>,
+}
+
+impl JobHandle {
+    pub async fn set_total(&self, total: usize) {
+        self.service.update(self.id, |job| job.total = total).await;
+    }
+
+    // One more item processed, with the error if it failed
+    pub async fn item_done(&self, error: Option<String>) {
+        self.service.update(self.id, |job| job.item_done(error)).await;
+    }
+
+    // Set once an admin cancelled the job; the work should stop at the next item
+    pub fn cancelled(&self) -> bool {
+        self.cancel.load(Ordering::Relaxed)
+    }
+}
+
+impl JobService {
+    pub async fn init(repo: Arc<dyn JobRepository>, leader: Arc<LeaderElection>) -> Arc<Self> {
+        match repo.fail_running_jobs(Some(node_id()), Utc::now(), "interrupted by a restart", Utc::now()).await {
+            Ok(0) => {}
+            Ok(failed) => tracing::warn!("Failed {} jobs interrupted by the last shutdown", failed),
+            Err(e) => tracing::warn!("Failed to clean up interrupted jobs: {}", e),
+        }
+
+        let (updates, _) = broadcast::channel(256);
+        let service = Arc::new(Self {
+            repo,
+            running: RwLock::new(HashMap::new()),
+            updates,
+        });
+
+        let heartbeat = service.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(HEARTBEAT_INTERVAL);
+            loop {
+                interval.tick().await;
+                let ids: Vec<Uuid> = heartbeat.running.read().await.keys().copied().collect();
+                if ids.is_empty() {
+                    continue;
+                }
+                if let Err(e) = heartbeat.repo.touch_jobs(&ids, Utc::now()).await {
+                    tracing::warn!("Failed to record job heartbeats: {}", e);
+                }
+            }
+        });
+
+        let pruner = service.clone();
+        spawn_singleton("job_prune", leader, PRUNE_INTERVAL, true, move || {
+            let pruner = pruner.clone();
+            async move {
+                pruner.prune().await;
+            }
+        });
+
+        service
+    }
+
+    async fn prune(&self) {
+        let now = Utc::now();
+        let stale_before = now - chrono::Duration::from_std(STALE_AFTER).unwrap_or_default();
+        match self.repo.fail_running_jobs(None, stale_before, "the node running it stopped", now).await {
+            Ok(0) => {}
+            Ok(failed) => tracing::warn!("Failed {} jobs whose node stopped", failed),
+            Err(e) => tracing::warn!("Failed to clean up stale jobs: {}", e),
+        }
+        let cutoff = now - chrono::Duration::from_std(RETENTION).unwrap_or_default();
+        match self.repo.delete_jobs_before(cutoff).await {
+            Ok(0) => {}
+            Ok(deleted) => tracing::info!("Deleted {} old jobs", deleted),
+            Err(e) => tracing::warn!("Failed to delete old jobs: {}", e),
+        }
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<Job> {
+        self.updates.subscribe()
+    }
+
+    pub async fn get(&self, id: Uuid) -> Result<Job> {
+        if let Some(running) = self.running.read().await.get(&id) {
+            return Ok(running.job.clone());
+        }
+        self.repo.get_job(id)
+            .await?
+            .ok_or_else(|| Error::NotFound(format!("job {}", id)))
+    }
+
+    // Newest first, with this node's running jobs at their latest progress
+    pub async fn list(&self) -> Result<Vec<Job>> {
+        let mut jobs = self.repo.recent_jobs(LIST_LIMIT).await?;
+        let running = self.running.read().await;
+        jobs.retain(|job| !running.contains_key(&job.id));
+        jobs.extend(running.values().map(|running| running.job.clone()));
+        jobs.sort_by_key(|job| std::cmp::Reverse(job.created_at));
+        Ok(jobs)
+    }
+
+    // Ask a job running on this node to stop; it ends as cancelled once its
+    // work notices. Fails with `Error::JobFinished` if it isn't running here.
+    pub async fn cancel(&self, id: Uuid) -> Result<Job> {
+        let running = self.running.read().await;
+        let running = running.get(&id).ok_or(Error::JobFinished)?;
+        running.cancel.store(true, Ordering::Relaxed);
+        tracing::info!("Job {} ({}) cancelled", id, running.job.kind);
+        Ok(running.job.clone())
+    }
+
+    // Run `work` in the background; returns the job as it starts. An error
+    // from `work` fails the job, item failures only count against it.
+    pub async fn spawn<F, Fut>(self: &Arc<Self>, kind: &str, created_by: &str, work: F) -> Job
+    where
+        F: FnOnce(JobHandle) -> Fut + Send + 'static,
+        Fut: Future<Output = Result<()>> + Send + 'static,
+    {
+        let (job, handle) = self.start(kind, created_by).await;
+        let service = self.clone();
+        let (id, kind) = (job.id, job.kind.clone());
+        tokio::spawn(async move {
+            let result = supervisor::catch(&kind, work(handle)).await;
+            service.finish(id, result).await;
+        });
+        job
+    }
+
+    // Run `work` as a job and wait for it to end
+    pub async fn run<F, Fut>(self: &Arc<Self>, kind: &str, created_by: &str, work: F)
+    where
+        F: FnOnce(JobHandle) -> Fut,
+        Fut: Future<Output = Result<()>>,
+    {
+        let (job, handle) = self.start(kind, created_by).await;
+        let result = supervisor::catch(kind, work(handle)).await;
+        self.finish(job.id, result).await;
+    }
+
+    // Run `work` every `period` on the leader only, each run recorded as a job
+    // started by "scheduler". The first run is one period after start unless
+    // `run_now` is set.
+    pub fn spawn_periodic<F, Fut>(self: &Arc<Self>, leader: Arc<LeaderElection>, kind: &'static str, period: Duration, run_now: bool, work: F)
+    where
+        F: Fn(JobHandle) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<()>> + Send,
+    {
+        let jobs = self.clone();
+        let work = Arc::new(work);
+        spawn_singleton(kind, leader, period, run_now, move || {
+            let jobs = jobs.clone();
+            let work = work.clone();
+            async move {
+                jobs.run(kind, SCHEDULER, |job| work(job)).await;
+            }
+        });
+    }
+
+    async fn start(self: &Arc<Self>, kind: &str, created_by: &str) -> (Job, JobHandle) {
+        let job = Job::new(kind, created_by);
+        let cancel = Arc::new(AtomicBool::new(false));
+        if let Err(e) = self.repo.save_job(&job).await {
+            tracing::warn!("Failed to save job {}: {}", job.id, e);
+        }
+        self.running.write().await.insert(job.id, Running {
+            job: job.clone(),
+            cancel: cancel.clone(),
+            saved_at: Instant::now(),
+        });
+        let _ = self.updates.send(job.clone());
+        tracing::info!("Job {} ({}) started by {}", job.id, job.kind, job.created_by);
+
+        let handle = JobHandle { id: job.id, cancel, service: self.clone() };
+        (job, handle)
+    }
+
+    // `result` is None if the work panicked
+    async fn finish(&self, id: Uuid, result: Option<Result<()>>) {
+        let Some(running) = self.running.write().await.remove(&id) else {
+            return;
+        };
+        let mut job = running.job;
+        let error = match result {
+            Some(result) => result.err().map(|e| e.to_string()),
+            None => Some("panicked".to_string()),
+        };
+        job.finish(error.clone(), running.cancel.load(Ordering::Relaxed));
+        job.updated_at = Utc::now();
+        // If this fails the record stays running until the leader fails it as stale
+        if let Err(e) = self.repo.save_job(&job).await {
+            tracing::warn!("Failed to save job {}: {}", id, e);
+        }
+        let _ = self.updates.send(job.clone());
+        match job.status {
+            JobStatus::Failed => tracing::warn!(
+                "Job {} ({}) failed after {} of {} items: {}",
+                id, job.kind, job.done, job.total, error.unwrap_or_default()
+            ),
+            JobStatus::Cancelled => tracing::info!("Job {} ({}) cancelled after {} of {} items", id, job.kind, job.done, job.total),
+            _ => tracing::info!("Job {} ({}) completed, {} of {} items failed", id, job.kind, job.failed, job.done),
+        }
+    }
+
+    async fn update(&self, id: Uuid, change: impl FnOnce(&mut Job)) {
+        let (updated, save) = {
+            let mut running = self.running.write().await;
+            let Some(running) = running.get_mut(&id) else {
+                return;
+            };
+            change(&mut running.job);
+            running.job.updated_at = Utc::now();
+            let save = running.saved_at.elapsed() >= SAVE_INTERVAL;
+            if save {
+                running.saved_at = Instant::now();
+            }
+            (running.job.clone(), save)
+        };
+        if save && let Err(e) = self.repo.save_job(&updated).await {
+            tracing::warn!("Failed to save progress of job {}: {}", id, e);
+        }
+        // No console open is the usual case
+        let _ = self.updates.send(updated);
+    }
+}
@@ -0,0 +1,113 @@
+use std::cell::{Cell, RefCell};
+use std::future::Future;
+use std::time::{Duration, Instant};
+
+use crate::metrics::METRICS;
+
+// Command name for frames that weren't a valid command
+const INVALID: &str = "invalid";
+
+tokio::task_local! {
+    static BUDGET: Budget;
+}
+
+// Where a client command spends its time, exported per command as the
+// spv_command_latency_ms histogram.
+//
+// The gateway runs every inbound frame inside `track`. The clock starts when
+// the frame is read off the socket; the gateway marks when it is parsed and
+// how long the command's handler took, HasuraClient adds each round trip and
+// send_message the time spent encoding and queueing outbound frames. The
+// stages overlap: `db` and most of `send` fall within `service`, and `total`
+// runs until the handler is done and any error reply is queued. Writing the
+// frame to the socket happens on the connection's send task and isn't
+// counted. Work the command hands to another task isn't counted either.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Stage {
+    Parse,
+    Service,
+    Db,
+    Send,
+    Total,
+}
+
+impl Stage {
+    pub const ALL: [Stage; 5] = [Stage::Parse, Stage::Service, Stage::Db, Stage::Send, Stage::Total];
+
+    pub fn to_str(self) -> &'static str {
+        match self {
+            Stage::Parse => "parse",
+            Stage::Service => "service",
+            Stage::Db => "db",
+            Stage::Send => "send",
+            Stage::Total => "total",
+        }
+    }
+}
+
+struct Budget {
+    received: Instant,
+    command: RefCell<String>,
+    parse: Cell<Option<Duration>>,
+    service: Cell<Option<Duration>>,
+    db: Cell<Duration>,
+    send: Cell<Duration>,
+}
+
+impl Budget {
+    fn record(&self) {
+        let mut stages = vec![
+            (Stage::Db, self.db.get()),
+            (Stage::Send, self.send.get()),
+            (Stage::Total, self.received.elapsed()),
+        ];
+        if let Some(parse) = self.parse.get() {
+            stages.push((Stage::Parse, parse));
+        }
+        if let Some(service) = self.service.get() {
+            stages.push((Stage::Service, service));
+        }
+        METRICS.record_command_latency(&self.command.borrow(), &stages);
+    }
+}
+
+// Handle one inbound frame, received just now, and record its stages
+pub async fn track<F: Future>(f: F) -> F::Output {
+    let budget = Budget {
+        received: Instant::now(),
+        command: RefCell::new(INVALID.to_string()),
+        parse: Cell::new(None),
+        service: Cell::new(None),
+        db: Cell::new(Duration::ZERO),
+        send: Cell::new(Duration::ZERO),
+    };
+    BUDGET.scope(budget, async move {
+        let output = f.await;
+        BUDGET.with(Budget::record);
+        output
+    }).await
+}
+
+// The frame has been parsed into a command
+pub fn parsed() {
+    let _ = BUDGET.try_with(|budget| budget.parse.set(Some(budget.received.elapsed())));
+}
+
+// The command's handler returned after `elapsed`. `command` labels the
+// metrics, so it must be one the gateway knows, never any name a client sent.
+pub fn served(command: &str, elapsed: Duration) {
+    let _ = BUDGET.try_with(|budget| {
+        *budget.command.borrow_mut() = command.to_string();
+        budget.service.set(Some(elapsed));
+    });
+}
+
+// A Hasura round trip; ignored outside a command
+pub fn db(elapsed: Duration) {
+    let _ = BUDGET.try_with(|budget| budget.db.set(budget.db.get() + elapsed));
+}
+
+// An outbound frame encoded and queued; ignored outside a command
+pub fn sent(elapsed: Duration) {
+    let _ = BUDGET.try_with(|budget| budget.send.set(budget.send.get() + elapsed));
+}
@@ -0,0 +1,31 @@
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+// lfg.post event: a player queued for a match type is looking for more
+// players to fill their room
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct LfgPost {
+    pub user_id: Uuid,
+    pub match_type: String,
+    pub zone_id: Option<String>,
+    // Players in the room so far, and needed to start it
+    pub players: i32,
+    pub required_players: i32,
+    pub posted_at: DateTime<Utc>,
+}
+
+// lfg.subscribe request; no match types means every one
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct LfgSubscribeRequest {
+    #[serde(default)]
+    pub match_types: Vec<String>,
+}
+
+// lfg.subscribe and lfg.unsubscribe reply
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct LfgSubscription {
+    pub subscribed: bool,
+    pub match_types: Vec<String>,
+}
@@ -0,0 +1,72 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+use std::time::Instant;
+
+use chrono::Utc;
+use tokio::sync::Mutex;
+use uuid::Uuid;
+
+use crate::config::LfgConfig;
+use crate::error::{Error, Result};
+use crate::matchmaking::service::QueuedRoom;
+use super::post::LfgPost;
+
+// Looking-for-group posts.
+//
+// A queued player posts their room with lfg.post, and the gateway fans the
+// post out to idle connections that opted in with lfg.subscribe, on every
+// node. Posts are limited per player, and per match type so a busy mode
+// doesn't drown out the others; both limits are kept per node.
+pub struct LfgService {
+    config: LfgConfig,
+    // Recent post times per player
+    posts: Mutex<HashMap<Uuid, VecDeque<Instant>>>,
+    // Last post per match type
+    last_by_type: Mutex<HashMap<String, Instant>>,
+}
+
+impl LfgService {
+    pub fn new(config: LfgConfig) -> Arc<Self> {
+        Arc::new(Self {
+            config,
+            posts: Mutex::new(HashMap::new()),
+            last_by_type: Mutex::new(HashMap::new()),
+        })
+    }
+
+    // Take a post slot for the player's room, or fail with RateLimited
+    pub async fn post(&self, user_id: Uuid, room: QueuedRoom) -> Result<LfgPost> {
+        let now = Instant::now();
+        let mut last_by_type = self.last_by_type.lock().await;
+        if last_by_type
+            .get(&room.match_type)
+            .is_some_and(|at| now.duration_since(*at) < self.config.type_interval)
+        {
+            return Err(Error::RateLimited);
+        }
+
+        let mut posts = self.posts.lock().await;
+        // Forget players whose posts are all out of the window
+        posts.retain(|_, times| {
+            while times.front().is_some_and(|at| now.duration_since(*at) >= self.config.window) {
+                times.pop_front();
+            }
+            !times.is_empty()
+        });
+        let times = posts.entry(user_id).or_default();
+        if times.len() >= self.config.limit {
+            return Err(Error::RateLimited);
+        }
+        times.push_back(now);
+        last_by_type.insert(room.match_type.clone(), now);
+
+        Ok(LfgPost {
+            user_id,
+            match_type: room.match_type,
+            zone_id: room.zone_id,
+            players: room.players,
+            required_players: room.required_players,
+            posted_at: Utc::now(),
+        })
+    }
+}
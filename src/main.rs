@@ -1,10 +1,9 @@
 use std::sync::Arc;
 use axum::{
     Router,
-    routing::{get, get_service},
-    extract::{WebSocketUpgrade, Query, State, ws::Message},
+    routing::{get, get_service, post},
+    extract::{WebSocketUpgrade, Query, State},
     response::{Response, IntoResponse},
-    http::{Request, StatusCode},
 };
 use tower_http::{
     services::ServeDir,
@@ -14,19 +13,123 @@ use uuid::Uuid;
 use std::collections::HashMap;
 use std::net::SocketAddr;
 use tokio::net::TcpListener;
+use axum_server::tls_rustls::RustlsConfig;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 use dotenv::dotenv;
 
+mod announcements;
+mod anticheat;
+mod apikeys;
+mod audit;
+mod chaos;
 mod config;
+mod content;
+mod correlation;
 mod error;
 mod models;
 mod db;
 mod gateway;
 mod matchmaking;
+mod cluster;
+mod game;
+mod api;
+mod auth;
+mod tls;
+mod client_ip;
+mod metrics;
+mod moderation;
+mod devices;
+mod experiments;
+mod heatmap;
+mod inbox;
+mod jobs;
+mod latency;
+mod memory;
+mod lfg;
+mod preferences;
+mod rating;
+mod remote_config;
+mod schedule;
+mod secrets;
+mod seed;
+mod signing;
+mod slow;
+mod supervisor;
+mod telemetry;
+mod tutorial;
+#[cfg(feature = "grpc")]
+mod grpc;
 
+use db::hasura_announcement_repository::HasuraAnnouncementRepository;
+use db::hasura_appeal_repository::HasuraAppealRepository;
+use db::hasura_api_key_repository::HasuraApiKeyRepository;
+use db::hasura_audit_repository::HasuraAuditRepository;
+use db::hasura_client::HasuraClient;
+use db::hasura_ban_repository::HasuraBanRepository;
+use db::hasura_device_repository::HasuraDeviceRepository;
+use db::hasura_experiment_repository::HasuraExperimentRepository;
+use db::hasura_fairness_repository::HasuraFairnessRepository;
+use db::hasura_identity_repository::HasuraIdentityRepository;
+use db::hasura_inbox_repository::HasuraInboxRepository;
+use db::hasura_job_repository::HasuraJobRepository;
+use db::hasura_match_repository::HasuraMatchRepository;
+use db::hasura_position_repository::HasuraPositionRepository;
+use db::hasura_preference_repository::HasuraPreferenceRepository;
+use db::hasura_quarantine_repository::HasuraQuarantineRepository;
+use db::hasura_rating_repository::HasuraRatingRepository;
+use db::hasura_remote_config_repository::HasuraRemoteConfigRepository;
+use db::hasura_schedule_repository::HasuraScheduleRepository;
+use db::hasura_telemetry_repository::HasuraTelemetryRepository;
+use db::hasura_treasure_repository::HasuraTreasureRepository;
+use db::hasura_tutorial_repository::HasuraTutorialRepository;
+use db::hasura_zone_repository::HasuraZoneRepository;
+use db::memory_repository::MemoryRepository;
+use db::migrations;
+use db::schema_check;
+use db::repository::{
+    AnnouncementRepository, ApiKeyRepository, AppealRepository, AuditRepository, BanRepository, DeviceRepository, ExperimentRepository,
+    FairnessRepository, IdentityRepository, InboxRepository, JobRepository, MatchRepository, PositionRepository, PreferenceRepository,
+    QuarantineRepository, RatingRepository, RemoteConfigRepository, ScheduleRepository, TelemetryRepository, TreasureRepository, TutorialRepository,
+    ZoneRepository,
+};
+use announcements::service::AnnouncementService;
+use anticheat::appeal::AppealService;
+use anticheat::quarantine::QuarantineService;
+use anticheat::trust::TrustTracker;
+use apikeys::service::ApiKeyService;
+use api::cache::ResponseCache;
+use audit::service::AuditLog;
+use auth::service::AuthService;
+use client_ip::ClientIp;
+use cluster::presence::Presence;
+use cluster::rpc::ClusterRpc;
+use cluster::scheduler::LeaderElection;
+use cluster::snapshot::Replication;
+use config::{Config, LocalConfig};
+use content::bundles::ContentBundles;
+use devices::service::DeviceService;
+use experiments::service::ExperimentService;
+use game::runtime::GameRuntime;
+use heatmap::service::HeatmapService;
+use inbox::service::InboxService;
+use lfg::service::LfgService;
 use gateway::handler::WebSocketHandler;
 use gateway::state::ConnectionManager;
+use matchmaking::catalog::TreasureCatalog;
+use matchmaking::events::EventBus;
+use matchmaking::fairness::FairnessService;
+use matchmaking::reconcile::ScoreReconciler;
+use matchmaking::review::MatchReviewService;
 use matchmaking::service::MatchService;
+use matchmaking::zones::{ZoneRegistry, ZoneSource};
+use moderation::service::BanService;
+use jobs::service::JobService;
+use preferences::service::PreferenceService;
+use rating::service::RatingService;
+use remote_config::service::RemoteConfigService;
+use schedule::service::ScheduleService;
+use telemetry::service::TelemetryService;
+use tutorial::service::TutorialService;
 
 #[tokio::main]
 async fn main() {
@@ -41,35 +144,493 @@ async fn main() {
         .with(tracing_subscriber::fmt::layer())
         .init();
     
-    // Create matchmaking service
-    let mut match_service = MatchService::new();
+    // Secrets come from files or a secret manager as well as the environment
+    if let Err(e) = secrets::load().await {
+        tracing::error!("Failed to load secrets: {}", e);
+        std::process::exit(1);
+    }
+    let config = Arc::new(Config::load());
+    chaos::init(config.chaos.as_ref());
+    slow::init(&config.slow);
+    signing::init(&config.signing);
     
-    // Create WebSocket handler
-    let ws_handler = Arc::new(WebSocketHandler::new(match_service.clone()));
-
-    if let Some(service) = Arc::get_mut(&mut match_service) {
-        service.set_ws_handler(ws_handler.clone());
+    // `migrate` subcommand: apply pending migrations and exit
+    if std::env::args().nth(1).as_deref() == Some("migrate") {
+        migrate().await;
+        return;
+    }
+    if config.hasura.auto_migrate && config.local.is_none() {
+        migrate().await;
+    }
+    
+    // `seed` subcommand: fill a fresh database with local development data and exit
+    if std::env::args().nth(1).as_deref() == Some("seed") {
+        seed_database(&config).await;
+        return;
+    }
+    
+    // Fail fast on a Hasura schema the repositories can't work with; an
+    // unreachable database is left to degraded mode
+    if config.hasura.schema_check && config.local.is_none() {
+        check_schema().await;
+    }
+    
+    // --local: every repository in memory, seeded with the development data
+    let memory = match &config.local {
+        Some(local) => Some(local_repository(&config, local).await),
+        None => None,
+    };
+    
+    // Internal event bus between matchmaking and the transports
+    let event_bus = EventBus::new(1024);
+    
+    // Connect the match repository
+    let repo: Arc<dyn MatchRepository> = match &memory {
+        Some(memory) => memory.clone(),
+        None => match HasuraMatchRepository::new().await {
+            Ok(repo) => Arc::new(repo),
+            Err(e) => {
+                tracing::error!("Failed to initialize match repository: {}", e);
+                std::process::exit(1);
+            }
+        },
+    };
+    
+    // Treasure catalog, used to reject discoveries of inactive treasures
+    let treasure_repo: Arc<dyn TreasureRepository> = match &memory {
+        Some(memory) => memory.clone(),
+        None => match HasuraTreasureRepository::new().await {
+            Ok(repo) => Arc::new(repo),
+            Err(e) => {
+                tracing::error!("Failed to initialize treasure repository: {}", e);
+                std::process::exit(1);
+            }
+        },
+    };
+    let catalog = TreasureCatalog::init(treasure_repo).await;
+    
+    // Map zones partitioning the match pools, from ZONES_FILE or the database
+    let zone_source = match &config.matchmaking.zones_file {
+        Some(path) => ZoneSource::File(path.clone()),
+        None => {
+            let zone_repo: Arc<dyn ZoneRepository> = match &memory {
+                Some(memory) => memory.clone(),
+                None => match HasuraZoneRepository::new().await {
+                    Ok(repo) => Arc::new(repo),
+                    Err(e) => {
+                        tracing::error!("Failed to initialize zone repository: {}", e);
+                        std::process::exit(1);
+                    }
+                },
+            };
+            ZoneSource::Database(zone_repo)
+        }
+    };
+    let zones = ZoneRegistry::init(zone_source).await;
+    
+    // Elected node running the fleet's singleton background jobs
+    let leader = LeaderElection::start().await;
+    
+    // Bulk admin operations and periodic work, tracked as jobs
+    let job_repo: Arc<dyn JobRepository> = match &memory {
+        Some(memory) => memory.clone(),
+        None => match HasuraJobRepository::new().await {
+            Ok(repo) => Arc::new(repo),
+            Err(e) => {
+                tracing::error!("Failed to initialize job repository: {}", e);
+                std::process::exit(1);
+            }
+        },
+    };
+    let jobs = JobService::init(job_repo, leader.clone()).await;
+    
+    // Periodic check of stored scores against the discovery records
+    let scores = ScoreReconciler::init(repo.clone(), config.matchmaking.clone(), config.game.match_duration, leader.clone(), &jobs);
+    
+    // Matchmaking quality reports, computed by the leader from recorded queue times
+    let fairness_repo: Arc<dyn FairnessRepository> = match &memory {
+        Some(memory) => memory.clone(),
+        None => match HasuraFairnessRepository::new().await {
+            Ok(repo) => Arc::new(repo),
+            Err(e) => {
+                tracing::error!("Failed to initialize fairness repository: {}", e);
+                std::process::exit(1);
+            }
+        },
+    };
+    let fairness = FairnessService::init(
+        fairness_repo,
+        repo.clone(),
+        config.matchmaking.fairness.clone(),
+        config.cluster.region.clone(),
+        leader.clone(),
+        &jobs,
+    );
+    
+    // Admin review and adjustment of match results
+    let reviews = MatchReviewService::new(repo.clone(), event_bus.clone());
+    
+    // Location trust scores from the spoofing detector
+    let trust = Arc::new(TrustTracker::new(config.anticheat.clone()));
+    
+    // Player bans, enforced on connect and when joining a match
+    let ban_repo: Arc<dyn BanRepository> = match &memory {
+        Some(memory) => memory.clone(),
+        None => match HasuraBanRepository::new().await {
+            Ok(repo) => Arc::new(repo),
+            Err(e) => {
+                tracing::error!("Failed to initialize ban repository: {}", e);
+                std::process::exit(1);
+            }
+        },
+    };
+    let bans = BanService::init(ban_repo).await;
+    
+    // Cheaters matched only with each other instead of being banned
+    let quarantine_repo: Arc<dyn QuarantineRepository> = match &memory {
+        Some(memory) => memory.clone(),
+        None => match HasuraQuarantineRepository::new().await {
+            Ok(repo) => Arc::new(repo),
+            Err(e) => {
+                tracing::error!("Failed to initialize quarantine repository: {}", e);
+                std::process::exit(1);
+            }
+        },
+    };
+    let quarantine = QuarantineService::init(quarantine_repo, config.anticheat.quarantine_threshold).await;
+    
+    // Matchmaking ratings and smurf detection
+    let rating_repo: Arc<dyn RatingRepository> = match &memory {
+        Some(memory) => memory.clone(),
+        None => match HasuraRatingRepository::new().await {
+            Ok(repo) => Arc::new(repo),
+            Err(e) => {
+                tracing::error!("Failed to initialize rating repository: {}", e);
+                std::process::exit(1);
+            }
+        },
+    };
+    let ratings = RatingService::new(config.rating.clone(), rating_repo, repo.clone());
+    
+    // Create matchmaking service; refuse to start without a working repository
+    let match_service = match MatchService::init(repo, catalog.clone(), zones.clone(), trust, quarantine, bans.clone(), ratings.clone(), fairness.clone(), event_bus.clone(), &config).await {
+        Ok(service) => service,
+        Err(e) => {
+            tracing::error!("Failed to initialize matchmaking service: {}", e);
+            std::process::exit(1);
+        }
+    };
+    
+    // Client telemetry, written to the database in the background
+    let telemetry_repo: Arc<dyn TelemetryRepository> = match &memory {
+        Some(memory) => memory.clone(),
+        None => match HasuraTelemetryRepository::new().await {
+            Ok(repo) => Arc::new(repo),
+            Err(e) => {
+                tracing::error!("Failed to initialize telemetry repository: {}", e);
+                std::process::exit(1);
+            }
+        },
+    };
+    let telemetry = TelemetryService::new(config.telemetry.clone(), telemetry_repo);
+    
+    // A/B experiment definitions, assigned to users on connect
+    let experiment_repo: Arc<dyn ExperimentRepository> = match &memory {
+        Some(memory) => memory.clone(),
+        None => match HasuraExperimentRepository::new().await {
+            Ok(repo) => Arc::new(repo),
+            Err(e) => {
+                tracing::error!("Failed to initialize experiment repository: {}", e);
+                std::process::exit(1);
+            }
+        },
+    };
+    let experiments = ExperimentService::init(experiment_repo).await;
+    
+    // Versioned remote config for clients, with its audit trail
+    let remote_config_repo: Arc<dyn RemoteConfigRepository> = match &memory {
+        Some(memory) => memory.clone(),
+        None => match HasuraRemoteConfigRepository::new().await {
+            Ok(repo) => Arc::new(repo),
+            Err(e) => {
+                tracing::error!("Failed to initialize remote config repository: {}", e);
+                std::process::exit(1);
+            }
+        },
+    };
+    let remote_config = RemoteConfigService::init(remote_config_repo).await;
+    
+    // Position history and the per-zone heatmap built from it
+    let position_repo: Arc<dyn PositionRepository> = match &memory {
+        Some(memory) => memory.clone(),
+        None => match HasuraPositionRepository::new().await {
+            Ok(repo) => Arc::new(repo),
+            Err(e) => {
+                tracing::error!("Failed to initialize position repository: {}", e);
+                std::process::exit(1);
+            }
+        },
+    };
+    let heatmap = HeatmapService::init(config.heatmap.clone(), position_repo, zones, leader.clone(), &jobs);
+    
+    // Events held for offline players until they next connect
+    let inbox_repo: Arc<dyn InboxRepository> = match &memory {
+        Some(memory) => memory.clone(),
+        None => match HasuraInboxRepository::new().await {
+            Ok(repo) => Arc::new(repo),
+            Err(e) => {
+                tracing::error!("Failed to initialize inbox repository: {}", e);
+                std::process::exit(1);
+            }
+        },
+    };
+    let inbox = InboxService::init(config.inbox.clone(), inbox_repo, leader.clone(), &jobs);
+    
+    // Devices players registered, for session takeover and push notifications
+    let device_repo: Arc<dyn DeviceRepository> = match &memory {
+        Some(memory) => memory.clone(),
+        None => match HasuraDeviceRepository::new().await {
+            Ok(repo) => Arc::new(repo),
+            Err(e) => {
+                tracing::error!("Failed to initialize device repository: {}", e);
+                std::process::exit(1);
+            }
+        },
+    };
+    let devices = DeviceService::init(config.devices.clone(), device_repo);
+    
+    // Which notifications each player wants
+    let preference_repo: Arc<dyn PreferenceRepository> = match &memory {
+        Some(memory) => memory.clone(),
+        None => match HasuraPreferenceRepository::new().await {
+            Ok(repo) => Arc::new(repo),
+            Err(e) => {
+                tracing::error!("Failed to initialize preference repository: {}", e);
+                std::process::exit(1);
+            }
+        },
+    };
+    let preferences = PreferenceService::init(preference_repo);
+    
+    // Localized text, delivered in each player's language
+    let content = ContentBundles::load(&config.content);
+    
+    // Looking-for-group posts from queued players
+    let lfg = LfgService::new(config.lfg.clone());
+    
+    // Sign-in with Google and Apple accounts, and guests linking them
+    let identity_repo: Arc<dyn IdentityRepository> = match &memory {
+        Some(memory) => memory.clone(),
+        None => match HasuraIdentityRepository::new().await {
+            Ok(repo) => Arc::new(repo),
+            Err(e) => {
+                tracing::error!("Failed to initialize identity repository: {}", e);
+                std::process::exit(1);
+            }
+        },
+    };
+    let auth = AuthService::new(config.auth.clone(), identity_repo, match_service.clone());
+    // Local mode has no sign-in to get a session from, so the test users' are logged
+    if memory.is_some() {
+        for user in seed::TEST_USERS {
+            tracing::info!("  test user {:<6} {} token {}", user.name, user.id, auth.session_for(user.id));
+        }
     }
     
-    // Create connection manager
+    // API keys of service callers of the admin API and gRPC
+    let api_key_repo: Arc<dyn ApiKeyRepository> = match &memory {
+        Some(memory) => memory.clone(),
+        None => match HasuraApiKeyRepository::new().await {
+            Ok(repo) => Arc::new(repo),
+            Err(e) => {
+                tracing::error!("Failed to initialize API key repository: {}", e);
+                std::process::exit(1);
+            }
+        },
+    };
+    let api_keys = ApiKeyService::init(api_key_repo, config.admin.key_rotation_grace).await;
+    
+    // Who changed what through the admin API and gRPC
+    let audit_repo: Arc<dyn AuditRepository> = match &memory {
+        Some(memory) => memory.clone(),
+        None => match HasuraAuditRepository::new().await {
+            Ok(repo) => Arc::new(repo),
+            Err(e) => {
+                tracing::error!("Failed to initialize audit repository: {}", e);
+                std::process::exit(1);
+            }
+        },
+    };
+    let audit = AuditLog::new(audit_repo);
+    
+    // Message of the day and announcements to the connected players
+    let announcement_repo: Arc<dyn AnnouncementRepository> = match &memory {
+        Some(memory) => memory.clone(),
+        None => match HasuraAnnouncementRepository::new().await {
+            Ok(repo) => Arc::new(repo),
+            Err(e) => {
+                tracing::error!("Failed to initialize announcement repository: {}", e);
+                std::process::exit(1);
+            }
+        },
+    };
+    let announcements = AnnouncementService::init(announcement_repo, config.cluster.region.clone()).await;
+    
+    // Private matches scheduled for later, moved along by the leader
+    let schedule_repo: Arc<dyn ScheduleRepository> = match &memory {
+        Some(memory) => memory.clone(),
+        None => match HasuraScheduleRepository::new().await {
+            Ok(repo) => Arc::new(repo),
+            Err(e) => {
+                tracing::error!("Failed to initialize schedule repository: {}", e);
+                std::process::exit(1);
+            }
+        },
+    };
+    let schedules = ScheduleService::init(
+        config.schedule.clone(),
+        schedule_repo,
+        match_service.clone(),
+        config.game.match_duration,
+        leader.clone(),
+    );
+    
+    // Scripted tutorial matches against bots, with progress per player
+    let tutorial_repo: Arc<dyn TutorialRepository> = match &memory {
+        Some(memory) => memory.clone(),
+        None => match HasuraTutorialRepository::new().await {
+            Ok(repo) => Arc::new(repo),
+            Err(e) => {
+                tracing::error!("Failed to initialize tutorial repository: {}", e);
+                std::process::exit(1);
+            }
+        },
+    };
+    let tutorials = TutorialService::init(tutorial_repo, match_service.clone(), config.new_players.bots.clone());
+    tutorials.clone().spawn_event_listener(event_bus.subscribe());
+    
+    // Players' appeals against their trust score or quarantine
+    let appeal_repo: Arc<dyn AppealRepository> = match &memory {
+        Some(memory) => memory.clone(),
+        None => match HasuraAppealRepository::new().await {
+            Ok(repo) => Arc::new(repo),
+            Err(e) => {
+                tracing::error!("Failed to initialize appeal repository: {}", e);
+                std::process::exit(1);
+            }
+        },
+    };
+    let appeals = AppealService::new(appeal_repo, match_service.clone());
+    
+    // Create connection manager, shared by the WebSocket handler and HTTP routes
     let conn_manager = ConnectionManager::new();
     
+    // Internal node-to-node API client and the directory of node addresses
+    let cluster = ClusterRpc::new(&config.cluster);
+    
+    // Where users are connected across the fleet, for cross-node delivery
+    let presence = Presence::from_env(cluster.clone());
+    
+    // Per-match game loop, following the match lifecycle
+    let game_runtime = GameRuntime::new(config.game.clone(), match_service.clone());
+    game_runtime.clone().spawn_event_listener(event_bus.subscribe());
+    
+    // Create WebSocket handler
+    let ws_handler = Arc::new(WebSocketHandler::new(
+        match_service.clone(),
+        game_runtime.clone(),
+        telemetry.clone(),
+        experiments.clone(),
+        remote_config.clone(),
+        heatmap.clone(),
+        presence.clone(),
+        inbox.clone(),
+        devices.clone(),
+        announcements.clone(),
+        schedules.clone(),
+        preferences.clone(),
+        content.clone(),
+        lfg.clone(),
+        tutorials.clone(),
+        appeals.clone(),
+        conn_manager.clone(),
+        config.clone(),
+    ));
+    
+    // Forward match events and game ticks to connected players
+    ws_handler.clone().spawn_event_listener(event_bus.subscribe());
+    ws_handler.clone().spawn_tick_listener(game_runtime.subscribe());
+    ws_handler.clone().spawn_ban_listener(bans.subscribe());
+    ws_handler.clone().spawn_announcement_listener(announcements.subscribe());
+    ws_handler.clone().spawn_schedule_listener(schedules.subscribe());
+    ws_handler.clone().spawn_tutorial_listener(tutorials.subscribe());
+    ws_handler.clone().spawn_cluster_listener(presence.subscribe());
+    ws_handler.clone().spawn_fanout();
+    ws_handler.clone().spawn_spectator_feed();
+    
+    // Snapshots of in-memory state, restored after a restart or by a standby
+    Replication::start(&config.snapshot, match_service.clone(), game_runtime.clone()).await;
+    
+    // Server-to-server gRPC API
+    #[cfg(feature = "grpc")]
+    if let Some(grpc_port) = config.server.grpc_port {
+        grpc::spawn(match_service.clone(), api_keys.clone(), audit.clone(), config.admin.clone(), grpc_port);
+    }
+    
     // Create a CORS layer
     let cors = CorsLayer::new()
         .allow_origin(Any)
         .allow_methods(Any)
         .allow_headers(Any);
     
+    // Cached REST responses, dropped as matches end
+    let cache = ResponseCache::new(config.cache.clone());
+    cache.clone().spawn_invalidation(event_bus.subscribe());
+
+    // Sizes of the in-memory state, exported at /metrics
+    memory::spawn_sampler(ws_handler.clone(), match_service.clone(), cache.clone());
+
     // Create app state
     let app_state = AppState {
+        config: config.clone(),
         ws_handler: ws_handler.clone(),
         conn_manager: conn_manager.clone(),
+        match_service: match_service.clone(),
+        telemetry: telemetry.clone(),
+        experiments: experiments.clone(),
+        remote_config: remote_config.clone(),
+        catalog: catalog.clone(),
+        heatmap: heatmap.clone(),
+        scores: scores.clone(),
+        fairness: fairness.clone(),
+        reviews: reviews.clone(),
+        bans: bans.clone(),
+        appeals: appeals.clone(),
+        announcements: announcements.clone(),
+        schedules: schedules.clone(),
+        content: content.clone(),
+        devices: devices.clone(),
+        auth: auth.clone(),
+        api_keys: api_keys.clone(),
+        audit: audit.clone(),
+        ratings: ratings.clone(),
+        game: game_runtime.clone(),
+        leader: leader.clone(),
+        cluster: cluster.clone(),
+        events: event_bus.clone(),
+        cache: cache.clone(),
+        jobs: jobs.clone(),
     };
     
     // Build the router
     let app = Router::new()
         .route("/ws", get(ws_handler_fn))
+        .route("/ws/admin", get(gateway::console::console_connect))
+        .route("/sse", get(gateway::sse::sse_connect))
+        .route("/sse/command", post(gateway::sse::sse_command))
+        .merge(api::router())
         .nest_service("/test", get_service(ServeDir::new("static")))
+        .layer(axum::middleware::from_fn_with_state(app_state.clone(), api::admin::authenticate))
         .layer(cors)
         .with_state(app_state);
     
@@ -81,37 +642,211 @@ async fn main() {
     
     let addr = SocketAddr::from(([0, 0, 0, 0], port));
     
-    tracing::info!("Starting server on {}", addr);
-    
-    // Start the server
-    let listener = TcpListener::bind(addr).await.unwrap();
-    axum::serve(listener, app).await.unwrap();
+    // Start the server, terminating TLS ourselves when certificates are configured
+    match &config.server.tls {
+        Some(tls_config) => {
+            let rustls_config = match tls::server_config(tls_config) {
+                Ok(rustls_config) => rustls_config,
+                Err(e) => {
+                    tracing::error!("Failed to load TLS certificates: {}", e);
+                    std::process::exit(1);
+                }
+            };
+            
+            if let Some(redirect_port) = tls_config.redirect_port {
+                tls::spawn_http_redirect(redirect_port, port);
+            }
+            
+            tracing::info!("Starting server on {} (TLS)", addr);
+            axum_server::bind_rustls(addr, RustlsConfig::from_config(rustls_config))
+                .serve(app.into_make_service_with_connect_info::<SocketAddr>())
+                .await
+                .unwrap();
+        }
+        None => {
+            tracing::info!("Starting server on {}", addr);
+            let listener = TcpListener::bind(addr).await.unwrap();
+            axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>()).await.unwrap();
+        }
+    }
 }
 
 // App state for sharing handlers
 #[derive(Clone)]
 struct AppState {
+    config: Arc<Config>,
     ws_handler: Arc<WebSocketHandler>,
     conn_manager: ConnectionManager,
+    match_service: Arc<MatchService>,
+    telemetry: Arc<TelemetryService>,
+    experiments: Arc<ExperimentService>,
+    remote_config: Arc<RemoteConfigService>,
+    catalog: Arc<TreasureCatalog>,
+    heatmap: Arc<HeatmapService>,
+    scores: Arc<ScoreReconciler>,
+    fairness: Arc<FairnessService>,
+    reviews: Arc<MatchReviewService>,
+    bans: Arc<BanService>,
+    appeals: Arc<AppealService>,
+    announcements: Arc<AnnouncementService>,
+    schedules: Arc<ScheduleService>,
+    content: Arc<ContentBundles>,
+    devices: Arc<DeviceService>,
+    auth: Arc<AuthService>,
+    api_keys: Arc<ApiKeyService>,
+    audit: Arc<AuditLog>,
+    ratings: Arc<RatingService>,
+    game: Arc<GameRuntime>,
+    leader: Arc<LeaderElection>,
+    cluster: Arc<ClusterRpc>,
+    events: EventBus,
+    cache: Arc<ResponseCache>,
+    jobs: Arc<JobService>,
+}
+
+// Apply pending schema migrations; exits if they can't be applied
+async fn migrate() {
+    let result = match HasuraClient::get_instance().await {
+        Ok(client) => migrations::run(&client).await,
+        Err(e) => Err(e),
+    };
+    match result {
+        Ok(report) if report.applied.is_empty() => {
+            tracing::info!("Database schema is up to date (version {})", report.version);
+        }
+        Ok(report) => {
+            let names: Vec<&str> = report.applied.iter().map(|m| m.name).collect();
+            tracing::info!("Applied {} migrations ({}), schema now at version {}", names.len(), names.join(", "), report.version);
+        }
+        Err(e) => {
+            tracing::error!("Database migration failed: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+// Test users, treasures and finished matches for local development
+async fn seed_database(config: &Config) {
+    let result = async {
+        let matches: Arc<dyn MatchRepository> = Arc::new(HasuraMatchRepository::new().await?);
+        let treasures: Arc<dyn TreasureRepository> = Arc::new(HasuraTreasureRepository::new().await?);
+        let ratings: Arc<dyn RatingRepository> = Arc::new(HasuraRatingRepository::new().await?);
+        seed::run(config, matches, treasures, ratings).await
+    }.await;
+    match result {
+        Ok(report) => {
+            tracing::info!(
+                "Seeded {} ratings, {} treasures and {} matches; test users:",
+                report.ratings, report.treasures, report.matches
+            );
+            for user in seed::TEST_USERS {
+                tracing::info!("  {:<6} {}", user.name, user.id);
+            }
+        }
+        Err(e) => {
+            tracing::error!("Seeding failed: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+// In-memory repositories for running without any external service
+async fn local_repository(config: &Config, local: &LocalConfig) -> Arc<MemoryRepository> {
+    tracing::info!(
+        "Local mode: in-memory repositories, {}-{} ms simulated latency, nothing is persisted",
+        local.latency_min.as_millis(), local.latency_max.as_millis()
+    );
+    let memory = Arc::new(MemoryRepository::new(local.latency_min, local.latency_max));
+    if let Err(e) = seed::run(config, memory.clone(), memory.clone(), memory.clone()).await {
+        tracing::warn!("Failed to seed the local repositories: {}", e);
+    }
+    memory
+}
+
+// Compare the Hasura schema with what the repositories expect and exit with
+// a report of every mismatch. Hasura being unreachable is only a warning.
+async fn check_schema() {
+    let client = match HasuraClient::get_instance().await {
+        Ok(client) => client,
+        Err(e) => {
+            tracing::warn!("Skipping Hasura schema check: {}", e);
+            return;
+        }
+    };
+    match schema_check::preflight(&client).await {
+        Ok(problems) if problems.is_empty() => tracing::info!("Hasura schema check passed"),
+        Ok(problems) => {
+            tracing::error!("Hasura schema does not match what the server expects ({} problems):", problems.len());
+            for problem in &problems {
+                tracing::error!("  - {}", problem);
+            }
+            tracing::error!("Fix the schema or metadata, or set HASURA_SCHEMA_CHECK=false to start anyway");
+            std::process::exit(1);
+        }
+        Err(e) => tracing::warn!("Could not check the Hasura schema, starting anyway: {}", e),
+    }
 }
 
 // WebSocket handler function
 async fn ws_handler_fn(
     State(state): State<AppState>,
+    ClientIp(ip): ClientIp,
     ws: WebSocketUpgrade,
     Query(params): Query<HashMap<String, String>>,
-) -> impl IntoResponse {
-    // In a real app, you'd validate a token here
-    // For testing, we'll use a simple user_id parameter
-    let user_id = params
-        .get("user_id")
-        .map(|id| Uuid::parse_str(id).unwrap_or_else(|_| Uuid::new_v4()))
-        .unwrap_or_else(Uuid::new_v4);
+) -> Response {
+    let user_id = match user_id_from_params(&state, &params) {
+        Ok(user_id) => user_id,
+        Err(e) => return e.into_response(),
+    };
+    // ?compress=gzip opts in to compressed binary frames for large messages
+    let compress = params.get("compress").is_some_and(|v| v == "gzip");
+    let platform = platform_from_params(&params);
+    let locale = locale_from_params(&params);
+    let device_id = device_id_from_params(&params);
+    // ?resume= carries the resume token of the player's last welcome
+    let resume = params.get("resume").filter(|token| !token.is_empty()).cloned();
+    
+    tracing::info!("WebSocket connection from user: {} ({})", user_id, ip);
+    
+    // Refused with a 503 before upgrading once the node holds MAX_CONNECTIONS sessions
+    if let Err(e) = state.ws_handler.check_capacity().await {
+        return e.into_response();
+    }
     
-    tracing::info!("WebSocket connection from user: {}", user_id);
+    // Banned players are still upgraded, so they can be told why and until when
+    let ban = state.bans.active_ban(user_id).await;
     
     // Upgrade the connection
     ws.on_upgrade(move |socket| async move {
-        state.ws_handler.handle_connection(socket, user_id).await;
-    })
-}
\ No newline at end of file
+        match ban {
+            Some(ban) => state.ws_handler.reject_banned(socket, &ban).await,
+            None => state.ws_handler.handle_connection(socket, user_id, ip, compress, platform, locale, device_id, resume).await,
+        }
+    }).into_response()
+}
+
+// ?token= carries the session token from sign-in, /api/auth/guest or a link;
+// the player is taken from it, so a missing or invalid token is refused
+fn user_id_from_params(state: &AppState, params: &HashMap<String, String>) -> error::Result<Uuid> {
+    let token = params.get("token").ok_or(error::Error::AuthError)?;
+    state.auth.session_user(token)
+}
+
+// ?platform=ios|android|web; anything else counts as an unknown platform
+fn platform_from_params(params: &HashMap<String, String>) -> Option<models::game::Platform> {
+    params.get("platform").and_then(|p| models::game::Platform::from_str(p))
+}
+
+// ?locale=pt-BR picks the language of localized content; malformed tags are ignored
+fn locale_from_params(params: &HashMap<String, String>) -> Option<String> {
+    params.get("locale").and_then(|locale| content::bundles::normalize(locale))
+}
+
+// ?device_id= names the client's device, so a new session replaces an older
+// one from the same device; blank or over-long ids are ignored
+fn device_id_from_params(params: &HashMap<String, String>) -> Option<String> {
+    params.get("device_id")
+        .map(|id| id.trim())
+        .filter(|id| !id.is_empty() && id.len() <= devices::service::MAX_DEVICE_ID_LEN)
+        .map(str::to_string)
+}
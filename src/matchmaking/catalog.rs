@@ -0,0 +1,152 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::Utc;
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+use crate::db::repository::TreasureRepository;
+use crate::error::{Error, Result};
+use crate::models::treasure::{Treasure, TreasureSpec};
+use crate::supervisor;
+
+// How often the catalog is reloaded, so edits made through another instance reach this one
+const REFRESH_INTERVAL: Duration = Duration::from_secs(60);
+
+// The treasure catalog, cached in memory so discoveries can be checked
+// without a database round trip (and while the database is offline).
+//
+// Also holds the treasures spawned into running matches by their game loop.
+// Those only live on the node running the match, and are gone once
+// discovered or when the match ends.
+pub struct TreasureCatalog {
+    repo: Arc<dyn TreasureRepository>,
+    treasures: RwLock<HashMap<Uuid, Treasure>>,
+    // Undiscovered spawned treasures per match
+    spawned: RwLock<HashMap<Uuid, HashMap<Uuid, Treasure>>>,
+}
+
+impl TreasureCatalog {
+    // Load the catalog and keep it fresh. Starts empty if the database can't
+    // be read yet; unknown treasures are then looked up one by one.
+    pub async fn init(repo: Arc<dyn TreasureRepository>) -> Arc<Self> {
+        let catalog = Arc::new(Self {
+            repo,
+            treasures: RwLock::new(HashMap::new()),
+            spawned: RwLock::new(HashMap::new()),
+        });
+        if let Err(e) = catalog.reload().await {
+            tracing::warn!("Failed to load treasure catalog: {}", e);
+        }
+
+        let refresher = catalog.clone();
+        supervisor::spawn_loop("catalog_refresh", move || {
+            let refresher = refresher.clone();
+            async move {
+                let mut interval = tokio::time::interval(REFRESH_INTERVAL);
+                interval.tick().await;
+                loop {
+                    interval.tick().await;
+                    if let Err(e) = refresher.reload().await {
+                        tracing::warn!("Failed to refresh treasure catalog: {}", e);
+                    }
+                }
+            }
+        });
+        catalog
+    }
+
+    // Replace the cached catalog with the stored treasures
+    pub async fn reload(&self) -> Result<()> {
+        let treasures = self.repo.list_treasures().await?;
+        *self.treasures.write().await = treasures.into_iter().map(|t| (t.id, t)).collect();
+        Ok(())
+    }
+
+    pub async fn list(&self, active_only: bool) -> Vec<Treasure> {
+        let now = Utc::now();
+        let mut treasures: Vec<Treasure> = self.treasures
+            .read()
+            .await
+            .values()
+            .filter(|t| !active_only || t.is_active(now))
+            .cloned()
+            .collect();
+        treasures.sort_by(|a, b| a.name.cmp(&b.name).then(a.id.cmp(&b.id)));
+        treasures
+    }
+
+    pub async fn get(&self, id: Uuid) -> Result<Treasure> {
+        if let Some(treasure) = self.treasures.read().await.get(&id) {
+            return Ok(treasure.clone());
+        }
+        let treasure = self.repo.get_treasure(id).await?;
+        self.treasures.write().await.insert(id, treasure.clone());
+        Ok(treasure)
+    }
+
+    pub async fn create(&self, spec: TreasureSpec) -> Result<Treasure> {
+        let treasure = spec.into_treasure(Uuid::new_v4())?;
+        self.repo.create_treasure(&treasure).await?;
+        self.treasures.write().await.insert(treasure.id, treasure.clone());
+        tracing::info!("Treasure {} ({}) created", treasure.id, treasure.name);
+        Ok(treasure)
+    }
+
+    pub async fn update(&self, id: Uuid, spec: TreasureSpec) -> Result<Treasure> {
+        let treasure = spec.into_treasure(id)?;
+        self.repo.update_treasure(&treasure).await?;
+        self.treasures.write().await.insert(id, treasure.clone());
+        tracing::info!("Treasure {} ({}) updated", treasure.id, treasure.name);
+        Ok(treasure)
+    }
+
+    pub async fn delete(&self, id: Uuid) -> Result<()> {
+        self.repo.delete_treasure(id).await?;
+        self.treasures.write().await.remove(&id);
+        tracing::info!("Treasure {} deleted", id);
+        Ok(())
+    }
+
+    // The treasure, if it exists and is inside its active window
+    pub async fn ensure_active(&self, id: Uuid) -> Result<Treasure> {
+        let treasure = self.get(id).await?;
+        if !treasure.is_active(Utc::now()) {
+            return Err(Error::TreasureNotActive);
+        }
+        Ok(treasure)
+    }
+
+    pub async fn spawn(&self, match_id: Uuid, treasures: &[Treasure]) {
+        self.spawned
+            .write()
+            .await
+            .entry(match_id)
+            .or_default()
+            .extend(treasures.iter().map(|t| (t.id, t.clone())));
+    }
+
+    // Spawned treasures of a match not discovered yet
+    pub async fn spawned(&self, match_id: Uuid) -> Vec<Treasure> {
+        self.spawned.read().await.get(&match_id).map(|t| t.values().cloned().collect()).unwrap_or_default()
+    }
+
+    pub async fn clear_spawned(&self, match_id: Uuid) {
+        self.spawned.write().await.remove(&match_id);
+    }
+
+    // Check a discovery in a match: a treasure spawned into it, which is
+    // taken so it can't be discovered twice, or an active catalog treasure
+    pub async fn claim(&self, match_id: Uuid, id: Uuid) -> Result<Treasure> {
+        let mut spawned = self.spawned.write().await;
+        if let Some(treasure) = spawned.get_mut(&match_id).and_then(|t| t.remove(&id)) {
+            return Ok(treasure);
+        }
+        if spawned.values().any(|t| t.contains_key(&id)) {
+            return Err(Error::TreasureNotActive);
+        }
+        drop(spawned);
+        self.ensure_active(id).await
+    }
+}
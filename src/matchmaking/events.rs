@@ -0,0 +1,193 @@
+use tokio::sync::broadcast;
+use uuid::Uuid;
+
+use crate::correlation;
+use chrono::{DateTime, Utc};
+
+use crate::models::game::{KickVote, LobbySelection, LobbyTeams, MatchResult, RoundResult, TeamAssignment, TreasureDiscovery};
+use crate::game::capture::CaptureUpdate;
+use crate::models::treasure::TreasureSpawn;
+use crate::rating::mmr::RankPlacement;
+use super::review::AuditEntry;
+
+// Domain events published by the matchmaking core. Transports (WebSocket
+// gateway, SSE, polling endpoints) subscribe and decide how to present them.
+#[derive(Debug, Clone)]
+pub enum MatchEvent {
+    PlayerJoined {
+        user_id: Uuid,
+        room: MatchResult,
+    },
+    PlayerLeft {
+        user_id: Uuid,
+        room: MatchResult,
+    },
+    // The room filled up; published instead of PlayerJoined for the players
+    // whose join filled it
+    RoomReady {
+        room: MatchResult,
+    },
+    // Another player took over as host of a private room
+    HostChanged {
+        match_id: Uuid,
+        host: Uuid,
+        previous: Uuid,
+    },
+    // Players of a private room joined, left or switched teams
+    TeamsChanged {
+        teams: LobbyTeams,
+    },
+    // A player voted to kick someone out of a private room, short of a majority
+    KickVoted {
+        vote: KickVote,
+    },
+    // A player was kicked out of a private room, after PlayerLeft
+    PlayerKicked {
+        match_id: Uuid,
+        user_id: Uuid,
+        // May not join again
+        blocked: bool,
+    },
+    // Teams are picked; the match starts at closes_at
+    LobbyOpened {
+        room: MatchResult,
+        teams: Vec<TeamAssignment>,
+        closes_at: DateTime<Utc>,
+    },
+    // A player picked a loadout or role in the lobby
+    LobbySelected {
+        match_id: Uuid,
+        user_id: Uuid,
+        selection: LobbySelection,
+        // The player's team, the player included
+        team: Vec<Uuid>,
+    },
+    MatchStarted {
+        room: MatchResult,
+        teams: Vec<TeamAssignment>,
+    },
+    DiscoveryRecorded {
+        discovery: TreasureDiscovery,
+    },
+    // The game loop added treasures to a running match
+    TreasuresSpawned {
+        spawn: TreasureSpawn,
+    },
+    // A capture zone became held, contested or neutral
+    CaptureChanged {
+        update: CaptureUpdate,
+    },
+    // A round of a best_of match is over
+    RoundEnded {
+        round: RoundResult,
+    },
+    MatchEnded {
+        match_id: Uuid,
+    },
+    // An admin changed the result of a match that is already over
+    ResultAdjusted {
+        entry: AuditEntry,
+        // Players of the match, wherever they are connected
+        users: Vec<Uuid>,
+    },
+    // A player finished their last placement match and was given a tier
+    RankPlaced {
+        match_id: Uuid,
+        placement: RankPlacement,
+    },
+}
+
+impl MatchEvent {
+    pub fn match_id(&self) -> Uuid {
+        match self {
+            MatchEvent::PlayerJoined { room, .. }
+            | MatchEvent::PlayerLeft { room, .. }
+            | MatchEvent::RoomReady { room }
+            | MatchEvent::LobbyOpened { room, .. }
+            | MatchEvent::MatchStarted { room, .. } => room.match_id,
+            MatchEvent::HostChanged { match_id, .. }
+            | MatchEvent::PlayerKicked { match_id, .. }
+            | MatchEvent::LobbySelected { match_id, .. } => *match_id,
+            MatchEvent::KickVoted { vote } => vote.match_id,
+            MatchEvent::TeamsChanged { teams } => teams.match_id,
+            MatchEvent::DiscoveryRecorded { discovery } => discovery.match_id,
+            MatchEvent::TreasuresSpawned { spawn } => spawn.match_id,
+            MatchEvent::CaptureChanged { update } => update.match_id,
+            MatchEvent::RoundEnded { round } => round.match_id,
+            MatchEvent::MatchEnded { match_id } => *match_id,
+            MatchEvent::ResultAdjusted { entry, .. } => entry.match_id,
+            MatchEvent::RankPlaced { match_id, .. } => *match_id,
+        }
+    }
+
+    // Stable name for logs and the admin event stream
+    pub fn name(&self) -> &'static str {
+        match self {
+            MatchEvent::PlayerJoined { .. } => "player_joined",
+            MatchEvent::PlayerLeft { .. } => "player_left",
+            MatchEvent::RoomReady { .. } => "room_ready",
+            MatchEvent::HostChanged { .. } => "host_changed",
+            MatchEvent::TeamsChanged { .. } => "teams_changed",
+            MatchEvent::KickVoted { .. } => "kick_voted",
+            MatchEvent::PlayerKicked { .. } => "player_kicked",
+            MatchEvent::LobbyOpened { .. } => "lobby_opened",
+            MatchEvent::LobbySelected { .. } => "lobby_selected",
+            MatchEvent::MatchStarted { .. } => "match_started",
+            MatchEvent::DiscoveryRecorded { .. } => "discovery_recorded",
+            MatchEvent::TreasuresSpawned { .. } => "treasures_spawned",
+            MatchEvent::CaptureChanged { .. } => "capture_changed",
+            MatchEvent::RoundEnded { .. } => "round_ended",
+            MatchEvent::MatchEnded { .. } => "match_ended",
+            MatchEvent::ResultAdjusted { .. } => "result_adjusted",
+            MatchEvent::RankPlaced { .. } => "rank_placed",
+        }
+    }
+
+    // Player the event is about, when there is one
+    pub fn user_id(&self) -> Option<Uuid> {
+        match self {
+            MatchEvent::PlayerJoined { user_id, .. }
+            | MatchEvent::PlayerLeft { user_id, .. }
+            | MatchEvent::PlayerKicked { user_id, .. }
+            | MatchEvent::LobbySelected { user_id, .. } => Some(*user_id),
+            MatchEvent::KickVoted { vote } => Some(vote.target),
+            MatchEvent::HostChanged { host, .. } => Some(*host),
+            MatchEvent::DiscoveryRecorded { discovery } => Some(discovery.user_id),
+            MatchEvent::RankPlaced { placement, .. } => Some(placement.user_id),
+            _ => None,
+        }
+    }
+}
+
+// A match event as delivered to subscribers
+#[derive(Debug, Clone)]
+pub struct Published {
+    pub event: MatchEvent,
+    // Correlation id of the client command that caused the event, if any
+    pub correlation_id: Option<Uuid>,
+}
+
+// In-process fan-out of match events to any number of subscribers
+#[derive(Clone)]
+pub struct EventBus {
+    sender: broadcast::Sender<Published>,
+}
+
+impl EventBus {
+    pub fn new(capacity: usize) -> Self {
+        let (sender, _) = broadcast::channel(capacity);
+        Self { sender }
+    }
+
+    pub fn publish(&self, event: MatchEvent) {
+        // No subscribers yet is not an error
+        let _ = self.sender.send(Published {
+            event,
+            correlation_id: correlation::current(),
+        });
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<Published> {
+        self.sender.subscribe()
+    }
+}
@@ -0,0 +1,277 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::cluster::scheduler::LeaderElection;
+use crate::config::FairnessConfig;
+use crate::db::repository::{FairnessRepository, MatchRepository};
+use crate::error::Result;
+use crate::jobs::service::JobService;
+use crate::models::game::{MatchScores, Seat, TeamAssignment};
+
+// Samples read per page while computing a report
+const READ_PAGE: i64 = 5_000;
+// Most reports returned by one query
+pub const MAX_REPORTS: usize = 100;
+
+// One player's wait in the queue, recorded when their match starts
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueueSample {
+    pub match_id: Uuid,
+    pub user_id: Uuid,
+    pub match_type: String,
+    // NODE_REGION of the node that started the match
+    pub region: Option<String>,
+    pub team_number: i32,
+    // Rating when the player was seated
+    pub mmr: f64,
+    pub queued_secs: f64,
+    pub started_at: DateTime<Utc>,
+}
+
+// Percentiles of a set of values; all zero when there were none
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+pub struct Distribution {
+    pub p50: f64,
+    pub p90: f64,
+    pub p99: f64,
+    pub max: f64,
+}
+
+impl Distribution {
+    fn of(mut values: Vec<f64>) -> Self {
+        if values.is_empty() {
+            return Self::default();
+        }
+        values.sort_by(|a, b| a.total_cmp(b));
+        // Nearest rank
+        let at = |p: f64| values[((p * values.len() as f64).ceil() as usize).clamp(1, values.len()) - 1];
+        Self {
+            p50: at(0.5),
+            p90: at(0.9),
+            p99: at(0.99),
+            max: values[values.len() - 1],
+        }
+    }
+}
+
+// Quality of the matches of one match type in one region
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct FairnessGroup {
+    pub match_type: String,
+    pub region: Option<String>,
+    // Matches started within the window
+    pub matches: usize,
+    pub players: usize,
+    pub queue_secs: Distribution,
+    // Highest minus lowest MMR in each match
+    pub mmr_spread: Distribution,
+    // Difference between the teams' average MMR in each match
+    pub team_mmr_gap: Distribution,
+    // Of those matches, the ones finished by the time of the report
+    pub finished: usize,
+    pub blowouts: usize,
+    // blowouts / finished; 0 without finished matches
+    pub blowout_rate: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct FairnessReport {
+    pub id: Uuid,
+    pub computed_at: DateTime<Utc>,
+    // Matches started from then on are covered
+    pub window_start: DateTime<Utc>,
+    // FAIRNESS_BLOWOUT_MARGIN the report was computed with
+    pub blowout_margin: f64,
+    // By match type, then region
+    pub groups: Vec<FairnessGroup>,
+}
+
+// Matchmaking quality metrics, to evaluate tuning changes to the matcher.
+//
+// Every player's queue time and MMR are recorded when their match starts. A
+// periodic job run by the leader turns the matches started within
+// FAIRNESS_WINDOW_HOURS into a report per match type and region: queue time
+// percentiles, the MMR spread within matches and between their teams, and the
+// share of finished matches that were blowouts. Reports are kept, so a change
+// can be compared with the reports from before it.
+pub struct FairnessService {
+    repo: Arc<dyn FairnessRepository>,
+    matches: Arc<dyn MatchRepository>,
+    config: FairnessConfig,
+    region: Option<String>,
+}
+
+impl FairnessService {
+    pub fn init(
+        repo: Arc<dyn FairnessRepository>,
+        matches: Arc<dyn MatchRepository>,
+        config: FairnessConfig,
+        region: Option<String>,
+        leader: Arc<LeaderElection>,
+        jobs: &Arc<JobService>,
+    ) -> Arc<Self> {
+        let service = Arc::new(Self { repo, matches, config, region });
+
+        let reporter = service.clone();
+        jobs.spawn_periodic(leader, "fairness_report", service.config.interval, false, move |_| {
+            let reporter = reporter.clone();
+            async move {
+                reporter.compute().await?;
+                Ok(())
+            }
+        });
+
+        service
+    }
+
+    // Record the queue time of every seated player of a match that just
+    // started. Bots have no seat and are left out. Written in the background;
+    // a failed write is only logged.
+    pub fn record_start(&self, match_id: Uuid, match_type: &str, teams: &[TeamAssignment], seats: &HashMap<Uuid, Seat>) {
+        let started_at = Utc::now();
+        let samples: Vec<QueueSample> = teams
+            .iter()
+            .flat_map(|team| team.players.iter().map(move |user_id| (team.team_number, user_id)))
+            .filter_map(|(team_number, user_id)| {
+                let seat = seats.get(user_id)?;
+                Some(QueueSample {
+                    match_id,
+                    user_id: *user_id,
+                    match_type: match_type.to_string(),
+                    region: self.region.clone(),
+                    team_number,
+                    mmr: seat.mmr,
+                    queued_secs: (started_at - seat.seated_at).num_milliseconds().max(0) as f64 / 1000.0,
+                    started_at,
+                })
+            })
+            .collect();
+        if samples.is_empty() {
+            return;
+        }
+
+        let repo = self.repo.clone();
+        tokio::spawn(async move {
+            if let Err(e) = repo.insert_queue_samples(&samples).await {
+                tracing::warn!("Failed to record queue times of match {}: {}", match_id, e);
+            }
+        });
+    }
+
+    // Compute and store a report over the window ending now
+    pub async fn compute(&self) -> Result<FairnessReport> {
+        let computed_at = Utc::now();
+        let window_start = computed_at - chrono::Duration::from_std(self.config.window).unwrap_or(chrono::Duration::zero());
+
+        let mut samples = Vec::new();
+        let mut offset = 0;
+        loop {
+            let page = self.repo.queue_samples(window_start, offset, READ_PAGE).await?;
+            let done = (page.len() as i64) < READ_PAGE;
+            samples.extend(page);
+            if done {
+                break;
+            }
+            offset += READ_PAGE;
+        }
+        let finished = self.matches.finished_match_scores(window_start).await?;
+
+        let report = FairnessReport {
+            id: Uuid::new_v4(),
+            computed_at,
+            window_start,
+            blowout_margin: self.config.blowout_margin,
+            groups: build_groups(&samples, &finished, self.config.blowout_margin),
+        };
+        self.repo.insert_report(&report).await?;
+        tracing::info!(
+            "Fairness report computed: {} groups from {} queue samples",
+            report.groups.len(),
+            samples.len()
+        );
+        Ok(report)
+    }
+
+    // Newest first
+    pub async fn reports(&self, limit: usize) -> Result<Vec<FairnessReport>> {
+        self.repo.recent_reports(limit.clamp(1, MAX_REPORTS)).await
+    }
+}
+
+fn build_groups(samples: &[QueueSample], finished: &[MatchScores], blowout_margin: f64) -> Vec<FairnessGroup> {
+    let mut by_match: HashMap<Uuid, Vec<&QueueSample>> = HashMap::new();
+    for sample in samples {
+        by_match.entry(sample.match_id).or_default().push(sample);
+    }
+    let finished: HashMap<Uuid, &MatchScores> = finished.iter().map(|scores| (scores.match_id, scores)).collect();
+
+    #[derive(Default)]
+    struct Acc {
+        matches: usize,
+        queue_secs: Vec<f64>,
+        mmr_spread: Vec<f64>,
+        team_mmr_gap: Vec<f64>,
+        finished: usize,
+        blowouts: usize,
+    }
+    let mut groups: BTreeMap<(String, Option<String>), Acc> = BTreeMap::new();
+    for (match_id, members) in &by_match {
+        let first = members[0];
+        let acc = groups.entry((first.match_type.clone(), first.region.clone())).or_default();
+        acc.matches += 1;
+        acc.queue_secs.extend(members.iter().map(|m| m.queued_secs));
+
+        let lowest = members.iter().map(|m| m.mmr).fold(f64::INFINITY, f64::min);
+        let highest = members.iter().map(|m| m.mmr).fold(f64::NEG_INFINITY, f64::max);
+        acc.mmr_spread.push(highest - lowest);
+
+        let mut teams: BTreeMap<i32, (f64, usize)> = BTreeMap::new();
+        for member in members {
+            let team = teams.entry(member.team_number).or_default();
+            team.0 += member.mmr;
+            team.1 += 1;
+        }
+        if teams.len() == 2 {
+            let averages: Vec<f64> = teams.values().map(|(sum, count)| sum / *count as f64).collect();
+            acc.team_mmr_gap.push((averages[0] - averages[1]).abs());
+        }
+
+        if let Some(scores) = finished.get(match_id) {
+            acc.finished += 1;
+            if is_blowout(scores, blowout_margin) {
+                acc.blowouts += 1;
+            }
+        }
+    }
+
+    groups
+        .into_iter()
+        .map(|((match_type, region), acc)| FairnessGroup {
+            match_type,
+            region,
+            matches: acc.matches,
+            players: acc.queue_secs.len(),
+            queue_secs: Distribution::of(acc.queue_secs),
+            mmr_spread: Distribution::of(acc.mmr_spread),
+            team_mmr_gap: Distribution::of(acc.team_mmr_gap),
+            finished: acc.finished,
+            blowouts: acc.blowouts,
+            blowout_rate: if acc.finished == 0 { 0.0 } else { acc.blowouts as f64 / acc.finished as f64 },
+        })
+        .collect()
+}
+
+// The winner beat the runner-up by at least `margin` of the winner's score
+fn is_blowout(scores: &MatchScores, margin: f64) -> bool {
+    let mut totals: Vec<i32> = scores.teams.iter().map(|team| team.total_score).collect();
+    totals.sort_unstable_by(|a, b| b.cmp(a));
+    match totals.as_slice() {
+        [winner, runner_up, ..] if *winner > 0 => f64::from(winner - runner_up) / f64::from(*winner) >= margin,
+        _ => false,
+    }
+}
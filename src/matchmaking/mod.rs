@@ -1 +1,11 @@
-pub mod service;
\ No newline at end of file
+pub mod catalog;
+pub mod events;
+pub mod fairness;
+pub mod pools;
+pub mod queue_leases;
+pub mod reconcile;
+pub mod review;
+pub mod service;
+pub mod verify;
+pub mod write_queue;
+pub mod zones;
\ No newline at end of file
@@ -0,0 +1,310 @@
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashMap};
+use std::time::{Duration, Instant};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::models::game::{MatchRoom, PlatformPool};
+
+// Rooms are pooled per match type and map zone; no zone is the global pool.
+// Each pool is split further by MMR bracket. Suspected location spoofers,
+// quarantined cheaters and new players who haven't graduated yet each get
+// pools of their own. Rooms opened by premade parties are pooled apart so
+// parties meet parties, and players who opted out of cross-play get
+// platform-restricted pools. Private rooms sit in pools matchmaking never offers.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct PoolKey {
+    pub match_type: String,
+    pub zone_id: Option<String>,
+    pub suspected: bool,
+    #[serde(default)]
+    pub quarantined: bool,
+    pub protected: bool,
+    pub bracket: i32,
+    pub premade: bool,
+    #[serde(default)]
+    pub platforms: PlatformPool,
+    #[serde(default)]
+    pub private: bool,
+}
+
+// Queue priority of a room, from its highest-tier player. Higher tiers are
+// offered to joining players first, so they fill and start sooner.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QueueTier {
+    #[default]
+    Normal,
+    Premium,
+    // Back after a disconnect the server caused
+    Returning,
+}
+
+// Rooms of one pool, and the order they are offered in
+#[derive(Debug, Default)]
+struct Pool {
+    rooms: Vec<MatchRoom>,
+    // Highest tier first, then oldest first
+    by_priority: BTreeMap<(Reverse<QueueTier>, u64), Uuid>,
+    // Rooms with players in them, longest waiting first
+    by_wait: BTreeMap<u64, Uuid>,
+}
+
+// Every pool of one match type
+#[derive(Debug, Default)]
+struct Shard {
+    pools: HashMap<PoolKey, Pool>,
+}
+
+// Where a room sits and how it ranks in its pool
+#[derive(Debug)]
+struct Placement {
+    key: PoolKey,
+    slot: usize,
+    tier: QueueTier,
+    // Insertion order, to rank rooms of the same tier
+    seq: u64,
+    // Order and time the first player was seated
+    waiting: Option<(u64, Instant)>,
+}
+
+// Rooms on this instance, sharded by match type.
+//
+// Each room is indexed by match id with the pool and slot it sits in, so
+// looking up, updating and removing a room doesn't scan the pools. Rooms are
+// only added and removed through `insert` and `remove`, which keep the index
+// in step; removal moves the pool's last room into the freed slot.
+//
+// `find` offers rooms by tier, then age. Rooms whose first player has waited
+// longer than the starvation wait come first regardless of tier, so normal
+// rooms still fill while priority players keep arriving.
+#[derive(Debug, Default)]
+pub struct MatchPools {
+    shards: HashMap<String, Shard>,
+    index: HashMap<Uuid, Placement>,
+    next_seq: u64,
+    // No aging when unset
+    starvation_wait: Option<Duration>,
+}
+
+impl MatchPools {
+    pub fn with_starvation_wait(starvation_wait: Duration) -> Self {
+        Self {
+            starvation_wait: Some(starvation_wait),
+            ..Self::default()
+        }
+    }
+
+    pub fn get(&self, match_id: Uuid) -> Option<(&PoolKey, &MatchRoom)> {
+        let placement = self.index.get(&match_id)?;
+        let room = self.pool(&placement.key)?.rooms.get(placement.slot)?;
+        Some((&placement.key, room))
+    }
+
+    pub fn get_mut(&mut self, match_id: Uuid) -> Option<(&PoolKey, &mut MatchRoom)> {
+        let placement = self.index.get(&match_id)?;
+        let room = self.shards.get_mut(&placement.key.match_type)?.pools.get_mut(&placement.key)?.rooms.get_mut(placement.slot)?;
+        Some((&placement.key, room))
+    }
+
+    // Add a room to a pool at the normal tier, creating the pool if needed
+    pub fn insert(&mut self, key: PoolKey, room: MatchRoom) -> Uuid {
+        let match_id = room.id;
+        let seq = self.next_seq;
+        self.next_seq += 1;
+        let pool = self.shards
+            .entry(key.match_type.clone())
+            .or_default()
+            .pools
+            .entry(key.clone())
+            .or_default();
+        pool.rooms.push(room);
+        pool.by_priority.insert((Reverse(QueueTier::Normal), seq), match_id);
+        self.index.insert(match_id, Placement {
+            key,
+            slot: pool.rooms.len() - 1,
+            tier: QueueTier::Normal,
+            seq,
+            waiting: None,
+        });
+        match_id
+    }
+
+    pub fn remove(&mut self, match_id: Uuid) -> Option<(PoolKey, MatchRoom)> {
+        let placement = self.index.remove(&match_id)?;
+        let key = placement.key;
+        let shard = self.shards.get_mut(&key.match_type)?;
+        let pool = shard.pools.get_mut(&key)?;
+        let room = pool.rooms.swap_remove(placement.slot);
+        pool.by_priority.remove(&(Reverse(placement.tier), placement.seq));
+        if let Some((wait_seq, _)) = placement.waiting {
+            pool.by_wait.remove(&wait_seq);
+        }
+        if let Some(moved) = pool.rooms.get(placement.slot).and_then(|moved| self.index.get_mut(&moved.id)) {
+            moved.slot = placement.slot;
+        }
+        if pool.rooms.is_empty() {
+            shard.pools.remove(&key);
+        }
+        Some((key, room))
+    }
+
+    // Players of `tier` were seated in the room: start its wait if it was
+    // empty, and raise it to `tier`. Rooms never drop back to a lower tier.
+    pub fn seat(&mut self, match_id: Uuid, tier: QueueTier) {
+        let Some(placement) = self.index.get_mut(&match_id) else {
+            return;
+        };
+        let Some(pool) = self.shards.get_mut(&placement.key.match_type).and_then(|shard| shard.pools.get_mut(&placement.key)) else {
+            return;
+        };
+        if placement.waiting.is_none() {
+            placement.waiting = Some((self.next_seq, Instant::now()));
+            pool.by_wait.insert(self.next_seq, match_id);
+            self.next_seq += 1;
+        }
+        if tier > placement.tier {
+            pool.by_priority.remove(&(Reverse(placement.tier), placement.seq));
+            pool.by_priority.insert((Reverse(tier), placement.seq), match_id);
+            placement.tier = tier;
+        }
+    }
+
+    // Room of the pool matching `pred` that is offered first: a starving
+    // room if there is one, otherwise by tier, then age
+    pub fn find(&self, key: &PoolKey, pred: impl Fn(&MatchRoom) -> bool) -> Option<Uuid> {
+        let pool = self.pool(key)?;
+        let room = |match_id: &Uuid| self.index.get(match_id).and_then(|placement| pool.rooms.get(placement.slot));
+
+        if let Some(starvation_wait) = self.starvation_wait {
+            let starving = pool.by_wait
+                .values()
+                .take_while(|match_id| {
+                    self.index.get(*match_id)
+                        .and_then(|p| p.waiting)
+                        .is_some_and(|(_, since)| since.elapsed() >= starvation_wait)
+                })
+                .filter_map(room)
+                .find(|room| pred(room));
+            if let Some(starving) = starving {
+                return Some(starving.id);
+            }
+        }
+        pool.by_priority.values().filter_map(room).find(|room| pred(room)).map(|room| room.id)
+    }
+
+    pub fn count(&self, key: &PoolKey, pred: impl Fn(&MatchRoom) -> bool) -> usize {
+        self.pool(key).map_or(0, |pool| pool.rooms.iter().filter(|room| pred(room)).count())
+    }
+
+    fn pool(&self, key: &PoolKey) -> Option<&Pool> {
+        self.shards.get(&key.match_type)?.pools.get(key)
+    }
+
+    // Every room, with its pool
+    pub fn rooms(&self) -> impl Iterator<Item = (&PoolKey, &MatchRoom)> {
+        self.shards
+            .values()
+            .flat_map(|shard| shard.pools.iter())
+            .flat_map(|(key, pool)| pool.rooms.iter().map(move |room| (key, room)))
+    }
+
+    // Every room, for updates that don't add or remove rooms
+    pub fn rooms_mut(&mut self) -> impl Iterator<Item = (&PoolKey, &mut MatchRoom)> {
+        self.shards
+            .values_mut()
+            .flat_map(|shard| shard.pools.iter_mut())
+            .flat_map(|(key, pool)| pool.rooms.iter_mut().map(move |room| (key, room)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::game::MatchStatus;
+
+    fn key(match_type: &str) -> PoolKey {
+        PoolKey {
+            match_type: match_type.to_string(),
+            zone_id: None,
+            suspected: false,
+            quarantined: false,
+            protected: false,
+            bracket: 0,
+            premade: false,
+            platforms: PlatformPool::Any,
+            private: false,
+        }
+    }
+
+    fn room() -> MatchRoom {
+        MatchRoom {
+            id: Uuid::new_v4(),
+            required_players: 2,
+            current_players: 0,
+            players: Vec::new(),
+            status: MatchStatus::Matching,
+            parties: Vec::new(),
+            platforms: HashMap::new(),
+            seats: HashMap::new(),
+            teams: Vec::new(),
+            selections: HashMap::new(),
+            lobby_closes_at: None,
+            private: None,
+        }
+    }
+
+    #[test]
+    fn removing_a_room_keeps_the_index_of_the_moved_one() {
+        let mut pools = MatchPools::default();
+        let first = pools.insert(key("1v1"), room());
+        let second = pools.insert(key("1v1"), room());
+        let third = pools.insert(key("1v1"), room());
+
+        // The last room moves into the freed slot
+        assert!(pools.remove(first).is_some());
+        assert_eq!(pools.get(third).map(|(_, room)| room.id), Some(third));
+        assert_eq!(pools.get(second).map(|(_, room)| room.id), Some(second));
+        assert!(pools.get(first).is_none());
+
+        pools.get_mut(third).unwrap().1.current_players = 1;
+        assert_eq!(pools.get(third).unwrap().1.current_players, 1);
+        assert_eq!(pools.count(&key("1v1"), |_| true), 2);
+    }
+
+    #[test]
+    fn an_emptied_pool_is_dropped() {
+        let mut pools = MatchPools::default();
+        let match_id = pools.insert(key("1v1"), room());
+        pools.remove(match_id);
+        assert!(pools.pool(&key("1v1")).is_none());
+        assert_eq!(pools.rooms().count(), 0);
+    }
+
+    #[test]
+    fn rooms_are_offered_by_tier_then_age() {
+        let mut pools = MatchPools::default();
+        let oldest = pools.insert(key("1v1"), room());
+        let premium = pools.insert(key("1v1"), room());
+        pools.insert(key("2v2"), room());
+
+        assert_eq!(pools.find(&key("1v1"), |_| true), Some(oldest));
+        pools.seat(premium, QueueTier::Premium);
+        assert_eq!(pools.find(&key("1v1"), |_| true), Some(premium));
+        // Seating normal players doesn't lower the tier
+        pools.seat(premium, QueueTier::Normal);
+        assert_eq!(pools.find(&key("1v1"), |_| true), Some(premium));
+        assert_eq!(pools.find(&key("1v1"), |room| room.id != premium), Some(oldest));
+    }
+
+    #[test]
+    fn starving_rooms_come_before_higher_tiers() {
+        let mut pools = MatchPools::with_starvation_wait(Duration::ZERO);
+        let waiting = pools.insert(key("1v1"), room());
+        let premium = pools.insert(key("1v1"), room());
+        pools.seat(waiting, QueueTier::Normal);
+        pools.seat(premium, QueueTier::Premium);
+
+        assert_eq!(pools.find(&key("1v1"), |_| true), Some(waiting));
+    }
+}
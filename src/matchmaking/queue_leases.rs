@@ -0,0 +1,161 @@
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Mutex;
+use uuid::Uuid;
+
+use crate::cluster::lock::{DistributedLock, LockGuard};
+use crate::error::{Error, Result};
+
+// A lease lapses this long after its node stopped renewing it...
+const LEASE_TTL: Duration = Duration::from_secs(60);
+// ...which it does this often while the player stays seated
+pub const RENEW_INTERVAL: Duration = Duration::from_secs(20);
+
+fn lease_key(user_id: Uuid) -> String {
+    format!("spv:queued:{}", user_id)
+}
+
+// Which players are queued anywhere in the cluster.
+//
+// The join lock only covers a single join, and each node's pools only know
+// their own rooms, so a player queued on one node could still queue on
+// another. A player seated in a room therefore holds a lease in the shared
+// lock store for as long as they stay seated, and joins on other nodes are
+// refused while it is held. If the node goes away its leases lapse.
+pub struct QueueLeases {
+    lock: Arc<DistributedLock>,
+    held: Mutex<HashMap<Uuid, Lease>>,
+}
+
+struct Lease {
+    guard: LockGuard,
+    // Taken for a join that hasn't seated the player yet
+    joining: bool,
+}
+
+impl QueueLeases {
+    pub fn new(lock: Arc<DistributedLock>) -> Self {
+        Self {
+            lock,
+            held: Mutex::new(HashMap::new()),
+        }
+    }
+
+    // Lease every one of `users`, or none of them if one is queued on another
+    // node. Returns the users newly leased, which count as joining until
+    // `joined`; leases this node already holds are kept, the local pools tell
+    // whether those players are seated.
+    pub async fn acquire(&self, users: &[Uuid]) -> Result<Vec<Uuid>> {
+        let mut held = self.held.lock().await;
+        let mut leased = Vec::new();
+        for &user_id in users {
+            if held.contains_key(&user_id) {
+                continue;
+            }
+            let guard = match self.lock.try_acquire(&lease_key(user_id), LEASE_TTL).await {
+                Ok(Some(guard)) => guard,
+                Ok(None) => {
+                    drop(held);
+                    self.release(&leased).await;
+                    return Err(Error::UserAlreadyInMatch);
+                }
+                Err(e) => {
+                    drop(held);
+                    self.release(&leased).await;
+                    return Err(e);
+                }
+            };
+            held.insert(user_id, Lease { guard, joining: true });
+            leased.push(user_id);
+        }
+        Ok(leased)
+    }
+
+    // The join that leased `users` has seated them
+    pub async fn joined(&self, users: &[Uuid]) {
+        let mut held = self.held.lock().await;
+        for user_id in users {
+            if let Some(lease) = held.get_mut(user_id) {
+                lease.joining = false;
+            }
+        }
+    }
+
+    pub async fn release(&self, users: &[Uuid]) {
+        let guards: Vec<(Uuid, LockGuard)> = {
+            let mut held = self.held.lock().await;
+            users.iter().filter_map(|user_id| Some((*user_id, held.remove(user_id)?.guard))).collect()
+        };
+        self.release_guards(guards).await;
+    }
+
+    // Renew the leases of players seated on this node, lease the seated
+    // players missing one and release the others. `seated` is only read once
+    // the leases are locked, so a join can't seat a player between the two
+    // and lose the lease it just took; leases of joins still under way are
+    // kept either way.
+    pub async fn renew(&self, seated: impl Future<Output = HashSet<Uuid>>) {
+        let left: Vec<(Uuid, LockGuard)> = {
+            let mut held = self.held.lock().await;
+            let seated = seated.await;
+            for (user_id, lease) in held.iter().filter(|(user_id, _)| seated.contains(user_id)) {
+                match self.lock.extend(&lease.guard, LEASE_TTL).await {
+                    Ok(true) => {}
+                    Ok(false) => tracing::warn!("Queue lease of {} lapsed while they were seated", user_id),
+                    Err(e) => tracing::warn!("Failed to renew queue lease of {}: {}", user_id, e),
+                }
+            }
+            let unleased: Vec<Uuid> = seated.iter().filter(|user_id| !held.contains_key(user_id)).copied().collect();
+            for user_id in unleased {
+                match self.lock.try_acquire(&lease_key(user_id), LEASE_TTL).await {
+                    Ok(Some(guard)) => {
+                        held.insert(user_id, Lease { guard, joining: false });
+                    }
+                    Ok(None) => tracing::warn!("Queue lease of seated player {} is held by another node", user_id),
+                    Err(e) => tracing::warn!("Failed to lease seated player {}: {}", user_id, e),
+                }
+            }
+            let unseated: Vec<Uuid> = held
+                .iter()
+                .filter(|(user_id, lease)| !lease.joining && !seated.contains(user_id))
+                .map(|(user_id, _)| *user_id)
+                .collect();
+            unseated.into_iter().filter_map(|user_id| Some((user_id, held.remove(&user_id)?.guard))).collect()
+        };
+        self.release_guards(left).await;
+    }
+
+    async fn release_guards(&self, guards: Vec<(Uuid, LockGuard)>) {
+        for (user_id, guard) in guards {
+            if let Err(e) = self.lock.release(guard).await {
+                tracing::warn!("Failed to release queue lease of {}: {}", user_id, e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn renewal_keeps_joining_leases_and_leases_seated_players() {
+        let lock = DistributedLock::from_env();
+        let leases = QueueLeases::new(lock.clone());
+        let joining = Uuid::new_v4();
+        let seated = Uuid::new_v4();
+        leases.acquire(&[joining]).await.unwrap();
+
+        // The join hasn't seated `joining` yet, and `seated` lost its lease
+        leases.renew(async { HashSet::from([seated]) }).await;
+        assert!(lock.try_acquire(&lease_key(joining), LEASE_TTL).await.unwrap().is_none());
+        assert!(lock.try_acquire(&lease_key(seated), LEASE_TTL).await.unwrap().is_none());
+
+        // Once joined, a player no longer seated gives the lease up
+        leases.joined(&[joining]).await;
+        leases.renew(async { HashSet::from([seated]) }).await;
+        assert!(lock.try_acquire(&lease_key(joining), LEASE_TTL).await.unwrap().is_some());
+    }
+}
@@ -0,0 +1,175 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::Utc;
+use serde::Serialize;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::cluster::scheduler::LeaderElection;
+use crate::config::MatchmakingConfig;
+use crate::db::repository::MatchRepository;
+use crate::error::{Error, Result};
+use crate::jobs::service::{JobHandle, JobService};
+use crate::metrics::METRICS;
+use crate::models::game::{MatchScores, MatchStatus};
+use super::verify::discovery_totals;
+
+// Which stored score was wrong
+#[derive(Debug, Clone, Copy, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ScoreTarget {
+    // match_teams.total_score; `id` is the team
+    Team,
+    // match_members.individual_score; `id` is the user
+    Member,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ScoreCorrection {
+    pub match_id: Uuid,
+    pub target: ScoreTarget,
+    pub id: Uuid,
+    // Stored value before the fix
+    pub recorded: i32,
+    // Sum of the discovery records
+    pub expected: i32,
+}
+
+#[derive(Debug, Clone, Default, Serialize, ToSchema)]
+pub struct ReconcileReport {
+    pub matches_checked: usize,
+    pub corrections: Vec<ScoreCorrection>,
+    // Matches whose winner changed after their team scores were fixed
+    pub winners_fixed: Vec<Uuid>,
+}
+
+// Recomputes team and individual scores from `match_discoveries`.
+//
+// Scores are incremented one mutation at a time as treasures are found, so a
+// failed increment leaves them short of the discovery records. A periodic job
+// checks matches finished within RECONCILE_WINDOW_HOURS and overwrites wrong
+// totals; admins can also run it for one match. Matches still being played are
+// left alone, since their increments may simply not have landed yet.
+pub struct ScoreReconciler {
+    repo: Arc<dyn MatchRepository>,
+    config: MatchmakingConfig,
+    // MATCH_DURATION_SECS, to split best-of matches into rounds
+    match_duration: Option<Duration>,
+}
+
+impl ScoreReconciler {
+    pub fn init(
+        repo: Arc<dyn MatchRepository>,
+        config: MatchmakingConfig,
+        match_duration: Option<Duration>,
+        leader: Arc<LeaderElection>,
+        jobs: &Arc<JobService>,
+    ) -> Arc<Self> {
+        let reconciler = Arc::new(Self { repo, config, match_duration });
+
+        let runner = reconciler.clone();
+        jobs.spawn_periodic(leader, "score_reconcile", reconciler.config.reconcile_interval, false, move |job| {
+            let runner = runner.clone();
+            async move {
+                runner.reconcile_recent(Some(&job)).await?;
+                Ok(())
+            }
+        });
+
+        reconciler
+    }
+
+    // Every match finished within the window, reporting progress to `job`
+    // when run as one
+    pub async fn reconcile_recent(&self, job: Option<&JobHandle>) -> Result<ReconcileReport> {
+        let window = chrono::Duration::from_std(self.config.reconcile_window).unwrap_or(chrono::Duration::zero());
+        let matches = self.repo.finished_match_scores(Utc::now() - window).await?;
+
+        let mut report = ReconcileReport::default();
+        if let Some(job) = job {
+            job.set_total(matches.len()).await;
+        }
+        for scores in &matches {
+            if job.is_some_and(|job| job.cancelled()) {
+                break;
+            }
+            self.reconcile(scores, &mut report).await?;
+            if let Some(job) = job {
+                job.item_done(None).await;
+            }
+        }
+        tracing::info!(
+            "Score reconciliation checked {} matches, {} corrections",
+            report.matches_checked,
+            report.corrections.len()
+        );
+        Ok(report)
+    }
+
+    pub async fn reconcile_match(&self, match_id: Uuid) -> Result<ReconcileReport> {
+        let scores = self.repo.get_match_scores(match_id).await?;
+        if scores.status != MatchStatus::Finished {
+            return Err(Error::MatchNotFinished);
+        }
+
+        let mut report = ReconcileReport::default();
+        self.reconcile(&scores, &mut report).await?;
+        Ok(report)
+    }
+
+    async fn reconcile(&self, scores: &MatchScores, report: &mut ReconcileReport) -> Result<()> {
+        let (team_totals, member_totals) = discovery_totals(scores);
+
+        let mut teams_fixed = false;
+        for team in &scores.teams {
+            let expected = team_totals.get(&team.team_id).copied().unwrap_or(0);
+            if team.total_score != expected {
+                self.repo.set_team_score(team.team_id, expected).await?;
+                self.log_correction(report, scores.match_id, ScoreTarget::Team, team.team_id, team.total_score, expected);
+                teams_fixed = true;
+            }
+        }
+
+        for member in &scores.members {
+            let expected = member_totals.get(&member.user_id).copied().unwrap_or(0);
+            if member.individual_score != expected {
+                self.repo.set_member_score(scores.match_id, member.user_id, expected).await?;
+                self.log_correction(report, scores.match_id, ScoreTarget::Member, member.user_id, member.individual_score, expected);
+            }
+        }
+
+        // Re-pick the winner from the fixed scores under the match's victory
+        // condition; a draw is left as it was
+        if teams_fixed {
+            let mut fixed = scores.clone();
+            for team in &mut fixed.teams {
+                team.total_score = team_totals.get(&team.team_id).copied().unwrap_or(0);
+            }
+            let evaluator = scores.victory_condition.unwrap_or_default().evaluator(self.match_duration);
+            if let Some(winner) = evaluator.winner(&fixed) && scores.winner_team_id != Some(winner) {
+                self.repo.set_winner(scores.match_id, winner).await?;
+                tracing::warn!("Winner of match {} corrected to team {}", scores.match_id, winner);
+                report.winners_fixed.push(scores.match_id);
+            }
+        }
+
+        report.matches_checked += 1;
+        Ok(())
+    }
+
+    fn log_correction(&self, report: &mut ReconcileReport, match_id: Uuid, target: ScoreTarget, id: Uuid, recorded: i32, expected: i32) {
+        tracing::warn!(
+            "Corrected {:?} score of {} in match {}: {} -> {}",
+            target, id, match_id, recorded, expected
+        );
+        METRICS.record_score_correction();
+        report.corrections.push(ScoreCorrection {
+            match_id,
+            target,
+            id,
+            recorded,
+            expected,
+        });
+    }
+}
@@ -0,0 +1,155 @@
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::db::repository::MatchRepository;
+use crate::error::{Error, Result};
+use crate::models::game::{DiscoveryScore, MatchStatus, MemberScore, TeamScore};
+use super::events::{EventBus, MatchEvent};
+use super::verify::Anomaly;
+
+// A change an admin makes to a match result
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, JsonSchema)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum Adjustment {
+    // Throw out a discovery; its score is taken off the team and the player
+    InvalidateDiscovery { discovery_id: Uuid },
+    SetTeamScore { team_id: Uuid, total_score: i32 },
+    SetMemberScore { user_id: Uuid, individual_score: i32 },
+    // Declare the winner and finalize the match
+    SetWinner { team_id: Uuid },
+    // Annul the match: no winner, and the results don't count
+    Void,
+}
+
+// Body of POST /admin/reviews/{match_id}/adjustments
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+pub struct AdjustmentRequest {
+    // Who made the change, for the audit log
+    pub actor: String,
+    pub reason: String,
+    pub adjustment: Adjustment,
+}
+
+// Audit log entry of one adjustment; also pushed to the match's players as `match.adjusted`
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, JsonSchema)]
+pub struct AuditEntry {
+    pub id: Uuid,
+    pub match_id: Uuid,
+    pub actor: String,
+    pub reason: String,
+    pub adjustment: Adjustment,
+    pub created_at: DateTime<Utc>,
+}
+
+// Everything a reviewer needs to judge a match
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct MatchReview {
+    pub match_id: Uuid,
+    pub match_type: String,
+    pub status: MatchStatus,
+    pub start_time: Option<DateTime<Utc>>,
+    pub end_time: Option<DateTime<Utc>>,
+    pub winner_team_id: Option<Uuid>,
+    // Why verification flagged the match; absent if it never was
+    pub review_notes: Option<Vec<Anomaly>>,
+    pub teams: Vec<TeamScore>,
+    pub members: Vec<MemberScore>,
+    // Event log of the match: every discovery in order, invalidated ones included
+    pub discoveries: Vec<DiscoveryScore>,
+    // Adjustments made so far, oldest first
+    pub adjustments: Vec<AuditEntry>,
+}
+
+// Admin review of flagged, finished or voided match results. Every
+// adjustment is written to the audit log and pushed to the match's players.
+pub struct MatchReviewService {
+    repo: Arc<dyn MatchRepository>,
+    events: EventBus,
+}
+
+impl MatchReviewService {
+    pub fn new(repo: Arc<dyn MatchRepository>, events: EventBus) -> Arc<Self> {
+        Arc::new(Self { repo, events })
+    }
+
+    // Matches waiting for a decision, oldest first
+    pub async fn queue(&self) -> Result<Vec<MatchReview>> {
+        self.repo.list_match_reviews(MatchStatus::UnderReview).await
+    }
+
+    pub async fn get(&self, match_id: Uuid) -> Result<MatchReview> {
+        self.repo.get_match_review(match_id).await
+    }
+
+    pub async fn adjust(&self, match_id: Uuid, request: AdjustmentRequest) -> Result<AuditEntry> {
+        if request.actor.trim().is_empty() || request.reason.trim().is_empty() {
+            return Err(Error::InvalidMessage);
+        }
+
+        let review = self.repo.get_match_review(match_id).await?;
+        if !matches!(review.status, MatchStatus::UnderReview | MatchStatus::Finished | MatchStatus::Voided) {
+            return Err(Error::MatchNotFinished);
+        }
+
+        match &request.adjustment {
+            Adjustment::InvalidateDiscovery { discovery_id } => {
+                self.repo.invalidate_discovery(match_id, *discovery_id).await?;
+            }
+            Adjustment::SetTeamScore { team_id, total_score } => {
+                if *total_score < 0 {
+                    return Err(Error::InvalidMessage);
+                }
+                ensure_team(&review, *team_id)?;
+                self.repo.set_team_score(*team_id, *total_score).await?;
+            }
+            Adjustment::SetMemberScore { user_id, individual_score } => {
+                if *individual_score < 0 {
+                    return Err(Error::InvalidMessage);
+                }
+                if !review.members.iter().any(|m| m.user_id == *user_id) {
+                    return Err(Error::NotFound(format!("player {} in match {}", user_id, match_id)));
+                }
+                self.repo.set_member_score(match_id, *user_id, *individual_score).await?;
+            }
+            Adjustment::SetWinner { team_id } => {
+                ensure_team(&review, *team_id)?;
+                self.repo.close_match(match_id, MatchStatus::Finished, Some(*team_id)).await?;
+            }
+            Adjustment::Void => {
+                self.repo.close_match(match_id, MatchStatus::Voided, None).await?;
+            }
+        }
+
+        let entry = AuditEntry {
+            id: Uuid::new_v4(),
+            match_id,
+            actor: request.actor,
+            reason: request.reason,
+            adjustment: request.adjustment,
+            created_at: Utc::now(),
+        };
+        self.repo.record_adjustment(&entry).await?;
+        tracing::info!("Match {} adjusted by {}: {:?} ({})", match_id, entry.actor, entry.adjustment, entry.reason);
+
+        let users = review.members.iter().map(|m| m.user_id).collect();
+        self.events.publish(MatchEvent::ResultAdjusted {
+            entry: entry.clone(),
+            users,
+        });
+
+        Ok(entry)
+    }
+}
+
+fn ensure_team(review: &MatchReview, team_id: Uuid) -> Result<()> {
+    if review.teams.iter().any(|t| t.team_id == team_id) {
+        Ok(())
+    } else {
+        Err(Error::NotFound(format!("team {} in match {}", team_id, review.match_id)))
+    }
+}
@@ -1,88 +1,468 @@
 use std::sync::Arc;
 use tokio::sync::RwLock;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
 use uuid::Uuid;
+use chrono::{DateTime, Utc};
+use rand::Rng;
 use rand::seq::SliceRandom;
 use rand::thread_rng;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 
+use crate::config::{Config, LoadLimits, LobbyConfig, NewPlayerConfig, OfflineConfig, OfflinePolicy, PriorityConfig};
 use crate::error::{Error, Result};
-use crate::gateway::handler::WebSocketHandler;
-use crate::models::game::{MatchResult, MatchRoom};
-use crate::db::hasura_match_repository::HasuraMatchRepository;
+use crate::models::game::{
+    KickVote, LobbySelection, LobbyTeams, MatchDetails, MatchResult, MatchRoom, MatchStatus, Platform, PlatformPool, PlayerPosition,
+    PrivateLobby, PrivateRoom, RoundResult, Seat, TeamAssignment, TeamStrength, TreasureDiscovery,
+};
+use crate::game::capture::CaptureUpdate;
+use crate::game::victory::{VictoryCondition, VictoryConfig};
+use crate::models::treasure::{Treasure, TreasureSpawn};
+use crate::db::health::DbHealth;
+use crate::db::repository::MatchRepository;
+use crate::cluster::lock::{DistributedLock, LockGuard};
+use crate::cluster::ownership::MatchOwnership;
+use crate::memory;
+use crate::metrics::METRICS;
+use crate::supervisor;
+use crate::anticheat::quarantine::QuarantineService;
+use crate::anticheat::trust::TrustTracker;
+use crate::moderation::service::BanService;
+use crate::rating::mmr::{PlayerRating, mean};
+use crate::rating::service::RatingService;
+use crate::tutorial::tutorial::TUTORIAL_MATCH_TYPE;
+use super::catalog::TreasureCatalog;
+use super::pools::{MatchPools, PoolKey, QueueTier};
+use super::queue_leases::{self, QueueLeases};
+use super::events::{EventBus, MatchEvent};
+use super::fairness::FairnessService;
+use super::verify::verify_result;
+use super::write_queue::{PendingWrite, WriteQueue};
+use super::zones::ZoneRegistry;
+
+// How long a join may hold the per-user lock before it is considered abandoned
+const JOIN_LOCK_TTL: Duration = Duration::from_secs(10);
+// Discoveries still in flight when a timed match runs out are accepted this late
+const LATE_CLAIM_GRACE: Duration = Duration::from_secs(5);
+// How often protected rooms are checked for bot padding
+const BOT_FILL_CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
+// Where match results currently go
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Persistence {
+    // Written straight to the database
+    Live,
+    // Database offline, writes queued for replay
+    Queued,
+    // Database offline and new matches refused
+    Unavailable,
+}
+
+// What the server can do right now; reported in /readyz and the welcome message
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, JsonSchema)]
+pub struct Capabilities {
+    pub db_available: bool,
+    pub matchmaking: bool,
+    pub maintenance: bool,
+    pub persistence: Persistence,
+    pub pending_writes: usize,
+}
+
+// Maintenance mode of this node, set from the admin API. While enabled new
+// matches are refused; matches already running play on.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+pub struct Maintenance {
+    pub enabled: bool,
+    // Told to players whose join is refused
+    pub message: Option<String>,
+    pub since: Option<DateTime<Utc>>,
+}
+
+// Rooms still filling up in one match type and zone
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct QueueDepth {
+    pub match_type: String,
+    pub zone_id: Option<String>,
+    pub rooms: usize,
+    pub players: usize,
+}
+
+// A public room still filling up, as advertised with lfg.post
+#[derive(Debug, Clone)]
+pub struct QueuedRoom {
+    pub match_type: String,
+    pub zone_id: Option<String>,
+    pub players: i32,
+    pub required_players: i32,
+}
+
+// Platforms of the players joining together, as reported when they
+// connected, and the platforms they accept to be matched with
+#[derive(Debug, Clone, Default)]
+pub struct CrossPlay {
+    pub platforms: HashMap<Uuid, Platform>,
+    pub pool: PlatformPool,
+}
+
+// Load limit a refused join ran into
+#[derive(Debug, Clone, Copy)]
+pub enum ShedReason {
+    Matches,
+    MatchType,
+    Queue,
+    PendingWrites,
+}
 
 pub struct MatchService {
-    match_pools: Arc<RwLock<HashMap<String, Vec<MatchRoom>>>>,
+    match_pools: Arc<RwLock<MatchPools>>,
     min_room_count: HashMap<String, usize>,
-    repo_cell: Arc<tokio::sync::OnceCell<Arc<HasuraMatchRepository>>>,
-    ws_handler: Option<Arc<WebSocketHandler>>,
+    repo: Arc<dyn MatchRepository>,
+    catalog: Arc<TreasureCatalog>,
+    zones: Arc<ZoneRegistry>,
+    trust: Arc<TrustTracker>,
+    quarantine: Arc<QuarantineService>,
+    bans: Arc<BanService>,
+    ratings: Arc<RatingService>,
+    fairness: Arc<FairnessService>,
+    events: EventBus,
+    join_lock: Arc<DistributedLock>,
+    // Who is queued on any node
+    queue_leases: QueueLeases,
+    // Running matches are owned by the node that started them
+    ownership: Arc<MatchOwnership>,
+    db_health: DbHealth,
+    offline: OfflineConfig,
+    write_queue: WriteQueue,
+    // MATCH_DURATION_SECS; discoveries after it are late claims
+    match_duration: Option<Duration>,
+    // VICTORY_CONDITIONS, recorded on each match when it starts
+    victory: VictoryConfig,
+    lobby: LobbyConfig,
+    new_players: NewPlayerConfig,
+    limits: LoadLimits,
+    priority: PriorityConfig,
+    // Players disconnected by the server, with when their returning tier lapses
+    returning: std::sync::Mutex<HashMap<Uuid, Instant>>,
+    maintenance: std::sync::RwLock<Maintenance>,
 }
 
 impl MatchService {
-    pub fn new() -> Arc<Self> {
-        // Create a shared repository
-        let repo_cell = Arc::new(tokio::sync::OnceCell::new());
-        let repo_cell_clone = repo_cell.clone();
+    // Build the service around an already-connected repository.
+    //
+    // Fails fast if the repository can't be reached: without persistence every
+    // match played would silently vanish.
+    //
+    // Once running, database outages are handled by the degraded mode selected
+    // with DB_OFFLINE_POLICY (see `OfflinePolicy`).
+    #[allow(clippy::too_many_arguments)]
+    pub async fn init(
+        repo: Arc<dyn MatchRepository>,
+        catalog: Arc<TreasureCatalog>,
+        zones: Arc<ZoneRegistry>,
+        trust: Arc<TrustTracker>,
+        quarantine: Arc<QuarantineService>,
+        bans: Arc<BanService>,
+        ratings: Arc<RatingService>,
+        fairness: Arc<FairnessService>,
+        events: EventBus,
+        config: &Config,
+    ) -> Result<Arc<Self>> {
+        // Bring old rows onto the canonical status vocabulary; doubles as a connectivity check
+        let migrated = repo.migrate_legacy_statuses().await?;
+        if migrated > 0 {
+            println!("Migrated {} legacy match statuses", migrated);
+        }
         
-        // Create the service
+        let join_lock = DistributedLock::from_env();
         let service = Arc::new(Self {
-            match_pools: Arc::new(RwLock::new(HashMap::new())),
+            match_pools: Arc::new(RwLock::new(MatchPools::with_starvation_wait(config.matchmaking.priority.starvation_wait))),
             min_room_count: HashMap::from([
                 ("1v1".to_string(), 5),
                 ("2v2".to_string(), 3),
                 ("5v5".to_string(), 2),
             ]),
-            repo_cell,
-            ws_handler: None,
+            repo,
+            catalog,
+            zones,
+            trust,
+            quarantine,
+            bans,
+            ratings,
+            fairness,
+            events,
+            join_lock: join_lock.clone(),
+            queue_leases: QueueLeases::new(join_lock),
+            ownership: MatchOwnership::from_env(),
+            db_health: DbHealth::new(),
+            offline: config.offline.clone(),
+            write_queue: WriteQueue::default(),
+            match_duration: config.game.match_duration,
+            victory: config.game.victory.clone(),
+            lobby: config.game.lobby.clone(),
+            new_players: config.new_players.clone(),
+            limits: config.matchmaking.limits.clone(),
+            priority: config.matchmaking.priority.clone(),
+            returning: std::sync::Mutex::new(HashMap::new()),
+            maintenance: std::sync::RwLock::new(Maintenance::default()),
         });
         
-        // Clone for init task
-        let service_clone = service.clone();
+        // Initialize match pools
+        service.initialize_pools().await?;
+        
+        // Watch database reachability and replay queued writes
+        service.clone().spawn_health_probe();
+        service.clone().spawn_queue_lease_renewal();
+        
+        if service.new_players.protected_matches > 0 && !service.new_players.bots.is_empty() {
+            service.clone().spawn_bot_filler();
+        }
+        
+        Ok(service)
+    }
+
+    pub fn catalog(&self) -> &TreasureCatalog {
+        &self.catalog
+    }
+
+    pub fn zones(&self) -> &ZoneRegistry {
+        &self.zones
+    }
+
+    pub fn trust(&self) -> &TrustTracker {
+        &self.trust
+    }
+
+    pub fn quarantine(&self) -> &QuarantineService {
+        &self.quarantine
+    }
+
+    pub fn capabilities(&self) -> Capabilities {
+        let db_available = self.db_health.is_available();
+        let persistence = if db_available {
+            Persistence::Live
+        } else if self.offline.policy == OfflinePolicy::Queue {
+            Persistence::Queued
+        } else {
+            Persistence::Unavailable
+        };
+        
+        let maintenance = self.maintenance.read().unwrap().enabled;
+        
+        Capabilities {
+            db_available,
+            matchmaking: persistence != Persistence::Unavailable && !maintenance,
+            maintenance,
+            persistence,
+            pending_writes: self.write_queue.len(),
+        }
+    }
+
+    fn mark_db_unreachable(&self) {
+        if self.db_health.set_available(false) {
+            eprintln!("Database unreachable, entering degraded mode (policy: {})", self.offline.policy.to_str());
+        }
+    }
+
+    pub fn maintenance(&self) -> Maintenance {
+        self.maintenance.read().unwrap().clone()
+    }
+
+    pub fn set_maintenance(&self, enabled: bool, message: Option<String>) -> Maintenance {
+        let mut maintenance = self.maintenance.write().unwrap();
+        *maintenance = Maintenance {
+            enabled,
+            message: message.filter(|_| enabled),
+            since: enabled.then(Utc::now),
+        };
+        maintenance.clone()
+    }
+
+    // Refuse new matches during maintenance, and while the database is down
+    // unless configured to queue writes
+    fn ensure_accepting_matches(&self) -> Result<()> {
+        let maintenance = self.maintenance();
+        if maintenance.enabled {
+            return Err(Error::Maintenance(maintenance.message.unwrap_or_else(|| "new matches are paused".to_string())));
+        }
+        if !self.capabilities().matchmaking {
+            return Err(Error::DbUnavailable);
+        }
+        Ok(())
+    }
+
+    fn spawn_health_probe(self: Arc<Self>) {
+        supervisor::spawn_loop("db_health_probe", move || {
+            let service = self.clone();
+            async move {
+                let mut interval = tokio::time::interval(service.offline.probe_interval);
+                loop {
+                    interval.tick().await;
+                    
+                    let reachable = service.repo.ping().await.is_ok();
+                    if service.db_health.set_available(reachable) {
+                        if reachable {
+                            println!("Database reachable again, leaving degraded mode");
+                        } else {
+                            eprintln!("Database unreachable, entering degraded mode (policy: {})", service.offline.policy.to_str());
+                        }
+                    }
+                    
+                    if reachable && !service.write_queue.is_empty() {
+                        service.replay_pending_writes().await;
+                    }
+                }
+            }
+        });
+    }
+
+    // Apply queued writes in order; stop at the first connectivity failure
+    async fn replay_pending_writes(&self) {
+        println!("Replaying {} queued database writes", self.write_queue.len());
         
-        // Initialize in background
-        tokio::spawn(async move {
-            // Initialize DB connection
-            match HasuraMatchRepository::new().await {
-                Ok(repo) => {
-                    let _ = repo_cell_clone.set(Arc::new(repo));
+        while let Some(write) = self.write_queue.front() {
+            match self.apply_write(&write).await {
+                Ok(()) => self.write_queue.pop_front(),
+                Err(Error::DbUnavailable) => {
+                    self.mark_db_unreachable();
+                    break;
                 }
                 Err(e) => {
-                    eprintln!("Failed to initialize match repository: {:?}", e);
+                    eprintln!("Dropping queued write {:?}: {:?}", write, e);
+                    self.write_queue.pop_front();
                 }
             }
-            
-            // Initialize match pools
-            if let Err(e) = service_clone.initialize_pools().await {
-                eprintln!("Failed to initialize pools: {:?}", e);
+        }
+    }
+
+    // Write through to the database, or queue the write while it is offline
+    async fn persist(&self, write: PendingWrite) -> Result<()> {
+        // Keep ordering: once something is queued, everything after it queues too
+        if self.db_health.is_available() && self.write_queue.is_empty() {
+            match self.apply_write(&write).await {
+                Err(Error::DbUnavailable) => self.mark_db_unreachable(),
+                other => return other,
             }
-        });
+        }
         
-        service
+        match self.offline.policy {
+            OfflinePolicy::Queue => {
+                println!("Database offline, queued write: {:?}", write);
+                self.write_queue.push(write);
+                Ok(())
+            }
+            OfflinePolicy::Refuse => Err(Error::DbUnavailable),
+        }
+    }
+
+    async fn apply_write(&self, write: &PendingWrite) -> Result<()> {
+        match write {
+            PendingWrite::StartMatch { match_id, match_type, players_per_team, teams, platforms, strengths, victory, lobby } => {
+                Self::persist_match_start(self.repo.as_ref(), *match_id, match_type, *players_per_team, teams, platforms, strengths, *victory, *lobby).await
+            }
+            PendingWrite::BeginMatch { match_id } => self.repo.start_match(*match_id).await,
+            PendingWrite::LobbySelection { match_id, user_id, selection } => {
+                self.repo.set_member_selection(*match_id, *user_id, selection).await
+            }
+            PendingWrite::Discovery(d) => {
+                self.repo.record_discovery(d.match_id, d.team_id, d.user_id, d.treasure_id, d.score).await?;
+                Ok(())
+            }
+            PendingWrite::ObjectivePoints { team_id, points, .. } => self.repo.add_objective_points(*team_id, *points).await,
+            PendingWrite::Round(round) => self.repo.record_round(round).await,
+            PendingWrite::EndMatch { match_id, unrated } => self.finish_match(*match_id, *unrated).await,
+        }
+    }
+
+    // Verify the records before declaring a winner; anomalous matches are
+    // put under review instead of being finalized
+    async fn finish_match(&self, match_id: Uuid, unrated: bool) -> Result<()> {
+        let scores = self.repo.get_match_scores(match_id).await?;
+
+        let mut ended_at = Utc::now();
+        if let (Some(limit), Some(start_time)) = (self.match_duration, scores.start_time)
+            && let Ok(limit) = chrono::Duration::from_std(limit + LATE_CLAIM_GRACE)
+        {
+            ended_at = ended_at.min(start_time + limit);
+        }
+
+        let anomalies = verify_result(&scores, ended_at);
+        if anomalies.is_empty() {
+            let winner = scores.victory_condition.unwrap_or_default().evaluator(self.match_duration).winner(&scores);
+            self.repo.end_match(match_id, winner).await?;
+            if !unrated {
+                self.rate_match(match_id).await;
+            }
+            return Ok(());
+        }
+
+        tracing::warn!("Match {} put under review: {:?}", match_id, anomalies);
+        self.repo.flag_for_review(match_id, &anomalies).await
     }
 
-    fn get_repo(&self) -> Option<Arc<HasuraMatchRepository>> {
-        self.repo_cell.get().cloned()
+    // Update the players' ratings from the final result. A failure here must
+    // not fail the write, or the match would be ended again on replay.
+    async fn rate_match(&self, match_id: Uuid) {
+        let rated = match self.repo.get_match_scores(match_id).await {
+            Ok(scores) => self.ratings.record_match(&scores).await,
+            Err(e) => Err(e),
+        };
+        match rated {
+            Ok(placements) => {
+                for placement in placements {
+                    self.events.publish(MatchEvent::RankPlaced { match_id, placement });
+                }
+            }
+            Err(e) => tracing::warn!("Failed to rate match {}: {}", match_id, e),
+        }
     }
 
-    pub fn set_ws_handler(&mut self, handler: Arc<WebSocketHandler>) {
-        self.ws_handler = Some(handler);
+    fn room_snapshot(key: &PoolKey, room: &MatchRoom) -> MatchResult {
+        MatchResult {
+            match_id: room.id,
+            status: room.status,
+            match_type: key.match_type.clone(),
+            zone_id: key.zone_id.clone(),
+            current_players: room.current_players,
+            required_players: room.required_players,
+        }
     }
 
     // Initialize match pools
     async fn initialize_pools(&self) -> Result<()> {
         let mut pools = self.match_pools.write().await;
         
+        // Zoned pools are created on demand
         for (match_type, &min_count) in &self.min_room_count {
-            let pool = pools.entry(match_type.clone())
-                .or_insert_with(Vec::new);
+            let key = PoolKey {
+                match_type: match_type.clone(),
+                zone_id: None,
+                suspected: false,
+                quarantined: false,
+                protected: false,
+                bracket: self.ratings.initial_bracket(),
+                premade: false,
+                platforms: PlatformPool::Any,
+                private: false,
+            };
             
             // Create initial rooms
-            while pool.len() < min_count {
-                pool.push(MatchRoom {
+            while pools.count(&key, |_| true) < min_count {
+                pools.insert(key.clone(), MatchRoom {
                     id: Uuid::new_v4(),
                     required_players: self.get_required_players(match_type)?,
                     current_players: 0,
                     players: Vec::new(),
-                    status: "matching".to_string(),
+                    status: MatchStatus::Matching,
+                    parties: Vec::new(),
+                    platforms: HashMap::new(),
+                    seats: HashMap::new(),
+                    teams: Vec::new(),
+                    selections: HashMap::new(),
+                    lobby_closes_at: None,
+                    private: None,
                 });
             }
         }
@@ -91,7 +471,7 @@ impl MatchService {
     }
 
     // Get required players for a match type
-    fn get_required_players(&self, match_type: &str) -> Result<i32> {
+    pub fn get_required_players(&self, match_type: &str) -> Result<i32> {
         match match_type {
             "1v1" => Ok(2),
             "2v2" => Ok(4),
@@ -101,317 +481,1548 @@ impl MatchService {
     }
 
     // Join a match
-    pub async fn join_match(self: Arc<Self>, user_id: Uuid, match_type: &str) -> Result<MatchResult> {
-        // Check if user is already in a match
-        if let Some(repo) = &self.get_repo() {
-            if let Some(_active_match) = repo.is_user_in_match(user_id).await? {
-                return Err(Error::UserAlreadyInMatch);
+    //
+    // Serialised per user across all instances, so concurrent joins for the
+    // same user can't land in different rooms.
+    //
+    // Players are matched within a map zone: the requested one, or else the
+    // zone around `position`; players outside every zone share the global pool.
+    //
+    // `party` lists the other members of a premade party queueing with the
+    // user; the whole party is put in one room, preferably against other parties.
+    //
+    // `crossplay` carries the members' platforms, recorded with the match, and
+    // the platforms they accept to play with.
+    pub async fn join_match(
+        self: Arc<Self>,
+        user_id: Uuid,
+        party: &[Uuid],
+        match_type: &str,
+        zone_id: Option<&str>,
+        position: Option<&PlayerPosition>,
+        crossplay: &CrossPlay,
+    ) -> Result<MatchResult> {
+        let mut members = vec![user_id];
+        for &member in party {
+            if !members.contains(&member) {
+                members.push(member);
             }
         }
-        
-        let mut pools = self.match_pools.write().await;
-        
-        // Get or create match pool
-        let pool = pools.entry(match_type.to_string())
-            .or_insert_with(Vec::new);
-        
-        // Get required players
         let required_players = self.get_required_players(match_type)?;
+        if members.len() > self.ratings.max_party_size() || members.len() as i32 > required_players / 2 {
+            return Err(Error::InvalidParty(format!(
+                "{} players can't queue together for {}",
+                members.len(),
+                match_type
+            )));
+        }
 
-        // Find an available room
-        if let Some(room) = pool.iter_mut().find(|r| 
-            r.status == "matching" && 
-            r.current_players < r.required_players && 
-            !r.players.contains(&user_id)
-        ) {
-            room.players.push(user_id);
-            room.current_players += 1;
-
-            // Check if room is full
-            if room.current_players == room.required_players {
-                println!("The room is ready. Let's begin the game.: {}", room.id);
-                room.status = "ready".to_string();
-
-                if let Some(handler) = &self.ws_handler {
-                    handler.broadcast_match_update(
-                        room.id, 
-                        &room.status, 
-                        match_type,
-                        room.current_players, 
-                        room.required_players
-                    ).await?;
+        let mut guards = Vec::with_capacity(members.len());
+        for &member in &members {
+            let lock_key = format!("spv:lock:join:{}", member);
+            match self.join_lock.try_acquire(&lock_key, JOIN_LOCK_TTL).await {
+                Ok(Some(guard)) => guards.push((member, guard)),
+                Ok(None) => {
+                    self.release_join_locks(guards).await;
+                    return Err(Error::JoinInProgress);
+                }
+                Err(e) => {
+                    self.release_join_locks(guards).await;
+                    return Err(e);
                 }
-                
-                // Clone room ID for async call
-                let match_id = room.id;
-                
-                // Clone the Arc for the background task
-                let match_service = self.clone();
-                tokio::spawn(async move {
-                    if let Err(e) = match_service.start_match(match_id).await {
-                        eprintln!("Failed to start match {}: {:?}", match_id, e);
-                    }
-                });
             }
-
-            return Ok(MatchResult {
-                match_id: room.id,
-                status: room.status.clone(),
-                match_type: match_type.to_string(),
-                current_players: room.current_players,
-                required_players: room.required_players,
-            });
         }
+        
+        let join = self.clone().join_match_locked(&members, match_type, zone_id, position, crossplay);
+        let result = self.with_queue_leases(&members, join).await;
+        
+        self.release_join_locks(guards).await;
+        result
+    }
 
-        // Create new room if none available
-        let new_room = MatchRoom {
-            id: Uuid::new_v4(),
-            required_players,
-            current_players: 1,
-            players: vec![user_id],
-            status: "matching".to_string(),
-        };
-
-        let result = MatchResult {
-            match_id: new_room.id,
-            status: new_room.status.clone(),
-            match_type: match_type.to_string(),
-            current_players: new_room.current_players,
-            required_players: new_room.required_players,
-        };
-
-        pool.push(new_room);
-        Ok(result)
+    // Run a join holding the players' queue leases; the leases it took are
+    // given up again if the join fails
+    async fn with_queue_leases<T>(&self, members: &[Uuid], join: impl Future<Output = Result<T>>) -> Result<T> {
+        let leased = self.queue_leases.acquire(members).await?;
+        let result = join.await;
+        match &result {
+            Ok(_) => self.queue_leases.joined(&leased).await,
+            Err(_) => self.queue_leases.release(&leased).await,
+        }
+        result
     }
 
-    // Leave a match
-    pub async fn leave_match(&self, user_id: Uuid, match_id: Uuid) -> Result<()> {
-        let mut pools = self.match_pools.write().await;
-        
-        for (match_type, pool) in pools.iter_mut() {
-            if let Some(index) = pool.iter().position(|r| r.id == match_id) {
-                let room = &mut pool[index];
-                
-                // Only allow leaving if match hasn't started
-                if room.status != "matching" {
-                    return Err(Error::MatchAlreadyStarted);
-                }
-                
-                if let Some(player_index) = room.players.iter().position(|&p| p == user_id) {
-                    room.players.remove(player_index);
-                    room.current_players -= 1;
-                    
-                    // Recycle empty rooms if above minimum count
-                    if room.current_players == 0 {
-                        let min_count = self.min_room_count.get(match_type).unwrap_or(&0);
-                        let empty_rooms = pool.iter()
-                            .filter(|r| r.current_players == 0)
-                            .count();
-                        
-                        if empty_rooms > *min_count {
-                            pool.remove(index);
-                        }
-                    }
+    fn spawn_queue_lease_renewal(self: Arc<Self>) {
+        supervisor::spawn_loop("queue_leases", move || {
+            let service = self.clone();
+            async move {
+                let mut interval = tokio::time::interval(queue_leases::RENEW_INTERVAL);
+                loop {
+                    interval.tick().await;
+                    let seated = async {
+                        service.match_pools.read().await.rooms()
+                            .filter(|(_, r)| matches!(r.status, MatchStatus::Matching | MatchStatus::Ready))
+                            .flat_map(|(_, r)| r.players.iter().copied())
+                            .collect::<HashSet<Uuid>>()
+                    };
+                    service.queue_leases.renew(seated).await;
                 }
-                return Ok(());
+            }
+        });
+    }
+
+    async fn release_join_locks(&self, guards: Vec<(Uuid, LockGuard)>) {
+        for (user_id, guard) in guards {
+            if let Err(e) = self.join_lock.release(guard).await {
+                eprintln!("Failed to release join lock for {}: {:?}", user_id, e);
             }
         }
-        
-        Err(Error::MatchNotFound)
     }
 
-    // Get match status
-    pub async fn get_match_status(&self, match_id: Uuid) -> Result<String> {
-        // First check in-memory pools
-        let pools = self.match_pools.read().await;
-        
-        for pool in pools.values() {
-            if let Some(room) = pool.iter().find(|r| r.id == match_id) {
-                return Ok(room.status.clone());
+    async fn join_match_locked(
+        self: Arc<Self>,
+        members: &[Uuid],
+        match_type: &str,
+        zone_id: Option<&str>,
+        position: Option<&PlayerPosition>,
+        crossplay: &CrossPlay,
+    ) -> Result<MatchResult> {
+        for &user_id in members {
+            self.bans.ensure_not_banned(user_id).await?;
+        }
+        if let Some(&user_id) = members.iter().find(|m| !crossplay.pool.admits(crossplay.platforms.get(m).copied())) {
+            return Err(Error::PlatformNotAllowed(format!("{} is not on a mobile platform", user_id)));
+        }
+
+        // Check if any member is already in a match (skipped while the database is offline)
+        for &user_id in members {
+            if !self.db_health.is_available() {
+                break;
+            }
+            match self.repo.is_user_in_match(user_id).await {
+                Ok(Some(_active_match)) => return Err(Error::UserAlreadyInMatch),
+                Ok(None) => {}
+                Err(Error::DbUnavailable) => self.mark_db_unreachable(),
+                Err(e) => return Err(e),
             }
         }
+        self.ensure_accepting_matches()?;
+        let tier = self.queue_tier(members);
         
-        // If not found in memory, check database
-        if let Some(repo) = &self.get_repo() {
-            match repo.get_match(match_id).await {
-                Ok(room) => return Ok(room.status),
-                Err(Error::MatchNotFound) => return Err(Error::MatchNotFound),
-                Err(_) => {} // Continue to next check if DB error
-            }
+        let zone = self.zones.resolve(zone_id, position).await?;
+        let mut ratings = Vec::with_capacity(members.len());
+        let mut suspected = false;
+        let mut quarantined = false;
+        let mut protected = true;
+        for &user_id in members {
+            let rating = self.player_rating(user_id).await;
+            suspected |= self.trust.is_suspected(user_id).await;
+            // One quarantined member takes the whole party into quarantine
+            let in_quarantine = self.quarantine.check(user_id, self.trust.score(user_id).await).await;
+            quarantined |= in_quarantine;
+            // Suspected smurfs and quarantined cheaters don't belong with new players
+            protected &= !rating.smurf_suspected && !in_quarantine && self.is_new_player(user_id).await;
+            ratings.push(rating);
         }
+        let premade = members.len() > 1;
+        let key = PoolKey {
+            match_type: match_type.to_string(),
+            zone_id: zone.map(|z| z.id),
+            suspected,
+            quarantined,
+            protected,
+            bracket: if premade { self.ratings.party_bracket(&ratings) } else { self.ratings.bracket(&ratings[0]) },
+            premade,
+            platforms: crossplay.pool,
+            private: false,
+        };
         
-        Err(Error::MatchNotFound)
-    }
-
-    // Start a match
-    pub async fn start_match(&self, match_id: Uuid) -> Result<()> {
-        // Find match room
-        let mut match_type = String::new();
-        let mut match_room = None;
+        let mut pools = self.match_pools.write().await;
         
-        {
-            let pools = self.match_pools.read().await;
-            
-            for (type_name, pool) in pools.iter() {
-                if let Some(room) = pool.iter().find(|r| r.id == match_id) {
-                    match_type = type_name.clone();
-                    match_room = Some(room.clone());
-                    break;
-                }
-            }
+        // Check if any member is already waiting in a room on this instance
+        let already_queued = pools.rooms()
+            .any(|(_, r)| matches!(r.status, MatchStatus::Matching | MatchStatus::Ready) && members.iter().any(|m| r.players.contains(m)));
+        if already_queued {
+            return Err(Error::UserAlreadyInMatch);
         }
+        self.check_load(&pools, match_type, members.len())?;
         
-        // Room not found
-        let room = match match_room {
-            Some(r) => r,
-            None => return Err(Error::MatchNotFound),
+        // Players in placement are matched more loosely
+        let spread = if ratings.iter().all(|r| self.ratings.in_placement(r)) {
+            self.ratings.placement_spread()
+        } else {
+            0
         };
+        let mut key = Self::widen_bracket(&pools, key, spread, members);
         
-        // Verify room is ready
-        if room.status != "ready" {
-            return Err(Error::MatchNotReady);
+        // Parties without a premade room to join fill up a room of solo players
+        if premade && !Self::pool_has_seat(&pools, &key, members) {
+            let solo = PoolKey { premade: false, ..key.clone() };
+            if Self::pool_has_seat(&pools, &solo, members) {
+                key = solo;
+            }
         }
         
-        // Proceed with starting the match if we have a repository
-        if let Some(repo) = &self.get_repo() {
-            println!("Create match's record: {}", match_id);
+        let required_players = self.get_required_players(match_type)?;
 
-            // Calculate players per team
-            let players_per_team = room.required_players / 2;
-            
-            // 1. Create match record in database
-            match repo.create_match(match_id, &match_type, players_per_team).await {
-                Ok(_) => println!("匹配记录创建成功"),
-                Err(e) => {
-                    println!("创建匹配记录失败: {:?}", e);
-                    return Err(e);
-                }
+        // Find an available room, or create one if none is available
+        let match_id = match pools.find(&key, |r| Self::has_seats_for(r, members)) {
+            Some(match_id) => match_id,
+            None => pools.insert(key.clone(), MatchRoom {
+                id: Uuid::new_v4(),
+                required_players,
+                current_players: 0,
+                players: Vec::new(),
+                status: MatchStatus::Matching,
+                parties: Vec::new(),
+                platforms: HashMap::new(),
+                seats: HashMap::new(),
+                teams: Vec::new(),
+                selections: HashMap::new(),
+                lobby_closes_at: None,
+                private: None,
+            }),
+        };
+        pools.seat(match_id, tier);
+        let (_, room) = pools.get_mut(match_id).ok_or(Error::MatchNotFound)?;
+
+        room.players.extend_from_slice(members);
+        room.current_players += members.len() as i32;
+        room.platforms.extend(members.iter().filter_map(|m| Some((*m, *crossplay.platforms.get(m)?))));
+        let seated_at = Utc::now();
+        room.seats.extend(ratings.iter().map(|rating| (rating.user_id, Seat { seated_at, mmr: rating.mmr })));
+        // The returning tier is used up once the players are seated
+        if tier == QueueTier::Returning {
+            let mut returning = self.returning.lock().unwrap();
+            for member in members {
+                returning.remove(member);
             }
+        }
+        if premade {
+            room.parties.push(members.to_vec());
+        }
 
-            println!("创建队伍");
-            
-            // 2. Create teams
-            let team1_id = Uuid::new_v4();
-            let team2_id = Uuid::new_v4();
-            
-            match repo.create_team(team1_id, match_id, 1, players_per_team).await {
-                Ok(_) => println!("队伍1创建成功: {}", team1_id),
-                Err(e) => {
-                    println!("创建队伍1失败: {:?}", e);
-                    return Err(e);
-                }
+        // Check if room is full
+        if room.current_players == room.required_players {
+            println!("The room is ready. Let's begin the game.: {}", room.id);
+            room.status = MatchStatus::Ready;
+            self.spawn_start(room.id);
+        }
+
+        // A join that fills the room is announced by RoomReady alone, which
+        // carries the same roster
+        let snapshot = Self::room_snapshot(&key, room);
+        if snapshot.status == MatchStatus::Ready {
+            self.events.publish(MatchEvent::RoomReady {
+                room: snapshot.clone(),
+            });
+        } else {
+            for &user_id in members {
+                self.events.publish(MatchEvent::PlayerJoined {
+                    user_id,
+                    room: snapshot.clone(),
+                });
             }
-            
-            match repo.create_team(team2_id, match_id, 2, players_per_team).await {
-                Ok(_) => println!("队伍2创建成功: {}", team2_id),
-                Err(e) => {
-                    println!("创建队伍2失败: {:?}", e);
-                    return Err(e);
-                }
+        }
+
+        Ok(snapshot)
+    }
+
+    // Each party goes whole to the team with more seats left, largest parties
+    // first; solo players, and parties that no longer fit, fill the rest
+    fn split_teams(room: &MatchRoom, players_per_team: usize) -> (Vec<Uuid>, Vec<Uuid>) {
+        let mut rng = thread_rng();
+        let mut parties = room.parties.clone();
+        parties.shuffle(&mut rng);
+        parties.sort_by_key(|party| std::cmp::Reverse(party.len()));
+
+        let mut teams: [Vec<Uuid>; 2] = [Vec::new(), Vec::new()];
+        for party in parties {
+            let team = if teams[0].len() <= teams[1].len() { 0 } else { 1 };
+            if teams[team].len() + party.len() <= players_per_team {
+                teams[team].extend(party);
             }
-            
-            // 3. Randomly assign players to teams
-            let mut players = room.players.clone();
-            players.shuffle(&mut thread_rng());
-            
-            // Split players into two teams
-            let team1_players = &players[0..players_per_team as usize];
-            let team2_players = &players[players_per_team as usize..];
-            
-            // Add Team 1 members
-            for &player_id in team1_players {
-                repo.add_player_to_team(match_id, team1_id, player_id).await?;
+        }
+
+        let mut solos: Vec<Uuid> = room.players.iter()
+            .copied()
+            .filter(|p| !teams.iter().any(|team| team.contains(p)))
+            .collect();
+        solos.shuffle(&mut rng);
+        for player in solos {
+            let team = if teams[0].len() < players_per_team { 0 } else { 1 };
+            teams[team].push(player);
+        }
+
+        let [first, second] = teams;
+        (first, second)
+    }
+
+    // Start a ready match in the background
+    fn spawn_start(self: &Arc<Self>, match_id: Uuid) {
+        let match_service = self.clone();
+        supervisor::spawn("match_start", async move {
+            if let Err(e) = match_service.start_match(match_id).await {
+                eprintln!("Failed to start match {}: {:?}", match_id, e);
+                return;
             }
-            
-            // Add Team 2 members
-            for &player_id in team2_players {
-                repo.add_player_to_team(match_id, team2_id, player_id).await?;
+            if !match_service.lobby.duration.is_zero() {
+                match_service.close_lobby_after(match_id, match_service.lobby.duration).await;
             }
-            
-            // 4. Start the match
-            repo.start_match(match_id).await?;
-        }
-        
-        // 更新内存中的状态
-        {
-            let mut pools = self.match_pools.write().await;
-            if let Some(pool) = pools.get_mut(&match_type) {
-                if let Some(room) = pool.iter_mut().find(|r| r.id == match_id) {
-                    room.status = "playing".to_string();
-                }
+        });
+    }
+
+    // Start playing once the lobby has been open for `wait`; a match that
+    // can't be started is aborted rather than left in its lobby
+    async fn close_lobby_after(&self, match_id: Uuid, wait: Duration) {
+        tokio::time::sleep(wait).await;
+        if let Err(e) = self.begin_match(match_id).await {
+            tracing::error!("Failed to close the lobby of match {}: {}", match_id, e);
+            if let Err(e) = self.abort_match(match_id).await {
+                tracing::error!("Failed to abort match {}: {}", match_id, e);
             }
         }
+    }
 
-        // 在状态更新后立即广播
-        if let Some(handler) = &self.ws_handler {
-            println!("开始广播匹配开始消息，匹配ID: {}, 状态: playing", match_id);
-            
-            // 获取所有相关连接并打印
-            let conns = handler.conn_manager.get_connections_by_match(match_id).await;
-                println!("找到 {} 个需要广播的连接", conns.len());
-                for conn in &conns {
-                    println!("  连接ID: {}", conn);
+    // The player's rating; without the database everyone is rated like a new player
+    async fn player_rating(&self, user_id: Uuid) -> PlayerRating {
+        if self.db_health.is_available() {
+            match self.ratings.rating(user_id).await {
+                Ok(rating) => return rating,
+                Err(e) => {
+                    if matches!(e, Error::DbUnavailable) {
+                        self.mark_db_unreachable();
+                    }
+                    tracing::warn!("Failed to look up rating of user {}: {}", user_id, e);
                 }
-            
-            // 发送广播
-            match handler.broadcast_match_update(
-                match_id,
-                "playing",
-                &match_type,
-                room.required_players,
-                room.required_players
-            ).await {
-                Ok(_) => println!("成功广播匹配状态更新为playing"),
-                Err(e) => println!("广播匹配状态失败: {:?}", e)
             }
+        }
+        self.ratings.initial(user_id)
+    }
+
+    // Highest queue tier among the players joining together
+    fn queue_tier(&self, members: &[Uuid]) -> QueueTier {
+        let mut returning = self.returning.lock().unwrap();
+        let now = Instant::now();
+        returning.retain(|_, until| *until > now);
+        if members.iter().any(|m| returning.contains_key(m)) {
+            QueueTier::Returning
+        } else if members.iter().any(|m| self.priority.premium.contains(m)) {
+            QueueTier::Premium
         } else {
-            println!("警告: ws_handler未设置，无法广播状态更新");
+            QueueTier::Normal
         }
+    }
 
-        Ok(())
+    // Queue these players at the returning tier the next time they join,
+    // after the server cut them off
+    pub fn grant_return_priority(&self, users: &[Uuid]) {
+        let until = Instant::now() + self.priority.return_window;
+        let mut returning = self.returning.lock().unwrap();
+        for &user_id in users {
+            returning.insert(user_id, until);
+        }
     }
-    
-    // End a match
-    pub async fn end_match(&self, match_id: Uuid) -> Result<()> {
-        // Update in-memory state first
-        {
-            let pools = self.match_pools.read().await;
-            for (match_type, pool) in pools.iter() {
-                if let Some(_) = pool.iter().find(|r| r.id == match_id) {
-                    // Found the match, remove it after updating DB
-                    let mut pools = self.match_pools.write().await;
-                    if let Some(pool) = pools.get_mut(match_type) {
-                        pool.retain(|r| r.id != match_id);
+
+    // Refuse players past the node's load limits, rather than queueing them
+    // for matches it couldn't run
+    fn check_load(&self, pools: &MatchPools, match_type: &str, joining: usize) -> Result<()> {
+        let limits = &self.limits;
+        let (mut matches, mut of_type, mut queued) = (0, 0, 0);
+        for (key, room) in pools.rooms() {
+            match room.status {
+                MatchStatus::Ready | MatchStatus::Lobby | MatchStatus::Playing => {
+                    matches += 1;
+                    if key.match_type == match_type {
+                        of_type += 1;
                     }
-                    break;
                 }
+                MatchStatus::Matching => queued += room.players.len(),
+                _ => {}
             }
         }
-        
-        // Update database
-        if let Some(repo) = &self.get_repo() {
-            repo.end_match(match_id).await?;
-        }
-        
-        Ok(())
+
+        let reason = if limits.max_matches.is_some_and(|max| matches >= max) {
+            ShedReason::Matches
+        } else if limits.max_matches_per_type.get(match_type).is_some_and(|&max| of_type >= max) {
+            ShedReason::MatchType
+        } else if limits.max_queued_players.is_some_and(|max| queued + joining > max) {
+            ShedReason::Queue
+        } else if limits.max_pending_writes.is_some_and(|max| self.write_queue.len() >= max) {
+            // Every match played while the database is down adds to the queue
+            ShedReason::PendingWrites
+        } else {
+            return Ok(());
+        };
+
+        METRICS.record_shed(reason);
+        // Spread the retries of everyone refused at once
+        let base = limits.retry_after.as_secs().max(1);
+        let retry_after_secs = base + thread_rng().gen_range(0..=base / 2);
+        tracing::debug!("Refusing {} join, node busy ({:?})", match_type, reason);
+        Err(Error::ServerBusy { retry_after_secs })
     }
-    
-    // Record treasure discovery
-    pub async fn record_discovery(&self, match_id: Uuid, team_id: Uuid, user_id: Uuid, treasure_id: Uuid, score: i32) -> Result<()> {
-        if let Some(repo) = &self.get_repo() {
-            repo.record_discovery(match_id, team_id, user_id, treasure_id, score).await?;
+
+    fn has_seats_for(room: &MatchRoom, members: &[Uuid]) -> bool {
+        room.status == MatchStatus::Matching
+            && room.required_players - room.current_players >= members.len() as i32
+            && !members.iter().any(|m| room.players.contains(m))
+    }
+
+    fn pool_has_seat(pools: &MatchPools, key: &PoolKey, members: &[Uuid]) -> bool {
+        pools.find(key, |r| Self::has_seats_for(r, members)).is_some()
+    }
+
+    // Prefer the player's own bracket; otherwise the nearest bracket within
+    // `spread` that has a seat free
+    fn widen_bracket(pools: &MatchPools, key: PoolKey, spread: i32, members: &[Uuid]) -> PoolKey {
+        if Self::pool_has_seat(pools, &key, members) {
+            return key;
         }
-        
-        Ok(())
+        (1..=spread)
+            .flat_map(|distance| [key.bracket - distance, key.bracket + distance])
+            .map(|bracket| PoolKey { bracket, ..key.clone() })
+            .find(|candidate| Self::pool_has_seat(pools, candidate, members))
+            .unwrap_or(key)
     }
-    
-    // Get full match details
-    pub async fn get_match_details(&self, match_id: Uuid) -> Result<crate::models::game::MatchDetails> {
-        if let Some(repo) = &self.get_repo() {
-            // Use the repository method that already handles this
-            return repo.get_match_details(match_id).await;
+
+    // New players stay in the protected pool until they have finished
+    // NEW_PLAYER_MATCHES matches, or their account is older than
+    // NEW_PLAYER_MAX_ACCOUNT_DAYS. Without the database everyone joins the main pool.
+    async fn is_new_player(&self, user_id: Uuid) -> bool {
+        if self.new_players.protected_matches == 0 || !self.db_health.is_available() {
+            return false;
+        }
+        let experience = match self.repo.player_experience(user_id).await {
+            Ok(experience) => experience,
+            Err(e) => {
+                if matches!(e, Error::DbUnavailable) {
+                    self.mark_db_unreachable();
+                }
+                tracing::warn!("Failed to look up experience of user {}: {}", user_id, e);
+                return false;
+            }
+        };
+        if experience.finished_matches >= self.new_players.protected_matches {
+            return false;
+        }
+        match (self.new_players.max_account_age, experience.account_created_at) {
+            (Some(max_age), Some(created_at)) => chrono::Duration::from_std(max_age)
+                .is_ok_and(|max_age| Utc::now() - created_at < max_age),
+            _ => true,
         }
-        
-        Err(Error::MatchNotFound)
     }
-}
\ No newline at end of file
+
+    // Pad protected rooms that have waited NEW_PLAYER_BOT_FILL_SECS with bot
+    // accounts, so new players aren't left queueing when few of them are online
+    fn spawn_bot_filler(self: Arc<Self>) {
+        supervisor::spawn_loop("bot_filler", move || {
+            let service = self.clone();
+            async move {
+                // When each waiting protected room was first seen with players in it
+                let mut waiting: HashMap<Uuid, Instant> = HashMap::new();
+                let mut interval = tokio::time::interval(BOT_FILL_CHECK_INTERVAL);
+                loop {
+                    interval.tick().await;
+                    service.fill_with_bots(&mut waiting).await;
+                }
+            }
+        });
+    }
+
+    async fn fill_with_bots(self: &Arc<Self>, waiting: &mut HashMap<Uuid, Instant>) {
+        let now = Instant::now();
+        let mut still_waiting = HashMap::new();
+        let mut pools = self.match_pools.write().await;
+
+        // A bot plays one match at a time on this instance
+        let mut busy: HashSet<Uuid> = pools.rooms()
+            .flat_map(|(_, r)| r.players.iter().copied())
+            .collect();
+
+        let waiting_rooms = pools.rooms_mut()
+            .filter(|(key, r)| key.protected && r.status == MatchStatus::Matching && r.current_players > 0);
+        for (key, room) in waiting_rooms {
+            let since = waiting.get(&room.id).copied().unwrap_or(now);
+            if now.duration_since(since) < self.new_players.bot_fill_after {
+                still_waiting.insert(room.id, since);
+                continue;
+            }
+
+            let missing = (room.required_players - room.current_players) as usize;
+            let bots: Vec<Uuid> = self.new_players.bots.iter()
+                .filter(|bot| !busy.contains(bot))
+                .take(missing)
+                .copied()
+                .collect();
+            if bots.len() < missing {
+                tracing::warn!("Not enough free bots to fill protected room {}", room.id);
+                still_waiting.insert(room.id, since);
+                continue;
+            }
+
+            // The bots fill the room, so only RoomReady is published
+            for bot in bots {
+                busy.insert(bot);
+                room.players.push(bot);
+                room.current_players += 1;
+            }
+            tracing::info!("Filled protected room {} with {} bots", room.id, missing);
+            room.status = MatchStatus::Ready;
+            self.events.publish(MatchEvent::RoomReady {
+                room: Self::room_snapshot(key, room),
+            });
+            self.spawn_start(room.id);
+        }
+
+        *waiting = still_waiting;
+    }
+
+    // Leave a match
+    pub async fn leave_match(&self, user_id: Uuid, match_id: Uuid) -> Result<()> {
+        if self.leave_room(user_id, match_id).await? {
+            self.queue_leases.release(&[user_id]).await;
+        }
+        Ok(())
+    }
+
+    // Take the player out of the room; false if they weren't in it
+    async fn leave_room(&self, user_id: Uuid, match_id: Uuid) -> Result<bool> {
+        let mut pools = self.match_pools.write().await;
+        let (key, room) = pools.get_mut(match_id).ok_or(Error::MatchNotFound)?;
+        
+        // Only allow leaving if match hasn't started
+        if room.status != MatchStatus::Matching {
+            return Err(Error::MatchAlreadyStarted);
+        }
+        
+        if !room.players.contains(&user_id) {
+            return Ok(false);
+        }
+        self.unseat(key, room, user_id);
+
+        // Private rooms go away with their last player
+        if room.private.is_some() && room.current_players == 0 {
+            pools.remove(match_id);
+            return Ok(true);
+        }
+
+        // Recycle empty rooms if above minimum count
+        if room.current_players == 0 {
+            let key = key.clone();
+            let min_count = self.min_room_count.get(&key.match_type).copied().unwrap_or(0);
+            if pools.count(&key, |r| r.current_players == 0) > min_count {
+                pools.remove(match_id);
+            }
+        }
+        Ok(true)
+    }
+
+    // Take a player out of a room that hasn't started; in a private room
+    // their kick votes are dropped and the host role passes on if it was theirs
+    fn unseat(&self, key: &PoolKey, room: &mut MatchRoom, user_id: Uuid) {
+        room.players.retain(|&p| p != user_id);
+        room.current_players -= 1;
+        room.platforms.remove(&user_id);
+        room.seats.remove(&user_id);
+        // The rest of a party stays queued; a lone member is a solo player again
+        for party in room.parties.iter_mut() {
+            party.retain(|&p| p != user_id);
+        }
+        room.parties.retain(|party| party.len() > 1);
+        if let Some(private) = room.private.as_mut() {
+            private.kick_votes.remove(&user_id);
+            for voters in private.kick_votes.values_mut() {
+                voters.remove(&user_id);
+            }
+            private.teams.remove(&user_id);
+            private.switch_requests.retain(|&p| p != user_id);
+        }
+        self.events.publish(MatchEvent::PlayerLeft {
+            user_id,
+            room: Self::room_snapshot(key, room),
+        });
+        if room.private.is_some() {
+            self.events.publish(MatchEvent::TeamsChanged { teams: Self::lobby_teams(room) });
+        }
+        if let Some(next) = room.players.first().copied() {
+            self.transfer_host(room, user_id, next);
+        }
+    }
+
+    // Remove a player from a private room that hasn't started. The host's
+    // kick takes effect at once; anyone else's counts as a vote, and the
+    // player goes once more than half of the others voted. `block` keeps the
+    // player from joining again (for a vote, when most voters asked for it).
+    pub async fn kick_private(&self, user_id: Uuid, match_id: Uuid, target: Uuid, block: bool) -> Result<KickVote> {
+        let mut pools = self.match_pools.write().await;
+        let (key, room) = pools.get_mut(match_id).ok_or(Error::MatchNotFound)?;
+        let private = room.private.as_mut().ok_or_else(|| Error::PermissionDenied("not a private lobby".to_string()))?;
+        if room.status != MatchStatus::Matching {
+            return Err(Error::MatchAlreadyStarted);
+        }
+        if !room.players.contains(&user_id) || !room.players.contains(&target) {
+            return Err(Error::PermissionDenied("not in this lobby".to_string()));
+        }
+        if user_id == target {
+            return Err(Error::PermissionDenied("use match.cancel to leave".to_string()));
+        }
+
+        let needed = (room.players.len() - 1) / 2 + 1;
+        let (votes, block) = if private.host == user_id {
+            (needed, block)
+        } else {
+            let voters = private.kick_votes.entry(target).or_default();
+            voters.insert(user_id, block);
+            let blocking = voters.values().filter(|block| **block).count();
+            (voters.len(), blocking * 2 > voters.len())
+        };
+        let vote = KickVote {
+            match_id,
+            target,
+            votes,
+            needed,
+            kicked: votes >= needed,
+        };
+        if !vote.kicked {
+            self.events.publish(MatchEvent::KickVoted { vote: vote.clone() });
+            return Ok(vote);
+        }
+
+        if block {
+            private.blocked.push(target);
+        }
+        tracing::info!("User {} was kicked from private room {}", target, match_id);
+        self.unseat(key, room, target);
+        self.events.publish(MatchEvent::PlayerKicked { match_id, user_id: target, blocked: block });
+        drop(pools);
+        self.queue_leases.release(&[target]).await;
+        Ok(vote)
+    }
+
+    // Move a player of a private room to team 1 or 2 before it starts. The
+    // host may move anyone, other players only themselves. A team is full at
+    // half the room; moving onto it then swaps with its oldest player asking
+    // to switch, or else leaves a player's own request waiting for one.
+    pub async fn switch_team(&self, user_id: Uuid, match_id: Uuid, player: Uuid, team: i32) -> Result<LobbyTeams> {
+        let mut pools = self.match_pools.write().await;
+        let (_, room) = pools.get_mut(match_id).ok_or(Error::MatchNotFound)?;
+        let private = room.private.as_mut().ok_or_else(|| Error::PermissionDenied("not a private lobby".to_string()))?;
+        if room.status != MatchStatus::Matching {
+            return Err(Error::MatchAlreadyStarted);
+        }
+        if !(1..=2).contains(&team) {
+            return Err(Error::InvalidMessage);
+        }
+        if player != user_id && private.host != user_id {
+            return Err(Error::PermissionDenied("only the host can move other players".to_string()));
+        }
+        let current = *private.teams.get(&player).ok_or_else(|| Error::PermissionDenied("not in this lobby".to_string()))?;
+
+        if current == team {
+            private.switch_requests.retain(|&p| p != player);
+        } else if private.teams.values().filter(|&&t| t == team).count() < (room.required_players / 2) as usize {
+            private.teams.insert(player, team);
+            private.switch_requests.retain(|&p| p != player);
+        } else if let Some(partner) = private.switch_requests.iter().copied().find(|p| private.teams.get(p) == Some(&team)) {
+            private.teams.insert(player, team);
+            private.teams.insert(partner, current);
+            private.switch_requests.retain(|&p| p != player && p != partner);
+        } else if player == user_id {
+            if !private.switch_requests.contains(&player) {
+                private.switch_requests.push(player);
+            }
+        } else {
+            return Err(Error::TeamFull);
+        }
+
+        let teams = Self::lobby_teams(room);
+        self.events.publish(MatchEvent::TeamsChanged { teams: teams.clone() });
+        Ok(teams)
+    }
+
+    fn lobby_teams(room: &MatchRoom) -> LobbyTeams {
+        let (first, second) = Self::private_teams(room);
+        LobbyTeams {
+            match_id: room.id,
+            teams: vec![first, second],
+            switch_requests: room.private.as_ref().map(|p| p.switch_requests.clone()).unwrap_or_default(),
+        }
+    }
+
+    // Players of teams 1 and 2 of a private room, in the order they joined
+    fn private_teams(room: &MatchRoom) -> (Vec<Uuid>, Vec<Uuid>) {
+        let team_of = |player: &Uuid| room.private.as_ref().and_then(|p| p.teams.get(player).copied());
+        room.players.iter().copied().partition(|player| team_of(player) != Some(2))
+    }
+
+    // Open a private room for `match_type` with the user as its host. Other
+    // players join with its invite code, and it starts when the host says so.
+    pub async fn create_private(&self, user_id: Uuid, match_type: &str, crossplay: &CrossPlay) -> Result<PrivateLobby> {
+        let required_players = self.get_required_players(match_type)?;
+        let guard = self.acquire_join_lock(user_id).await?;
+        let result = self.with_queue_leases(&[user_id], async {
+            self.check_can_queue(user_id, crossplay).await?;
+            let rating = self.player_rating(user_id).await;
+
+            let mut pools = self.match_pools.write().await;
+            Self::ensure_not_queued(&pools, user_id)?;
+            self.check_load(&pools, match_type, 1)?;
+            let match_id = self.insert_private(&mut pools, match_type, required_players, user_id, crossplay.pool);
+            self.seat_private(&mut pools, match_id, &rating, crossplay)
+        }).await;
+        self.release_join_locks(vec![(user_id, guard)]).await;
+        result
+    }
+
+    // Open an empty private room for a scheduled match; the host joins with
+    // its invite code like everyone else
+    pub async fn open_scheduled(&self, match_type: &str, host: Uuid) -> Result<PrivateLobby> {
+        let required_players = self.get_required_players(match_type)?;
+        self.ensure_accepting_matches()?;
+        let mut pools = self.match_pools.write().await;
+        self.check_load(&pools, match_type, 0)?;
+        let match_id = self.insert_private(&mut pools, match_type, required_players, host, PlatformPool::Any);
+        let (key, room) = pools.get(match_id).ok_or(Error::MatchNotFound)?;
+        Self::private_lobby(key, room)
+    }
+
+    // Start a solo tutorial match for the user against a bot account. The
+    // room sits in a private pool of its own and the match is never rated.
+    pub async fn start_tutorial(self: &Arc<Self>, user_id: Uuid, bot: Uuid) -> Result<MatchResult> {
+        self.ensure_accepting_matches()?;
+        let guard = self.acquire_join_lock(user_id).await?;
+        let result = self.with_queue_leases(&[user_id], async {
+            self.check_can_queue(user_id, &CrossPlay::default()).await?;
+
+            let mut pools = self.match_pools.write().await;
+            Self::ensure_not_queued(&pools, user_id)?;
+            self.check_load(&pools, TUTORIAL_MATCH_TYPE, 1)?;
+            let key = PoolKey {
+                match_type: TUTORIAL_MATCH_TYPE.to_string(),
+                zone_id: None,
+                suspected: false,
+                quarantined: false,
+                protected: false,
+                bracket: self.ratings.initial_bracket(),
+                premade: false,
+                platforms: PlatformPool::Any,
+                private: true,
+            };
+            let match_id = pools.insert(key, MatchRoom {
+                id: Uuid::new_v4(),
+                required_players: 2,
+                current_players: 2,
+                players: vec![user_id, bot],
+                status: MatchStatus::Ready,
+                parties: Vec::new(),
+                platforms: HashMap::new(),
+                seats: HashMap::new(),
+                teams: Vec::new(),
+                selections: HashMap::new(),
+                lobby_closes_at: None,
+                private: None,
+            });
+            let (key, room) = pools.get(match_id).ok_or(Error::MatchNotFound)?;
+            let snapshot = Self::room_snapshot(key, room);
+            self.events.publish(MatchEvent::RoomReady {
+                room: snapshot.clone(),
+            });
+            self.spawn_start(match_id);
+            Ok(snapshot)
+        }).await;
+        self.release_join_locks(vec![(user_id, guard)]).await;
+        result
+    }
+
+    // Start a scheduled match's room at its start time with whoever is in it.
+    // Returns false, after closing the room, when fewer than `min_players`
+    // joined; a room its host already started counts as started.
+    pub async fn start_scheduled(self: &Arc<Self>, match_id: Uuid, min_players: i32) -> Result<bool> {
+        let mut pools = self.match_pools.write().await;
+        let Some((key, room)) = pools.get_mut(match_id) else {
+            return Ok(false);
+        };
+        if room.status != MatchStatus::Matching {
+            return Ok(true);
+        }
+        if room.current_players < min_players {
+            tracing::info!("Closing scheduled room {} with {} of {} players", match_id, room.current_players, min_players);
+            pools.remove(match_id);
+            return Ok(false);
+        }
+
+        room.status = MatchStatus::Ready;
+        self.events.publish(MatchEvent::RoomReady {
+            room: Self::room_snapshot(key, room),
+        });
+        self.spawn_start(match_id);
+        Ok(true)
+    }
+
+    fn insert_private(&self, pools: &mut MatchPools, match_type: &str, required_players: i32, host: Uuid, platforms: PlatformPool) -> Uuid {
+        let key = PoolKey {
+            match_type: match_type.to_string(),
+            zone_id: None,
+            suspected: false,
+            quarantined: false,
+            protected: false,
+            bracket: self.ratings.initial_bracket(),
+            premade: false,
+            platforms,
+            private: true,
+        };
+        let code = loop {
+            let code = invite_code();
+            if !pools.rooms().any(|(_, r)| r.private.as_ref().is_some_and(|p| p.code == code)) {
+                break code;
+            }
+        };
+        pools.insert(key, MatchRoom {
+            id: Uuid::new_v4(),
+            required_players,
+            current_players: 0,
+            players: Vec::new(),
+            status: MatchStatus::Matching,
+            parties: Vec::new(),
+            platforms: HashMap::new(),
+            seats: HashMap::new(),
+            teams: Vec::new(),
+            selections: HashMap::new(),
+            lobby_closes_at: None,
+            private: Some(PrivateRoom {
+                code,
+                host,
+                blocked: Vec::new(),
+                kick_votes: HashMap::new(),
+                teams: HashMap::new(),
+                switch_requests: Vec::new(),
+            }),
+        })
+    }
+
+    // Join the private room with this invite code
+    pub async fn join_private(&self, user_id: Uuid, code: &str, crossplay: &CrossPlay) -> Result<PrivateLobby> {
+        let guard = self.acquire_join_lock(user_id).await?;
+        let result = self.with_queue_leases(&[user_id], async {
+            self.check_can_queue(user_id, crossplay).await?;
+            let rating = self.player_rating(user_id).await;
+
+            let mut pools = self.match_pools.write().await;
+            Self::ensure_not_queued(&pools, user_id)?;
+            let (key, room) = pools.rooms()
+                .find(|(_, r)| r.private.as_ref().is_some_and(|p| p.code.eq_ignore_ascii_case(code.trim())))
+                .ok_or_else(|| Error::NotFound("private lobby".to_string()))?;
+            if room.status != MatchStatus::Matching {
+                return Err(Error::MatchAlreadyStarted);
+            }
+            if room.private.as_ref().is_some_and(|p| p.blocked.contains(&user_id)) {
+                return Err(Error::PermissionDenied("you were removed from this lobby".to_string()));
+            }
+            if room.current_players >= room.required_players {
+                return Err(Error::TeamFull);
+            }
+            let (match_type, match_id) = (key.match_type.clone(), room.id);
+            self.check_load(&pools, &match_type, 1)?;
+            self.seat_private(&mut pools, match_id, &rating, crossplay)
+        }).await;
+        self.release_join_locks(vec![(user_id, guard)]).await;
+        result
+    }
+
+    // Start a full private room; only its host may
+    pub async fn start_private(self: &Arc<Self>, user_id: Uuid, match_id: Uuid) -> Result<MatchResult> {
+        let mut pools = self.match_pools.write().await;
+        let (key, room) = pools.get_mut(match_id).ok_or(Error::MatchNotFound)?;
+        Self::ensure_host(room, user_id)?;
+        if room.status != MatchStatus::Matching {
+            return Err(Error::MatchAlreadyStarted);
+        }
+        if room.current_players < room.required_players {
+            return Err(Error::MatchNotReady);
+        }
+
+        room.status = MatchStatus::Ready;
+        let snapshot = Self::room_snapshot(key, room);
+        self.events.publish(MatchEvent::RoomReady {
+            room: snapshot.clone(),
+        });
+        self.spawn_start(match_id);
+        Ok(snapshot)
+    }
+
+    // Hand a private room over to the longest-waiting player still connected
+    // when its host drops off; `connected` lists the room's connected players
+    pub async fn host_disconnected(&self, user_id: Uuid, match_id: Uuid, connected: &[Uuid]) {
+        let mut pools = self.match_pools.write().await;
+        let Some((_, room)) = pools.get_mut(match_id) else {
+            return;
+        };
+        if room.status != MatchStatus::Matching {
+            return;
+        }
+        if let Some(next) = room.players.iter().copied().find(|p| *p != user_id && connected.contains(p)) {
+            self.transfer_host(room, user_id, next);
+        }
+    }
+
+    // Make `next` the host of the room if `previous` was
+    fn transfer_host(&self, room: &mut MatchRoom, previous: Uuid, next: Uuid) {
+        let Some(private) = room.private.as_mut().filter(|p| p.host == previous) else {
+            return;
+        };
+        private.host = next;
+        tracing::info!("User {} took over private room {} from {}", next, room.id, previous);
+        self.events.publish(MatchEvent::HostChanged {
+            match_id: room.id,
+            host: next,
+            previous,
+        });
+    }
+
+    fn ensure_host(room: &MatchRoom, user_id: Uuid) -> Result<()> {
+        match &room.private {
+            Some(private) if private.host == user_id => Ok(()),
+            Some(_) => Err(Error::PermissionDenied("only the host can do this".to_string())),
+            None => Err(Error::PermissionDenied("not a private lobby".to_string())),
+        }
+    }
+
+    async fn acquire_join_lock(&self, user_id: Uuid) -> Result<LockGuard> {
+        let lock_key = format!("spv:lock:join:{}", user_id);
+        self.join_lock.try_acquire(&lock_key, JOIN_LOCK_TTL).await?.ok_or(Error::JoinInProgress)
+    }
+
+    // The checks join_match runs before seating a player
+    async fn check_can_queue(&self, user_id: Uuid, crossplay: &CrossPlay) -> Result<()> {
+        self.bans.ensure_not_banned(user_id).await?;
+        if !crossplay.pool.admits(crossplay.platforms.get(&user_id).copied()) {
+            return Err(Error::PlatformNotAllowed(format!("{} is not on a mobile platform", user_id)));
+        }
+        if self.db_health.is_available() {
+            match self.repo.is_user_in_match(user_id).await {
+                Ok(Some(_)) => return Err(Error::UserAlreadyInMatch),
+                Ok(None) => {}
+                Err(Error::DbUnavailable) => self.mark_db_unreachable(),
+                Err(e) => return Err(e),
+            }
+        }
+        self.ensure_accepting_matches()
+    }
+
+    fn ensure_not_queued(pools: &MatchPools, user_id: Uuid) -> Result<()> {
+        let queued = pools.rooms()
+            .any(|(_, r)| matches!(r.status, MatchStatus::Matching | MatchStatus::Ready) && r.players.contains(&user_id));
+        if queued {
+            return Err(Error::UserAlreadyInMatch);
+        }
+        Ok(())
+    }
+
+    fn seat_private(&self, pools: &mut MatchPools, match_id: Uuid, rating: &PlayerRating, crossplay: &CrossPlay) -> Result<PrivateLobby> {
+        let user_id = rating.user_id;
+        let (key, room) = pools.get_mut(match_id).ok_or(Error::MatchNotFound)?;
+        // Onto the smaller team
+        let (first, second) = Self::private_teams(room);
+        if let Some(private) = room.private.as_mut() {
+            private.teams.insert(user_id, if second.len() < first.len() { 2 } else { 1 });
+        }
+        room.players.push(user_id);
+        room.current_players += 1;
+        if let Some(platform) = crossplay.platforms.get(&user_id) {
+            room.platforms.insert(user_id, *platform);
+        }
+        room.seats.insert(user_id, Seat { seated_at: Utc::now(), mmr: rating.mmr });
+        self.events.publish(MatchEvent::PlayerJoined {
+            user_id,
+            room: Self::room_snapshot(key, room),
+        });
+        self.events.publish(MatchEvent::TeamsChanged { teams: Self::lobby_teams(room) });
+        Self::private_lobby(key, room)
+    }
+
+    fn private_lobby(key: &PoolKey, room: &MatchRoom) -> Result<PrivateLobby> {
+        let private = room.private.as_ref().ok_or(Error::MatchNotFound)?;
+        Ok(PrivateLobby {
+            match_id: room.id,
+            match_type: key.match_type.clone(),
+            code: private.code.clone(),
+            host: private.host,
+            players: room.players.clone(),
+            required_players: room.required_players,
+            teams: Self::lobby_teams(room).teams,
+        })
+    }
+
+    // Active match (queued, ready or playing) the user belongs to, if any
+    pub async fn active_match_for_user(&self, user_id: Uuid) -> Result<Option<Uuid>> {
+        {
+            let pools = self.match_pools.read().await;
+            let room = pools.rooms()
+                .map(|(_, r)| r)
+                .find(|r| r.status != MatchStatus::Finished && r.players.contains(&user_id));
+            if let Some(room) = room {
+                return Ok(Some(room.id));
+            }
+        }
+        
+        self.repo.is_user_in_match(user_id).await
+    }
+
+    // Get match status
+    pub async fn get_match_status(&self, match_id: Uuid) -> Result<MatchStatus> {
+        // First check in-memory pools
+        if let Some((_, room)) = self.match_pools.read().await.get(match_id) {
+            return Ok(room.status);
+        }
+        
+        // If not found in memory, check database
+        match self.repo.get_match(match_id).await {
+            Ok(room) => Ok(room.status),
+            Err(Error::MatchNotFound) => Err(Error::MatchNotFound),
+            Err(e) => {
+                println!("Failed to load match {} status from database: {:?}", match_id, e);
+                Err(Error::MatchNotFound)
+            }
+        }
+    }
+
+    // Start a match
+    pub async fn start_match(&self, match_id: Uuid) -> Result<()> {
+        // Find match room
+        let (key, room) = match self.match_pools.read().await.get(match_id) {
+            Some((key, room)) => (key.clone(), room.clone()),
+            None => return Err(Error::MatchNotFound),
+        };
+        
+        // Verify room is ready
+        if room.status != MatchStatus::Ready {
+            return Err(Error::MatchNotReady);
+        }
+        
+        // Calculate players per team
+        let players_per_team = room.required_players / 2;
+        
+        // This node runs the match from now on
+        if !self.ownership.claim(match_id).await? {
+            return Err(Error::ClusterError(format!("match {} is owned by another node", match_id)));
+        }
+        
+        // Randomly assign players to teams, keeping parties together; private
+        // rooms keep the teams picked before the start
+        let (first, second) = if room.private.is_some() {
+            Self::private_teams(&room)
+        } else {
+            Self::split_teams(&room, players_per_team as usize)
+        };
+        
+        let teams = vec![
+            TeamAssignment {
+                team_id: Uuid::new_v4(),
+                team_number: 1,
+                players: first,
+            },
+            TeamAssignment {
+                team_id: Uuid::new_v4(),
+                team_number: 2,
+                players: second,
+            },
+        ];
+        let strengths = self.team_strengths(&teams, &room.seats);
+        // Tutorials start right away
+        let lobby = !self.lobby.duration.is_zero() && key.match_type != TUTORIAL_MATCH_TYPE;
+        
+        // Persist the match (queued while the database is offline)
+        let persisted = self.persist(PendingWrite::StartMatch {
+            match_id,
+            match_type: key.match_type.clone(),
+            players_per_team,
+            teams: teams.clone(),
+            platforms: room.platforms.clone(),
+            strengths,
+            victory: self.victory.condition_for(&key.match_type),
+            lobby,
+        }).await;
+        if let Err(e) = persisted {
+            if let Err(release_err) = self.ownership.release(match_id).await {
+                tracing::warn!("Failed to release ownership of match {}: {}", match_id, release_err);
+            }
+            return Err(e);
+        }
+
+        if lobby {
+            self.open_lobby(&key, room, teams).await;
+        } else {
+            self.mark_playing(&key, room, teams).await;
+        }
+        Ok(())
+    }
+
+    // Keep the players in the lobby with their teams until it closes
+    async fn open_lobby(&self, key: &PoolKey, mut room: MatchRoom, teams: Vec<TeamAssignment>) {
+        let closes_at = Utc::now() + chrono::Duration::from_std(self.lobby.duration).unwrap_or_default();
+        room.status = MatchStatus::Lobby;
+        room.teams = teams.clone();
+        room.lobby_closes_at = Some(closes_at);
+        if let Some((_, pooled)) = self.match_pools.write().await.get_mut(room.id) {
+            pooled.status = MatchStatus::Lobby;
+            pooled.teams = teams.clone();
+            pooled.lobby_closes_at = Some(closes_at);
+        }
+        self.events.publish(MatchEvent::LobbyOpened {
+            room: Self::room_snapshot(key, &room),
+            teams,
+            closes_at,
+        });
+    }
+
+    // Close the lobby of a match and start playing it
+    pub async fn begin_match(&self, match_id: Uuid) -> Result<()> {
+        let (key, room) = match self.match_pools.read().await.get(match_id) {
+            Some((key, room)) => (key.clone(), room.clone()),
+            None => return Err(Error::MatchNotFound),
+        };
+        if room.status != MatchStatus::Lobby {
+            return Err(Error::MatchNotReady);
+        }
+
+        self.persist(PendingWrite::BeginMatch { match_id }).await?;
+        let teams = room.teams.clone();
+        self.mark_playing(&key, room, teams).await;
+        Ok(())
+    }
+
+    async fn mark_playing(&self, key: &PoolKey, mut room: MatchRoom, teams: Vec<TeamAssignment>) {
+        // 更新内存中的状态
+        if let Some((_, pooled)) = self.match_pools.write().await.get_mut(room.id) {
+            pooled.status = MatchStatus::Playing;
+        }
+
+        self.fairness.record_start(room.id, &key.match_type, &teams, &room.seats);
+
+        // 在状态更新后立即通知订阅者
+        room.status = MatchStatus::Playing;
+        self.events.publish(MatchEvent::MatchStarted {
+            room: Self::room_snapshot(key, &room),
+            teams,
+        });
+    }
+
+    // A player's lobby pick. The loadout and role must be on offer, and each
+    // role can be taken by one player per team; leaving a field out clears it.
+    pub async fn select_loadout(&self, match_id: Uuid, user_id: Uuid, selection: LobbySelection) -> Result<LobbySelection> {
+        let team = {
+            let mut pools = self.match_pools.write().await;
+            let (_, room) = pools.get_mut(match_id).ok_or(Error::MatchNotFound)?;
+            if room.status != MatchStatus::Lobby {
+                return Err(Error::MatchNotReady);
+            }
+            let team = room.teams
+                .iter()
+                .find(|team| team.players.contains(&user_id))
+                .ok_or_else(|| Error::PermissionDenied("not in this match".to_string()))?
+                .players
+                .clone();
+
+            if let Some(loadout) = selection.loadout.as_ref().filter(|l| !self.lobby.loadouts.contains(l)) {
+                return Err(Error::InvalidSelection(format!("unknown loadout {}", loadout)));
+            }
+            if let Some(role) = &selection.role {
+                if !self.lobby.roles.contains(role) {
+                    return Err(Error::InvalidSelection(format!("unknown role {}", role)));
+                }
+                let taken = team
+                    .iter()
+                    .filter(|player| **player != user_id)
+                    .any(|player| room.selections.get(player).and_then(|s| s.role.as_ref()) == Some(role));
+                if taken {
+                    return Err(Error::InvalidSelection(format!("role {} is already taken", role)));
+                }
+            }
+            room.selections.insert(user_id, selection.clone());
+            team
+        };
+
+        self.persist(PendingWrite::LobbySelection { match_id, user_id, selection: selection.clone() }).await?;
+        self.events.publish(MatchEvent::LobbySelected { match_id, user_id, selection: selection.clone(), team });
+        Ok(selection)
+    }
+    
+    // Average MMR of each team from the ratings its members were seated with,
+    // and its predicted chance to win. Bots have no seat and count as new
+    // players.
+    fn team_strengths(&self, teams: &[TeamAssignment], seats: &HashMap<Uuid, Seat>) -> HashMap<Uuid, TeamStrength> {
+        let averages: Vec<(Uuid, f64)> = teams
+            .iter()
+            .filter_map(|team| {
+                let mmr = mean(team.players.iter().map(|user_id| {
+                    seats.get(user_id).map_or_else(|| self.ratings.initial(*user_id).mmr, |seat| seat.mmr)
+                }))?;
+                Some((team.team_id, mmr))
+            })
+            .collect();
+        match averages.as_slice() {
+            [(first_id, first), (second_id, second)] => {
+                let win_probability = self.ratings.win_probability(*first, *second);
+                HashMap::from([
+                    (*first_id, TeamStrength { mmr: *first, win_probability }),
+                    (*second_id, TeamStrength { mmr: *second, win_probability: 1.0 - win_probability }),
+                ])
+            }
+            _ => HashMap::new(),
+        }
+    }
+
+    // Create the match, its teams and members, then mark it as playing
+    // (matches with a lobby are marked when it closes). A start replayed after
+    // an outage may find part of it written already; that part is skipped.
+    #[allow(clippy::too_many_arguments)]
+    async fn persist_match_start(
+        repo: &dyn MatchRepository,
+        match_id: Uuid,
+        match_type: &str,
+        players_per_team: i32,
+        teams: &[TeamAssignment],
+        platforms: &HashMap<Uuid, Platform>,
+        strengths: &HashMap<Uuid, TeamStrength>,
+        victory: VictoryCondition,
+        lobby: bool,
+    ) -> Result<()> {
+        println!("Create match's record: {}", match_id);
+        
+        // 1. Create match record in database
+        let written = match repo.create_match(match_id, match_type, players_per_team, victory).await {
+            Ok(_) => {
+                println!("匹配记录创建成功");
+                Vec::new()
+            }
+            Err(Error::DuplicateKey(_)) => {
+                println!("匹配记录已存在, 继续写入: {}", match_id);
+                repo.get_match_teams(match_id).await?
+            }
+            Err(e) => {
+                println!("创建匹配记录失败: {:?}", e);
+                return Err(e);
+            }
+        };
+
+        println!("创建队伍");
+        
+        // 2. Create teams and add their members
+        for team in teams {
+            let stored = written.iter().find(|stored| stored.id == team.team_id);
+            if stored.is_none() {
+                match repo.create_team(team.team_id, match_id, team.team_number, players_per_team, strengths.get(&team.team_id).copied()).await {
+                    Ok(_) => println!("队伍{}创建成功: {}", team.team_number, team.team_id),
+                    Err(e) => {
+                        println!("创建队伍{}失败: {:?}", team.team_number, e);
+                        return Err(e);
+                    }
+                }
+            }
+            
+            for &player_id in &team.players {
+                if stored.is_some_and(|stored| stored.members.iter().any(|member| member.user_id == player_id)) {
+                    continue;
+                }
+                let platform = platforms.get(&player_id).copied();
+                repo.add_player_to_team(match_id, team.team_id, player_id, platform).await?;
+            }
+        }
+        
+        // 3. Start the match, unless it has a lobby to go through first
+        if !lobby {
+            repo.start_match(match_id).await?;
+        }
+        
+        Ok(())
+    }
+    
+    // End a match
+    pub async fn end_match(&self, match_id: Uuid) -> Result<()> {
+        // Update in-memory state first
+        let removed = self.match_pools.write().await.remove(match_id);
+        let unrated = removed.is_some_and(|(key, _)| key.match_type == TUTORIAL_MATCH_TYPE);
+        
+        // Update database
+        self.persist(PendingWrite::EndMatch { match_id, unrated }).await?;
+        
+        self.events.publish(MatchEvent::MatchEnded { match_id });
+        
+        if let Err(e) = self.ownership.release(match_id).await {
+            tracing::warn!("Failed to release ownership of match {}: {}", match_id, e);
+        }
+        
+        Ok(())
+    }
+
+    // End a match on the server's initiative; its players get the returning
+    // tier for their next match
+    pub async fn abort_match(&self, match_id: Uuid) -> Result<()> {
+        let players = self.match_pools.read().await
+            .get(match_id)
+            .map(|(_, room)| room.players.clone())
+            .unwrap_or_default();
+        self.end_match(match_id).await?;
+        self.grant_return_priority(&players);
+        Ok(())
+    }
+
+    // Node running the match, when that is another node. Without an answer
+    // the match is handled here.
+    pub async fn remote_owner(&self, match_id: Uuid) -> Option<String> {
+        match self.ownership.remote_owner(match_id).await {
+            Ok(owner) => owner,
+            Err(e) => {
+                tracing::warn!("Failed to look up owner of match {}: {}", match_id, e);
+                None
+            }
+        }
+    }
+    
+    pub async fn owns(&self, match_id: Uuid) -> bool {
+        self.ownership.owns(match_id).await
+    }
+
+    // Running matches this node owns
+    pub async fn owned_match_count(&self) -> usize {
+        self.ownership.owned_count().await
+    }
+
+    // Finished and voided matches that ended in [from, to), for purging
+    pub async fn closed_matches_between(&self, from: DateTime<Utc>, to: DateTime<Utc>) -> Result<Vec<Uuid>> {
+        self.repo.closed_matches_between(from, to).await
+    }
+
+    // Delete closed matches and everything recorded about them
+    pub async fn delete_matches(&self, match_ids: &[Uuid]) -> Result<i64> {
+        self.repo.delete_matches(match_ids).await
+    }
+
+    // Take over a running match from another node; returns the match for the
+    // game loop to resume
+    pub async fn adopt_match(&self, match_id: Uuid, from_node: &str) -> Result<MatchDetails> {
+        let details = self.repo.get_match_details(match_id).await?;
+        if details.status != MatchStatus::Playing {
+            return Err(Error::MatchNotReady);
+        }
+        if !self.ownership.adopt(match_id, from_node).await? {
+            return Err(Error::ClusterError(format!("match {} is not owned by node {}", match_id, from_node)));
+        }
+        tracing::info!("Adopted match {} from node {}", match_id, from_node);
+        Ok(details)
+    }
+
+    // Drop a match another node has adopted
+    pub async fn hand_over(&self, match_id: Uuid) {
+        self.ownership.forget(match_id).await;
+        self.match_pools.write().await.remove(match_id);
+    }
+    
+    // Rooms waiting for players on this instance, by match type and zone
+    // Rooms and queued writes held in memory
+    pub async fn memory_usage(&self) -> Vec<(&'static str, usize)> {
+        vec![
+            (memory::ROOMS, self.match_pools.read().await.rooms().count()),
+            (memory::PENDING_WRITES, self.write_queue.len()),
+        ]
+    }
+
+    pub async fn queue_depths(&self) -> Vec<QueueDepth> {
+        let pools = self.match_pools.read().await;
+        let mut depths: Vec<QueueDepth> = Vec::new();
+        for (key, room) in pools.rooms().filter(|(_, room)| room.status == MatchStatus::Matching) {
+            match depths.iter_mut().find(|d| d.match_type == key.match_type && d.zone_id == key.zone_id) {
+                Some(depth) => {
+                    depth.rooms += 1;
+                    depth.players += room.players.len();
+                }
+                None => depths.push(QueueDepth {
+                    match_type: key.match_type.clone(),
+                    zone_id: key.zone_id.clone(),
+                    rooms: 1,
+                    players: room.players.len(),
+                }),
+            }
+        }
+        depths.sort_by(|a, b| (&a.match_type, &a.zone_id).cmp(&(&b.match_type, &b.zone_id)));
+        depths
+    }
+    
+    // The room if it is a public one on this instance still waiting for
+    // players, with the user in it
+    pub async fn queued_room(&self, match_id: Uuid, user_id: Uuid) -> Option<QueuedRoom> {
+        let pools = self.match_pools.read().await;
+        let (key, room) = pools.get(match_id)?;
+        if key.private || room.status != MatchStatus::Matching || !room.players.contains(&user_id) {
+            return None;
+        }
+        Some(QueuedRoom {
+            match_type: key.match_type.clone(),
+            zone_id: key.zone_id.clone(),
+            players: room.current_players,
+            required_players: room.required_players,
+        })
+    }
+
+    // Whether the match may be watched: a public match being played on this
+    // instance. Private lobbies and tutorials can't be.
+    pub async fn spectatable(&self, match_id: Uuid) -> bool {
+        let pools = self.match_pools.read().await;
+        pools.get(match_id).is_some_and(|(key, room)| {
+            !key.private && key.match_type != TUTORIAL_MATCH_TYPE && room.status == MatchStatus::Playing
+        })
+    }
+
+    // Rooms on this instance and writes waiting for the database, for
+    // replication to a standby
+    pub async fn snapshot(&self) -> (Vec<(PoolKey, MatchRoom)>, Vec<PendingWrite>) {
+        let rooms = self.match_pools.read().await
+            .rooms()
+            .map(|(key, room)| (key.clone(), room.clone()))
+            .collect();
+        (rooms, self.write_queue.snapshot())
+    }
+
+    // Take over the rooms and pending writes of a node that went away. Rooms
+    // already known here are kept as they are; ready rooms are started, and
+    // lobbies closed when they were due to.
+    pub async fn restore(self: &Arc<Self>, rooms: Vec<(PoolKey, MatchRoom)>, writes: Vec<PendingWrite>) {
+        let mut ready = Vec::new();
+        let mut lobbies = Vec::new();
+        {
+            let mut pools = self.match_pools.write().await;
+            for (key, room) in rooms {
+                if pools.get(room.id).is_some() {
+                    continue;
+                }
+                match room.status {
+                    MatchStatus::Ready => ready.push(room.id),
+                    MatchStatus::Lobby => lobbies.push((room.id, room.lobby_closes_at)),
+                    _ => {}
+                }
+                pools.insert(key, room);
+            }
+        }
+        for match_id in ready {
+            self.spawn_start(match_id);
+        }
+        for (match_id, closes_at) in lobbies {
+            let wait = closes_at.and_then(|at| (at - Utc::now()).to_std().ok()).unwrap_or_default();
+            let match_service = self.clone();
+            supervisor::spawn("lobby_timer", async move { match_service.close_lobby_after(match_id, wait).await });
+        }
+        // Replayed by the health probe
+        for write in writes {
+            self.write_queue.push(write);
+        }
+    }
+
+    // Own a running match of a node that went away: take its lease if it is
+    // still held, or claim the match if the lease has lapsed
+    pub async fn take_over(&self, match_id: Uuid, from_node: &str) -> Result<bool> {
+        if self.ownership.adopt(match_id, from_node).await? {
+            return Ok(true);
+        }
+        self.ownership.claim(match_id).await
+    }
+    
+    // Record treasure discovery; only active catalog treasures and undiscovered
+    // treasures spawned into the match count, and suspected location spoofers
+    // can't score. Without a score the treasure's base score counts
+    pub async fn record_discovery(&self, match_id: Uuid, team_id: Uuid, user_id: Uuid, treasure_id: Uuid, score: Option<i32>) -> Result<()> {
+        if self.trust.is_suspected(user_id).await {
+            return Err(Error::LocationUntrusted);
+        }
+        let treasure = self.catalog.claim(match_id, treasure_id).await?;
+        let score = score.unwrap_or(treasure.base_score);
+        
+        let discovery = TreasureDiscovery {
+            match_id,
+            team_id,
+            user_id,
+            treasure_id,
+            score,
+        };
+        
+        self.persist(PendingWrite::Discovery(discovery.clone())).await?;
+        
+        self.events.publish(MatchEvent::DiscoveryRecorded { discovery });
+        
+        Ok(())
+    }
+    
+    // Add treasures to a running match and tell its players
+    pub async fn spawn_treasures(&self, match_id: Uuid, treasures: Vec<Treasure>) {
+        self.catalog.spawn(match_id, &treasures).await;
+        self.events.publish(MatchEvent::TreasuresSpawned {
+            spawn: TreasureSpawn { match_id, treasures },
+        });
+    }
+
+    // Tell the players of a match that a capture zone changed hands
+    pub fn capture_changed(&self, update: CaptureUpdate) {
+        self.events.publish(MatchEvent::CaptureChanged { update });
+    }
+
+    // Add points a team earned holding a capture zone; they count towards
+    // the winner along with the team's discoveries
+    pub async fn award_objective(&self, match_id: Uuid, team_id: Uuid, points: i32) -> Result<()> {
+        self.persist(PendingWrite::ObjectivePoints { match_id, team_id, points }).await
+    }
+
+    // Store the result of a finished round and tell the players of the match
+    pub async fn round_ended(&self, round: RoundResult) -> Result<()> {
+        self.persist(PendingWrite::Round(round.clone())).await?;
+        self.events.publish(MatchEvent::RoundEnded { round });
+        Ok(())
+    }
+
+    // Get full match details
+    pub async fn get_match_details(&self, match_id: Uuid) -> Result<MatchDetails> {
+        self.repo.get_match_details(match_id).await
+    }
+}
+
+// Six characters, leaving out ones easily mistaken for each other (0/O, 1/I)
+fn invite_code() -> String {
+    const ALPHABET: &[u8] = b"ABCDEFGHJKLMNPQRSTUVWXYZ23456789";
+    let mut rng = thread_rng();
+    (0..6).map(|_| ALPHABET[rng.gen_range(0..ALPHABET.len())] as char).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::memory_repository::MemoryRepository;
+
+    #[tokio::test]
+    async fn replaying_a_partially_written_start_completes_it() {
+        let repo = MemoryRepository::new(Duration::ZERO, Duration::ZERO);
+        let match_id = Uuid::new_v4();
+        let teams = vec![
+            TeamAssignment { team_id: Uuid::new_v4(), team_number: 1, players: vec![Uuid::new_v4(), Uuid::new_v4()] },
+            TeamAssignment { team_id: Uuid::new_v4(), team_number: 2, players: vec![Uuid::new_v4(), Uuid::new_v4()] },
+        ];
+
+        // The database went away after the match, its first team and one member were written
+        repo.create_match(match_id, "2v2", 2, VictoryCondition::default()).await.unwrap();
+        repo.create_team(teams[0].team_id, match_id, 1, 2, None).await.unwrap();
+        repo.add_player_to_team(match_id, teams[0].team_id, teams[0].players[0], None).await.unwrap();
+
+        MatchService::persist_match_start(&repo, match_id, "2v2", 2, &teams, &HashMap::new(), &HashMap::new(), VictoryCondition::default(), false)
+            .await
+            .unwrap();
+
+        let written = repo.get_match_teams(match_id).await.unwrap();
+        assert_eq!(written.len(), 2);
+        for (team, stored) in teams.iter().zip(&written) {
+            let mut members: Vec<Uuid> = stored.members.iter().map(|member| member.user_id).collect();
+            let mut expected = team.players.clone();
+            members.sort();
+            expected.sort();
+            assert_eq!(members, expected);
+        }
+        assert_eq!(repo.get_match_scores(match_id).await.unwrap().status, MatchStatus::Playing);
+    }
+}
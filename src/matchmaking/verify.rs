@@ -0,0 +1,81 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::models::game::MatchScores;
+
+// Why a match result can't be finalized as is
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Anomaly {
+    // A discovery by someone who isn't a member of the match, or not of that team
+    UnregisteredPlayer { user_id: Uuid, team_id: Uuid },
+    // A discovery recorded after the match was over
+    LateClaim { user_id: Uuid, discovered_at: DateTime<Utc> },
+    TeamScoreMismatch { team_id: Uuid, recorded: i32, expected: i32 },
+    MemberScoreMismatch { user_id: Uuid, recorded: i32, expected: i32 },
+}
+
+// Sums of the valid discovery records, per team and per player
+pub fn discovery_totals(scores: &MatchScores) -> (HashMap<Uuid, i32>, HashMap<Uuid, i32>) {
+    let mut team_totals: HashMap<Uuid, i32> = HashMap::new();
+    let mut member_totals: HashMap<Uuid, i32> = HashMap::new();
+    for discovery in scores.discoveries.iter().filter(|d| !d.invalidated) {
+        *team_totals.entry(discovery.team_id).or_insert(0) += discovery.score;
+        *member_totals.entry(discovery.user_id).or_insert(0) += discovery.score;
+    }
+    (team_totals, member_totals)
+}
+
+// Check a match's records before its winner is declared. `ended_at` is when
+// the match was over; discoveries recorded later don't count.
+pub fn verify_result(scores: &MatchScores, ended_at: DateTime<Utc>) -> Vec<Anomaly> {
+    let mut anomalies = Vec::new();
+
+    let team_of: HashMap<Uuid, Uuid> = scores
+        .members
+        .iter()
+        .map(|member| (member.user_id, member.team_id))
+        .collect();
+    for discovery in scores.discoveries.iter().filter(|d| !d.invalidated) {
+        if team_of.get(&discovery.user_id) != Some(&discovery.team_id) {
+            anomalies.push(Anomaly::UnregisteredPlayer {
+                user_id: discovery.user_id,
+                team_id: discovery.team_id,
+            });
+        }
+        if discovery.discovered_at > ended_at {
+            anomalies.push(Anomaly::LateClaim {
+                user_id: discovery.user_id,
+                discovered_at: discovery.discovered_at,
+            });
+        }
+    }
+
+    let (team_totals, member_totals) = discovery_totals(scores);
+    for team in &scores.teams {
+        let expected = team_totals.get(&team.team_id).copied().unwrap_or(0);
+        if team.total_score != expected {
+            anomalies.push(Anomaly::TeamScoreMismatch {
+                team_id: team.team_id,
+                recorded: team.total_score,
+                expected,
+            });
+        }
+    }
+    for member in &scores.members {
+        let expected = member_totals.get(&member.user_id).copied().unwrap_or(0);
+        if member.individual_score != expected {
+            anomalies.push(Anomaly::MemberScoreMismatch {
+                user_id: member.user_id,
+                recorded: member.individual_score,
+                expected,
+            });
+        }
+    }
+
+    anomalies
+}
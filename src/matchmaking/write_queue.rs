@@ -0,0 +1,84 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::game::victory::VictoryCondition;
+use crate::models::game::{LobbySelection, Platform, RoundResult, TeamAssignment, TeamStrength, TreasureDiscovery};
+
+// A database write that couldn't be applied while the database was offline
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum PendingWrite {
+    StartMatch {
+        match_id: Uuid,
+        match_type: String,
+        players_per_team: i32,
+        teams: Vec<TeamAssignment>,
+        // Stored with each member
+        #[serde(default)]
+        platforms: HashMap<Uuid, Platform>,
+        // By team id
+        #[serde(default)]
+        strengths: HashMap<Uuid, TeamStrength>,
+        #[serde(default)]
+        victory: VictoryCondition,
+        // The match is only marked as playing by BeginMatch, once its lobby closes
+        #[serde(default)]
+        lobby: bool,
+    },
+    BeginMatch {
+        match_id: Uuid,
+    },
+    LobbySelection {
+        match_id: Uuid,
+        user_id: Uuid,
+        selection: LobbySelection,
+    },
+    Discovery(TreasureDiscovery),
+    // Points a team earned holding a capture zone
+    ObjectivePoints {
+        match_id: Uuid,
+        team_id: Uuid,
+        points: i32,
+    },
+    Round(RoundResult),
+    EndMatch {
+        match_id: Uuid,
+        // Tutorial matches leave ratings alone
+        #[serde(default)]
+        unrated: bool,
+    },
+}
+
+// FIFO of pending writes, replayed in order once the database is reachable
+#[derive(Debug, Default)]
+pub struct WriteQueue {
+    items: Mutex<VecDeque<PendingWrite>>,
+}
+
+impl WriteQueue {
+    pub fn push(&self, write: PendingWrite) {
+        self.items.lock().unwrap().push_back(write);
+    }
+
+    pub fn front(&self) -> Option<PendingWrite> {
+        self.items.lock().unwrap().front().cloned()
+    }
+
+    pub fn pop_front(&self) {
+        self.items.lock().unwrap().pop_front();
+    }
+
+    // Copy of every pending write, oldest first
+    pub fn snapshot(&self) -> Vec<PendingWrite> {
+        self.items.lock().unwrap().iter().cloned().collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
@@ -0,0 +1,109 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::sync::RwLock;
+
+use crate::db::repository::ZoneRepository;
+use crate::error::{Error, Result};
+use crate::models::game::PlayerPosition;
+use crate::models::zone::Zone;
+use crate::supervisor;
+
+// How often zones are reloaded from the database
+const REFRESH_INTERVAL: Duration = Duration::from_secs(300);
+
+// Where zone definitions come from
+pub enum ZoneSource {
+    // ZONES_FILE: a JSON array of zones, read once at startup
+    File(String),
+    // The `zones` table, refreshed periodically
+    Database(Arc<dyn ZoneRepository>),
+}
+
+// Map zones used to partition matchmaking. With no zones defined every
+// player queues in the same global pool per match type.
+pub struct ZoneRegistry {
+    zones: RwLock<Vec<Zone>>,
+}
+
+impl ZoneRegistry {
+    pub async fn init(source: ZoneSource) -> Arc<Self> {
+        let registry = Arc::new(Self {
+            zones: RwLock::new(Vec::new()),
+        });
+
+        match source {
+            ZoneSource::File(path) => match load_file(&path) {
+                Ok(zones) => registry.set(zones).await,
+                Err(e) => tracing::error!("Failed to load zones from {}: {}", path, e),
+            },
+            ZoneSource::Database(repo) => {
+                match repo.list_zones().await {
+                    Ok(zones) => registry.set(zones).await,
+                    Err(e) => tracing::warn!("Failed to load zones, matchmaking is not zoned for now: {}", e),
+                }
+                let refresher = registry.clone();
+                supervisor::spawn_loop("zone_refresh", move || {
+                    let refresher = refresher.clone();
+                    let repo = repo.clone();
+                    async move {
+                        let mut interval = tokio::time::interval(REFRESH_INTERVAL);
+                        interval.tick().await;
+                        loop {
+                            interval.tick().await;
+                            match repo.list_zones().await {
+                                Ok(zones) => refresher.set(zones).await,
+                                Err(e) => tracing::warn!("Failed to refresh zones: {}", e),
+                            }
+                        }
+                    }
+                });
+            }
+        }
+        registry
+    }
+
+    async fn set(&self, zones: Vec<Zone>) {
+        let valid: Vec<Zone> = zones
+            .into_iter()
+            .filter(|zone| match zone.validate() {
+                Ok(()) => true,
+                Err(_) => {
+                    tracing::warn!("Ignoring invalid zone definition: {}", zone.id);
+                    false
+                }
+            })
+            .collect();
+        tracing::info!("Loaded {} map zones", valid.len());
+        *self.zones.write().await = valid;
+    }
+
+    pub async fn list(&self) -> Vec<Zone> {
+        self.zones.read().await.clone()
+    }
+
+    // Pick the zone a player queues in: the requested one (which must contain
+    // the player's position, if given), otherwise the zone around the position.
+    // None means the global pool.
+    pub async fn resolve(&self, requested: Option<&str>, position: Option<&PlayerPosition>) -> Result<Option<Zone>> {
+        let zones = self.zones.read().await;
+        match requested {
+            Some(zone_id) => {
+                let zone = zones
+                    .iter()
+                    .find(|z| z.id == zone_id)
+                    .ok_or_else(|| Error::NotFound(format!("zone {}", zone_id)))?;
+                if position.is_some_and(|pos| !zone.contains(pos)) {
+                    return Err(Error::PermissionDenied("position is outside the requested zone".to_string()));
+                }
+                Ok(Some(zone.clone()))
+            }
+            None => Ok(position.and_then(|pos| zones.iter().find(|z| z.contains(pos)).cloned())),
+        }
+    }
+}
+
+fn load_file(path: &str) -> std::result::Result<Vec<Zone>, String> {
+    let text = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+    serde_json::from_str(&text).map_err(|e| e.to_string())
+}
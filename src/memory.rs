@@ -0,0 +1,48 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::api::cache::ResponseCache;
+use crate::gateway::handler::WebSocketHandler;
+use crate::matchmaking::service::MatchService;
+use crate::metrics::METRICS;
+use crate::supervisor;
+
+// How often the sizes are sampled
+const SAMPLE_INTERVAL: Duration = Duration::from_secs(15);
+
+// Kinds of in-memory state, as labelled at /metrics
+pub const CONNECTIONS: &str = "connections";
+pub const ROOMS: &str = "rooms";
+pub const PENDING_WRITES: &str = "pending_writes";
+pub const MATCH_STATES: &str = "match_states";
+pub const MATCH_STATS: &str = "match_stats";
+pub const SPECTATOR_BUFFER: &str = "spectator_buffer";
+pub const FANOUT_QUEUE: &str = "fanout_queue";
+pub const RESPONSE_CACHE: &str = "response_cache";
+
+// State this node holds in memory and that grows with traffic.
+//
+// Sizes are sampled into spv_memory_items. Each kind has a hard cap so a
+// traffic spike can't grow it without bound: new sessions and matches are
+// refused past theirs, buffers and caches drop their oldest items. Every
+// refused or dropped item is counted in spv_memory_cap_hits_total.
+pub fn spawn_sampler(ws_handler: Arc<WebSocketHandler>, match_service: Arc<MatchService>, cache: Arc<ResponseCache>) {
+    supervisor::spawn_loop("memory_sampler", move || {
+        let ws_handler = ws_handler.clone();
+        let match_service = match_service.clone();
+        let cache = cache.clone();
+        async move {
+            let mut interval = tokio::time::interval(SAMPLE_INTERVAL);
+            loop {
+                interval.tick().await;
+                for (kind, items) in ws_handler.memory_usage().await {
+                    METRICS.set_memory_items(kind, items);
+                }
+                for (kind, items) in match_service.memory_usage().await {
+                    METRICS.set_memory_items(kind, items);
+                }
+                METRICS.set_memory_items(RESPONSE_CACHE, cache.len());
+            }
+        }
+    });
+}
@@ -0,0 +1,332 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use crate::latency::Stage;
+use crate::matchmaking::service::ShedReason;
+use crate::slow::SlowKind;
+use crate::telemetry::schema::Violation;
+use crate::telemetry::service::TelemetryAck;
+
+// Upper bounds (ms) of the client RTT histogram buckets
+const RTT_BUCKETS_MS: [u64; 6] = [50, 100, 200, 400, 800, 1600];
+// Upper bounds (ms) of the command latency histogram buckets
+const LATENCY_BUCKETS_MS: [f64; 11] = [1.0, 2.5, 5.0, 10.0, 25.0, 50.0, 100.0, 250.0, 500.0, 1000.0, 2500.0];
+
+// One command stage's latency histogram
+#[derive(Default)]
+struct Histogram {
+    buckets: [u64; LATENCY_BUCKETS_MS.len()],
+    sum_ms: f64,
+    count: u64,
+}
+
+// Process-wide counters, exported in Prometheus text format at /metrics
+pub struct Metrics {
+    // Messages sent gzip-compressed
+    pub compressed_messages: AtomicU64,
+    // Payload size before / after compression
+    pub compression_bytes_in: AtomicU64,
+    pub compression_bytes_out: AtomicU64,
+    // Client-reported connection quality (sys.net_report)
+    rtt_buckets: [AtomicU64; RTT_BUCKETS_MS.len()],
+    rtt_sum_ms: AtomicU64,
+    net_reports: AtomicU64,
+    // Packet loss in basis points (1/100 of a percent), summed over reports
+    packet_loss_sum_bp: AtomicU64,
+    // Client telemetry events by outcome
+    telemetry_accepted: AtomicU64,
+    telemetry_sampled_out: AtomicU64,
+    telemetry_rejected: AtomicU64,
+    telemetry_dropped: AtomicU64,
+    telemetry_write_failed: AtomicU64,
+    // Rejected telemetry events by reason, in Violation::ALL order
+    telemetry_violations: [AtomicU64; Violation::ALL.len()],
+    // Stored scores overwritten by the reconciliation job
+    score_corrections: AtomicU64,
+    // Broadcasts merged into one already queued
+    fanout_coalesced: AtomicU64,
+    // Hasura operations and commands over their slow threshold
+    slow_queries: AtomicU64,
+    slow_commands: AtomicU64,
+    // Joins refused as busy, by the limit they hit
+    shed_matches: AtomicU64,
+    shed_match_type: AtomicU64,
+    shed_queue: AtomicU64,
+    shed_pending_writes: AtomicU64,
+    // Hasura responses missing selected fields or with changed types, by
+    // whether the model could do without them
+    schema_tolerated: AtomicU64,
+    schema_failed: AtomicU64,
+    // Background task panics and supervised restarts, by task name
+    task_panics: Mutex<BTreeMap<String, u64>>,
+    task_restarts: Mutex<BTreeMap<String, u64>>,
+    // Command latency by command, per stage in Stage::ALL order
+    command_latency: Mutex<BTreeMap<String, [Histogram; Stage::ALL.len()]>>,
+    // Items held in memory by kind, as last sampled
+    memory_items: Mutex<BTreeMap<&'static str, u64>>,
+    // Items dropped or refused at a memory cap, by kind
+    memory_cap_hits: Mutex<BTreeMap<&'static str, u64>>,
+}
+
+pub static METRICS: Metrics = Metrics::new();
+
+impl Metrics {
+    const fn new() -> Self {
+        Self {
+            compressed_messages: AtomicU64::new(0),
+            compression_bytes_in: AtomicU64::new(0),
+            compression_bytes_out: AtomicU64::new(0),
+            rtt_buckets: [const { AtomicU64::new(0) }; RTT_BUCKETS_MS.len()],
+            rtt_sum_ms: AtomicU64::new(0),
+            net_reports: AtomicU64::new(0),
+            packet_loss_sum_bp: AtomicU64::new(0),
+            telemetry_accepted: AtomicU64::new(0),
+            telemetry_sampled_out: AtomicU64::new(0),
+            telemetry_rejected: AtomicU64::new(0),
+            telemetry_dropped: AtomicU64::new(0),
+            telemetry_write_failed: AtomicU64::new(0),
+            telemetry_violations: [const { AtomicU64::new(0) }; Violation::ALL.len()],
+            score_corrections: AtomicU64::new(0),
+            fanout_coalesced: AtomicU64::new(0),
+            slow_queries: AtomicU64::new(0),
+            slow_commands: AtomicU64::new(0),
+            shed_matches: AtomicU64::new(0),
+            shed_match_type: AtomicU64::new(0),
+            shed_queue: AtomicU64::new(0),
+            shed_pending_writes: AtomicU64::new(0),
+            schema_tolerated: AtomicU64::new(0),
+            schema_failed: AtomicU64::new(0),
+            task_panics: Mutex::new(BTreeMap::new()),
+            task_restarts: Mutex::new(BTreeMap::new()),
+            command_latency: Mutex::new(BTreeMap::new()),
+            memory_items: Mutex::new(BTreeMap::new()),
+            memory_cap_hits: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    pub fn record_net_report(&self, rtt_ms: u32, packet_loss: f32) {
+        let rtt_ms = rtt_ms as u64;
+        // Cumulative buckets, as Prometheus expects
+        for (bucket, bound) in self.rtt_buckets.iter().zip(RTT_BUCKETS_MS) {
+            if rtt_ms <= bound {
+                bucket.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+        self.rtt_sum_ms.fetch_add(rtt_ms, Ordering::Relaxed);
+        self.net_reports.fetch_add(1, Ordering::Relaxed);
+        let loss_bp = (packet_loss.clamp(0.0, 1.0) * 10_000.0).round() as u64;
+        self.packet_loss_sum_bp.fetch_add(loss_bp, Ordering::Relaxed);
+    }
+
+    pub fn record_telemetry(&self, ack: &TelemetryAck) {
+        self.telemetry_accepted.fetch_add(ack.accepted as u64, Ordering::Relaxed);
+        self.telemetry_sampled_out.fetch_add(ack.sampled_out as u64, Ordering::Relaxed);
+        self.telemetry_rejected.fetch_add(ack.rejected as u64, Ordering::Relaxed);
+        self.telemetry_dropped.fetch_add(ack.dropped as u64, Ordering::Relaxed);
+        for rejection in &ack.errors {
+            if let Some(i) = Violation::ALL.iter().position(|v| *v == rejection.reason) {
+                self.telemetry_violations[i].fetch_add(1, Ordering::Relaxed);
+            }
+        }
+    }
+
+    pub fn record_telemetry_write_failure(&self, events: usize) {
+        self.telemetry_write_failed.fetch_add(events as u64, Ordering::Relaxed);
+    }
+
+    pub fn record_score_correction(&self) {
+        self.score_corrections.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_compression(&self, original: usize, compressed: usize) {
+        self.compressed_messages.fetch_add(1, Ordering::Relaxed);
+        self.compression_bytes_in.fetch_add(original as u64, Ordering::Relaxed);
+        self.compression_bytes_out.fetch_add(compressed as u64, Ordering::Relaxed);
+    }
+
+    pub fn record_fanout_coalesced(&self) {
+        self.fanout_coalesced.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_slow(&self, kind: SlowKind) {
+        match kind {
+            SlowKind::Query => self.slow_queries.fetch_add(1, Ordering::Relaxed),
+            SlowKind::Command => self.slow_commands.fetch_add(1, Ordering::Relaxed),
+        };
+    }
+
+    pub fn record_shed(&self, reason: ShedReason) {
+        match reason {
+            ShedReason::Matches => self.shed_matches.fetch_add(1, Ordering::Relaxed),
+            ShedReason::MatchType => self.shed_match_type.fetch_add(1, Ordering::Relaxed),
+            ShedReason::Queue => self.shed_queue.fetch_add(1, Ordering::Relaxed),
+            ShedReason::PendingWrites => self.shed_pending_writes.fetch_add(1, Ordering::Relaxed),
+        };
+    }
+
+    pub fn record_schema_mismatch(&self, failed: bool) {
+        if failed {
+            self.schema_failed.fetch_add(1, Ordering::Relaxed);
+        } else {
+            self.schema_tolerated.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    pub fn record_task_panic(&self, task: &str) {
+        let mut panics = self.task_panics.lock().unwrap_or_else(|e| e.into_inner());
+        *panics.entry(task.to_string()).or_default() += 1;
+    }
+
+    pub fn record_task_restart(&self, task: &str) {
+        let mut restarts = self.task_restarts.lock().unwrap_or_else(|e| e.into_inner());
+        *restarts.entry(task.to_string()).or_default() += 1;
+    }
+
+    pub fn record_command_latency(&self, command: &str, stages: &[(Stage, Duration)]) {
+        let mut latency = self.command_latency.lock().unwrap_or_else(|e| e.into_inner());
+        let histograms = latency.entry(command.to_string()).or_default();
+        for &(stage, elapsed) in stages {
+            let ms = elapsed.as_secs_f64() * 1000.0;
+            let histogram = &mut histograms[stage as usize];
+            for (bucket, bound) in histogram.buckets.iter_mut().zip(LATENCY_BUCKETS_MS) {
+                if ms <= bound {
+                    *bucket += 1;
+                }
+            }
+            histogram.sum_ms += ms;
+            histogram.count += 1;
+        }
+    }
+
+    pub fn set_memory_items(&self, kind: &'static str, items: usize) {
+        self.memory_items.lock().unwrap_or_else(|e| e.into_inner()).insert(kind, items as u64);
+    }
+
+    pub fn record_memory_cap_hit(&self, kind: &'static str) {
+        let mut hits = self.memory_cap_hits.lock().unwrap_or_else(|e| e.into_inner());
+        *hits.entry(kind).or_default() += 1;
+    }
+
+    pub fn render(&self) -> String {
+        let bytes_in = self.compression_bytes_in.load(Ordering::Relaxed);
+        let bytes_out = self.compression_bytes_out.load(Ordering::Relaxed);
+
+        let mut out = String::new();
+        counter(&mut out, "spv_ws_compressed_messages_total", "Messages sent gzip-compressed",
+            self.compressed_messages.load(Ordering::Relaxed));
+        counter(&mut out, "spv_ws_compression_bytes_in_total", "Bytes before compression", bytes_in);
+        counter(&mut out, "spv_ws_compression_bytes_out_total", "Bytes after compression", bytes_out);
+        counter(&mut out, "spv_ws_compression_bytes_saved_total", "Bytes saved by compression",
+            bytes_in.saturating_sub(bytes_out));
+
+        let reports = self.net_reports.load(Ordering::Relaxed);
+        let _ = writeln!(out, "# HELP spv_client_rtt_ms Client-reported round trip time");
+        let _ = writeln!(out, "# TYPE spv_client_rtt_ms histogram");
+        for (bucket, bound) in self.rtt_buckets.iter().zip(RTT_BUCKETS_MS) {
+            let _ = writeln!(out, "spv_client_rtt_ms_bucket{{le=\"{}\"}} {}", bound, bucket.load(Ordering::Relaxed));
+        }
+        let _ = writeln!(out, "spv_client_rtt_ms_bucket{{le=\"+Inf\"}} {}", reports);
+        let _ = writeln!(out, "spv_client_rtt_ms_sum {}", self.rtt_sum_ms.load(Ordering::Relaxed));
+        let _ = writeln!(out, "spv_client_rtt_ms_count {}", reports);
+
+        let avg_loss = if reports == 0 {
+            0.0
+        } else {
+            self.packet_loss_sum_bp.load(Ordering::Relaxed) as f64 / 10_000.0 / reports as f64
+        };
+        let _ = writeln!(out, "# HELP spv_client_packet_loss_avg Average client-reported packet loss ratio");
+        let _ = writeln!(out, "# TYPE spv_client_packet_loss_avg gauge");
+        let _ = writeln!(out, "spv_client_packet_loss_avg {}", avg_loss);
+
+        let _ = writeln!(out, "# HELP spv_telemetry_events_total Client telemetry events by outcome");
+        let _ = writeln!(out, "# TYPE spv_telemetry_events_total counter");
+        for (outcome, value) in [
+            ("accepted", &self.telemetry_accepted),
+            ("sampled_out", &self.telemetry_sampled_out),
+            ("rejected", &self.telemetry_rejected),
+            ("dropped", &self.telemetry_dropped),
+            ("write_failed", &self.telemetry_write_failed),
+        ] {
+            let _ = writeln!(out, "spv_telemetry_events_total{{outcome=\"{}\"}} {}", outcome, value.load(Ordering::Relaxed));
+        }
+        let _ = writeln!(out, "# HELP spv_telemetry_rejected_total Client telemetry events rejected, by reason");
+        let _ = writeln!(out, "# TYPE spv_telemetry_rejected_total counter");
+        for (reason, value) in Violation::ALL.iter().zip(&self.telemetry_violations) {
+            let _ = writeln!(out, "spv_telemetry_rejected_total{{reason=\"{}\"}} {}", reason.to_str(), value.load(Ordering::Relaxed));
+        }
+
+        counter(&mut out, "spv_score_corrections_total", "Team and player scores fixed by reconciliation",
+            self.score_corrections.load(Ordering::Relaxed));
+        counter(&mut out, "spv_fanout_coalesced_total", "Broadcasts merged into a queued one before sending",
+            self.fanout_coalesced.load(Ordering::Relaxed));
+
+        let _ = writeln!(out, "# HELP spv_slow_operations_total Hasura operations and commands over their slow threshold");
+        let _ = writeln!(out, "# TYPE spv_slow_operations_total counter");
+        for (kind, value) in [("query", &self.slow_queries), ("command", &self.slow_commands)] {
+            let _ = writeln!(out, "spv_slow_operations_total{{kind=\"{}\"}} {}", kind, value.load(Ordering::Relaxed));
+        }
+
+        let _ = writeln!(out, "# HELP spv_matchmaking_shed_total Joins refused because the node was at a load limit");
+        let _ = writeln!(out, "# TYPE spv_matchmaking_shed_total counter");
+        for (reason, value) in [
+            ("matches", &self.shed_matches),
+            ("match_type", &self.shed_match_type),
+            ("queue", &self.shed_queue),
+            ("pending_writes", &self.shed_pending_writes),
+        ] {
+            let _ = writeln!(out, "spv_matchmaking_shed_total{{reason=\"{}\"}} {}", reason, value.load(Ordering::Relaxed));
+        }
+
+        let _ = writeln!(out, "# HELP spv_hasura_schema_mismatch_total Hasura responses that didn't match the query's fields");
+        let _ = writeln!(out, "# TYPE spv_hasura_schema_mismatch_total counter");
+        for (outcome, value) in [("tolerated", &self.schema_tolerated), ("failed", &self.schema_failed)] {
+            let _ = writeln!(out, "spv_hasura_schema_mismatch_total{{outcome=\"{}\"}} {}", outcome, value.load(Ordering::Relaxed));
+        }
+
+        let _ = writeln!(out, "# HELP spv_task_panics_total Panics caught in background tasks");
+        let _ = writeln!(out, "# TYPE spv_task_panics_total counter");
+        for (task, value) in self.task_panics.lock().unwrap_or_else(|e| e.into_inner()).iter() {
+            let _ = writeln!(out, "spv_task_panics_total{{task=\"{}\"}} {}", task, value);
+        }
+        let _ = writeln!(out, "# HELP spv_task_restarts_total Background loops restarted after a panic");
+        let _ = writeln!(out, "# TYPE spv_task_restarts_total counter");
+        for (task, value) in self.task_restarts.lock().unwrap_or_else(|e| e.into_inner()).iter() {
+            let _ = writeln!(out, "spv_task_restarts_total{{task=\"{}\"}} {}", task, value);
+        }
+
+        let _ = writeln!(out, "# HELP spv_memory_items Items held in memory, by kind");
+        let _ = writeln!(out, "# TYPE spv_memory_items gauge");
+        for (kind, value) in self.memory_items.lock().unwrap_or_else(|e| e.into_inner()).iter() {
+            let _ = writeln!(out, "spv_memory_items{{kind=\"{}\"}} {}", kind, value);
+        }
+        let _ = writeln!(out, "# HELP spv_memory_cap_hits_total Items dropped or refused at a memory cap, by kind");
+        let _ = writeln!(out, "# TYPE spv_memory_cap_hits_total counter");
+        for (kind, value) in self.memory_cap_hits.lock().unwrap_or_else(|e| e.into_inner()).iter() {
+            let _ = writeln!(out, "spv_memory_cap_hits_total{{kind=\"{}\"}} {}", kind, value);
+        }
+
+        let _ = writeln!(out, "# HELP spv_command_latency_ms Time client commands spent per stage, from frame receipt");
+        let _ = writeln!(out, "# TYPE spv_command_latency_ms histogram");
+        for (command, histograms) in self.command_latency.lock().unwrap_or_else(|e| e.into_inner()).iter() {
+            for (stage, histogram) in Stage::ALL.iter().zip(histograms) {
+                let labels = format!("cmd=\"{}\",stage=\"{}\"", command, stage.to_str());
+                for (bucket, bound) in histogram.buckets.iter().zip(LATENCY_BUCKETS_MS) {
+                    let _ = writeln!(out, "spv_command_latency_ms_bucket{{{},le=\"{}\"}} {}", labels, bound, bucket);
+                }
+                let _ = writeln!(out, "spv_command_latency_ms_bucket{{{},le=\"+Inf\"}} {}", labels, histogram.count);
+                let _ = writeln!(out, "spv_command_latency_ms_sum{{{}}} {:.3}", labels, histogram.sum_ms);
+                let _ = writeln!(out, "spv_command_latency_ms_count{{{}}} {}", labels, histogram.count);
+            }
+        }
+        out
+    }
+}
+
+fn counter(out: &mut String, name: &str, help: &str, value: u64) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} counter", name);
+    let _ = writeln!(out, "{} {}", name, value);
+}
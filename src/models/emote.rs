@@ -0,0 +1,51 @@
+use serde::Serialize;
+use utoipa::ToSchema;
+
+// Who receives an emote
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Audience {
+    // Everyone in the match
+    Match,
+    // The sender's team, the sender included
+    Team,
+}
+
+// A predefined emote or quick-chat phrase. Players can only send these, so
+// quick communication needs no free-text moderation; clients localize and
+// render them by id.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct Emote {
+    // Stable id sent with game.emote, e.g. "gg"
+    pub id: &'static str,
+    // English text, for clients without their own translation
+    pub text: &'static str,
+    pub audience: Audience,
+}
+
+const fn emote(id: &'static str, text: &'static str, audience: Audience) -> Emote {
+    Emote { id, text, audience }
+}
+
+pub const CATALOG: &[Emote] = &[
+    emote("wave", "Hello!", Audience::Match),
+    emote("gl_hf", "Good luck, have fun!", Audience::Match),
+    emote("gg", "Good game!", Audience::Match),
+    emote("well_played", "Well played!", Audience::Match),
+    emote("thanks", "Thanks!", Audience::Match),
+    emote("oops", "Oops!", Audience::Match),
+    emote("laugh", "Ha ha!", Audience::Match),
+    emote("thumbs_up", "Nice!", Audience::Match),
+    emote("on_my_way", "On my way!", Audience::Team),
+    emote("follow_me", "Follow me!", Audience::Team),
+    emote("need_help", "Need help!", Audience::Team),
+    emote("wait", "Wait for me!", Audience::Team),
+    emote("treasure_here", "Treasure over here!", Audience::Team),
+    emote("enemy_near", "Enemy nearby!", Audience::Team),
+    emote("retreat", "Fall back!", Audience::Team),
+    emote("split_up", "Let's split up.", Audience::Team),
+];
+
+pub fn find(id: &str) -> Option<&'static Emote> {
+    CATALOG.iter().find(|emote| emote.id == id)
+}
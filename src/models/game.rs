@@ -1,6 +1,12 @@
+use std::collections::HashMap;
+
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 use uuid::Uuid;
 
+use crate::game::victory::VictoryCondition;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum MatchType {
     OneVsOne,
@@ -17,25 +23,150 @@ impl MatchType {
         }
     }
     
+    pub fn to_str(&self) -> &'static str {
+        match self {
+            MatchType::OneVsOne => "1v1",
+            MatchType::TwoVsTwo => "2v2",
+            MatchType::FiveVsFive => "5v5",
+        }
+    }
+}
+
+// Canonical match lifecycle, shared by the in-memory pools, the database and broadcasts.
+// matching -> ready -> lobby -> playing -> finished, or under_review when the result fails
+// verification; reviewed matches end up finished or voided. The lobby is skipped unless
+// LOBBY_DURATION_SECS is set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, JsonSchema, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum MatchStatus {
+    Matching,
+    Ready,
+    // Teams are picked; players chat and choose loadouts until the match starts
+    Lobby,
+    // "in_progress" was written by older builds
+    #[serde(alias = "in_progress")]
+    Playing,
+    // "completed" was written by older builds
+    #[serde(alias = "completed")]
+    Finished,
+    // Ended without a winner until an admin reviews the records
+    UnderReview,
+    // Annulled by an admin; no winner and the results don't count
+    Voided,
+}
+
+impl MatchStatus {
+    // Legacy values still present in old rows, with their canonical replacement
+    pub const LEGACY_VALUES: [(&'static str, MatchStatus); 2] = [
+        ("in_progress", MatchStatus::Playing),
+        ("completed", MatchStatus::Finished),
+    ];
+
+    pub fn to_str(self) -> &'static str {
+        match self {
+            MatchStatus::Matching => "matching",
+            MatchStatus::Ready => "ready",
+            MatchStatus::Lobby => "lobby",
+            MatchStatus::Playing => "playing",
+            MatchStatus::Finished => "finished",
+            MatchStatus::UnderReview => "under_review",
+            MatchStatus::Voided => "voided",
+        }
+    }
+}
+
+impl std::fmt::Display for MatchStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.to_str())
+    }
+}
+
+// Client platform, sent as ?platform= when connecting
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, JsonSchema, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Platform {
+    Ios,
+    Android,
+    Web,
+}
+
+impl Platform {
     pub fn from_str(s: &str) -> Option<Self> {
         match s {
-            "1v1" => Some(MatchType::OneVsOne),
-            "2v2" => Some(MatchType::TwoVsTwo),
-            "5v5" => Some(MatchType::FiveVsFive),
+            "ios" => Some(Platform::Ios),
+            "android" => Some(Platform::Android),
+            "web" => Some(Platform::Web),
             _ => None,
         }
     }
-    
-    pub fn to_str(&self) -> &'static str {
+
+    pub fn to_str(self) -> &'static str {
         match self {
-            MatchType::OneVsOne => "1v1",
-            MatchType::TwoVsTwo => "2v2",
-            MatchType::FiveVsFive => "5v5",
+            Platform::Ios => "ios",
+            Platform::Android => "android",
+            Platform::Web => "web",
         }
     }
+
+    pub fn is_mobile(&self) -> bool {
+        matches!(self, Platform::Ios | Platform::Android)
+    }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+// Platforms a player accepts to be matched with
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PlatformPool {
+    // Cross-play with every platform
+    #[default]
+    Any,
+    // iOS and Android players only
+    Mobile,
+}
+
+impl PlatformPool {
+    // Whether a player on `platform` may queue in this pool; an unknown
+    // platform only gets into the cross-play pool
+    pub fn admits(&self, platform: Option<Platform>) -> bool {
+        match self {
+            PlatformPool::Any => true,
+            PlatformPool::Mobile => platform.is_some_and(|p| p.is_mobile()),
+        }
+    }
+}
+
+// Players per platform
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema, ToSchema)]
+pub struct PlatformBreakdown {
+    pub ios: usize,
+    pub android: usize,
+    pub web: usize,
+    // Connected without a known ?platform=
+    pub unknown: usize,
+}
+
+impl PlatformBreakdown {
+    pub fn add(&mut self, platform: Option<Platform>) {
+        match platform {
+            Some(Platform::Ios) => self.ios += 1,
+            Some(Platform::Android) => self.android += 1,
+            Some(Platform::Web) => self.web += 1,
+            None => self.unknown += 1,
+        }
+    }
+}
+
+impl FromIterator<Option<Platform>> for PlatformBreakdown {
+    fn from_iter<I: IntoIterator<Item = Option<Platform>>>(iter: I) -> Self {
+        let mut breakdown = Self::default();
+        for platform in iter {
+            breakdown.add(platform);
+        }
+        breakdown
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct PlayerPosition {
     pub x: f32,
     pub y: f32,
@@ -44,25 +175,133 @@ pub struct PlayerPosition {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MatchResult {
     pub match_id: Uuid,
-    pub status: String,
+    pub status: MatchStatus,
     pub match_type: String,
+    // Map zone the match was made in; None for the global pool
+    pub zone_id: Option<String>,
     pub current_players: i32,
     pub required_players: i32,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MatchRoom {
     pub id: Uuid,
     pub required_players: i32,
     pub current_players: i32,
     pub players: Vec<Uuid>,
-    pub status: String,
+    pub status: MatchStatus,
+    // Premade parties among the players, kept on the same team
+    pub parties: Vec<Vec<Uuid>>,
+    // Platform of each player that reported one
+    #[serde(default)]
+    pub platforms: HashMap<Uuid, Platform>,
+    // When each player was seated, for the fairness report
+    #[serde(default)]
+    pub seats: HashMap<Uuid, Seat>,
+    // Picked when the lobby opens; empty before
+    #[serde(default)]
+    pub teams: Vec<TeamAssignment>,
+    // Lobby picks by player
+    #[serde(default)]
+    pub selections: HashMap<Uuid, LobbySelection>,
+    // When the lobby closes and the match starts
+    #[serde(default)]
+    pub lobby_closes_at: Option<chrono::DateTime<chrono::Utc>>,
+    // Set on rooms created with lobby.create
+    #[serde(default)]
+    pub private: Option<PrivateRoom>,
+}
+
+// A private room is joined with its invite code instead of through
+// matchmaking, and started by its host
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PrivateRoom {
+    pub code: String,
+    pub host: Uuid,
+    // Kicked players who may not join again
+    #[serde(default)]
+    pub blocked: Vec<Uuid>,
+    // Open kick votes: for each player voted against, every voter and
+    // whether they asked to block re-entry
+    #[serde(default)]
+    pub kick_votes: HashMap<Uuid, HashMap<Uuid, bool>>,
+    // Team number (1 or 2) of each player, kept when the match starts
+    #[serde(default)]
+    pub teams: HashMap<Uuid, i32>,
+    // Players waiting for a seat on the other team, oldest request first
+    #[serde(default)]
+    pub switch_requests: Vec<Uuid>,
+}
+
+// lobby.teams event and lobby.team reply: the players of teams 1 and 2 of a
+// private room, in the order they joined, and who asked to switch sides
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct LobbyTeams {
+    pub match_id: Uuid,
+    pub teams: Vec<Vec<Uuid>>,
+    pub switch_requests: Vec<Uuid>,
+}
+
+// lobby.kick reply and lobby.kick_vote event. The host's kick, or a vote
+// reaching `needed`, removes the player right away.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct KickVote {
+    pub match_id: Uuid,
+    pub target: Uuid,
+    pub votes: usize,
+    // More than half of the other players
+    pub needed: usize,
+    pub kicked: bool,
+}
+
+// lobby.create and lobby.join reply
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct PrivateLobby {
+    pub match_id: Uuid,
+    pub match_type: String,
+    pub code: String,
+    pub host: Uuid,
+    // In the order they joined; the first one takes over as host
+    pub players: Vec<Uuid>,
+    pub required_players: i32,
+    // Players of teams 1 and 2
+    pub teams: Vec<Vec<Uuid>>,
+}
+
+// lobby.select request: a player's loadout and role for the match, each one
+// of those offered in LOBBY_LOADOUTS and LOBBY_ROLES; stored on their match member
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct LobbySelection {
+    pub loadout: Option<String>,
+    pub role: Option<String>,
+}
+
+// A player's seat in a room
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Seat {
+    pub seated_at: chrono::DateTime<chrono::Utc>,
+    // Rating when seated, so later rating changes don't skew the report
+    pub mmr: f64,
+}
+
+// A team's average MMR when its match started, and its predicted chance of winning
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct TeamStrength {
+    pub mmr: f64,
+    pub win_probability: f64,
+}
+
+// Which players were put on which team when a match started
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct TeamAssignment {
+    pub team_id: Uuid,
+    pub team_number: i32,
+    pub players: Vec<Uuid>,
 }
 
 #[derive(Debug, Clone)]
 pub struct MatchMember {
     pub user_id: Uuid,
-    pub score: i32,
 }
 
 #[derive(Debug, Clone)]
@@ -70,17 +309,22 @@ pub struct MatchTeam {
     pub id: Uuid,
     pub team_number: i32,
     pub members: Vec<MatchMember>,
-    pub total_score: i32,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MatchDetails {
     pub id: Uuid,
     pub match_type: String,
-    pub status: String,
+    pub status: MatchStatus,
     pub start_time: Option<chrono::DateTime<chrono::Utc>>,
     pub teams: Vec<TeamDetails>,
     pub duration: Option<std::time::Duration>,
+    pub platforms: PlatformBreakdown,
+    #[serde(default)]
+    pub victory_condition: VictoryCondition,
+    // Rounds played so far, for best_of matches
+    #[serde(default)]
+    pub rounds: Vec<RoundResult>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -89,21 +333,101 @@ pub struct TeamDetails {
     pub team_number: i32,
     pub members: Vec<MemberDetails>,
     pub total_score: i32,
+    // Chance of winning predicted at the start; None for matches started
+    // before predictions were recorded
+    pub win_probability: Option<f64>,
+    // Points from holding capture zones, on top of total_score
+    #[serde(default)]
+    pub objective_score: i32,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct MemberDetails {
     pub user_id: Uuid,
     pub nickname: String,
     pub avatar_url: String,
     pub score: i32,
+    pub platform: Option<Platform>,
+    // Picked in the lobby
+    #[serde(default)]
+    pub loadout: Option<String>,
+    #[serde(default)]
+    pub role: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct TreasureDiscovery {
     pub match_id: Uuid,
     pub team_id: Uuid,
     pub user_id: Uuid,
     pub treasure_id: Uuid,
     pub score: i32,
-}
\ No newline at end of file
+}
+// Stored scores of a match next to the discoveries they should add up to
+#[derive(Debug, Clone, Deserialize)]
+pub struct MatchScores {
+    pub match_id: Uuid,
+    // Recorded at the start; None for matches started before conditions were
+    #[serde(default)]
+    pub victory_condition: Option<VictoryCondition>,
+    pub status: MatchStatus,
+    pub start_time: Option<chrono::DateTime<chrono::Utc>>,
+    pub winner_team_id: Option<Uuid>,
+    pub teams: Vec<TeamScore>,
+    pub members: Vec<MemberScore>,
+    pub discoveries: Vec<DiscoveryScore>,
+    // Results of the rounds the game loop ran, in order
+    #[serde(default)]
+    pub rounds: Vec<RoundResult>,
+}
+
+// match.round event: one round of a best_of match is over. Also stored per
+// round, the match winner being the team that won the most of them.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct RoundResult {
+    pub match_id: Uuid,
+    // From 1
+    pub round_number: i32,
+    // Discovery points each team scored in the round
+    pub scores: HashMap<Uuid, i32>,
+    // None when the round was tied
+    pub winner_team_id: Option<Uuid>,
+    pub ended_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct TeamScore {
+    pub team_id: Uuid,
+    // Sum of the team's discoveries
+    pub total_score: i32,
+    // Capture zone points; highest_score picks the highest sum of both
+    #[serde(default)]
+    pub objective_score: i32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct MemberScore {
+    pub user_id: Uuid,
+    pub team_id: Uuid,
+    pub individual_score: i32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct DiscoveryScore {
+    pub id: Uuid,
+    pub team_id: Uuid,
+    pub user_id: Uuid,
+    pub treasure_id: Uuid,
+    pub score: i32,
+    pub discovered_at: chrono::DateTime<chrono::Utc>,
+    // Thrown out by an admin review; no longer counts towards any score
+    pub invalidated: bool,
+}
+
+// How much a player has played, deciding whether they are still a new player
+#[derive(Debug, Clone)]
+pub struct PlayerExperience {
+    pub finished_matches: i64,
+    // None if the account row is missing
+    pub account_created_at: Option<chrono::DateTime<chrono::Utc>>,
+}
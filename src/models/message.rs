@@ -1,17 +1,42 @@
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 use uuid::Uuid;
 
-#[derive(Debug, Serialize, Deserialize)]
+use crate::correlation;
+
+#[derive(Debug, Serialize, Deserialize, ToSchema, JsonSchema)]
 pub struct ClientMessage {
     pub msg_id: Uuid,
     pub cmd: String,
+    #[schema(value_type = Object)]
     pub data: serde_json::Value,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, JsonSchema)]
 pub struct ServerMessage {
     pub msg_id: Uuid,
+    // Name of a server-pushed event; absent on replies, which echo the request's msg_id
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub event: Option<String>,
     pub code: i32,
     pub data: Option<serde_json::Value>,
     pub error: Option<String>,
+    // Id of the client command this message results from, for tracing it through the server logs
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub correlation_id: Option<Uuid>,
+}
+
+impl ServerMessage {
+    // A server-pushed event; see gateway::protocol::ServerEvent for the typed payloads
+    pub fn event(msg_id: Uuid, event: &str, data: serde_json::Value) -> Self {
+        Self {
+            msg_id,
+            event: Some(event.to_string()),
+            code: 0,
+            data: Some(data),
+            error: None,
+            correlation_id: correlation::current(),
+        }
+    }
 }
\ No newline at end of file
@@ -1,2 +1,5 @@
 pub mod message;
-pub mod game;
\ No newline at end of file
+pub mod game;
+pub mod treasure;
+pub mod zone;
+pub mod emote;
\ No newline at end of file
@@ -0,0 +1,98 @@
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::error::{Error, Result};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Rarity {
+    Common,
+    Rare,
+    Epic,
+    Legendary,
+}
+
+impl Rarity {
+    pub fn to_str(self) -> &'static str {
+        match self {
+            Rarity::Common => "common",
+            Rarity::Rare => "rare",
+            Rarity::Epic => "epic",
+            Rarity::Legendary => "legendary",
+        }
+    }
+}
+
+// A treasure of the catalog. Only treasures inside their active window can be
+// discovered in matches.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, ToSchema)]
+pub struct Treasure {
+    pub id: Uuid,
+    pub name: String,
+    // Map coordinates, same space as PlayerPosition
+    pub x: f32,
+    pub y: f32,
+    pub base_score: i32,
+    pub rarity: Rarity,
+    // Open-ended when unset
+    pub active_from: Option<DateTime<Utc>>,
+    pub active_until: Option<DateTime<Utc>>,
+}
+
+impl Treasure {
+    pub fn is_active(&self, now: DateTime<Utc>) -> bool {
+        self.active_from.is_none_or(|from| from <= now)
+            && self.active_until.is_none_or(|until| now < until)
+    }
+}
+
+// Treasures the game loop added to a running match. They can be discovered
+// once, by a player of that match only.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct TreasureSpawn {
+    pub match_id: Uuid,
+    pub treasures: Vec<Treasure>,
+}
+
+// POST /admin/treasures and PUT /admin/treasures/{id} body
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+pub struct TreasureSpec {
+    pub name: String,
+    pub x: f32,
+    pub y: f32,
+    pub base_score: i32,
+    pub rarity: Rarity,
+    pub active_from: Option<DateTime<Utc>>,
+    pub active_until: Option<DateTime<Utc>>,
+}
+
+impl TreasureSpec {
+    pub fn into_treasure(self, id: Uuid) -> Result<Treasure> {
+        let window_ok = match (self.active_from, self.active_until) {
+            (Some(from), Some(until)) => from < until,
+            _ => true,
+        };
+        if self.name.trim().is_empty()
+            || !self.x.is_finite()
+            || !self.y.is_finite()
+            || self.base_score <= 0
+            || !window_ok
+        {
+            return Err(Error::InvalidMessage);
+        }
+
+        Ok(Treasure {
+            id,
+            name: self.name.trim().to_string(),
+            x: self.x,
+            y: self.y,
+            base_score: self.base_score,
+            rarity: self.rarity,
+            active_from: self.active_from,
+            active_until: self.active_until,
+        })
+    }
+}
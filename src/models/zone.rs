@@ -0,0 +1,75 @@
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::error::{Error, Result};
+use super::game::PlayerPosition;
+
+// A playable area of the map. Players queue per zone so matches are made
+// among players who are physically close to each other.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct Zone {
+    // Short stable id, e.g. "downtown"
+    pub id: String,
+    pub name: String,
+    // Boundary as [x, y] vertices, in PlayerPosition coordinates; closed implicitly
+    pub polygon: Vec<[f32; 2]>,
+    // Treasures per unit of area, used when seeding matches in this zone
+    pub treasure_density: f32,
+}
+
+impl Zone {
+    pub fn validate(&self) -> Result<()> {
+        let points_ok = self.polygon.iter().flatten().all(|c| c.is_finite());
+        if self.id.is_empty()
+            || self.polygon.len() < 3
+            || !points_ok
+            || !self.treasure_density.is_finite()
+            || self.treasure_density < 0.0
+        {
+            return Err(Error::InvalidMessage);
+        }
+        Ok(())
+    }
+
+    // Shoelace formula
+    pub fn area(&self) -> f32 {
+        let mut twice = 0.0;
+        let mut j = self.polygon.len().wrapping_sub(1);
+        for (i, &[xi, yi]) in self.polygon.iter().enumerate() {
+            let [xj, yj] = self.polygon[j];
+            twice += (xj + xi) * (yj - yi);
+            j = i;
+        }
+        (twice / 2.0).abs()
+    }
+
+    // Uniformly random point inside the zone, by rejection from its bounding
+    // box; None if none was found in `attempts` tries
+    pub fn random_point(&self, rng: &mut impl Rng, attempts: usize) -> Option<PlayerPosition> {
+        let (min_x, max_x, min_y, max_y) = self.polygon.iter().fold(
+            (f32::INFINITY, f32::NEG_INFINITY, f32::INFINITY, f32::NEG_INFINITY),
+            |(min_x, max_x, min_y, max_y), &[x, y]| (min_x.min(x), max_x.max(x), min_y.min(y), max_y.max(y)),
+        );
+        if !(min_x < max_x && min_y < max_y) {
+            return None;
+        }
+        (0..attempts)
+            .map(|_| PlayerPosition { x: rng.gen_range(min_x..max_x), y: rng.gen_range(min_y..max_y) })
+            .find(|pos| self.contains(pos))
+    }
+
+    // Ray casting: count polygon edges crossed by a ray going right from the point
+    pub fn contains(&self, pos: &PlayerPosition) -> bool {
+        let mut inside = false;
+        let mut j = self.polygon.len().wrapping_sub(1);
+        for (i, &[xi, yi]) in self.polygon.iter().enumerate() {
+            let [xj, yj] = self.polygon[j];
+            if (yi > pos.y) != (yj > pos.y) && pos.x < (xj - xi) * (pos.y - yi) / (yj - yi) + xi {
+                inside = !inside;
+            }
+            j = i;
+        }
+        inside
+    }
+}
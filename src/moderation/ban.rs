@@ -0,0 +1,52 @@
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+// A ban on a player. Bans are never deleted: lifting one stamps `lifted_at`,
+// so the history stays available to reviewers.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct Ban {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub reason: String,
+    // Admin who issued the ban
+    pub issued_by: String,
+    pub created_at: DateTime<Utc>,
+    // Absent for a permanent ban
+    pub expires_at: Option<DateTime<Utc>>,
+    pub lifted_at: Option<DateTime<Utc>>,
+    pub lifted_by: Option<String>,
+}
+
+impl Ban {
+    pub fn is_active(&self, now: DateTime<Utc>) -> bool {
+        self.lifted_at.is_none() && self.expires_at.is_none_or(|until| until > now)
+    }
+
+    pub fn notice(&self) -> BanNotice {
+        BanNotice {
+            reason: self.reason.clone(),
+            banned_until: self.expires_at,
+        }
+    }
+}
+
+// PUT /admin/bans/{user_id} body
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+pub struct BanSpec {
+    pub issued_by: String,
+    // Shown to the player
+    pub reason: String,
+    // Length of a timed ban; the ban is permanent when absent
+    pub duration_secs: Option<u64>,
+}
+
+// Sent to a banned player as `sys.banned` right before the server closes the connection
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct BanNotice {
+    pub reason: String,
+    // Absent for a permanent ban
+    pub banned_until: Option<DateTime<Utc>>,
+}
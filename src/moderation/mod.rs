@@ -0,0 +1,2 @@
+pub mod ban;
+pub mod service;
\ No newline at end of file
@@ -0,0 +1,161 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::Utc;
+use tokio::sync::{RwLock, broadcast};
+use uuid::Uuid;
+
+use crate::db::repository::BanRepository;
+use crate::error::{Error, Result};
+use crate::supervisor;
+use super::ban::{Ban, BanSpec};
+
+// Bans issued on another instance take effect here within this long
+const REFRESH_INTERVAL: Duration = Duration::from_secs(30);
+
+// Player bans, stored in the database with the active ones cached in memory so
+// the checks on connect and on join need no round trip.
+//
+// New bans are announced on a broadcast channel, both the ones issued here and
+// the ones picked up on refresh, so the gateway can disconnect the player
+// wherever they are connected.
+pub struct BanService {
+    repo: Arc<dyn BanRepository>,
+    active: RwLock<HashMap<Uuid, Ban>>,
+    issued: broadcast::Sender<Ban>,
+}
+
+impl BanService {
+    // Load the active bans and keep them fresh. A database failure here is not
+    // fatal: the server starts with no bans and picks them up on refresh.
+    pub async fn init(repo: Arc<dyn BanRepository>) -> Arc<Self> {
+        let (issued, _) = broadcast::channel(64);
+        let service = Arc::new(Self {
+            repo,
+            active: RwLock::new(HashMap::new()),
+            issued,
+        });
+        if let Err(e) = service.reload().await {
+            tracing::warn!("Failed to load bans, starting without any: {}", e);
+        }
+
+        let refresher = service.clone();
+        supervisor::spawn_loop("ban_refresh", move || {
+            let refresher = refresher.clone();
+            async move {
+                let mut interval = tokio::time::interval(REFRESH_INTERVAL);
+                interval.tick().await;
+                loop {
+                    interval.tick().await;
+                    if let Err(e) = refresher.reload().await {
+                        tracing::warn!("Failed to refresh bans: {}", e);
+                    }
+                }
+            }
+        });
+        service
+    }
+
+    // Replace the cached bans with the ones in force, announcing the new ones
+    pub async fn reload(&self) -> Result<()> {
+        let loaded: HashMap<Uuid, Ban> = self.repo.active_bans(Utc::now()).await?
+            .into_iter()
+            .map(|ban| (ban.user_id, ban))
+            .collect();
+        let mut active = self.active.write().await;
+        for ban in loaded.values() {
+            if active.get(&ban.user_id).is_none_or(|known| known.id != ban.id) {
+                let _ = self.issued.send(ban.clone());
+            }
+        }
+        *active = loaded;
+        Ok(())
+    }
+
+    // Bans as they are issued, on this instance or another
+    pub fn subscribe(&self) -> broadcast::Receiver<Ban> {
+        self.issued.subscribe()
+    }
+
+    pub async fn active_ban(&self, user_id: Uuid) -> Option<Ban> {
+        self.active
+            .read()
+            .await
+            .get(&user_id)
+            .filter(|ban| ban.is_active(Utc::now()))
+            .cloned()
+    }
+
+    pub async fn ensure_not_banned(&self, user_id: Uuid) -> Result<()> {
+        match self.active_ban(user_id).await {
+            Some(_) => Err(Error::Banned),
+            None => Ok(()),
+        }
+    }
+
+    // Active bans, newest first
+    pub async fn list(&self) -> Vec<Ban> {
+        let now = Utc::now();
+        let mut bans: Vec<Ban> = self.active
+            .read()
+            .await
+            .values()
+            .filter(|ban| ban.is_active(now))
+            .cloned()
+            .collect();
+        bans.sort_by_key(|ban| std::cmp::Reverse(ban.created_at));
+        bans
+    }
+
+    // Every ban of a player, lifted and expired ones included, newest first
+    pub async fn history(&self, user_id: Uuid) -> Result<Vec<Ban>> {
+        self.repo.user_bans(user_id).await
+    }
+
+    // Ban a player; an active ban they already have is replaced
+    pub async fn issue(&self, user_id: Uuid, spec: BanSpec) -> Result<Ban> {
+        if spec.issued_by.trim().is_empty() || spec.reason.trim().is_empty() || spec.duration_secs == Some(0) {
+            return Err(Error::InvalidMessage);
+        }
+
+        let created_at = Utc::now();
+        let expires_at = match spec.duration_secs {
+            Some(secs) => {
+                let length = chrono::Duration::try_seconds(i64::try_from(secs).map_err(|_| Error::InvalidMessage)?)
+                    .ok_or(Error::InvalidMessage)?;
+                Some(created_at.checked_add_signed(length).ok_or(Error::InvalidMessage)?)
+            }
+            None => None,
+        };
+        let ban = Ban {
+            id: Uuid::new_v4(),
+            user_id,
+            reason: spec.reason,
+            issued_by: spec.issued_by,
+            created_at,
+            expires_at,
+            lifted_at: None,
+            lifted_by: None,
+        };
+
+        self.repo.insert_ban(&ban).await?;
+        self.active.write().await.insert(user_id, ban.clone());
+        tracing::info!("User {} banned by {} until {:?}: {}", user_id, ban.issued_by, ban.expires_at, ban.reason);
+        let _ = self.issued.send(ban.clone());
+        Ok(ban)
+    }
+
+    pub async fn lift(&self, user_id: Uuid, lifted_by: &str) -> Result<()> {
+        if lifted_by.trim().is_empty() {
+            return Err(Error::InvalidMessage);
+        }
+        let lifted = self.repo.lift_bans(user_id, lifted_by, Utc::now()).await?;
+        self.active.write().await.remove(&user_id);
+        if lifted == 0 {
+            return Err(Error::NotFound(format!("active ban of {}", user_id)));
+        }
+        tracing::info!("Ban of user {} lifted by {}", user_id, lifted_by);
+        Ok(())
+    }
+}
@@ -0,0 +1,106 @@
+use chrono::{DateTime, Duration, NaiveTime, Utc};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+// Furthest UTC offsets in use, in minutes
+const MIN_UTC_OFFSET_MINS: i32 = -12 * 60;
+const MAX_UTC_OFFSET_MINS: i32 = 14 * 60;
+
+// What a notification is about, for the player's preferences
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NotificationKind {
+    MatchFound,
+    // Nothing sends these yet; the preference is kept for clients that show it
+    #[allow(dead_code)]
+    FriendRequest,
+    Announcement,
+    // Anything else the player can't turn off
+    Other,
+}
+
+// Hours of the player's day without push notifications. Runs past midnight
+// when `end` is before `start`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct QuietHours {
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+    // The player's time zone, as minutes ahead of UTC
+    pub utc_offset_mins: i32,
+}
+
+impl QuietHours {
+    pub fn is_valid(&self) -> bool {
+        (MIN_UTC_OFFSET_MINS..=MAX_UTC_OFFSET_MINS).contains(&self.utc_offset_mins)
+    }
+
+    pub fn contains(&self, at: DateTime<Utc>) -> bool {
+        let local = (at + Duration::minutes(self.utc_offset_mins as i64)).time();
+        if self.start <= self.end {
+            self.start <= local && local < self.end
+        } else {
+            local >= self.start || local < self.end
+        }
+    }
+}
+
+// Which notifications a player wants. Players who never set any get the
+// defaults: everything on, no quiet hours.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct NotificationPreferences {
+    pub user_id: Uuid,
+    // Push when a match starts while the player is away
+    pub match_found: bool,
+    pub friend_requests: bool,
+    // sys.announcement messages from the operators
+    pub announcements: bool,
+    pub quiet_hours: Option<QuietHours>,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+impl NotificationPreferences {
+    pub fn defaults(user_id: Uuid) -> Self {
+        Self {
+            user_id,
+            match_found: true,
+            friend_requests: true,
+            announcements: true,
+            quiet_hours: None,
+            updated_at: None,
+        }
+    }
+
+    pub fn wants(&self, kind: NotificationKind) -> bool {
+        match kind {
+            NotificationKind::MatchFound => self.match_found,
+            NotificationKind::FriendRequest => self.friend_requests,
+            NotificationKind::Announcement => self.announcements,
+            NotificationKind::Other => true,
+        }
+    }
+
+    // Whether to push a notification of this kind to the player's devices now
+    pub fn wants_push(&self, kind: NotificationKind, now: DateTime<Utc>) -> bool {
+        self.wants(kind) && !self.quiet_hours.is_some_and(|quiet| quiet.contains(now))
+    }
+}
+
+// user.update_preferences request; fields left out keep their value, and
+// `quiet_hours: null` turns quiet hours off
+#[derive(Debug, Clone, Default, Deserialize, JsonSchema)]
+pub struct PreferencesUpdate {
+    pub match_found: Option<bool>,
+    pub friend_requests: Option<bool>,
+    pub announcements: Option<bool>,
+    #[serde(default, deserialize_with = "present")]
+    pub quiet_hours: Option<Option<QuietHours>>,
+}
+
+// Tells a null field (Some(None)) from a missing one (None)
+fn present<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
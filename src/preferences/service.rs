@@ -0,0 +1,89 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use chrono::Utc;
+use uuid::Uuid;
+
+use crate::db::repository::PreferenceRepository;
+use crate::error::{Error, Result};
+use super::preferences::{NotificationKind, NotificationPreferences, PreferencesUpdate};
+
+// Notification preferences of players.
+//
+// Players set them with user.update_preferences. The gateway asks which
+// players want a notification before pushing it to their devices or sending
+// them an announcement; players who turned a kind off still get the events
+// held in their inbox when they connect. When the preferences can't be
+// loaded, everyone is notified.
+pub struct PreferenceService {
+    repo: Arc<dyn PreferenceRepository>,
+}
+
+impl PreferenceService {
+    pub fn init(repo: Arc<dyn PreferenceRepository>) -> Arc<Self> {
+        Arc::new(Self { repo })
+    }
+
+    pub async fn get(&self, user_id: Uuid) -> Result<NotificationPreferences> {
+        let stored = self.repo.get_preferences(&[user_id]).await?;
+        Ok(stored.into_iter().next().unwrap_or_else(|| NotificationPreferences::defaults(user_id)))
+    }
+
+    pub async fn update(&self, user_id: Uuid, update: PreferencesUpdate) -> Result<NotificationPreferences> {
+        if update.quiet_hours.flatten().is_some_and(|quiet| !quiet.is_valid()) {
+            return Err(Error::InvalidMessage);
+        }
+        let mut preferences = self.get(user_id).await?;
+        if let Some(match_found) = update.match_found {
+            preferences.match_found = match_found;
+        }
+        if let Some(friend_requests) = update.friend_requests {
+            preferences.friend_requests = friend_requests;
+        }
+        if let Some(announcements) = update.announcements {
+            preferences.announcements = announcements;
+        }
+        if let Some(quiet_hours) = update.quiet_hours {
+            preferences.quiet_hours = quiet_hours;
+        }
+        preferences.updated_at = Some(Utc::now());
+        self.repo.upsert_preferences(&preferences).await?;
+        Ok(preferences)
+    }
+
+    async fn for_users(&self, user_ids: &[Uuid]) -> HashMap<Uuid, NotificationPreferences> {
+        if user_ids.is_empty() {
+            return HashMap::new();
+        }
+        match self.repo.get_preferences(user_ids).await {
+            Ok(stored) => stored.into_iter().map(|preferences| (preferences.user_id, preferences)).collect(),
+            Err(e) => {
+                tracing::warn!("Failed to load notification preferences of {} players: {}", user_ids.len(), e);
+                HashMap::new()
+            }
+        }
+    }
+
+    // Those of these players who want notifications of this kind
+    pub async fn wanting(&self, user_ids: &[Uuid], kind: NotificationKind) -> Vec<Uuid> {
+        if kind == NotificationKind::Other {
+            return user_ids.to_vec();
+        }
+        let preferences = self.for_users(user_ids).await;
+        user_ids.iter()
+            .filter(|user_id| preferences.get(user_id).is_none_or(|p| p.wants(kind)))
+            .copied()
+            .collect()
+    }
+
+    // Those of these players to push a notification of this kind to now,
+    // leaving out the ones in their quiet hours
+    pub async fn pushable(&self, user_ids: &[Uuid], kind: NotificationKind) -> Vec<Uuid> {
+        let preferences = self.for_users(user_ids).await;
+        let now = Utc::now();
+        user_ids.iter()
+            .filter(|user_id| preferences.get(user_id).is_none_or(|p| p.wants_push(kind, now)))
+            .copied()
+            .collect()
+    }
+}
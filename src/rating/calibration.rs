@@ -0,0 +1,170 @@
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use super::mmr::expected_result;
+
+// Probability buckets of the report, 10% wide
+const BUCKETS: usize = 10;
+// Matches needed before a better RATING_SCALE is suggested
+const MIN_FIT_MATCHES: usize = 50;
+// RATING_SCALE values tried when looking for the best fit
+const SCALE_RANGE: (u32, u32, u32) = (100, 1200, 10);
+// Keeps the log loss finite for predictions of 0 or 1
+const EPSILON: f64 = 1e-6;
+
+// A finished match with what was predicted for its teams at the start
+#[derive(Debug, Clone, Deserialize)]
+pub struct MatchPrediction {
+    pub end_time: DateTime<Utc>,
+    pub winner_team_id: Option<Uuid>,
+    pub teams: Vec<TeamPrediction>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct TeamPrediction {
+    pub team_id: Uuid,
+    // Average MMR of the team at the start
+    pub mmr: f64,
+    pub win_probability: f64,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct CalibrationBucket {
+    // Predicted probabilities in [from, to)
+    pub from: f64,
+    pub to: f64,
+    pub predictions: usize,
+    // Average predicted probability
+    pub predicted: f64,
+    // How often those teams won; draws count half
+    pub won: f64,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct DailyError {
+    pub day: NaiveDate,
+    pub matches: usize,
+    pub brier_score: f64,
+}
+
+// Win predictions made at match start compared with how the matches ended
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct CalibrationReport {
+    pub since: DateTime<Utc>,
+    pub matches: usize,
+    // One per team
+    pub predictions: usize,
+    // RATING_SCALE in use
+    pub scale: f64,
+    // Mean squared error of the predictions; 0.25 for always predicting 50%
+    pub brier_score: f64,
+    pub log_loss: f64,
+    pub buckets: Vec<CalibrationBucket>,
+    // Prediction error per day of match end, oldest first
+    pub daily: Vec<DailyError>,
+    // RATING_SCALE that would have predicted these outcomes best, from the
+    // teams' MMR; None with fewer than 50 matches
+    pub suggested_scale: Option<f64>,
+}
+
+// Result of a team: 1 for a win, 0.5 for a draw, 0 for a loss
+fn outcome(prediction: &MatchPrediction, team_id: Uuid) -> f64 {
+    match prediction.winner_team_id {
+        Some(winner) if winner == team_id => 1.0,
+        Some(_) => 0.0,
+        None => 0.5,
+    }
+}
+
+fn log_loss(predicted: f64, actual: f64) -> f64 {
+    let p = predicted.clamp(EPSILON, 1.0 - EPSILON);
+    -(actual * p.ln() + (1.0 - actual) * (1.0 - p).ln())
+}
+
+// Only two-team matches are rated, so only those are judged
+pub fn report(since: DateTime<Utc>, matches: &[MatchPrediction], scale: f64) -> CalibrationReport {
+    let matches: Vec<&MatchPrediction> = matches.iter().filter(|m| m.teams.len() == 2).collect();
+
+    let mut squared_error = 0.0;
+    let mut loss = 0.0;
+    let mut predictions = 0;
+    let mut buckets = vec![(0usize, 0.0, 0.0); BUCKETS];
+    let mut daily: BTreeMap<NaiveDate, (usize, f64, usize)> = BTreeMap::new();
+    for prediction in &matches {
+        let day = daily.entry(prediction.end_time.date_naive()).or_default();
+        day.0 += 1;
+        for team in &prediction.teams {
+            let actual = outcome(prediction, team.team_id);
+            let error = (team.win_probability - actual).powi(2);
+            squared_error += error;
+            loss += log_loss(team.win_probability, actual);
+            predictions += 1;
+            day.1 += error;
+            day.2 += 1;
+
+            let index = ((team.win_probability * BUCKETS as f64) as usize).min(BUCKETS - 1);
+            let bucket = &mut buckets[index];
+            bucket.0 += 1;
+            bucket.1 += team.win_probability;
+            bucket.2 += actual;
+        }
+    }
+    let average = |sum: f64, count: usize| if count == 0 { 0.0 } else { sum / count as f64 };
+
+    CalibrationReport {
+        since,
+        matches: matches.len(),
+        predictions,
+        scale,
+        brier_score: average(squared_error, predictions),
+        log_loss: average(loss, predictions),
+        buckets: buckets
+            .into_iter()
+            .enumerate()
+            .map(|(index, (count, predicted, won))| CalibrationBucket {
+                from: index as f64 / BUCKETS as f64,
+                to: (index + 1) as f64 / BUCKETS as f64,
+                predictions: count,
+                predicted: average(predicted, count),
+                won: average(won, count),
+            })
+            .collect(),
+        daily: daily
+            .into_iter()
+            .map(|(day, (count, error, predictions))| DailyError {
+                day,
+                matches: count,
+                brier_score: average(error, predictions),
+            })
+            .collect(),
+        suggested_scale: best_scale(&matches),
+    }
+}
+
+// Scale with the lowest log loss over the first team of every match; the
+// second team's prediction is its complement and adds nothing
+fn best_scale(matches: &[&MatchPrediction]) -> Option<f64> {
+    if matches.len() < MIN_FIT_MATCHES {
+        return None;
+    }
+    let (from, to, step) = SCALE_RANGE;
+    (from..=to)
+        .step_by(step as usize)
+        .map(|scale| {
+            let scale = f64::from(scale);
+            let loss: f64 = matches
+                .iter()
+                .map(|m| {
+                    let (team, opponent) = (&m.teams[0], &m.teams[1]);
+                    log_loss(expected_result(team.mmr, opponent.mmr, scale), outcome(m, team.team_id))
+                })
+                .sum();
+            (scale, loss)
+        })
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(scale, _)| scale)
+}
@@ -0,0 +1,254 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::config::{RatingConfig, SmurfConfig};
+use crate::models::game::MatchScores;
+
+// A player's matchmaking rating
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct PlayerRating {
+    pub user_id: Uuid,
+    pub mmr: f64,
+    // Rated matches played
+    pub matches_played: i32,
+    pub wins: i32,
+    // Wins and summed score percentiles over the first SMURF_WINDOW_MATCHES matches
+    pub early_wins: i32,
+    pub early_percentile_sum: f64,
+    // Plays like an experienced player on a new account
+    pub smurf_suspected: bool,
+    // When the player was put on the smurf review list
+    pub flagged_at: Option<DateTime<Utc>>,
+    // An admin found the suspicion wrong; the player is never suspected again
+    pub smurf_cleared: bool,
+}
+
+// Visible rank, derived from MMR once placement is over
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Tier {
+    Bronze,
+    Silver,
+    Gold,
+    Platinum,
+    Diamond,
+}
+
+impl Tier {
+    pub fn from_mmr(mmr: f64) -> Self {
+        if mmr < 1300.0 {
+            Tier::Bronze
+        } else if mmr < 1450.0 {
+            Tier::Silver
+        } else if mmr < 1600.0 {
+            Tier::Gold
+        } else if mmr < 1750.0 {
+            Tier::Platinum
+        } else {
+            Tier::Diamond
+        }
+    }
+}
+
+// Summary of a player's placement, sent to them as `rank.placed` after their last placement match
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct RankPlacement {
+    pub user_id: Uuid,
+    pub tier: Tier,
+    pub mmr: i32,
+    pub matches: i32,
+    pub wins: i32,
+}
+
+// A row of GET /api/leaderboard
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct LeaderboardEntry {
+    // 1-based
+    pub rank: usize,
+    pub user_id: Uuid,
+    pub tier: Tier,
+    pub mmr: i32,
+    pub matches: i32,
+    pub wins: i32,
+}
+
+// How one player did in a rated match
+#[derive(Debug, Clone)]
+pub struct MatchOutcome {
+    pub user_id: Uuid,
+    // 1 for a win, 0.5 for a draw, 0 for a loss
+    pub result: f64,
+    // Result expected from the MMRs of both sides
+    pub expected: f64,
+    // Share of the other players in the match who scored less
+    pub percentile: f64,
+}
+
+impl PlayerRating {
+    pub fn new(user_id: Uuid, initial_mmr: f64) -> Self {
+        Self {
+            user_id,
+            mmr: initial_mmr,
+            matches_played: 0,
+            wins: 0,
+            early_wins: 0,
+            early_percentile_sum: 0.0,
+            smurf_suspected: false,
+            flagged_at: None,
+            smurf_cleared: false,
+        }
+    }
+
+    // Still playing placement matches; the rating is provisional and not shown as a rank
+    pub fn provisional(&self, config: &RatingConfig) -> bool {
+        self.matches_played < config.placement_matches
+    }
+
+    pub fn placement(&self) -> RankPlacement {
+        RankPlacement {
+            user_id: self.user_id,
+            tier: Tier::from_mmr(self.mmr),
+            mmr: self.mmr.round() as i32,
+            matches: self.matches_played,
+            wins: self.wins,
+        }
+    }
+
+    // Suspected smurfs climb faster and are bracketed higher until they have
+    // played twice the detection window; their MMR has caught up by then
+    pub fn accelerated(&self, config: &SmurfConfig) -> bool {
+        self.smurf_suspected && self.matches_played < 2 * config.window_matches
+    }
+
+    // MMR the player is matched by
+    pub fn matchmaking_mmr(&self, config: &RatingConfig) -> f64 {
+        let boost = if self.accelerated(&config.smurf) { config.smurf.bracket_boost } else { 0.0 };
+        self.mmr + boost
+    }
+
+    // MMR bracket whose pools the player queues in
+    pub fn bracket(&self, config: &RatingConfig) -> i32 {
+        bracket_of(self.matchmaking_mmr(config), config)
+    }
+
+    pub fn apply(&mut self, outcome: &MatchOutcome, config: &RatingConfig) {
+        let mut k = if self.provisional(config) { config.placement_k_factor } else { config.k_factor };
+        if self.accelerated(&config.smurf) {
+            k *= config.smurf.k_multiplier;
+        }
+        self.mmr += k * (outcome.result - outcome.expected);
+
+        let won = outcome.result >= 1.0;
+        if self.matches_played < config.smurf.window_matches {
+            self.early_percentile_sum += outcome.percentile;
+            if won {
+                self.early_wins += 1;
+            }
+        }
+        self.matches_played += 1;
+        if won {
+            self.wins += 1;
+        }
+    }
+
+    // Judge the player's first matches; true when they have just become a suspect
+    pub fn detect_smurf(&mut self, config: &SmurfConfig) -> bool {
+        if self.smurf_suspected || self.smurf_cleared || self.matches_played > config.window_matches {
+            return false;
+        }
+        let early = self.matches_played.min(config.window_matches);
+        if early < config.min_matches.max(1) {
+            return false;
+        }
+        let win_rate = f64::from(self.early_wins) / f64::from(early);
+        let percentile = self.early_percentile_sum / f64::from(early);
+        if win_rate >= config.win_rate && percentile >= config.score_percentile {
+            self.smurf_suspected = true;
+        }
+        self.smurf_suspected
+    }
+}
+
+pub fn bracket_of(mmr: f64, config: &RatingConfig) -> i32 {
+    if config.bracket_width <= 0.0 {
+        return 0;
+    }
+    (mmr / config.bracket_width).floor() as i32
+}
+
+// MMR a premade party is matched by. Parties carry weaker members and play
+// coordinated, so the average alone underrates them: the strongest member is
+// weighted in and every extra member adds a bonus.
+pub fn party_mmr(members: &[PlayerRating], config: &RatingConfig) -> f64 {
+    let mmrs = || members.iter().map(|m| m.matchmaking_mmr(config));
+    let Some(average) = mean(mmrs()) else {
+        return config.initial_mmr;
+    };
+    let top = mmrs().fold(f64::MIN, f64::max);
+    let weight = config.party.top_weight;
+    let extra = members.len().saturating_sub(1) as f64;
+    (1.0 - weight) * average + weight * top + config.party.member_bonus * extra
+}
+
+// Chance that a side rated `own` beats one rated `opponent`
+pub fn expected_result(own: f64, opponent: f64, scale: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf((opponent - own) / scale))
+}
+
+pub fn mean(values: impl Iterator<Item = f64>) -> Option<f64> {
+    let (sum, count) = values.fold((0.0, 0u32), |(sum, count), v| (sum + v, count + 1));
+    (count > 0).then(|| sum / f64::from(count))
+}
+
+// Outcome of every member of a finished match. A team's strength is the
+// average MMR of its members; a match without a winner is a draw.
+pub fn match_outcomes(scores: &MatchScores, ratings: &HashMap<Uuid, PlayerRating>, config: &RatingConfig) -> Vec<MatchOutcome> {
+    let initial_mmr = config.initial_mmr;
+    let mmr_of = |user_id: &Uuid| ratings.get(user_id).map_or(initial_mmr, |r| r.mmr);
+
+    scores
+        .members
+        .iter()
+        .map(|member| {
+            let own = mean(scores.members.iter().filter(|m| m.team_id == member.team_id).map(|m| mmr_of(&m.user_id)))
+                .unwrap_or(initial_mmr);
+            let opponent = mean(scores.members.iter().filter(|m| m.team_id != member.team_id).map(|m| mmr_of(&m.user_id)))
+                .unwrap_or(own);
+            let result = match scores.winner_team_id {
+                Some(winner) if winner == member.team_id => 1.0,
+                Some(_) => 0.0,
+                None => 0.5,
+            };
+
+            // Ties count half
+            let others = scores.members.len().saturating_sub(1);
+            let percentile = if others == 0 {
+                0.5
+            } else {
+                let below: f64 = scores
+                    .members
+                    .iter()
+                    .filter(|m| m.user_id != member.user_id)
+                    .map(|m| match m.individual_score.cmp(&member.individual_score) {
+                        std::cmp::Ordering::Less => 1.0,
+                        std::cmp::Ordering::Equal => 0.5,
+                        std::cmp::Ordering::Greater => 0.0,
+                    })
+                    .sum();
+                below / others as f64
+            };
+
+            MatchOutcome {
+                user_id: member.user_id,
+                result,
+                expected: expected_result(own, opponent, config.scale),
+                percentile,
+            }
+        })
+        .collect()
+}
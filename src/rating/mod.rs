@@ -0,0 +1,3 @@
+pub mod calibration;
+pub mod mmr;
+pub mod service;
\ No newline at end of file
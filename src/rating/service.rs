@@ -0,0 +1,159 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use chrono::Utc;
+use uuid::Uuid;
+
+use crate::config::RatingConfig;
+use crate::db::repository::{MatchRepository, RatingRepository};
+use crate::error::Result;
+use crate::models::game::{MatchScores, MatchStatus};
+use super::calibration::{self, CalibrationReport};
+use super::mmr::{LeaderboardEntry, PlayerRating, RankPlacement, Tier, bracket_of, expected_result, match_outcomes, party_mmr};
+
+// Matchmaking ratings, updated with an Elo rule after every finished match.
+//
+// New players first play RATING_PLACEMENT_MATCHES placement matches on a
+// provisional rating that moves faster and is matched more loosely; after the
+// last one they are given their starting tier.
+//
+// The first SMURF_WINDOW_MATCHES matches of each player are also checked for
+// experienced players on new accounts: a suspect's MMR moves faster, they are
+// bracketed higher, and they are listed for admin review.
+//
+// Premade parties are bracketed by an MMR adjusted upwards for playing
+// together (PARTY_TOP_MMR_WEIGHT, PARTY_MEMBER_MMR_BONUS).
+//
+// Each team's chance of winning is predicted when its match starts; comparing
+// the predictions with the results shows whether RATING_SCALE fits the game.
+pub struct RatingService {
+    config: RatingConfig,
+    repo: Arc<dyn RatingRepository>,
+    matches: Arc<dyn MatchRepository>,
+}
+
+impl RatingService {
+    pub fn new(config: RatingConfig, repo: Arc<dyn RatingRepository>, matches: Arc<dyn MatchRepository>) -> Arc<Self> {
+        Arc::new(Self { config, repo, matches })
+    }
+
+    // Stored rating, or the initial one for players who haven't finished a match
+    pub async fn rating(&self, user_id: Uuid) -> Result<PlayerRating> {
+        let rating = self.repo.get_ratings(&[user_id]).await?.into_iter().next();
+        Ok(rating.unwrap_or_else(|| self.initial(user_id)))
+    }
+
+    // Best ranked players; players still in placement have no rank yet
+    pub async fn leaderboard(&self, limit: usize) -> Result<Vec<LeaderboardEntry>> {
+        let top = self.repo.top_ratings(self.config.placement_matches, limit).await?;
+        Ok(top.into_iter()
+            .enumerate()
+            .map(|(i, rating)| LeaderboardEntry {
+                rank: i + 1,
+                user_id: rating.user_id,
+                tier: Tier::from_mmr(rating.mmr),
+                mmr: rating.mmr.round() as i32,
+                matches: rating.matches_played,
+                wins: rating.wins,
+            })
+            .collect())
+    }
+
+    // Rating of a player who hasn't finished a match
+    pub fn initial(&self, user_id: Uuid) -> PlayerRating {
+        PlayerRating::new(user_id, self.config.initial_mmr)
+    }
+
+    pub fn bracket(&self, rating: &PlayerRating) -> i32 {
+        rating.bracket(&self.config)
+    }
+
+    // Bracket of a premade party, from its adjusted MMR
+    pub fn party_bracket(&self, members: &[PlayerRating]) -> i32 {
+        bracket_of(party_mmr(members, &self.config), &self.config)
+    }
+
+    // Chance that a team of average MMR `own` beats one of average MMR `opponent`
+    pub fn win_probability(&self, own: f64, opponent: f64) -> f64 {
+        expected_result(own, opponent, self.config.scale)
+    }
+
+    // Predictions of the matches finished in the last `days` days against their results
+    pub async fn calibration(&self, days: u32) -> Result<CalibrationReport> {
+        let since = Utc::now() - chrono::Duration::days(i64::from(days));
+        let predictions = self.matches.finished_predictions(since).await?;
+        Ok(calibration::report(since, &predictions, self.config.scale))
+    }
+
+    // Largest party allowed to queue together
+    pub fn max_party_size(&self) -> usize {
+        self.config.party.max_size
+    }
+
+    pub fn in_placement(&self, rating: &PlayerRating) -> bool {
+        rating.provisional(&self.config)
+    }
+
+    // How many brackets away from their own players in placement may be matched
+    pub fn placement_spread(&self) -> i32 {
+        self.config.placement_bracket_spread
+    }
+
+    // Bracket of players without a rating, also used while the database is offline
+    pub fn initial_bracket(&self) -> i32 {
+        self.initial(Uuid::nil()).bracket(&self.config)
+    }
+
+    // Rate every member of a finished match; returns the players who have
+    // just finished placement
+    pub async fn record_match(&self, scores: &MatchScores) -> Result<Vec<RankPlacement>> {
+        if scores.status != MatchStatus::Finished || scores.members.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let user_ids: Vec<Uuid> = scores.members.iter().map(|m| m.user_id).collect();
+        let mut ratings: HashMap<Uuid, PlayerRating> = self.repo.get_ratings(&user_ids).await?
+            .into_iter()
+            .map(|rating| (rating.user_id, rating))
+            .collect();
+
+        let outcomes = match_outcomes(scores, &ratings, &self.config);
+        let mut updated = Vec::with_capacity(outcomes.len());
+        let mut placed = Vec::new();
+        for outcome in &outcomes {
+            let mut rating = ratings
+                .remove(&outcome.user_id)
+                .unwrap_or_else(|| self.initial(outcome.user_id));
+            let was_provisional = rating.provisional(&self.config);
+            rating.apply(outcome, &self.config);
+            if was_provisional && !rating.provisional(&self.config) {
+                placed.push(rating.placement());
+            }
+            if rating.detect_smurf(&self.config.smurf) {
+                tracing::warn!(
+                    "User {} suspected of smurfing: {} wins in {} matches, MMR {:.0}",
+                    rating.user_id, rating.early_wins, rating.matches_played, rating.mmr
+                );
+                if self.config.smurf.flag_for_review {
+                    rating.flagged_at = Some(Utc::now());
+                }
+            }
+            updated.push(rating);
+        }
+
+        self.repo.upsert_ratings(&updated).await?;
+        Ok(placed)
+    }
+
+    // Suspected smurfs waiting for review, most recently flagged first
+    pub async fn flagged(&self) -> Result<Vec<PlayerRating>> {
+        self.repo.flagged_ratings().await
+    }
+
+    // Clear a suspicion an admin found to be wrong
+    pub async fn clear_smurf(&self, user_id: Uuid) -> Result<()> {
+        self.repo.clear_smurf(user_id).await?;
+        tracing::info!("Smurf suspicion of user {} cleared by admin", user_id);
+        Ok(())
+    }
+}
@@ -0,0 +1,94 @@
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use utoipa::ToSchema;
+
+use crate::error::{Error, Result};
+
+// Tunables for clients (feature flags, timers, UI toggles). Every change
+// bumps `version`; version 0 is the empty document before any change.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, JsonSchema)]
+pub struct ClientConfig {
+    pub version: u64,
+    #[schema(value_type = Object)]
+    pub values: Map<String, Value>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl ClientConfig {
+    pub fn empty() -> Self {
+        Self {
+            version: 0,
+            values: Map::new(),
+            updated_at: DateTime::<Utc>::UNIX_EPOCH,
+        }
+    }
+}
+
+// One key changed by an update; absent old/new means added/removed
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct FieldChange {
+    pub key: String,
+    #[schema(value_type = Object)]
+    pub old: Option<Value>,
+    #[schema(value_type = Object)]
+    pub new: Option<Value>,
+}
+
+// Audit trail entry: who produced a version and what it changed
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ConfigChange {
+    pub version: u64,
+    pub changed_by: String,
+    pub changed_at: DateTime<Utc>,
+    pub changes: Vec<FieldChange>,
+}
+
+// PATCH /admin/config/client body
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+pub struct ConfigUpdate {
+    // Recorded in the audit trail
+    pub changed_by: String,
+    // Reject the update if the document moved past this version meanwhile
+    pub expected_version: Option<u64>,
+    // Keys to add or overwrite
+    #[serde(default)]
+    #[schema(value_type = Object)]
+    pub set: Map<String, Value>,
+    // Keys to delete
+    #[serde(default)]
+    pub remove: Vec<String>,
+}
+
+impl ConfigUpdate {
+    // Apply to `current`; returns the new values and what changed
+    pub fn apply(&self, current: &Map<String, Value>) -> Result<(Map<String, Value>, Vec<FieldChange>)> {
+        if self.changed_by.trim().is_empty() || self.set.keys().any(|k| k.is_empty()) {
+            return Err(Error::InvalidMessage);
+        }
+
+        let mut values = current.clone();
+        let mut changes = Vec::new();
+        for (key, value) in &self.set {
+            let old = values.insert(key.clone(), value.clone());
+            if old.as_ref() != Some(value) {
+                changes.push(FieldChange {
+                    key: key.clone(),
+                    old,
+                    new: Some(value.clone()),
+                });
+            }
+        }
+        for key in &self.remove {
+            if let Some(old) = values.remove(key) {
+                changes.push(FieldChange {
+                    key: key.clone(),
+                    old: Some(old),
+                    new: None,
+                });
+            }
+        }
+        Ok((values, changes))
+    }
+}
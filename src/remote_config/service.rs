@@ -0,0 +1,116 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::Utc;
+use tokio::sync::{Mutex, RwLock};
+
+use crate::db::keyset::KeysetQuery;
+use crate::db::repository::RemoteConfigRepository;
+use crate::error::{Error, Result};
+use crate::supervisor;
+use super::document::{ClientConfig, ConfigChange, ConfigUpdate};
+
+// Polling interval for a newer config version
+const REFRESH_INTERVAL: Duration = Duration::from_secs(30);
+
+// The client config document, stored as one database row per version (which
+// doubles as the audit trail) and cached in memory for the welcome message
+pub struct RemoteConfigService {
+    repo: Arc<dyn RemoteConfigRepository>,
+    current: RwLock<ClientConfig>,
+    // Serializes updates on this instance; the version's primary key catches the rest
+    updates: Mutex<()>,
+}
+
+impl RemoteConfigService {
+    // Load the latest version and keep it fresh. Starts from the empty
+    // document if the database can't be read yet.
+    pub async fn init(repo: Arc<dyn RemoteConfigRepository>) -> Arc<Self> {
+        let service = Arc::new(Self {
+            repo,
+            current: RwLock::new(ClientConfig::empty()),
+            updates: Mutex::new(()),
+        });
+        if let Err(e) = service.reload().await {
+            tracing::warn!("Failed to load client config, starting with an empty one: {}", e);
+        }
+
+        let refresher = service.clone();
+        supervisor::spawn_loop("client_config_refresh", move || {
+            let refresher = refresher.clone();
+            async move {
+                let mut interval = tokio::time::interval(REFRESH_INTERVAL);
+                interval.tick().await;
+                loop {
+                    interval.tick().await;
+                    if let Err(e) = refresher.reload().await {
+                        tracing::warn!("Failed to refresh client config: {}", e);
+                    }
+                }
+            }
+        });
+        service
+    }
+
+    // Move to the latest stored version if it is newer than ours
+    pub async fn reload(&self) -> Result<()> {
+        if let Some(latest) = self.repo.latest_config().await? {
+            let mut current = self.current.write().await;
+            if latest.version > current.version {
+                *current = latest;
+            }
+        }
+        Ok(())
+    }
+
+    pub async fn current(&self) -> ClientConfig {
+        self.current.read().await.clone()
+    }
+
+    // Apply an update as a new version. An update that changes nothing
+    // returns the current document without creating a version.
+    pub async fn update(&self, update: ConfigUpdate) -> Result<ClientConfig> {
+        let _guard = self.updates.lock().await;
+        let current = self.current().await;
+        if update.expected_version.is_some_and(|v| v != current.version) {
+            return Err(Error::VersionConflict);
+        }
+
+        let (values, changes) = update.apply(&current.values)?;
+        if changes.is_empty() {
+            return Ok(current);
+        }
+
+        let now = Utc::now();
+        let next = ClientConfig {
+            version: current.version + 1,
+            values,
+            updated_at: now,
+        };
+        let change = ConfigChange {
+            version: next.version,
+            changed_by: update.changed_by.trim().to_string(),
+            changed_at: now,
+            changes,
+        };
+        // Another instance already wrote this version
+        self.repo.insert_config_version(&next, &change).await.map_err(|e| match e {
+            Error::DuplicateKey(_) => Error::VersionConflict,
+            other => other,
+        })?;
+
+        tracing::info!(
+            "Client config v{} by {}: {:?}",
+            next.version,
+            change.changed_by,
+            change.changes.iter().map(|c| &c.key).collect::<Vec<_>>()
+        );
+        *self.current.write().await = next.clone();
+        Ok(next)
+    }
+
+    // One page of the changes and how many match its filters
+    pub async fn history(&self, page: &KeysetQuery) -> Result<(Vec<ConfigChange>, usize)> {
+        self.repo.config_history(page).await
+    }
+}
@@ -0,0 +1,55 @@
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+
+use super::scheduled::{ScheduleStatus, ScheduledMatch};
+
+// Length of the calendar entry for matches without MATCH_DURATION_SECS
+const DEFAULT_LENGTH: Duration = Duration::from_secs(30 * 60);
+
+// iCalendar (RFC 5545) invite for a scheduled match, to add to a calendar.
+// Served again after changes, with a higher SEQUENCE, so calendars that
+// subscribed to it pick up a cancellation.
+pub fn invite(schedule: &ScheduledMatch, length: Option<Duration>, lobby_opens_before: Duration) -> String {
+    let ends_at = schedule.starts_at + chrono::Duration::from_std(length.unwrap_or(DEFAULT_LENGTH)).unwrap_or_default();
+    let (status, sequence) = match schedule.status {
+        ScheduleStatus::Cancelled => ("CANCELLED", 1),
+        _ => ("CONFIRMED", 0),
+    };
+    let description = format!(
+        "The private lobby opens {} minutes before the start. Join it with the invite code sent then.",
+        lobby_opens_before.as_secs() / 60
+    );
+
+    let lines = [
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        "PRODID:-//SPV//Scheduled matches//EN".to_string(),
+        "METHOD:PUBLISH".to_string(),
+        "BEGIN:VEVENT".to_string(),
+        format!("UID:{}@spv", schedule.id),
+        format!("SEQUENCE:{}", sequence),
+        format!("DTSTAMP:{}", timestamp(schedule.created_at)),
+        format!("DTSTART:{}", timestamp(schedule.starts_at)),
+        format!("DTEND:{}", timestamp(ends_at)),
+        format!("SUMMARY:{}", escape(&format!("{} treasure hunt", schedule.match_type))),
+        format!("DESCRIPTION:{}", escape(&description)),
+        format!("STATUS:{}", status),
+        "END:VEVENT".to_string(),
+        "END:VCALENDAR".to_string(),
+    ];
+    let mut ics = lines.join("\r\n");
+    ics.push_str("\r\n");
+    ics
+}
+
+fn timestamp(at: DateTime<Utc>) -> String {
+    at.format("%Y%m%dT%H%M%SZ").to_string()
+}
+
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
@@ -0,0 +1,3 @@
+pub mod calendar;
+pub mod scheduled;
+pub mod service;
@@ -0,0 +1,65 @@
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ScheduleStatus {
+    Scheduled,
+    // Players were reminded it is coming up
+    Reminded,
+    // Its private lobby is open
+    Open,
+    // Enough players were in the lobby at the start time
+    Started,
+    Cancelled,
+}
+
+impl ScheduleStatus {
+    // Still waiting for one of the steps leading up to the start
+    pub fn is_pending(self) -> bool {
+        matches!(self, ScheduleStatus::Scheduled | ScheduleStatus::Reminded | ScheduleStatus::Open)
+    }
+}
+
+// A private match planned for a later time. Its lobby opens shortly before
+// `starts_at`, and it is called off if fewer than `min_players` are in it then.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, JsonSchema)]
+pub struct ScheduledMatch {
+    pub id: Uuid,
+    pub match_type: String,
+    pub host: Uuid,
+    pub invited: Vec<Uuid>,
+    pub starts_at: DateTime<Utc>,
+    pub min_players: i32,
+    pub status: ScheduleStatus,
+    // The private lobby, once open
+    pub match_id: Option<Uuid>,
+    pub code: Option<String>,
+    pub cancelled_reason: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl ScheduledMatch {
+    // The host and everyone invited
+    pub fn participants(&self) -> Vec<Uuid> {
+        std::iter::once(self.host).chain(self.invited.iter().copied()).collect()
+    }
+}
+
+// What happened to a scheduled match, told to its participants
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScheduleNoticeKind {
+    Invited,
+    Reminder,
+    LobbyOpen,
+    Cancelled,
+}
+
+#[derive(Debug, Clone)]
+pub struct ScheduleNotice {
+    pub kind: ScheduleNoticeKind,
+    pub schedule: ScheduledMatch,
+}
@@ -0,0 +1,214 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use tokio::sync::broadcast;
+use uuid::Uuid;
+
+use crate::cluster::scheduler::{LeaderElection, spawn_singleton};
+use crate::config::ScheduleConfig;
+use crate::db::repository::ScheduleRepository;
+use crate::error::{Error, Result};
+use crate::matchmaking::service::MatchService;
+use super::calendar;
+use super::scheduled::{ScheduleNotice, ScheduleNoticeKind, ScheduleStatus, ScheduledMatch};
+
+// How often the leader moves scheduled matches along
+const TICK_INTERVAL: Duration = Duration::from_secs(15);
+
+// Private matches planned for a later time.
+//
+// Schedules live in the database. The leader instance polls for the ones
+// coming up: it reminds the participants SCHEDULE_REMIND_MINS before the start,
+// opens the private lobby SCHEDULE_LOBBY_OPEN_MINS before it and sends everyone the
+// invite code, then at the start time starts the lobby with whoever joined,
+// or calls the match off if fewer than `min_players` did. Each step is
+// handed to the gateway as a notice for the participants.
+pub struct ScheduleService {
+    config: ScheduleConfig,
+    repo: Arc<dyn ScheduleRepository>,
+    match_service: Arc<MatchService>,
+    // Length of the calendar entries
+    match_duration: Option<Duration>,
+    notices: broadcast::Sender<ScheduleNotice>,
+}
+
+impl ScheduleService {
+    pub fn init(
+        config: ScheduleConfig,
+        repo: Arc<dyn ScheduleRepository>,
+        match_service: Arc<MatchService>,
+        match_duration: Option<Duration>,
+        leader: Arc<LeaderElection>,
+    ) -> Arc<Self> {
+        let (notices, _) = broadcast::channel(64);
+        let service = Arc::new(Self { config, repo, match_service, match_duration, notices });
+
+        let ticker = service.clone();
+        spawn_singleton("schedule_tick", leader, TICK_INTERVAL, true, move || {
+            let ticker = ticker.clone();
+            async move {
+                if let Err(e) = ticker.advance_due().await {
+                    tracing::warn!("Failed to advance scheduled matches: {}", e);
+                }
+            }
+        });
+
+        service
+    }
+
+    // Notices for the participants, as they happen on this instance
+    pub fn subscribe(&self) -> broadcast::Receiver<ScheduleNotice> {
+        self.notices.subscribe()
+    }
+
+    fn notify(&self, kind: ScheduleNoticeKind, schedule: &ScheduledMatch) {
+        let _ = self.notices.send(ScheduleNotice { kind, schedule: schedule.clone() });
+    }
+
+    async fn advance_due(&self) -> Result<()> {
+        let now = Utc::now();
+        let horizon = now + to_chrono(self.config.remind_before.max(self.config.open_before));
+        for schedule in self.repo.pending_schedules(horizon).await? {
+            let id = schedule.id;
+            if let Err(e) = self.advance(schedule, now).await {
+                tracing::warn!("Failed to advance scheduled match {}: {}", id, e);
+            }
+        }
+        Ok(())
+    }
+
+    // Take the next step due for one schedule
+    async fn advance(&self, mut schedule: ScheduledMatch, now: DateTime<Utc>) -> Result<()> {
+        let opens_at = schedule.starts_at - to_chrono(self.config.open_before);
+        match schedule.status {
+            ScheduleStatus::Open if now >= schedule.starts_at => {
+                let Some(match_id) = schedule.match_id else {
+                    return self.call_off(schedule, "the lobby was not opened").await;
+                };
+                if !self.match_service.start_scheduled(match_id, schedule.min_players).await? {
+                    return self.call_off(schedule, "not enough players").await;
+                }
+                schedule.status = ScheduleStatus::Started;
+                self.repo.save_schedule(&schedule).await?;
+                tracing::info!("Scheduled match {} started as {}", schedule.id, match_id);
+            }
+            ScheduleStatus::Scheduled | ScheduleStatus::Reminded if now >= opens_at => {
+                match self.match_service.open_scheduled(&schedule.match_type, schedule.host).await {
+                    Ok(lobby) => {
+                        schedule.status = ScheduleStatus::Open;
+                        schedule.match_id = Some(lobby.match_id);
+                        schedule.code = Some(lobby.code);
+                        self.repo.save_schedule(&schedule).await?;
+                        tracing::info!("Opened lobby {} for scheduled match {}", lobby.match_id, schedule.id);
+                        self.notify(ScheduleNoticeKind::LobbyOpen, &schedule);
+                    }
+                    // Tried again next tick until the start time
+                    Err(e) if now < schedule.starts_at => return Err(e),
+                    Err(e) => {
+                        tracing::warn!("Failed to open lobby for scheduled match {}: {}", schedule.id, e);
+                        return self.call_off(schedule, "the lobby could not be opened").await;
+                    }
+                }
+            }
+            ScheduleStatus::Scheduled => {
+                schedule.status = ScheduleStatus::Reminded;
+                self.repo.save_schedule(&schedule).await?;
+                self.notify(ScheduleNoticeKind::Reminder, &schedule);
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    async fn call_off(&self, mut schedule: ScheduledMatch, reason: &str) -> Result<()> {
+        schedule.status = ScheduleStatus::Cancelled;
+        schedule.cancelled_reason = Some(reason.to_string());
+        self.repo.save_schedule(&schedule).await?;
+        tracing::info!("Scheduled match {} called off: {}", schedule.id, reason);
+        self.notify(ScheduleNoticeKind::Cancelled, &schedule);
+        Ok(())
+    }
+
+    // Schedule a private match hosted by the user. `invited` fill the other
+    // seats; `min_players` (the whole room by default) must be in the lobby
+    // at the start for the match to go ahead.
+    pub async fn create(
+        &self,
+        host: Uuid,
+        match_type: &str,
+        starts_at: DateTime<Utc>,
+        invited: Vec<Uuid>,
+        min_players: Option<i32>,
+    ) -> Result<ScheduledMatch> {
+        let required_players = self.match_service.get_required_players(match_type)?;
+        let now = Utc::now();
+        if starts_at <= now || starts_at > now + to_chrono(self.config.max_ahead) {
+            return Err(Error::InvalidMessage);
+        }
+        let mut seen = HashSet::from([host]);
+        let invited: Vec<Uuid> = invited.into_iter().filter(|user_id| seen.insert(*user_id)).collect();
+        if invited.len() >= required_players as usize {
+            return Err(Error::TeamFull);
+        }
+
+        let schedule = ScheduledMatch {
+            id: Uuid::new_v4(),
+            match_type: match_type.to_string(),
+            host,
+            invited,
+            starts_at,
+            // At least one player per team
+            min_players: min_players.unwrap_or(required_players).clamp(2, required_players),
+            status: ScheduleStatus::Scheduled,
+            match_id: None,
+            code: None,
+            cancelled_reason: None,
+            created_at: now,
+        };
+        self.repo.save_schedule(&schedule).await?;
+        tracing::info!("User {} scheduled a {} match for {}", host, match_type, starts_at);
+        self.notify(ScheduleNoticeKind::Invited, &schedule);
+        Ok(schedule)
+    }
+
+    // Call off a scheduled match before its lobby opens; host only
+    pub async fn cancel(&self, user_id: Uuid, id: Uuid) -> Result<ScheduledMatch> {
+        let schedule = self.get(id).await?;
+        if schedule.host != user_id {
+            return Err(Error::PermissionDenied("only the host can cancel a scheduled match".to_string()));
+        }
+        match schedule.status {
+            ScheduleStatus::Scheduled | ScheduleStatus::Reminded => {}
+            ScheduleStatus::Open => return Err(Error::PermissionDenied("the lobby is already open".to_string())),
+            ScheduleStatus::Started | ScheduleStatus::Cancelled => return Err(Error::MatchAlreadyStarted),
+        }
+        let mut cancelled = schedule.clone();
+        cancelled.status = ScheduleStatus::Cancelled;
+        cancelled.cancelled_reason = Some("cancelled by the host".to_string());
+        self.repo.save_schedule(&cancelled).await?;
+        self.notify(ScheduleNoticeKind::Cancelled, &cancelled);
+        Ok(cancelled)
+    }
+
+    // Scheduled matches the user hosts or is invited to, from those starting
+    // in the last day on
+    pub async fn for_user(&self, user_id: Uuid) -> Result<Vec<ScheduledMatch>> {
+        self.repo.user_schedules(user_id, Utc::now() - chrono::Duration::days(1)).await
+    }
+
+    pub async fn get(&self, id: Uuid) -> Result<ScheduledMatch> {
+        self.repo.get_schedule(id).await?.ok_or_else(|| Error::NotFound(format!("scheduled match {}", id)))
+    }
+
+    // iCalendar invite for a scheduled match
+    pub async fn calendar(&self, id: Uuid) -> Result<String> {
+        let schedule = self.get(id).await?;
+        Ok(calendar::invite(&schedule, self.match_duration, self.config.open_before))
+    }
+}
+
+fn to_chrono(duration: Duration) -> chrono::Duration {
+    chrono::Duration::from_std(duration).unwrap_or_default()
+}
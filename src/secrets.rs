@@ -0,0 +1,330 @@
+use std::collections::HashMap;
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use serde::Deserialize;
+use serde_json::json;
+use sha2::{Digest, Sha256};
+
+// Every secret the server reads. Anything else stays a plain environment
+// variable.
+const NAMES: &[&str] = &[
+    "HASURA_ADMIN_SECRET",
+    "NEXT_PUBLIC_HASURA_ADMIN_SECRET",
+    "HASURA_JWT_SECRET",
+    "ADMIN_TOKEN",
+    "CLUSTER_TOKEN",
+    "TURN_SECRET",
+    "RESUME_TOKEN_SECRET",
+    "SESSION_TOKEN_SECRET",
+    "WEBHOOK_SIGNING_KEYS",
+    "PUSH_WEBHOOK_URL",
+    "SLOW_ALERT_WEBHOOK",
+    "REDIS_URL",
+];
+
+// Where Docker and Kubernetes mount secrets by default
+const DEFAULT_DIR: &str = "/run/secrets";
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+static SECRETS: OnceLock<HashMap<&'static str, String>> = OnceLock::new();
+
+// A secret loaded at startup; None when no source has it
+pub fn get(name: &str) -> Option<String> {
+    SECRETS.get()?.get(name).cloned()
+}
+
+// Resolves every secret once, before the configuration is read. Per secret
+// the first source that has it wins:
+//
+// 1. the file named by `NAME_FILE`
+// 2. `NAME` itself
+// 3. `SECRETS_DIR/name` (lowercase), /run/secrets by default
+// 4. the secret manager picked by SECRETS_PROVIDER, as SECRETS_PREFIX + NAME
+//
+// Secrets found in the environment are then removed from it, along with the
+// cloud credentials, so they don't leak to child processes or crash dumps.
+pub async fn load() -> Result<(), String> {
+    let dir = std::env::var("SECRETS_DIR").unwrap_or_else(|_| DEFAULT_DIR.to_string());
+    let mut secrets = HashMap::new();
+    let mut missing = Vec::new();
+    for &name in NAMES {
+        match local(name, &dir)? {
+            Some(value) => {
+                secrets.insert(name, value);
+            }
+            None => missing.push(name),
+        }
+    }
+
+    let provider = Provider::from_env()?;
+    scrub(provider.is_some());
+
+    if let Some(provider) = provider {
+        let http = reqwest::Client::builder()
+            .timeout(REQUEST_TIMEOUT)
+            .build()
+            .map_err(|e| format!("Failed to build secret manager client: {}", e))?;
+        let prefix = std::env::var("SECRETS_PREFIX").unwrap_or_default();
+        let token = provider.token(&http).await?;
+        for name in missing {
+            let id = format!("{}{}", prefix, name);
+            if let Some(value) = provider.fetch(&http, &token, &id).await? {
+                tracing::info!("Loaded {} from {}", name, provider.label());
+                secrets.insert(name, value);
+            }
+        }
+    }
+
+    SECRETS.set(secrets).map_err(|_| "Secrets loaded twice".to_string())
+}
+
+fn local(name: &str, dir: &str) -> Result<Option<String>, String> {
+    if let Some(path) = std::env::var(format!("{}_FILE", name)).ok().filter(|s| !s.is_empty()) {
+        let value = std::fs::read_to_string(&path)
+            .map_err(|e| format!("Failed to read {}_FILE {}: {}", name, path, e))?;
+        return Ok(Some(value.trim().to_string()).filter(|s| !s.is_empty()));
+    }
+    if let Some(value) = std::env::var(name).ok().filter(|s| !s.is_empty()) {
+        return Ok(Some(value));
+    }
+    // A missing or unreadable mount just means the secret isn't there
+    let path = std::path::Path::new(dir).join(name.to_lowercase());
+    Ok(std::fs::read_to_string(path)
+        .ok()
+        .map(|value| value.trim().to_string())
+        .filter(|s| !s.is_empty()))
+}
+
+// Removes plaintext secrets from the environment
+fn scrub(cloud: bool) {
+    let credentials: &[&str] = if cloud { &["AWS_SECRET_ACCESS_KEY", "AWS_SESSION_TOKEN"] } else { &[] };
+    for name in NAMES.iter().chain(credentials) {
+        if std::env::var_os(name).is_some() {
+            // SAFETY: runs at startup, before anything that reads the
+            // environment from another thread has been spawned
+            unsafe { std::env::remove_var(name) };
+        }
+    }
+}
+
+// SECRETS_PROVIDER
+enum Provider {
+    // AWS Secrets Manager, with static keys or ECS task credentials
+    Aws {
+        region: String,
+        credentials: Option<AwsCredentials>,
+    },
+    // GCP Secret Manager, authenticated as the instance's service account
+    Gcp { project: String },
+}
+
+#[derive(Clone, Deserialize)]
+struct AwsCredentials {
+    #[serde(rename = "AccessKeyId")]
+    access_key: String,
+    #[serde(rename = "SecretAccessKey")]
+    secret_key: String,
+    #[serde(rename = "Token")]
+    session_token: Option<String>,
+}
+
+// Access credentials for one load
+enum Token {
+    Aws(AwsCredentials),
+    Gcp(String),
+}
+
+impl Provider {
+    fn from_env() -> Result<Option<Self>, String> {
+        let provider = match std::env::var("SECRETS_PROVIDER").ok().filter(|s| !s.is_empty()) {
+            None => return Ok(None),
+            Some(provider) => provider,
+        };
+        match provider.as_str() {
+            "aws" => {
+                let region = std::env::var("AWS_REGION")
+                    .or_else(|_| std::env::var("AWS_DEFAULT_REGION"))
+                    .map_err(|_| "SECRETS_PROVIDER=aws needs AWS_REGION".to_string())?;
+                let credentials = match (std::env::var("AWS_ACCESS_KEY_ID"), std::env::var("AWS_SECRET_ACCESS_KEY")) {
+                    (Ok(access_key), Ok(secret_key)) => Some(AwsCredentials {
+                        access_key,
+                        secret_key,
+                        session_token: std::env::var("AWS_SESSION_TOKEN").ok(),
+                    }),
+                    _ => None,
+                };
+                Ok(Some(Provider::Aws { region, credentials }))
+            }
+            "gcp" => {
+                let project = std::env::var("SECRETS_GCP_PROJECT")
+                    .map_err(|_| "SECRETS_PROVIDER=gcp needs SECRETS_GCP_PROJECT".to_string())?;
+                Ok(Some(Provider::Gcp { project }))
+            }
+            other => Err(format!("Unknown SECRETS_PROVIDER {}, expected aws or gcp", other)),
+        }
+    }
+
+    fn label(&self) -> &'static str {
+        match self {
+            Provider::Aws { .. } => "AWS Secrets Manager",
+            Provider::Gcp { .. } => "GCP Secret Manager",
+        }
+    }
+
+    async fn token(&self, http: &reqwest::Client) -> Result<Token, String> {
+        match self {
+            Provider::Aws { credentials: Some(credentials), .. } => Ok(Token::Aws(credentials.clone())),
+            Provider::Aws { credentials: None, .. } => {
+                let uri = std::env::var("AWS_CONTAINER_CREDENTIALS_RELATIVE_URI")
+                    .map_err(|_| "SECRETS_PROVIDER=aws needs AWS_ACCESS_KEY_ID/AWS_SECRET_ACCESS_KEY or ECS task credentials".to_string())?;
+                let credentials = http.get(format!("http://169.254.170.2{}", uri))
+                    .send()
+                    .await
+                    .and_then(|response| response.error_for_status())
+                    .map_err(|e| format!("Failed to get ECS task credentials: {}", e))?
+                    .json::<AwsCredentials>()
+                    .await
+                    .map_err(|e| format!("Invalid ECS task credentials: {}", e))?;
+                Ok(Token::Aws(credentials))
+            }
+            Provider::Gcp { .. } => {
+                #[derive(Deserialize)]
+                struct MetadataToken {
+                    access_token: String,
+                }
+                let token = http.get("http://metadata.google.internal/computeMetadata/v1/instance/service-accounts/default/token")
+                    .header("Metadata-Flavor", "Google")
+                    .send()
+                    .await
+                    .and_then(|response| response.error_for_status())
+                    .map_err(|e| format!("Failed to get GCP access token: {}", e))?
+                    .json::<MetadataToken>()
+                    .await
+                    .map_err(|e| format!("Invalid GCP access token: {}", e))?;
+                Ok(Token::Gcp(token.access_token))
+            }
+        }
+    }
+
+    // None when the manager has no such secret
+    async fn fetch(&self, http: &reqwest::Client, token: &Token, id: &str) -> Result<Option<String>, String> {
+        match (self, token) {
+            (Provider::Aws { region, .. }, Token::Aws(credentials)) => aws_fetch(http, region, credentials, id).await,
+            (Provider::Gcp { project }, Token::Gcp(token)) => gcp_fetch(http, project, token, id).await,
+            _ => unreachable!("token from another provider"),
+        }
+    }
+}
+
+async fn aws_fetch(http: &reqwest::Client, region: &str, credentials: &AwsCredentials, id: &str) -> Result<Option<String>, String> {
+    #[derive(Deserialize)]
+    struct SecretValue {
+        #[serde(rename = "SecretString")]
+        secret_string: Option<String>,
+    }
+
+    let host = format!("secretsmanager.{}.amazonaws.com", region);
+    let body = json!({ "SecretId": id }).to_string();
+    let now = Utc::now();
+    let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+    let date = now.format("%Y%m%d").to_string();
+
+    // Signature Version 4, with every header we send signed
+    let mut headers = vec![
+        ("content-type", "application/x-amz-json-1.1".to_string()),
+        ("host", host.clone()),
+        ("x-amz-date", amz_date.clone()),
+        ("x-amz-target", "secretsmanager.GetSecretValue".to_string()),
+    ];
+    if let Some(session_token) = &credentials.session_token {
+        headers.push(("x-amz-security-token", session_token.clone()));
+    }
+    let canonical_headers: String = headers.iter().map(|(name, value)| format!("{}:{}\n", name, value)).collect();
+    let signed_headers = headers.iter().map(|(name, _)| *name).collect::<Vec<_>>().join(";");
+    let canonical_request = format!(
+        "POST\n/\n\n{}\n{}\n{}",
+        canonical_headers, signed_headers, hex::encode(Sha256::digest(body.as_bytes()))
+    );
+    let scope = format!("{}/{}/secretsmanager/aws4_request", date, region);
+    let string_to_sign = format!(
+        "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+        amz_date, scope, hex::encode(Sha256::digest(canonical_request.as_bytes()))
+    );
+    let key = [region, "secretsmanager", "aws4_request"]
+        .iter()
+        .fold(hmac_sha256(format!("AWS4{}", credentials.secret_key).as_bytes(), &date), |key, part| hmac_sha256(&key, part));
+    let signature = hex::encode(hmac_sha256(&key, &string_to_sign));
+
+    let mut request = http.post(format!("https://{}/", host)).body(body);
+    for (name, value) in &headers {
+        if *name != "host" {
+            request = request.header(*name, value);
+        }
+    }
+    let response = request
+        .header("authorization", format!(
+            "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+            credentials.access_key, scope, signed_headers, signature
+        ))
+        .send()
+        .await
+        .map_err(|e| format!("Failed to reach AWS Secrets Manager: {}", e))?;
+
+    if !response.status().is_success() {
+        let status = response.status();
+        let text = response.text().await.unwrap_or_default();
+        if text.contains("ResourceNotFoundException") {
+            return Ok(None);
+        }
+        return Err(format!("AWS Secrets Manager refused {}: {} {}", id, status, text));
+    }
+    let value: SecretValue = response.json().await
+        .map_err(|e| format!("Invalid AWS Secrets Manager response for {}: {}", id, e))?;
+    Ok(value.secret_string.filter(|s| !s.is_empty()))
+}
+
+async fn gcp_fetch(http: &reqwest::Client, project: &str, token: &str, id: &str) -> Result<Option<String>, String> {
+    #[derive(Deserialize)]
+    struct Payload {
+        data: String,
+    }
+    #[derive(Deserialize)]
+    struct AccessResponse {
+        payload: Payload,
+    }
+
+    let response = http.get(format!(
+            "https://secretmanager.googleapis.com/v1/projects/{}/secrets/{}/versions/latest:access",
+            project, id
+        ))
+        .bearer_auth(token)
+        .send()
+        .await
+        .map_err(|e| format!("Failed to reach GCP Secret Manager: {}", e))?;
+
+    if response.status() == reqwest::StatusCode::NOT_FOUND {
+        return Ok(None);
+    }
+    if !response.status().is_success() {
+        let status = response.status();
+        let text = response.text().await.unwrap_or_default();
+        return Err(format!("GCP Secret Manager refused {}: {} {}", id, status, text));
+    }
+    let access: AccessResponse = response.json().await
+        .map_err(|e| format!("Invalid GCP Secret Manager response for {}: {}", id, e))?;
+    let bytes = STANDARD.decode(access.payload.data)
+        .map_err(|e| format!("Invalid payload of {}: {}", id, e))?;
+    let value = String::from_utf8(bytes).map_err(|_| format!("Secret {} is not UTF-8", id))?;
+    Ok(Some(value.trim().to_string()).filter(|s| !s.is_empty()))
+}
+
+fn hmac_sha256(key: &[u8], data: &str) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts any key length");
+    mac.update(data.as_bytes());
+    mac.finalize().into_bytes().to_vec()
+}
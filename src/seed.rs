@@ -0,0 +1,143 @@
+use std::sync::Arc;
+
+use uuid::Uuid;
+
+use crate::config::Config;
+use crate::db::repository::{MatchRepository, RatingRepository, TreasureRepository};
+use crate::error::Result;
+use crate::game::victory::VictoryCondition;
+use crate::models::game::MatchType;
+use crate::models::treasure::{Rarity, Treasure};
+use crate::rating::mmr::PlayerRating;
+
+// Local development data for a fresh database: test users with ratings, a
+// treasure catalog and a few finished matches, all written through the
+// repositories. Ids are fixed, so running it twice leaves existing rows alone
+// and the test page can be opened as a known user.
+
+pub struct TestUser {
+    pub name: &'static str,
+    pub id: Uuid,
+}
+
+const fn user(name: &'static str, id: u128) -> TestUser {
+    TestUser { name, id: Uuid::from_u128(id) }
+}
+
+// The first two are the users preselectable on the test page
+pub const TEST_USERS: &[TestUser] = &[
+    user("alice", 0x918fb097_5aa6_4ec0_8b25_96ed9c230bbc),
+    user("bob", 0x47da4d5e_9ed1_4347_b86c_7bb1947e369d),
+    user("carol", 0x5e7d0000_0000_4000_8000_000000000003),
+    user("dave", 0x5e7d0000_0000_4000_8000_000000000004),
+    user("eve", 0x5e7d0000_0000_4000_8000_000000000005),
+    user("frank", 0x5e7d0000_0000_4000_8000_000000000006),
+    user("grace", 0x5e7d0000_0000_4000_8000_000000000007),
+    user("heidi", 0x5e7d0000_0000_4000_8000_000000000008),
+];
+
+// (name, x, y, base score, rarity)
+const TREASURES: &[(&str, f32, f32, i32, Rarity)] = &[
+    ("Old Coin", 12.0, 8.0, 10, Rarity::Common),
+    ("Rusty Compass", 40.0, 22.0, 10, Rarity::Common),
+    ("Bottle Message", 75.0, 60.0, 15, Rarity::Common),
+    ("Silver Locket", 55.0, 35.0, 25, Rarity::Rare),
+    ("Captain's Map", 20.0, 70.0, 30, Rarity::Rare),
+    ("Jade Idol", 88.0, 15.0, 50, Rarity::Epic),
+    ("Sunken Crown", 50.0, 90.0, 100, Rarity::Legendary),
+];
+
+// Players of each team, by index
+type Rosters = &'static [&'static [usize]];
+// (player, treasure), by index
+type Discoveries = &'static [(usize, usize)];
+
+const MATCHES: &[(MatchType, Rosters, Discoveries)] = &[
+    (MatchType::OneVsOne, &[&[0], &[1]], &[(0, 0), (1, 1), (0, 3)]),
+    (MatchType::TwoVsTwo, &[&[0, 2], &[1, 3]], &[(0, 2), (2, 4), (1, 0), (3, 5)]),
+    (MatchType::TwoVsTwo, &[&[4, 5], &[6, 7]], &[(4, 1), (6, 6), (7, 2)]),
+];
+
+#[derive(Debug, Default)]
+pub struct SeedReport {
+    pub ratings: usize,
+    pub treasures: usize,
+    pub matches: usize,
+}
+
+pub async fn run(
+    config: &Config,
+    matches: Arc<dyn MatchRepository>,
+    treasures: Arc<dyn TreasureRepository>,
+    ratings: Arc<dyn RatingRepository>,
+) -> Result<SeedReport> {
+    let mut report = SeedReport::default();
+
+    // Test users only exist as ratings here; accounts belong to the auth service
+    let user_ids: Vec<Uuid> = TEST_USERS.iter().map(|u| u.id).collect();
+    let existing = ratings.get_ratings(&user_ids).await?;
+    let missing: Vec<PlayerRating> = user_ids
+        .iter()
+        .filter(|id| !existing.iter().any(|r| r.user_id == **id))
+        .map(|id| PlayerRating::new(*id, config.rating.initial_mmr))
+        .collect();
+    if !missing.is_empty() {
+        ratings.upsert_ratings(&missing).await?;
+        report.ratings = missing.len();
+    }
+
+    let catalog = treasures.list_treasures().await?;
+    for (i, (name, x, y, base_score, rarity)) in TREASURES.iter().enumerate() {
+        let id = treasure_id(i);
+        if catalog.iter().any(|t| t.id == id) {
+            continue;
+        }
+        treasures.create_treasure(&Treasure {
+            id,
+            name: name.to_string(),
+            x: *x,
+            y: *y,
+            base_score: *base_score,
+            rarity: *rarity,
+            active_from: None,
+            active_until: None,
+        }).await?;
+        report.treasures += 1;
+    }
+
+    for (i, (match_type, teams, discoveries)) in MATCHES.iter().enumerate() {
+        let match_id = Uuid::from_u128(0x5e7d0001_0000_4000_8000_000000000000 | i as u128);
+        if matches.get_match(match_id).await.is_ok() {
+            continue;
+        }
+        let team_size = match_type.team_size();
+        matches.create_match(match_id, match_type.to_str(), team_size, VictoryCondition::HighestScore).await?;
+        let mut team_ids = Vec::new();
+        for (number, players) in teams.iter().enumerate() {
+            let team_id = matches.create_team(Uuid::new_v4(), match_id, number as i32 + 1, team_size, None).await?;
+            for player in *players {
+                matches.add_player_to_team(match_id, team_id, TEST_USERS[*player].id, None).await?;
+            }
+            team_ids.push(team_id);
+        }
+        matches.start_match(match_id).await?;
+        for (player, treasure) in *discoveries {
+            let team_id = teams
+                .iter()
+                .position(|players| players.contains(player))
+                .map(|team| team_ids[team])
+                .unwrap_or(team_ids[0]);
+            let (_, _, _, score, _) = TREASURES[*treasure];
+            matches.record_discovery(match_id, team_id, TEST_USERS[*player].id, treasure_id(*treasure), score).await?;
+        }
+        let scores = matches.get_match_scores(match_id).await?;
+        matches.end_match(match_id, VictoryCondition::HighestScore.evaluator(None).winner(&scores)).await?;
+        report.matches += 1;
+    }
+
+    Ok(report)
+}
+
+fn treasure_id(index: usize) -> Uuid {
+    Uuid::from_u128(0x5e7d0002_0000_4000_8000_000000000000 | index as u128)
+}
@@ -0,0 +1,111 @@
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+
+use axum::http::HeaderMap;
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use serde::Serialize;
+use sha2::Sha256;
+use uuid::Uuid;
+
+use crate::config::SigningConfig;
+use crate::error::{Error, Result};
+
+pub const TIMESTAMP_HEADER: &str = "x-spv-timestamp";
+pub const NONCE_HEADER: &str = "x-spv-nonce";
+// "<key id>=<hex HMAC-SHA256 of '<timestamp>.<nonce>.<body>'>"
+pub const SIGNATURE_HEADER: &str = "x-spv-signature";
+
+// HMAC signatures on the webhooks the server sends and receives.
+//
+// Every signed payload carries a Unix timestamp, a random nonce and a
+// signature over both plus the raw body, made with one of the shared keys in
+// WEBHOOK_SIGNING_KEYS and naming that key, so keys can be rotated without
+// downtime. Incoming payloads are refused when unsigned, signed with an
+// unknown key, outside WEBHOOK_SIGNATURE_TOLERANCE_SECS of now, or carrying a
+// nonce this node already accepted within that window.
+struct Signing {
+    config: SigningConfig,
+    // Accepted nonces and when they may be forgotten (Unix seconds)
+    seen: Mutex<HashMap<String, i64>>,
+}
+
+static SIGNING: OnceLock<Signing> = OnceLock::new();
+
+pub fn init(config: &SigningConfig) {
+    if config.keys.is_empty() {
+        tracing::info!("WEBHOOK_SIGNING_KEYS is not set: outgoing webhooks are unsigned and incoming ones are refused");
+    }
+    let _ = SIGNING.set(Signing {
+        config: config.clone(),
+        seen: Mutex::new(HashMap::new()),
+    });
+}
+
+fn signature(secret: &str, timestamp: &str, nonce: &str, body: &[u8]) -> Hmac<Sha256> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes())
+        .expect("HMAC accepts keys of any length");
+    mac.update(timestamp.as_bytes());
+    mac.update(b".");
+    mac.update(nonce.as_bytes());
+    mac.update(b".");
+    mac.update(body);
+    mac
+}
+
+// A JSON POST of the payload, signed with the current key when there is one
+pub fn signed_post<T: Serialize>(http: &reqwest::Client, url: &str, payload: &T) -> reqwest::RequestBuilder {
+    let body = match serde_json::to_vec(payload) {
+        Ok(body) => body,
+        // Let reqwest report the serialization error when the request is sent
+        Err(_) => return http.post(url).json(payload),
+    };
+    let mut request = http.post(url)
+        .header(reqwest::header::CONTENT_TYPE, "application/json");
+    if let Some(key) = SIGNING.get().and_then(|signing| signing.config.keys.first()) {
+        let timestamp = Utc::now().timestamp().to_string();
+        let nonce = Uuid::new_v4().simple().to_string();
+        let mac = signature(&key.secret, &timestamp, &nonce, &body);
+        request = request
+            .header(TIMESTAMP_HEADER, timestamp)
+            .header(NONCE_HEADER, nonce)
+            .header(SIGNATURE_HEADER, format!("{}={}", key.id, hex::encode(mac.finalize().into_bytes())));
+    }
+    request.body(body)
+}
+
+// Check an incoming payload's signature, age and nonce. `Error::AuthError`
+// for anything forged, stale or replayed.
+pub fn verify(headers: &HeaderMap, body: &[u8]) -> Result<()> {
+    let Some(signing) = SIGNING.get().filter(|signing| !signing.config.keys.is_empty()) else {
+        return Err(Error::PermissionDenied("webhook signing keys are not configured".to_string()));
+    };
+    let header = |name: &str| headers.get(name).and_then(|value| value.to_str().ok()).ok_or(Error::AuthError);
+    let timestamp = header(TIMESTAMP_HEADER)?;
+    let nonce = header(NONCE_HEADER)?;
+    let (key_id, digest) = header(SIGNATURE_HEADER)?.split_once('=').ok_or(Error::AuthError)?;
+
+    let now = Utc::now().timestamp();
+    let tolerance = signing.config.tolerance.as_secs() as i64;
+    let sent_at: i64 = timestamp.parse().map_err(|_| Error::AuthError)?;
+    if (now - sent_at).abs() > tolerance {
+        tracing::warn!("Refused webhook signed {} seconds from now", sent_at - now);
+        return Err(Error::AuthError);
+    }
+
+    let key = signing.config.keys.iter().find(|key| key.id == key_id).ok_or(Error::AuthError)?;
+    let digest = hex::decode(digest).map_err(|_| Error::AuthError)?;
+    signature(&key.secret, timestamp, nonce, body)
+        .verify_slice(&digest)
+        .map_err(|_| Error::AuthError)?;
+
+    // Checked after the signature, so forged requests can't fill the nonce table
+    let mut seen = signing.seen.lock().unwrap();
+    seen.retain(|_, forget_at| *forget_at > now);
+    if seen.contains_key(nonce) {
+        tracing::warn!("Refused replayed webhook (nonce {})", nonce);
+        return Err(Error::AuthError);
+    }
+    seen.insert(nonce.to_string(), sent_at + tolerance);
+    Ok(())
+}
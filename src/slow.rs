@@ -0,0 +1,182 @@
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use serde_json::{Map, Value};
+use uuid::Uuid;
+
+use crate::cluster::node_id;
+use crate::config::SlowConfig;
+use crate::correlation;
+use crate::metrics::METRICS;
+use crate::signing;
+
+// Least time between two webhook alerts about the same operation
+const ALERT_INTERVAL: Duration = Duration::from_secs(60);
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(5);
+
+// Slow Hasura operations and command handling.
+//
+// Anything over its threshold is logged as a structured warning and counted
+// at /metrics. With SLOW_ALERT_WEBHOOK set it is also POSTed there as JSON,
+// at most once a minute per operation, so a struggling database doesn't flood
+// the receiver. Variables are summarized by shape only, never by value.
+struct Slow {
+    config: SlowConfig,
+    http: reqwest::Client,
+    last_alert: Mutex<HashMap<String, Instant>>,
+}
+
+static SLOW: OnceLock<Slow> = OnceLock::new();
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SlowKind {
+    Query,
+    Command,
+}
+
+// Webhook payload
+#[derive(Debug, Serialize)]
+struct SlowAlert {
+    kind: SlowKind,
+    operation: String,
+    duration_ms: u64,
+    threshold_ms: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    variables: Option<Value>,
+    correlation_id: Option<Uuid>,
+    node: String,
+}
+
+pub fn init(config: &SlowConfig) {
+    let http = reqwest::Client::builder()
+        .timeout(WEBHOOK_TIMEOUT)
+        .build()
+        .unwrap_or_default();
+    let _ = SLOW.set(Slow {
+        config: config.clone(),
+        http,
+        last_alert: Mutex::new(HashMap::new()),
+    });
+}
+
+// Called after every Hasura request
+pub fn query(query: &str, variables: &Value, elapsed: Duration) {
+    let Some(slow) = SLOW.get() else {
+        return;
+    };
+    if elapsed < slow.config.query {
+        return;
+    }
+    let operation = operation_name(query);
+    let summary = summarize(variables);
+    tracing::warn!(
+        operation = %operation,
+        variables = %summary,
+        duration_ms = elapsed.as_millis() as u64,
+        threshold_ms = slow.config.query.as_millis() as u64,
+        correlation_id = ?correlation::current(),
+        "Slow Hasura operation"
+    );
+    METRICS.record_slow(SlowKind::Query);
+    slow.alert(SlowKind::Query, operation, Some(summary), elapsed, slow.config.query);
+}
+
+// Called after every client command
+pub fn command(cmd: &str, elapsed: Duration) {
+    let Some(slow) = SLOW.get() else {
+        return;
+    };
+    if elapsed < slow.config.command {
+        return;
+    }
+    tracing::warn!(
+        operation = %cmd,
+        duration_ms = elapsed.as_millis() as u64,
+        threshold_ms = slow.config.command.as_millis() as u64,
+        correlation_id = ?correlation::current(),
+        "Slow command"
+    );
+    METRICS.record_slow(SlowKind::Command);
+    slow.alert(SlowKind::Command, cmd.to_string(), None, elapsed, slow.config.command);
+}
+
+impl Slow {
+    fn alert(&self, kind: SlowKind, operation: String, variables: Option<Value>, elapsed: Duration, threshold: Duration) {
+        let Some(url) = &self.config.webhook else {
+            return;
+        };
+        {
+            let now = Instant::now();
+            let mut last_alert = self.last_alert.lock().unwrap();
+            if last_alert.get(&operation).is_some_and(|at| now.duration_since(*at) < ALERT_INTERVAL) {
+                return;
+            }
+            last_alert.retain(|_, at| now.duration_since(*at) < ALERT_INTERVAL);
+            last_alert.insert(operation.clone(), now);
+        }
+
+        let alert = SlowAlert {
+            kind,
+            operation,
+            duration_ms: elapsed.as_millis() as u64,
+            threshold_ms: threshold.as_millis() as u64,
+            variables,
+            correlation_id: correlation::current(),
+            node: node_id().to_string(),
+        };
+        let request = signing::signed_post(&self.http, url, &alert);
+        tokio::spawn(async move {
+            match request.send().await {
+                Ok(response) if !response.status().is_success() => {
+                    tracing::warn!("Slow operation webhook answered {}", response.status());
+                }
+                Ok(_) => {}
+                Err(e) => tracing::warn!("Slow operation webhook failed: {}", e),
+            }
+        });
+    }
+}
+
+// "query Motd($id: Int!) { ... }" -> "Motd"; anonymous operations are named by their type
+pub fn operation_name(query: &str) -> String {
+    let query = query.trim_start();
+    let (kind, rest) = match query.split_once(char::is_whitespace) {
+        Some((kind, rest)) if kind == "query" || kind == "mutation" => (kind, rest.trim_start()),
+        _ => return "anonymous".to_string(),
+    };
+    let name: String = rest
+        .chars()
+        .take_while(|c| c.is_alphanumeric() || *c == '_')
+        .collect();
+    if name.is_empty() {
+        kind.to_string()
+    } else {
+        name
+    }
+}
+
+// Shape of the variables, e.g. {"id": "string", "objects": "array(12)"}
+fn summarize(variables: &Value) -> Value {
+    let Value::Object(fields) = variables else {
+        return Value::String(shape(variables));
+    };
+    let summary: Map<String, Value> = fields
+        .iter()
+        .map(|(key, value)| (key.clone(), Value::String(shape(value))))
+        .collect();
+    Value::Object(summary)
+}
+
+fn shape(value: &Value) -> String {
+    match value {
+        Value::Null => "null".to_string(),
+        Value::Bool(_) => "bool".to_string(),
+        Value::Number(_) => "number".to_string(),
+        Value::String(_) => "string".to_string(),
+        Value::Array(items) => format!("array({})", items.len()),
+        Value::Object(fields) => format!("object({})", fields.len()),
+    }
+}
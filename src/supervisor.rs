@@ -0,0 +1,86 @@
+use std::any::Any;
+use std::future::Future;
+use std::panic::AssertUnwindSafe;
+use std::time::{Duration, Instant};
+
+use futures_util::FutureExt;
+use tokio::task::JoinHandle;
+
+use crate::correlation;
+use crate::metrics::METRICS;
+
+// Wait before restarting a loop that panicked, doubled for each panic in a row
+const RESTART_DELAY: Duration = Duration::from_secs(1);
+const MAX_RESTART_DELAY: Duration = Duration::from_secs(60);
+// A loop that ran this long before panicking is restarted after RESTART_DELAY again
+const HEALTHY_AFTER: Duration = Duration::from_secs(60);
+
+// Panic isolation for background tasks.
+//
+// A panic in a bare `tokio::spawn` task ends only that task, and nothing
+// notices: a timer stops firing, a consumer stops consuming. Tasks started
+// here have their panics logged with the task's name and the correlation id
+// in scope, and counted as spv_task_panics_total at /metrics. Loops started
+// with `spawn_loop` are restarted after a panic; consumers that own their
+// receiver wrap each message in `catch` instead, so one bad event is skipped
+// rather than the subscription lost.
+
+// Await `future`; None if it panicked
+pub async fn catch<F: Future>(task: &str, future: F) -> Option<F::Output> {
+    match AssertUnwindSafe(future).catch_unwind().await {
+        Ok(output) => Some(output),
+        Err(panic) => {
+            report(task, panic.as_ref());
+            None
+        }
+    }
+}
+
+// A one-off task whose panic is reported instead of lost
+pub fn spawn<F>(task: &'static str, future: F) -> JoinHandle<Option<F::Output>>
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    tokio::spawn(catch(task, future))
+}
+
+// A loop built by `start`, started again whenever it panics. Panics in a row
+// wait longer each time, up to a minute. A loop that returns is done.
+pub fn spawn_loop<F, Fut>(task: &'static str, start: F) -> JoinHandle<()>
+where
+    F: Fn() -> Fut + Send + 'static,
+    Fut: Future<Output = ()> + Send + 'static,
+{
+    tokio::spawn(async move {
+        let mut delay = RESTART_DELAY;
+        loop {
+            let started = Instant::now();
+            if catch(task, start()).await.is_some() {
+                return;
+            }
+            if started.elapsed() >= HEALTHY_AFTER {
+                delay = RESTART_DELAY;
+            }
+            tracing::warn!(task = %task, "Restarting {} in {:?}", task, delay);
+            tokio::time::sleep(delay).await;
+            METRICS.record_task_restart(task);
+            delay = (delay * 2).min(MAX_RESTART_DELAY);
+        }
+    })
+}
+
+fn report(task: &str, panic: &(dyn Any + Send)) {
+    let message = panic.downcast_ref::<&str>()
+        .copied()
+        .or_else(|| panic.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("non-string payload");
+    tracing::error!(
+        task = %task,
+        correlation_id = ?correlation::current(),
+        "Task {} panicked: {}",
+        task,
+        message
+    );
+    METRICS.record_task_panic(task);
+}
@@ -0,0 +1,132 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use super::schema::Violation;
+
+// Longest accepted event name
+const MAX_NAME_LEN: usize = 64;
+// Largest accepted `properties` object, serialized
+const MAX_PROPERTIES_BYTES: usize = 2048;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, ToSchema, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum TelemetryKind {
+    ScreenView,
+    Error,
+    Custom,
+}
+
+impl TelemetryKind {
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "screen_view" => Some(TelemetryKind::ScreenView),
+            "error" => Some(TelemetryKind::Error),
+            "custom" => Some(TelemetryKind::Custom),
+            _ => None,
+        }
+    }
+
+    pub fn to_str(self) -> &'static str {
+        match self {
+            TelemetryKind::ScreenView => "screen_view",
+            TelemetryKind::Error => "error",
+            TelemetryKind::Custom => "custom",
+        }
+    }
+}
+
+// A structured client event, as sent by telemetry.event and POST /api/telemetry
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, JsonSchema)]
+pub struct TelemetryEvent {
+    pub kind: TelemetryKind,
+    // Screen name, error code or custom event name
+    pub name: String,
+    // Client clock when the event happened, Unix milliseconds
+    pub client_time: Option<i64>,
+    // Free-form details, at most 2 KB of JSON
+    #[serde(default)]
+    #[schema(value_type = Object)]
+    pub properties: Map<String, Value>,
+}
+
+impl TelemetryEvent {
+    // Checks every event gets, with or without a schema registry
+    pub fn validate(&self) -> Result<(), Violation> {
+        if self.name.is_empty() || self.name.len() > MAX_NAME_LEN {
+            return Err(Violation::InvalidName);
+        }
+        let properties_len = serde_json::to_vec(&self.properties)
+            .map_err(|_| Violation::TooLarge)?
+            .len();
+        if properties_len > MAX_PROPERTIES_BYTES {
+            return Err(Violation::TooLarge);
+        }
+        Ok(())
+    }
+}
+
+// A validated event waiting to be written
+#[derive(Debug, Clone)]
+pub struct TelemetryRecord {
+    pub user_id: Option<Uuid>,
+    // "ws" or "http"
+    pub source: &'static str,
+    pub event: TelemetryEvent,
+    pub received_at: DateTime<Utc>,
+}
+
+// Share of events kept per kind, between 0.0 and 1.0
+#[derive(Debug, Clone)]
+pub struct SamplingConfig {
+    pub default: f64,
+    pub by_kind: HashMap<TelemetryKind, f64>,
+}
+
+impl Default for SamplingConfig {
+    fn default() -> Self {
+        Self {
+            default: 1.0,
+            by_kind: HashMap::new(),
+        }
+    }
+}
+
+impl SamplingConfig {
+    // TELEMETRY_SAMPLE_RATES="screen_view=0.1,error=1,*=0.5"
+    pub fn from_str(s: &str) -> Self {
+        let mut config = Self::default();
+        for entry in s.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            let parsed = entry.split_once('=').and_then(|(kind, rate)| {
+                let rate: f64 = rate.trim().parse().ok()?;
+                (0.0..=1.0).contains(&rate).then_some((kind.trim(), rate))
+            });
+            match parsed {
+                Some(("*", rate)) => config.default = rate,
+                Some((kind, rate)) => match TelemetryKind::from_str(kind) {
+                    Some(kind) => {
+                        config.by_kind.insert(kind, rate);
+                    }
+                    None => tracing::warn!("Ignoring sample rate for unknown telemetry kind: {}", entry),
+                },
+                None => tracing::warn!("Ignoring invalid telemetry sample rate: {}", entry),
+            }
+        }
+        config
+    }
+
+    pub fn rate_for(&self, kind: TelemetryKind) -> f64 {
+        self.by_kind.get(&kind).copied().unwrap_or(self.default)
+    }
+
+    // Whether to keep one event of this kind
+    pub fn keep(&self, kind: TelemetryKind) -> bool {
+        let rate = self.rate_for(kind);
+        rate >= 1.0 || (rate > 0.0 && rand::random::<f64>() < rate)
+    }
+}
@@ -0,0 +1,3 @@
+pub mod event;
+pub mod schema;
+pub mod service;
\ No newline at end of file
@@ -0,0 +1,146 @@
+use std::collections::{BTreeMap, HashMap};
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use utoipa::ToSchema;
+
+use super::event::{TelemetryEvent, TelemetryKind};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum FieldType {
+    String,
+    Number,
+    Integer,
+    Boolean,
+    Object,
+    Array,
+}
+
+impl FieldType {
+    fn matches(&self, value: &Value) -> bool {
+        match self {
+            FieldType::String => value.is_string(),
+            FieldType::Number => value.is_number(),
+            FieldType::Integer => value.is_i64() || value.is_u64(),
+            FieldType::Boolean => value.is_boolean(),
+            FieldType::Object => value.is_object(),
+            FieldType::Array => value.is_array(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, JsonSchema)]
+pub struct FieldSpec {
+    #[serde(rename = "type")]
+    pub field_type: FieldType,
+    #[serde(default)]
+    pub required: bool,
+}
+
+// One allowed event and the properties it may carry
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, JsonSchema)]
+pub struct EventSchema {
+    pub kind: TelemetryKind,
+    pub name: String,
+    #[serde(default)]
+    pub fields: BTreeMap<String, FieldSpec>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Violation {
+    // Empty or longer than 64 characters
+    InvalidName,
+    // Properties over 2 KB of JSON
+    TooLarge,
+    // Kind and name not in the registry
+    UnknownEvent,
+    UnknownField,
+    MissingField,
+    WrongType,
+}
+
+impl Violation {
+    pub const ALL: [Violation; 6] = [
+        Violation::InvalidName,
+        Violation::TooLarge,
+        Violation::UnknownEvent,
+        Violation::UnknownField,
+        Violation::MissingField,
+        Violation::WrongType,
+    ];
+
+    pub fn to_str(self) -> &'static str {
+        match self {
+            Violation::InvalidName => "invalid_name",
+            Violation::TooLarge => "too_large",
+            Violation::UnknownEvent => "unknown_event",
+            Violation::UnknownField => "unknown_field",
+            Violation::MissingField => "missing_field",
+            Violation::WrongType => "wrong_type",
+        }
+    }
+}
+
+// Why one event of a batch was rejected
+#[derive(Debug, Clone, Serialize, ToSchema, JsonSchema)]
+pub struct Rejection {
+    // Position of the event in the submitted batch
+    pub index: usize,
+    pub reason: Violation,
+    // Offending property, for field violations
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub field: Option<String>,
+}
+
+// Allowed telemetry events, read once at startup from TELEMETRY_SCHEMA_PATH:
+// a JSON array of `{kind, name, fields: {<property>: {type, required}}}`.
+// Events that are not listed, or whose properties don't match, are rejected.
+pub struct SchemaRegistry {
+    schemas: HashMap<(TelemetryKind, String), EventSchema>,
+}
+
+impl SchemaRegistry {
+    pub fn load(path: &str) -> Result<Self, String> {
+        let raw = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+        let list: Vec<EventSchema> = serde_json::from_str(&raw).map_err(|e| e.to_string())?;
+        let schemas = list
+            .into_iter()
+            .map(|schema| ((schema.kind, schema.name.clone()), schema))
+            .collect();
+        Ok(Self { schemas })
+    }
+
+    pub fn len(&self) -> usize {
+        self.schemas.len()
+    }
+
+    // Sorted by kind and name
+    pub fn list(&self) -> Vec<EventSchema> {
+        let mut list: Vec<EventSchema> = self.schemas.values().cloned().collect();
+        list.sort_by(|a, b| (a.kind.to_str(), &a.name).cmp(&(b.kind.to_str(), &b.name)));
+        list
+    }
+
+    // First violation of the event's schema, with the property it concerns
+    pub fn check(&self, event: &TelemetryEvent) -> Result<(), (Violation, Option<String>)> {
+        let Some(schema) = self.schemas.get(&(event.kind, event.name.clone())) else {
+            return Err((Violation::UnknownEvent, None));
+        };
+        if let Some(field) = event.properties.keys().find(|field| !schema.fields.contains_key(*field)) {
+            return Err((Violation::UnknownField, Some(field.clone())));
+        }
+        for (field, spec) in &schema.fields {
+            match event.properties.get(field) {
+                None if spec.required => return Err((Violation::MissingField, Some(field.clone()))),
+                Some(value) if !spec.field_type.matches(value) => {
+                    return Err((Violation::WrongType, Some(field.clone())));
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,146 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::Utc;
+use schemars::JsonSchema;
+use serde::Serialize;
+use tokio::sync::mpsc;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::config::TelemetryConfig;
+use crate::db::repository::TelemetryRepository;
+use crate::error::{Error, Result};
+use crate::metrics::METRICS;
+use super::event::{TelemetryEvent, TelemetryRecord};
+use super::schema::{EventSchema, Rejection, SchemaRegistry};
+
+// Most records written in one insert
+const WRITE_BATCH: usize = 500;
+// Longest a record waits in the queue before being written
+const FLUSH_INTERVAL: Duration = Duration::from_secs(2);
+
+// What happened to each event of a submitted batch
+#[derive(Debug, Clone, Default, Serialize, ToSchema, JsonSchema)]
+pub struct TelemetryAck {
+    // Queued for storage
+    pub accepted: usize,
+    // Skipped by TELEMETRY_SAMPLE_RATES
+    pub sampled_out: usize,
+    // Failed validation (empty or long name, oversized properties, or not
+    // matching the schema registry)
+    pub rejected: usize,
+    // Queue full; the server is shedding telemetry load
+    pub dropped: usize,
+    // Why each rejected event was rejected
+    pub errors: Vec<Rejection>,
+}
+
+// Best-effort ingestion of client telemetry.
+//
+// Events are validated, against the schema registry when one is configured,
+// and sampled on submit, then go through a bounded queue
+// to a background writer that inserts them in batches. When the queue is full
+// events are dropped rather than slowing down the gateway; write failures are
+// logged and counted but never retried.
+pub struct TelemetryService {
+    config: TelemetryConfig,
+    // Every well-formed event is accepted when unset
+    registry: Option<SchemaRegistry>,
+    queue: mpsc::Sender<TelemetryRecord>,
+}
+
+impl TelemetryService {
+    pub fn new(config: TelemetryConfig, repo: Arc<dyn TelemetryRepository>) -> Arc<Self> {
+        let registry = config.schema_path.as_deref().and_then(|path| match SchemaRegistry::load(path) {
+            Ok(registry) => {
+                tracing::info!("Loaded {} telemetry event schemas from {}", registry.len(), path);
+                Some(registry)
+            }
+            Err(e) => {
+                tracing::error!("Failed to load telemetry schemas from {}: {}", path, e);
+                None
+            }
+        });
+        let (queue, records) = mpsc::channel(config.queue_capacity.max(1));
+        tokio::spawn(run_writer(repo, records));
+        Arc::new(Self { config, registry, queue })
+    }
+
+    // Registered events; empty when every event is accepted
+    pub fn schemas(&self) -> Vec<EventSchema> {
+        self.registry.as_ref().map(SchemaRegistry::list).unwrap_or_default()
+    }
+
+    // Validate, sample and queue a batch. Fails only when the batch itself is
+    // empty or larger than TELEMETRY_MAX_BATCH; per-event outcomes are in the ack.
+    pub fn submit(&self, user_id: Option<Uuid>, source: &'static str, events: Vec<TelemetryEvent>) -> Result<TelemetryAck> {
+        if events.is_empty() || events.len() > self.config.max_batch {
+            return Err(Error::InvalidMessage);
+        }
+
+        let received_at = Utc::now();
+        let mut ack = TelemetryAck::default();
+        for (index, event) in events.into_iter().enumerate() {
+            let checked = event.validate().map_err(|reason| (reason, None)).and_then(|()| match &self.registry {
+                Some(registry) => registry.check(&event),
+                None => Ok(()),
+            });
+            if let Err((reason, field)) = checked {
+                ack.rejected += 1;
+                ack.errors.push(Rejection { index, reason, field });
+                continue;
+            }
+            if !self.config.sampling.keep(event.kind) {
+                ack.sampled_out += 1;
+                continue;
+            }
+            let record = TelemetryRecord {
+                user_id,
+                source,
+                event,
+                received_at,
+            };
+            match self.queue.try_send(record) {
+                Ok(()) => ack.accepted += 1,
+                Err(_) => ack.dropped += 1,
+            }
+        }
+
+        METRICS.record_telemetry(&ack);
+        Ok(ack)
+    }
+}
+
+async fn run_writer(repo: Arc<dyn TelemetryRepository>, mut records: mpsc::Receiver<TelemetryRecord>) {
+    let mut batch = Vec::with_capacity(WRITE_BATCH);
+    let mut interval = tokio::time::interval(FLUSH_INTERVAL);
+    loop {
+        tokio::select! {
+            record = records.recv() => match record {
+                Some(record) => {
+                    batch.push(record);
+                    if batch.len() >= WRITE_BATCH {
+                        flush(repo.as_ref(), &mut batch).await;
+                    }
+                }
+                None => {
+                    flush(repo.as_ref(), &mut batch).await;
+                    break;
+                }
+            },
+            _ = interval.tick() => flush(repo.as_ref(), &mut batch).await,
+        }
+    }
+}
+
+async fn flush(repo: &dyn TelemetryRepository, batch: &mut Vec<TelemetryRecord>) {
+    if batch.is_empty() {
+        return;
+    }
+    if let Err(e) = repo.insert_events(batch).await {
+        tracing::warn!("Failed to write {} telemetry events: {}", batch.len(), e);
+        METRICS.record_telemetry_write_failure(batch.len());
+    }
+    batch.clear();
+}
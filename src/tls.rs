@@ -0,0 +1,126 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::BufReader;
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use axum::{
+    Router,
+    extract::Host,
+    http::{StatusCode, Uri, uri::Authority},
+    response::{IntoResponse, Redirect},
+};
+use rustls::{
+    Certificate, PrivateKey, ServerConfig,
+    server::{ClientHello, ResolvesServerCert},
+    sign::{self, CertifiedKey},
+};
+use tokio::net::TcpListener;
+
+use crate::config::TlsConfig;
+
+// Picks a certificate by SNI host name, falling back to the default one
+// (also used by clients that don't send SNI).
+struct SniResolver {
+    default: Arc<CertifiedKey>,
+    by_host: HashMap<String, Arc<CertifiedKey>>,
+}
+
+impl ResolvesServerCert for SniResolver {
+    fn resolve(&self, client_hello: ClientHello) -> Option<Arc<CertifiedKey>> {
+        let key = client_hello
+            .server_name()
+            .and_then(|name| self.by_host.get(&name.to_lowercase()))
+            .unwrap_or(&self.default);
+        Some(key.clone())
+    }
+}
+
+// Build the rustls config for the given certificates
+pub fn server_config(tls: &TlsConfig) -> Result<Arc<ServerConfig>, String> {
+    let default = load_certified_key(&tls.cert_path, &tls.key_path)?;
+
+    let mut by_host = HashMap::new();
+    for sni in &tls.sni_certs {
+        by_host.insert(sni.host.clone(), load_certified_key(&sni.cert_path, &sni.key_path)?);
+    }
+
+    let mut config = ServerConfig::builder()
+        .with_safe_defaults()
+        .with_no_client_auth()
+        .with_cert_resolver(Arc::new(SniResolver { default, by_host }));
+    // WebSocket upgrades need HTTP/1.1
+    config.alpn_protocols = vec![b"http/1.1".to_vec()];
+
+    Ok(Arc::new(config))
+}
+
+fn load_certified_key(cert_path: &str, key_path: &str) -> Result<Arc<CertifiedKey>, String> {
+    let certs = read_pem(cert_path, rustls_pemfile::certs)?
+        .into_iter()
+        .map(Certificate)
+        .collect::<Vec<_>>();
+    if certs.is_empty() {
+        return Err(format!("no certificate found in {}", cert_path));
+    }
+
+    // PKCS#8 first, then traditional RSA keys
+    let mut keys = read_pem(key_path, rustls_pemfile::pkcs8_private_keys)?;
+    if keys.is_empty() {
+        keys = read_pem(key_path, rustls_pemfile::rsa_private_keys)?;
+    }
+    let key = keys
+        .into_iter()
+        .next()
+        .ok_or_else(|| format!("no private key found in {}", key_path))?;
+
+    let signing_key = sign::any_supported_type(&PrivateKey(key))
+        .map_err(|e| format!("unsupported private key in {}: {}", key_path, e))?;
+
+    Ok(Arc::new(CertifiedKey::new(certs, signing_key)))
+}
+
+fn read_pem(
+    path: &str,
+    parse: fn(&mut dyn std::io::BufRead) -> std::io::Result<Vec<Vec<u8>>>,
+) -> Result<Vec<Vec<u8>>, String> {
+    let file = File::open(path).map_err(|e| format!("failed to open {}: {}", path, e))?;
+    parse(&mut BufReader::new(file)).map_err(|e| format!("failed to parse {}: {}", path, e))
+}
+
+// Plain HTTP listener that sends every request to the HTTPS port
+pub fn spawn_http_redirect(redirect_port: u16, https_port: u16) {
+    tokio::spawn(async move {
+        let redirect = move |Host(host): Host, uri: Uri| async move {
+            match https_uri(&host, &uri, https_port) {
+                Some(target) => Redirect::permanent(&target).into_response(),
+                None => StatusCode::BAD_REQUEST.into_response(),
+            }
+        };
+
+        let addr = SocketAddr::from(([0, 0, 0, 0], redirect_port));
+        tracing::info!("Redirecting HTTP on {} to HTTPS port {}", addr, https_port);
+
+        let listener = match TcpListener::bind(addr).await {
+            Ok(listener) => listener,
+            Err(e) => {
+                tracing::error!("Failed to bind HTTP redirect listener: {}", e);
+                return;
+            }
+        };
+        if let Err(e) = axum::serve(listener, Router::new().fallback(redirect)).await {
+            tracing::error!("HTTP redirect listener stopped: {}", e);
+        }
+    });
+}
+
+fn https_uri(host: &str, uri: &Uri, https_port: u16) -> Option<String> {
+    let authority: Authority = host.parse().ok()?;
+    let host = authority.host();
+    let path = uri.path_and_query().map(|pq| pq.as_str()).unwrap_or("/");
+    if https_port == 443 {
+        Some(format!("https://{}{}", host, path))
+    } else {
+        Some(format!("https://{}:{}{}", host, https_port, path))
+    }
+}
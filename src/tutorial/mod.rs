@@ -0,0 +1,3 @@
+pub mod service;
+#[allow(clippy::module_inception)]
+pub mod tutorial;
@@ -0,0 +1,139 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use chrono::Utc;
+use rand::seq::SliceRandom;
+use tokio::sync::{Mutex, broadcast};
+use uuid::Uuid;
+
+use crate::db::repository::TutorialRepository;
+use crate::error::{Error, Result};
+use crate::matchmaking::events::{MatchEvent, Published};
+use crate::matchmaking::service::MatchService;
+use crate::supervisor;
+use super::tutorial::{SCRIPT, TUTORIAL_MATCH_TYPE, Trigger, TutorialNotice, TutorialProgress, TutorialStarted};
+
+// Scripted tutorial matches for new players.
+//
+// tutorial.start puts the player in a match against one of the NEW_PLAYER_BOTS
+// accounts. The service follows the match events of tutorial matches run by
+// this instance and moves each one through SCRIPT, saving the player's
+// progress at every step and handing it to the gateway as a notice.
+pub struct TutorialService {
+    repo: Arc<dyn TutorialRepository>,
+    match_service: Arc<MatchService>,
+    bots: Vec<Uuid>,
+    // Running tutorial matches: the player and the next step
+    active: Mutex<HashMap<Uuid, (Uuid, usize)>>,
+    notices: broadcast::Sender<TutorialNotice>,
+}
+
+impl TutorialService {
+    pub fn init(repo: Arc<dyn TutorialRepository>, match_service: Arc<MatchService>, bots: Vec<Uuid>) -> Arc<Self> {
+        let (notices, _) = broadcast::channel(64);
+        Arc::new(Self {
+            repo,
+            match_service,
+            bots,
+            active: Mutex::new(HashMap::new()),
+            notices,
+        })
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<TutorialNotice> {
+        self.notices.subscribe()
+    }
+
+    pub async fn progress(&self, user_id: Uuid) -> Result<TutorialProgress> {
+        Ok(self.repo.get_tutorial_progress(user_id).await?.unwrap_or_else(|| TutorialProgress::new(user_id)))
+    }
+
+    // Start a tutorial match; players may replay it after completing it
+    pub async fn start(&self, user_id: Uuid) -> Result<TutorialStarted> {
+        let bot = self.bots
+            .choose(&mut rand::thread_rng())
+            .copied()
+            .ok_or_else(|| Error::NotFound("tutorial bot".to_string()))?;
+        let room = self.match_service.start_tutorial(user_id, bot).await?;
+        Ok(TutorialStarted {
+            match_id: room.match_id,
+            steps: SCRIPT.iter().map(|step| step.id.to_string()).collect(),
+        })
+    }
+
+    pub fn spawn_event_listener(self: Arc<Self>, mut events: broadcast::Receiver<Published>) {
+        tokio::spawn(async move {
+            loop {
+                match events.recv().await.map(|published| published.event) {
+                    // A panic handling one event skips it; the subscription stays
+                    Ok(event) => {
+                        supervisor::catch("tutorial_events", self.on_event(event)).await;
+                    }
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        tracing::warn!("Tutorial listener lagged, skipped {} match events", skipped);
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                }
+            }
+        });
+    }
+
+    async fn on_event(&self, event: MatchEvent) {
+        match event {
+            MatchEvent::MatchStarted { room, teams } if room.match_type == TUTORIAL_MATCH_TYPE => {
+                let player = teams
+                    .iter()
+                    .flat_map(|team| team.players.iter().copied())
+                    .find(|player| !self.bots.contains(player));
+                if let Some(player) = player {
+                    self.active.lock().await.insert(room.match_id, (player, 0));
+                    self.advance(room.match_id, |_| true, Trigger::MatchStarted).await;
+                }
+            }
+            MatchEvent::DiscoveryRecorded { discovery } => {
+                let user_id = discovery.user_id;
+                self.advance(discovery.match_id, |player| player == user_id, Trigger::Discovery).await;
+            }
+            MatchEvent::MatchEnded { match_id } => {
+                self.advance(match_id, |_| true, Trigger::MatchEnded).await;
+                self.active.lock().await.remove(&match_id);
+            }
+            _ => {}
+        }
+    }
+
+    // Move the match on to the next step shown on `trigger`, if the event
+    // concerns its player
+    async fn advance(&self, match_id: Uuid, concerns: impl Fn(Uuid) -> bool, trigger: Trigger) {
+        let (user_id, index) = {
+            let mut active = self.active.lock().await;
+            let Some((player, next)) = active.get_mut(&match_id) else {
+                return;
+            };
+            if !concerns(*player) {
+                return;
+            }
+            let Some(index) = (*next..SCRIPT.len()).find(|i| SCRIPT[*i].shown_on == trigger) else {
+                return;
+            };
+            *next = index + 1;
+            (*player, index)
+        };
+
+        if let Err(e) = self.save_progress(user_id, index).await {
+            tracing::warn!("Failed to save tutorial progress of user {}: {}", user_id, e);
+        }
+        let _ = self.notices.send(TutorialNotice { user_id, match_id, index });
+    }
+
+    async fn save_progress(&self, user_id: Uuid, index: usize) -> Result<()> {
+        let mut progress = self.progress(user_id).await?;
+        let now = Utc::now();
+        progress.step = Some(SCRIPT[index].id.to_string());
+        if index + 1 == SCRIPT.len() && progress.completed_at.is_none() {
+            progress.completed_at = Some(now);
+        }
+        progress.updated_at = Some(now);
+        self.repo.save_tutorial_progress(&progress).await
+    }
+}
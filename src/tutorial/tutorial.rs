@@ -0,0 +1,96 @@
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+// Match type of tutorial matches, which match.start doesn't offer
+pub const TUTORIAL_MATCH_TYPE: &str = "tutorial";
+
+// What moves a tutorial on to a step
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Trigger {
+    MatchStarted,
+    // The player claimed a treasure
+    Discovery,
+    MatchEnded,
+}
+
+pub struct StepSpec {
+    pub id: &'static str,
+    // Shown when the content bundles have no `tutorial.<id>` text
+    pub text: &'static str,
+    pub shown_on: Trigger,
+}
+
+// The tutorial, in order. A step whose trigger never fires is skipped.
+pub const SCRIPT: &[StepSpec] = &[
+    StepSpec {
+        id: "find_treasure",
+        text: "Treasures are hidden around you. Walk up to one to claim it.",
+        shown_on: Trigger::MatchStarted,
+    },
+    StepSpec {
+        id: "keep_going",
+        text: "Nice find! Claim as many as you can before time runs out.",
+        shown_on: Trigger::Discovery,
+    },
+    StepSpec {
+        id: "complete",
+        text: "Tutorial complete. You're ready for your first match!",
+        shown_on: Trigger::MatchEnded,
+    },
+];
+
+// Onboarding state of one player
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, JsonSchema)]
+pub struct TutorialProgress {
+    pub user_id: Uuid,
+    // Last step reached; None before the first tutorial
+    pub step: Option<String>,
+    // Set the first time the player reaches the last step
+    pub completed_at: Option<DateTime<Utc>>,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+impl TutorialProgress {
+    pub fn new(user_id: Uuid) -> Self {
+        Self {
+            user_id,
+            step: None,
+            completed_at: None,
+            updated_at: None,
+        }
+    }
+}
+
+// tutorial.step event
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct TutorialStep {
+    pub match_id: Uuid,
+    pub step: String,
+    // From 0, out of `total`
+    pub index: usize,
+    pub total: usize,
+    // In the player's locale
+    pub text: String,
+    // The last step; the tutorial is done
+    pub completed: bool,
+}
+
+// tutorial.start reply
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct TutorialStarted {
+    pub match_id: Uuid,
+    // Step ids, in order
+    pub steps: Vec<String>,
+}
+
+// A step reached by the player of a tutorial match on this instance
+#[derive(Debug, Clone)]
+pub struct TutorialNotice {
+    pub user_id: Uuid,
+    pub match_id: Uuid,
+    // Into SCRIPT
+    pub index: usize,
+}